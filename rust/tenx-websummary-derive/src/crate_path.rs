@@ -0,0 +1,176 @@
+//!
+//! Path of the tenx-websummary crate in the generated code, shared by the
+//! derives. In order of precedence, it is
+//! - the `websummary_crate` attribute of the derive, e.g.
+//!   `#[html(websummary_crate = "crate")]`
+//! - the `crate` of the attribute shared by the derives, e.g.
+//!   `#[websummary(crate = "crate")]`
+//! - the name of the dependency in the Cargo.toml of the crate being
+//!   compiled, so that renamed dependencies such as
+//!   `ws = { package = "tenx-websummary", ... }` work without an attribute
+//! - `tenx_websummary`
+//!
+
+use std::path::PathBuf;
+
+use darling::FromAttributes;
+use syn::{Attribute, Path};
+
+const PACKAGE_NAME: &str = "tenx-websummary";
+
+/// The attribute shared by all the derives
+#[derive(Debug, Default, FromAttributes)]
+#[darling(attributes(websummary))]
+struct WebsummaryAttrs {
+    #[darling(rename = "crate")]
+    krate: Option<Path>,
+}
+
+/// The path of the crate for a derive, given its own `websummary_crate`
+/// attribute and the `#[websummary(...)]` attributes of the item
+pub(crate) fn websummary_crate(
+    derive_attr: Option<&Path>,
+    attrs: &[Attribute],
+) -> darling::Result<Path> {
+    let shared = WebsummaryAttrs::from_attributes(attrs)?;
+    Ok(match (derive_attr, shared.krate) {
+        (Some(path), _) => path.clone(),
+        (None, Some(path)) => path,
+        (None, None) => {
+            let name = dependency_name().unwrap_or_else(|| "tenx_websummary".to_string());
+            Path::from(proc_macro2::Ident::new(
+                &name,
+                proc_macro2::Span::call_site(),
+            ))
+        }
+    })
+}
+
+/// Name of the tenx-websummary dependency in the Cargo.toml of the crate
+/// being compiled, along with the `[workspace.dependencies]` of its workspace
+/// for dependencies inherited with `workspace = true`.
+///
+/// The workspace manifest is the one given by `package.workspace`, or else
+/// the closest Cargo.toml with a `[workspace]` table above the crate. Unlike
+/// cargo, a crate excluded from that workspace is not detected, which only
+/// matters if it also inherits a dependency from there.
+fn dependency_name() -> Option<String> {
+    let dir = PathBuf::from(std::env::var_os("CARGO_MANIFEST_DIR")?);
+    let manifest = read_manifest(&dir.join("Cargo.toml"))?;
+    let workspace = workspace_manifest(&dir, &manifest);
+    dependency_name_in(&manifest, workspace.as_ref())
+}
+
+fn read_manifest(path: &std::path::Path) -> Option<toml::Table> {
+    std::fs::read_to_string(path).ok()?.parse().ok()
+}
+
+fn workspace_manifest(dir: &std::path::Path, manifest: &toml::Table) -> Option<toml::Table> {
+    if manifest.contains_key("workspace") {
+        return Some(manifest.clone());
+    }
+    let explicit = manifest
+        .get("package")
+        .and_then(|package| package.get("workspace"))
+        .and_then(toml::Value::as_str);
+    match explicit {
+        Some(path) => read_manifest(&dir.join(path).join("Cargo.toml")),
+        None => dir
+            .ancestors()
+            .skip(1)
+            .filter_map(|dir| read_manifest(&dir.join("Cargo.toml")))
+            .find(|manifest| manifest.contains_key("workspace")),
+    }
+}
+
+/// Look for the package in the dependencies of every kind, including those
+/// specific to a target. The package of a dependency inherited from the
+/// workspace is looked up in `[workspace.dependencies]` of `workspace`.
+fn dependency_name_in(manifest: &toml::Table, workspace: Option<&toml::Table>) -> Option<String> {
+    const KINDS: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+    let targets = manifest
+        .get("target")
+        .and_then(toml::Value::as_table)
+        .into_iter()
+        .flat_map(|targets| targets.values().filter_map(toml::Value::as_table));
+    let workspace_deps = workspace
+        .and_then(|workspace| workspace.get("workspace"))
+        .and_then(|workspace| workspace.get("dependencies"));
+    std::iter::once(manifest)
+        .chain(targets)
+        .flat_map(|table| KINDS.iter().filter_map(|kind| table.get(*kind)))
+        .filter_map(toml::Value::as_table)
+        .flat_map(|deps| deps.iter())
+        .find(|(name, dep)| {
+            let inherited = dep.get("workspace").and_then(toml::Value::as_bool) == Some(true);
+            let dep = match workspace_deps {
+                Some(deps) if inherited => deps.get(name.as_str()),
+                _ => Some(*dep),
+            };
+            let package = dep
+                .and_then(|dep| dep.get("package"))
+                .and_then(toml::Value::as_str)
+                .unwrap_or(name.as_str());
+            package == PACKAGE_NAME
+        })
+        .map(|(name, _)| name.replace('-', "_"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(manifest: &str) -> Option<String> {
+        dependency_name_in(&manifest.parse().unwrap(), None)
+    }
+
+    #[test]
+    fn test_dependency_name() {
+        assert_eq!(
+            name("[dependencies]\ntenx-websummary = { path = \"..\" }"),
+            Some("tenx_websummary".into())
+        );
+        assert_eq!(
+            name("[dev-dependencies]\nws = { package = \"tenx-websummary\", path = \"..\" }"),
+            Some("ws".into())
+        );
+        assert_eq!(
+            name("[target.'cfg(unix)'.dependencies]\nweb-summary = { package = \"tenx-websummary\", version = \"0.1\" }"),
+            Some("web_summary".into())
+        );
+        assert_eq!(name("[dependencies]\nserde = \"1\""), None);
+        assert_eq!(name("[package]\nname = \"tenx-websummary\""), None);
+    }
+
+    #[test]
+    fn test_dependency_name_workspace() {
+        let workspace: toml::Table = "[workspace.dependencies]\nws = { package = \"tenx-websummary\", path = \"..\" }\nserde = \"1\""
+            .parse()
+            .unwrap();
+        let name =
+            |manifest: &str| dependency_name_in(&manifest.parse().unwrap(), Some(&workspace));
+        assert_eq!(
+            name("[dependencies]\nws = { workspace = true }"),
+            Some("ws".into())
+        );
+        assert_eq!(name("[dependencies]\nserde = { workspace = true }"), None);
+        // Missing from the workspace, so only the name is known
+        assert_eq!(
+            name("[dependencies]\ntenx-websummary = { workspace = true }"),
+            Some("tenx_websummary".into())
+        );
+    }
+
+    #[test]
+    fn test_precedence() {
+        let attrs: Vec<Attribute> = vec![syn::parse_quote!(#[websummary(crate = "crate")])];
+        let derive_attr: Path = syn::parse_quote!(ws);
+        let path = |derive_attr, attrs| {
+            let path = websummary_crate(derive_attr, attrs).unwrap();
+            quote::quote!(#path).to_string()
+        };
+        assert_eq!(path(Some(&derive_attr), &attrs), "ws");
+        assert_eq!(path(None, &attrs), "crate");
+        assert!(websummary_crate(None, &[syn::parse_quote!(#[websummary(krate = "ws")])]).is_err());
+    }
+}
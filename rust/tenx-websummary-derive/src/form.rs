@@ -6,8 +6,10 @@
 use darling::{ast, FromDeriveInput, FromField, FromMeta, FromVariant};
 use itertools::Itertools;
 use proc_macro2::TokenStream;
-use quote::{quote, ToTokens, TokenStreamExt};
-use syn::{Generics, Path};
+use quote::{quote, quote_spanned, ToTokens, TokenStreamExt};
+use syn::{spanned::Spanned, Attribute, Generics, Path};
+
+use crate::crate_path;
 
 #[derive(FromMeta, PartialEq, Eq, Debug, Clone, Copy)]
 enum Method {
@@ -15,17 +17,89 @@ enum Method {
     Post,
 }
 
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+enum VisibilityOp {
+    Eq,
+    Ne,
+}
+
+/// Parsed from `#[html_form(visible_when = "field == value")]` or
+/// `#[html_form(visible_when = "field != value")]`
+#[derive(Debug, Clone)]
+struct VisibleWhen {
+    field: String,
+    op: VisibilityOp,
+    value: String,
+}
+
+impl VisibleWhen {
+    fn parse(expr: &str) -> darling::Result<Self> {
+        let (field, op, value) = if let Some((field, value)) = expr.split_once("!=") {
+            (field, VisibilityOp::Ne, value)
+        } else if let Some((field, value)) = expr.split_once("==") {
+            (field, VisibilityOp::Eq, value)
+        } else {
+            return Err(darling::Error::custom(format!(
+                "Expected an expression of the form `field == value` or `field != value`, found `{expr}`"
+            )));
+        };
+        let field = field.trim();
+        if syn::parse_str::<syn::Ident>(field).is_err() {
+            return Err(darling::Error::custom(format!(
+                "`{field}` is not a valid field name in `{expr}`"
+            )));
+        }
+        let value = value.trim();
+        let value = ["\"", "'"]
+            .into_iter()
+            .find_map(|q| value.strip_prefix(q).and_then(|v| v.strip_suffix(q)))
+            .unwrap_or(value);
+        if value.is_empty() || value.contains("==") || value.contains("!=") {
+            return Err(darling::Error::custom(format!(
+                "Invalid value to compare against in `{expr}`"
+            )));
+        }
+        Ok(VisibleWhen {
+            field: field.to_string(),
+            op,
+            value: value.to_string(),
+        })
+    }
+
+    fn make_rule(&self, websummary_crate: &Path) -> TokenStream {
+        let VisibleWhen { field, op, value } = self;
+        let op = match op {
+            VisibilityOp::Eq => quote! { #websummary_crate::form::VisibilityOp::Eq },
+            VisibilityOp::Ne => quote! { #websummary_crate::form::VisibilityOp::Ne },
+        };
+        quote! {
+            #websummary_crate::form::VisibilityRule {
+                field: #field.to_string(),
+                op: #op,
+                value: #value.to_string(),
+            }
+        }
+    }
+}
+
 #[derive(Debug, FromDeriveInput)]
-#[darling(attributes(html_form), supports(struct_named, enum_unit))]
+#[darling(
+    attributes(html_form),
+    forward_attrs(websummary),
+    supports(struct_named, enum_unit)
+)]
 pub(crate) struct HtmlFormReceiver {
     /// The struct name.
     ident: syn::Ident,
     data: ast::Data<HtmlFormVariantReceiver, HtmlFormFieldReceiver>,
     generics: Generics,
+    attrs: Vec<Attribute>,
     websummary_crate: Option<Path>,
     method: Option<Method>,
     config_trait: Option<String>,
     configure: Option<bool>,
+    /// Label of the submit button
+    submit_label: Option<String>,
 }
 
 impl HtmlFormReceiver {
@@ -41,16 +115,165 @@ impl HtmlFormReceiver {
             proc_macro2::Span::call_site(),
         )
     }
+    /// Every `visible_when` rule should refer to another field of the struct
+    /// and the input configuration attributes should suit the field type
+    pub(crate) fn check_field_attributes(&self) -> darling::Result<()> {
+        let fields = match self.data {
+            ast::Data::Struct(ref f) => &f.fields,
+            ast::Data::Enum(_) => return Ok(()),
+        };
+        let mut errors = darling::Error::accumulator();
+        for field in fields {
+            errors.handle(field.config_attrs());
+            if let Some(rule) = errors.handle(field.visible_when()).flatten() {
+                let ident_str = field.ident_string();
+                if rule.field == ident_str || !fields.iter().any(|f| f.ident_string() == rule.field)
+                {
+                    errors.push(
+                        darling::Error::custom(format!(
+                            "`visible_when` of `{ident_str}` refers to `{}` which is not another field of `{}`",
+                            rule.field,
+                            self.ident_string()
+                        ))
+                        .with_span(field.ident.as_ref().unwrap()),
+                    );
+                }
+            }
+        }
+        errors.handle(self.field_steps());
+        errors.finish()
+    }
+    /// Step of every field from `#[html_form(wizard_step = N)]`, or an empty
+    /// list if no field has the attribute. Fields without the attribute are
+    /// on step 1. The steps should be contiguous starting at 1.
+    fn field_steps(&self) -> darling::Result<Vec<usize>> {
+        let fields = match self.data {
+            ast::Data::Struct(ref f) => &f.fields,
+            ast::Data::Enum(_) => return Ok(Vec::new()),
+        };
+        if fields.iter().all(|f| f.wizard_step.is_none()) {
+            return Ok(Vec::new());
+        }
+        let mut errors = darling::Error::accumulator();
+        for field in fields {
+            if field.wizard_step == Some(0) {
+                errors.push(
+                    darling::Error::custom(format!(
+                        "`wizard_step` of `{}` should be at least 1",
+                        field.ident_string()
+                    ))
+                    .with_span(field.ident.as_ref().unwrap()),
+                );
+            }
+        }
+        errors.finish()?;
+        let steps: Vec<_> = fields.iter().map(|f| f.wizard_step.unwrap_or(1)).collect();
+        let num_steps = steps.iter().copied().max().unwrap_or(1);
+        if let Some(missing) = (1..=num_steps).find(|step| !steps.contains(step)) {
+            return Err(darling::Error::custom(format!(
+                "Steps of `{}` should be contiguous starting at 1, but no field is on step {missing}",
+                self.ident_string()
+            ))
+            .with_span(&self.ident));
+        }
+        Ok(steps)
+    }
 }
 
 #[derive(Debug, FromField)]
-#[darling(forward_attrs(doc))]
+#[darling(attributes(html_form), forward_attrs(doc))]
 struct HtmlFormFieldReceiver {
     /// Name of the field
     ident: Option<syn::Ident>,
     /// The type of the field
     ty: syn::Type,
     attrs: Vec<syn::Attribute>,
+    /// Only show the field when the expression over a sibling field holds.
+    /// Parsed into `VisibleWhen`.
+    visible_when: Option<String>,
+    /// Placeholder of a text input or a text area
+    placeholder: Option<String>,
+    /// Show a text area with these many rows instead of a text input
+    rows: Option<u32>,
+    /// Maximum number of characters of a text input or a text area, which
+    /// is also checked along with the other bounds of the config
+    max_length: Option<usize>,
+    /// Range of a numeric input
+    min: Option<i64>,
+    max: Option<i64>,
+    step: Option<i64>,
+    /// Step of a wizard form in which the field is shown, starting at 1
+    wizard_step: Option<usize>,
+}
+
+/// Input configuration from the field attributes, so that the
+/// `configure_<field>()` function need not be written by hand
+#[derive(Debug, Clone)]
+enum ConfigAttrs {
+    Text {
+        placeholder: Option<String>,
+        rows: Option<u32>,
+        max_length: Option<usize>,
+    },
+    Number {
+        min: Option<i64>,
+        max: Option<i64>,
+        step: Option<i64>,
+    },
+}
+
+impl ConfigAttrs {
+    fn make_config(&self, websummary_crate: &Path) -> TokenStream {
+        let quote_option = |value: Option<TokenStream>| match value {
+            Some(value) => quote! { Some(#value) },
+            None => quote! { None },
+        };
+        match self {
+            ConfigAttrs::Text {
+                placeholder,
+                rows: Some(rows),
+                max_length,
+            } => {
+                let placeholder =
+                    quote_option(placeholder.as_ref().map(|p| quote! { #p.to_string() }));
+                let max_length = quote_option(max_length.map(|m| quote! { #m }));
+                quote! {
+                    #websummary_crate::form::FormInputConfigString::TextArea {
+                        rows: Some(#rows),
+                        placeholder: #placeholder,
+                        max_length: #max_length,
+                    }
+                }
+            }
+            ConfigAttrs::Text {
+                placeholder,
+                rows: None,
+                max_length,
+            } => {
+                let placeholder =
+                    quote_option(placeholder.as_ref().map(|p| quote! { #p.to_string() }));
+                let max_length = quote_option(max_length.map(|m| quote! { #m }));
+                quote! {
+                    #websummary_crate::form::FormInputConfigString::Text {
+                        placeholder: #placeholder,
+                        max_length: #max_length,
+                    }
+                }
+            }
+            ConfigAttrs::Number { min, max, step } => {
+                let min = min.map_or(quote! { i64::MIN }, |m| quote! { #m });
+                let max = max.map_or(quote! { i64::MAX }, |m| quote! { #m });
+                let step = step.unwrap_or(1);
+                quote! {
+                    #websummary_crate::form::FormInputConfigI64::Input {
+                        min: #min,
+                        max: #max,
+                        step: #step,
+                    }
+                }
+            }
+        }
+    }
 }
 
 impl HtmlFormFieldReceiver {
@@ -58,24 +281,91 @@ impl HtmlFormFieldReceiver {
         self.ident.as_ref().unwrap().to_string()
     }
 
-    fn make_title(&self, websummary_crate: &Path) -> TokenStream {
-        let ident_str = self.ident_string();
+    /// Name of the type if it is a plain path such as `String` or `i64`
+    fn type_name(&self) -> Option<String> {
+        match &self.ty {
+            syn::Type::Path(p) => p.path.segments.last().map(|s| s.ident.to_string()),
+            _ => None,
+        }
+    }
 
-        let doc_comments: Vec<_> = self
-            .attrs
-            .iter()
-            .filter_map(|attr| {
-                if let Ok(syn::Meta::NameValue(nv)) = &attr.parse_meta() {
-                    if nv.path.is_ident("doc") {
-                        if let syn::Lit::Str(lit_str) = &nv.lit {
-                            return Some(lit_str.value().trim().to_string());
-                        }
+    fn config_attrs(&self) -> darling::Result<Option<ConfigAttrs>> {
+        let text_attrs: Vec<_> = [
+            ("placeholder", self.placeholder.is_some()),
+            ("rows", self.rows.is_some()),
+            ("max_length", self.max_length.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(name, present)| present.then_some(name))
+        .collect();
+        let number_attrs: Vec<_> = [
+            ("min", self.min.is_some()),
+            ("max", self.max.is_some()),
+            ("step", self.step.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(name, present)| present.then_some(name))
+        .collect();
+        let ident_str = self.ident_string();
+        let error =
+            |msg: String| Err(darling::Error::custom(msg).with_span(self.ident.as_ref().unwrap()));
+        let type_name = self.type_name();
+        match (&text_attrs[..], &number_attrs[..]) {
+            ([], []) => Ok(None),
+            ([text, ..], [number, ..]) => error(format!(
+                "`{text}` cannot be combined with `{number}` on the field `{ident_str}`"
+            )),
+            ([text, ..], []) => {
+                if matches!(type_name.as_deref(), Some("i64")) {
+                    return error(format!(
+                        "`{text}` is not supported on the i64 field `{ident_str}`. It can only be used on String fields"
+                    ));
+                }
+                Ok(Some(ConfigAttrs::Text {
+                    placeholder: self.placeholder.clone(),
+                    rows: self.rows,
+                    max_length: self.max_length,
+                }))
+            }
+            ([], [number, ..]) => {
+                if matches!(type_name.as_deref(), Some("String")) {
+                    return error(format!(
+                        "`{number}` is not supported on the String field `{ident_str}`. It can only be used on i64 fields"
+                    ));
+                }
+                if let (Some(min), Some(max)) = (self.min, self.max) {
+                    if min > max {
+                        return error(format!(
+                            "`min` ({min}) is greater than `max` ({max}) on the field `{ident_str}`"
+                        ));
                     }
                 }
-                None
-            })
-            .filter(|x| !x.is_empty())
-            .collect();
+                if matches!(self.step, Some(step) if step <= 0) {
+                    return error(format!(
+                        "`step` should be positive on the field `{ident_str}`"
+                    ));
+                }
+                Ok(Some(ConfigAttrs::Number {
+                    min: self.min,
+                    max: self.max,
+                    step: self.step,
+                }))
+            }
+        }
+    }
+
+    fn visible_when(&self) -> darling::Result<Option<VisibleWhen>> {
+        self.visible_when
+            .as_deref()
+            .map(VisibleWhen::parse)
+            .transpose()
+            .map_err(|e| e.with_span(self.ident.as_ref().unwrap()))
+    }
+
+    fn make_title(&self, websummary_crate: &Path) -> TokenStream {
+        let ident_str = self.ident_string();
+
+        let doc_comments = crate::doc_comments(&self.attrs);
 
         match &doc_comments[..] {
             [] => {
@@ -84,10 +374,9 @@ impl HtmlFormFieldReceiver {
             [heading, rest @ ..] => {
                 let help = rest.iter().join("\n");
                 quote! {
-                    #websummary_crate::components::Title::WithHelp(#websummary_crate::components::TitleWithHelp {
-                        title: #heading.to_string(),
-                        help: #help.to_string(),
-                    })
+                    #websummary_crate::components::Title::WithHelp(
+                        #websummary_crate::components::TitleWithHelp::new(#heading, #help)
+                    )
                 }
             }
         }
@@ -98,12 +387,15 @@ impl HtmlFormFieldReceiver {
             proc_macro2::Span::call_site(),
         );
         let ty = &self.ty;
+        let validate = quote! {
+            <#ty as #websummary_crate::form::FieldValidation>::validate(value)
+        };
         (
             validate_fn_ident.clone(),
             quote! {
                 fn #validate_fn_ident(&self, value: &#ty) -> #websummary_crate::form::FieldValidationResult {
                     use #websummary_crate::form::FieldValidation;
-                    <#ty as #websummary_crate::form::FieldValidation>::validate(value)
+                    #validate
                 }
             },
         )
@@ -114,11 +406,22 @@ impl HtmlFormFieldReceiver {
             proc_macro2::Span::call_site(),
         );
         let ty = &self.ty;
+        // Already checked in `check_field_attributes`
+        let config = match self.config_attrs().unwrap() {
+            Some(attrs) => {
+                let config = attrs.make_config(websummary_crate);
+                // A type mismatch is reported at the field type
+                quote_spanned! { ty.span() => #config }
+            }
+            None => quote! {
+                <#ty as #websummary_crate::form::CreateFormInput>::default_config()
+            },
+        };
         (
             config_fn_ident.clone(),
             quote! {
                 fn #config_fn_ident() -> <#ty as #websummary_crate::form::CreateFormInput>::Config {
-                    <#ty as #websummary_crate::form::CreateFormInput>::default_config()
+                    #config
                 }
             },
         )
@@ -133,12 +436,14 @@ struct HtmlFormVariantReceiver {
 
 impl ToTokens for HtmlFormReceiver {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        let websummary_crate = self.websummary_crate.clone().unwrap_or_else(|| {
-            Path::from(proc_macro2::Ident::new(
-                "tenx_websummary",
-                proc_macro2::Span::call_site(),
-            ))
-        });
+        let websummary_crate =
+            match crate_path::websummary_crate(self.websummary_crate.as_ref(), &self.attrs) {
+                Ok(path) => path,
+                Err(e) => {
+                    tokens.append_all(e.write_errors());
+                    return;
+                }
+            };
 
         let struct_or_enum_ident = &self.ident;
         let (impl_generics, ty_generics, where_clause) = self.generics.split_for_impl();
@@ -149,6 +454,9 @@ impl ToTokens for HtmlFormReceiver {
                 let config_trait_name = self.config_trait_name();
                 let mut config_trait_impl = quote! {};
                 let mut field_validations = quote! {};
+                let mut field_visibility = quote! {};
+                let mut field_previews = quote! {};
+                let mut clear_empty_submissions = quote! {};
                 for field in &f.fields {
                     let ident = field.ident.as_ref().unwrap();
                     let ident_str = ident.to_string();
@@ -162,22 +470,67 @@ impl ToTokens for HtmlFormReceiver {
                         #validate_fn_impl
                         #config_fn_impl
                     };
+                    let ty = &field.ty;
+                    // The bounds of the config are checked outside of the
+                    // `validate_<field>` which the user can override
                     field_validations = quote! {
                         #field_validations
-                        <#struct_or_enum_ident as #config_trait_name>::#validate_fn_name(&self, &self.#ident),
+                        match <#struct_or_enum_ident as #config_trait_name>::#validate_fn_name(&self, &self.#ident) {
+                            #websummary_crate::form::FieldValidationResult::Valid => {
+                                <#ty as #websummary_crate::form::CreateFormInput>::validate_config(
+                                    &self.#ident,
+                                    &<#struct_or_enum_ident as #config_trait_name>::#config_fn_name(),
+                                )
+                            }
+                            invalid => invalid,
+                        },
+                    };
+                    field_previews = quote! {
+                        #field_previews
+                        <#ty as #websummary_crate::form::FieldValidation>::preview(&self.#ident),
+                    };
+                    clear_empty_submissions = quote! {
+                        #clear_empty_submissions
+                        <#ty as #websummary_crate::form::FieldValidation>::clear_empty_submission(&mut self.#ident);
+                    };
+
+                    // Already checked in `check_visibility_rules`
+                    let visible_when = match field.visible_when().unwrap() {
+                        Some(visible_when) => {
+                            let rule = visible_when.make_rule(&websummary_crate);
+                            let other_ident = syn::Ident::new(
+                                &visible_when.field,
+                                proc_macro2::Span::call_site(),
+                            );
+                            field_visibility = quote! {
+                                #field_visibility
+                                #rule.is_satisfied_by(&self.#other_ident),
+                            };
+                            quote! { Some(#rule) }
+                        }
+                        None => {
+                            field_visibility = quote! {
+                                #field_visibility
+                                true,
+                            };
+                            quote! { None }
+                        }
                     };
 
                     let ty = &field.ty;
                     elements = quote! {
                         #elements
-                        #websummary_crate::form::FormElement {
-                            title: #title,
-                            input: <#ty as #websummary_crate::form::CreateFormInput>::create_form_input(
-                                <#struct_or_enum_ident as #config_trait_name>::#config_fn_name(),
-                                #ident_str.to_string(),
-                                value.map(|x| x.#ident.to_owned()),
-                            ),
-                            feedback: Default::default(),
+                        {
+                            let mut element = #websummary_crate::form::FormElement::new(
+                                #title,
+                                <#ty as #websummary_crate::form::CreateFormInput>::create_form_input(
+                                    <#struct_or_enum_ident as #config_trait_name>::#config_fn_name(),
+                                    #ident_str.to_string(),
+                                    value.map(|x| x.#ident.to_owned()),
+                                ),
+                            );
+                            element.visible_when = #visible_when;
+                            element
                         },
                     }
                 }
@@ -185,6 +538,21 @@ impl ToTokens for HtmlFormReceiver {
                     Method::Get => quote! { #websummary_crate::form::FormMethod::Get },
                     Method::Post => quote! { #websummary_crate::form::FormMethod::Post },
                 };
+                let submit_label = match &self.submit_label {
+                    Some(label) => quote! { Some(#label.to_string()) },
+                    None => quote! { None },
+                };
+                // Already checked in `check_field_attributes`
+                let field_steps = self.field_steps().unwrap();
+                let field_steps_fn = if field_steps.is_empty() {
+                    quote! {}
+                } else {
+                    quote! {
+                        fn _field_steps() -> Vec<usize> {
+                            vec![#(#field_steps),*]
+                        }
+                    }
+                };
                 let impl_config_trait = if self.configure.unwrap_or_default() {
                     quote! {}
                 } else {
@@ -195,15 +563,28 @@ impl ToTokens for HtmlFormReceiver {
                     #[allow(clippy::all)]
                     trait #config_trait_name #ty_generics #where_clause {
                         #config_trait_impl
+                        fn submission_token() -> Option<String> {
+                            Some(#websummary_crate::form::generate_submission_token())
+                        }
+                        fn submit_label() -> Option<String> {
+                            #submit_label
+                        }
+                        fn step_titles() -> Vec<String> {
+                            Vec::new()
+                        }
                     }
                     #impl_config_trait
                     #[automatically_derived]
                     impl #impl_generics #websummary_crate::form::IntoHtmlForm for #struct_or_enum_ident #ty_generics #where_clause {
                         fn _into_html_form(value: Option<&Self>) -> #websummary_crate::form::Form {
                             #websummary_crate::form::Form {
-                                config: #websummary_crate::form::FormConfig {
-                                    url: String::new(),
-                                    method: #method,
+                                config: {
+                                    let mut config = #websummary_crate::form::FormConfig::new(String::new(), #method);
+                                    config.submission_token = <#struct_or_enum_ident as #config_trait_name>::submission_token();
+                                    match <#struct_or_enum_ident as #config_trait_name>::submit_label() {
+                                        Some(label) => config.with_submit_label(label),
+                                        None => config,
+                                    }
                                 },
                                 elements: vec![#elements]
                             }
@@ -213,6 +594,23 @@ impl ToTokens for HtmlFormReceiver {
                                 #field_validations
                             ]
                         }
+                        fn _field_visibility(&self) -> Vec<bool> {
+                            vec![
+                                #field_visibility
+                            ]
+                        }
+                        fn _field_previews(&self) -> Vec<Option<#websummary_crate::components::GenericTable>> {
+                            vec![
+                                #field_previews
+                            ]
+                        }
+                        fn clear_empty_submissions(&mut self) {
+                            #clear_empty_submissions
+                        }
+                        #field_steps_fn
+                        fn _step_titles() -> Vec<String> {
+                            <#struct_or_enum_ident as #config_trait_name>::step_titles()
+                        }
                     }
                 });
             }
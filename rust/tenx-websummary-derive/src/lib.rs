@@ -4,14 +4,18 @@ use std::collections::{hash_map::Entry, HashMap};
 
 use darling::{ast, FromDeriveInput, FromField};
 use form::HtmlFormReceiver;
+use itertools::Itertools;
 use proc_macro2::TokenStream;
 use quote::{quote, ToTokens, TokenStreamExt};
-use syn::{DeriveInput, Generics, Path};
+use shared_resource::SharedResourceReceiver;
+use syn::{Attribute, DeriveInput, Generics, Path};
 
+mod crate_path;
 mod form;
+mod shared_resource;
 
 #[derive(Debug, FromDeriveInput)]
-#[darling(attributes(html), supports(struct_named))]
+#[darling(attributes(html), forward_attrs(websummary), supports(struct_named))]
 struct HtmlTemplateReceiver {
     /// The struct name.
     ident: syn::Ident,
@@ -22,7 +26,148 @@ struct HtmlTemplateReceiver {
 
     generics: Generics,
 
+    /// The `#[websummary(...)]` attributes shared by the derives
+    attrs: Vec<Attribute>,
+
     websummary_crate: Option<Path>,
+
+    /// Add an `id` derived from the data key to the wrapper div of every
+    /// field, so that in-page links can target the sections
+    #[darling(default)]
+    section_ids: bool,
+
+    /// Generate `with_doc_tooltips()`, which sets the help of the
+    /// `Documented` fields from their doc comments
+    #[darling(default)]
+    doc_tooltips: bool,
+}
+
+impl HtmlTemplateReceiver {
+    fn fields(&self) -> impl Iterator<Item = &FieldReceiver> {
+        match self.data {
+            ast::Data::Struct(ref f) => f.fields.iter(),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Every field with a doc comment must be a `Documented<T>`
+    fn check_doc_tooltips(&self) -> darling::Result<()> {
+        if !self.doc_tooltips {
+            return Ok(());
+        }
+        let mut errors = darling::Error::accumulator();
+        for field in self.fields() {
+            if !doc_comments(&field.attrs).is_empty() && !field.is_documented() {
+                let ident = field.ident.as_ref().unwrap();
+                errors.push(
+                    darling::Error::custom(format!(
+                        "`doc_tooltips` sets the help of the fields with a doc comment, so the \
+                         field `{ident}` must be a `Documented<T>`, e.g. \
+                         `{ident}: Documented<HeroMetric>` created with `Documented::new()`"
+                    ))
+                    .with_span(&field.ty),
+                );
+            }
+        }
+        errors.finish()
+    }
+
+    /// The data key of every field is its serialized name, so two fields
+    /// serialized under the same name would show the same data. A `row`
+    /// named after another field which is in its own row would silently
+    /// join that row.
+    fn check_field_names(&self) -> darling::Result<()> {
+        let mut errors = darling::Error::accumulator();
+        let mut serialized = HashMap::<String, &syn::Ident>::new();
+        for field in self.fields() {
+            let ident = field.ident.as_ref().unwrap();
+            let Some(name) = field.serialized_name() else {
+                continue;
+            };
+            match serialized.entry(name) {
+                Entry::Occupied(e) => errors.push(
+                    darling::Error::custom(format!(
+                        "The fields `{}` and `{ident}` are both serialized as `{}`, so they \
+                         would use the same data key and one of them would not be shown",
+                        e.get(),
+                        e.key()
+                    ))
+                    .with_span(ident),
+                ),
+                Entry::Vacant(v) => {
+                    v.insert(ident);
+                }
+            }
+        }
+        let own_rows: HashMap<String, &syn::Ident> = self
+            .fields()
+            .filter(|field| field.row.is_none())
+            .map(|field| {
+                let ident = field.ident.as_ref().unwrap();
+                (ident.to_string(), ident)
+            })
+            .collect();
+        for field in self.fields() {
+            let (Some(row), Some(ident)) = (&field.row, &field.ident) else {
+                continue;
+            };
+            if let Some(other) = own_rows.get(row).filter(|other| **other != ident) {
+                errors.push(
+                    darling::Error::custom(format!(
+                        "The row `{row}` of the field `{ident}` is the name of the field \
+                         `{other}`, which is in a row of its own. Use another row name, or \
+                         add `#[html(row = \"{row}\")]` to `{other}` to share the row."
+                    ))
+                    .with_span(ident),
+                );
+            }
+        }
+        errors.finish()
+    }
+
+    fn make_doc_tooltips(&self, websummary_crate: &Path) -> TokenStream {
+        let ident = &self.ident;
+        let (impl_generics, ty_generics, where_clause) = self.generics.split_for_impl();
+        let set_help = self.fields().filter_map(|field| {
+            let (heading, help) = match &doc_comments(&field.attrs)[..] {
+                [] => return None,
+                [heading, rest @ ..] => (heading.clone(), rest.iter().join("\n")),
+            };
+            let field_ident = field.ident.as_ref().unwrap();
+            Some(quote! {
+                self.#field_ident.help = Some(
+                    #websummary_crate::components::TitleWithHelp::new(#heading, #help)
+                );
+            })
+        });
+        quote! {
+            impl #impl_generics #ident #ty_generics #where_clause {
+                /// Set the help of the `Documented` fields from their doc comments
+                pub fn with_doc_tooltips(mut self) -> Self {
+                    #(#set_help)*
+                    self
+                }
+            }
+        }
+    }
+}
+
+/// The non-empty lines of the doc comments, trimmed
+pub(crate) fn doc_comments(attrs: &[Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter_map(|attr| {
+            if let Ok(syn::Meta::NameValue(nv)) = &attr.parse_meta() {
+                if nv.path.is_ident("doc") {
+                    if let syn::Lit::Str(lit_str) = &nv.lit {
+                        return Some(lit_str.value().trim().to_string());
+                    }
+                }
+            }
+            None
+        })
+        .filter(|x| !x.is_empty())
+        .collect()
 }
 
 impl ToTokens for HtmlTemplateReceiver {
@@ -31,12 +176,14 @@ impl ToTokens for HtmlTemplateReceiver {
         let mut ordered_rows = Vec::new();
         let mut fields_of_row = HashMap::<String, Vec<&FieldReceiver>>::new();
 
-        let websummary_crate = self.websummary_crate.clone().unwrap_or_else(|| {
-            Path::from(proc_macro2::Ident::new(
-                "tenx_websummary",
-                proc_macro2::Span::call_site(),
-            ))
-        });
+        let websummary_crate =
+            match crate_path::websummary_crate(self.websummary_crate.as_ref(), &self.attrs) {
+                Ok(path) => path,
+                Err(e) => {
+                    tokens.append_all(e.write_errors());
+                    return;
+                }
+            };
 
         let (impl_generics, ty_generics, where_clause) = self.generics.split_for_impl();
 
@@ -57,48 +204,63 @@ impl ToTokens for HtmlTemplateReceiver {
             _ => unreachable!(),
         }
         let mut template_fn = quote! {
-            use ::std::fmt::Write;
             use #websummary_crate::components::ReactComponent;
-            let mut template = String::new();
+            use #websummary_crate::HtmlTemplate as _;
+        };
+        let open_col = if self.section_ids {
+            quote! {
+                template.push_str(&#websummary_crate::components::section_div(&field_name));
+            }
+        } else {
+            quote! {
+                template.push_str("<div class=\"col\">\n");
+            }
         };
         for row in ordered_rows {
             let mut inner = quote! {};
             for field in &fields_of_row[&row] {
                 let field_ident = field.ident.clone().unwrap();
-                let field_ident_str = field_ident.to_string();
+                let field_key = field.data_key();
                 inner = quote! {
                     #inner
                     let field_name = match data_key {
-                        Some(ref key) => format!("{}.{}", key, #field_ident_str),
-                        None => format!("{}", #field_ident_str)
+                        Some(ref key) => format!("{}.{}", key, #field_key),
+                        None => format!("{}", #field_key)
                     };
-                    writeln!(&mut template, r#"<div class="col">"#).unwrap();
-                    writeln!(&mut template, r#"{}"#, self.#field_ident.template(Some(field_name))).unwrap();
-                    writeln!(&mut template, r#"</div>"#).unwrap();
+                    #open_col
+                    self.#field_ident.template_into(Some(field_name), template);
+                    template.push_str("\n</div>\n");
                 };
             }
             template_fn = quote! {
                 #template_fn
-                writeln!(&mut template, r#"<div class="row">"#).unwrap();
+                template.push_str("<div class=\"row\">\n");
                 #inner
-                writeln!(&mut template, r#"</div>"#).unwrap();
+                template.push_str("</div>\n");
             };
         }
 
         tokens.append_all(quote! {
             impl #impl_generics #websummary_crate::HtmlTemplate for #ident #ty_generics #where_clause {
                 fn template(&self, data_key: Option<String>) -> String {
-                    #template_fn
+                    let mut template = String::new();
+                    #websummary_crate::HtmlTemplate::template_into(self, data_key, &mut template);
                     template
                 }
+                fn template_into(&self, data_key: Option<String>, template: &mut String) {
+                    #template_fn
+                }
             }
         });
+        if self.doc_tooltips {
+            tokens.append_all(self.make_doc_tooltips(&websummary_crate));
+        }
     }
 }
 
 #[allow(dead_code)]
 #[derive(Debug, FromField)]
-#[darling(attributes(html))]
+#[darling(attributes(html), forward_attrs(doc, serde))]
 struct FieldReceiver {
     /// Name of the field
     ident: Option<syn::Ident>,
@@ -106,11 +268,80 @@ struct FieldReceiver {
     /// The type of the field
     ty: syn::Type,
 
+    /// The doc comments and the serde attributes of the field
+    attrs: Vec<Attribute>,
+
     #[darling(default)]
     row: Option<String>,
 }
 
 impl FieldReceiver {
+    /// Whether the type of the field is `Documented<T>`, by name since the
+    /// derive cannot resolve the type
+    fn is_documented(&self) -> bool {
+        match &self.ty {
+            syn::Type::Path(path) => path
+                .path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "Documented"),
+            _ => false,
+        }
+    }
+
+    /// The data key of the field in the template: its serialized name, or its
+    /// name if it is not serialized under a key of its own
+    fn data_key(&self) -> String {
+        self.serialized_name()
+            .unwrap_or_else(|| self.ident.as_ref().unwrap().to_string())
+    }
+
+    /// The key of the field in the serialized struct, taking the serde
+    /// `rename` into account. None if the field is not serialized under a
+    /// key of its own, i.e. it is skipped or flattened.
+    fn serialized_name(&self) -> Option<String> {
+        let mut name = self.ident.as_ref().unwrap().to_string();
+        for attr in self.attrs.iter().filter(|attr| attr.path.is_ident("serde")) {
+            let Ok(syn::Meta::List(list)) = attr.parse_meta() else {
+                continue;
+            };
+            for nested in list.nested {
+                match nested {
+                    syn::NestedMeta::Meta(syn::Meta::Path(path))
+                        if ["skip", "skip_serializing", "flatten"]
+                            .iter()
+                            .any(|key| path.is_ident(key)) =>
+                    {
+                        return None;
+                    }
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(nv))
+                        if nv.path.is_ident("rename") =>
+                    {
+                        if let syn::Lit::Str(lit) = nv.lit {
+                            name = lit.value();
+                        }
+                    }
+                    // `rename(serialize = "..", deserialize = "..")`
+                    syn::NestedMeta::Meta(syn::Meta::List(list))
+                        if list.path.is_ident("rename") =>
+                    {
+                        for nested in list.nested {
+                            if let syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) = nested {
+                                if let (true, syn::Lit::Str(lit)) =
+                                    (nv.path.is_ident("serialize"), nv.lit)
+                                {
+                                    name = lit.value();
+                                }
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Some(name)
+    }
+
     fn row_name(&self) -> String {
         match self.row {
             Some(ref r) => r.to_string(),
@@ -122,7 +353,7 @@ impl FieldReceiver {
 const HTML_TEMPLATE_UNSUPPORTED_ERROR: &str =
     r#"HtmlTemplate can only be derived for structs with named fields"#;
 
-#[proc_macro_derive(HtmlTemplate, attributes(html))]
+#[proc_macro_derive(HtmlTemplate, attributes(html, websummary))]
 pub fn html_template(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let item = syn::parse::<DeriveInput>(item).unwrap();
     let struct_receiver = match HtmlTemplateReceiver::from_derive_input(&item) {
@@ -134,6 +365,12 @@ pub fn html_template(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
             )
         }
     };
+    let mut errors = darling::Error::accumulator();
+    errors.handle(struct_receiver.check_doc_tooltips());
+    errors.handle(struct_receiver.check_field_names());
+    if let Err(e) = errors.finish() {
+        return proc_macro::TokenStream::from(e.write_errors());
+    }
     quote! {
         #struct_receiver
     }
@@ -143,7 +380,7 @@ pub fn html_template(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
 const HTML_FORM_UNSUPPORTED_ERROR: &str =
     r#"HtmlForm can only be derived for structs with named fields or enum with unit variants"#;
 
-#[proc_macro_derive(HtmlForm, attributes(html_form))]
+#[proc_macro_derive(HtmlForm, attributes(html_form, websummary))]
 pub fn html_form(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let item = syn::parse::<DeriveInput>(item).unwrap();
     let struct_receiver = match HtmlFormReceiver::from_derive_input(&item) {
@@ -155,6 +392,33 @@ pub fn html_form(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
             )
         }
     };
+    if let Err(e) = struct_receiver.check_field_attributes() {
+        return proc_macro::TokenStream::from(e.write_errors());
+    }
+    quote! {
+        #struct_receiver
+    }
+    .into()
+}
+
+const SHARED_RESOURCE_UNSUPPORTED_ERROR: &str =
+    r#"AddToSharedResource can only be derived for structs with named fields"#;
+
+/// Moves the resources of every field into the shared resources. Fields
+/// whose type does not implement `AddToSharedResource` are marked with
+/// `#[shared_resource(skip)]`.
+#[proc_macro_derive(AddToSharedResource, attributes(shared_resource, websummary))]
+pub fn add_to_shared_resource(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let item = syn::parse::<DeriveInput>(item).unwrap();
+    let struct_receiver = match SharedResourceReceiver::from_derive_input(&item) {
+        Ok(r) => r,
+        Err(e) => {
+            return proc_macro::TokenStream::from(
+                darling::Error::custom(format!("{SHARED_RESOURCE_UNSUPPORTED_ERROR}. {e}"))
+                    .write_errors(),
+            )
+        }
+    };
     quote! {
         #struct_receiver
     }
@@ -178,4 +442,10 @@ mod tests {
         let t = trybuild::TestCases::new();
         t.compile_fail("tests/ui_derive_form/*.rs");
     }
+
+    #[test]
+    fn ui_renamed_crate() {
+        let t = trybuild::TestCases::new();
+        t.pass("tests/ui_derive_pass/*.rs");
+    }
 }
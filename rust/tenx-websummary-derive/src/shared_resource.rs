@@ -0,0 +1,79 @@
+//!
+//! Helpers for procedural macro #[derive(AddToSharedResource)]
+//!
+
+use darling::{ast, FromDeriveInput, FromField};
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens, TokenStreamExt};
+use syn::{Attribute, Generics, Path};
+
+use crate::crate_path;
+
+#[derive(Debug, FromDeriveInput)]
+#[darling(
+    attributes(shared_resource),
+    forward_attrs(websummary),
+    supports(struct_named)
+)]
+pub(crate) struct SharedResourceReceiver {
+    ident: syn::Ident,
+
+    data: ast::Data<(), SharedResourceFieldReceiver>,
+
+    generics: Generics,
+
+    attrs: Vec<Attribute>,
+
+    websummary_crate: Option<Path>,
+}
+
+#[derive(Debug, FromField)]
+#[darling(attributes(shared_resource))]
+struct SharedResourceFieldReceiver {
+    ident: Option<syn::Ident>,
+
+    /// The type of the field does not hold any shared resources
+    #[darling(default)]
+    skip: bool,
+}
+
+impl ToTokens for SharedResourceReceiver {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let ident = &self.ident;
+        let websummary_crate =
+            match crate_path::websummary_crate(self.websummary_crate.as_ref(), &self.attrs) {
+                Ok(path) => path,
+                Err(e) => {
+                    tokens.append_all(e.write_errors());
+                    return;
+                }
+            };
+        let (impl_generics, ty_generics, where_clause) = self.generics.split_for_impl();
+
+        let fields = match self.data {
+            ast::Data::Struct(ref f) => f
+                .fields
+                .iter()
+                .filter(|field| !field.skip)
+                .map(|field| field.ident.clone().unwrap())
+                .collect::<Vec<_>>(),
+            _ => unreachable!(),
+        };
+
+        tokens.append_all(quote! {
+            impl #impl_generics #websummary_crate::AddToSharedResource for #ident #ty_generics #where_clause {
+                fn add_to_shared_resource(
+                    &mut self,
+                    shared_resource: &mut #websummary_crate::SharedResources,
+                ) {
+                    #(
+                        #websummary_crate::AddToSharedResource::add_to_shared_resource(
+                            &mut self.#fields,
+                            shared_resource,
+                        );
+                    )*
+                }
+            }
+        });
+    }
+}
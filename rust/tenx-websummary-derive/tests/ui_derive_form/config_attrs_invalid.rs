@@ -0,0 +1,33 @@
+use tenx_websummary_derive::HtmlForm;
+
+#[derive(HtmlForm)]
+struct TextAttrOnNumber {
+    #[html_form(rows = 3)]
+    num_cells: i64,
+}
+
+#[derive(HtmlForm)]
+struct NumberAttrOnText {
+    #[html_form(min = 1)]
+    sample: String,
+}
+
+#[derive(HtmlForm)]
+struct MixedAttrs {
+    #[html_form(placeholder = "Sample", max = 10)]
+    sample: String,
+}
+
+#[derive(HtmlForm)]
+struct MaxLengthOnNumber {
+    #[html_form(max_length = 10)]
+    num_cells: i64,
+}
+
+#[derive(HtmlForm)]
+struct EmptyRange {
+    #[html_form(min = 10, max = 1)]
+    num_cells: i64,
+}
+
+fn main() {}
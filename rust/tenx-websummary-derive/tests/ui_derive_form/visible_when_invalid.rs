@@ -0,0 +1,17 @@
+use tenx_websummary_derive::HtmlForm;
+
+#[derive(HtmlForm)]
+struct MissingOperator {
+    reference: String,
+    #[html_form(visible_when = "reference Custom")]
+    custom_reference: String,
+}
+
+#[derive(HtmlForm)]
+struct UnknownField {
+    reference: String,
+    #[html_form(visible_when = "genome == Custom")]
+    custom_reference: String,
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+use tenx_websummary_derive::HtmlForm;
+
+#[derive(HtmlForm)]
+struct StepZero {
+    #[html_form(wizard_step = 0)]
+    sample: String,
+}
+
+#[derive(HtmlForm)]
+struct MissingStep {
+    sample: String,
+    #[html_form(wizard_step = 3)]
+    reference: String,
+}
+
+fn main() {}
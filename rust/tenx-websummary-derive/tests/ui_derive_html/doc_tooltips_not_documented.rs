@@ -0,0 +1,14 @@
+use tenx_websummary_derive::HtmlTemplate;
+use ws::components::{Documented, HeroMetric};
+
+#[derive(HtmlTemplate)]
+#[html(doc_tooltips)]
+struct Summary {
+    /// Number of cells
+    cells: Documented<HeroMetric>,
+    /// Number of reads
+    reads: HeroMetric,
+    umis: HeroMetric,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use serde::Serialize;
+use tenx_websummary_derive::HtmlTemplate;
+use ws::components::HeroMetric;
+
+#[derive(Serialize, HtmlTemplate)]
+struct Summary {
+    cells: HeroMetric,
+    #[html(row = "cells")]
+    reads: HeroMetric,
+}
+
+fn main() {}
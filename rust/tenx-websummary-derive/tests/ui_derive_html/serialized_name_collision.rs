@@ -0,0 +1,23 @@
+use serde::Serialize;
+use tenx_websummary_derive::HtmlTemplate;
+use ws::components::{HeroMetric, TableMetric};
+
+#[derive(Serialize, HtmlTemplate)]
+struct Summary {
+    #[serde(rename = "metrics")]
+    cells: HeroMetric,
+    #[serde(rename = "metrics")]
+    reads: TableMetric,
+    // Not serialized under its name, so it does not collide
+    #[serde(skip)]
+    metrics: HeroMetric,
+}
+
+#[derive(Serialize, HtmlTemplate)]
+struct Renamed {
+    #[serde(rename(serialize = "umis", deserialize = "cells"))]
+    cells: HeroMetric,
+    umis: HeroMetric,
+}
+
+fn main() {}
@@ -0,0 +1,33 @@
+// The crate is a dependency named `ws`, and neither derive has an attribute
+// naming it
+use serde::Serialize;
+use ws::components::HeroMetric;
+use ws::form::IntoHtmlForm;
+use ws::{HtmlForm, HtmlTemplate};
+
+#[derive(Serialize, HtmlTemplate)]
+struct Summary {
+    cells: HeroMetric,
+    reads: HeroMetric,
+}
+
+#[derive(Serialize, HtmlForm)]
+struct SampleForm {
+    sample_id: String,
+    num_cells: i64,
+}
+
+fn main() {
+    let summary = Summary {
+        cells: HeroMetric::new("Number of cells", "3,487"),
+        reads: HeroMetric::new("Number of reads", "1.2M"),
+    };
+    assert!(summary.template(None).contains(r#"data-key="cells""#));
+
+    let form = SampleForm {
+        sample_id: "sample1".into(),
+        num_cells: 3487,
+    };
+    let _ = form.validate();
+    let _ = SampleForm::form();
+}
@@ -0,0 +1,33 @@
+// Fields share a row by naming it explicitly, including a row named after
+// one of the fields in it
+use serde::Serialize;
+use ws::components::HeroMetric;
+use ws::HtmlTemplate;
+
+#[derive(Serialize, HtmlTemplate)]
+struct Summary {
+    #[html(row = "cells")]
+    cells: HeroMetric,
+    #[html(row = "cells")]
+    reads: HeroMetric,
+    #[html(row = "metrics")]
+    #[serde(rename = "genes")]
+    median_genes: HeroMetric,
+    #[html(row = "metrics")]
+    umis: HeroMetric,
+}
+
+fn main() {
+    let summary = Summary {
+        cells: HeroMetric::new("Number of cells", "3,487"),
+        reads: HeroMetric::new("Number of reads", "1.2M"),
+        median_genes: HeroMetric::new("Median genes per cell", "1,850"),
+        umis: HeroMetric::new("Median UMIs per cell", "5,412"),
+    };
+    let template = summary.template(None);
+    assert_eq!(template.matches(r#"<div class="row">"#).count(), 2);
+    assert!(template.contains(r#"data-key="reads""#));
+    // The data key of a renamed field is its serialized name
+    assert!(template.contains(r#"data-key="genes""#));
+    assert!(!template.contains("median_genes"));
+}
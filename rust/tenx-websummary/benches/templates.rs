@@ -0,0 +1,150 @@
+//! Benchmarks for building the html templates of large pages
+//!
+//! Run with `cargo bench --features derive`
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde::Serialize;
+use serde_json::json;
+use tenx_websummary::components::{
+    DynGrid, GridLayout, HeroMetric, PlotlyChart, TableMetric, Tabs, Title, WithTitle,
+};
+use tenx_websummary::{HtmlTemplate, SinglePageHtml};
+
+fn metric(i: usize) -> HeroMetric {
+    HeroMetric::new(format!("Metric {i}"), format!("{i}"))
+}
+
+fn metrics_grid(n: usize) -> DynGrid {
+    let mut grid = DynGrid::new(GridLayout::MaxCols(4));
+    for i in 0..n {
+        grid.push(metric(i));
+    }
+    grid
+}
+
+#[derive(Serialize, HtmlTemplate)]
+struct Leaf {
+    cells: HeroMetric,
+    reads: HeroMetric,
+    table: TableMetric,
+}
+
+#[derive(Serialize, HtmlTemplate)]
+struct Level1 {
+    left: WithTitle<Leaf>,
+    right: WithTitle<Leaf>,
+}
+
+#[derive(Serialize, HtmlTemplate)]
+struct Level2 {
+    first: Level1,
+    second: Level1,
+    more: Vec<Level1>,
+}
+
+#[derive(Serialize, HtmlTemplate)]
+struct Nested {
+    sections: Vec<Level2>,
+}
+
+fn leaf(i: usize) -> WithTitle<Leaf> {
+    WithTitle::new(
+        Title::new(format!("Leaf {i}")),
+        Leaf {
+            cells: metric(i),
+            reads: metric(i + 1),
+            table: TableMetric {
+                rows: (0..10)
+                    .map(|r| (format!("Row {r}"), r.to_string()))
+                    .collect(),
+                row_ids: Vec::new(),
+            },
+        },
+    )
+}
+
+fn level1(i: usize) -> Level1 {
+    Level1 {
+        left: leaf(i),
+        right: leaf(i + 1),
+    }
+}
+
+fn nested() -> Nested {
+    Nested {
+        sections: (0..20)
+            .map(|i| Level2 {
+                first: level1(i),
+                second: level1(i + 1),
+                more: (0..5).map(level1).collect(),
+            })
+            .collect(),
+    }
+}
+
+/// A page shaped like a Visium HD summary: tabs of metric grids, plots and
+/// per-cluster sections
+#[derive(Serialize, HtmlTemplate)]
+struct HdPage {
+    summary: Tabs,
+    clusters: DynGrid,
+}
+
+fn hd_page() -> SinglePageHtml<HdPage> {
+    let mut plots = DynGrid::new(GridLayout::MaxCols(2));
+    for i in 0..50 {
+        plots.push(WithTitle::new(
+            Title::new(format!("Plot {i}")),
+            PlotlyChart::with_layout_and_data(
+                json!({"title": format!("Plot {i}")}),
+                vec![json!({"type": "scatter", "x": [1, 2, 3], "y": [i, i + 1, i + 2]})],
+            ),
+        ));
+    }
+    let mut bins = Tabs::new();
+    for bin in [2, 8, 16] {
+        bins.push(format!("{bin} µm"), metrics_grid(40));
+    }
+    let summary = Tabs::new()
+        .tab("Summary", metrics_grid(100))
+        .tab("Bins", bins)
+        .tab("Plots", plots);
+    let mut clusters = DynGrid::new(GridLayout::MaxCols(2));
+    for i in 0..200 {
+        clusters.push(Tabs::new().tab("Metrics", metrics_grid(6)).tab(
+            "Table",
+            TableMetric {
+                rows: vec![(format!("Cluster {i}"), i.to_string())],
+                row_ids: Vec::new(),
+            },
+        ));
+    }
+    SinglePageHtml::from_content(HdPage { summary, clusters })
+}
+
+fn bench_templates(c: &mut Criterion) {
+    let grid = metrics_grid(1000);
+    c.bench_function("grid_1000_metrics", |b| {
+        b.iter(|| black_box(&grid).template(Some("grid".into())))
+    });
+
+    let nested = nested();
+    c.bench_function("nested_derived_structs", |b| {
+        b.iter(|| black_box(&nested).template(None))
+    });
+
+    let page = hd_page();
+    c.bench_function("hd_page", |b| b.iter(|| black_box(&page).template(None)));
+
+    c.bench_function("grid_1000_metrics_build", |b| {
+        b.iter(|| metrics_grid(black_box(1000)))
+    });
+
+    let metrics: Vec<_> = (0..50_000).map(metric).collect();
+    c.bench_function("vec_50000_metrics", |b| {
+        b.iter(|| black_box(&metrics).template(Some("metrics".into())))
+    });
+}
+
+criterion_group!(benches, bench_templates);
+criterion_main!(benches);
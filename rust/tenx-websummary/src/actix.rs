@@ -1,26 +1,87 @@
 use actix_web::{body::BoxBody, http::StatusCode, HttpResponse, HttpResponseBuilder, Responder};
 use serde::Serialize;
 
+#[cfg(feature = "form")]
+use crate::components::WsNavBar;
+use crate::fallback::{HtmlOutcome, WithFallback};
+#[cfg(feature = "form")]
+use crate::form::{Form, FormPage, FormValidationResult, IntoHtmlForm};
 use crate::{HtmlTemplate, SinglePageHtml};
 
 impl<P: HtmlTemplate + Serialize> Responder for SinglePageHtml<P> {
     type Body = BoxBody;
 
-    fn respond_to(self, _: &actix_web::HttpRequest) -> HttpResponse<Self::Body> {
-        let mut buffer = Vec::new();
-        match self.generate_html(&mut buffer) {
-            Ok(_) => {
-                tracing::info!("Serving {}", bytesize::ByteSize(buffer.len() as u64));
-                HttpResponseBuilder::new(StatusCode::OK)
-                    .content_type("text/html; charset=utf-8")
-                    .body(buffer)
-            }
-            Err(err) => {
-                tracing::error!("Failed to generate html due to {:?}", err);
-                HttpResponse::from_error(actix_web::error::ErrorInternalServerError(
-                    err.to_string(),
-                ))
+    fn respond_to(self, req: &actix_web::HttpRequest) -> HttpResponse<Self::Body> {
+        respond_with_status(self, req, StatusCode::OK)
+    }
+}
+
+/// Serves the fallback page if the summary cannot be rendered
+impl<P: HtmlTemplate + Serialize> Responder for WithFallback<P> {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &actix_web::HttpRequest) -> HttpResponse<Self::Body> {
+        respond_with_html(req, StatusCode::OK, |buffer| {
+            let outcome = self.page.generate_html_or_fallback(buffer, self.fallback)?;
+            if let HtmlOutcome::Fallback(err) = outcome {
+                tracing::error!("Serving the fallback page due to {:?}", err);
             }
+            Ok(())
+        })
+    }
+}
+
+fn respond_with_status<P: HtmlTemplate + Serialize>(
+    page: SinglePageHtml<P>,
+    req: &actix_web::HttpRequest,
+    status: StatusCode,
+) -> HttpResponse {
+    respond_with_html(req, status, |buffer| page.generate_html(buffer))
+}
+
+/// Respond with the html written by `render`, or with an internal server
+/// error if it fails
+fn respond_with_html(
+    req: &actix_web::HttpRequest,
+    status: StatusCode,
+    render: impl FnOnce(&mut Vec<u8>) -> Result<(), anyhow::Error>,
+) -> HttpResponse {
+    // The render spans are nested under the span of the request
+    let _span = tracing::info_span!("render_html", path = req.path()).entered();
+    let mut buffer = Vec::new();
+    match render(&mut buffer) {
+        Ok(()) => {
+            tracing::info!("Serving {}", bytesize::ByteSize(buffer.len() as u64));
+            HttpResponseBuilder::new(status)
+                .content_type("text/html; charset=utf-8")
+                .body(buffer)
+        }
+        Err(err) => {
+            tracing::error!("Failed to generate html due to {:?}", err);
+            HttpResponse::from_error(actix_web::error::ErrorInternalServerError(err.to_string()))
+        }
+    }
+}
+
+#[cfg(feature = "form")]
+impl FormPage {
+    /// Validate a submitted form. Returns the validated form, or a response
+    /// which shows the form page again with the feedback of every field,
+    /// with the status `422 Unprocessable Entity`. The response is boxed
+    /// since it is much larger than the form.
+    pub fn validate_or_respond<T: IntoHtmlForm>(
+        value: &T,
+        nav: WsNavBar,
+        action_url: impl ToString,
+        req: &actix_web::HttpRequest,
+    ) -> Result<Form, Box<HttpResponse>> {
+        match value.validate() {
+            FormValidationResult::Valid(form) => Ok(form),
+            FormValidationResult::Invalid(form) => Err(Box::new(respond_with_status(
+                FormPage::invalid(form, nav, action_url),
+                req,
+                StatusCode::UNPROCESSABLE_ENTITY,
+            ))),
         }
     }
 }
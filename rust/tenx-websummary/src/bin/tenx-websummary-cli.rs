@@ -0,0 +1,173 @@
+//!
+//! Render, scrape and validate summaries from the command line, for
+//! pipelines which write the data and the summary html without linking the
+//! crate.
+//!
+//! ```text
+//! tenx-websummary-cli render --data data.json --summary summary.html [--template-dir DIR] --out out.html
+//! tenx-websummary-cli scrape --html out.html --out data.json
+//! tenx-websummary-cli validate --html out.html
+//! ```
+//!
+//! Errors are written to stderr as a single line starting with `error: `.
+//! The exit code is 0 on success, 1 if the command failed or the summary did
+//! not pass the validation, and 2 if the arguments are invalid.
+//!
+
+use std::collections::BTreeMap;
+use std::fs::{read_to_string, File};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use anyhow::{bail, Context, Error};
+use tenx_websummary::check::{check_rendered_html, IssueSeverity};
+use tenx_websummary::generate_html::TemplateInfo;
+use tenx_websummary::generate_html_summary;
+use tenx_websummary::scrape_json::scrape_json_from_html;
+
+const USAGE: &str = "Usage:
+  tenx-websummary-cli render --data <data.json> --summary <summary.html> [--template-dir <dir>] --out <out.html>
+  tenx-websummary-cli scrape --html <out.html> --out <data.json>
+  tenx-websummary-cli validate --html <out.html>";
+
+/// Exit code for invalid arguments
+const USAGE_EXIT_CODE: u8 = 2;
+
+/// Invalid command line, reported along with the usage
+#[derive(Debug)]
+struct UsageError(String);
+
+impl std::fmt::Display for UsageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for UsageError {}
+
+/// The `--name value` options of a subcommand
+struct Options {
+    values: BTreeMap<String, String>,
+}
+
+impl Options {
+    fn parse(args: &[String], allowed: &[&str]) -> Result<Self, UsageError> {
+        let mut values = BTreeMap::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let Some(name) = arg.strip_prefix("--").filter(|name| allowed.contains(name)) else {
+                return Err(UsageError(format!("unexpected argument {arg:?}")));
+            };
+            let Some(value) = args.next() else {
+                return Err(UsageError(format!("missing value for --{name}")));
+            };
+            if values.insert(name.to_string(), value.clone()).is_some() {
+                return Err(UsageError(format!("--{name} is given more than once")));
+            }
+        }
+        Ok(Options { values })
+    }
+
+    fn optional(&self, name: &str) -> Option<PathBuf> {
+        self.values.get(name).map(PathBuf::from)
+    }
+
+    fn required(&self, name: &str) -> Result<PathBuf, UsageError> {
+        self.optional(name)
+            .ok_or_else(|| UsageError(format!("missing required option --{name}")))
+    }
+}
+
+fn render(options: &Options) -> Result<(), Error> {
+    let data = options.required("data")?;
+    let summary = options.required("summary")?;
+    let template_dir = options.optional("template-dir");
+    let out = options.required("out")?;
+
+    let json_data =
+        read_to_string(&data).with_context(|| format!("Unable to read {}", data.display()))?;
+    serde_json::from_str::<serde_json::Value>(&json_data)
+        .with_context(|| format!("{} is not valid JSON", data.display()))?;
+    let summary_contents = read_to_string(&summary)
+        .with_context(|| format!("Unable to read {}", summary.display()))?;
+    let template_info = match template_dir {
+        Some(dir) => TemplateInfo::Dynamic(dir),
+        None => TemplateInfo::Default,
+    };
+    // Rendered in memory so that a failure does not leave a partial file
+    let mut html = Vec::new();
+    generate_html_summary(&json_data, summary_contents, template_info, &mut html)
+        .with_context(|| format!("Unable to render {}", summary.display()))?;
+    std::fs::write(&out, html).with_context(|| format!("Unable to write {}", out.display()))?;
+    Ok(())
+}
+
+fn scrape(options: &Options) -> Result<(), Error> {
+    let html = options.required("html")?;
+    let out = options.required("out")?;
+
+    let file = File::open(&html).with_context(|| format!("Unable to open {}", html.display()))?;
+    let data = scrape_json_from_html(file)
+        .with_context(|| format!("Unable to scrape the data from {}", html.display()))?;
+    std::fs::write(&out, serde_json::to_string_pretty(&data)?)
+        .with_context(|| format!("Unable to write {}", out.display()))?;
+    Ok(())
+}
+
+fn validate(options: &Options) -> Result<(), Error> {
+    let html = options.required("html")?;
+
+    let contents =
+        read_to_string(&html).with_context(|| format!("Unable to read {}", html.display()))?;
+    let issues = check_rendered_html(&contents)
+        .with_context(|| format!("Unable to validate {}", html.display()))?;
+    for issue in &issues {
+        eprintln!("{issue}");
+    }
+    let num_errors = issues
+        .iter()
+        .filter(|issue| issue.severity == IssueSeverity::Error)
+        .count();
+    if num_errors > 0 {
+        bail!(
+            "{} failed the validation with {num_errors} error(s)",
+            html.display()
+        );
+    }
+    Ok(())
+}
+
+fn run(args: &[String]) -> Result<(), Error> {
+    let Some((command, args)) = args.split_first() else {
+        bail!(UsageError("missing command".into()));
+    };
+    match command.as_str() {
+        "render" => render(&Options::parse(
+            args,
+            &["data", "summary", "template-dir", "out"],
+        )?),
+        "scrape" => scrape(&Options::parse(args, &["html", "out"])?),
+        "validate" => validate(&Options::parse(args, &["html"])?),
+        _ => bail!(UsageError(format!("unknown command {command:?}"))),
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if matches!(args.first().map(String::as_str), Some("--help" | "-h" | "help")) {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) if e.is::<UsageError>() => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            ExitCode::from(USAGE_EXIT_CODE)
+        }
+        Err(e) => {
+            // A single line with the causes, so that it can be grepped from logs
+            eprintln!("error: {e:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
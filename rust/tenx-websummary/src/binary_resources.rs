@@ -0,0 +1,273 @@
+//!
+//! Binary shared resources, e.g. downloadable files or large numeric arrays,
+//! which would pay for the escaping of a json string on top of the base64
+//! encoding. `SharedResources::insert_binary()` stores the bytes apart from
+//! the json resources, and returns a reference string like the other
+//! resources, e.g. `_resources_b000`.
+//!
+//! The bytes of all the binary resources are embedded as a single base64
+//! block under `_resources._binary`, and each binary resource is a
+//! descriptor `{"binary": {"mime": .., "offset": .., "size": ..}}` into that
+//! block. A loader script, run right after the data is defined and before
+//! the frontend reads it, replaces the descriptors with object urls of the
+//! bytes, so that the frontend resolves the reference strings as usual, see
+//! `PageHead::data_scripts`. Use `scrape_json::binary_resources()` to
+//! list the binary resources of a scraped summary.
+//!
+
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use crate::{next_free_key, RESOURCES_PREFIX};
+
+/// Prefix of the keys of the binary resources within the shared resources
+pub const BINARY_KEY_PREFIX: &str = "b";
+/// Key of the base64 block holding the bytes within the shared resources
+pub const BINARY_BLOCK_KEY: &str = "_binary";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryResource {
+    pub mime: String,
+    pub bytes: Vec<u8>,
+}
+
+/// The binary resources, keyed as in the shared resources
+#[derive(Debug, Clone, Default)]
+pub struct BinaryResources(pub BTreeMap<String, BinaryResource>);
+
+impl BinaryResources {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Insert the bytes unless identical bytes with the same mime type are
+    /// already present, returning the key
+    #[cfg(feature = "binary_resources")]
+    pub(crate) fn insert(&mut self, bytes: Vec<u8>, mime: &str) -> String {
+        let resource = BinaryResource {
+            mime: mime.to_string(),
+            bytes,
+        };
+        if let Some(key) = self
+            .0
+            .iter()
+            .find_map(|(key, val)| (val == &resource).then(|| key.clone()))
+        {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(key, "Deduplicated binary shared resource");
+            return key;
+        }
+        let key = next_free_key(BINARY_KEY_PREFIX, self.0.len(), |key| {
+            self.0.contains_key(key)
+        });
+        self.0.insert(key.clone(), resource);
+        key
+    }
+
+    /// Move the resources of `other` into these, as in
+    /// `SharedResources::merge()`. Returns the old and new reference strings
+    /// of the resources of `other` whose key changed.
+    pub(crate) fn merge(&mut self, other: BinaryResources) -> Vec<(String, String)> {
+        let mut remap = Vec::new();
+        for (key, resource) in other.0 {
+            let new_key = match self
+                .0
+                .iter()
+                .find_map(|(k, r)| (r == &resource).then(|| k.clone()))
+            {
+                Some(existing) => existing,
+                None => {
+                    let new_key = if self.0.contains_key(&key) {
+                        next_free_key(BINARY_KEY_PREFIX, self.0.len(), |k| self.0.contains_key(k))
+                    } else {
+                        key.clone()
+                    };
+                    self.0.insert(new_key.clone(), resource);
+                    new_key
+                }
+            };
+            if new_key != key {
+                remap.push((
+                    format!("{RESOURCES_PREFIX}_{key}"),
+                    format!("{RESOURCES_PREFIX}_{new_key}"),
+                ));
+            }
+        }
+        remap
+    }
+
+    /// Entries of the shared resources which embed the binary resources: a
+    /// descriptor for each and the base64 block
+    #[cfg(feature = "binary_resources")]
+    pub(crate) fn json_entries(&self) -> Vec<(String, Value)> {
+        let mut block = Vec::with_capacity(self.0.values().map(|r| r.bytes.len()).sum());
+        let mut entries = Vec::with_capacity(self.0.len() + 1);
+        for (key, resource) in &self.0 {
+            let descriptor = serde_json::json!({"binary": {
+                "mime": resource.mime,
+                "offset": block.len(),
+                "size": resource.bytes.len(),
+            }});
+            block.extend_from_slice(&resource.bytes);
+            entries.push((key.clone(), descriptor));
+        }
+        entries.push((
+            BINARY_BLOCK_KEY.to_string(),
+            Value::String(base64::encode(block)),
+        ));
+        entries
+    }
+}
+
+/// Script replacing the descriptors of the binary resources in the data with
+/// object urls of their bytes
+pub(crate) fn binary_loader() -> String {
+    format!(
+        r#"(function () {{
+  const resources = data["{RESOURCES_PREFIX}"];
+  const block = atob(resources["{BINARY_BLOCK_KEY}"] || "");
+  const bytes = new Uint8Array(block.length);
+  for (let i = 0; i < block.length; i++) {{
+    bytes[i] = block.charCodeAt(i);
+  }}
+  for (const [key, value] of Object.entries(resources)) {{
+    if (value && value.binary) {{
+      const {{ mime, offset, size }} = value.binary;
+      const blob = new Blob([bytes.subarray(offset, offset + size)], {{ type: mime }});
+      resources[key] = URL.createObjectURL(blob);
+    }}
+  }}
+  delete resources["{BINARY_BLOCK_KEY}"];
+}})();"#
+    )
+}
+
+/// A binary resource found in the data of a summary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryResourceInfo {
+    /// Reference string of the resource, e.g. `_resources_b000`
+    pub key: String,
+    pub mime: String,
+    /// Number of bytes
+    pub size: usize,
+    offset: usize,
+}
+
+impl BinaryResourceInfo {
+    /// The bytes of the resource from the data of the summary
+    #[cfg(feature = "binary_resources")]
+    pub fn decode(&self, data: &Value) -> Result<Vec<u8>, anyhow::Error> {
+        let Some(block) = data[RESOURCES_PREFIX][BINARY_BLOCK_KEY].as_str() else {
+            anyhow::bail!("The data does not have a block of binary resources");
+        };
+        let block = base64::decode(block)?;
+        match block.get(self.offset..self.offset + self.size) {
+            Some(bytes) => Ok(bytes.to_vec()),
+            None => anyhow::bail!(
+                "The binary resource {} is outside the block of {} bytes",
+                self.key,
+                block.len()
+            ),
+        }
+    }
+}
+
+/// The binary resources in the data of a summary, sorted by key
+pub(crate) fn binary_resource_infos(data: &Value) -> Vec<BinaryResourceInfo> {
+    let Some(resources) = data[RESOURCES_PREFIX].as_object() else {
+        return Vec::new();
+    };
+    let mut infos: Vec<_> = resources
+        .iter()
+        .filter_map(|(key, value)| {
+            let binary = value.get("binary")?;
+            Some(BinaryResourceInfo {
+                key: format!("{RESOURCES_PREFIX}_{key}"),
+                mime: binary["mime"].as_str()?.to_string(),
+                size: binary["size"].as_u64()? as usize,
+                offset: binary["offset"].as_u64()? as usize,
+            })
+        })
+        .collect();
+    infos.sort_by(|a, b| a.key.cmp(&b.key));
+    infos
+}
+
+#[cfg(all(test, feature = "binary_resources"))]
+mod tests {
+    use super::*;
+    use crate::SharedResources;
+
+    #[test]
+    fn test_insert_binary_dedup() {
+        let mut resources = SharedResources::new();
+        let a = resources.insert_binary(vec![1, 2, 3], "application/octet-stream");
+        let b = resources.insert_binary(vec![1, 2, 3], "application/octet-stream");
+        let c = resources.insert_binary(vec![1, 2, 3], "text/plain");
+        let json = resources.insert(serde_json::json!([1, 2, 3]));
+        assert_eq!(a, "_resources_b000");
+        assert_eq!(a, b);
+        assert_eq!(c, "_resources_b001");
+        assert_eq!(json, "_resources_000");
+        assert_eq!(resources.binary().len(), 2);
+        assert_eq!(resources.0.len(), 1);
+    }
+
+    #[test]
+    fn test_merge_binary() {
+        let mut first = SharedResources::new();
+        first.insert_binary(b"abc".to_vec(), "text/plain");
+        let mut second = SharedResources::new();
+        second.insert_binary(b"defg".to_vec(), "text/csv");
+        second.insert_binary(b"abc".to_vec(), "text/plain");
+        let remap = first.merge(second);
+        assert_eq!(remap.get("_resources_b000"), Some("_resources_b001"));
+        assert_eq!(remap.get("_resources_b001"), Some("_resources_b000"));
+        assert_eq!(first.1 .0["b001"].bytes, b"defg");
+        assert_eq!(first.1.len(), 2);
+    }
+
+    #[test]
+    fn test_serialize_binary() {
+        let mut resources = SharedResources::new();
+        resources.insert_binary(b"abc".to_vec(), "text/plain");
+        resources.insert_binary(b"defg".to_vec(), "text/csv");
+        let data = serde_json::json!({ RESOURCES_PREFIX: resources });
+        assert_eq!(
+            data[RESOURCES_PREFIX]["b001"],
+            serde_json::json!({"binary": {"mime": "text/csv", "offset": 3, "size": 4}})
+        );
+        assert_eq!(
+            data[RESOURCES_PREFIX][BINARY_BLOCK_KEY],
+            base64::encode("abcdefg")
+        );
+        // The loader is a script of the page, not a part of the data
+        assert_eq!(data[RESOURCES_PREFIX].as_object().unwrap().len(), 3);
+
+        let infos = binary_resource_infos(&data);
+        assert_eq!(
+            infos
+                .iter()
+                .map(|info| (info.key.as_str(), info.mime.as_str(), info.size))
+                .collect::<Vec<_>>(),
+            [
+                ("_resources_b000", "text/plain", 3),
+                ("_resources_b001", "text/csv", 4)
+            ]
+        );
+        assert_eq!(infos[1].decode(&data).unwrap(), b"defg");
+
+        // Only the json resources without binary resources
+        let mut resources = SharedResources::new();
+        resources.insert(serde_json::json!("x"));
+        assert_eq!(
+            serde_json::to_value(&resources).unwrap(),
+            serde_json::json!({"000": "x"})
+        );
+    }
+}
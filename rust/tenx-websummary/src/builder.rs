@@ -0,0 +1,269 @@
+//!
+//! High level API to assemble a web summary from a nav bar, alerts and a
+//! list of sections.
+//!
+
+use serde::{ser::SerializeMap, Serialize};
+
+use crate::{
+    components::{ErasedSection, WsNavBar},
+    AddToSharedResource, Alert, HtmlTemplate, SharedResources, SinglePageHtml, RESERVED_KEYS,
+};
+
+/// A web summary assembled using the `WebSummaryBuilder`
+pub type WebSummary = SinglePageHtml<BuiltContent>;
+
+impl WebSummary {
+    pub fn builder() -> WebSummaryBuilder {
+        WebSummaryBuilder::default()
+    }
+}
+
+/// Sections of the page in the order in which they were added. Each section
+/// is serialized under its key, which is also used as its data key.
+#[derive(Debug, Clone, Default)]
+pub struct BuiltContent {
+    sections: Vec<(String, ErasedSection)>,
+}
+
+impl BuiltContent {
+    pub fn new() -> Self {
+        BuiltContent::default()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.sections.iter().map(|(key, _)| key.as_str())
+    }
+
+    /// Add a section, e.g. using `html_content!`. Unlike
+    /// `WebSummaryBuilder::section()`, the resources of the component are
+    /// left in place.
+    ///
+    /// # Panics
+    /// Same as `WebSummaryBuilder::section()`
+    pub fn section<T: HtmlTemplate + Serialize>(mut self, key: &str, component: T) -> Self {
+        self.insert(key, ErasedSection::new(component));
+        self
+    }
+
+    /// Insert the section, replacing the section with the same key in place
+    fn insert(&mut self, key: &str, section: ErasedSection) {
+        assert!(
+            !RESERVED_KEYS.contains(&key),
+            "Section key {key:?} is reserved by the web summary"
+        );
+        match self.sections.iter_mut().find(|(k, _)| k == key) {
+            Some((_, existing)) => *existing = section,
+            None => self.sections.push((key.to_string(), section)),
+        }
+    }
+}
+
+impl Serialize for BuiltContent {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.sections.len()))?;
+        for (key, section) in &self.sections {
+            map.serialize_entry(key, section)?;
+        }
+        map.end()
+    }
+}
+
+impl HtmlTemplate for BuiltContent {
+    fn template(&self, data_key: Option<String>) -> String {
+        self.sections
+            .iter()
+            .map(|(key, section)| {
+                let key = match data_key {
+                    Some(ref d) => format!("{d}.{key}"),
+                    None => key.clone(),
+                };
+                section.template(Some(key))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Assembles a `WebSummary`. Components added using `section()` have their
+/// resources moved into the shared resources owned by the builder, so that
+/// identical resources are only stored once in the page.
+#[derive(Debug, Clone, Default)]
+pub struct WebSummaryBuilder {
+    nav_bar: Option<WsNavBar>,
+    alerts: Vec<Alert>,
+    content: BuiltContent,
+    resources: SharedResources,
+}
+
+impl WebSummaryBuilder {
+    pub fn new() -> Self {
+        WebSummaryBuilder::default()
+    }
+
+    pub fn nav(mut self, pipeline: &str, id: &str, description: &str) -> Self {
+        self.nav_bar = Some(WsNavBar::new(pipeline, id, description));
+        self
+    }
+
+    pub fn alert(mut self, alert: Alert) -> Self {
+        self.alerts.push(alert);
+        self
+    }
+
+    /// Add a section whose resources are moved into the shared resources.
+    ///
+    /// # Panics
+    /// If the key is one of the keys reserved by `SinglePageHtml` or if the
+    /// component cannot be serialized.
+    pub fn section<T>(mut self, key: &str, mut component: T) -> Self
+    where
+        T: HtmlTemplate + Serialize + AddToSharedResource,
+    {
+        component.add_to_shared_resource(&mut self.resources);
+        self.insert(key, component);
+        self
+    }
+
+    /// Add a section for a component which does not hold any shared resources.
+    ///
+    /// # Panics
+    /// Same as `section()`
+    pub fn section_plain<T>(mut self, key: &str, component: T) -> Self
+    where
+        T: HtmlTemplate + Serialize,
+    {
+        self.insert(key, component);
+        self
+    }
+
+    fn insert<T: HtmlTemplate + Serialize>(&mut self, key: &str, component: T) {
+        self.content.insert(key, ErasedSection::new(component));
+    }
+
+    pub fn build(self) -> WebSummary {
+        let summary = SinglePageHtml::from_content(self.content)
+            .alerts(self.alerts)
+            .resources(self.resources);
+        match self.nav_bar {
+            Some(nav_bar) => summary.nav_bar(nav_bar),
+            None => summary,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{BlendedImage, BlendedImageSliderSize, HeroMetric, NumOrStr};
+    use crate::AlertLevel;
+
+    const IMAGE_A: &str = "data:image/png;base64,AAAA";
+    const IMAGE_B: &str = "data:image/png;base64,BBBB";
+
+    fn blended(image1: &str, image2: &str) -> BlendedImage {
+        BlendedImage {
+            image1: image1.to_string(),
+            image1_title: None,
+            image2: image2.to_string(),
+            image2_title: None,
+            size: BlendedImageSliderSize {
+                width: NumOrStr::Num(300),
+            },
+            plot_title: None,
+            slider_title: None,
+        }
+    }
+
+    fn summary() -> WebSummary {
+        WebSummary::builder()
+            .nav("Space Ranger", "sample1", "Mouse brain")
+            .alert(Alert {
+                level: AlertLevel::Warn,
+                title: "Low fraction reads in cells".into(),
+                formatted_value: None,
+                message: "Fraction reads in cells is 50%".into(),
+                group: None,
+            })
+            .section_plain("metric", HeroMetric::new("Number of spots", "4,987"))
+            .section("tissue", blended(IMAGE_A, IMAGE_B))
+            .section("tissue_reversed", blended(IMAGE_B, IMAGE_A))
+            .build()
+    }
+
+    #[test]
+    fn test_builder() {
+        let summary = summary();
+        assert_eq!(
+            summary.content.keys().collect::<Vec<_>>(),
+            ["metric", "tissue", "tissue_reversed"]
+        );
+        let json = serde_json::to_value(&summary).unwrap();
+
+        // The images are only stored once
+        assert_eq!(json[crate::RESOURCES_PREFIX].as_object().unwrap().len(), 2);
+        assert_eq!(json["tissue"]["imgA"], json["tissue_reversed"]["imgB"]);
+        assert_eq!(json["tissue"]["imgB"], json["tissue_reversed"]["imgA"]);
+        assert_eq!(json["sample"]["id"], "sample1");
+        assert_eq!(json["alarms"]["alarms"].as_array().unwrap().len(), 1);
+
+        // Sections are rendered in insertion order and every key resolves
+        let template = summary.template(None);
+        let re = regex::Regex::new(r#"data-key="([^"]+)""#).unwrap();
+        let keys: Vec<_> = re
+            .captures_iter(&template)
+            .map(|cap| cap[1].to_string())
+            .collect();
+        assert_eq!(keys, ["metric", "tissue", "tissue_reversed"]);
+        for key in keys {
+            assert!(!json[&key].is_null(), "Unable to resolve {key}");
+        }
+    }
+
+    #[test]
+    fn test_builder_replaces_section() {
+        let summary = WebSummary::builder()
+            .section_plain("a", HeroMetric::new("A", "1"))
+            .section_plain("b", HeroMetric::new("B", "2"))
+            .section_plain("a", HeroMetric::new("A", "3"))
+            .build();
+        assert_eq!(summary.content.keys().collect::<Vec<_>>(), ["a", "b"]);
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["a"]["metric"], "3");
+        assert!(json["sample"].is_null());
+    }
+
+    #[test]
+    #[should_panic(expected = "reserved")]
+    fn test_builder_reserved_key() {
+        WebSummary::builder().section_plain("alarms", HeroMetric::new("A", "1"));
+    }
+
+    #[test]
+    #[should_panic(expected = "reserved")]
+    fn test_builder_reserved_chunk_key() {
+        WebSummary::builder().section_plain(
+            crate::chunked::CHUNK_MANIFEST_KEY,
+            HeroMetric::new("A", "1"),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "reserved")]
+    fn test_builder_reserved_dark_mode_key() {
+        WebSummary::builder().section_plain("_dark_mode", HeroMetric::new("A", "1"));
+    }
+
+    #[test]
+    fn test_built_content_order() {
+        let content = WebSummary::builder()
+            .section_plain("z", HeroMetric::new("Z", "1"))
+            .section_plain("a", HeroMetric::new("A", "2"))
+            .build()
+            .content;
+        assert_eq!(
+            serde_json::to_string(&content).unwrap(),
+            r#"{"z":{"metric":"1","name":"Z","threshold":null},"a":{"metric":"2","name":"A","threshold":null}}"#
+        );
+    }
+}
@@ -0,0 +1,596 @@
+//!
+//! Checks run before rendering a summary, to catch summaries which would
+//! render as a blank looking page.
+//!
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt;
+
+use anyhow::{bail, Error};
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::components::{component_link_targets, downgrade_component_links, section_id};
+use crate::scrape_json::{scrape_json_from_html, DATA_BEGIN_MARKER, DATA_END_MARKER};
+use crate::RESERVED_KEYS;
+use crate::{replace_strings, HtmlTemplate, SinglePageHtml, WebSummaryBuildFiles};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IssueSeverity {
+    Warning,
+    /// `generate_html_checked` fails on these
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IssueKind {
+    /// A field of the nav bar, e.g. the sample id, is empty
+    EmptyNavBarField(&'static str),
+    /// The content serializes to an empty object
+    EmptyContent,
+    /// An alert without a message. Holds the title of the alert.
+    EmptyAlertMessage(String),
+    /// A data key in the template which does not resolve to a value
+    DanglingDataKey(String),
+    /// A data key used by more than one component in the template
+    DuplicateDataKey(String),
+    /// The target data key of a `ComponentLink` without a section with its
+    /// id in the template. The link is rendered as plain text.
+    DanglingComponentLink(String),
+    /// An alert group without an `AlertGroupSlot` in the content. Its alerts
+    /// are shown in the banner at the top of the page. Holds the name of the
+    /// group.
+    AlertGroupWithoutSlot(String),
+}
+
+impl IssueKind {
+    fn severity(&self) -> IssueSeverity {
+        match self {
+            IssueKind::EmptyContent | IssueKind::DanglingDataKey(_) => IssueSeverity::Error,
+            IssueKind::EmptyNavBarField(_)
+            | IssueKind::EmptyAlertMessage(_)
+            | IssueKind::DuplicateDataKey(_)
+            | IssueKind::DanglingComponentLink(_)
+            | IssueKind::AlertGroupWithoutSlot(_) => IssueSeverity::Warning,
+        }
+    }
+}
+
+/// A problem found by `SinglePageHtml::check()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SummaryIssue {
+    pub severity: IssueSeverity,
+    pub kind: IssueKind,
+}
+
+impl From<IssueKind> for SummaryIssue {
+    fn from(kind: IssueKind) -> Self {
+        SummaryIssue {
+            severity: kind.severity(),
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for SummaryIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            IssueSeverity::Warning => "warning",
+            IssueSeverity::Error => "error",
+        };
+        write!(f, "{severity}: ")?;
+        match &self.kind {
+            IssueKind::EmptyNavBarField(field) => write!(f, "The nav bar {field} is empty"),
+            IssueKind::EmptyContent => write!(f, "The content of the summary is empty"),
+            IssueKind::EmptyAlertMessage(title) => {
+                write!(f, "The alert {title:?} does not have a message")
+            }
+            IssueKind::DanglingDataKey(key) => {
+                write!(f, "The data key {key:?} in the template does not resolve")
+            }
+            IssueKind::DuplicateDataKey(key) => {
+                write!(
+                    f,
+                    "The data key {key:?} is used more than once in the template"
+                )
+            }
+            IssueKind::DanglingComponentLink(key) => {
+                write!(
+                    f,
+                    "The link to {key:?} does not resolve to a section and is shown as plain text"
+                )
+            }
+            IssueKind::AlertGroupWithoutSlot(name) => {
+                write!(
+                    f,
+                    "The alert group {name:?} has no slot in the content and is shown in the banner"
+                )
+            }
+        }
+    }
+}
+
+/// JSON pointer of a data key such as `tabs.tab_data[0].grid_data[1]`, i.e.
+/// `/tabs/tab_data/0/grid_data/1`
+fn data_key_pointer(key: &str) -> Option<String> {
+    let mut pointer = String::new();
+    for part in key.split('.') {
+        let (key, indices) = match part.split_once('[') {
+            Some((key, indices)) => (key, Some(indices)),
+            None => (part, None),
+        };
+        pointer.push('/');
+        pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+        for index in indices.iter().flat_map(|indices| indices.split('[')) {
+            let index = index.trim_end_matches(']').parse::<usize>().ok()?;
+            pointer.push_str(&format!("/{index}"));
+        }
+    }
+    Some(pointer)
+}
+
+/// Resolve a data key such as `tabs.tab_data[0].grid_data[1]` in the data
+pub(crate) fn resolve_data_key<'a>(data: &'a Value, key: &str) -> Option<&'a Value> {
+    data.pointer(&data_key_pointer(key)?)
+}
+
+/// Same as `resolve_data_key()`, returning a mutable reference
+pub(crate) fn resolve_data_key_mut<'a>(data: &'a mut Value, key: &str) -> Option<&'a mut Value> {
+    data.pointer_mut(&data_key_pointer(key)?)
+}
+
+/// Data keys in the template which do not resolve, and which are used more
+/// than once
+pub(crate) fn check_data_keys(template: &str, data: &Value) -> Vec<IssueKind> {
+    let mut issues = Vec::new();
+    let mut seen = HashSet::new();
+    for key in template_data_keys(template) {
+        if !seen.insert(key) {
+            issues.push(IssueKind::DuplicateDataKey(key.to_string()));
+        } else if resolve_data_key(data, key)
+            .filter(|value| !value.is_null())
+            .is_none()
+        {
+            issues.push(IssueKind::DanglingDataKey(key.to_string()));
+        }
+    }
+    issues
+}
+
+/// Data keys of the components in the template, in order
+pub(crate) fn template_data_keys(template: &str) -> Vec<&str> {
+    let re = Regex::new(r#"data-key="([^"]+)""#).unwrap();
+    re.captures_iter(template)
+        .map(|cap| cap.get(1).unwrap().as_str())
+        .collect()
+}
+
+fn collect_strings<'a>(value: &'a Value, out: &mut Vec<&'a str>) {
+    match value {
+        Value::String(s) => out.push(s),
+        Value::Array(values) => values.iter().for_each(|v| collect_strings(v, out)),
+        Value::Object(map) => map.values().for_each(|v| collect_strings(v, out)),
+        _ => {}
+    }
+}
+
+/// Targets of the `ComponentLink`s in the template and in the strings of
+/// the data, e.g. help texts, whose section id is not rendered in the
+/// template, so the link would point at nothing
+pub(crate) fn dangling_component_links(template: &str, data: &Value) -> Vec<String> {
+    let mut strings = vec![template];
+    collect_strings(data, &mut strings);
+    let mut targets: Vec<String> = strings
+        .into_iter()
+        .flat_map(component_link_targets)
+        .collect();
+    targets.sort();
+    targets.dedup();
+    targets.retain(|key| !template.contains(&format!(r#" id="{}""#, section_id(key))));
+    targets
+}
+
+/// Render the dangling `ComponentLink`s of the template and of the json data
+/// as plain text, see `dangling_component_links()`
+pub(crate) fn downgrade_dangling_component_links(
+    json_data: String,
+    template: String,
+    pretty: bool,
+) -> Result<(String, String), Error> {
+    let has_links = |html: &str| html.contains("data-link-key=");
+    if !has_links(&template) && !has_links(&json_data) {
+        return Ok((json_data, template));
+    }
+    let mut data: Value = serde_json::from_str(&json_data)?;
+    let dangling = dangling_component_links(&template, &data);
+    if dangling.is_empty() {
+        return Ok((json_data, template));
+    }
+    #[cfg(feature = "tracing")]
+    for key in &dangling {
+        tracing::warn!(
+            data_key = key.as_str(),
+            "The component link does not resolve and is shown as plain text"
+        );
+    }
+    let template = downgrade_component_links(&template, &dangling).into_owned();
+    if !has_links(&json_data) {
+        return Ok((json_data, template));
+    }
+    replace_strings(
+        &mut data,
+        &|s| match downgrade_component_links(s, &dangling) {
+            Cow::Owned(s) => Some(Value::String(s)),
+            Cow::Borrowed(_) => None,
+        },
+    );
+    let json_data = if pretty {
+        serde_json::to_string_pretty(&data)?
+    } else {
+        serde_json::to_string(&data)?
+    };
+    Ok((json_data, template))
+}
+
+/// Fails if any of the issues is an error, otherwise returns the warnings,
+/// which are also logged
+fn ensure_no_errors(issues: Vec<SummaryIssue>) -> Result<Vec<SummaryIssue>, Error> {
+    let (errors, warnings): (Vec<_>, Vec<_>) = issues
+        .into_iter()
+        .partition(|issue| issue.severity == IssueSeverity::Error);
+    if !errors.is_empty() {
+        bail!(
+            "The summary failed the checks:\n{}",
+            errors
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+    }
+    #[cfg(feature = "tracing")]
+    for warning in &warnings {
+        tracing::warn!("{warning}");
+    }
+    Ok(warnings)
+}
+
+impl<P: Serialize + HtmlTemplate> SinglePageHtml<P> {
+    /// Problems in the summary which would make it render as a blank or
+    /// incomplete page
+    pub fn check(&self) -> Vec<SummaryIssue> {
+        let mut issues = Vec::new();
+        if let Some(nav_bar) = &self.nav_bar {
+            for (field, value) in [
+                ("pipeline", &nav_bar.pipeline),
+                ("id", &nav_bar.id),
+                ("description", &nav_bar.description),
+            ] {
+                if value.trim().is_empty() {
+                    issues.push(IssueKind::EmptyNavBarField(field));
+                }
+            }
+        }
+        match serde_json::to_value(&self.content) {
+            Ok(Value::Object(map)) if !map.is_empty() => {}
+            _ => issues.push(IssueKind::EmptyContent),
+        }
+        for alert in self.all_alerts() {
+            if alert.message.trim().is_empty() {
+                issues.push(IssueKind::EmptyAlertMessage(alert.title));
+            }
+        }
+        issues.extend(
+            self.unplaced_alert_groups()
+                .into_iter()
+                .map(IssueKind::AlertGroupWithoutSlot),
+        );
+        if let Ok(data) = serde_json::to_value(self) {
+            let template = self.template(None);
+            issues.extend(check_data_keys(&template, &data));
+            issues.extend(
+                dangling_component_links(&template, &data)
+                    .into_iter()
+                    .map(IssueKind::DanglingComponentLink),
+            );
+        }
+        issues.into_iter().map(SummaryIssue::from).collect()
+    }
+
+    /// Same as `generate_html`, but fails if `check()` finds any errors.
+    /// Returns the warnings, if any, which are also logged with `tracing` if
+    /// the `tracing` feature is enabled.
+    #[cfg(feature = "generate_html")]
+    pub fn generate_html_checked<W: std::io::Write>(
+        self,
+        writer: W,
+    ) -> Result<Vec<SummaryIssue>, Error> {
+        let warnings = ensure_no_errors(self.check())?;
+        self.generate_html(writer)?;
+        Ok(warnings)
+    }
+
+    pub fn generate_html_checked_with_build_files<W: std::io::Write>(
+        self,
+        writer: W,
+        build_files: WebSummaryBuildFiles<'_>,
+    ) -> Result<Vec<SummaryIssue>, Error> {
+        let warnings = ensure_no_errors(self.check())?;
+        self.generate_html_with_build_files(writer, build_files)?;
+        Ok(warnings)
+    }
+}
+
+/// Problems found in an html which was already rendered, e.g. by another
+/// tool: the data keys of the components which do not resolve in the data,
+/// and an empty content. Fails if the data cannot be scraped.
+pub fn check_rendered_html(html: &str) -> Result<Vec<SummaryIssue>, Error> {
+    let data = scrape_json_from_html(html.as_bytes())?;
+    let mut issues = Vec::new();
+    match &data {
+        Value::Object(map) if map.keys().any(|key| !RESERVED_KEYS.contains(&key.as_str())) => {}
+        _ => issues.push(IssueKind::EmptyContent),
+    }
+    // The data itself may hold text which looks like a data key
+    let template = match (html.find(DATA_BEGIN_MARKER), html.find(DATA_END_MARKER)) {
+        (Some(begin), Some(end)) if begin < end => {
+            format!("{}{}", &html[..begin], &html[end..])
+        }
+        _ => html.to_string(),
+    };
+    issues.extend(check_data_keys(&template, &data));
+    Ok(issues.into_iter().map(SummaryIssue::from).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::components::{ComponentLink, HeroMetric, WsNavBar};
+    use crate::test_utils::test_build_files;
+    use crate::{Alert, AlertLevel, WebSummary};
+
+    fn alert(message: &str) -> Alert {
+        Alert {
+            level: AlertLevel::Warn,
+            title: "Low fraction reads in cells".into(),
+            formatted_value: None,
+            message: message.into(),
+            group: None,
+        }
+    }
+
+    fn kinds(issues: &[SummaryIssue]) -> Vec<&IssueKind> {
+        issues.iter().map(|issue| &issue.kind).collect()
+    }
+
+    #[test]
+    fn test_check_ok() -> Result<(), Error> {
+        let summary = WebSummary::builder()
+            .nav("Cell Ranger", "sample1", "PBMC")
+            .alert(alert("Ideal > 70%"))
+            .section_plain("cells", HeroMetric::new("Number of cells", "3,487"))
+            .build();
+        assert!(summary.check().is_empty());
+        assert!(summary
+            .generate_html_checked_with_build_files(Vec::new(), test_build_files())?
+            .is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_warnings() -> Result<(), Error> {
+        let summary = WebSummary::builder()
+            .nav("Cell Ranger", " ", "")
+            .alert(alert(""))
+            .section_plain("cells", HeroMetric::new("Number of cells", "3,487"))
+            .build();
+        let issues = summary.check();
+        assert_eq!(
+            kinds(&issues),
+            [
+                &IssueKind::EmptyNavBarField("id"),
+                &IssueKind::EmptyNavBarField("description"),
+                &IssueKind::EmptyAlertMessage("Low fraction reads in cells".into()),
+            ]
+        );
+        assert!(issues
+            .iter()
+            .all(|issue| issue.severity == IssueSeverity::Warning));
+        assert_eq!(issues[0].to_string(), "warning: The nav bar id is empty");
+
+        // Warnings do not fail the generation, and are returned
+        let mut out = Vec::new();
+        let warnings =
+            summary.generate_html_checked_with_build_files(&mut out, test_build_files())?;
+        assert_eq!(warnings, issues);
+        assert!(!out.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_empty_content() {
+        let summary = WebSummary::builder().build();
+        let issues = summary.check();
+        assert_eq!(kinds(&issues), [&IssueKind::EmptyContent]);
+        assert_eq!(issues[0].severity, IssueSeverity::Error);
+
+        let err = summary
+            .generate_html_checked_with_build_files(Vec::new(), test_build_files())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The summary failed the checks:\nerror: The content of the summary is empty"
+        );
+    }
+
+    #[derive(Serialize)]
+    struct Linked {
+        cells: HeroMetric,
+        plot: HeroMetric,
+        reads: HeroMetric,
+    }
+
+    impl HtmlTemplate for Linked {
+        fn template(&self, _: Option<String>) -> String {
+            let link = ComponentLink::to_data_key;
+            // Only the cells have a section id, the plot is a bare component
+            [
+                r#"<div class="col" id="cells">"#.to_string(),
+                self.cells.template(Some("cells".into())),
+                "</div>".into(),
+                self.plot.template(Some("plot".into())),
+                self.reads.template(Some("reads".into())),
+                link("the cells", "cells").html(),
+                link("the plot", "plot").html(),
+            ]
+            .join("\n")
+        }
+    }
+
+    #[test]
+    fn test_component_links() -> Result<(), Error> {
+        let link = ComponentLink::to_data_key;
+        let summary = SinglePageHtml::from_content(Linked {
+            cells: HeroMetric::new("Number of cells", "3,487"),
+            plot: HeroMetric::new("Fraction of reads in cells", "92.1%"),
+            reads: HeroMetric::new(link("Reads", "sequencing").html(), "335,174,516"),
+        })
+        .nav_bar(WsNavBar::new("Cell Ranger", "sample1", "PBMC"));
+        let issues = summary.check();
+        assert_eq!(
+            kinds(&issues),
+            [
+                &IssueKind::DanglingComponentLink("plot".into()),
+                &IssueKind::DanglingComponentLink("sequencing".into()),
+            ]
+        );
+        assert_eq!(issues[0].severity, IssueSeverity::Warning);
+
+        let mut out = Vec::new();
+        summary.generate_html_checked_with_build_files(&mut out, test_build_files())?;
+        let html = String::from_utf8(out)?;
+        // Only the link to the cells is left, the others are plain text
+        assert_eq!(html.matches("data-link-key").count(), 1);
+        assert!(html.contains(r##"<a href="#cells" data-link-key="cells">the cells</a>"##));
+        assert!(html.contains(r#""name":"Reads""#));
+        assert!(html.contains("the plot"));
+
+        // The text and the target are escaped, and the target is unescaped
+        // to match the section id
+        let escaped = link("<Reads & UMIs>", "a\"b").html();
+        assert_eq!(
+            escaped,
+            r##"<a href="#a-b" data-link-key="a&quot;b">&lt;Reads &amp; UMIs&gt;</a>"##
+        );
+        let template = format!(r#"<div class="col" id="a-b"></div>{escaped}"#);
+        assert!(dangling_component_links(&template, &json!({})).is_empty());
+        assert_eq!(dangling_component_links(&escaped, &json!({})), ["a\"b"]);
+
+        // Sections with an id are targets too
+        let template = r#"<div class="col" id="sequencing"></div>"#.to_string()
+            + &link("Sequencing", "sequencing").html()
+            + &link("Reads", "sequencing.reads").html();
+        assert_eq!(
+            dangling_component_links(&template, &json!({})),
+            ["sequencing.reads"]
+        );
+        Ok(())
+    }
+
+    #[derive(Serialize)]
+    struct Content {
+        metrics: Vec<HeroMetric>,
+        plot: Option<Value>,
+    }
+
+    impl HtmlTemplate for Content {
+        fn template(&self, _: Option<String>) -> String {
+            [
+                r#"<div data-key="metrics[0]" data-component="Metric"></div>"#,
+                r#"<div data-key="metrics[1]" data-component="Metric"></div>"#,
+                r#"<div data-key="metrics[0]" data-component="Metric"></div>"#,
+                r#"<div data-key="plot" data-component="Plot"></div>"#,
+            ]
+            .join("\n")
+        }
+    }
+
+    #[test]
+    fn test_check_data_keys() {
+        let summary = SinglePageHtml::from_content(Content {
+            metrics: vec![HeroMetric::new("Number of cells", "3,487")],
+            plot: None,
+        })
+        .nav_bar(WsNavBar::new("Cell Ranger", "sample1", "PBMC"));
+        let issues = summary.check();
+        assert_eq!(
+            kinds(&issues),
+            [
+                &IssueKind::DanglingDataKey("metrics[1]".into()),
+                &IssueKind::DuplicateDataKey("metrics[0]".into()),
+                &IssueKind::DanglingDataKey("plot".into()),
+            ]
+        );
+        assert_eq!(
+            issues
+                .iter()
+                .map(|issue| issue.severity)
+                .collect::<Vec<_>>(),
+            [
+                IssueSeverity::Error,
+                IssueSeverity::Warning,
+                IssueSeverity::Error
+            ]
+        );
+        assert!(summary
+            .generate_html_checked_with_build_files(Vec::new(), test_build_files())
+            .is_err());
+    }
+
+    #[test]
+    fn test_check_rendered_html() -> Result<(), Error> {
+        let summary = SinglePageHtml::from_content(Content {
+            metrics: vec![HeroMetric::new("Number of cells", r#"data-key="hidden""#)],
+            plot: Some(json!({"data": []})),
+        });
+        let mut out = Vec::new();
+        summary.generate_html_with_build_files(&mut out, test_build_files())?;
+        let issues = check_rendered_html(&String::from_utf8(out)?)?;
+        assert_eq!(
+            kinds(&issues),
+            [
+                &IssueKind::DanglingDataKey("metrics[1]".into()),
+                &IssueKind::DuplicateDataKey("metrics[0]".into()),
+            ]
+        );
+
+        let mut out = Vec::new();
+        WebSummary::builder()
+            .build()
+            .generate_html_with_build_files(&mut out, test_build_files())?;
+        let issues = check_rendered_html(&String::from_utf8(out)?)?;
+        assert_eq!(kinds(&issues), [&IssueKind::EmptyContent]);
+        assert!(check_rendered_html("<html></html>").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_data_key() {
+        let data = json!({"tabs": {"tab_data": [{"grid": [[1, 2]]}]}});
+        assert_eq!(
+            resolve_data_key(&data, "tabs.tab_data[0].grid[0][1]"),
+            Some(&json!(2))
+        );
+        assert_eq!(resolve_data_key(&data, "tabs.tab_data[1]"), None);
+        assert_eq!(resolve_data_key(&data, "tabs.missing"), None);
+        assert_eq!(resolve_data_key(&data, "tabs.tab_data[x]"), None);
+
+        let mut data = data;
+        *resolve_data_key_mut(&mut data, "tabs.tab_data[0].grid[0][1]").unwrap() = json!(3);
+        assert_eq!(data["tabs"]["tab_data"][0]["grid"][0][1], 3);
+    }
+}
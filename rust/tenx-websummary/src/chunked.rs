@@ -0,0 +1,367 @@
+//!
+//! Split the data of a huge summary into chunks which are loaded lazily, e.g.
+//! so that a summary with many tabs only embeds the data of the tabs which
+//! are not chunked. The chunks are written as scripts next to the html,
+//! and the embedded data holds a manifest of the chunk files under
+//! `_chunks`. The data keys in the template are unchanged: the frontend
+//! resolves the keys missing from the embedded data from the chunks using
+//! the loader in `_chunk_loader`. The loader adds a script tag per chunk,
+//! since `fetch()` fails for the html opened from a `file://` url.
+//!
+//! Use `scrape_json::scrape_chunked_json_from_html()` to read the data of a
+//! chunked summary back in one piece.
+//!
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{bail, format_err, Context, Error};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::components::JavaScript;
+use crate::generate_html::{generate_html_summary_with_head, TemplateInfo};
+use crate::{HtmlTemplate, SinglePageHtml, WebSummaryBuildFiles};
+
+/// Name of the html file written by `generate_html_chunked()`
+pub const CHUNKED_HTML_FILE: &str = "web_summary.html";
+/// Key of the manifest in the embedded data, which maps each chunked key to
+/// the name of its chunk file
+pub const CHUNK_MANIFEST_KEY: &str = "_chunks";
+/// Key of the javascript which loads the chunks
+pub const CHUNK_LOADER_KEY: &str = "_chunk_loader";
+
+/// How to split the data of the summary into chunks
+#[derive(Debug, Clone)]
+pub enum ChunkBy {
+    /// Each of these top level keys of the content goes into its own chunk
+    TopLevelKeys(Vec<String>),
+}
+
+/// Function called by a chunk script with the key and the data of the chunk
+const CHUNK_CALLBACK: &str = "webSummaryChunkLoaded";
+
+/// Name of the chunk file for the top level key. Characters which are not
+/// safe in a file name are replaced with `_`, so distinct keys can have the
+/// same file name, which `generate_html_chunked()` rejects.
+pub fn chunk_file_name(key: &str) -> String {
+    let key: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("chunk_{key}.js")
+}
+
+/// The script of a chunk file, which hands the data of the key to the loader
+fn write_chunk_script(
+    mut writer: impl Write,
+    key: &str,
+    chunk: &Value,
+    pretty: bool,
+) -> Result<(), Error> {
+    writeln!(writer, "{CHUNK_CALLBACK}({},", serde_json::to_string(key)?)?;
+    if pretty {
+        serde_json::to_writer_pretty(&mut writer, chunk)?;
+    } else {
+        serde_json::to_writer(&mut writer, chunk)?;
+    }
+    writeln!(writer, "\n);")?;
+    Ok(())
+}
+
+/// The data of a chunk file written by `write_chunk_script()`
+pub(crate) fn parse_chunk_script(script: &str) -> Result<Value, Error> {
+    let json = script
+        .split_once('\n')
+        .filter(|(first, _)| first.starts_with(CHUNK_CALLBACK))
+        .and_then(|(_, rest)| rest.trim_end().strip_suffix(");"))
+        .ok_or_else(|| format_err!("Not a chunk script written by tenx-websummary"))?;
+    Ok(serde_json::from_str(json)?)
+}
+
+fn chunk_loader() -> JavaScript {
+    JavaScript::new(format!(
+        r#"(function () {{
+  const chunks = data["{CHUNK_MANIFEST_KEY}"] || {{}};
+  const pending = {{}};
+  const resolvers = {{}};
+  window.{CHUNK_CALLBACK} = (key, value) => {{
+    data[key] = value;
+    if (key in resolvers) {{
+      resolvers[key](value);
+    }}
+  }};
+  window.loadWebSummaryChunk = (key) => {{
+    if (key in data || !(key in chunks)) {{
+      return Promise.resolve(data[key]);
+    }}
+    if (!(key in pending)) {{
+      pending[key] = new Promise((resolve, reject) => {{
+        resolvers[key] = resolve;
+        const script = document.createElement("script");
+        script.src = chunks[key];
+        script.onerror = () => reject(new Error("Failed to load " + chunks[key]));
+        document.head.appendChild(script);
+      }});
+    }}
+    return pending[key];
+  }};
+}})();"#
+    ))
+}
+
+/// Move the data of the chunked keys out of the page data, returning the
+/// chunks along with their keys and file names
+fn split_chunks(
+    data: &mut Map<String, Value>,
+    chunk_by: &ChunkBy,
+) -> Result<Vec<(String, String, Value)>, Error> {
+    let ChunkBy::TopLevelKeys(keys) = chunk_by;
+    let mut manifest = Map::new();
+    let mut keys_of_files = HashMap::new();
+    let mut chunks = Vec::new();
+    for key in keys {
+        if crate::RESERVED_KEYS.contains(&key.as_str()) {
+            bail!("{key} is not a part of the content and cannot be chunked");
+        }
+        let Some(value) = data.remove(key) else {
+            bail!("The key {key} to chunk is not in the content of the summary");
+        };
+        let file_name = chunk_file_name(key);
+        if manifest
+            .insert(key.clone(), Value::String(file_name.clone()))
+            .is_some()
+        {
+            bail!("The key {key} is chunked more than once");
+        }
+        if let Some(other) = keys_of_files.insert(file_name.clone(), key) {
+            bail!("The keys {other} and {key} would both be chunked into {file_name}");
+        }
+        chunks.push((key.clone(), file_name, value));
+    }
+    data.insert(CHUNK_MANIFEST_KEY.to_string(), Value::Object(manifest));
+    data.insert(
+        CHUNK_LOADER_KEY.to_string(),
+        serde_json::to_value(chunk_loader())?,
+    );
+    Ok(chunks)
+}
+
+impl<P: Serialize + HtmlTemplate> SinglePageHtml<P> {
+    /// Write the html to `CHUNKED_HTML_FILE` in the directory, with the data
+    /// of the chunked keys in separate json files next to it. See
+    /// `chunked`.
+    #[cfg(feature = "generate_html")]
+    pub fn generate_html_chunked(
+        self,
+        dir: impl AsRef<Path>,
+        chunk_by: ChunkBy,
+    ) -> Result<(), Error> {
+        self.generate_html_chunked_with_build_files(dir, chunk_by, WebSummaryBuildFiles::bundled())
+    }
+
+    pub fn generate_html_chunked_with_build_files(
+        self,
+        dir: impl AsRef<Path>,
+        chunk_by: ChunkBy,
+        build_files: WebSummaryBuildFiles<'_>,
+    ) -> Result<(), Error> {
+        let dir = dir.as_ref();
+        let pretty = self.config.pretty_json;
+        let head = self.page_head();
+        let (json_data, template) = self.render()?;
+        let Value::Object(mut data) = serde_json::from_str(&json_data)? else {
+            bail!("The data of the summary is not an object");
+        };
+        let chunks = split_chunks(&mut data, &chunk_by)?;
+
+        std::fs::create_dir_all(dir)?;
+        for (key, file_name, chunk) in chunks {
+            let path = dir.join(file_name);
+            let writer = BufWriter::new(
+                File::create(&path).with_context(|| format!("Failed to create {path:?}"))?,
+            );
+            write_chunk_script(writer, &key, &chunk, pretty)?;
+        }
+
+        let json_data = if pretty {
+            serde_json::to_string_pretty(&data)?
+        } else {
+            serde_json::to_string(&data)?
+        };
+        let template = format!(
+            "{template}{}\n",
+            chunk_loader().template(Some(CHUNK_LOADER_KEY.into()))
+        );
+        let writer = BufWriter::new(File::create(dir.join(CHUNKED_HTML_FILE))?);
+        generate_html_summary_with_head(
+            &json_data,
+            template,
+            TemplateInfo::<String>::Default,
+            writer,
+            build_files,
+            &head,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{HeroMetric, Tabs};
+    use crate::scrape_json::{scrape_chunked_json_from_html, scrape_json_from_html};
+    use crate::test_utils::{test_build_files, ScratchDir};
+    use crate::WebSummary;
+
+    fn summary() -> WebSummary {
+        WebSummary::builder()
+            .nav("Space Ranger", "sample1", "Mouse brain")
+            .section_plain("cells", HeroMetric::new("Number of cells", "3,487"))
+            .section_plain(
+                "clustering",
+                Tabs::new()
+                    .tab("Graph", HeroMetric::new("Number of clusters", "12"))
+                    .tab("K-means", HeroMetric::new("K", "10")),
+            )
+            .section_plain(
+                "spatial",
+                HeroMetric::new("Fraction of spots under tissue", "92.1%"),
+            )
+            .build()
+    }
+
+    #[test]
+    fn test_two_chunks() {
+        let dir = ScratchDir::new("chunked_two_chunks");
+        summary()
+            .generate_html_chunked_with_build_files(
+                &dir,
+                ChunkBy::TopLevelKeys(vec!["clustering".into(), "spatial".into()]),
+                test_build_files(),
+            )
+            .unwrap();
+        assert!(dir.join("chunk_clustering.js").exists());
+        assert!(dir.join("chunk_spatial.js").exists());
+
+        let html_path = dir.join(CHUNKED_HTML_FILE);
+        let html = std::fs::read_to_string(&html_path).unwrap();
+        let main = scrape_json_from_html(html.as_bytes()).unwrap();
+        assert!(main.get("clustering").is_none());
+        assert!(main.get("spatial").is_none());
+        assert!(!html.contains("Number of clusters"));
+        assert_eq!(main["cells"]["metric"], "3,487");
+        assert_eq!(
+            main[CHUNK_MANIFEST_KEY],
+            serde_json::json!({
+                "clustering": "chunk_clustering.js",
+                "spatial": "chunk_spatial.js"
+            })
+        );
+        // The data keys of the chunked sections are unchanged
+        assert!(html.contains(r#"data-key="spatial" data-component="Metric""#));
+        assert!(html.contains(&format!(
+            r#"<div data-key="{CHUNK_LOADER_KEY}" data-component="JavaScript"></div>"#
+        )));
+
+        let merged = scrape_chunked_json_from_html(&html_path).unwrap();
+        assert_eq!(merged, serde_json::to_value(summary()).unwrap());
+    }
+
+    #[cfg(feature = "binary_resources")]
+    #[test]
+    fn test_chunks_with_binary_resources() {
+        let dir = ScratchDir::new("chunked_binary");
+        let mut resources = crate::SharedResources::new();
+        resources.insert_binary(vec![0, 1, 2, 255], "application/octet-stream");
+        summary()
+            .resources(resources)
+            .generate_html_chunked_with_build_files(
+                &dir,
+                ChunkBy::TopLevelKeys(vec!["clustering".into()]),
+                test_build_files(),
+            )
+            .unwrap();
+        // The binary resources stay in the html with the loader
+        let html_path = dir.join(CHUNKED_HTML_FILE);
+        let html = std::fs::read_to_string(&html_path).unwrap();
+        assert!(html.contains("createObjectURL"));
+        let merged = scrape_chunked_json_from_html(&html_path).unwrap();
+        let binary = crate::scrape_json::binary_resources(&merged);
+        assert_eq!(binary.len(), 1);
+        assert_eq!(binary[0].decode(&merged).unwrap(), [0, 1, 2, 255]);
+        assert!(merged.get("clustering").is_some());
+    }
+
+    #[test]
+    fn test_invalid_chunk_keys() {
+        let dir = ScratchDir::new("chunked_invalid");
+        let out = dir.join("out");
+        let generate = |keys: &[&str]| {
+            summary()
+                .generate_html_chunked_with_build_files(
+                    &out,
+                    ChunkBy::TopLevelKeys(keys.iter().map(|k| k.to_string()).collect()),
+                    test_build_files(),
+                )
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            generate(&["umap"]),
+            "The key umap to chunk is not in the content of the summary"
+        );
+        assert_eq!(
+            generate(&["alarms"]),
+            "alarms is not a part of the content and cannot be chunked"
+        );
+        assert!(!out.exists());
+    }
+
+    #[test]
+    fn test_chunk_file_name() {
+        assert_eq!(chunk_file_name("clustering"), "chunk_clustering.js");
+        assert_eq!(chunk_file_name("../tabs 1"), "chunk____tabs_1.js");
+    }
+
+    #[test]
+    fn test_chunk_file_name_collision() {
+        let dir = ScratchDir::new("chunked_collision");
+        let out = dir.join("out");
+        let err = WebSummary::builder()
+            .section_plain("a.b", HeroMetric::new("Number of cells", "3,487"))
+            .section_plain("a_b", HeroMetric::new("Number of reads", "1.2M"))
+            .build()
+            .generate_html_chunked_with_build_files(
+                &out,
+                ChunkBy::TopLevelKeys(vec!["a.b".into(), "a_b".into()]),
+                test_build_files(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The keys a.b and a_b would both be chunked into chunk_a_b.js"
+        );
+        assert!(!out.exists());
+    }
+
+    #[test]
+    fn test_chunk_script() {
+        let chunk = serde_json::json!({"name": "Graph", "values": [1, 2]});
+        for pretty in [false, true] {
+            let mut script = Vec::new();
+            write_chunk_script(&mut script, "clu\"stering", &chunk, pretty).unwrap();
+            let script = String::from_utf8(script).unwrap();
+            assert!(script.starts_with(r#"webSummaryChunkLoaded("clu\"stering","#));
+            assert_eq!(parse_chunk_script(&script).unwrap(), chunk);
+        }
+        assert!(parse_chunk_script(r#"{"name": "Graph"}"#).is_err());
+    }
+}
@@ -0,0 +1,241 @@
+//!
+//! Standard colors for plots, so that the summaries look consistent. The
+//! categorical palette is used for clusters, both in the plotly charts and
+//! in the HD clustering plots.
+//!
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::components::{CategoricalLegend, HdClusteringSingleClusterData, PlotlyChart};
+
+/// Categorical palette, in the order in which the colors are assigned
+pub const CATEGORICAL_PALETTE: [&str; 20] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#7f7f7f",
+    "#bcbd22", "#17becf", "#aec7e8", "#ffbb78", "#98df8a", "#ff9896", "#c5b0d5", "#c49c94",
+    "#f7b6d2", "#c7c7c7", "#dbdb8d", "#9edae5",
+];
+
+/// Font of the plots, both the plotly charts and the vega lite plots
+pub const FONT_FAMILY: &str = "Helvetica Neue, Helvetica, Arial, sans-serif";
+/// Color of the grid lines of the plots
+pub const GRID_COLOR: &str = "#e8e8e8";
+
+/// Color of the i-th category, cycling through the palette
+pub fn categorical_color(i: usize) -> &'static str {
+    CATEGORICAL_PALETTE[i % CATEGORICAL_PALETTE.len()]
+}
+
+/// Color of the cluster with the given 0-based index. Used for all the plots
+/// in which clusters are colored, so that a cluster has the same color
+/// everywhere.
+pub fn cluster_color(cluster_index: usize) -> &'static str {
+    categorical_color(cluster_index)
+}
+
+/// A plotly colorscale, i.e. a list of stops from 0 to 1 and their colors.
+/// Serialized as `[[0.0, "#..."], ...]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Colorscale(pub Vec<(f64, String)>);
+
+impl Colorscale {
+    /// Evenly spaced stops for the colors. No colors give an empty scale.
+    pub fn from_colors(colors: &[&str]) -> Self {
+        let last = colors.len().saturating_sub(1).max(1) as f64;
+        Colorscale(
+            colors
+                .iter()
+                .enumerate()
+                .map(|(i, color)| (i as f64 / last, color.to_string()))
+                .collect(),
+        )
+    }
+    /// Viridis, for values such as expression or UMI counts
+    pub fn sequential() -> Self {
+        Colorscale::from_colors(&[
+            "#440154", "#472d7b", "#3b528b", "#2c728e", "#21918c", "#28ae80", "#5ec962", "#addc30",
+            "#fde725",
+        ])
+    }
+    /// Blue to red through white, for values centered around zero such as log
+    /// fold changes
+    pub fn diverging() -> Self {
+        Colorscale::from_colors(&[
+            "#2166ac", "#67a9cf", "#d1e5f0", "#f7f7f7", "#fddbc7", "#ef8a62", "#b2182b",
+        ])
+    }
+    pub fn stops(&self) -> &[(f64, String)] {
+        &self.0
+    }
+    /// Value for the `colorscale` of a plotly trace or the layout
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
+
+/// Whether the trace sets its own marker or line color
+fn has_explicit_color(trace: &Value) -> bool {
+    ["marker", "line"]
+        .iter()
+        .any(|key| !trace[key]["color"].is_null())
+}
+
+impl PlotlyChart {
+    /// Color the traces which do not have an explicit marker or line color
+    /// using the categorical palette, based on the position of the trace
+    pub fn apply_categorical_colors(&mut self) {
+        for (i, trace) in self.data.iter_mut().enumerate() {
+            if has_explicit_color(trace) {
+                continue;
+            }
+            let Value::Object(map) = trace else {
+                continue;
+            };
+            let color = categorical_color(i);
+            let is_line = map
+                .get("mode")
+                .and_then(Value::as_str)
+                .is_some_and(|mode| mode.contains("lines"));
+            let keys: &[&str] = if is_line || map.contains_key("line") {
+                &["marker", "line"]
+            } else {
+                &["marker"]
+            };
+            for key in keys {
+                match map.get_mut(*key) {
+                    Some(Value::Object(style)) => {
+                        style.insert("color".into(), json!(color));
+                    }
+                    _ => {
+                        map.insert(key.to_string(), json!({ "color": color }));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl HdClusteringSingleClusterData {
+    /// Same as `new`, using the palette color of the cluster
+    pub fn with_cluster_index(
+        cluster_index: usize,
+        cluster_name: impl Into<String>,
+        spatial_plot: String,
+        umap_plot: String,
+    ) -> Self {
+        HdClusteringSingleClusterData::new(
+            cluster_name,
+            cluster_color(cluster_index),
+            spatial_plot,
+            umap_plot,
+        )
+    }
+}
+
+impl CategoricalLegend {
+    /// Legend of the labels colored with the categorical palette in order,
+    /// matching `apply_categorical_colors()` and `cluster_color()`
+    pub fn from_palette<S: ToString>(labels: impl IntoIterator<Item = S>) -> Self {
+        CategoricalLegend::new(
+            labels
+                .into_iter()
+                .enumerate()
+                .map(|(i, label)| (label.to_string(), categorical_color(i).to_string()))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette_cycling() {
+        assert_eq!(categorical_color(0), "#1f77b4");
+        assert_eq!(categorical_color(19), "#9edae5");
+        assert_eq!(categorical_color(20), categorical_color(0));
+        assert_eq!(categorical_color(43), categorical_color(3));
+        assert_eq!(
+            HdClusteringSingleClusterData::with_cluster_index(
+                21,
+                "22",
+                "data:image/png;base64,S22".into(),
+                "data:image/png;base64,UMAP".into()
+            )
+            .hex_color,
+            categorical_color(1)
+        );
+    }
+
+    #[test]
+    fn test_colorscale_from_colors() {
+        assert!(Colorscale::from_colors(&[]).stops().is_empty());
+        assert_eq!(
+            Colorscale::from_colors(&["#000000"]).stops(),
+            [(0.0, "#000000".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_colorscale_json() {
+        let scale = Colorscale::diverging();
+        assert_eq!(scale.stops().len(), 7);
+        assert_eq!(scale.stops()[0], (0.0, "#2166ac".to_string()));
+        assert_eq!(scale.stops()[3].0, 0.5);
+        assert_eq!(scale.stops()[6].0, 1.0);
+
+        let value = Colorscale::sequential().to_value();
+        let stops = value.as_array().unwrap();
+        assert_eq!(stops.len(), 9);
+        assert_eq!(stops[0], json!([0.0, "#440154"]));
+        assert_eq!(stops[8], json!([1.0, "#fde725"]));
+        assert_eq!(
+            serde_json::from_value::<Colorscale>(value).unwrap(),
+            Colorscale::sequential()
+        );
+    }
+
+    #[test]
+    fn test_apply_categorical_colors() {
+        let mut chart = PlotlyChart::with_layout_and_data(
+            json!({}),
+            vec![
+                json!({"type": "scatter", "mode": "markers", "x": [1], "y": [1]}),
+                json!({"type": "scatter", "marker": {"color": "black", "size": 3}}),
+                json!({"type": "scatter", "mode": "lines", "line": {"width": 2}}),
+                json!({"type": "bar", "marker": {"opacity": 0.5}}),
+                json!({"type": "scatter", "line": {"color": "red"}}),
+            ],
+        );
+        chart.apply_categorical_colors();
+        let data = &chart.data;
+        assert_eq!(data[0]["marker"], json!({"color": "#1f77b4"}));
+        assert!(data[0].get("line").is_none());
+        assert_eq!(data[1]["marker"], json!({"color": "black", "size": 3}));
+        assert_eq!(data[2]["line"], json!({"color": "#2ca02c", "width": 2}));
+        assert_eq!(data[2]["marker"]["color"], "#2ca02c");
+        assert_eq!(
+            data[3]["marker"],
+            json!({"color": "#d62728", "opacity": 0.5})
+        );
+        assert_eq!(
+            data[4],
+            json!({"type": "scatter", "line": {"color": "red"}})
+        );
+    }
+
+    #[test]
+    fn test_legend_from_palette() {
+        let labels: Vec<String> = (1..=22).map(|i| format!("Cluster {i}")).collect();
+        let legend = CategoricalLegend::from_palette(&labels);
+        assert_eq!(legend, CategoricalLegend::from_palette(&labels));
+        assert_eq!(legend.entries.len(), 22);
+        assert_eq!(legend.entries[0].label, "Cluster 1");
+        assert_eq!(legend.entries[0].color, "#1f77b4");
+        assert_eq!(legend.entries[3].color, cluster_color(3));
+        // The palette cycles
+        assert_eq!(legend.entries[21].color, legend.entries[1].color);
+    }
+}
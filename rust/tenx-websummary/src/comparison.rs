@@ -0,0 +1,202 @@
+//!
+//! Compare two runs of the same sample side by side in one page, instead of
+//! opening two summaries. The runs are the data of the summaries, e.g. from
+//! `scrape_json::scrape_json_from_html()`. Each aligned section is shown in
+//! a `TwoColumn` with the first run on the left, and the sections which are
+//! in only one of the runs are listed in a table.
+//!
+//! Only sections which are a single react component can be aligned, since
+//! the data does not record the template of a section. Shared resources of
+//! the runs are not carried over.
+//!
+
+use std::collections::BTreeSet;
+
+use anyhow::{bail, Context, Error};
+use serde_json::{Map, Value};
+
+use crate::builder::{WebSummary, WebSummaryBuilder};
+use crate::components::{CustomComponent, GenericTable, Title, TwoColumn, WithTitle, WsNavBar};
+use crate::RESERVED_KEYS;
+
+/// Key of the table listing the sections which are in only one of the runs
+pub const DIFFERENCES_KEY: &str = "differences";
+
+/// The content of the run, i.e. its data without the nav bar, the alerts etc.
+fn content(run: &Value, label: &str) -> Result<Map<String, Value>, Error> {
+    let Value::Object(data) = run else {
+        bail!("The data of {label} is not an object");
+    };
+    Ok(data
+        .iter()
+        .filter(|(key, _)| !RESERVED_KEYS.contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect())
+}
+
+fn nav_bar(run: &Value, label: &str) -> Result<WsNavBar, Error> {
+    serde_json::from_value(run.get("sample").cloned().unwrap_or_default())
+        .with_context(|| format!("The data of {label} does not have a nav bar"))
+}
+
+/// Page with the sections of `run_a` and `run_b` side by side. The sections
+/// are `(key, react component name)` pairs, e.g. `("cells", "Metric")` for a
+/// `HeroMetric`. The nav bar of the page is that of `run_a`, with the ids of
+/// both runs in the description.
+pub fn comparison_page(
+    run_a: &Value,
+    run_b: &Value,
+    sections: &[(&str, &'static str)],
+) -> Result<WebSummary, Error> {
+    let nav_a = nav_bar(run_a, "run A")?;
+    let nav_b = nav_bar(run_b, "run B")?;
+    let content_a = content(run_a, "run A")?;
+    let content_b = content(run_b, "run B")?;
+
+    let mut builder = WebSummaryBuilder::new().nav(
+        &nav_a.pipeline,
+        &nav_a.id,
+        &format!("{} (left) vs {} (right)", nav_a.id, nav_b.id),
+    );
+    for &(key, component) in sections {
+        if key == DIFFERENCES_KEY {
+            bail!("The section key {DIFFERENCES_KEY} is used for the table of differences");
+        }
+        match (content_a.get(key), content_b.get(key)) {
+            (Some(a), Some(b)) => {
+                builder = builder.section_plain(
+                    key,
+                    TwoColumn {
+                        left: CustomComponent::new(component, a.clone()),
+                        right: CustomComponent::new(component, b.clone()),
+                    },
+                );
+            }
+            (None, None) => bail!("The section {key} is in neither of the runs"),
+            // Listed in the differences below
+            _ => {}
+        }
+    }
+
+    let keys: BTreeSet<_> = content_a.keys().chain(content_b.keys()).collect();
+    let presence = |content: &Map<String, Value>, key: &str| {
+        if content.contains_key(key) {
+            "present".to_string()
+        } else {
+            "missing".to_string()
+        }
+    };
+    let differences: Vec<_> = keys
+        .into_iter()
+        .filter(|key| content_a.contains_key(*key) != content_b.contains_key(*key))
+        .map(|key| {
+            vec![
+                key.clone(),
+                presence(&content_a, key),
+                presence(&content_b, key),
+            ]
+        })
+        .collect();
+    if !differences.is_empty() {
+        builder = builder.section_plain(
+            DIFFERENCES_KEY,
+            WithTitle::new(
+                Title::new("Sections in only one run"),
+                GenericTable::from_rows(
+                    differences,
+                    Some(vec!["Section".into(), nav_a.id, nav_b.id]),
+                ),
+            ),
+        );
+    }
+    Ok(builder.build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{HeroMetric, TableMetric};
+    use crate::HtmlTemplate;
+
+    fn run(id: &str, cells: &str, extra: &str) -> Value {
+        let summary = WebSummaryBuilder::new()
+            .nav("Cell Ranger", id, "PBMC")
+            .section_plain("cells", HeroMetric::new("Number of cells", cells))
+            .section_plain(
+                "sequencing",
+                TableMetric {
+                    rows: vec![("Number of reads".into(), "1,000,000".into())],
+                    row_ids: Vec::new(),
+                },
+            )
+            .section_plain(extra, HeroMetric::new("Extra", "1"))
+            .build();
+        serde_json::to_value(summary).unwrap()
+    }
+
+    #[test]
+    fn test_comparison_page() {
+        let run_a = run("run1", "3,487", "umap");
+        let run_b = run("run2", "3,601", "saturation");
+        let page = comparison_page(
+            &run_a,
+            &run_b,
+            &[
+                ("cells", "Metric"),
+                ("sequencing", "TableMetric"),
+                ("umap", "Metric"),
+            ],
+        )
+        .unwrap();
+
+        let template = page.template(None);
+        for key in [
+            "cells.left",
+            "cells.right",
+            "sequencing.left",
+            "sequencing.right",
+        ] {
+            assert!(template.contains(&format!(r#"data-key="{key}""#)), "{key}");
+        }
+        assert!(template.contains(r#"data-key="sequencing.right" data-component="TableMetric""#));
+        assert!(!template.contains(r#"data-key="umap"#));
+
+        let data = serde_json::to_value(&page).unwrap();
+        assert_eq!(data["cells"]["left"], run_a["cells"]);
+        assert_eq!(data["cells"]["right"]["metric"], "3,601");
+        assert_eq!(data["sample"]["description"], "run1 (left) vs run2 (right)");
+        assert_eq!(
+            data[DIFFERENCES_KEY]["inner"]["header"],
+            serde_json::json!(["Section", "run1", "run2"])
+        );
+        assert_eq!(
+            data[DIFFERENCES_KEY]["inner"]["rows"],
+            serde_json::json!([
+                ["saturation", "missing", "present"],
+                ["umap", "present", "missing"]
+            ])
+        );
+    }
+
+    #[test]
+    fn test_comparison_page_errors() {
+        let run_a = run("run1", "3,487", "umap");
+        let err = |sections: &[(&str, &'static str)]| {
+            comparison_page(&run_a, &run_a, sections)
+                .err()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            err(&[("clustering", "Plot")]),
+            "The section clustering is in neither of the runs"
+        );
+        assert_eq!(
+            err(&[(DIFFERENCES_KEY, "Table")]),
+            "The section key differences is used for the table of differences"
+        );
+        // Identical runs do not have a table of differences
+        let page = comparison_page(&run_a, &run_a, &[("cells", "Metric")]).unwrap();
+        assert!(serde_json::to_value(&page).unwrap()[DIFFERENCES_KEY].is_null());
+    }
+}
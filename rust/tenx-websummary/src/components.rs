@@ -18,17 +18,54 @@
 //! | BlendedImage | ImageRegistViewer.js |
 //! | VegaLitePlot | VegaLitePlot.js |
 //! | Tooltip | ReactTooltip.js |
+//! | DataHeading | Heading.js |
+//! | QcBanner | QcBanner.js |
+//! | EmptyState | EmptyState.js |
+//! | Sparkline | Sparkline.js |
+//! | FractionBar | FractionBar.js |
+//! | GroupedHeaderTable | GroupedHeaderTable.js |
+//! | CategoricalLegend | CategoricalLegend.js |
+//! | ColumnarTable | ColumnarTable.js |
+//! | GroupedTableMetric | GroupedTableMetric.js |
+//! | AlertGroup | AlertGroup.js |
+//! | Footer | Footer.js |
 //!
 
-use std::{collections::HashMap, fmt::Display, marker::PhantomData};
-
-use anyhow::Error;
+#[cfg(feature = "image_base64_encode")]
+use std::path::PathBuf;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    fmt::Write as _,
+    marker::PhantomData,
+    sync::Arc,
+};
+
+#[cfg(feature = "image_base64_encode")]
+use anyhow::Context;
+use anyhow::{bail, Error};
 use itertools::Itertools;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::{react_component, AddToSharedResource, HtmlTemplate, SharedResources};
+use crate::data_uri::{check_if_strict, validate_data_uri, DataUriError};
+#[cfg(feature = "form")]
+use crate::form::{
+    InputElement, InputFeedback, MultiSelect, RepeatedGroupInput, SingleSelect, SpreadsheetInput,
+    TextArea,
+};
+#[cfg(feature = "image_base64_encode")]
+use crate::image_base64_encode::Base64ImageEncoder;
+use crate::metrics_mapping::MetricFormat;
+use crate::number_format::NumberLocale;
+use crate::sanitize::{sanitize_numbers_at, NanPolicy};
+#[cfg(feature = "time")]
+use crate::timestamp::Timestamp;
+use crate::{
+    react_component, AddToSharedResource, AlertLevel, Alerts, HtmlTemplate, SharedResources,
+};
 
 impl HtmlTemplate for String {
     fn template(&self, _data_key: Option<String>) -> String {
@@ -36,7 +73,7 @@ impl HtmlTemplate for String {
     }
 }
 
-fn join_data_key(data_key: &Option<String>, key: &str) -> String {
+pub(crate) fn join_data_key(data_key: &Option<String>, key: &str) -> String {
     match data_key {
         Some(prefix) => format!("{prefix}.{key}"),
         None => key.into(),
@@ -56,6 +93,24 @@ pub enum Threshold {
     Error,
 }
 
+impl Threshold {
+    fn rank(&self) -> u8 {
+        match self {
+            Threshold::Pass => 0,
+            Threshold::Warn => 1,
+            Threshold::Error => 2,
+        }
+    }
+    /// The more severe of the two
+    fn worst(self, other: Threshold) -> Threshold {
+        if other.rank() > self.rank() {
+            other
+        } else {
+            self
+        }
+    }
+}
+
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
 /// This struct determines the navigation bar and header in the web summary.
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -66,6 +121,43 @@ pub struct WsNavBar {
     pub id: String,
     /// Page title is {id} - {description}
     pub description: String,
+    /// Product name treatment next to the 10x logo, see `with_product()`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    product: Option<ProductVariant>,
+}
+
+impl WsNavBar {
+    pub fn new(pipeline: impl ToString, id: impl ToString, description: impl ToString) -> Self {
+        WsNavBar {
+            pipeline: pipeline.to_string(),
+            id: id.to_string(),
+            description: description.to_string(),
+            product: None,
+        }
+    }
+    /// Show the product name treatment next to the 10x logo
+    pub fn with_product(mut self, product: ProductVariant) -> Self {
+        self.product = Some(product);
+        self
+    }
+    pub fn product(&self) -> Option<ProductVariant> {
+        self.product
+    }
+}
+
+/// The product shown next to the 10x logo in the nav bar
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ProductVariant {
+    #[serde(rename = "cellranger")]
+    CellRanger,
+    #[serde(rename = "cellranger-arc")]
+    CellRangerArc,
+    #[serde(rename = "cellranger-atac")]
+    CellRangerAtac,
+    #[serde(rename = "spaceranger")]
+    SpaceRanger,
+    #[serde(rename = "xeniumranger")]
+    XeniumRanger,
 }
 
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
@@ -79,6 +171,13 @@ pub struct HeroMetric {
     pub metric: String,
     /// Optionally control the display color
     pub threshold: Option<Threshold>,
+    /// Optional comparison against a baseline, e.g. the previous run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comparison: Option<MetricComparison>,
+    /// Stable machine readable id of the metric, e.g. `valid_barcodes_frac`,
+    /// which does not change with the wording of the name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
 }
 
 impl HeroMetric {
@@ -87,6 +186,8 @@ impl HeroMetric {
             name: name.to_string(),
             metric: value.to_string(),
             threshold: None,
+            comparison: None,
+            id: None,
         }
     }
     pub fn with_threshold<N: Display, V: Display>(name: N, value: V, threshold: Threshold) -> Self {
@@ -94,6 +195,153 @@ impl HeroMetric {
             name: name.to_string(),
             metric: value.to_string(),
             threshold: Some(threshold),
+            comparison: None,
+            id: None,
+        }
+    }
+    /// The metric from its raw value, formatted with the separators of the
+    /// locale, e.g. `3.487` for a `MetricFormat::Count` in
+    /// `NumberLocale::EuComma`
+    pub fn from_value(
+        name: impl Display,
+        value: f64,
+        format: MetricFormat,
+        locale: NumberLocale,
+    ) -> Self {
+        HeroMetric::new(name, format.format_with_locale(value, locale))
+    }
+    pub fn id(mut self, id: impl ToString) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+    /// Compare the current value of the metric against a baseline, such as
+    /// the value from the previous run. The delta is the change relative to
+    /// the baseline, e.g. `+3.2%`. If no threshold is set, the metric is
+    /// colored as a pass when it improved and as a warning when it got worse.
+    ///
+    /// A zero or non-finite baseline (or current value) leaves the metric
+    /// without a comparison.
+    pub fn with_comparison(
+        self,
+        current: f64,
+        baseline: f64,
+        format: MetricFormat,
+        higher_is_better: bool,
+    ) -> Self {
+        self.with_comparison_in_locale(
+            current,
+            baseline,
+            format,
+            higher_is_better,
+            NumberLocale::EnUs,
+        )
+    }
+    /// Same as `with_comparison()`, with the baseline and the delta
+    /// formatted with the separators of the locale
+    pub fn with_comparison_in_locale(
+        mut self,
+        current: f64,
+        baseline: f64,
+        format: MetricFormat,
+        higher_is_better: bool,
+        locale: NumberLocale,
+    ) -> Self {
+        self.comparison =
+            MetricComparison::new_in_locale(current, baseline, format, higher_is_better, locale);
+        if self.threshold.is_none() {
+            self.threshold = self
+                .comparison
+                .as_ref()
+                .and_then(|comparison| comparison.direction.threshold());
+        }
+        self
+    }
+}
+
+/// Comparison of a `HeroMetric` against a baseline
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MetricComparison {
+    /// String formatted value of the baseline
+    pub baseline: String,
+    /// String formatted change relative to the baseline, e.g. `+3.2%`
+    pub delta: String,
+    pub direction: DeltaDirection,
+}
+
+impl MetricComparison {
+    /// `None` if the relative change is undefined, i.e. for a zero or
+    /// non-finite baseline or a non-finite current value
+    pub fn new(
+        current: f64,
+        baseline: f64,
+        format: MetricFormat,
+        higher_is_better: bool,
+    ) -> Option<Self> {
+        Self::new_in_locale(
+            current,
+            baseline,
+            format,
+            higher_is_better,
+            NumberLocale::EnUs,
+        )
+    }
+
+    /// Same as `new()`, with the baseline and the delta formatted with the
+    /// separators of the locale
+    pub fn new_in_locale(
+        current: f64,
+        baseline: f64,
+        format: MetricFormat,
+        higher_is_better: bool,
+        locale: NumberLocale,
+    ) -> Option<Self> {
+        let change = (current - baseline) / baseline.abs();
+        if !change.is_finite() {
+            return None;
+        }
+        let delta = MetricFormat::Percent.format_with_locale(change, locale);
+        let zero = MetricFormat::Percent.format_with_locale(0.0, locale);
+        // Changes which round to zero are shown as unchanged
+        let (delta, direction) = if delta.trim_start_matches('-') == zero {
+            (zero, DeltaDirection::Unchanged)
+        } else if (change > 0.0) == higher_is_better {
+            (Self::signed(delta, change), DeltaDirection::Better)
+        } else {
+            (Self::signed(delta, change), DeltaDirection::Worse)
+        };
+        Some(MetricComparison {
+            baseline: format.format_with_locale(baseline, locale),
+            delta,
+            direction,
+        })
+    }
+
+    fn signed(delta: String, change: f64) -> String {
+        if change > 0.0 {
+            format!("+{delta}")
+        } else {
+            delta
+        }
+    }
+}
+
+/// Whether the metric improved compared to the baseline, which determines
+/// the color of the delta
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DeltaDirection {
+    Better,
+    Worse,
+    Unchanged,
+}
+
+impl DeltaDirection {
+    /// The color of the metric for this direction
+    pub fn threshold(self) -> Option<Threshold> {
+        match self {
+            DeltaDirection::Better => Some(Threshold::Pass),
+            DeltaDirection::Worse => Some(Threshold::Warn),
+            DeltaDirection::Unchanged => None,
         }
     }
 }
@@ -102,9 +350,54 @@ impl HeroMetric {
 /// Usually used to attach heading to a card with a help snippet
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct TitleWithHelp {
+    /// Rendered as raw html, which existing summaries rely on for links and
+    /// lists. Prefer `bullets` and `link` for those, which hold plain text.
     #[serde(rename = "helpText")]
     pub help: String,
     pub title: String,
+    /// Bullet points shown below the help text. Plain text, which is escaped
+    /// when rendered.
+    #[serde(
+        rename = "helpBullets",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub bullets: Option<Vec<String>>,
+    /// A "learn more" link shown at the end of the help
+    #[serde(rename = "helpLink", default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<HelpLink>,
+}
+
+impl TitleWithHelp {
+    pub fn new(title: impl ToString, help: impl ToString) -> Self {
+        TitleWithHelp {
+            help: help.to_string(),
+            title: title.to_string(),
+            bullets: None,
+            link: None,
+        }
+    }
+    /// Bullet points of plain text
+    pub fn bullets(mut self, bullets: impl IntoIterator<Item = impl ToString>) -> Self {
+        self.bullets = Some(bullets.into_iter().map(|b| b.to_string()).collect());
+        self
+    }
+    /// A link with plain text, e.g. to a page on the support site
+    pub fn link(mut self, text: impl ToString, url: impl ToString) -> Self {
+        self.link = Some(HelpLink {
+            text: text.to_string(),
+            url: url.to_string(),
+        });
+        self
+    }
+}
+
+/// A link in the help of a `TitleWithHelp`. The text and url are plain text,
+/// which is escaped when rendered.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HelpLink {
+    pub text: String,
+    pub url: String,
 }
 
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
@@ -146,26 +439,287 @@ impl From<Vec<String>> for TableRow {
 }
 
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+/// Content of a single cell in a table with rich rows
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RichCell {
+    Text(String),
+    /// Raw html rendered as is within the cell
+    Html(String),
+    Link {
+        text: String,
+        href: String,
+    },
+    Sparkline(Sparkline),
+    FractionBar(FractionBar),
+}
+
+impl RichCell {
+    /// Plain text shown by renderers which do not support rich cells
+    pub fn plain_text(&self) -> String {
+        match self {
+            RichCell::Text(text) => text.clone(),
+            RichCell::Html(html) => strip_html_tags(html),
+            RichCell::Link { text, .. } => text.clone(),
+            RichCell::Sparkline(sparkline) => sparkline.values().iter().join(" "),
+            RichCell::FractionBar(bar) => bar.text(),
+        }
+    }
+}
+
+/// Escape the text so that it can be shown in an `HtmlFragment`
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Unescape the common entities, the reverse of `escape_html()`
+fn unescape_html(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+/// Remove the tags from the html and unescape the common entities
+fn strip_html_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    unescape_html(&text)
+}
+
+impl From<String> for RichCell {
+    fn from(text: String) -> Self {
+        RichCell::Text(text)
+    }
+}
+
+impl From<&str> for RichCell {
+    fn from(text: &str) -> Self {
+        RichCell::Text(text.to_string())
+    }
+}
+
+impl From<LinkedText> for RichCell {
+    fn from(linked: LinkedText) -> Self {
+        RichCell::Link {
+            text: linked.text,
+            href: linked.link,
+        }
+    }
+}
+
+impl From<Sparkline> for RichCell {
+    fn from(sparkline: Sparkline) -> Self {
+        RichCell::Sparkline(sparkline)
+    }
+}
+
+impl From<FractionBar> for RichCell {
+    fn from(bar: FractionBar) -> Self {
+        RichCell::FractionBar(bar)
+    }
+}
+
+/// A single row in a table where each cell could be a text, html, a link or
+/// a small inline visualization
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RichTableRow(pub Vec<RichCell>);
+
+impl RichTableRow {
+    pub fn plain(&self) -> TableRow {
+        TableRow(self.0.iter().map(RichCell::plain_text).collect())
+    }
+}
+
+impl From<TableRow> for RichTableRow {
+    fn from(row: TableRow) -> Self {
+        RichTableRow(row.0.into_iter().map(RichCell::Text).collect())
+    }
+}
+
+impl From<Vec<RichCell>> for RichTableRow {
+    fn from(cells: Vec<RichCell>) -> Self {
+        RichTableRow(cells)
+    }
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+/// The page size of a table, which the frontend cannot paginate with if it is
+/// zero. The field is public, so it is checked here as well as in `paginate()`.
+fn serialize_page_size<S: serde::Serializer>(
+    page_size: &Option<usize>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    if *page_size == Some(0) {
+        return Err(serde::ser::Error::custom(
+            "Page size of a table must be greater than zero",
+        ));
+    }
+    page_size.serialize(serializer)
+}
+
 /// Table with optional headers
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct GenericTable {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub header: Option<Vec<String>>,
     pub rows: Vec<TableRow>,
+    /// Number of rows shown per page. All rows are shown if this is None.
+    /// A page size of zero fails to serialize.
+    #[serde(
+        default,
+        rename = "pageSize",
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_page_size"
+    )]
+    pub page_size: Option<usize>,
+    /// Keep the header visible while scrolling through the rows
+    #[serde(
+        default,
+        rename = "stickyHeader",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sticky_header: Option<bool>,
+    /// Rows with cells which are not plain text. When present, `rows` holds
+    /// the plain text fallback of these rows
+    #[serde(default, rename = "richRows", skip_serializing_if = "Option::is_none")]
+    pub rich_rows: Option<Vec<RichTableRow>>,
+}
+
+/// Check that every row has as many columns as the header, or as the first
+/// row if there is no header
+fn check_num_cols(rows: &[Vec<String>], header: Option<&Vec<String>>) -> Result<(), Error> {
+    let (expected, source) = match (header, rows.first()) {
+        (Some(header), _) => (header.len(), "the header"),
+        (None, Some(first)) => (first.len(), "row 0"),
+        (None, None) => return Ok(()),
+    };
+    if let Some((i, row)) = rows
+        .iter()
+        .enumerate()
+        .find(|(_, row)| row.len() != expected)
+    {
+        bail!(
+            "Row {i} of the table has {} columns, expected {expected} columns as in {source}",
+            row.len()
+        );
+    }
+    Ok(())
 }
 
 impl GenericTable {
-    /// Generate a generic table from rows and header
+    /// Generate a generic table from rows and header. The number of columns
+    /// is not checked, see `try_from_rows()` and `from_rows_padded()`.
     pub fn from_rows(rows_vec: Vec<Vec<String>>, header: Option<Vec<String>>) -> Self {
         let rows = rows_vec.into_iter().map(TableRow::from).collect();
-        GenericTable { header, rows }
+        GenericTable {
+            header,
+            rows,
+            page_size: None,
+            sticky_header: None,
+            rich_rows: None,
+        }
+    }
+
+    /// Same as `from_rows`, but every row must have as many columns as the
+    /// header, or as the first row if there is no header. The error names the
+    /// first row which does not.
+    pub fn try_from_rows(
+        rows_vec: Vec<Vec<String>>,
+        header: Option<Vec<String>>,
+    ) -> Result<Self, Error> {
+        check_num_cols(&rows_vec, header.as_ref())?;
+        Ok(GenericTable::from_rows(rows_vec, header))
+    }
+
+    /// Same as `from_rows`, but the rows are padded on the right with empty
+    /// strings up to the number of columns of the widest row or the header.
+    /// The header is padded too if a row is wider than it.
+    pub fn from_rows_padded(
+        mut rows_vec: Vec<Vec<String>>,
+        mut header: Option<Vec<String>>,
+    ) -> Self {
+        let num_cols = rows_vec
+            .iter()
+            .chain(&header)
+            .map(Vec::len)
+            .max()
+            .unwrap_or(0);
+        for row in rows_vec.iter_mut().chain(&mut header) {
+            row.resize(num_cols, String::new());
+        }
+        GenericTable::from_rows(rows_vec, header)
+    }
+
+    /// Generate a generic table from rich rows and header. The plain text
+    /// `rows` are derived from the rich rows.
+    pub fn from_rich_rows(
+        rich_rows: Vec<impl Into<RichTableRow>>,
+        header: Option<Vec<String>>,
+    ) -> Self {
+        let rich_rows: Vec<RichTableRow> = rich_rows.into_iter().map(Into::into).collect();
+        GenericTable {
+            header,
+            rows: rich_rows.iter().map(RichTableRow::plain).collect(),
+            page_size: None,
+            sticky_header: None,
+            rich_rows: Some(rich_rows),
+        }
+    }
+
+    /// Convert the plain text rows into rich rows so that individual cells
+    /// can be replaced with links or html
+    pub fn into_rich(mut self) -> Self {
+        if self.rich_rows.is_none() {
+            self.rich_rows = Some(self.rows.iter().cloned().map(RichTableRow::from).collect());
+        }
+        self
+    }
+
+    /// Drop the rich rows, keeping only their plain text fallback
+    pub fn into_plain(mut self) -> Self {
+        if let Some(rich_rows) = self.rich_rows.take() {
+            self.rows = rich_rows.iter().map(RichTableRow::plain).collect();
+        }
+        self
+    }
+
+    /// Show `page_size` rows per page. A page size of zero is an error.
+    pub fn paginate(mut self, page_size: usize) -> Result<Self, Error> {
+        if page_size == 0 {
+            bail!("Page size of a table must be greater than zero");
+        }
+        self.page_size = Some(page_size);
+        Ok(self)
+    }
+
+    /// Keep the header visible while scrolling through the rows
+    pub fn sticky_header(mut self) -> Self {
+        self.sticky_header = Some(true);
+        self
     }
 
     /// Generate a generic table from columns
     /// Uses the headers in creating the GenericTable if provided
+    /// Columns shorter than the longest column are padded at the bottom with
+    /// empty strings, see `try_from_columns()` for the checked version.
     pub fn from_columns(columns: Vec<Vec<String>>, header: Option<Vec<String>>) -> Self {
         let num_cols = columns.len();
-        let num_rows = columns.iter().map(std::vec::Vec::len).max().unwrap();
+        let num_rows = columns.iter().map(std::vec::Vec::len).max().unwrap_or(0);
 
         let mut rows = vec![vec![String::new(); num_cols]; num_rows];
         for (col_num, column) in columns.into_iter().enumerate() {
@@ -176,6 +730,37 @@ impl GenericTable {
 
         GenericTable::from_rows(rows, header)
     }
+
+    /// Same as `from_columns`, but every column must have as many rows as the
+    /// first column, and as many columns as the header if there is one.
+    pub fn try_from_columns(
+        columns: Vec<Vec<String>>,
+        header: Option<Vec<String>>,
+    ) -> Result<Self, Error> {
+        if let Some(ref header) = header {
+            if header.len() != columns.len() {
+                bail!(
+                    "The table has {} columns, expected {} columns as in the header",
+                    columns.len(),
+                    header.len()
+                );
+            }
+        }
+        if let Some(first) = columns.first() {
+            if let Some((i, column)) = columns
+                .iter()
+                .enumerate()
+                .find(|(_, column)| column.len() != first.len())
+            {
+                bail!(
+                    "Column {i} of the table has {} rows, expected {} rows as in column 0",
+                    column.len(),
+                    first.len()
+                );
+            }
+        }
+        Ok(GenericTable::from_columns(columns, header))
+    }
 }
 
 fn deserialize_tuple_list_as_string<'de, D>(
@@ -199,6 +784,134 @@ pub struct TableMetric {
     /// Vector of (metric name, metric value)
     #[serde(deserialize_with = "deserialize_tuple_list_as_string")]
     pub rows: Vec<(String, String)>,
+    /// Stable machine readable ids of the rows, parallel to `rows`. Missing
+    /// trailing entries are rows without an id.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub row_ids: Vec<Option<String>>,
+}
+
+impl TableMetric {
+    /// Set the id of the row at `index`
+    pub fn row_id(mut self, index: usize, id: impl ToString) -> Self {
+        if self.row_ids.len() <= index {
+            self.row_ids.resize(index + 1, None);
+        }
+        self.row_ids[index] = Some(id.to_string());
+        self
+    }
+
+    /// The id of the row at `index`, if any
+    pub fn get_row_id(&self, index: usize) -> Option<&str> {
+        self.row_ids.get(index)?.as_deref()
+    }
+
+    /// Append a row with the raw value formatted with the separators of the
+    /// locale
+    pub fn value_row(
+        mut self,
+        name: impl ToString,
+        value: f64,
+        format: MetricFormat,
+        locale: NumberLocale,
+    ) -> Self {
+        self.rows
+            .push((name.to_string(), format.format_with_locale(value, locale)));
+        self
+    }
+}
+
+/// Rows of a `GroupedTableMetric` shown under a subheader
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MetricGroup {
+    /// Title of the subheader. Rows of a group without a title are shown
+    /// without a subheader.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Vector of (metric name, metric value)
+    #[serde(deserialize_with = "deserialize_tuple_list_as_string")]
+    pub rows: Vec<(String, String)>,
+    /// Stable machine readable ids of the rows, as in `TableMetric`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub row_ids: Vec<Option<String>>,
+}
+
+/// A `TableMetric` whose rows are grouped under subheaders, e.g.
+/// "Sequencing", "Mapping" and "Cells" in a single card
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct GroupedTableMetric {
+    pub groups: Vec<MetricGroup>,
+}
+
+impl GroupedTableMetric {
+    /// Table from `(group, metric name, metric value)` triples. The groups
+    /// are in the order in which they first appear, and so are the rows
+    /// within each group.
+    pub fn from_rows<G, N, V>(rows: impl IntoIterator<Item = (G, N, V)>) -> Self
+    where
+        G: ToString,
+        N: ToString,
+        V: ToString,
+    {
+        let mut groups: Vec<MetricGroup> = Vec::new();
+        for (group, name, value) in rows {
+            let title = group.to_string();
+            let row = (name.to_string(), value.to_string());
+            match groups
+                .iter_mut()
+                .find(|g| g.title.as_deref() == Some(title.as_str()))
+            {
+                Some(g) => g.rows.push(row),
+                None => groups.push(MetricGroup {
+                    title: Some(title),
+                    rows: vec![row],
+                    row_ids: Vec::new(),
+                }),
+            }
+        }
+        GroupedTableMetric { groups }
+    }
+
+    /// A plain table, where the name of each metric is prefixed with the
+    /// title of its group, e.g. `Sequencing: Number of reads`
+    pub fn to_table_metric(&self) -> TableMetric {
+        let mut table = TableMetric {
+            rows: Vec::new(),
+            row_ids: Vec::new(),
+        };
+        for group in &self.groups {
+            for (i, (name, value)) in group.rows.iter().enumerate() {
+                let name = match &group.title {
+                    Some(title) => format!("{title}: {name}"),
+                    None => name.clone(),
+                };
+                table.rows.push((name, value.clone()));
+                table.row_ids.push(group.row_ids.get(i).cloned().flatten());
+            }
+        }
+        if table.row_ids.iter().all(Option::is_none) {
+            table.row_ids.clear();
+        }
+        table
+    }
+}
+
+/// A single group without a title
+impl From<TableMetric> for GroupedTableMetric {
+    fn from(table: TableMetric) -> Self {
+        GroupedTableMetric {
+            groups: vec![MetricGroup {
+                title: None,
+                rows: table.rows,
+                row_ids: table.row_ids,
+            }],
+        }
+    }
+}
+
+impl From<GroupedTableMetric> for TableMetric {
+    fn from(table: GroupedTableMetric) -> Self {
+        table.to_table_metric()
+    }
 }
 
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
@@ -213,6 +926,30 @@ pub struct PlotlyChart {
     pub layout: Option<Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub style: Option<Style>,
+    /// What is moved into the shared resources by `add_to_shared_resource()`,
+    /// see `share_large_arrays()`. Nothing is shared by default.
+    #[serde(skip)]
+    shared_arrays: Option<SharePlotlyArrays>,
+}
+
+/// Fields of a trace which are shared by `PlotlyChart::share_large_arrays()`
+pub const DEFAULT_SHARED_PLOTLY_FIELDS: [&str; 3] = ["x", "y", "customdata"];
+
+/// Which parts of the traces of a `PlotlyChart` are moved into the shared
+/// resources. Only the values whose serialized size is larger than
+/// `threshold_bytes` are moved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharePlotlyArrays {
+    pub threshold_bytes: usize,
+    pub target: SharedPlotlyTarget,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SharedPlotlyTarget {
+    /// Whole traces
+    Traces,
+    /// The array fields with these names within each trace
+    Fields(Vec<String>),
 }
 
 impl PlotlyChart {
@@ -225,6 +962,7 @@ impl PlotlyChart {
                 .collect(),
             layout: Some(serde_json::to_value(&layout).unwrap()),
             style: None,
+            shared_arrays: None,
         }
     }
 
@@ -233,10 +971,54 @@ impl PlotlyChart {
         self
     }
 
+    /// Move the `x`, `y` and `customdata` arrays of the traces which are
+    /// larger than `threshold_bytes` when serialized into the shared
+    /// resources, when `add_to_shared_resource()` is called. Charts which
+    /// plot the same coordinates then store them only once in the page.
+    pub fn share_large_arrays(self, threshold_bytes: usize) -> Self {
+        self.share_large_arrays_in(threshold_bytes, &DEFAULT_SHARED_PLOTLY_FIELDS)
+    }
+
+    /// Same as `share_large_arrays()` for the given fields of the traces
+    pub fn share_large_arrays_in(mut self, threshold_bytes: usize, fields: &[&str]) -> Self {
+        self.shared_arrays = Some(SharePlotlyArrays {
+            threshold_bytes,
+            target: SharedPlotlyTarget::Fields(fields.iter().map(ToString::to_string).collect()),
+        });
+        self
+    }
+
+    /// Same as `share_large_arrays()`, moving whole traces instead of the
+    /// arrays within them
+    pub fn share_large_traces(mut self, threshold_bytes: usize) -> Self {
+        self.shared_arrays = Some(SharePlotlyArrays {
+            threshold_bytes,
+            target: SharedPlotlyTarget::Traces,
+        });
+        self
+    }
+
     pub fn from_json_str(json_str: &str) -> Result<Self, Error> {
         Ok(serde_json::from_str(json_str)?)
     }
 
+    /// Apply the policy to the non-finite numbers in the numeric arrays of the
+    /// config, the traces and the layout, which can only hold them as strings.
+    /// The path in the error starts at the chart, e.g. `data[0].y[3]`. See
+    /// `sanitize`.
+    pub fn sanitize(mut self, policy: NanPolicy) -> Result<Self, Error> {
+        if let Some(config) = &mut self.config {
+            sanitize_numbers_at(config, policy, "config")?;
+        }
+        for (i, trace) in self.data.iter_mut().enumerate() {
+            sanitize_numbers_at(trace, policy, &format!("data[{i}]"))?;
+        }
+        if let Some(layout) = &mut self.layout {
+            sanitize_numbers_at(layout, policy, "layout")?;
+        }
+        Ok(self)
+    }
+
     pub fn default_config() -> Value {
         const DEFAULT_PLOTLY_CONFIG: &str = r#"{
             "displayModeBar": true,
@@ -252,6 +1034,41 @@ impl PlotlyChart {
     }
 }
 
+/// Replace the value with a reference to it in the shared resources if its
+/// serialized size is larger than the threshold
+fn share_if_larger(value: &mut Value, threshold_bytes: usize, resources: &mut SharedResources) {
+    if value.is_string() || value.to_string().len() <= threshold_bytes {
+        return;
+    }
+    *value = Value::String(resources.insert(value.take()));
+}
+
+impl AddToSharedResource for PlotlyChart {
+    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
+        let Some(SharePlotlyArrays {
+            threshold_bytes,
+            ref target,
+        }) = self.shared_arrays
+        else {
+            return;
+        };
+        for trace in &mut self.data {
+            match target {
+                SharedPlotlyTarget::Traces => {
+                    share_if_larger(trace, threshold_bytes, shared_resource);
+                }
+                SharedPlotlyTarget::Fields(fields) => {
+                    for field in fields {
+                        if let Some(value) = trace.get_mut(field).filter(|v| v.is_array()) {
+                            share_if_larger(value, threshold_bytes, shared_resource);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
 /// A tooltip that appears on hover of the underlying `content`
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
@@ -340,16 +1157,98 @@ pub struct VegaLitePlot {
     pub actions: Option<Value>,
     #[serde(default)]
     pub renderer: Option<VegaLiteRenderer>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<Style>,
+    /// Config passed to vega-embed along with the spec
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<Value>,
 }
 
 impl VegaLitePlot {
     pub fn from_json_str(json_str: &str) -> Result<Self, Error> {
         Ok(VegaLitePlot {
             spec: serde_json::from_str(json_str)?,
-            actions: None,
-            renderer: None,
+            ..Default::default()
+        })
+    }
+
+    /// Plot of the spec with the 10x theme merged into its `config`. The
+    /// values in the spec take precedence over the theme.
+    pub fn themed(mut spec: Value) -> Self {
+        if let Value::Object(map) = &mut spec {
+            let mut config = Self::theme();
+            if let Some(spec_config) = map.remove("config") {
+                merge_json(&mut config, spec_config);
+            }
+            map.insert("config".into(), config);
+        }
+        VegaLitePlot {
+            spec,
+            ..Default::default()
+        }
+    }
+
+    /// The 10x theme, as a vega lite config
+    pub fn theme() -> Value {
+        serde_json::json!({
+            "font": crate::colors::FONT_FAMILY,
+            "title": {"fontSize": 15, "fontWeight": "normal"},
+            "axis": {
+                "labelFontSize": 12,
+                "titleFontSize": 13,
+                "titleFontWeight": "normal",
+                "gridColor": crate::colors::GRID_COLOR
+            },
+            "legend": {"labelFontSize": 12, "titleFontSize": 13},
+            "view": {"stroke": null},
+            "range": {"category": crate::colors::CATEGORICAL_PALETTE}
         })
     }
+
+    /// Set the width in the spec, unless the spec already has one
+    pub fn width(self, width: u32) -> Self {
+        self.spec_default("width", width)
+    }
+
+    /// Set the height in the spec, unless the spec already has one
+    pub fn height(self, height: u32) -> Self {
+        self.spec_default("height", height)
+    }
+
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = Some(style);
+        self
+    }
+
+    pub fn config(mut self, config: Value) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    fn spec_default(mut self, key: &str, value: u32) -> Self {
+        if let Value::Object(map) = &mut self.spec {
+            map.entry(key).or_insert(value.into());
+        }
+        self
+    }
+}
+
+/// Merge the overlay into the base. Objects are merged recursively, and
+/// everything else, including arrays, is replaced by the overlay.
+pub(crate) fn merge_json(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
 }
 
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
@@ -393,6 +1292,26 @@ impl Style {
         self.push("image-rendering", "pixelated");
         self
     }
+    /// Keep the element on a single page when printing
+    pub fn break_inside_avoid(mut self) -> Self {
+        self.push("break-inside", "avoid");
+        self.push("page-break-inside", "avoid");
+        self
+    }
+    /// Start the element on a new page when printing
+    pub fn page_break_before(mut self) -> Self {
+        self.push("break-before", "page");
+        self.push("page-break-before", "always");
+        self
+    }
+    /// Inline css for a `style` attribute, with the properties sorted
+    pub fn css(&self) -> String {
+        self.0
+            .iter()
+            .sorted()
+            .map(|(key, val)| format!("{key}: {val};"))
+            .join(" ")
+    }
 }
 
 impl FromIterator<(String, String)> for Style {
@@ -495,26 +1414,172 @@ impl ImageProps {
     }
 }
 
-/// A raw image that needs to be encoded in base64
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+/// Source of an image. Images from a path or from raw bytes are base64
+/// encoded only when serialized, so that the encoded strings need not be held
+/// in memory until the summary is rendered. Serializes as the encoded string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageSource {
+    /// Base64 encoded image
+    Encoded(String),
+    /// Path to a png or jpeg image which is encoded when serialized
+    #[cfg(feature = "image_base64_encode")]
+    Path(PathBuf),
+    /// Contents of a png or jpeg image which are encoded when serialized
+    #[cfg(feature = "image_base64_encode")]
+    Bytes(Vec<u8>),
+}
+
+impl ImageSource {
+    /// The base64 encoded image
+    pub fn encoded(&self) -> Result<Cow<'_, str>, Error> {
+        Ok(match self {
+            ImageSource::Encoded(image) => Cow::Borrowed(image),
+            #[cfg(feature = "image_base64_encode")]
+            ImageSource::Path(path) => Cow::Owned(
+                Base64ImageEncoder::guess(path)
+                    .and_then(|encoder| encoder.encode(path))
+                    .with_context(|| format!("Unable to encode the image {}", path.display()))?,
+            ),
+            #[cfg(feature = "image_base64_encode")]
+            ImageSource::Bytes(bytes) => {
+                Cow::Owned(Base64ImageEncoder::guess_from_bytes(bytes)?.encode_bytes(bytes))
+            }
+        })
+    }
+
+    /// Encode the image now, so that serializing it can no longer fail
+    pub fn materialize(&mut self) -> Result<(), Error> {
+        if !matches!(self, ImageSource::Encoded(_)) {
+            *self = ImageSource::Encoded(self.encoded()?.into_owned());
+        }
+        Ok(())
+    }
+}
+
+impl From<String> for ImageSource {
+    fn from(src: String) -> Self {
+        ImageSource::Encoded(src)
+    }
+}
+
+impl<'a> From<&'a str> for ImageSource {
+    fn from(src: &'a str) -> Self {
+        ImageSource::Encoded(src.to_string())
+    }
+}
+
+impl Serialize for ImageSource {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let encoded = self
+            .encoded()
+            .map_err(|e| serde::ser::Error::custom(format!("{e:#}")))?;
+        serializer.serialize_str(&encoded)
+    }
+}
+
+impl<'de> Deserialize<'de> for ImageSource {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(ImageSource::Encoded(String::deserialize(deserializer)?))
+    }
+}
+
+/// Only images which are already encoded are moved into the shared resources.
+/// Call `materialize` first to share lazily encoded images.
+impl AddToSharedResource for ImageSource {
+    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
+        match self {
+            ImageSource::Encoded(image) => {
+                *image = shared_resource.insert(Value::String(std::mem::take(image)));
+            }
+            #[cfg(feature = "image_base64_encode")]
+            ImageSource::Path(_) | ImageSource::Bytes(_) => {}
+        }
+    }
+}
+
+/// The image at another resolution, relative to the base image of a
+/// `RawImage`, e.g. a scale of 2 for high density displays
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ImageVariant {
+    pub scale: f32,
+    /// Base 64 encoded image
+    pub encoded_image: String,
+}
+
+impl ImageVariant {
+    pub fn new(scale: f32, encoded_image: String) -> Self {
+        ImageVariant {
+            scale,
+            encoded_image,
+        }
+    }
+}
+
+/// A raw image that needs to be encoded in base64
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RawImage {
     /// Base 64 encoded image
-    encoded_image: String,
+    encoded_image: ImageSource,
     zoom_pan: Option<ImageZoomPan>,
     link: Option<String>,
     #[serde(flatten)]
     props: ImageProps,
+    /// Other resolutions of the image, sorted by scale. The frontend picks
+    /// the one which best matches the display.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    variants: Option<Vec<ImageVariant>>,
+}
+
+/// The variants are moved into the shared resources along with the image
+impl AddToSharedResource for RawImage {
+    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
+        self.encoded_image.add_to_shared_resource(shared_resource);
+        for variant in self.variants.iter_mut().flatten() {
+            variant.encoded_image =
+                shared_resource.insert(Value::String(std::mem::take(&mut variant.encoded_image)));
+        }
+    }
 }
 
 impl RawImage {
     pub fn new(encoded_image: String) -> Self {
+        check_if_strict(&encoded_image);
+        RawImage::from_source(ImageSource::Encoded(encoded_image))
+    }
+    /// Similar to `new`, but returns an error if the image is not a valid
+    /// base64 encoded data URI
+    pub fn try_new(encoded_image: String) -> Result<Self, DataUriError> {
+        validate_data_uri(&encoded_image)?;
+        Ok(RawImage::from_source(ImageSource::Encoded(encoded_image)))
+    }
+    pub fn from_source(encoded_image: ImageSource) -> Self {
         RawImage {
             encoded_image,
             props: ImageProps::new(),
             zoom_pan: None,
             link: None,
+            variants: None,
         }
     }
+    /// Other resolutions of the image. The variants are sorted by scale and
+    /// only the first variant of each scale is kept. Variants whose scale is
+    /// not a positive number are dropped.
+    pub fn variants(mut self, variants: impl IntoIterator<Item = ImageVariant>) -> Self {
+        let mut variants: Vec<_> = variants
+            .into_iter()
+            .filter(|variant| variant.scale.is_finite() && variant.scale > 0.0)
+            .collect();
+        // Stable, so that the first of the variants with the same scale is kept
+        variants.sort_by(|a, b| a.scale.total_cmp(&b.scale));
+        variants.dedup_by(|b, a| a.scale == b.scale);
+        self.variants = (!variants.is_empty()).then_some(variants);
+        self
+    }
+    /// Encode the image now, so that serializing it can no longer fail
+    pub fn materialize(&mut self) -> Result<(), Error> {
+        self.encoded_image.materialize()
+    }
     pub fn props(mut self, props: ImageProps) -> Self {
         self.props = props;
         self
@@ -636,10 +1701,14 @@ impl<T: HtmlTemplate> HtmlTemplate for ButtonSelector<T> {
 
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+/// A size or a value which is either a number or a string such as `"100%"`.
+/// Numbers are tried before strings, so that a number in the JSON is never
+/// parsed as a string.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(untagged)]
 pub enum NumOrStr {
     Num(usize),
+    Float(f64),
     Str(String),
 }
 
@@ -655,17 +1724,31 @@ impl From<usize> for NumOrStr {
     }
 }
 
+impl From<f64> for NumOrStr {
+    fn from(value: f64) -> Self {
+        NumOrStr::Float(value)
+    }
+}
+
+impl From<f32> for NumOrStr {
+    fn from(value: f32) -> Self {
+        NumOrStr::Float(value.into())
+    }
+}
+
 impl Display for NumOrStr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             NumOrStr::Num(n) => write!(f, "{}", n),
+            // Display of f64 has no trailing ".0" for integral values
+            NumOrStr::Float(x) => write!(f, "{}", x),
             NumOrStr::Str(s) => write!(f, "{}", s),
         }
     }
 }
 
 /// Controls the opacity slider width in a blended image
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct BlendedImageSliderSize {
     pub width: NumOrStr,
 }
@@ -673,7 +1756,7 @@ pub struct BlendedImageSliderSize {
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
 /// Show two images on top of each other with a slider to adjust opacity.
 /// Typically used to show two aligned images
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct BlendedImage {
     /// Base64 encoded image
     #[serde(rename = "imgA")]
@@ -695,8 +1778,49 @@ pub struct BlendedImage {
 
 impl AddToSharedResource for BlendedImage {
     fn add_to_shared_resource(&mut self, resources: &mut SharedResources) {
-        self.image1 = resources.insert(Value::String(self.image1.clone()));
-        self.image2 = resources.insert(Value::String(self.image2.clone()));
+        self.image1 = resources.insert(Value::String(std::mem::take(&mut self.image1)));
+        self.image2 = resources.insert(Value::String(std::mem::take(&mut self.image2)));
+    }
+}
+
+impl BlendedImage {
+    pub fn new(image1: String, image2: String, size: BlendedImageSliderSize) -> Self {
+        check_if_strict(&image1);
+        check_if_strict(&image2);
+        BlendedImage {
+            image1,
+            image1_title: None,
+            image2,
+            image2_title: None,
+            size,
+            plot_title: None,
+            slider_title: None,
+        }
+    }
+    /// Similar to `new`, but returns an error if either of the images is not
+    /// a valid base64 encoded data URI
+    pub fn try_new(
+        image1: String,
+        image2: String,
+        size: BlendedImageSliderSize,
+    ) -> Result<Self, DataUriError> {
+        validate_data_uri(&image1)?;
+        validate_data_uri(&image2)?;
+        Ok(BlendedImage::new(image1, image2, size))
+    }
+    /// Similar to `new`, with the images encoded from their sources, e.g. an
+    /// `ImageSource::Path`. Both the images are encoded right away, since
+    /// the blended image holds the encoded strings.
+    pub fn from_sources(
+        image1: ImageSource,
+        image2: ImageSource,
+        size: BlendedImageSliderSize,
+    ) -> Result<Self, Error> {
+        Ok(BlendedImage::new(
+            image1.encoded()?.into_owned(),
+            image2.encoded()?.into_owned(),
+            size,
+        ))
     }
 }
 
@@ -764,7 +1888,7 @@ impl BlendedImageZoomable {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ZoomViewerSize {
     pub width: NumOrStr,
     pub height: NumOrStr,
@@ -786,49 +1910,99 @@ pub trait ReactComponent {
 }
 
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
-// The mapping between structs defined in this module and the react components
-// defined in `websummary/src/components/**/*.js`.
-react_component!(HeroMetric, "Metric");
-react_component!(TitleWithTermDesc, "DynamicHelptext");
-react_component!(TitleWithHelp, "HeaderWithHelp");
-react_component!(GenericTable, "Table");
-react_component!(TableMetric, "TableMetric");
-react_component!(PlotlyChart, "Plot");
-react_component!(VegaLitePlot, "VegaLitePlot");
-react_component!(RawImage, "RawImage");
-react_component!(BlendedImage, "ImageRegistViewer");
-react_component!(BlendedImageZoomable, "BlenderViewerZoomable");
-react_component!(ZoomViewer, "ZoomViewer");
-react_component!(StepProgress, "StepProgress");
-react_component!(CodeBlock, "CodeBlock");
-react_component!(Tooltip, "ReactTooltip");
-react_component!(HdClusteringPlot, "HdClusteringPlot");
-react_component!(HtmlFragment, "HtmlFragment");
-react_component!(JavaScript, "JavaScript");
-react_component!(DifferentialExpressionTable, "DifferentialExpressionTable");
-react_component!(HdEndToEndAlignment, "HdEndToEndAlignment");
-react_component!(MultiLayerImages, "MultiLayerImages");
-react_component!(DownloadableFile, "DownloadableFile");
+// The mapping between the structs of this crate and the react components
+// defined in `websummary/src/components/**/*.js`. Every component of the crate
+// is registered here, so that `REACT_COMPONENTS` lists all of them.
+react_component! {
+    HeroMetric => "Metric",
+    TitleWithTermDesc => "DynamicHelptext",
+    TitleWithHelp => "HeaderWithHelp",
+    GenericTable => "Table",
+    TableMetric => "TableMetric",
+    PlotlyChart => "Plot",
+    VegaLitePlot => "VegaLitePlot",
+    RawImage => "RawImage",
+    BlendedImage => "ImageRegistViewer",
+    BlendedImageZoomable => "BlenderViewerZoomable",
+    ZoomViewer => "ZoomViewer",
+    StepProgress => "StepProgress",
+    CodeBlock => "CodeBlock",
+    Tooltip => "ReactTooltip",
+    HdClusteringPlot => "HdClusteringPlot",
+    HtmlFragment => "HtmlFragment",
+    JavaScript => "JavaScript",
+    DifferentialExpressionTable => "DifferentialExpressionTable",
+    HdEndToEndAlignment => "HdEndToEndAlignment",
+    MultiLayerImages => "MultiLayerImages",
+    DownloadableFile => "DownloadableFile",
+    QcBanner => "QcBanner",
+    DataHeading => "Heading",
+    EmptyState => "EmptyState",
+    Sparkline => "Sparkline",
+    FractionBar => "FractionBar",
+    GroupedHeaderTable => "GroupedHeaderTable",
+    CategoricalLegend => "CategoricalLegend",
+    ColumnarTable => "ColumnarTable",
+    GroupedTableMetric => "GroupedTableMetric",
+    AlertGroup => "AlertGroup",
+    Footer => "Footer",
+    CommandLine => "DynamicHelptext",
+    #[cfg(feature = "form")]
+    InputFeedback => "InputFeedback",
+    #[cfg(feature = "form")]
+    InputElement => "InputElement",
+    #[cfg(feature = "form")]
+    SingleSelect => "SingleSelect",
+    #[cfg(feature = "form")]
+    MultiSelect => "MultiSelect",
+    #[cfg(feature = "form")]
+    TextArea => "TextArea",
+    #[cfg(feature = "form")]
+    RepeatedGroupInput => "RepeatedGroupInput",
+    #[cfg(feature = "form")]
+    SpreadsheetInput => "SpreadsheetInput",
+    #[cfg(feature = "time")]
+    Timestamp => "Timestamp",
+}
 
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
 impl<T: ReactComponent> HtmlTemplate for T {
     fn template(&self, data_key: Option<String>) -> String {
+        let mut template = String::new();
+        self.template_into(data_key, &mut template);
+        template
+    }
+    fn template_into(&self, data_key: Option<String>, out: &mut String) {
         let data_key = data_key.unwrap_or_else(|| {
             panic!(
                 "data-key is required to convert a react component {} into a template",
                 T::component_name()
             )
         });
-        format!(
-            r#"<div data-key="{data_key}" data-component="{}"></div>"#,
-            T::component_name()
-        )
+        react_component_template_into(&data_key, T::component_name(), out);
+    }
+    fn template_is_keyed() -> bool {
+        true
     }
 }
 
+/// Append `<div data-key="{data_key}" data-component="{component_name}"></div>`
+/// to `out` without formatting
+fn react_component_template_into(data_key: &str, component_name: &str, out: &mut String) {
+    const OPEN: &str = "<div data-key=\"";
+    const COMPONENT: &str = "\" data-component=\"";
+    const CLOSE: &str = "\"></div>";
+    out.reserve(OPEN.len() + data_key.len() + COMPONENT.len() + component_name.len() + CLOSE.len());
+    out.push_str(OPEN);
+    out.push_str(data_key);
+    out.push_str(COMPONENT);
+    out.push_str(component_name);
+    out.push_str(CLOSE);
+}
+
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
 // Show progress in a series of steps
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct StepProgress {
     pub steps: Vec<String>,
@@ -857,6 +2031,16 @@ impl<P: ParentComponentProps, C: HtmlTemplate> ComponentWithChildren<P, C> {
     }
 }
 
+impl<P, C> AddToSharedResource for ComponentWithChildren<P, C>
+where
+    P: ParentComponentProps,
+    C: HtmlTemplate + AddToSharedResource,
+{
+    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
+        self.children.add_to_shared_resource(shared_resource);
+    }
+}
+
 impl<P: ParentComponentProps, C: HtmlTemplate> HtmlTemplate for ComponentWithChildren<P, C> {
     fn template(&self, data_key: Option<String>) -> String {
         const PARENT_PROPS: &str = "parent_props";
@@ -875,6 +2059,41 @@ impl<P: ParentComponentProps, C: HtmlTemplate> HtmlTemplate for ComponentWithChi
     }
 }
 
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+/// A react component which is not defined in this crate, e.g. from a custom
+/// JS bundle, for one-off use without defining a struct and implementing
+/// `ReactComponent` for it. Serializes exactly like the props.
+#[derive(Serialize, Clone)]
+#[serde(transparent)]
+pub struct CustomComponent<P: Serialize> {
+    /// Name of the react component
+    #[serde(skip)]
+    pub component: &'static str,
+    pub props: P,
+}
+
+impl<P: Serialize> CustomComponent<P> {
+    pub fn new(component: &'static str, props: P) -> Self {
+        CustomComponent { component, props }
+    }
+}
+
+impl<P: Serialize> HtmlTemplate for CustomComponent<P> {
+    fn template(&self, data_key: Option<String>) -> String {
+        let component = self.component;
+        let data_key = data_key.unwrap_or_else(|| {
+            panic!("data-key is required to convert a react component {component} into a template")
+        });
+        format!(r#"<div data-key="{data_key}" data-component="{component}"></div>"#)
+    }
+}
+
+impl<P: Serialize + AddToSharedResource> AddToSharedResource for CustomComponent<P> {
+    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
+        self.props.add_to_shared_resource(shared_resource);
+    }
+}
+
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
 // Inline alerts which can show up anywhere in the html unlike a top level alert
 #[derive(Serialize, Clone)]
@@ -891,8 +2110,25 @@ pub enum InlineAlertLevel {
 }
 
 #[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct InlineAlertProps {
     pub level: InlineAlertLevel,
+    /// Show a button which closes the alert
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dismissible: Option<bool>,
+    /// Name of the icon shown before the children, e.g. `info-circle`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+}
+
+impl InlineAlertProps {
+    pub fn new(level: InlineAlertLevel) -> Self {
+        InlineAlertProps {
+            level,
+            dismissible: None,
+            icon: None,
+        }
+    }
 }
 
 impl ParentComponentProps for InlineAlertProps {
@@ -904,9 +2140,22 @@ impl ParentComponentProps for InlineAlertProps {
 pub type InlineAlert<T> = ComponentWithChildren<InlineAlertProps, T>;
 pub type InlineTextAlert = InlineAlert<HtmlFragment>;
 
+impl<T: HtmlTemplate> InlineAlert<T> {
+    /// Show a button which closes the alert
+    pub fn dismissible(mut self) -> Self {
+        self.parent_props.dismissible = Some(true);
+        self
+    }
+    /// Show the icon before the children, e.g. `info-circle`
+    pub fn icon(mut self, icon: impl ToString) -> Self {
+        self.parent_props.icon = Some(icon.to_string());
+        self
+    }
+}
+
 impl InlineTextAlert {
     pub fn with_level_and_text(level: InlineAlertLevel, text: impl ToString) -> Self {
-        InlineAlert::new(InlineAlertProps { level }, HtmlFragment::new(text))
+        InlineAlert::new(InlineAlertProps::new(level), HtmlFragment::new(text))
     }
     pub fn primary(text: impl ToString) -> Self {
         InlineTextAlert::with_level_and_text(InlineAlertLevel::Primary, text)
@@ -954,7 +2203,7 @@ impl InlineHelp {
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
 // HTML heading
 
-#[derive(Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub enum HeadingLevel {
     H1,
     H2,
@@ -977,6 +2226,8 @@ impl HeadingLevel {
     }
 }
 
+/// A static heading. The text is inlined into the template and does not
+/// appear in the data. See `DataHeading` for a heading backed by data.
 #[derive(Serialize, Deserialize)]
 pub struct Heading {
     text: String,
@@ -1017,6 +2268,32 @@ impl HtmlTemplate for Heading {
     }
 }
 
+/// A heading whose text and level are serialized as props, so that the
+/// heading is part of the data like any other component.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DataHeading {
+    pub text: String,
+    pub level: HeadingLevel,
+}
+
+impl DataHeading {
+    pub fn new(level: HeadingLevel, text: impl ToString) -> Self {
+        DataHeading {
+            text: text.to_string(),
+            level,
+        }
+    }
+}
+
+impl From<Heading> for DataHeading {
+    fn from(src: Heading) -> Self {
+        DataHeading {
+            text: src.text,
+            level: src.level,
+        }
+    }
+}
+
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
 /// Two column
 
@@ -1030,6 +2307,18 @@ pub struct TwoColumn<L: HtmlTemplate, R: HtmlTemplate> {
     pub right: R,
 }
 
+#[cfg(feature = "derive")]
+impl<L, R> AddToSharedResource for TwoColumn<L, R>
+where
+    L: HtmlTemplate + AddToSharedResource,
+    R: HtmlTemplate + AddToSharedResource,
+{
+    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
+        self.left.add_to_shared_resource(shared_resource);
+        self.right.add_to_shared_resource(shared_resource);
+    }
+}
+
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
 // Collapsible panel
 #[derive(Serialize, Deserialize)]
@@ -1078,10 +2367,7 @@ pub enum Title {
 
 impl Title {
     pub fn new(title: impl Into<String>) -> Self {
-        Title::WithHelp(TitleWithHelp {
-            help: String::new(),
-            title: title.into(),
-        })
+        Title::WithHelp(TitleWithHelp::new(title.into(), ""))
     }
 }
 
@@ -1125,9 +2411,65 @@ impl<T: HtmlTemplate> WithTitle<T> {
     }
 }
 
+#[cfg(feature = "derive")]
+impl<T: HtmlTemplate + AddToSharedResource> AddToSharedResource for WithTitle<T> {
+    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
+        self.inner.add_to_shared_resource(shared_resource);
+    }
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+/// A component with help from the doc comment of its field. With
+/// `#[html(doc_tooltips)]` on a struct deriving `HtmlTemplate`, the generated
+/// `with_doc_tooltips()` sets the help of the `Documented` fields: the first
+/// line of the doc comment is the title and the other lines are the help.
+/// The data is `{"help": {...}, "inner": {...}}`, without `help` until it is
+/// set.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Documented<T: HtmlTemplate> {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub help: Option<TitleWithHelp>,
+    pub inner: T,
+}
+
+impl<T: HtmlTemplate> Documented<T> {
+    pub fn new(inner: T) -> Self {
+        Documented { help: None, inner }
+    }
+    pub fn help(mut self, help: TitleWithHelp) -> Self {
+        self.help = Some(help);
+        self
+    }
+}
+
+impl<T: HtmlTemplate> HtmlTemplate for Documented<T> {
+    fn template(&self, data_key: Option<String>) -> String {
+        let mut template = String::new();
+        self.template_into(data_key, &mut template);
+        template
+    }
+    fn template_into(&self, data_key: Option<String>, out: &mut String) {
+        let key = |field: &str| match &data_key {
+            Some(key) => format!("{key}.{field}"),
+            None => field.to_string(),
+        };
+        if let Some(help) = &self.help {
+            help.template_into(Some(key("help")), out);
+            out.push('\n');
+        }
+        self.inner.template_into(Some(key("inner")), out);
+    }
+}
+
+impl<T: HtmlTemplate + AddToSharedResource> AddToSharedResource for Documented<T> {
+    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
+        self.inner.add_to_shared_resource(shared_resource);
+    }
+}
+
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
 /// String holding javascript code
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct JavaScript {
     pub code: String,
 }
@@ -1142,7 +2484,7 @@ impl JavaScript {
 
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
 /// String holding html
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct HtmlFragment {
     pub html: String,
 }
@@ -1159,13 +2501,13 @@ impl HtmlFragment {
 /// Wrapping underlying template within a div. Useful for layout customization
 #[derive(Debug, Clone, Serialize, PartialEq, Eq)]
 #[serde(transparent)] // Works becasue we skip `class`
-struct DivWrapper<'a, T: HtmlTemplate> {
+struct DivWrapper<'a, T: HtmlTemplate + ?Sized> {
     inner: &'a T,
     #[serde(skip)]
     class: String,
 }
 
-impl<'a, T: HtmlTemplate> DivWrapper<'a, T> {
+impl<'a, T: HtmlTemplate + ?Sized> DivWrapper<'a, T> {
     fn new(inner: &'a T, class: &str) -> Self {
         DivWrapper {
             inner,
@@ -1180,13 +2522,18 @@ impl<'a, T: HtmlTemplate> DivWrapper<'a, T> {
     }
 }
 
-impl<'a, T: HtmlTemplate> HtmlTemplate for DivWrapper<'a, T> {
+impl<'a, T: HtmlTemplate + ?Sized> HtmlTemplate for DivWrapper<'a, T> {
     fn template(&self, data_key: Option<String>) -> String {
-        format!(
-            "<div class=\"{}\">\n{}\n</div>",
-            self.class,
-            self.inner.template(data_key)
-        )
+        let mut template = String::new();
+        self.template_into(data_key, &mut template);
+        template
+    }
+    fn template_into(&self, data_key: Option<String>, out: &mut String) {
+        out.push_str("<div class=\"");
+        out.push_str(&self.class);
+        out.push_str("\">\n");
+        self.inner.template_into(data_key, out);
+        out.push_str("\n</div>");
     }
 }
 
@@ -1234,7 +2581,20 @@ impl<T: 'static + HtmlTemplate + Serialize> Grid<T> {
         }
     }
     pub fn push(&mut self, element: T) {
-        self.dyn_grid.push(element)
+        match self.dyn_grid.elements.last() {
+            // Every element has the same template
+            Some(last) if T::template_is_keyed() => {
+                let template = last.clone();
+                self.dyn_grid.push_with_template(element, template);
+            }
+            _ => self.dyn_grid.push(element),
+        }
+    }
+    /// Push the element if it is present, see `DynGrid::push_optional()`
+    pub fn push_optional(&mut self, element: Option<T>) {
+        if let Some(element) = element {
+            self.push(element);
+        }
     }
     pub fn with_elements(elements: Vec<T>, layout: GridLayout) -> Self {
         let mut grid = Grid::new(layout);
@@ -1243,12 +2603,54 @@ impl<T: 'static + HtmlTemplate + Serialize> Grid<T> {
         }
         grid
     }
+    /// Grid of the elements which are present
+    pub fn from_optional_elements(elements: Vec<Option<T>>, layout: GridLayout) -> Self {
+        Grid::with_elements(elements.into_iter().flatten().collect(), layout)
+    }
 }
 
 impl<T: HtmlTemplate> HtmlTemplate for Grid<T> {
     fn template(&self, data_key: Option<String>) -> String {
         self.dyn_grid.template(data_key)
     }
+    fn template_into(&self, data_key: Option<String>, out: &mut String) {
+        self.dyn_grid.template_into(data_key, out)
+    }
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+/// A template generated with a marker in place of the data key. The template
+/// is split at the marker once, so that rendering it with the actual key does
+/// not rescan the whole template.
+#[derive(Debug, Clone)]
+struct KeyedTemplate {
+    /// Shared, so that elements with the same template can reuse it
+    pieces: Arc<[String]>,
+}
+
+impl KeyedTemplate {
+    fn new<T: HtmlTemplate + ?Sized>(component: &T, marker: &str) -> Self {
+        KeyedTemplate {
+            pieces: component
+                .template(Some(marker.into()))
+                .split(marker)
+                .map(String::from)
+                .collect::<Vec<_>>()
+                .into(),
+        }
+    }
+    /// Length of the rendered template, given the length of the key
+    fn len(&self, key_len: usize) -> usize {
+        self.pieces.iter().map(String::len).sum::<usize>() + (self.pieces.len() - 1) * key_len
+    }
+    fn render_into(&self, key: &str, out: &mut String) {
+        for (i, piece) in self.pieces.iter().enumerate() {
+            if i > 0 {
+                out.push_str(key);
+            }
+            out.push_str(piece);
+        }
+    }
 }
 
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
@@ -1261,7 +2663,7 @@ const DYN_GRID_MARKER: &str = "__AUbkUE__DYN_GRID__WhcSw=__";
 pub struct DynGrid {
     grid_data: Vec<Value>,
     #[serde(skip)]
-    elements: Vec<String>,
+    elements: Vec<KeyedTemplate>,
     #[serde(skip)]
     layout: GridLayout,
 }
@@ -1274,10 +2676,22 @@ impl DynGrid {
             layout,
         }
     }
+    /// Pushing a `None` keeps a `null` entry in `grid_data` and an empty
+    /// column, as a placeholder gap. Use `push_optional()` to leave it out.
     pub fn push<T: HtmlTemplate + Serialize>(&mut self, element: T) {
+        let template = KeyedTemplate::new(&element, DYN_GRID_MARKER);
+        self.push_with_template(element, template);
+    }
+    /// Push the element if it is present. A `None` is skipped entirely, so
+    /// the following elements take its place in the grid.
+    pub fn push_optional<T: HtmlTemplate + Serialize>(&mut self, element: Option<T>) {
+        if let Some(element) = element {
+            self.push(element);
+        }
+    }
+    fn push_with_template<T: Serialize>(&mut self, element: T, template: KeyedTemplate) {
         self.grid_data.push(serde_json::to_value(&element).unwrap());
-        self.elements
-            .push(element.template(Some(DYN_GRID_MARKER.into())));
+        self.elements.push(template);
     }
     pub fn with_elements<T: 'static + HtmlTemplate + Serialize>(
         elements: Vec<T>,
@@ -1289,62 +2703,116 @@ impl DynGrid {
         }
         grid
     }
+    /// Grid of the elements which are present, see `push_optional()`
+    pub fn from_optional_elements<T: 'static + HtmlTemplate + Serialize>(
+        elements: Vec<Option<T>>,
+        layout: GridLayout,
+    ) -> Self {
+        DynGrid::with_elements(elements.into_iter().flatten().collect(), layout)
+    }
 }
 
 impl HtmlTemplate for DynGrid {
     fn template(&self, data_key: Option<String>) -> String {
-        match self.layout {
-            GridLayout::MaxCols(n) => self
-                .elements
-                .iter()
-                .enumerate()
-                .chunks(n as usize)
-                .into_iter()
-                .map(|same_row_elements| {
-                    DivWrapper::row(
-                        &same_row_elements
-                            .map(|(i, element)| {
-                                let data_key = match &data_key {
-                                    Some(key) => format!("{key}.grid_data[{i}]"),
-                                    None => format!("grid_data[{i}]"),
-                                };
-                                DivWrapper::new(
-                                    &element.replace(DYN_GRID_MARKER, &data_key),
-                                    self.layout.col_class(),
-                                )
-                                .template(None)
-                            })
-                            .join("\n"),
-                    )
-                    .template(None)
-                })
-                .join("\n"),
-            GridLayout::MaxColsNonResponsive(n) => {
-                let rows = self
-                    .elements
-                    .iter()
-                    .enumerate()
-                    .chunks(n as usize)
-                    .into_iter()
-                    .map(|same_row_elements| {
-                        let tds = same_row_elements
-                            .map(|(i, element)| {
-                                let data_key = match &data_key {
-                                    Some(key) => format!("{key}.grid_data[{i}]"),
-                                    None => format!("grid_data[{i}]"),
-                                };
-                                format!("<td>{}</td>", element.replace(DYN_GRID_MARKER, &data_key))
-                            })
-                            .join("\n");
-                        format!("<tr>{}</tr>", tds)
-                    })
-                    .join("\n");
-                format!("<table><tbody>{}</tbody></table>", rows)
+        // Estimate for the rendered elements and the divs around them
+        let key_len = data_key.as_ref().map_or(0, String::len) + "grid_data[0000]".len() + 1;
+        let capacity = self
+            .elements
+            .iter()
+            .map(|element| element.len(key_len) + 40)
+            .sum();
+        let mut template = String::with_capacity(capacity);
+        self.template_into(data_key, &mut template);
+        template
+    }
+    fn template_into(&self, data_key: Option<String>, out: &mut String) {
+        let base_data_key = join_data_key(&data_key, "grid_data");
+        let (ncols, responsive) = match self.layout {
+            GridLayout::MaxCols(n) => (n as usize, true),
+            GridLayout::MaxColsNonResponsive(n) => (n as usize, false),
+        };
+        if !responsive {
+            out.push_str("<table><tbody>");
+        }
+        let mut element_key = String::new();
+        for (row, same_row_elements) in self.elements.chunks(ncols).enumerate() {
+            if row > 0 {
+                out.push('\n');
             }
+            out.push_str(if responsive {
+                "<div class=\"row\">\n"
+            } else {
+                "<tr>"
+            });
+            for (col, element) in same_row_elements.iter().enumerate() {
+                if col > 0 {
+                    out.push('\n');
+                }
+                element_key.clear();
+                write!(element_key, "{base_data_key}[{}]", row * ncols + col).unwrap();
+                if responsive {
+                    out.push_str("<div class=\"");
+                    out.push_str(self.layout.col_class());
+                    out.push_str("\">\n");
+                    element.render_into(&element_key, out);
+                    out.push_str("\n</div>");
+                } else {
+                    out.push_str("<td>");
+                    element.render_into(&element_key, out);
+                    out.push_str("</td>");
+                }
+            }
+            out.push_str(if responsive { "\n</div>" } else { "</tr>" });
+        }
+        if !responsive {
+            out.push_str("</tbody></table>");
         }
     }
 }
 
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+// A unique marker for keys in the ErasedSection divs. This will be replaced
+// with the correct key when building the template.
+const ERASED_SECTION_MARKER: &str = "__AUbkUE__ERASED_SECTION__WhcSw=__";
+
+/// A component whose type has been erased, so that components of different
+/// types can be held together, e.g. in a `Vec<ErasedSection>`. Serializes
+/// exactly like the underlying component.
+#[derive(Debug, Clone, Serialize)]
+#[serde(transparent)] // Works becasue we skip `template`
+pub struct ErasedSection {
+    data: Value,
+    #[serde(skip)]
+    template: KeyedTemplate,
+}
+
+impl ErasedSection {
+    pub fn new<T: HtmlTemplate + Serialize>(component: T) -> Self {
+        ErasedSection::try_new(component).unwrap()
+    }
+    pub fn try_new<T: HtmlTemplate + Serialize>(component: T) -> Result<Self, Error> {
+        Ok(ErasedSection {
+            data: serde_json::to_value(&component)?,
+            template: KeyedTemplate::new(&component, ERASED_SECTION_MARKER),
+        })
+    }
+}
+
+impl HtmlTemplate for ErasedSection {
+    fn template(&self, data_key: Option<String>) -> String {
+        let mut template = String::new();
+        self.template_into(data_key, &mut template);
+        template
+    }
+    fn template_into(&self, data_key: Option<String>, out: &mut String) {
+        let data_key = data_key.unwrap_or_else(|| {
+            panic!("data-key is required to convert an ErasedSection into a template")
+        });
+        out.reserve(self.template.len(data_key.len()));
+        self.template.render_into(&data_key, out);
+    }
+}
+
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
 // A card which has a raised border
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -1389,433 +2857,4136 @@ impl CardWidth {
     }
 }
 
+impl<T: HtmlTemplate + AddToSharedResource> AddToSharedResource for Card<T> {
+    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
+        self.inner.add_to_shared_resource(shared_resource);
+    }
+}
+
 impl<T: HtmlTemplate> HtmlTemplate for Card<T> {
     fn template(&self, data_key: Option<String>) -> String {
         DivWrapper::new(&self.inner, self.width.class()).template(data_key)
     }
 }
 
-impl<T: HtmlTemplate> HtmlTemplate for Option<T> {
-    fn template(&self, data_key: Option<String>) -> String {
-        self.as_ref()
-            .map(|inner| inner.template(data_key))
-            .unwrap_or_default()
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+/// Css class which hides the debug only sections until they are toggled
+pub const DEBUG_HIDDEN_CLASS: &str = "d-none";
+
+/// Content which is present in the data, but hidden by default. The hidden
+/// sections are revealed by the toggle added by
+/// `SinglePageHtml::with_debug_toggle()`. The data keys of the inner content
+/// are unchanged.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DebugOnly<T: HtmlTemplate> {
+    #[serde(flatten)]
+    pub inner: T,
+    debug: bool,
+}
+
+impl<T: HtmlTemplate> DebugOnly<T> {
+    pub fn new(inner: T) -> Self {
+        DebugOnly { inner, debug: true }
     }
 }
 
-impl<T: HtmlTemplate> HtmlTemplate for Vec<T> {
+impl<T: HtmlTemplate> HtmlTemplate for DebugOnly<T> {
     fn template(&self, data_key: Option<String>) -> String {
-        self.iter()
-            .enumerate()
-            .map(|(i, inner)| {
-                DivWrapper::row(&DivWrapper::col(inner))
-                    .template(data_key.as_ref().map(|k| format!("{k}[{i}]")))
-            })
-            .join("\n")
+        format!(
+            "<div class=\"{DEBUG_HIDDEN_CLASS}\" data-debug=\"true\">\n{}\n</div>",
+            self.inner.template(data_key)
+        )
     }
 }
 
-// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
-// A unique marker for keys in the Tabs divs. This will be replaced
-// with the correct key when building the template.
-const TAB_MARKER: &str = "__AUbkUE__TAB__WhcSw=__";
+/// Key of the marker of `Experimental` content. Prefixed with an underscore
+/// like the other reserved keys, so that it does not collide with a field of
+/// the content.
+pub const EXPERIMENTAL_KEY: &str = "_experimental";
 
-/// Each tab is defined by a title and an element
-/// TODO: Support deriving tabs from a struct
-#[derive(Serialize, Default)]
-pub struct Tabs {
-    tab_data: Vec<Value>,
-    #[serde(skip)]
-    elements: Vec<String>,
-    #[serde(skip)]
-    titles: Vec<String>,
+/// Content which is only shown in internal builds. The data is the inner
+/// content with an `_experimental: true` marker, and the template is that of
+/// the inner content. `SinglePageHtml::strip_experimental()` drops it from
+/// the data and the template for external builds.
+///
+/// The inner content should serialize as a map, e.g. a struct, otherwise
+/// serializing fails.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct Experimental<T: HtmlTemplate> {
+    #[serde(flatten)]
+    pub inner: T,
+    /// See `EXPERIMENTAL_KEY`
+    #[serde(rename = "_experimental")]
+    experimental: bool,
 }
 
-impl Tabs {
-    pub fn new() -> Self {
-        Tabs::default()
+impl<T: HtmlTemplate + Serialize> Serialize for Experimental<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        let mut map = serializer.serialize_map(None)?;
+        self.inner.serialize(FlattenInto(&mut map))?;
+        map.serialize_entry(EXPERIMENTAL_KEY, &self.experimental)?;
+        map.end()
     }
-    pub fn push<T: HtmlTemplate + Serialize>(&mut self, tab_title: impl Into<String>, element: T) {
-        self.tab_data.push(serde_json::to_value(&element).unwrap());
-        self.elements
-            .push(element.template(Some(TAB_MARKER.into())));
-        self.titles.push(tab_title.into());
+}
+
+/// Serializer writing the entries of a map or a struct into an outer map,
+/// like `#[serde(flatten)]`, which fails for the other values with an error
+/// naming `Experimental`
+struct FlattenInto<'a, M>(&'a mut M);
+
+fn not_a_map<E: serde::ser::Error>(found: &str) -> E {
+    E::custom(format!(
+        "The content of Experimental should serialize as a map, found {found}"
+    ))
+}
+
+impl<'a, M: serde::ser::SerializeMap> serde::Serializer for FlattenInto<'a, M> {
+    type Ok = ();
+    type Error = M::Error;
+    type SerializeSeq = serde::ser::Impossible<(), M::Error>;
+    type SerializeTuple = serde::ser::Impossible<(), M::Error>;
+    type SerializeTupleStruct = serde::ser::Impossible<(), M::Error>;
+    type SerializeTupleVariant = serde::ser::Impossible<(), M::Error>;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = serde::ser::Impossible<(), M::Error>;
+
+    fn serialize_bool(self, _: bool) -> Result<(), M::Error> {
+        Err(not_a_map("a boolean"))
+    }
+    fn serialize_i8(self, _: i8) -> Result<(), M::Error> {
+        Err(not_a_map("a number"))
     }
-    pub fn tab<T: HtmlTemplate + Serialize>(
-        mut self,
-        tab_title: impl Into<String>,
-        element: T,
-    ) -> Self {
-        self.push(tab_title, element);
-        self
+    fn serialize_i16(self, _: i16) -> Result<(), M::Error> {
+        Err(not_a_map("a number"))
+    }
+    fn serialize_i32(self, _: i32) -> Result<(), M::Error> {
+        Err(not_a_map("a number"))
+    }
+    fn serialize_i64(self, _: i64) -> Result<(), M::Error> {
+        Err(not_a_map("a number"))
+    }
+    fn serialize_u8(self, _: u8) -> Result<(), M::Error> {
+        Err(not_a_map("a number"))
+    }
+    fn serialize_u16(self, _: u16) -> Result<(), M::Error> {
+        Err(not_a_map("a number"))
+    }
+    fn serialize_u32(self, _: u32) -> Result<(), M::Error> {
+        Err(not_a_map("a number"))
+    }
+    fn serialize_u64(self, _: u64) -> Result<(), M::Error> {
+        Err(not_a_map("a number"))
+    }
+    fn serialize_f32(self, _: f32) -> Result<(), M::Error> {
+        Err(not_a_map("a number"))
+    }
+    fn serialize_f64(self, _: f64) -> Result<(), M::Error> {
+        Err(not_a_map("a number"))
+    }
+    fn serialize_char(self, _: char) -> Result<(), M::Error> {
+        Err(not_a_map("a string"))
+    }
+    fn serialize_str(self, _: &str) -> Result<(), M::Error> {
+        Err(not_a_map("a string"))
+    }
+    fn serialize_bytes(self, _: &[u8]) -> Result<(), M::Error> {
+        Err(not_a_map("bytes"))
+    }
+    // An absent or a unit content has no entries
+    fn serialize_none(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), M::Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), M::Error> {
+        Ok(())
+    }
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+    ) -> Result<(), M::Error> {
+        Err(not_a_map("an enum variant"))
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), M::Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<(), M::Error> {
+        Err(not_a_map("an enum variant"))
+    }
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, M::Error> {
+        Err(not_a_map("a sequence"))
+    }
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, M::Error> {
+        Err(not_a_map("a sequence"))
+    }
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, M::Error> {
+        Err(not_a_map("a sequence"))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, M::Error> {
+        Err(not_a_map("an enum variant"))
+    }
+    fn serialize_map(self, _: Option<usize>) -> Result<Self, M::Error> {
+        Ok(self)
+    }
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self, M::Error> {
+        Ok(self)
+    }
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, M::Error> {
+        Err(not_a_map("an enum variant"))
     }
 }
 
-impl HtmlTemplate for Tabs {
+impl<M: serde::ser::SerializeMap> serde::ser::SerializeMap for FlattenInto<'_, M> {
+    type Ok = ();
+    type Error = M::Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), M::Error> {
+        self.0.serialize_key(key)
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), M::Error> {
+        self.0.serialize_value(value)
+    }
+    fn end(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+}
+
+impl<M: serde::ser::SerializeMap> serde::ser::SerializeStruct for FlattenInto<'_, M> {
+    type Ok = ();
+    type Error = M::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), M::Error> {
+        self.0.serialize_entry(key, value)
+    }
+    fn end(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+}
+
+impl<T: HtmlTemplate> Experimental<T> {
+    pub fn new(inner: T) -> Self {
+        Experimental {
+            inner,
+            experimental: true,
+        }
+    }
+}
+
+impl<T: HtmlTemplate> HtmlTemplate for Experimental<T> {
     fn template(&self, data_key: Option<String>) -> String {
-        let base_data_key = match data_key {
-            Some(key) => format!("{key}.tab_data"),
-            None => "tab_data".into(),
-        };
-        let inner = std::iter::zip(&self.elements, &self.titles)
-            .enumerate()
-            .map(|(i, (element, title))| {
-                let inner = element.replace(TAB_MARKER, &format!("{base_data_key}[{i}]"));
-                format!(
-                    r#"<div class="tab-wrapper" data-event-key="tab_{i}" data-title="{title}">
-{inner}
-</div>"#
-                )
-            })
-            .join("\n");
-        format!(
-            r#"<div class="tabs-wrapper" data-default-active-key="tab_0" data-id="main-tabs">
-{inner}
-</div>"#
-        )
+        self.inner.template(data_key)
+    }
+    fn template_into(&self, data_key: Option<String>, out: &mut String) {
+        self.inner.template_into(data_key, out);
     }
 }
 
-// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
-/// A text with a hyperlink
-pub struct LinkedText {
-    pub link: String,
-    pub text: String,
+/// Replace the objects marked by `Experimental` with null, returning their
+/// data keys
+pub(crate) fn strip_experimental_data(data: &mut Value) -> Vec<String> {
+    fn strip(value: &mut Value, key: &mut String, stripped: &mut Vec<String>) {
+        if value.get(EXPERIMENTAL_KEY) == Some(&Value::Bool(true)) {
+            *value = Value::Null;
+            stripped.push(key.clone());
+            return;
+        }
+        let len = key.len();
+        match value {
+            Value::Array(values) => {
+                for (i, value) in values.iter_mut().enumerate() {
+                    key.push_str(&format!("[{i}]"));
+                    strip(value, key, stripped);
+                    key.truncate(len);
+                }
+            }
+            Value::Object(map) => {
+                for (k, value) in map.iter_mut() {
+                    if !key.is_empty() {
+                        key.push('.');
+                    }
+                    key.push_str(k);
+                    strip(value, key, stripped);
+                    key.truncate(len);
+                }
+            }
+            _ => {}
+        }
+    }
+    let mut stripped = Vec::new();
+    strip(data, &mut String::new(), &mut stripped);
+    stripped
 }
 
-impl LinkedText {
-    pub fn html(&self) -> String {
-        format!("<a href=\"{}\">{}</a>", self.link, self.text)
+/// Remove the divs whose data key is one of the keys or within one of them,
+/// e.g. `tabs.tab_data[0]` is within `tabs`, along with their children
+pub(crate) fn remove_data_key_divs(template: &str, keys: &[String]) -> String {
+    let is_removed = |data_key: &str| {
+        keys.iter().any(|key| {
+            data_key
+                .strip_prefix(key.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(['.', '[']))
+        })
+    };
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("<div") {
+        let Some(tag_len) = rest[start..].find('>') else {
+            break;
+        };
+        let tag_end = start + tag_len + 1;
+        let data_key = rest[start..tag_end]
+            .split_once(r#"data-key=""#)
+            .and_then(|(_, attr)| attr.split_once('"'))
+            .map(|(data_key, _)| data_key);
+        if !data_key.is_some_and(is_removed) {
+            out.push_str(&rest[..tag_end]);
+            rest = &rest[tag_end..];
+            continue;
+        }
+        out.push_str(&rest[..start]);
+        rest = &rest[tag_end..];
+        // Skip to the matching closing tag
+        let mut depth = 1;
+        while depth > 0 {
+            match (rest.find("<div"), rest.find("</div>")) {
+                (Some(open), Some(close)) if open < close => {
+                    depth += 1;
+                    rest = &rest[open + "<div".len()..];
+                }
+                (_, Some(close)) => {
+                    depth -= 1;
+                    rest = &rest[close + "</div>".len()..];
+                }
+                (_, None) => {
+                    rest = "";
+                    break;
+                }
+            }
+        }
     }
+    out.push_str(rest);
+    out
+}
+
+/// Toggles the visibility of all the `DebugOnly` sections. The sections are
+/// shown if the page is opened with a `debug` query parameter and can be
+/// toggled using Ctrl+Shift+D.
+pub(crate) fn debug_toggle() -> JavaScript {
+    JavaScript::new(format!(
+        r#"(function () {{
+  const toggle = () =>
+    document
+      .querySelectorAll('[data-debug="true"]')
+      .forEach((el) => el.classList.toggle("{DEBUG_HIDDEN_CLASS}"));
+  document.addEventListener("keydown", (e) => {{
+    if (e.ctrlKey && e.shiftKey && e.key === "D") {{
+      toggle();
+    }}
+  }});
+  if (new URLSearchParams(window.location.search).has("debug")) {{
+    if (document.readyState === "complete") {{
+      toggle();
+    }} else {{
+      window.addEventListener("load", toggle);
+    }}
+  }}
+}})();"#
+    ))
 }
 
-impl HtmlTemplate for LinkedText {
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+/// Forces a page break when the summary is printed. Has no data.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct PageBreak;
+
+impl HtmlTemplate for PageBreak {
     fn template(&self, _: Option<String>) -> String {
-        self.html()
+        format!(
+            "<div style=\"{}\"></div>",
+            Style::new().page_break_before().css()
+        )
     }
 }
 
-// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
-/// HdClusteringPlot
+/// Avoids splitting the inner content across pages when the summary is
+/// printed. The data and the data keys of the inner content are unchanged.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct NoBreak<T: HtmlTemplate> {
+    pub inner: T,
+}
 
-#[derive(Serialize, Deserialize)]
-pub struct HdClusteringSingleClusterData {
-    pub cluster_name: String,
-    pub hex_color: String,
-    pub spatial_plot: String,
-    pub umap_plot: String,
+impl<T: HtmlTemplate> NoBreak<T> {
+    pub fn new(inner: T) -> Self {
+        NoBreak { inner }
+    }
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct HdClusteringSpatialPlotProps {
-    pub title: String,
-    pub tissue_image: String,
-    pub tissue_css_transform: Vec<f64>,
-    pub spot_css_transform: Vec<f64>,
-    pub width: u32,
-    pub height: u32,
-    pub initial_zoom_pan: InitialZoomPan,
+impl<T: HtmlTemplate> HtmlTemplate for NoBreak<T> {
+    fn template(&self, data_key: Option<String>) -> String {
+        format!(
+            "<div style=\"{}\">\n{}\n</div>",
+            Style::new().break_inside_avoid().css(),
+            self.inner.template(data_key)
+        )
+    }
 }
 
-impl AddToSharedResource for HdClusteringSpatialPlotProps {
-    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
-        self.tissue_image = shared_resource.insert(Value::String(self.tissue_image.clone()));
+impl<T: HtmlTemplate> HtmlTemplate for Option<T> {
+    fn template(&self, data_key: Option<String>) -> String {
+        self.as_ref()
+            .map(|inner| inner.template(data_key))
+            .unwrap_or_default()
+    }
+    fn template_into(&self, data_key: Option<String>, out: &mut String) {
+        if let Some(inner) = self {
+            inner.template_into(data_key, out);
+        }
     }
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct HdClusteringUmapPlotProps {
-    pub title: String,
+impl<T: HtmlTemplate> HtmlTemplate for Vec<T> {
+    fn template(&self, data_key: Option<String>) -> String {
+        let mut template = String::new();
+        self.template_into(data_key, &mut template);
+        template
+    }
+    fn template_into(&self, data_key: Option<String>, out: &mut String) {
+        match (self.first(), &data_key) {
+            (Some(first), Some(key)) if T::template_is_keyed() => {
+                // Render the template once and splice in the key of each
+                // element, which produces the same output as below
+                let template = KeyedTemplate::new(first, VEC_MARKER);
+                let mut element_key = String::new();
+                for i in 0..self.len() {
+                    if i > 0 {
+                        out.push('\n');
+                    }
+                    element_key.clear();
+                    write!(element_key, "{key}[{i}]").unwrap();
+                    out.push_str("<div class=\"row\">\n<div class=\"col\">\n");
+                    template.render_into(&element_key, out);
+                    out.push_str("\n</div>\n</div>");
+                }
+            }
+            _ => {
+                for (i, inner) in self.iter().enumerate() {
+                    if i > 0 {
+                        out.push('\n');
+                    }
+                    DivWrapper::row(&DivWrapper::col(inner))
+                        .template_into(data_key.as_ref().map(|k| format!("{k}[{i}]")), out);
+                }
+            }
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct HdClusteringPlot {
-    pub spatial_plot_props: HdClusteringSpatialPlotProps,
-    pub umap_plot_props: HdClusteringUmapPlotProps,
-    pub clusters: Vec<HdClusteringSingleClusterData>,
+// A unique marker for keys in the elements of a Vec. This will be replaced
+// with the correct key when building the template.
+const VEC_MARKER: &str = "__AUbkUE__VEC__WhcSw=__";
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+// Smart pointers and references render like the component they point to, and
+// serde serializes them like the component too (`Rc` and `Arc` need the `rc`
+// feature of serde).
+//
+// `Box<T>` and `&T` can not implement `HtmlTemplate` for every `T`, since
+// that would overlap with the impl for every `ReactComponent`. Instead they
+// are react components if `T` is, and `Box<dyn HtmlTemplate>` and
+// `&dyn HtmlTemplate` can be used for any other template, e.g. where only the
+// template is needed. Use `Rc` or `Arc` to share any other component.
+impl<T: HtmlTemplate + ?Sized> HtmlTemplate for std::rc::Rc<T> {
+    fn template(&self, data_key: Option<String>) -> String {
+        (**self).template(data_key)
+    }
+    fn template_into(&self, data_key: Option<String>, out: &mut String) {
+        (**self).template_into(data_key, out)
+    }
 }
 
-impl AddToSharedResource for HdClusteringPlot {
-    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
-        self.spatial_plot_props
-            .add_to_shared_resource(shared_resource);
+impl<T: HtmlTemplate + ?Sized> HtmlTemplate for std::sync::Arc<T> {
+    fn template(&self, data_key: Option<String>) -> String {
+        (**self).template(data_key)
+    }
+    fn template_into(&self, data_key: Option<String>, out: &mut String) {
+        (**self).template_into(data_key, out)
     }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-pub struct DifferentialExpressionTable {
-    pub table: Value,
+impl<T: ReactComponent> ReactComponent for Box<T> {
+    fn component_name() -> &'static str {
+        T::component_name()
+    }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-#[serde(rename_all = "camelCase")]
-pub struct HdEndToEndAlignment {
-    pub tissue_image: String,
-    pub tissue_image_title: String,
-    pub tissue_css_transform: Vec<f64>,
-    pub display_height: u32,
-    pub display_width: u32,
-    pub umi_legend_images: Vec<HdEndToEndAlignmentUmiLegendImage>,
-    pub grayscale_umi_image: String,
-    pub umi_image_title: String,
-    pub umi_css_transform: Vec<f64>,
-    pub tissue_mask_image: String,
-    pub initial_zoom_pan: Option<InitialZoomPan>,
+impl<T: ReactComponent> ReactComponent for &T {
+    fn component_name() -> &'static str {
+        T::component_name()
+    }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-#[serde(rename_all = "camelCase")]
-pub struct HdEndToEndAlignmentUmiLegendImage {
-    pub colormap: String,
-    pub legend_image: String,
+impl HtmlTemplate for Box<dyn HtmlTemplate> {
+    fn template(&self, data_key: Option<String>) -> String {
+        (**self).template(data_key)
+    }
+    fn template_into(&self, data_key: Option<String>, out: &mut String) {
+        (**self).template_into(data_key, out)
+    }
 }
 
-impl AddToSharedResource for HdEndToEndAlignmentUmiLegendImage {
-    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
-        self.legend_image = shared_resource.insert(Value::String(self.legend_image.clone()));
+impl HtmlTemplate for &dyn HtmlTemplate {
+    fn template(&self, data_key: Option<String>) -> String {
+        (**self).template(data_key)
+    }
+    fn template_into(&self, data_key: Option<String>, out: &mut String) {
+        (**self).template_into(data_key, out)
     }
 }
 
-impl AddToSharedResource for HdEndToEndAlignment {
-    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
-        self.tissue_image = shared_resource.insert(Value::String(self.tissue_image.clone()));
-        self.grayscale_umi_image =
-            shared_resource.insert(Value::String(self.grayscale_umi_image.clone()));
-        for umi_image in &mut self.umi_legend_images {
-            umi_image.add_to_shared_resource(shared_resource);
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+/// Overall QC status of the summary along with a chip for each section,
+/// shown as a banner at the top of the page
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QcBanner {
+    pub overall: Threshold,
+    pub sections: Vec<(String, Threshold)>,
+}
+
+impl QcBanner {
+    /// Status of each section from the thresholds of its metrics, which is
+    /// the worst threshold of the section. Metrics without a threshold pass.
+    /// The overall status is FAIL if any section or alert is an error, else
+    /// WARN if any of them is a warning, else PASS. Info alerts are ignored.
+    pub fn from_alerts_and_metrics<'a>(
+        alerts: &Alerts,
+        metrics: impl IntoIterator<Item = (&'a str, Option<Threshold>)>,
+    ) -> Self {
+        let mut sections: Vec<(String, Threshold)> = Vec::new();
+        for (section, threshold) in metrics {
+            let threshold = threshold.unwrap_or(Threshold::Pass);
+            match sections.iter_mut().find(|(name, _)| name == section) {
+                Some((_, status)) => *status = status.clone().worst(threshold),
+                None => sections.push((section.to_string(), threshold)),
+            }
         }
+        let overall = alerts
+            .alerts
+            .iter()
+            .filter_map(|alert| match alert.level {
+                AlertLevel::Error => Some(Threshold::Error),
+                AlertLevel::Warn => Some(Threshold::Warn),
+                AlertLevel::Info => None,
+            })
+            .chain(sections.iter().map(|(_, status)| status.clone()))
+            .fold(Threshold::Pass, Threshold::worst);
+        QcBanner { overall, sections }
     }
 }
 
-// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
-/// MultiLayerImages
-#[derive(Debug, Serialize, Deserialize)]
-pub struct InitialFocus {
-    pub x: i32,
-    pub y: i32,
-    pub width: i32,
-    pub height: i32,
+/// Data key of the `QcBanner` added by `SinglePageHtml::with_qc_banner()`
+pub const QC_BANNER_KEY: &str = "_qc_banner";
+
+/// Content with a `QcBanner` above it. The content is flattened, so its data
+/// keys are unchanged and the banner is under `QC_BANNER_KEY`.
+#[derive(Debug, Clone, Serialize)]
+pub struct WithQcBanner<P> {
+    #[serde(rename = "_qc_banner")]
+    pub qc_banner: QcBanner,
+    #[serde(flatten)]
+    pub content: P,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct LabeledImage {
-    pub label: Option<String>,
-    pub color: Option<String>,
-    pub image: String,
-    pub css_transform: Option<Vec<f64>>,
+impl<P: HtmlTemplate> HtmlTemplate for WithQcBanner<P> {
+    fn template(&self, data_key: Option<String>) -> String {
+        format!(
+            "{}\n{}",
+            self.qc_banner
+                .template(Some(join_data_key(&data_key, QC_BANNER_KEY))),
+            self.content.template(data_key)
+        )
+    }
 }
 
-impl AddToSharedResource for LabeledImage {
-    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
-        self.image = shared_resource.insert(Value::String(self.image.clone()));
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+/// Data key of the alert groups of `SinglePageHtml`
+pub const ALERT_GROUPS_KEY: &str = "_alert_groups";
+
+/// The alerts routed to a group with `Alert::in_group()`, shown like the
+/// alerts at the top of the page wherever the `AlertGroupSlot` of the group
+/// is in the content
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertGroup {
+    pub name: String,
+    #[serde(flatten)]
+    pub alerts: Alerts,
+}
+
+impl AlertGroup {
+    pub fn new(name: impl Into<String>) -> Self {
+        AlertGroup {
+            name: name.into(),
+            alerts: Alerts::default(),
+        }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Layer {
+/// Data key of the group in `SinglePageHtml`
+pub fn alert_group_data_key(name: &str) -> String {
+    format!("{ALERT_GROUPS_KEY}.{name}")
+}
+
+/// Placement of an `AlertGroup` in the content, e.g. on the tab of a
+/// library. The group is looked up by name in the data of the page, so the
+/// slot can be anywhere in the content, and nothing is shown if the group has
+/// no alerts. The name is used in a data key, so it should not contain `.`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AlertGroupSlot {
     pub name: String,
-    pub images: Vec<LabeledImage>,
 }
 
-impl AddToSharedResource for Layer {
-    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
-        self.images
-            .iter_mut()
-            .for_each(|labelled_image| labelled_image.add_to_shared_resource(shared_resource));
+impl AlertGroupSlot {
+    pub fn new(name: impl Into<String>) -> Self {
+        AlertGroupSlot { name: name.into() }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct MultiLayerImages {
-    pub focus: InitialFocus,
-    pub layers: Vec<Layer>,
-    pub full_screen: bool,
+impl HtmlTemplate for AlertGroupSlot {
+    fn template(&self, _: Option<String>) -> String {
+        AlertGroup::new(self.name.clone()).template(Some(alert_group_data_key(&self.name)))
+    }
 }
 
-impl AddToSharedResource for MultiLayerImages {
-    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
-        self.layers
-            .iter_mut()
-            .for_each(|layer| layer.add_to_shared_resource(shared_resource));
+/// Data keys of the `AlertGroupSlot`s in the template
+pub(crate) fn alert_group_slot_keys(template: &str) -> Vec<String> {
+    let prefix = format!(r#"data-key="{ALERT_GROUPS_KEY}."#);
+    template
+        .match_indices(&prefix)
+        .filter_map(|(start, _)| {
+            let key = &template[start + r#"data-key=""#.len()..];
+            key.split_once('"').map(|(key, _)| key.to_string())
+        })
+        .collect()
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+/// Data key of the footer of `SinglePageHtml`
+pub const FOOTER_KEY: &str = "_footer";
+
+/// Standard footer below the content of the page, added with
+/// `SinglePageHtml::footer()`: support links, a note such as the license,
+/// and optionally the generation info. The pipeline of the generation info is
+/// the one of the nav bar.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Footer {
+    pub links: Vec<LinkedText>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub show_generation_info: bool,
+    /// RFC 3339 time at which the summary was generated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generated_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pipeline_version: Option<String>,
+}
+
+impl Footer {
+    pub fn new() -> Self {
+        Footer::default()
+    }
+    pub fn link(mut self, text: impl Into<String>, link: impl Into<String>) -> Self {
+        self.links.push(LinkedText {
+            link: link.into(),
+            text: text.into(),
+        });
+        self
+    }
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+    /// Show when and by which version of the pipeline the summary was
+    /// generated. With the `time` feature, the time defaults to the current
+    /// time, otherwise set it with `generated_at()`.
+    pub fn with_generation_info(mut self, pipeline_version: impl Into<String>) -> Self {
+        self.show_generation_info = true;
+        self.pipeline_version = Some(pipeline_version.into());
+        #[cfg(feature = "time")]
+        self.generated_at
+            .get_or_insert_with(|| crate::timestamp::Timestamp::now(Default::default()).rfc3339());
+        self
+    }
+    /// Time at which the summary was generated, e.g. a fixed time so that
+    /// the html is reproducible
+    pub fn generated_at(mut self, rfc3339: impl Into<String>) -> Self {
+        self.generated_at = Some(rfc3339.into());
+        self
+    }
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+/// Error shown in place of a section which failed to compute
+#[derive(Serialize, Clone)]
+pub struct SectionError {
+    pub error: String,
+    /// Props of the `InlineAlert` rendered in the template
+    #[serde(flatten)]
+    alert: InlineTextAlert,
+}
+
+impl SectionError {
+    pub fn new(error: impl ToString) -> Self {
+        let error = error.to_string();
+        SectionError {
+            alert: InlineTextAlert::danger(escape_html(&error)),
+            error,
+        }
+    }
+}
+
+impl HtmlTemplate for SectionError {
+    fn template(&self, data_key: Option<String>) -> String {
+        self.alert.template(data_key)
+    }
+}
+
+impl AddToSharedResource for SectionError {
+    fn add_to_shared_resource(&mut self, _shared_resource: &mut SharedResources) {}
+}
+
+/// A section which is either the component or what to show in its place,
+/// by default an error message, so that a partial failure shows up as an
+/// error panel in place of the section. Either side serializes and renders
+/// exactly like the component it holds.
+#[derive(Serialize, Clone)]
+#[serde(untagged)]
+pub enum SectionResult<T, E = SectionError> {
+    Ok(T),
+    Err(E),
+}
+
+impl<T, E> SectionResult<T, E> {
+    pub fn is_ok(&self) -> bool {
+        matches!(self, SectionResult::Ok(_))
+    }
+}
+
+impl<T> SectionResult<T> {
+    pub fn err(error: impl ToString) -> Self {
+        SectionResult::Err(SectionError::new(error))
+    }
+}
+
+impl<T> From<Result<T, Error>> for SectionResult<T> {
+    fn from(result: Result<T, Error>) -> Self {
+        match result {
+            Ok(inner) => SectionResult::Ok(inner),
+            // Include the context chain in the message
+            Err(e) => SectionResult::err(format!("{e:#}")),
+        }
+    }
+}
+
+impl<T: HtmlTemplate, E: HtmlTemplate> HtmlTemplate for SectionResult<T, E> {
+    fn template(&self, data_key: Option<String>) -> String {
+        match self {
+            SectionResult::Ok(inner) => inner.template(data_key),
+            SectionResult::Err(err) => err.template(data_key),
+        }
+    }
+}
+
+impl<T: AddToSharedResource, E: AddToSharedResource> AddToSharedResource for SectionResult<T, E> {
+    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
+        match self {
+            SectionResult::Ok(inner) => inner.add_to_shared_resource(shared_resource),
+            SectionResult::Err(err) => err.add_to_shared_resource(shared_resource),
+        }
+    }
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+/// Reason shown by a `MaybeSection` created from `None`
+pub const DEFAULT_MISSING_REASON: &str = "This analysis was not run for this sample.";
+
+/// Placeholder shown in place of a section whose data is absent
+#[derive(Serialize, Clone)]
+pub struct MissingSection {
+    pub missing_reason: String,
+    /// Props of the `InlineAlert` rendered in the template
+    #[serde(flatten)]
+    alert: InlineTextAlert,
+}
+
+impl MissingSection {
+    pub fn new(reason: impl ToString) -> Self {
+        let missing_reason = reason.to_string();
+        MissingSection {
+            alert: InlineTextAlert::secondary(escape_html(&missing_reason)),
+            missing_reason,
+        }
+    }
+}
+
+impl HtmlTemplate for MissingSection {
+    fn template(&self, data_key: Option<String>) -> String {
+        self.alert.template(data_key)
+    }
+}
+
+impl AddToSharedResource for MissingSection {
+    fn add_to_shared_resource(&mut self, _shared_resource: &mut SharedResources) {}
+}
+
+/// A section which is either the component or the reason it is absent, e.g.
+/// an optional analysis which did not run. Unlike an `Option<T>`, which drops
+/// the section, the reason is shown in its place.
+pub type MaybeSection<T> = SectionResult<T, MissingSection>;
+
+impl<T> SectionResult<T, MissingSection> {
+    pub fn present(inner: T) -> Self {
+        SectionResult::Ok(inner)
+    }
+    pub fn absent(reason: impl ToString) -> Self {
+        SectionResult::Err(MissingSection::new(reason))
+    }
+}
+
+impl<T> From<Option<T>> for MaybeSection<T> {
+    fn from(option: Option<T>) -> Self {
+        match option {
+            Some(inner) => MaybeSection::present(inner),
+            None => MaybeSection::absent(DEFAULT_MISSING_REASON),
+        }
+    }
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+/// Icon of an `EmptyState` created using `EmptyState::new()`
+pub const DEFAULT_EMPTY_STATE_ICON: &str = "inbox";
+/// Title of an `EmptyState` created using `EmptyState::new()`
+pub const DEFAULT_EMPTY_STATE_TITLE: &str = "No data";
+
+/// Shown in place of a table or a plot which has nothing to show, instead of
+/// an empty frame
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EmptyState {
+    /// Name of the icon shown above the title
+    pub icon: String,
+    pub title: String,
+    pub message: String,
+}
+
+impl EmptyState {
+    pub fn new(message: impl ToString) -> Self {
+        EmptyState {
+            icon: DEFAULT_EMPTY_STATE_ICON.to_string(),
+            title: DEFAULT_EMPTY_STATE_TITLE.to_string(),
+            message: message.to_string(),
+        }
+    }
+    pub fn icon(mut self, icon: impl ToString) -> Self {
+        self.icon = icon.to_string();
+        self
+    }
+    pub fn title(mut self, title: impl ToString) -> Self {
+        self.title = title.to_string();
+        self
+    }
+}
+
+/// The component, or an `EmptyState` with the message in its place if
+/// `is_empty` is true
+pub fn with_empty_state<T>(
+    component: T,
+    is_empty: bool,
+    message: impl ToString,
+) -> SectionResult<T, EmptyState> {
+    if is_empty {
+        SectionResult::Err(EmptyState::new(message))
+    } else {
+        SectionResult::Ok(component)
+    }
+}
+
+impl GenericTable {
+    /// True if the table has no rows
+    pub fn is_empty(&self) -> bool {
+        match self.rich_rows {
+            Some(ref rich_rows) => rich_rows.is_empty(),
+            None => self.rows.is_empty(),
+        }
+    }
+
+    /// The table, or an `EmptyState` with the message if it has no rows
+    pub fn or_empty_state(self, message: impl ToString) -> SectionResult<Self, EmptyState> {
+        let is_empty = self.is_empty();
+        with_empty_state(self, is_empty, message)
+    }
+}
+
+impl PlotlyChart {
+    /// True if the chart has no traces, or if none of its traces has a
+    /// non-empty array. Arrays moved into the shared resources count as
+    /// non-empty.
+    pub fn is_empty(&self) -> bool {
+        !self.data.iter().any(|trace| {
+            trace.as_object().is_some_and(|fields| {
+                fields.values().any(|value| match value {
+                    Value::Array(values) => !values.is_empty(),
+                    Value::String(s) => s.starts_with(crate::RESOURCES_PREFIX),
+                    _ => false,
+                })
+            })
+        })
+    }
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+/// A tiny line chart without axes, e.g. the quality per sequencing cycle,
+/// which is cheap enough to show in every row of a table. The values are
+/// serialized as a flat list of numbers.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Sparkline {
+    #[serde(deserialize_with = "deserialize_sparkline_values")]
+    values: Vec<f64>,
+    /// Css color of the line. The frontend picks a color if this is None.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// Size in pixels. The frontend sizes it to the text if this is None.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+}
+
+impl Sparkline {
+    /// Fails if there are no values or if any value is not finite
+    pub fn new(values: Vec<f64>) -> Result<Self, Error> {
+        check_sparkline_values(&values)?;
+        Ok(Sparkline {
+            values,
+            color: None,
+            width: None,
+            height: None,
+        })
+    }
+    pub fn color(mut self, color: impl ToString) -> Self {
+        self.color = Some(color.to_string());
+        self
+    }
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.width = Some(width);
+        self.height = Some(height);
+        self
+    }
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+}
+
+/// The values are finite, both when constructed and when deserialized
+impl Eq for Sparkline {}
+
+fn check_sparkline_values(values: &[f64]) -> Result<(), Error> {
+    if values.is_empty() {
+        bail!("A sparkline needs at least one value");
+    }
+    if let Some(i) = values.iter().position(|v| !v.is_finite()) {
+        bail!(
+            "Value {i} of the sparkline is {}, expected a finite number",
+            values[i]
+        );
+    }
+    Ok(())
+}
+
+fn deserialize_sparkline_values<'de, D>(deserializer: D) -> Result<Vec<f64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let values = Vec::<f64>::deserialize(deserializer)?;
+    check_sparkline_values(&values).map_err(serde::de::Error::custom)?;
+    Ok(values)
+}
+
+/// A single horizontal bar filled up to a fraction, e.g. the fraction of
+/// reads in cells
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FractionBar {
+    /// Between 0 and 1
+    #[serde(deserialize_with = "deserialize_fraction")]
+    fraction: f64,
+    /// Text shown next to the bar. The frontend shows the percentage if this
+    /// is None.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Colors the bar like a `HeroMetric` with the threshold
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<Threshold>,
+}
+
+impl FractionBar {
+    /// Fails if the fraction is not within [0, 1]
+    pub fn new(fraction: f64) -> Result<Self, Error> {
+        check_fraction(fraction)?;
+        Ok(FractionBar {
+            fraction,
+            label: None,
+            threshold: None,
+        })
+    }
+    pub fn label(mut self, label: impl ToString) -> Self {
+        self.label = Some(label.to_string());
+        self
+    }
+    pub fn threshold(mut self, threshold: Threshold) -> Self {
+        self.threshold = Some(threshold);
+        self
+    }
+    pub fn fraction(&self) -> f64 {
+        self.fraction
+    }
+    /// The label, or the fraction as a percentage
+    fn text(&self) -> String {
+        self.label
+            .clone()
+            .unwrap_or_else(|| format!("{:.1}%", self.fraction * 100.0))
+    }
+}
+
+/// The fraction is within [0, 1], both when constructed and when deserialized
+impl Eq for FractionBar {}
+
+fn check_fraction(fraction: f64) -> Result<(), Error> {
+    if !(0.0..=1.0).contains(&fraction) {
+        bail!("The fraction in a fraction bar should be between 0 and 1, found {fraction}");
+    }
+    Ok(())
+}
+
+fn deserialize_fraction<'de, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let fraction = f64::deserialize(deserializer)?;
+    check_fraction(fraction).map_err(serde::de::Error::custom)?;
+    Ok(fraction)
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+/// A category in a `CategoricalLegend`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LegendEntry {
+    pub label: String,
+    /// Css color of the swatch
+    pub color: String,
+    /// Number of items in the category, e.g. cells in a cluster
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u64>,
+    /// The category is initially toggled off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hidden: Option<bool>,
+}
+
+impl LegendEntry {
+    pub fn new(label: impl ToString, color: impl ToString) -> Self {
+        LegendEntry {
+            label: label.to_string(),
+            color: color.to_string(),
+            count: None,
+            hidden: None,
+        }
+    }
+    pub fn count(mut self, count: u64) -> Self {
+        self.count = Some(count);
+        self
+    }
+    pub fn hidden(mut self) -> Self {
+        self.hidden = Some(true);
+        self
+    }
+}
+
+/// Legend of the categories of a plot or of layered images, with a color
+/// swatch, the label and optionally the count of each category. See
+/// `CategoricalLegend::from_palette()` for the colors of the categorical
+/// palette.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CategoricalLegend {
+    pub entries: Vec<LegendEntry>,
+}
+
+impl CategoricalLegend {
+    /// Legend from the (label, color) of each category
+    pub fn new(entries: Vec<(String, String)>) -> Self {
+        CategoricalLegend {
+            entries: entries
+                .into_iter()
+                .map(|(label, color)| LegendEntry::new(label, color))
+                .collect(),
+        }
+    }
+
+    /// Set the counts of the categories, in the order of the entries
+    pub fn counts(mut self, counts: impl IntoIterator<Item = u64>) -> Self {
+        for (entry, count) in self.entries.iter_mut().zip(counts) {
+            entry.count = Some(count);
+        }
+        self
+    }
+
+    /// Table with a swatch and a label per category, and the counts if any
+    /// category has one, for where the legend component is not available
+    pub fn to_generic_table(&self) -> GenericTable {
+        let with_counts = self.entries.iter().any(|entry| entry.count.is_some());
+        let rows: Vec<RichTableRow> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let mut row = vec![
+                    RichCell::Html(format!(
+                        r#"<span style="display:inline-block;width:1em;height:1em;background-color:{}"></span>"#,
+                        escape_html(&entry.color)
+                    )),
+                    RichCell::from(entry.label.as_str()),
+                ];
+                if with_counts {
+                    row.push(RichCell::from(
+                        entry.count.map(|count| count.to_string()).unwrap_or_default(),
+                    ));
+                }
+                RichTableRow(row)
+            })
+            .collect();
+        let mut header = vec![String::new(), "Category".to_string()];
+        if with_counts {
+            header.push("Count".to_string());
+        }
+        GenericTable::from_rich_rows(rows, Some(header))
+    }
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+// Ids of the sections generated by `#[html(section_ids)]`, so that in-page
+// links can target them
+
+/// Start of the wrapper div of a field with a section id in a derived template.
+/// The `data-section` attribute tells these divs apart from other html with
+/// ids, e.g. in an `HtmlFragment`, which `dedup_section_ids()` leaves alone.
+const SECTION_ID_DIV: &str = r#"<div class="col" data-section id=""#;
+
+/// A valid html id from the data key, e.g. `tabs.tab_data[0].cells` becomes
+/// `tabs-tab_data-0-cells`. Ids which do not start with a letter get a
+/// `section-` prefix.
+pub fn section_id(data_key: &str) -> String {
+    let mut id = String::with_capacity(data_key.len());
+    for c in data_key.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            id.push(c);
+        } else if !id.is_empty() && !id.ends_with('-') {
+            id.push('-');
+        }
+    }
+    let id = id.trim_end_matches('-');
+    if id.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        id.to_string()
+    } else if id.is_empty() {
+        "section".to_string()
+    } else {
+        format!("section-{id}")
+    }
+}
+
+/// Opening wrapper div of the field at `data_key`, with its section id
+pub fn section_div(data_key: &str) -> String {
+    format!("{SECTION_ID_DIV}{}\">\n", section_id(data_key))
+}
+
+/// Append `-2`, `-3` etc. to the repeats of a section id in the template, in
+/// the order in which they appear. Repeats show up when a component renders
+/// its children more than once with the same data key. Only the divs of
+/// `section_div()` are changed.
+pub fn dedup_section_ids(template: &str) -> Cow<'_, str> {
+    let mut seen = HashSet::new();
+    let mut repeated = false;
+    let mut parts = template.split(SECTION_ID_DIV);
+    let mut deduped = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        deduped.push_str(SECTION_ID_DIV);
+        let (id, rest) = part.split_at(part.find('"').unwrap_or(part.len()));
+        let mut unique = id.to_string();
+        let mut n = 2;
+        while !seen.insert(unique.clone()) {
+            repeated = true;
+            unique = format!("{id}-{n}");
+            n += 1;
+        }
+        deduped.push_str(&unique);
+        deduped.push_str(rest);
+    }
+    if repeated {
+        Cow::Owned(deduped)
+    } else {
+        Cow::Borrowed(template)
+    }
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+// A unique marker for keys in the Tabs divs. This will be replaced
+// with the correct key when building the template.
+const TAB_MARKER: &str = "__AUbkUE__TAB__WhcSw=__";
+
+/// Each tab is defined by a title and an element
+///
+/// The event keys and the `data-id` of the tabs are namespaced by the data
+/// key of the `Tabs`, so that tabs can be nested inside other tabs. This
+/// changes the template compared to older versions, which always used
+/// `tab_{i}` and `main-tabs`. Use [`Tabs::legacy`] to generate the old
+/// format for a single level of tabs.
+/// TODO: Support deriving tabs from a struct
+#[derive(Serialize, Default)]
+pub struct Tabs {
+    tab_data: Vec<Value>,
+    titles: Vec<String>,
+    #[serde(skip)]
+    elements: Vec<KeyedTemplate>,
+    #[serde(skip)]
+    legacy_keys: bool,
+}
+
+impl Tabs {
+    pub fn new() -> Self {
+        Tabs::default()
+    }
+    /// Tabs using the `tab_{i}` event keys and the `main-tabs` id. These
+    /// collide when the tabs are nested, so they should only be used when
+    /// the template has to match the one generated by older versions.
+    pub fn legacy() -> Self {
+        Tabs {
+            legacy_keys: true,
+            ..Default::default()
+        }
+    }
+    pub fn push<T: HtmlTemplate + Serialize>(&mut self, tab_title: impl Into<String>, element: T) {
+        self.tab_data.push(serde_json::to_value(&element).unwrap());
+        self.elements.push(KeyedTemplate::new(&element, TAB_MARKER));
+        self.titles.push(tab_title.into());
+    }
+    pub fn tab<T: HtmlTemplate + Serialize>(
+        mut self,
+        tab_title: impl Into<String>,
+        element: T,
+    ) -> Self {
+        self.push(tab_title, element);
+        self
+    }
+    pub fn titles(&self) -> &[String] {
+        &self.titles
+    }
+}
+
+impl HtmlTemplate for Tabs {
+    fn template(&self, data_key: Option<String>) -> String {
+        let mut template = String::new();
+        self.template_into(data_key, &mut template);
+        template
+    }
+    fn template_into(&self, data_key: Option<String>, out: &mut String) {
+        let base_data_key = join_data_key(&data_key, "tab_data");
+        let (event_key_prefix, data_id) = if self.legacy_keys {
+            ("tab".to_string(), "main-tabs".to_string())
+        } else {
+            (
+                format!("tab_{base_data_key}"),
+                format!("tabs_{base_data_key}"),
+            )
+        };
+        writeln!(
+            out,
+            r#"<div class="tabs-wrapper" data-default-active-key="{event_key_prefix}_0" data-id="{data_id}">"#
+        )
+        .unwrap();
+        for (i, (element, title)) in std::iter::zip(&self.elements, &self.titles).enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            writeln!(
+                out,
+                r#"<div class="tab-wrapper" data-event-key="{event_key_prefix}_{i}" data-title="{title}">"#
+            )
+            .unwrap();
+            element.render_into(&format!("{base_data_key}[{i}]"), out);
+            out.push_str("\n</div>");
+        }
+        out.push_str("\n</div>");
+    }
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+/// A text with a hyperlink
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkedText {
+    pub link: String,
+    pub text: String,
+}
+
+impl LinkedText {
+    pub fn html(&self) -> String {
+        format!("<a href=\"{}\">{}</a>", self.link, self.text)
+    }
+}
+
+impl HtmlTemplate for LinkedText {
+    fn template(&self, _: Option<String>) -> String {
+        self.html()
+    }
+}
+
+/// Attribute holding the target data key of a `ComponentLink`
+const COMPONENT_LINK_ATTR: &str = "data-link-key";
+
+/// A text which scrolls to another component of the page when clicked, e.g.
+/// "see the barcode rank plot" in a help text. The link targets the id of the
+/// section with the data key, see `section_id()`, so the target should be a
+/// section with `#[html(section_ids)]`. Links whose target has no section
+/// id in the template are rendered as plain text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ComponentLink {
+    pub text: String,
+    pub data_key: String,
+}
+
+impl ComponentLink {
+    pub fn to_data_key(text: impl Into<String>, data_key: impl Into<String>) -> Self {
+        ComponentLink {
+            text: text.into(),
+            data_key: data_key.into(),
+        }
+    }
+    /// The link, which can also be embedded in the html of a help text. The
+    /// text and the data key are escaped.
+    pub fn html(&self) -> String {
+        format!(
+            r##"<a href="#{}" {COMPONENT_LINK_ATTR}="{}">{}</a>"##,
+            section_id(&self.data_key),
+            escape_html(&self.data_key),
+            escape_html(&self.text)
+        )
+    }
+}
+
+impl HtmlTemplate for ComponentLink {
+    fn template(&self, _: Option<String>) -> String {
+        self.html()
+    }
+}
+
+fn component_link_regex() -> regex::Regex {
+    regex::Regex::new(&format!(
+        r##"<a href="#[^"]*" {COMPONENT_LINK_ATTR}="([^"]*)">(.*?)</a>"##
+    ))
+    .unwrap()
+}
+
+/// Target data keys of the `ComponentLink`s in the html
+pub(crate) fn component_link_targets(html: &str) -> Vec<String> {
+    if !html.contains(COMPONENT_LINK_ATTR) {
+        return Vec::new();
+    }
+    component_link_regex()
+        .captures_iter(html)
+        .map(|cap| unescape_html(&cap[1]))
+        .collect()
+}
+
+/// Replace the `ComponentLink`s to the data keys by their text
+pub(crate) fn downgrade_component_links<'a>(html: &'a str, data_keys: &[String]) -> Cow<'a, str> {
+    let is_downgraded = |key: &String| data_keys.contains(key);
+    if !component_link_targets(html).iter().any(is_downgraded) {
+        return Cow::Borrowed(html);
+    }
+    component_link_regex().replace_all(html, |cap: &regex::Captures<'_>| {
+        if is_downgraded(&unescape_html(&cap[1])) {
+            cap[2].to_string()
+        } else {
+            cap[0].to_string()
+        }
+    })
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+/// HdClusteringPlot
+
+#[derive(Serialize, Deserialize)]
+pub struct HdClusteringSingleClusterData {
+    pub cluster_name: String,
+    pub hex_color: String,
+    pub spatial_plot: String,
+    pub umap_plot: String,
+}
+
+impl HdClusteringSingleClusterData {
+    pub fn new(
+        cluster_name: impl Into<String>,
+        hex_color: impl Into<String>,
+        spatial_plot: String,
+        umap_plot: String,
+    ) -> Self {
+        check_if_strict(&spatial_plot);
+        check_if_strict(&umap_plot);
+        HdClusteringSingleClusterData {
+            cluster_name: cluster_name.into(),
+            hex_color: hex_color.into(),
+            spatial_plot,
+            umap_plot,
+        }
+    }
+    /// Similar to `new`, but returns an error if either of the plots is not a
+    /// valid base64 encoded data URI
+    pub fn try_new(
+        cluster_name: impl Into<String>,
+        hex_color: impl Into<String>,
+        spatial_plot: String,
+        umap_plot: String,
+    ) -> Result<Self, DataUriError> {
+        validate_data_uri(&spatial_plot)?;
+        validate_data_uri(&umap_plot)?;
+        Ok(HdClusteringSingleClusterData::new(
+            cluster_name,
+            hex_color,
+            spatial_plot,
+            umap_plot,
+        ))
+    }
+}
+
+impl AddToSharedResource for HdClusteringSingleClusterData {
+    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
+        self.spatial_plot = shared_resource.insert(Value::String(self.spatial_plot.clone()));
+        self.umap_plot = shared_resource.insert(Value::String(self.umap_plot.clone()));
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct HdClusteringSpatialPlotProps {
+    pub title: String,
+    pub tissue_image: String,
+    pub tissue_css_transform: Vec<f64>,
+    pub spot_css_transform: Vec<f64>,
+    pub width: u32,
+    pub height: u32,
+    pub initial_zoom_pan: InitialZoomPan,
+}
+
+impl AddToSharedResource for HdClusteringSpatialPlotProps {
+    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
+        self.tissue_image = shared_resource.insert(Value::String(self.tissue_image.clone()));
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct HdClusteringUmapPlotProps {
+    pub title: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct HdClusteringPlot {
+    pub spatial_plot_props: HdClusteringSpatialPlotProps,
+    pub umap_plot_props: HdClusteringUmapPlotProps,
+    pub clusters: Vec<HdClusteringSingleClusterData>,
+}
+
+impl AddToSharedResource for HdClusteringPlot {
+    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
+        self.spatial_plot_props
+            .add_to_shared_resource(shared_resource);
+        for cluster in &mut self.clusters {
+            cluster.add_to_shared_resource(shared_resource);
+        }
+    }
+}
+
+impl HdClusteringPlot {
+    pub fn new(
+        spatial_plot_props: HdClusteringSpatialPlotProps,
+        umap_plot_props: HdClusteringUmapPlotProps,
+    ) -> Self {
+        HdClusteringPlot {
+            spatial_plot_props,
+            umap_plot_props,
+            clusters: Vec::new(),
+        }
+    }
+
+    /// Add the cluster, moving its plots into the shared resources
+    pub fn push_cluster(
+        &mut self,
+        cluster: HdClusteringSingleClusterData,
+        shared_resource: &mut SharedResources,
+    ) {
+        self.clusters
+            .push(cluster.with_shared_resource(shared_resource));
+    }
+
+    /// Builder version of `push_cluster`
+    pub fn with_cluster(
+        mut self,
+        cluster: HdClusteringSingleClusterData,
+        shared_resource: &mut SharedResources,
+    ) -> Self {
+        self.push_cluster(cluster, shared_resource);
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DifferentialExpressionTable {
+    pub table: Value,
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+/// Consecutive columns of a `GroupedHeaderTable` under a shared title, e.g.
+/// the columns of one cluster
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HeaderGroup {
+    pub title: String,
+    pub columns: Vec<String>,
+}
+
+impl HeaderGroup {
+    pub fn new(title: impl ToString, columns: Vec<String>) -> Self {
+        HeaderGroup {
+            title: title.to_string(),
+            columns,
+        }
+    }
+}
+
+/// Wide table with a two level header: the title of each group spans its
+/// columns, which have their own headers below it. Every row has one cell
+/// per column of all the groups.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GroupedHeaderTable {
+    pub groups: Vec<HeaderGroup>,
+    pub rows: Vec<TableRow>,
+}
+
+impl GroupedHeaderTable {
+    /// Fails if a row does not have as many cells as there are columns in
+    /// the groups
+    pub fn new(groups: Vec<HeaderGroup>, rows: Vec<Vec<String>>) -> Result<Self, Error> {
+        let table = GroupedHeaderTable {
+            groups,
+            rows: rows.into_iter().map(TableRow::from).collect(),
+        };
+        table.validate()?;
+        Ok(table)
+    }
+
+    /// Total number of columns in the groups
+    pub fn num_cols(&self) -> usize {
+        self.groups.iter().map(|group| group.columns.len()).sum()
+    }
+
+    /// Check that every row has as many cells as there are columns in the
+    /// groups. The error names the first row which does not.
+    pub fn validate(&self) -> Result<(), Error> {
+        let expected = self.num_cols();
+        if let Some((i, row)) = self
+            .rows
+            .iter()
+            .enumerate()
+            .find(|(_, row)| row.0.len() != expected)
+        {
+            bail!(
+                "Row {i} of the table has {} columns, expected {expected} columns as in the groups",
+                row.0.len()
+            );
+        }
+        Ok(())
+    }
+
+    /// Group the columns of the table. The grouping is a list of
+    /// `(title, number of columns)`, in the order of the columns, which
+    /// must cover all the columns of the header. The rich rows, pagination
+    /// and sticky header of the table are dropped.
+    pub fn from_generic_table(
+        table: GenericTable,
+        grouping: &[(&str, usize)],
+    ) -> Result<Self, Error> {
+        let table = table.into_plain();
+        let Some(header) = table.header else {
+            bail!("A table without a header cannot be grouped");
+        };
+        let grouped: usize = grouping.iter().map(|(_, n)| n).sum();
+        if grouped != header.len() {
+            bail!(
+                "The grouping covers {grouped} columns, but the table has {} columns",
+                header.len()
+            );
+        }
+        let mut columns = header.into_iter();
+        let groups = grouping
+            .iter()
+            .map(|&(title, n)| HeaderGroup::new(title, columns.by_ref().take(n).collect()))
+            .collect();
+        let table = GroupedHeaderTable {
+            groups,
+            rows: table.rows,
+        };
+        table.validate()?;
+        Ok(table)
+    }
+
+    /// Table with a single level header of `group / column`, for rendering
+    /// where grouped headers are not supported
+    pub fn to_generic_table(&self) -> GenericTable {
+        let header = self
+            .groups
+            .iter()
+            .flat_map(|group| {
+                group
+                    .columns
+                    .iter()
+                    .map(move |column| format!("{} / {column}", group.title))
+            })
+            .collect();
+        GenericTable {
+            header: Some(header),
+            rows: self.rows.clone(),
+            page_size: None,
+            sticky_header: None,
+            rich_rows: None,
+        }
+    }
+}
+
+impl From<GroupedHeaderTable> for GenericTable {
+    fn from(table: GroupedHeaderTable) -> Self {
+        table.to_generic_table()
+    }
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+/// Values of a column of a `ColumnarTable`. The numbers are serialized as a
+/// json array of numbers, where non-finite numbers are `null`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnValues {
+    Number(Vec<f64>),
+    Text(Vec<String>),
+}
+
+impl ColumnValues {
+    pub fn len(&self) -> usize {
+        match self {
+            ColumnValues::Number(values) => values.len(),
+            ColumnValues::Text(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The value in the row as text. Numbers use the shortest representation
+    /// which parses back to the same number.
+    pub fn text(&self, row: usize) -> String {
+        match self {
+            ColumnValues::Number(values) => values[row].to_string(),
+            ColumnValues::Text(values) => values[row].clone(),
+        }
+    }
+}
+
+impl From<Vec<f64>> for ColumnValues {
+    fn from(values: Vec<f64>) -> Self {
+        ColumnValues::Number(values)
+    }
+}
+
+impl From<Vec<String>> for ColumnValues {
+    fn from(values: Vec<String>) -> Self {
+        ColumnValues::Text(values)
+    }
+}
+
+/// Table stored and serialized by column, for large tables built from
+/// columnar data. The data is the header and an array per column, e.g.
+/// `{"header": ["Barcode", "UMIs"], "columns": [{"text": [..]}, {"number": [..]}]}`,
+/// which avoids a string per numeric cell and the transpose into rows.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ColumnarTable {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header: Option<Vec<String>>,
+    pub columns: Vec<ColumnValues>,
+    /// See `GenericTable::page_size`
+    #[serde(
+        default,
+        rename = "pageSize",
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_page_size"
+    )]
+    pub page_size: Option<usize>,
+    #[serde(
+        default,
+        rename = "stickyHeader",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sticky_header: Option<bool>,
+}
+
+impl ColumnarTable {
+    /// Table from named columns of numbers or text, which are moved into the
+    /// table as is. Fails if the columns do not have the same length.
+    pub fn from_columns_typed(
+        columns: impl IntoIterator<Item = (impl Into<String>, ColumnValues)>,
+    ) -> Result<Self, Error> {
+        let (header, columns): (Vec<String>, Vec<ColumnValues>) = columns
+            .into_iter()
+            .map(|(name, values)| (name.into(), values))
+            .unzip();
+        let table = ColumnarTable {
+            header: Some(header),
+            columns,
+            page_size: None,
+            sticky_header: None,
+        };
+        table.validate()?;
+        Ok(table)
+    }
+
+    /// Number of rows, which is the length of every column
+    pub fn num_rows(&self) -> usize {
+        self.columns.first().map_or(0, ColumnValues::len)
+    }
+
+    /// Check that the columns have the same length and match the header
+    pub fn validate(&self) -> Result<(), Error> {
+        if let Some(header) = &self.header {
+            if header.len() != self.columns.len() {
+                bail!(
+                    "The header has {} columns, but the table has {} columns",
+                    header.len(),
+                    self.columns.len()
+                );
+            }
+        }
+        let expected = self.num_rows();
+        if let Some((i, column)) = self
+            .columns
+            .iter()
+            .enumerate()
+            .find(|(_, column)| column.len() != expected)
+        {
+            bail!(
+                "Column {i} of the table has {} rows, expected {expected} rows as in column 0",
+                column.len()
+            );
+        }
+        Ok(())
+    }
+
+    /// Table with the columns of the generic table, all as text so that the
+    /// conversion back is exact. The rich rows are converted to plain text as
+    /// in `GenericTable::into_plain()`.
+    pub fn from_generic_table(table: GenericTable) -> Result<Self, Error> {
+        let table = table.into_plain();
+        let rows: Vec<Vec<String>> = table.rows.into_iter().map(|row| row.0).collect();
+        check_num_cols(&rows, table.header.as_ref())?;
+        let num_cols = match (&table.header, rows.first()) {
+            (Some(header), _) => header.len(),
+            (None, Some(first)) => first.len(),
+            (None, None) => 0,
+        };
+        let mut columns = vec![Vec::with_capacity(rows.len()); num_cols];
+        for row in rows {
+            for (column, value) in columns.iter_mut().zip(row) {
+                column.push(value);
+            }
+        }
+        Ok(ColumnarTable {
+            header: table.header,
+            columns: columns.into_iter().map(ColumnValues::Text).collect(),
+            page_size: table.page_size,
+            sticky_header: table.sticky_header,
+        })
+    }
+
+    /// Table by row, for rendering where columnar tables are not supported
+    pub fn to_generic_table(&self) -> GenericTable {
+        GenericTable {
+            header: self.header.clone(),
+            rows: (0..self.num_rows())
+                .map(|row| TableRow(self.columns.iter().map(|c| c.text(row)).collect()))
+                .collect(),
+            page_size: self.page_size,
+            sticky_header: self.sticky_header,
+            rich_rows: None,
+        }
+    }
+}
+
+impl From<ColumnarTable> for GenericTable {
+    fn from(table: ColumnarTable) -> Self {
+        table.to_generic_table()
+    }
+}
+
+impl TryFrom<GenericTable> for ColumnarTable {
+    type Error = Error;
+    fn try_from(table: GenericTable) -> Result<Self, Error> {
+        ColumnarTable::from_generic_table(table)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HdEndToEndAlignment {
+    pub tissue_image: String,
+    pub tissue_image_title: String,
+    pub tissue_css_transform: Vec<f64>,
+    pub display_height: u32,
+    pub display_width: u32,
+    pub umi_legend_images: Vec<HdEndToEndAlignmentUmiLegendImage>,
+    pub grayscale_umi_image: String,
+    pub umi_image_title: String,
+    pub umi_css_transform: Vec<f64>,
+    pub tissue_mask_image: String,
+    pub initial_zoom_pan: Option<InitialZoomPan>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HdEndToEndAlignmentUmiLegendImage {
+    pub colormap: String,
+    pub legend_image: String,
+}
+
+impl HdEndToEndAlignmentUmiLegendImage {
+    pub fn new(colormap: impl Into<String>, legend_image: String) -> Self {
+        check_if_strict(&legend_image);
+        HdEndToEndAlignmentUmiLegendImage {
+            colormap: colormap.into(),
+            legend_image,
+        }
+    }
+    /// Similar to `new`, but returns an error if the legend image is not a
+    /// valid base64 encoded data URI
+    pub fn try_new(
+        colormap: impl Into<String>,
+        legend_image: String,
+    ) -> Result<Self, DataUriError> {
+        validate_data_uri(&legend_image)?;
+        Ok(HdEndToEndAlignmentUmiLegendImage::new(
+            colormap,
+            legend_image,
+        ))
+    }
+}
+
+impl AddToSharedResource for HdEndToEndAlignmentUmiLegendImage {
+    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
+        self.legend_image = shared_resource.insert(Value::String(self.legend_image.clone()));
+    }
+}
+
+impl AddToSharedResource for HdEndToEndAlignment {
+    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
+        self.tissue_image = shared_resource.insert(Value::String(self.tissue_image.clone()));
+        self.grayscale_umi_image =
+            shared_resource.insert(Value::String(self.grayscale_umi_image.clone()));
+        for umi_image in &mut self.umi_legend_images {
+            umi_image.add_to_shared_resource(shared_resource);
+        }
+    }
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+/// MultiLayerImages
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InitialFocus {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LabeledImage {
+    pub label: Option<String>,
+    pub color: Option<String>,
+    pub image: String,
+    pub css_transform: Option<Vec<f64>>,
+}
+
+impl LabeledImage {
+    pub fn new(image: String) -> Self {
+        check_if_strict(&image);
+        LabeledImage {
+            label: None,
+            color: None,
+            image,
+            css_transform: None,
+        }
+    }
+    /// Similar to `new`, but returns an error if the image is not a valid
+    /// base64 encoded data URI
+    pub fn try_new(image: String) -> Result<Self, DataUriError> {
+        validate_data_uri(&image)?;
+        Ok(LabeledImage::new(image))
+    }
+}
+
+impl AddToSharedResource for LabeledImage {
+    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
+        self.image = shared_resource.insert(Value::String(self.image.clone()));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Layer {
+    pub name: String,
+    pub images: Vec<LabeledImage>,
+}
+
+impl AddToSharedResource for Layer {
+    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
+        self.images
+            .iter_mut()
+            .for_each(|labelled_image| labelled_image.add_to_shared_resource(shared_resource));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultiLayerImages {
+    pub focus: InitialFocus,
+    pub layers: Vec<Layer>,
+    pub full_screen: bool,
+}
+
+impl AddToSharedResource for MultiLayerImages {
+    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
+        self.layers
+            .iter_mut()
+            .for_each(|layer| layer.add_to_shared_resource(shared_resource));
+    }
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+// Csv download link
+#[derive(Serialize, Deserialize)]
+pub struct DownloadableFile {
+    pub data: String,
+    pub filename: String,
+    pub text: String,
+    pub mime_type: String,
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+// Command line template
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandLine {
+    pub title: String,
+    pub data: Vec<TermDesc>,
+    pub show_dark_button_icon: bool,
+}
+
+impl CommandLine {
+    pub fn new(cmdline: &str) -> Result<Self, Error> {
+        Ok(Self {
+            title: "Command Line Arguments".to_string(),
+            data: vec![TermDesc("".to_string(), 
+                vec![format!("<span style='font-size: 18px;'><code><pre style='white-space: pre-wrap;'>{}</pre></code></span>", 
+                    cmdline)])],
+            show_dark_button_icon: true,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn check_eq_json(j1: &str, j2: &str) {
+        assert_eq!(
+            serde_json::from_str::<serde_json::value::Value>(j1).unwrap(),
+            serde_json::from_str::<serde_json::value::Value>(j2).unwrap()
+        );
+    }
+
+    fn test_json_roundtrip<T: Serialize + serde::de::DeserializeOwned>(json: &str) -> T {
+        let parsed: T = serde_json::from_str(json).unwrap();
+        let parsed_str = serde_json::to_string(&parsed).unwrap();
+        check_eq_json(&parsed_str, json);
+        parsed
+    }
+
+    fn metrics(n: usize) -> Vec<HeroMetric> {
+        (0..n)
+            .map(|i| HeroMetric::new(format!("Metric {i}"), i))
+            .collect()
+    }
+
+    #[test]
+    fn test_keyed_templates_match_formatting() {
+        let metric = HeroMetric::new("Number of cells", "3,487");
+        assert!(HeroMetric::template_is_keyed());
+        assert!(!Option::<HeroMetric>::template_is_keyed());
+        assert!(!Box::<dyn HtmlTemplate>::template_is_keyed());
+        assert_eq!(
+            metric.template(Some("cells".into())),
+            r#"<div data-key="cells" data-component="Metric"></div>"#
+        );
+
+        let elements = metrics(12);
+        let expected = elements
+            .iter()
+            .enumerate()
+            .map(|(i, inner)| {
+                DivWrapper::row(&DivWrapper::col(inner)).template(Some(format!("metrics[{i}]")))
+            })
+            .join("\n");
+        assert_eq!(elements.template(Some("metrics".into())), expected);
+        // Elements which are not keyed are rendered one by one
+        let optional: Vec<_> = elements.iter().cloned().map(Some).collect();
+        assert_eq!(optional.template(Some("metrics".into())), expected);
+
+        let grid = Grid::with_elements(elements.clone(), GridLayout::MaxCols(4));
+        let mut dyn_grid = DynGrid::new(GridLayout::MaxCols(4));
+        for element in elements {
+            // Render every element separately
+            dyn_grid.push(Some(element));
+        }
+        assert_eq!(
+            grid.template(Some("grid".into())),
+            dyn_grid.template(Some("grid".into()))
+        );
+    }
+
+    #[test]
+    fn test_mixed_vec_templates() {
+        let table = TableMetric {
+            rows: vec![("Number of reads".into(), "335,174,516".into())],
+            row_ids: Vec::new(),
+        };
+        let expected = [
+            r#"<div data-key="mixed[0]" data-component="Metric"></div>"#,
+            r#"<div data-key="mixed[1]" data-component="TableMetric"></div>"#,
+        ];
+        let boxed: Vec<Box<dyn HtmlTemplate>> = vec![
+            Box::new(HeroMetric::new("Number of cells", "3,487")),
+            Box::new(table.clone()),
+        ];
+        let shared: Vec<std::rc::Rc<dyn HtmlTemplate>> = vec![
+            std::rc::Rc::new(HeroMetric::new("Number of cells", "3,487")),
+            std::rc::Rc::new(table),
+        ];
+        for template in [
+            boxed.template(Some("mixed".into())),
+            shared.template(Some("mixed".into())),
+        ] {
+            for component in expected {
+                assert!(template.contains(component), "{template}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_large_vec_template_time() {
+        let elements = metrics(50_000);
+        let start = std::time::Instant::now();
+        let template = elements.template(Some("metrics".into()));
+        // Generous bound for a debug build on a slow machine, which would be
+        // exceeded if the template became quadratic
+        assert!(
+            start.elapsed() < std::time::Duration::from_secs(5),
+            "{:?}",
+            start.elapsed()
+        );
+        assert_eq!(
+            template.matches("data-component=\"Metric\"").count(),
+            50_000
+        );
+        assert!(template.ends_with(
+            "<div data-key=\"metrics[49999]\" data-component=\"Metric\"></div>\n</div>\n</div>"
+        ));
+    }
+
+    #[test]
+    fn test_hero_metric_without_comparison() {
+        // Json written before the comparison was added
+        let json = r#"{"name": "Number of cells", "metric": "3,487", "threshold": "pass"}"#;
+        let metric = test_json_roundtrip::<HeroMetric>(json);
+        assert_eq!(
+            metric,
+            HeroMetric::with_threshold("Number of cells", "3,487", Threshold::Pass)
+        );
+        assert_eq!(metric.comparison, None);
+    }
+
+    #[test]
+    fn test_raw_image_variants() {
+        let image = RawImage::new("data:image/png;base64,AAAA".into());
+        let json = serde_json::to_value(&image).unwrap();
+        assert!(json.get("variants").is_none());
+
+        let image = image.variants([
+            ImageVariant::new(3.0, "data:image/png;base64,CCCC".into()),
+            ImageVariant::new(2.0, "data:image/png;base64,BBBB".into()),
+            ImageVariant::new(f32::NAN, "data:image/png;base64,NNNN".into()),
+            ImageVariant::new(2.0, "data:image/png;base64,DDDD".into()),
+            ImageVariant::new(0.0, "data:image/png;base64,ZZZZ".into()),
+        ]);
+        assert_eq!(
+            serde_json::to_value(&image).unwrap()["variants"],
+            serde_json::json!([
+                {"scale": 2.0, "encoded_image": "data:image/png;base64,BBBB"},
+                {"scale": 3.0, "encoded_image": "data:image/png;base64,CCCC"},
+            ])
+        );
+        assert!(image.clone().variants([]).variants.is_none());
+
+        let mut resources = SharedResources::new();
+        let image = image.with_shared_resource(&mut resources);
+        assert_eq!(resources.0.len(), 3);
+        assert_eq!(
+            serde_json::to_value(&image).unwrap()["variants"],
+            serde_json::json!([
+                {"scale": 2.0, "encoded_image": "_resources_001"},
+                {"scale": 3.0, "encoded_image": "_resources_002"},
+            ])
+        );
+        assert_eq!(resources.0["002"], "data:image/png;base64,CCCC");
+    }
+
+    fn grouped_table_metric() -> GroupedTableMetric {
+        GroupedTableMetric::from_rows([
+            ("Sequencing", "Number of reads", "335,174,516"),
+            ("Cells", "Estimated number of cells", "3,487"),
+            ("Sequencing", "Valid barcodes", "97.3%"),
+        ])
+    }
+
+    #[test]
+    fn test_metric_helpers_locale() {
+        let metric = HeroMetric::from_value(
+            "Number of reads",
+            335174516.0,
+            MetricFormat::Count,
+            NumberLocale::EuComma,
+        )
+        .with_comparison_in_locale(
+            335174516.0,
+            300000000.0,
+            MetricFormat::Count,
+            true,
+            NumberLocale::EuComma,
+        );
+        assert_eq!(metric.metric, "335.174.516");
+        let comparison = metric.comparison.unwrap();
+        assert_eq!(comparison.baseline, "300.000.000");
+        assert_eq!(comparison.delta, "+11,7%");
+        let unchanged = MetricComparison::new_in_locale(
+            1.0,
+            1.0,
+            MetricFormat::Float(1),
+            true,
+            NumberLocale::EuComma,
+        )
+        .unwrap();
+        assert_eq!(unchanged.delta, "0,0%");
+        assert_eq!(unchanged.direction, DeltaDirection::Unchanged);
+
+        let table = TableMetric {
+            rows: Vec::new(),
+            row_ids: Vec::new(),
+        }
+        .value_row(
+            "Valid barcodes",
+            0.973,
+            MetricFormat::Percent,
+            NumberLocale::EuComma,
+        )
+        .value_row(
+            "Mean reads per cell",
+            96121.87,
+            MetricFormat::Float(1),
+            NumberLocale::Plain,
+        )
+        .value_row(
+            "Number of cells",
+            3487.0,
+            MetricFormat::Count,
+            NumberLocale::EnUs,
+        );
+        assert_eq!(
+            table.rows,
+            [
+                ("Valid barcodes", "97,3%"),
+                ("Mean reads per cell", "96121.9"),
+                ("Number of cells", "3,487"),
+            ]
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_grouped_table_metric() {
+        let table = grouped_table_metric();
+        let json = r#"{
+            "groups": [
+                {
+                    "title": "Sequencing",
+                    "rows": [["Number of reads", "335,174,516"], ["Valid barcodes", "97.3%"]]
+                },
+                {"title": "Cells", "rows": [["Estimated number of cells", "3,487"]]}
+            ]
+        }"#;
+        check_eq_json(&serde_json::to_string(&table).unwrap(), json);
+        assert_eq!(test_json_roundtrip::<GroupedTableMetric>(json), table);
+        // Numbers in the rows are read as strings, as in a TableMetric
+        let numeric: GroupedTableMetric =
+            serde_json::from_str(r#"{"groups": [{"rows": [["Number of cells", 3487]]}]}"#).unwrap();
+        assert_eq!(numeric.groups[0].rows[0].1, "3487");
+        assert_eq!(numeric.groups[0].title, None);
+    }
+
+    #[test]
+    fn test_grouped_table_metric_conversion() {
+        let mut grouped = grouped_table_metric();
+        grouped.groups[1].row_ids = vec![Some("estimated_number_of_cells".into())];
+        let table = TableMetric::from(grouped);
+        assert_eq!(
+            table.rows,
+            vec![
+                ("Sequencing: Number of reads".into(), "335,174,516".into()),
+                ("Sequencing: Valid barcodes".into(), "97.3%".into()),
+                ("Cells: Estimated number of cells".into(), "3,487".into()),
+            ]
+        );
+        assert_eq!(table.get_row_id(2), Some("estimated_number_of_cells"));
+        assert_eq!(table.get_row_id(0), None);
+        assert!(grouped_table_metric().to_table_metric().row_ids.is_empty());
+
+        // A plain table is a single group without a title, and back
+        let plain = TableMetric {
+            rows: vec![("Number of reads".into(), "335,174,516".into())],
+            row_ids: Vec::new(),
+        }
+        .row_id(0, "number_of_reads");
+        let grouped = GroupedTableMetric::from(plain.clone());
+        assert_eq!(grouped.groups.len(), 1);
+        assert_eq!(grouped.groups[0].title, None);
+        assert_eq!(grouped.to_table_metric(), plain);
+    }
+
+    #[test]
+    fn test_metric_ids() {
+        // Json written before the ids has none, and absent ids are omitted
+        let json = r#"{"name": "Number of cells", "metric": "3,487", "threshold": null}"#;
+        let metric = test_json_roundtrip::<HeroMetric>(json);
+        assert_eq!(metric.id, None);
+        assert!(serde_json::to_value(&metric).unwrap().get("id").is_none());
+
+        let metric = metric.id("estimated_number_of_cells");
+        assert_eq!(
+            serde_json::to_value(&metric).unwrap()["id"],
+            "estimated_number_of_cells"
+        );
+        let json = serde_json::to_string(&metric).unwrap();
+        assert_eq!(test_json_roundtrip::<HeroMetric>(&json), metric);
+
+        let json = r#"{"rows": [["Number of reads", "335,174,516"], ["Valid barcodes", "97.3%"]]}"#;
+        let table = test_json_roundtrip::<TableMetric>(json);
+        assert!(table.row_ids.is_empty());
+        assert!(serde_json::to_value(&table)
+            .unwrap()
+            .get("row_ids")
+            .is_none());
+
+        let table = table.row_id(1, "valid_barcodes_frac");
+        check_eq_json(
+            &serde_json::to_string(&table).unwrap(),
+            r#"{
+                "rows": [["Number of reads", "335,174,516"], ["Valid barcodes", "97.3%"]],
+                "row_ids": [null, "valid_barcodes_frac"]
+            }"#,
+        );
+        assert_eq!(table.get_row_id(0), None);
+        assert_eq!(table.get_row_id(1), Some("valid_barcodes_frac"));
+        assert_eq!(table.get_row_id(2), None);
+    }
+
+    #[test]
+    fn test_hero_metric_comparison() {
+        let metric = HeroMetric::new("Number of cells", "3,600").with_comparison(
+            3600.0,
+            3487.0,
+            MetricFormat::Count,
+            true,
+        );
+        assert_eq!(metric.threshold, Some(Threshold::Pass));
+        check_eq_json(
+            &serde_json::to_string(&metric).unwrap(),
+            r#"{
+                "name": "Number of cells",
+                "metric": "3,600",
+                "threshold": "pass",
+                "comparison": {"baseline": "3,487", "delta": "+3.2%", "direction": "better"}
+            }"#,
+        );
+        test_json_roundtrip::<HeroMetric>(&serde_json::to_string(&metric).unwrap());
+
+        // An explicit threshold is kept
+        let metric = HeroMetric::with_threshold("Q30 bases", "91.0%", Threshold::Error)
+            .with_comparison(0.91, 0.95, MetricFormat::Percent, true);
+        assert_eq!(metric.threshold, Some(Threshold::Error));
+        assert_eq!(
+            metric.comparison,
+            Some(MetricComparison {
+                baseline: "95.0%".into(),
+                delta: "-4.2%".into(),
+                direction: DeltaDirection::Worse,
+            })
+        );
+    }
+
+    #[test]
+    fn test_metric_comparison_delta() {
+        let compare = |current, baseline, higher_is_better| {
+            MetricComparison::new(current, baseline, MetricFormat::Float(2), higher_is_better)
+                .map(|comparison| (comparison.delta, comparison.direction))
+        };
+        assert_eq!(
+            compare(110.0, 100.0, true),
+            Some(("+10.0%".into(), DeltaDirection::Better))
+        );
+        assert_eq!(
+            compare(110.0, 100.0, false),
+            Some(("+10.0%".into(), DeltaDirection::Worse))
+        );
+        assert_eq!(
+            compare(90.0, 100.0, false),
+            Some(("-10.0%".into(), DeltaDirection::Better))
+        );
+        // Relative to the magnitude of a negative baseline
+        assert_eq!(
+            compare(-1.0, -2.0, true),
+            Some(("+50.0%".into(), DeltaDirection::Better))
+        );
+        // Tiny changes round to zero, without a sign
+        assert_eq!(
+            compare(100.0, 100.0, true),
+            Some(("0.0%".into(), DeltaDirection::Unchanged))
+        );
+        assert_eq!(
+            compare(99.9999, 100.0, true),
+            Some(("0.0%".into(), DeltaDirection::Unchanged))
+        );
+        assert_eq!(
+            MetricComparison::new(1.5, 2.0, MetricFormat::Float(2), true)
+                .unwrap()
+                .baseline,
+            "2.00"
+        );
+    }
+
+    #[test]
+    fn test_metric_comparison_undefined() {
+        assert_eq!(
+            MetricComparison::new(5.0, 0.0, MetricFormat::Count, true),
+            None
+        );
+        assert_eq!(
+            MetricComparison::new(0.0, 0.0, MetricFormat::Count, true),
+            None
+        );
+        assert_eq!(
+            MetricComparison::new(5.0, f64::NAN, MetricFormat::Count, true),
+            None
+        );
+        assert_eq!(
+            MetricComparison::new(f64::INFINITY, 1.0, MetricFormat::Count, true),
+            None
+        );
+        let metric = HeroMetric::new("Number of cells", "5").with_comparison(
+            5.0,
+            0.0,
+            MetricFormat::Count,
+            true,
+        );
+        assert_eq!(metric, HeroMetric::new("Number of cells", "5"));
+        assert!(!serde_json::to_string(&metric).unwrap().contains("NaN"));
+    }
+
+    #[test]
+    fn test_header_with_help() {
+        test_json_roundtrip::<TitleWithHelp>(
+            r#"{
+            "helpText": "This is the help text", 
+            "title": "This is the title"
+        }"#,
+        );
+    }
+
+    #[test]
+    fn test_header_with_help_bullets_and_link() {
+        let title = TitleWithHelp::new("Cells", "Estimated <b>number</b> of cells")
+            .bullets(["Barcodes with UMIs > 500", "Ambient RNA & doublets removed"])
+            .link(
+                "Learn more",
+                "https://support.10xgenomics.com/cells?a=1&b=2",
+            );
+        let json = r#"{
+            "helpText": "Estimated <b>number</b> of cells",
+            "title": "Cells",
+            "helpBullets": ["Barcodes with UMIs > 500", "Ambient RNA & doublets removed"],
+            "helpLink": {
+                "text": "Learn more",
+                "url": "https://support.10xgenomics.com/cells?a=1&b=2"
+            }
+        }"#;
+        check_eq_json(&serde_json::to_string(&title).unwrap(), json);
+        assert_eq!(test_json_roundtrip::<TitleWithHelp>(json), title);
+
+        // The new fields are omitted when not set
+        let plain = TitleWithHelp::new("Cells", "Help");
+        check_eq_json(
+            &serde_json::to_string(&plain).unwrap(),
+            r#"{"helpText": "Help", "title": "Cells"}"#,
+        );
+    }
+
+    #[test]
+    fn test_title_untagged_ambiguity() {
+        let parse = |json: &str| serde_json::from_str::<Title>(json).unwrap();
+        assert!(matches!(
+            parse(r#"{"title": "Cells", "data": [["Term", ["Desc"]]]}"#),
+            Title::WithTermDesc(_)
+        ));
+        assert!(matches!(
+            parse(r#"{"title": "Cells", "helpText": "Help"}"#),
+            Title::WithHelp(_)
+        ));
+        let with_link = parse(
+            r#"{"title": "Cells", "helpText": "", "helpLink": {"text": "Docs", "url": "/docs"}}"#,
+        );
+        assert_eq!(
+            with_link,
+            Title::WithHelp(TitleWithHelp::new("Cells", "").link("Docs", "/docs"))
+        );
+        // The optional fields of TitleWithHelp alone do not make it a
+        // TitleWithHelp, so a TitleWithTermDesc is still recognized
+        assert!(matches!(
+            parse(r#"{"title": "Cells", "helpBullets": ["a"], "data": []}"#),
+            Title::WithTermDesc(_)
+        ));
+        for title in [
+            Title::new("Cells"),
+            TitleWithHelp::new("Cells", "Help")
+                .bullets(["a"])
+                .link("Docs", "/docs")
+                .into(),
+            Title::WithTermDesc(TitleWithTermDesc {
+                title: "Cells".into(),
+                data: vec![TermDesc::with_one_desc("Term", "Desc")],
+            }),
+        ] {
+            let json = serde_json::to_string(&title).unwrap();
+            assert_eq!(serde_json::from_str::<Title>(&json).unwrap(), title);
+        }
+    }
+
+    #[test]
+    fn test_section_id() {
+        assert_eq!(section_id("cells"), "cells");
+        assert_eq!(
+            section_id("tabs.tab_data[0].grid_data[12]"),
+            "tabs-tab_data-0-grid_data-12"
+        );
+        assert_eq!(section_id("10x_data.cells"), "section-10x_data-cells");
+        assert_eq!(section_id("_private"), "section-_private");
+        assert_eq!(section_id("a b/c"), "a-b-c");
+        assert_eq!(section_id(""), "section");
+    }
+
+    #[test]
+    fn test_component_link() {
+        let link = ComponentLink::to_data_key("barcode rank plot", "10x.barcode_rank");
+        // The link and the section ids share the id of the data key
+        assert_eq!(section_id("10x.barcode_rank"), "section-10x-barcode_rank");
+        let html = link.html();
+        assert_eq!(
+            html,
+            r##"<a href="#section-10x-barcode_rank" data-link-key="10x.barcode_rank">barcode rank plot</a>"##
+        );
+        assert_eq!(link.template(Some("ignored".into())), html);
+
+        let help = format!(
+            "See the {html} and the {}",
+            LinkedText {
+                link: "https://www.10xgenomics.com".into(),
+                text: "docs".into(),
+            }
+            .html()
+        );
+        assert_eq!(component_link_targets(&help), ["10x.barcode_rank"]);
+        assert!(matches!(
+            downgrade_component_links(&help, &["cells".into()]),
+            Cow::Borrowed(_)
+        ));
+        assert_eq!(
+            downgrade_component_links(&help, &["10x.barcode_rank".into()]),
+            r#"See the barcode rank plot and the <a href="https://www.10xgenomics.com">docs</a>"#
+        );
+    }
+
+    #[test]
+    fn test_dedup_section_ids() {
+        let div = |id: &str| format!("{}x</div>", section_div(id));
+        let template = [div("a"), div("b"), div("a"), div("a-2"), div("a")].concat();
+        assert_eq!(
+            dedup_section_ids(&template),
+            [div("a"), div("b"), div("a-2"), div("a-2-2"), div("a-3")].concat()
+        );
+        let unique = [div("a"), div("b")].concat();
+        assert!(matches!(dedup_section_ids(&unique), Cow::Borrowed(_)));
+        // Ids which are not section ids, e.g. in an HtmlFragment
+        let fragment = r#"<div class="col" id="a">x</div>"#;
+        let template = [div("a"), fragment.to_string(), fragment.to_string()].concat();
+        assert!(matches!(dedup_section_ids(&template), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_term_descriptions() {
+        test_json_roundtrip::<TermDesc>(
+            r#"[
+                "Inner title", 
+                [
+                    "Inner help description 1",
+                    "Inner help description 2"
+                ]
+            ]"#,
+        );
+    }
+
+    #[test]
+    fn test_dyn_help() {
+        test_json_roundtrip::<TitleWithTermDesc>(
+            r#"{
+                "data": [
+                    [
+                        "Metric 1", 
+                        [
+                            "Help 1"
+                        ]
+                    ], 
+                    [
+                        "Metric 2", 
+                        [
+                            "Help 2"
+                        ]
+                    ]
+                ], 
+                "title": "Title text"
+            }"#,
+        );
+    }
+
+    #[test]
+    fn test_config_valid_json() {
+        let _ = PlotlyChart::default_config();
+    }
+
+    #[test]
+    fn test_generic_table() {
+        test_json_roundtrip::<GenericTable>(
+            r#"{
+                "rows": [
+                    [
+                        "Sample ID", 
+                        "Human PBMC BCR"
+                    ], 
+                    [
+                        "Sample Description", 
+                        "Pre vs post vaccination"
+                    ]
+                ]
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_generic_table_with_header() {
+        test_json_roundtrip::<GenericTable>(
+            r#"{
+                "header": [
+                    "Donor", 
+                    "Origin", 
+                    "Cells",
+                    "Clonotypes"
+                ], 
+                "rows": [
+                    [
+                        "Donor1",
+                        "PreVac",
+                        "10,000",
+                        "7,000"
+                    ],
+                    [
+                        "Donor1",
+                        "PostVac",
+                        "8,000",
+                        "2,000"
+                    ]
+                ]
+            }"#,
+        );
+    }
+
+    #[test]
+    fn test_generic_table_with_pagination() {
+        let table = test_json_roundtrip::<GenericTable>(
+            r#"{
+                "header": ["Donor", "Cells"],
+                "rows": [
+                    ["Donor1", "10,000"],
+                    ["Donor2", "8,000"]
+                ],
+                "pageSize": 1,
+                "stickyHeader": true
+            }"#,
+        );
+        assert_eq!(table.page_size, Some(1));
+        assert_eq!(table.sticky_header, Some(true));
+    }
+
+    #[test]
+    fn test_generic_table_builders() {
+        let table = GenericTable::from_rows(vec![vec!["Donor1".to_string()]], None);
+        assert_eq!(table.page_size, None);
+        assert_eq!(table.sticky_header, None);
+        assert!(table.clone().paginate(0).is_err());
+        let zero = GenericTable {
+            page_size: Some(0),
+            ..table.clone()
+        };
+        assert_eq!(
+            serde_json::to_string(&zero).unwrap_err().to_string(),
+            "Page size of a table must be greater than zero"
+        );
+        assert!(serde_json::to_string(&ColumnarTable::try_from(zero).unwrap()).is_err());
+        check_eq_json(
+            &serde_json::to_string(&table.paginate(10).unwrap().sticky_header()).unwrap(),
+            r#"{"rows":[["Donor1"]],"pageSize":10,"stickyHeader":true}"#,
+        );
+    }
+
+    #[test]
+    fn test_generic_table_rich_rows() {
+        let table = GenericTable::from_rich_rows(
+            vec![vec![
+                RichCell::from("Donor1"),
+                RichCell::Html("<b>10,000</b> &amp; more".into()),
+                LinkedText {
+                    link: "https://example.com".into(),
+                    text: "Details".into(),
+                }
+                .into(),
+            ]],
+            Some(vec!["Donor".into(), "Cells".into(), "Link".into()]),
+        );
+        let json = r#"{
+            "header": ["Donor", "Cells", "Link"],
+            "rows": [["Donor1", "10,000 & more", "Details"]],
+            "richRows": [[
+                {"text": "Donor1"},
+                {"html": "<b>10,000</b> &amp; more"},
+                {"link": {"text": "Details", "href": "https://example.com"}}
+            ]]
+        }"#;
+        check_eq_json(&serde_json::to_string(&table).unwrap(), json);
+        assert_eq!(test_json_roundtrip::<GenericTable>(json), table);
+
+        // Tables without rich rows are serialized as before
+        let plain = GenericTable::from_rows(vec![vec!["Donor1".to_string()]], None);
+        check_eq_json(
+            &serde_json::to_string(&plain).unwrap(),
+            r#"{"rows":[["Donor1"]]}"#,
+        );
+    }
+
+    #[test]
+    fn test_generic_table_rich_to_plain() {
+        let plain =
+            GenericTable::from_rows(vec![vec!["Donor1".to_string(), "10,000".to_string()]], None);
+        let rich = plain.clone().into_rich();
+        assert_eq!(
+            rich.rich_rows,
+            Some(vec![RichTableRow(vec![
+                RichCell::Text("Donor1".into()),
+                RichCell::Text("10,000".into())
+            ])])
+        );
+        assert_eq!(rich.into_plain(), plain);
+
+        let mut rich = plain.clone().into_rich();
+        rich.rich_rows.as_mut().unwrap()[0].0[0] = RichCell::Link {
+            text: "Donor2".into(),
+            href: "#donor2".into(),
+        };
+        assert_eq!(
+            rich.into_plain().rows,
+            vec![TableRow::two_col("Donor2", "10,000")]
+        );
+    }
+
+    #[test]
+    fn test_sparkline_serde() {
+        let sparkline = Sparkline::new(vec![30.5, 32.0, 31.25])
+            .unwrap()
+            .color("#1f77b4")
+            .size(80, 16);
+        let json = r##"{"values":[30.5,32.0,31.25],"color":"#1f77b4","width":80,"height":16}"##;
+        check_eq_json(&serde_json::to_string(&sparkline).unwrap(), json);
+        assert_eq!(test_json_roundtrip::<Sparkline>(json), sparkline);
+
+        let plain = Sparkline::new(vec![1.0]).unwrap();
+        check_eq_json(
+            &serde_json::to_string(&plain).unwrap(),
+            r#"{"values":[1.0]}"#,
+        );
+        assert_eq!(
+            test_json_roundtrip::<Sparkline>(r#"{"values":[1.0]}"#),
+            plain
+        );
+    }
+
+    #[test]
+    fn test_sparkline_invalid() {
+        assert_eq!(
+            Sparkline::new(vec![]).unwrap_err().to_string(),
+            "A sparkline needs at least one value"
+        );
+        assert_eq!(
+            Sparkline::new(vec![1.0, f64::NAN]).unwrap_err().to_string(),
+            "Value 1 of the sparkline is NaN, expected a finite number"
+        );
+        assert!(Sparkline::new(vec![f64::INFINITY]).is_err());
+        assert!(serde_json::from_str::<Sparkline>(r#"{"values":[]}"#).is_err());
+    }
+
+    #[test]
+    fn test_fraction_bar_serde() {
+        let bar = FractionBar::new(0.875)
+            .unwrap()
+            .label("87.5% reads in cells")
+            .threshold(Threshold::Pass);
+        let json = r#"{"fraction":0.875,"label":"87.5% reads in cells","threshold":"pass"}"#;
+        check_eq_json(&serde_json::to_string(&bar).unwrap(), json);
+        assert_eq!(test_json_roundtrip::<FractionBar>(json), bar);
+
+        for fraction in [0.0, 1.0] {
+            let bar = FractionBar::new(fraction).unwrap();
+            let json = serde_json::to_string(&bar).unwrap();
+            assert_eq!(test_json_roundtrip::<FractionBar>(&json), bar);
+        }
+    }
+
+    #[test]
+    fn test_fraction_bar_invalid() {
+        assert_eq!(
+            FractionBar::new(1.5).unwrap_err().to_string(),
+            "The fraction in a fraction bar should be between 0 and 1, found 1.5"
+        );
+        assert!(FractionBar::new(-0.1).is_err());
+        assert!(FractionBar::new(f64::NAN).is_err());
+        assert!(serde_json::from_str::<FractionBar>(r#"{"fraction":1.5}"#).is_err());
+    }
+
+    fn grouped_table() -> GroupedHeaderTable {
+        GroupedHeaderTable::new(
+            vec![
+                HeaderGroup::new("Feature", vec!["ID".into(), "Name".into()]),
+                HeaderGroup::new("Cluster 1", vec!["L2FC".into(), "p-value".into()]),
+            ],
+            vec![
+                vec!["ENSG1".into(), "CD3E".into(), "2.1".into(), "1e-5".into()],
+                vec!["ENSG2".into(), "MS4A1".into(), "-0.4".into(), "0.2".into()],
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_grouped_header_table_serde() {
+        let json = r#"{
+            "groups": [
+                {"title": "Feature", "columns": ["ID", "Name"]},
+                {"title": "Cluster 1", "columns": ["L2FC", "p-value"]}
+            ],
+            "rows": [
+                ["ENSG1", "CD3E", "2.1", "1e-5"],
+                ["ENSG2", "MS4A1", "-0.4", "0.2"]
+            ]
+        }"#;
+        check_eq_json(&serde_json::to_string(&grouped_table()).unwrap(), json);
+        assert_eq!(
+            test_json_roundtrip::<GroupedHeaderTable>(json),
+            grouped_table()
+        );
+        assert_eq!(grouped_table().num_cols(), 4);
+    }
+
+    #[test]
+    fn test_grouped_header_table_invalid() {
+        let err = GroupedHeaderTable::new(
+            vec![HeaderGroup::new(
+                "Cluster 1",
+                vec!["L2FC".into(), "p-value".into()],
+            )],
+            vec![vec!["2.1".into(), "1e-5".into()], vec!["-0.4".into()]],
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Row 1 of the table has 1 columns, expected 2 columns as in the groups"
+        );
+
+        let table = grouped_table().to_generic_table();
+        let err = |grouping: &[(&str, usize)]| {
+            GroupedHeaderTable::from_generic_table(table.clone(), grouping)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            err(&[("Feature", 2), ("Cluster 1", 1)]),
+            "The grouping covers 3 columns, but the table has 4 columns"
+        );
+        let mut headerless = table.clone();
+        headerless.header = None;
+        assert_eq!(
+            GroupedHeaderTable::from_generic_table(headerless, &[("Feature", 4)])
+                .unwrap_err()
+                .to_string(),
+            "A table without a header cannot be grouped"
+        );
+    }
+
+    #[test]
+    fn test_grouped_header_table_generic_table() {
+        let generic = GenericTable::from(grouped_table());
+        assert_eq!(
+            generic.header,
+            Some(vec![
+                "Feature / ID".to_string(),
+                "Feature / Name".to_string(),
+                "Cluster 1 / L2FC".to_string(),
+                "Cluster 1 / p-value".to_string(),
+            ])
+        );
+        assert_eq!(generic.rows, grouped_table().rows);
+
+        let table = GenericTable::from_rows(
+            grouped_table().rows.into_iter().map(|row| row.0).collect(),
+            Some(vec![
+                "ID".into(),
+                "Name".into(),
+                "L2FC".into(),
+                "p-value".into(),
+            ]),
+        );
+        let grouped =
+            GroupedHeaderTable::from_generic_table(table, &[("Feature", 2), ("Cluster 1", 2)])
+                .unwrap();
+        assert_eq!(grouped, grouped_table());
+        assert_eq!(grouped.to_generic_table(), generic);
+    }
+
+    #[test]
+    fn test_categorical_legend_serde() {
+        let mut legend = CategoricalLegend::new(vec![
+            ("T cells".into(), "#1f77b4".into()),
+            ("B cells".into(), "#ff7f0e".into()),
+        ])
+        .counts([1200, 340]);
+        legend.entries[1] = legend.entries[1].clone().hidden();
+        let json = r##"{"entries": [
+            {"label": "T cells", "color": "#1f77b4", "count": 1200},
+            {"label": "B cells", "color": "#ff7f0e", "count": 340, "hidden": true}
+        ]}"##;
+        check_eq_json(&serde_json::to_string(&legend).unwrap(), json);
+        assert_eq!(test_json_roundtrip::<CategoricalLegend>(json), legend);
+
+        let plain = r##"{"entries": [{"label": "NK", "color": "green"}]}"##;
+        assert_eq!(
+            test_json_roundtrip::<CategoricalLegend>(plain),
+            CategoricalLegend {
+                entries: vec![LegendEntry::new("NK", "green")]
+            }
+        );
+    }
+
+    #[test]
+    fn test_categorical_legend_generic_table() {
+        let legend = CategoricalLegend::new(vec![
+            ("T cells".into(), "#1f77b4".into()),
+            ("B <cells>".into(), "#ff7f0e".into()),
+        ]);
+        let table = legend.to_generic_table();
+        assert_eq!(
+            table.header,
+            Some(vec![String::new(), "Category".to_string()])
+        );
+        assert_eq!(
+            table.rows,
+            vec![
+                TableRow(vec![String::new(), "T cells".into()]),
+                TableRow(vec![String::new(), "B <cells>".into()]),
+            ]
+        );
+        let rich_rows = table.rich_rows.unwrap();
+        assert_eq!(
+            rich_rows[0].0[0],
+            RichCell::Html(
+                r#"<span style="display:inline-block;width:1em;height:1em;background-color:#1f77b4"></span>"#
+                    .into()
+            )
+        );
+        assert_eq!(rich_rows[1].0[1], RichCell::Text("B <cells>".into()));
+
+        let with_counts = legend.counts([10]).to_generic_table();
+        assert_eq!(with_counts.header.unwrap()[2], "Count");
+        assert_eq!(with_counts.rows[0].0[2], "10");
+        assert_eq!(with_counts.rows[1].0[2], "");
+    }
+
+    #[test]
+    fn test_generic_table_visual_cells() {
+        let table = GenericTable::from_rich_rows(
+            vec![vec![
+                RichCell::from("Donor1"),
+                Sparkline::new(vec![30.0, 35.5]).unwrap().into(),
+                FractionBar::new(0.5).unwrap().into(),
+            ]],
+            None,
+        );
+        let json = r#"{
+            "rows": [["Donor1", "30 35.5", "50.0%"]],
+            "richRows": [[
+                {"text": "Donor1"},
+                {"sparkline": {"values": [30.0, 35.5]}},
+                {"fraction_bar": {"fraction": 0.5}}
+            ]]
+        }"#;
+        check_eq_json(&serde_json::to_string(&table).unwrap(), json);
+        assert_eq!(test_json_roundtrip::<GenericTable>(json), table);
+    }
+
+    fn hd_cluster(name: &str, spatial_plot: &str) -> HdClusteringSingleClusterData {
+        HdClusteringSingleClusterData {
+            cluster_name: name.into(),
+            hex_color: "#1f77b4".into(),
+            spatial_plot: spatial_plot.into(),
+            umap_plot: "data:image/png;base64,UMAP".into(),
+        }
+    }
+
+    #[test]
+    fn test_dyn_html_template() {
+        let metric = HeroMetric::new("Number of cells", "3,487");
+        let expected = DivWrapper::row(&metric).template(Some("cells".into()));
+        let boxed: Box<dyn HtmlTemplate> = Box::new(metric.clone());
+        assert_eq!(
+            DivWrapper::row(&boxed).template(Some("cells".into())),
+            expected
+        );
+        let borrowed: &dyn HtmlTemplate = &metric;
+        assert_eq!(
+            DivWrapper::row(borrowed).template(Some("cells".into())),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_plotly_is_empty() {
+        let chart =
+            |data: Vec<Value>| PlotlyChart::with_layout_and_data(serde_json::json!({}), data);
+        assert!(chart(vec![]).is_empty());
+        assert!(chart(vec![
+            serde_json::json!({"type": "scatter", "x": [], "y": []})
+        ])
+        .is_empty());
+        assert!(chart(vec![
+            serde_json::json!({"type": "scatter", "x": [], "y": [], "marker": {"color": [1]}}),
+            serde_json::json!({"type": "bar", "name": "empty"}),
+        ])
+        .is_empty());
+        assert!(!chart(vec![
+            serde_json::json!({"type": "scatter", "x": [], "y": []}),
+            serde_json::json!({"type": "bar", "x": ["a"], "y": [1]}),
+        ])
+        .is_empty());
+        assert!(!chart(vec![
+            serde_json::json!({"type": "scatter", "x": "_resources_000"})
+        ])
+        .is_empty());
+    }
+
+    #[test]
+    fn test_with_empty_state() {
+        let table = GenericTable::from_rows(vec![], Some(vec!["Sample".into()]));
+        assert!(table.is_empty());
+        let result = table.or_empty_state("No samples passed the filters");
+        assert!(!result.is_ok());
+        assert_eq!(
+            result.template(Some("samples".into())),
+            r#"<div data-key="samples" data-component="EmptyState"></div>"#
+        );
+        check_eq_json(
+            &serde_json::to_string(&result).unwrap(),
+            r#"{"icon": "inbox", "title": "No data", "message": "No samples passed the filters"}"#,
+        );
+
+        let table = GenericTable::from_rows(vec![vec!["PBMC".into()]], Some(vec!["Sample".into()]));
+        assert!(!table.is_empty());
+        let result = table
+            .clone()
+            .or_empty_state("No samples passed the filters");
+        assert!(result.is_ok());
+        assert_eq!(
+            result.template(Some("samples".into())),
+            table.template(Some("samples".into()))
+        );
+        assert_eq!(
+            serde_json::to_string(&result).unwrap(),
+            serde_json::to_string(&table).unwrap()
+        );
+
+        let chart = PlotlyChart::with_layout_and_data(serde_json::json!({}), Vec::<Value>::new());
+        let result = with_empty_state(chart.clone(), chart.is_empty(), "No cells");
+        assert!(!result.is_ok());
+    }
+
+    #[test]
+    fn test_plotly_share_large_arrays() {
+        let coordinates: Vec<usize> = (0..100).collect();
+        let chart = |color: &str| {
+            PlotlyChart::with_layout_and_data(
+                serde_json::json!({}),
+                vec![serde_json::json!({
+                    "type": "scattergl",
+                    "x": coordinates,
+                    "y": [1, 2, 3],
+                    "marker": {"color": color},
+                })],
+            )
+        };
+        let mut resources = SharedResources::new();
+
+        let mut unshared = chart("red");
+        unshared.add_to_shared_resource(&mut resources);
+        assert_eq!(unshared, chart("red"));
+        assert!(resources.0.is_empty());
+
+        let chart1 = chart("red")
+            .share_large_arrays(100)
+            .with_shared_resource(&mut resources);
+        let chart2 = chart("blue")
+            .share_large_arrays(100)
+            .with_shared_resource(&mut resources);
+        assert_eq!(resources.0.len(), 1);
+        assert_eq!(resources.0["000"], serde_json::json!(coordinates));
+        for chart in [&chart1, &chart2] {
+            assert_eq!(chart.data[0]["x"], "_resources_000");
+            assert_eq!(chart.data[0]["y"], serde_json::json!([1, 2, 3]));
+        }
+        assert_eq!(chart2.data[0]["marker"]["color"], "blue");
+
+        // Sharing again does not move the reference
+        let chart1 = chart1.with_shared_resource(&mut resources);
+        assert_eq!(chart1.data[0]["x"], "_resources_000");
+        assert_eq!(resources.0.len(), 1);
+    }
+
+    #[test]
+    fn test_plotly_share_large_traces() {
+        let mut resources = SharedResources::new();
+        let large = serde_json::json!({"type": "scatter", "x": (0..100).collect::<Vec<_>>()});
+        let small = serde_json::json!({"type": "bar", "x": [1]});
+        let chart = PlotlyChart::with_layout_and_data(
+            serde_json::json!({}),
+            vec![large.clone(), small.clone()],
+        )
+        .share_large_traces(100)
+        .with_shared_resource(&mut resources);
+        assert_eq!(chart.data[0], "_resources_000");
+        assert_eq!(resources.0["000"], large);
+        assert_eq!(chart.data[1], small);
+    }
+
+    #[test]
+    fn test_plotly_sanitize() {
+        let chart = || {
+            PlotlyChart::with_layout_and_data(
+                serde_json::json!({"title": "Saturation"}),
+                vec![
+                    serde_json::json!({"x": [1, 2]}),
+                    serde_json::json!({"y": [[1.5, 2.5], [f64::NAN.to_string(), 3.5]]}),
+                ],
+            )
+        };
+
+        let nulled = chart().sanitize(NanPolicy::ReplaceWithNull).unwrap();
+        assert_eq!(
+            nulled.data[1]["y"],
+            serde_json::json!([[1.5, 2.5], [null, 3.5]])
+        );
+        assert_eq!(nulled.data[0], chart().data[0]);
+        assert_eq!(nulled.layout, chart().layout);
+
+        let zeroed = chart().sanitize(NanPolicy::ReplaceWith(0.0)).unwrap();
+        assert_eq!(
+            zeroed.data[1]["y"],
+            serde_json::json!([[1.5, 2.5], [0.0, 3.5]])
+        );
+
+        let err = chart().sanitize(NanPolicy::Error).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            r#"Non-finite number "NaN" at data[1].y[1][0]"#
+        );
+    }
+
+    #[test]
+    fn test_hd_clustering_shared_resources() {
+        let mut resources = SharedResources::new();
+        let plot = HdClusteringPlot::new(
+            HdClusteringSpatialPlotProps {
+                title: "Spatial".into(),
+                tissue_image: "data:image/png;base64,TISSUE".into(),
+                tissue_css_transform: vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+                spot_css_transform: vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+                width: 100,
+                height: 100,
+                initial_zoom_pan: InitialZoomPan {
+                    scale: None,
+                    dx: None,
+                    dy: None,
+                },
+            },
+            HdClusteringUmapPlotProps {
+                title: "UMAP".into(),
+            },
+        )
+        .with_cluster(hd_cluster("1", "data:image/png;base64,S1"), &mut resources)
+        .with_cluster(hd_cluster("2", "data:image/png;base64,S2"), &mut resources);
+
+        // The umap plot is stored once and both clusters refer to it
+        assert_eq!(resources.0.len(), 3);
+        let [c1, c2] = &plot.clusters[..] else {
+            unreachable!()
+        };
+        assert_eq!(c1.umap_plot, c2.umap_plot);
+        assert_ne!(c1.spatial_plot, c2.spatial_plot);
+        assert_eq!(
+            resources.0[c1.umap_plot.strip_prefix("_resources_").unwrap()],
+            "data:image/png;base64,UMAP"
+        );
+
+        // Adding the whole plot moves the tissue image and the clusters
+        let mut resources = SharedResources::new();
+        let mut plot = HdClusteringPlot {
+            clusters: vec![
+                hd_cluster("1", "data:image/png;base64,S1"),
+                hd_cluster("2", "data:image/png;base64,S2"),
+            ],
+            ..plot
+        };
+        plot.spatial_plot_props.tissue_image = "data:image/png;base64,TISSUE".into();
+        plot.add_to_shared_resource(&mut resources);
+        assert_eq!(resources.0.len(), 4);
+        assert_eq!(plot.clusters[0].umap_plot, plot.clusters[1].umap_plot);
+        assert!(plot
+            .spatial_plot_props
+            .tissue_image
+            .starts_with(crate::RESOURCES_PREFIX));
+    }
+
+    #[test]
+    fn test_gentable_transpose() {
+        let table_json = r#"{"header":["Donor","Origin","Cells","Clonotypes"],"rows":[["Donor1","PreVac","10,000","7,000"],["Donor2","","8,000","2,000"]]}"#;
+        let header = Some(
+            vec!["Donor", "Origin", "Cells", "Clonotypes"]
+                .into_iter()
+                .map(std::string::ToString::to_string)
+                .collect(),
+        );
+        let columns_in = vec![
+            vec!["Donor1".to_string(), "Donor2".to_string()],
+            vec!["PreVac".to_string()],
+            vec!["10,000".to_string(), "8,000".to_string()],
+            vec!["7,000".to_string(), "2,000".to_string()],
+        ];
+        assert_eq!(
+            table_json,
+            serde_json::to_string(&GenericTable::from_columns(columns_in, header)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_gentable_try_from_rows() {
+        let svec = |row: &[&str]| row.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let table = GenericTable::try_from_rows(
+            vec![svec(&["a", "b"]), svec(&["c", "d"])],
+            Some(svec(&["x", "y"])),
+        )
+        .unwrap();
+        assert_eq!(table.rows[1], TableRow(svec(&["c", "d"])));
+
+        let err = GenericTable::try_from_rows(
+            vec![
+                svec(&["a", "b"]),
+                svec(&["c", "d"]),
+                svec(&["e"]),
+                svec(&[]),
+            ],
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Row 2 of the table has 1 columns, expected 2 columns as in row 0"
+        );
+
+        let err = GenericTable::try_from_rows(
+            vec![svec(&["a", "b"]), svec(&["c", "d"])],
+            Some(svec(&["x", "y", "z"])),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Row 0 of the table has 2 columns, expected 3 columns as in the header"
+        );
+
+        assert!(GenericTable::try_from_rows(vec![], None).is_ok());
+    }
+
+    #[test]
+    fn test_gentable_from_rows_padded() {
+        let svec = |row: &[&str]| row.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let table = GenericTable::from_rows_padded(
+            vec![svec(&["a"]), svec(&["b", "c"]), svec(&[])],
+            Some(svec(&["x", "y", "z"])),
+        );
+        assert_eq!(
+            table.rows,
+            vec![
+                TableRow(svec(&["a", "", ""])),
+                TableRow(svec(&["b", "c", ""])),
+                TableRow(svec(&["", "", ""])),
+            ]
+        );
+        assert_eq!(table.header, Some(svec(&["x", "y", "z"])));
+
+        let table = GenericTable::from_rows_padded(
+            vec![svec(&["a", "b", "c"]), svec(&["d"])],
+            Some(svec(&["x"])),
+        );
+        assert_eq!(table.header, Some(svec(&["x", "", ""])));
+        assert_eq!(table.rows[1], TableRow(svec(&["d", "", ""])));
+    }
+
+    #[test]
+    fn test_gentable_try_from_columns() {
+        let svec = |col: &[&str]| col.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let table = GenericTable::try_from_columns(
+            vec![svec(&["a", "b"]), svec(&["c", "d"])],
+            Some(svec(&["x", "y"])),
+        )
+        .unwrap();
+        assert_eq!(table.rows[0], TableRow(svec(&["a", "c"])));
+
+        let err = GenericTable::try_from_columns(vec![svec(&["a", "b"]), svec(&["c"])], None)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Column 1 of the table has 1 rows, expected 2 rows as in column 0"
+        );
+
+        let err = GenericTable::try_from_columns(vec![svec(&["a"])], Some(svec(&["x", "y"])))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The table has 1 columns, expected 2 columns as in the header"
+        );
+
+        assert!(GenericTable::from_columns(vec![], None).rows.is_empty());
+    }
+
+    /// Every data-key in the template must resolve to a value in the json
+    fn check_data_keys_resolve(template: &str, json: &Value) {
+        let re = regex::Regex::new(r#"data-key="([^"]+)""#).unwrap();
+        let index_re = regex::Regex::new(r"^(\w+)\[(\d+)\]$").unwrap();
+        let mut num_keys = 0;
+        for cap in re.captures_iter(template) {
+            let value = cap[1].split('.').fold(json, |value, part| {
+                let value = match index_re.captures(part) {
+                    Some(c) => &value[&c[1]][c[2].parse::<usize>().unwrap()],
+                    None => &value[part],
+                };
+                assert!(!value.is_null(), "Unable to resolve {}", &cap[1]);
+                value
+            });
+            assert!(!value.is_null());
+            num_keys += 1;
+        }
+        assert!(num_keys > 0);
+    }
+
+    #[test]
+    fn test_erased_sections() {
+        let sections = vec![
+            ErasedSection::new(HeroMetric::new("Number of cells", "3,487")),
+            ErasedSection::new(GenericTable::from_rows(
+                vec![vec!["Donor1".to_string(), "10,000".to_string()]],
+                Some(vec!["Donor".to_string(), "Cells".to_string()]),
+            )),
+            ErasedSection::new(PlotlyChart::with_layout_and_data(
+                serde_json::json!({"title": "Plot"}),
+                vec![serde_json::json!({"x": [1, 2], "y": [3, 4]})],
+            )),
+        ];
+        let template = sections.template(Some("sections".into()));
+        assert_eq!(
+            template,
+            r#"<div class="row">
+<div class="col">
+<div data-key="sections[0]" data-component="Metric"></div>
+</div>
+</div>
+<div class="row">
+<div class="col">
+<div data-key="sections[1]" data-component="Table"></div>
+</div>
+</div>
+<div class="row">
+<div class="col">
+<div data-key="sections[2]" data-component="Plot"></div>
+</div>
+</div>"#
+        );
+        let json = serde_json::json!({ "sections": sections });
+        check_data_keys_resolve(&template, &json);
+        assert_eq!(json["sections"][0]["metric"], "3,487");
+        assert_eq!(json["sections"][1]["rows"][0][0], "Donor1");
+        assert_eq!(json["sections"][2]["layout"]["title"], "Plot");
+    }
+
+    #[test]
+    fn test_erased_section_nested() {
+        let section = ErasedSection::new(CollapsablePanel::with_title_and_content(
+            "Panel",
+            HeroMetric::new("Number of cells", "3,487"),
+        ));
+        let template = section.template(Some("panel".into()));
+        assert_eq!(
+            template,
+            r#"<div data-key="panel.parent_props" data-component="CollapsablePanel">
+<div data-key="panel.children" data-component="Metric"></div>
+</div>"#
+        );
+        check_data_keys_resolve(&template, &serde_json::json!({ "panel": section }));
+    }
+
+    #[test]
+    fn test_data_heading() {
+        let heading = test_json_roundtrip::<DataHeading>(r#"{"text": "Cells", "level": "H2"}"#);
+        assert_eq!(heading, DataHeading::from(Heading::h2("Cells")));
+        assert_eq!(
+            heading.template(Some("heading".into())),
+            r#"<div data-key="heading" data-component="Heading"></div>"#
+        );
+    }
+
+    #[cfg(feature = "generate_html")]
+    #[test]
+    fn test_data_heading_scrape() {
+        use crate::{scrape_json::scrape_json_from_html, SinglePageHtml};
+
+        #[derive(Serialize)]
+        struct Content {
+            heading: DataHeading,
+        }
+        impl HtmlTemplate for Content {
+            fn template(&self, _: Option<String>) -> String {
+                self.heading.template(Some("heading".into()))
+            }
+        }
+
+        let mut html = Vec::new();
+        SinglePageHtml::from_content(Content {
+            heading: DataHeading::new(HeadingLevel::H3, "Cell Calling"),
+        })
+        .generate_html(&mut html)
+        .unwrap();
+        let json = scrape_json_from_html(html.as_slice()).unwrap();
+        assert_eq!(json["heading"]["text"], "Cell Calling");
+        assert_eq!(json["heading"]["level"], "H3");
+    }
+
+    fn columnar_table() -> ColumnarTable {
+        ColumnarTable::from_columns_typed([
+            (
+                "Barcode",
+                ColumnValues::from(vec!["AAACCTGA-1".to_string(), "AAACGGGC-1".to_string()]),
+            ),
+            ("UMIs", ColumnValues::from(vec![5123.0, 0.1 + 0.2])),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_columnar_table_json() {
+        let table = columnar_table();
+        assert_eq!(table.num_rows(), 2);
+        assert_eq!(
+            serde_json::to_value(&table).unwrap(),
+            serde_json::json!({
+                "header": ["Barcode", "UMIs"],
+                "columns": [
+                    {"text": ["AAACCTGA-1", "AAACGGGC-1"]},
+                    {"number": [5123.0, 0.30000000000000004]}
+                ]
+            })
+        );
+        let json = serde_json::to_string(&table).unwrap();
+        // The numbers are not strings and are read back exactly
+        assert!(json.contains("[5123.0,0.30000000000000004]"));
+        assert_eq!(test_json_roundtrip::<ColumnarTable>(&json), table);
+        assert_eq!(
+            table.template(Some("barcodes".into())),
+            r#"<div data-key="barcodes" data-component="ColumnarTable"></div>"#
+        );
+    }
+
+    #[test]
+    fn test_columnar_table_generic() {
+        let generic = columnar_table().to_generic_table();
+        assert_eq!(
+            generic.rows,
+            vec![
+                TableRow::two_col("AAACCTGA-1", "5123"),
+                TableRow::two_col("AAACGGGC-1", "0.30000000000000004"),
+            ]
+        );
+        assert_eq!(generic.rows[1].0[1].parse::<f64>().unwrap(), 0.1 + 0.2);
+
+        let generic = generic.paginate(10).unwrap();
+        let columnar = ColumnarTable::try_from(generic.clone()).unwrap();
+        assert!(columnar
+            .columns
+            .iter()
+            .all(|column| matches!(column, ColumnValues::Text(_))));
+        assert_eq!(columnar.page_size, Some(10));
+        assert_eq!(GenericTable::from(columnar), generic);
+
+        let ragged = GenericTable {
+            header: None,
+            rows: vec![TableRow(vec!["a".into()]), TableRow::two_col("b", "c")],
+            page_size: None,
+            sticky_header: None,
+            rich_rows: None,
+        };
+        assert!(ColumnarTable::from_generic_table(ragged).is_err());
+    }
+
+    #[test]
+    fn test_columnar_table_from_columns() {
+        // The columns are moved into the table without a copy
+        let umis: Vec<f64> = (0..50_000).map(f64::from).collect();
+        let ptr = umis.as_ptr();
+        let table =
+            ColumnarTable::from_columns_typed([("UMIs", ColumnValues::Number(umis))]).unwrap();
+        let ColumnValues::Number(values) = &table.columns[0] else {
+            panic!("Expected a numeric column");
+        };
+        assert_eq!(values.as_ptr(), ptr);
+        assert_eq!(table.num_rows(), 50_000);
+
+        let err = ColumnarTable::from_columns_typed([
+            ("a", ColumnValues::from(vec![1.0, 2.0])),
+            ("b", ColumnValues::from(vec!["x".to_string()])),
+        ])
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Column 1 of the table has 1 rows, expected 2 rows as in column 0"
+        );
+    }
+
+    #[test]
+    fn test_documented() {
+        let documented = Documented::new(HeroMetric::new("Valid barcodes", "93.6%"));
+        assert_eq!(
+            documented.template(Some("barcodes".into())),
+            r#"<div data-key="barcodes.inner" data-component="Metric"></div>"#
+        );
+        let documented = documented.help(TitleWithHelp::new(
+            "Valid barcodes",
+            "Matching the whitelist",
+        ));
+        assert_eq!(
+            documented.template(None),
+            "<div data-key=\"help\" data-component=\"HeaderWithHelp\"></div>\n\
+             <div data-key=\"inner\" data-component=\"Metric\"></div>"
+        );
+        assert_eq!(
+            test_json_roundtrip::<Documented<HeroMetric>>(
+                &serde_json::to_string(&documented).unwrap()
+            ),
+            documented
+        );
+    }
+
+    #[test]
+    fn test_experimental() {
+        let metric = HeroMetric::new("Doublet rate", "1.2%");
+        let experimental = Experimental::new(metric.clone());
+        let mut expected = serde_json::to_value(&metric).unwrap();
+        expected[EXPERIMENTAL_KEY] = Value::Bool(true);
+        assert_eq!(serde_json::to_value(&experimental).unwrap(), expected);
+        assert_eq!(
+            experimental.template(Some("doublets".into())),
+            metric.template(Some("doublets".into()))
+        );
+        assert_eq!(
+            test_json_roundtrip::<Experimental<HeroMetric>>(&expected.to_string()),
+            experimental
+        );
+
+        // The content is flattened along with its own optional fields
+        let with_id = Experimental::new(metric.id("doublet_rate"));
+        assert_eq!(
+            serde_json::to_value(&with_id).unwrap()["id"],
+            "doublet_rate"
+        );
+        let err = serde_json::to_string(&Experimental::new("text".to_string())).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The content of Experimental should serialize as a map, found a string"
+        );
+    }
+
+    #[test]
+    fn test_strip_experimental_data() {
+        let mut data = serde_json::json!({
+            "cells": {"metric": "3,487"},
+            "doublets": {"metric": "1.2%", "_experimental": true},
+            "tabs": {"tab_data": [{"a": 1}, {"b": 2, "_experimental": true}]},
+            "flag": {"_experimental": false},
+            "user": {"experimental": true}
+        });
+        let stripped = strip_experimental_data(&mut data);
+        assert_eq!(stripped, ["doublets", "tabs.tab_data[1]"]);
+        assert!(data["doublets"].is_null());
+        assert_eq!(data["tabs"]["tab_data"][0], serde_json::json!({"a": 1}));
+        assert!(data["tabs"]["tab_data"][1].is_null());
+        assert_eq!(data["flag"], serde_json::json!({"_experimental": false}));
+        // A field of the content with the unprefixed name is kept
+        assert_eq!(data["user"], serde_json::json!({"experimental": true}));
+    }
+
+    #[test]
+    fn test_remove_data_key_divs() {
+        let template = r#"<div class="col"><div data-key="cells" data-component="Metric"></div></div>
+<div data-key="tabs.props" data-component="ButtonSelector"><div name="a"><div data-key="tabs.options[0]" data-component="Metric"></div></div></div>
+<div data-key="tabs_other" data-component="Metric"></div>"#;
+        assert_eq!(
+            remove_data_key_divs(template, &["tabs".into()]),
+            r#"<div class="col"><div data-key="cells" data-component="Metric"></div></div>
+
+<div data-key="tabs_other" data-component="Metric"></div>"#
+        );
+        assert_eq!(
+            remove_data_key_divs(template, &["cells".into()]),
+            template.replacen(
+                r#"<div data-key="cells" data-component="Metric"></div>"#,
+                "",
+                1
+            )
+        );
+        assert_eq!(remove_data_key_divs(template, &[]), template);
+    }
+
+    #[test]
+    fn test_debug_only() {
+        let debug = DebugOnly::new(HeroMetric::new("Time (s)", "12.5"));
+        assert_eq!(
+            debug.template(Some("timing".into())),
+            r#"<div class="d-none" data-debug="true">
+<div data-key="timing" data-component="Metric"></div>
+</div>"#
+        );
+        check_eq_json(
+            &serde_json::to_string(&debug).unwrap(),
+            r#"{"name": "Time (s)", "metric": "12.5", "threshold": null, "debug": true}"#,
+        );
     }
-}
 
-// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
-// Csv download link
-#[derive(Serialize, Deserialize)]
-pub struct DownloadableFile {
-    pub data: String,
-    pub filename: String,
-    pub text: String,
-    pub mime_type: String,
-}
+    #[test]
+    fn test_page_break_and_no_break() {
+        assert_eq!(
+            PageBreak.template(Some("break".into())),
+            r#"<div style="break-before: page; page-break-before: always;"></div>"#
+        );
+        assert_eq!(serde_json::to_string(&PageBreak).unwrap(), "null");
 
-// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
-// Command line template
-#[derive(Serialize, Deserialize, Clone, Debug)]
-#[serde(rename_all = "camelCase")]
-pub struct CommandLine {
-    pub title: String,
-    pub data: Vec<TermDesc>,
-    pub show_dark_button_icon: bool,
-}
+        let card = Card::full_width(NoBreak::new(HeroMetric::new("Number of cells", "3,487")));
+        assert_eq!(
+            card.template(Some("cells".into())),
+            r#"<div class="summary_row">
+<div style="break-inside: avoid; page-break-inside: avoid;">
+<div data-key="cells" data-component="Metric"></div>
+</div>
+</div>"#
+        );
+        check_eq_json(
+            &serde_json::to_string(&card).unwrap(),
+            r#"{"name": "Number of cells", "metric": "3,487", "threshold": null}"#,
+        );
+    }
 
-impl ReactComponent for CommandLine {
-    fn component_name() -> &'static str {
-        "DynamicHelptext"
+    #[test]
+    fn test_nav_bar_product() {
+        let nav_bar: WsNavBar = serde_json::from_str(
+            r#"{"pipeline": "Cell Ranger", "id": "sample1", "description": "PBMC"}"#,
+        )
+        .unwrap();
+        assert_eq!(nav_bar.product(), None);
+        check_eq_json(
+            &serde_json::to_string(&nav_bar).unwrap(),
+            r#"{"pipeline": "Cell Ranger", "id": "sample1", "description": "PBMC"}"#,
+        );
+
+        let nav_bar = test_json_roundtrip::<WsNavBar>(
+            r#"{
+                "pipeline": "Space Ranger",
+                "id": "sample1",
+                "description": "Visium HD",
+                "product": "spaceranger"
+            }"#,
+        );
+        assert_eq!(nav_bar.product(), Some(ProductVariant::SpaceRanger));
+        check_eq_json(
+            &serde_json::to_string(
+                &WsNavBar::new("Space Ranger", "sample1", "Visium HD")
+                    .with_product(ProductVariant::SpaceRanger),
+            )
+            .unwrap(),
+            &serde_json::to_string(&nav_bar).unwrap(),
+        );
+        assert!(serde_json::from_str::<WsNavBar>(
+            r#"{"pipeline": "", "id": "", "description": "", "product": "unknown"}"#
+        )
+        .is_err());
     }
-}
 
-impl CommandLine {
-    pub fn new(cmdline: &str) -> Result<Self, Error> {
-        Ok(Self {
-            title: "Command Line Arguments".to_string(),
-            data: vec![TermDesc("".to_string(), 
-                vec![format!("<span style='font-size: 18px;'><code><pre style='white-space: pre-wrap;'>{}</pre></code></span>", 
-                    cmdline)])],
-            show_dark_button_icon: true,
-        })
+    fn alerts(levels: &[AlertLevel]) -> Alerts {
+        Alerts {
+            alerts: levels
+                .iter()
+                .map(|level| crate::Alert {
+                    level: level.clone(),
+                    title: "Title".into(),
+                    formatted_value: None,
+                    message: "Message".into(),
+                    group: None,
+                })
+                .collect(),
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
+    #[test]
+    fn test_qc_banner_aggregation() {
+        let metrics = [
+            ("Cells", Some(Threshold::Pass)),
+            ("Sequencing", None),
+            ("Cells", Some(Threshold::Warn)),
+            ("Mapping", Some(Threshold::Pass)),
+            ("Cells", Some(Threshold::Pass)),
+        ];
+        let banner = QcBanner::from_alerts_and_metrics(&alerts(&[AlertLevel::Info]), metrics);
+        assert_eq!(
+            banner,
+            QcBanner {
+                overall: Threshold::Warn,
+                sections: vec![
+                    ("Cells".into(), Threshold::Warn),
+                    ("Sequencing".into(), Threshold::Pass),
+                    ("Mapping".into(), Threshold::Pass),
+                ],
+            }
+        );
 
-    use super::*;
-    use pretty_assertions::assert_eq;
+        // Any error fails, whether from an alert or a metric
+        let banner = QcBanner::from_alerts_and_metrics(
+            &alerts(&[AlertLevel::Warn, AlertLevel::Error]),
+            [("Cells", Some(Threshold::Pass))],
+        );
+        assert_eq!(banner.overall, Threshold::Error);
+        assert_eq!(banner.sections, vec![("Cells".into(), Threshold::Pass)]);
+        let banner = QcBanner::from_alerts_and_metrics(
+            &alerts(&[AlertLevel::Warn]),
+            [("Mapping", Some(Threshold::Error))],
+        );
+        assert_eq!(banner.overall, Threshold::Error);
+
+        // Warnings from the alerts alone
+        let banner = QcBanner::from_alerts_and_metrics(&alerts(&[AlertLevel::Warn]), []);
+        assert_eq!(banner.overall, Threshold::Warn);
+        assert!(banner.sections.is_empty());
+
+        let banner = QcBanner::from_alerts_and_metrics(&alerts(&[]), [("Cells", None)]);
+        assert_eq!(banner.overall, Threshold::Pass);
+        check_eq_json(
+            &serde_json::to_string(&banner).unwrap(),
+            r#"{"overall": "pass", "sections": [["Cells", "pass"]]}"#,
+        );
+    }
 
-    fn check_eq_json(j1: &str, j2: &str) {
+    #[test]
+    fn test_merge_json() {
+        let mut base = serde_json::json!({
+            "axis": {"labelFontSize": 12, "grid": {"color": "gray", "width": 1}},
+            "range": {"category": ["red", "blue"]},
+            "font": "Helvetica"
+        });
+        merge_json(
+            &mut base,
+            serde_json::json!({
+                "axis": {"grid": {"color": "black"}, "labelAngle": 45},
+                "range": {"category": ["green"]},
+                "font": null,
+                "padding": 5
+            }),
+        );
         assert_eq!(
-            serde_json::from_str::<serde_json::value::Value>(j1).unwrap(),
-            serde_json::from_str::<serde_json::value::Value>(j2).unwrap()
+            base,
+            serde_json::json!({
+                "axis": {
+                    "labelFontSize": 12,
+                    "grid": {"color": "black", "width": 1},
+                    "labelAngle": 45
+                },
+                "range": {"category": ["green"]},
+                "font": null,
+                "padding": 5
+            })
         );
     }
 
-    fn test_json_roundtrip<T: Serialize + serde::de::DeserializeOwned>(json: &str) -> T {
-        let parsed: T = serde_json::from_str(json).unwrap();
-        let parsed_str = serde_json::to_string(&parsed).unwrap();
-        check_eq_json(&parsed_str, json);
-        parsed
+    #[test]
+    fn test_vega_lite_themed() {
+        // A spec without a config gets the whole theme
+        let plot = VegaLitePlot::themed(serde_json::json!({"mark": "bar"}));
+        assert_eq!(plot.spec["config"], VegaLitePlot::theme());
+        assert_eq!(plot.spec["config"]["range"]["category"][0], "#1f77b4");
+
+        let plot = VegaLitePlot::themed(serde_json::json!({
+            "mark": "bar",
+            "width": 300,
+            "config": {"axis": {"labelFontSize": 9}, "range": {"category": ["black"]}}
+        }))
+        .width(500)
+        .height(200);
+        let config = &plot.spec["config"];
+        assert_eq!(config["axis"]["labelFontSize"], 9);
+        assert_eq!(config["axis"]["titleFontSize"], 13);
+        assert_eq!(config["range"]["category"], serde_json::json!(["black"]));
+        assert_eq!(config["font"], VegaLitePlot::theme()["font"]);
+        assert_eq!(plot.spec["width"], 300);
+        assert_eq!(plot.spec["height"], 200);
+
+        // The new fields are only serialized when set
+        check_eq_json(
+            &serde_json::to_string(&VegaLitePlot::from_json_str(r#"{"mark": "bar"}"#).unwrap())
+                .unwrap(),
+            r#"{"spec": {"mark": "bar"}, "actions": null, "renderer": null}"#,
+        );
+        let plot = test_json_roundtrip::<VegaLitePlot>(
+            r#"{
+                "spec": {"mark": "bar"},
+                "actions": null,
+                "renderer": "svg",
+                "style": {"width": "100%"},
+                "config": {"autosize": "fit"}
+            }"#,
+        );
+        assert_eq!(plot.style, Some(Style::new().width("100%")));
     }
 
     #[test]
-    fn test_header_with_help() {
-        test_json_roundtrip::<TitleWithHelp>(
-            r#"{
-            "helpText": "This is the help text", 
-            "title": "This is the title"
-        }"#,
+    fn test_section_result_ok() {
+        let metric = || HeroMetric::new("Number of cells", "3,487");
+        let ok: SectionResult<_> = Ok(metric()).into();
+        assert!(ok.is_ok());
+        assert_eq!(
+            ok.template(Some("cells".into())),
+            metric().template(Some("cells".into()))
+        );
+        assert_eq!(
+            serde_json::to_string(&ok).unwrap(),
+            serde_json::to_string(&metric()).unwrap()
         );
     }
 
     #[test]
-    fn test_term_descriptions() {
-        test_json_roundtrip::<TermDesc>(
-            r#"[
-                "Inner title", 
-                [
-                    "Inner help description 1",
-                    "Inner help description 2"
-                ]
-            ]"#,
+    fn test_section_result_err() {
+        let err: SectionResult<HeroMetric> =
+            Err(anyhow::anyhow!("did not converge").context("Clustering <k-means> failed")).into();
+        assert!(!err.is_ok());
+        assert_eq!(
+            err.template(Some("clustering".into())),
+            r#"<div data-key="clustering.parent_props" data-component="InlineAlert">
+<div data-key="clustering.children" data-component="HtmlFragment"></div>
+</div>"#
+        );
+        check_eq_json(
+            &serde_json::to_string(&err).unwrap(),
+            r#"{
+                "error": "Clustering <k-means> failed: did not converge",
+                "parent_props": {"level": "danger"},
+                "children": {"html": "Clustering &lt;k-means&gt; failed: did not converge"}
+            }"#,
         );
     }
 
     #[test]
-    fn test_dyn_help() {
-        test_json_roundtrip::<TitleWithTermDesc>(
+    fn test_inline_alert_props() {
+        check_eq_json(
+            &serde_json::to_string(&InlineTextAlert::warning("Low <b>yield</b>")).unwrap(),
             r#"{
-                "data": [
-                    [
-                        "Metric 1", 
-                        [
-                            "Help 1"
-                        ]
-                    ], 
-                    [
-                        "Metric 2", 
-                        [
-                            "Help 2"
-                        ]
-                    ]
-                ], 
-                "title": "Title text"
+                "parent_props": {"level": "warning"},
+                "children": {"html": "Low <b>yield</b>"}
+            }"#,
+        );
+        let alert = InlineTextAlert::info("Rerun with more reads")
+            .dismissible()
+            .icon("info-circle");
+        check_eq_json(
+            &serde_json::to_string(&alert).unwrap(),
+            r#"{
+                "parent_props": {"level": "info", "dismissible": true, "icon": "info-circle"},
+                "children": {"html": "Rerun with more reads"}
             }"#,
         );
+        // The new props do not change the keys of the children
+        let template = r#"<div data-key="qc.parent_props" data-component="InlineAlert">
+<div data-key="qc.children" data-component="HtmlFragment"></div>
+</div>"#;
+        assert_eq!(alert.template(Some("qc".into())), template);
+        assert_eq!(
+            InlineTextAlert::info("Rerun with more reads").template(Some("qc".into())),
+            template
+        );
+    }
+
+    /// The rendering of `DynGrid` before the templates were split at the
+    /// marker, which rescanned every element template with `replace()`
+    fn replace_based_grid(elements: &[HeroMetric], ncols: usize, responsive: bool) -> String {
+        let templates: Vec<_> = elements
+            .iter()
+            .map(|e| e.template(Some(DYN_GRID_MARKER.into())))
+            .collect();
+        let rows = templates
+            .iter()
+            .enumerate()
+            .chunks(ncols)
+            .into_iter()
+            .map(|row| {
+                let cols = row
+                    .map(|(i, element)| {
+                        let element =
+                            element.replace(DYN_GRID_MARKER, &format!("g.grid_data[{i}]"));
+                        if responsive {
+                            format!("<div class=\"col-sm-6\">\n{element}\n</div>")
+                        } else {
+                            format!("<td>{element}</td>")
+                        }
+                    })
+                    .join("\n");
+                if responsive {
+                    format!("<div class=\"row\">\n{cols}\n</div>")
+                } else {
+                    format!("<tr>{cols}</tr>")
+                }
+            })
+            .join("\n");
+        if responsive {
+            rows
+        } else {
+            format!("<table><tbody>{rows}</tbody></table>")
+        }
     }
 
     #[test]
-    fn test_config_valid_json() {
-        let _ = PlotlyChart::default_config();
+    fn test_grid_template_unchanged() {
+        let metrics: Vec<_> = (0..5)
+            .map(|i| HeroMetric::new(format!("Metric {i}"), i.to_string()))
+            .collect();
+        for (layout, responsive) in [
+            (GridLayout::MaxCols(2), true),
+            (GridLayout::MaxColsNonResponsive(2), false),
+        ] {
+            let grid = DynGrid::with_elements(metrics.clone(), layout);
+            assert_eq!(
+                grid.template(Some("g".into())),
+                replace_based_grid(&metrics, 2, responsive)
+            );
+        }
+        let empty = DynGrid::new(GridLayout::MaxColsNonResponsive(3));
+        assert_eq!(empty.template(None), "<table><tbody></tbody></table>");
+        assert_eq!(DynGrid::new(GridLayout::MaxCols(3)).template(None), "");
     }
 
     #[test]
-    fn test_generic_table() {
-        test_json_roundtrip::<GenericTable>(
-            r#"{
-                "rows": [
-                    [
-                        "Sample ID", 
-                        "Human PBMC BCR"
-                    ], 
-                    [
-                        "Sample Description", 
-                        "Pre vs post vaccination"
-                    ]
-                ]
-            }
-            "#,
+    fn test_grid_optional_elements() {
+        let elements: Vec<_> = (0..6)
+            .map(|i| (i % 2 == 0).then(|| HeroMetric::new(format!("Metric {i}"), i.to_string())))
+            .collect();
+        let present: Vec<_> = elements.iter().flatten().cloned().collect();
+
+        let grid = DynGrid::from_optional_elements(elements.clone(), GridLayout::MaxCols(2));
+        let mut pushed = DynGrid::new(GridLayout::MaxCols(2));
+        for element in elements.clone() {
+            pushed.push_optional(element);
+        }
+        let typed = Grid::from_optional_elements(elements.clone(), GridLayout::MaxCols(2));
+        for grid in [&grid, &pushed] {
+            let json = serde_json::to_value(grid).unwrap();
+            assert_eq!(json["grid_data"].as_array().unwrap().len(), 3);
+            assert_eq!(json["grid_data"][1]["name"], "Metric 2");
+            assert_eq!(
+                grid.template(Some("g".into())),
+                replace_based_grid(&present, 2, true)
+            );
+        }
+        assert_eq!(
+            serde_json::to_value(&typed).unwrap(),
+            serde_json::to_value(&grid).unwrap()
+        );
+        let template = grid.template(Some("g".into()));
+        assert_eq!(template.matches(r#"<div class="row">"#).count(), 2);
+        for i in 0..3 {
+            assert!(template.contains(&format!(r#"data-key="g.grid_data[{i}]""#)));
+        }
+        assert!(!template.contains("grid_data[3]"));
+
+        // Pushing the options keeps the gaps
+        let gaps = DynGrid::with_elements(elements, GridLayout::MaxCols(2));
+        let json = serde_json::to_value(&gaps).unwrap();
+        assert_eq!(json["grid_data"].as_array().unwrap().len(), 6);
+        assert!(json["grid_data"][1].is_null());
+        assert_eq!(
+            gaps.template(None).matches(r#"<div class="row">"#).count(),
+            3
         );
     }
 
     #[test]
-    fn test_generic_table_with_header() {
-        test_json_roundtrip::<GenericTable>(
+    fn test_template_into_matches_template() {
+        fn check<T: HtmlTemplate>(component: &T, data_key: Option<&str>) {
+            let data_key = data_key.map(String::from);
+            let mut out = String::from("<p>before</p>");
+            component.template_into(data_key.clone(), &mut out);
+            assert_eq!(
+                out,
+                format!("<p>before</p>{}", component.template(data_key))
+            );
+        }
+        let metric = |i: usize| HeroMetric::new("Cells", i.to_string());
+        let mut inner = DynGrid::new(GridLayout::MaxCols(3));
+        inner.push(metric(0));
+        inner.push(Tabs::new().tab("A", metric(1)).tab("B", metric(2)));
+        let tabs = Tabs::new()
+            .tab("Grid", inner)
+            .tab("Erased", ErasedSection::new(metric(3)));
+        let mut outer = DynGrid::new(GridLayout::MaxColsNonResponsive(2));
+        outer.push(tabs);
+        outer.push(vec![Some(metric(4)), None]);
+        check(&outer, Some("outer"));
+        check(&outer, None);
+        check(&ErasedSection::new(outer), Some("erased"));
+        check(&vec![metric(5), metric(6)], Some("list"));
+        check(&Some(metric(7)), Some("maybe"));
+        check(&None::<HeroMetric>, Some("maybe"));
+
+        let template = Tabs::new()
+            .tab("Inner", Tabs::new().tab("A", metric(0)))
+            .template(Some("t".into()));
+        assert!(template.contains(r#"data-key="t.tab_data[0].tab_data[0]""#));
+        assert!(!template.contains(TAB_MARKER));
+    }
+
+    #[test]
+    fn test_maybe_section_present() {
+        let metric = || HeroMetric::new("Antibodies", "17");
+        let present = MaybeSection::present(metric());
+        assert!(present.is_ok());
+        assert_eq!(
+            present.template(Some("antibody".into())),
+            metric().template(Some("antibody".into()))
+        );
+        assert_eq!(
+            serde_json::to_string(&present).unwrap(),
+            serde_json::to_string(&metric()).unwrap()
+        );
+        let from_some: MaybeSection<_> = Some(metric()).into();
+        assert_eq!(
+            serde_json::to_string(&from_some).unwrap(),
+            serde_json::to_string(&metric()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_maybe_section_absent() {
+        let absent: MaybeSection<HeroMetric> = MaybeSection::absent("No <antibody> library");
+        assert!(!absent.is_ok());
+        assert_eq!(
+            absent.template(Some("antibody".into())),
+            r#"<div data-key="antibody.parent_props" data-component="InlineAlert">
+<div data-key="antibody.children" data-component="HtmlFragment"></div>
+</div>"#
+        );
+        check_eq_json(
+            &serde_json::to_string(&absent).unwrap(),
             r#"{
-                "header": [
-                    "Donor", 
-                    "Origin", 
-                    "Cells",
-                    "Clonotypes"
-                ], 
-                "rows": [
-                    [
-                        "Donor1",
-                        "PreVac",
-                        "10,000",
-                        "7,000"
-                    ],
-                    [
-                        "Donor1",
-                        "PostVac",
-                        "8,000",
-                        "2,000"
-                    ]
-                ]
+                "missing_reason": "No <antibody> library",
+                "parent_props": {"level": "secondary"},
+                "children": {"html": "No &lt;antibody&gt; library"}
             }"#,
         );
+        let from_none: MaybeSection<HeroMetric> = None.into();
+        assert_eq!(
+            serde_json::to_value(&from_none).unwrap()["missing_reason"],
+            DEFAULT_MISSING_REASON
+        );
+    }
+
+    fn attribute_values<'a>(template: &'a str, attribute: &str) -> Vec<&'a str> {
+        template
+            .split(&format!(r#"{attribute}=""#))
+            .skip(1)
+            .map(|rest| &rest[..rest.find('"').unwrap()])
+            .collect()
     }
 
     #[test]
-    fn test_gentable_transpose() {
-        let table_json = r#"{"header":["Donor","Origin","Cells","Clonotypes"],"rows":[["Donor1","PreVac","10,000","7,000"],["Donor2","","8,000","2,000"]]}"#;
-        let header = Some(
-            vec!["Donor", "Origin", "Cells", "Clonotypes"]
-                .into_iter()
-                .map(std::string::ToString::to_string)
-                .collect(),
+    fn test_nested_tabs() {
+        let inner = Tabs::new()
+            .tab("Cells", HeroMetric::new("Number of cells", "3,487"))
+            .tab("Reads", HeroMetric::new("Number of reads", "335,174,516"));
+        let tabs = Tabs::new()
+            .tab("Gene Expression", inner)
+            .tab("Antibody", HeroMetric::new("Antibodies", "17"));
+        let template = tabs.template(Some("tabs".into()));
+
+        let event_keys = attribute_values(&template, "data-event-key");
+        assert_eq!(
+            event_keys,
+            vec![
+                "tab_tabs.tab_data_0",
+                "tab_tabs.tab_data[0].tab_data_0",
+                "tab_tabs.tab_data[0].tab_data_1",
+                "tab_tabs.tab_data_1",
+            ]
         );
-        let columns_in = vec![
-            vec!["Donor1".to_string(), "Donor2".to_string()],
-            vec!["PreVac".to_string()],
-            vec!["10,000".to_string(), "8,000".to_string()],
-            vec!["7,000".to_string(), "2,000".to_string()],
-        ];
+        let ids = attribute_values(&template, "data-id");
         assert_eq!(
-            table_json,
-            serde_json::to_string(&GenericTable::from_columns(columns_in, header)).unwrap()
+            ids,
+            vec!["tabs_tabs.tab_data", "tabs_tabs.tab_data[0].tab_data"]
+        );
+        assert_eq!(
+            attribute_values(&template, "data-default-active-key"),
+            vec!["tab_tabs.tab_data_0", "tab_tabs.tab_data[0].tab_data_0"]
+        );
+        assert!(template.contains(r#"data-key="tabs.tab_data[0].tab_data[1]""#));
+
+        let json = serde_json::to_value(&tabs).unwrap();
+        assert_eq!(
+            json["titles"],
+            serde_json::json!(["Gene Expression", "Antibody"])
+        );
+        assert_eq!(
+            json["tab_data"][0]["titles"],
+            serde_json::json!(["Cells", "Reads"])
+        );
+        assert_eq!(json["tab_data"][1]["metric"], "17");
+    }
+
+    #[test]
+    fn test_legacy_tabs() {
+        let tabs = Tabs::legacy()
+            .tab("Cells", HeroMetric::new("Number of cells", "3,487"))
+            .tab("Reads", HeroMetric::new("Number of reads", "335,174,516"));
+        assert_eq!(
+            tabs.template(None),
+            r#"<div class="tabs-wrapper" data-default-active-key="tab_0" data-id="main-tabs">
+<div class="tab-wrapper" data-event-key="tab_0" data-title="Cells">
+<div data-key="tab_data[0]" data-component="Metric"></div>
+</div>
+<div class="tab-wrapper" data-event-key="tab_1" data-title="Reads">
+<div data-key="tab_data[1]" data-component="Metric"></div>
+</div>
+</div>"#
         );
+        assert_eq!(tabs.titles(), ["Cells", "Reads"]);
     }
 
     #[test]
@@ -1849,4 +7020,47 @@ mod tests {
         }"#;
         let _: BlendedImage = serde_json::from_str(json_str).unwrap();
     }
+
+    #[test]
+    fn test_slider_size_serde() {
+        for (json_str, width) in [
+            (r#"{"width":470}"#, NumOrStr::Num(470)),
+            (r#"{"width":470.5}"#, NumOrStr::Float(470.5)),
+            (r#"{"width":"470px"}"#, NumOrStr::Str("470px".into())),
+        ] {
+            let size: BlendedImageSliderSize = test_json_roundtrip(json_str);
+            assert_eq!(size, BlendedImageSliderSize { width });
+            assert_eq!(serde_json::to_string(&size).unwrap(), json_str);
+        }
+    }
+
+    #[test]
+    fn test_zoom_viewer_size_serde() {
+        let size: ZoomViewerSize = test_json_roundtrip(r#"{"width":400,"height":"100%"}"#);
+        assert_eq!(
+            size,
+            ZoomViewerSize {
+                width: 400.into(),
+                height: "100%".into(),
+            }
+        );
+        let size: ZoomViewerSize = test_json_roundtrip(r#"{"width":399.75,"height":300.0}"#);
+        assert_eq!(
+            size,
+            ZoomViewerSize {
+                width: 399.75.into(),
+                height: 300.0.into(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_num_or_str_display() {
+        assert_eq!(NumOrStr::Num(470).to_string(), "470");
+        assert_eq!(NumOrStr::Float(470.0).to_string(), "470");
+        assert_eq!(NumOrStr::Float(470.5).to_string(), "470.5");
+        assert_eq!(NumOrStr::from(0.25f32).to_string(), "0.25");
+        assert_eq!(NumOrStr::Float(-3.0).to_string(), "-3");
+        assert_eq!(NumOrStr::Str("100%".into()).to_string(), "100%");
+    }
 }
@@ -0,0 +1,204 @@
+//!
+//! Stable hash of the content of a summary, e.g. as a cache key to know when
+//! a summary needs to be rendered again. The hash is computed over the
+//! serialized data with sorted keys, so it does not depend on the html
+//! template or on the order of the keys. Fields which change from run to run
+//! for the same content, such as generated tooltip ids, are listed in
+//! `VOLATILE_FIELDS` and excluded.
+//!
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::SinglePageHtml;
+
+/// A field whose value is generated afresh each time the same content is
+/// built
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VolatileField {
+    /// Keys which identify the objects holding the field
+    pub object_keys: &'static [&'static str],
+    pub field: &'static str,
+    /// Only string values with this prefix are volatile
+    pub prefix: &'static str,
+    /// Stable value which replaces the field
+    pub placeholder: &'static str,
+}
+
+/// The fields which are excluded from the content hash and normalized in the
+/// golden files
+pub const VOLATILE_FIELDS: &[VolatileField] = &[
+    // Generated by `Tooltip::new()`
+    VolatileField {
+        object_keys: &["tooltip", "content"],
+        field: "id",
+        prefix: "tt-",
+        placeholder: "tt-normalized",
+    },
+    // Generated by `generate_submission_token()` for a `FormConfig`
+    VolatileField {
+        object_keys: &["url", "method"],
+        field: "submission_token",
+        prefix: "",
+        placeholder: "normalized-token",
+    },
+];
+
+/// The volatile field which matches the entry of the object, if any
+pub(crate) fn volatile_field(
+    object: &Map<String, Value>,
+    key: &str,
+    value: &Value,
+) -> Option<&'static VolatileField> {
+    let Value::String(value) = value else {
+        return None;
+    };
+    VOLATILE_FIELDS.iter().find(|volatile| {
+        volatile.field == key
+            && value.starts_with(volatile.prefix)
+            && volatile
+                .object_keys
+                .iter()
+                .all(|object_key| object.contains_key(*object_key))
+    })
+}
+
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            out.push('{');
+            for (i, (key, val)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::from(key.as_str()).to_string());
+                out.push(':');
+                match volatile_field(map, key, val) {
+                    Some(volatile) => out.push_str(&Value::from(volatile.placeholder).to_string()),
+                    None => write_canonical(val, out),
+                }
+            }
+            out.push('}');
+        }
+        Value::Array(values) => {
+            out.push('[');
+            for (i, val) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(val, out);
+            }
+            out.push(']');
+        }
+        value => out.push_str(&value.to_string()),
+    }
+}
+
+/// Compact JSON with sorted keys and the volatile fields replaced by their
+/// placeholders
+pub fn canonical_json(value: &Value) -> String {
+    let mut out = String::new();
+    write_canonical(value, &mut out);
+    out
+}
+
+/// 64 bit FNV-1a, which unlike the std hashers is stable across releases
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    bytes.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// Hash of the `canonical_json()` of the value
+pub fn canonical_json_hash(value: &Value) -> u64 {
+    fnv1a(canonical_json(value).as_bytes())
+}
+
+impl<P: Serialize> SinglePageHtml<P> {
+    /// Hash of the data in the summary, including the nav bar, the alerts and
+    /// the shared resources. See the module docs.
+    pub fn content_hash(&self) -> Result<u64> {
+        Ok(canonical_json_hash(&serde_json::to_value(self)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::components::{BlendedImage, BlendedImageSliderSize, HeroMetric, NumOrStr, Tooltip};
+    use crate::WebSummary;
+
+    fn summary(cells: &str, image: &str) -> WebSummary {
+        let blended = BlendedImage::new(
+            image.to_string(),
+            "data:image/png;base64,BBBB".to_string(),
+            BlendedImageSliderSize {
+                width: NumOrStr::Num(300),
+            },
+        );
+        WebSummary::builder()
+            .nav("Cell Ranger", "sample1", "PBMC")
+            .section_plain("cells", HeroMetric::new("Number of cells", cells))
+            .section_plain(
+                "help",
+                Tooltip::new("Estimated number of cells", "Cells", None, None),
+            )
+            .section("tissue", blended)
+            .build()
+    }
+
+    #[test]
+    fn test_content_hash() -> Result<()> {
+        let first = summary("3,487", "data:image/png;base64,AAAA");
+        let second = summary("3,487", "data:image/png;base64,AAAA");
+        assert_eq!(first.content_hash()?, second.content_hash()?);
+
+        let changed_metric = summary("3,488", "data:image/png;base64,AAAA");
+        assert_ne!(first.content_hash()?, changed_metric.content_hash()?);
+
+        // The image is only referenced by key in the content
+        let changed_image = summary("3,487", "data:image/png;base64,CCCC");
+        assert_eq!(
+            serde_json::to_value(&first)?["tissue"],
+            serde_json::to_value(&changed_image)?["tissue"]
+        );
+        assert_ne!(first.content_hash()?, changed_image.content_hash()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_canonical_json() {
+        let value = json!({
+            "b": [1, {"d": null, "c": "x"}],
+            "a": {"tooltip": "t", "content": "c", "id": "tt-123"},
+            "form": {"url": "/", "method": "post", "submission_token": "abc"}
+        });
+        assert_eq!(
+            canonical_json(&value),
+            r#"{"a":{"content":"c","id":"tt-normalized","tooltip":"t"},"b":[1,{"c":"x","d":null}],"form":{"method":"post","submission_token":"normalized-token","url":"/"}}"#
+        );
+        // Ids set explicitly are part of the content
+        let explicit = json!({"tooltip": "t", "content": "c", "id": "cells-help"});
+        assert_eq!(
+            canonical_json(&explicit),
+            r#"{"content":"c","id":"cells-help","tooltip":"t"}"#
+        );
+        assert_eq!(
+            canonical_json_hash(&json!({"x": 1, "y": 2})),
+            canonical_json_hash(&json!({"y": 2, "x": 1}))
+        );
+        assert_ne!(
+            canonical_json_hash(&json!({"x": 1})),
+            canonical_json_hash(&json!({"x": "1"}))
+        );
+        // The empty string hashes to the FNV offset basis
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+    }
+}
@@ -1,12 +1,95 @@
-use std::{io::Read, path::Path};
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
 
-use crate::components::{GenericTable, TableRow};
-use anyhow::Result;
+use crate::components::{GenericTable, TableMetric, TableRow};
+use crate::number_format::{parse_number, NumberLocale};
+use crate::HtmlTemplate;
+use anyhow::{bail, Result};
 use itertools::Itertools;
+use serde::Serialize;
+
+/// Split a plain decimal number such as `-1234.5` into the sign, integer and
+/// fractional parts. Returns None for anything else, including numbers which
+/// are already formatted such as `1,234` or `83.2%`.
+fn split_number(value: &str) -> Option<(&str, &str, &str)> {
+    let (sign, unsigned) = match value.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", value),
+    };
+    let (int_part, frac_part) = match unsigned.split_once('.') {
+        Some((_, "")) => return None,
+        Some(parts) => parts,
+        None => (unsigned, ""),
+    };
+    let all_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    (!int_part.is_empty() && all_digits(int_part) && all_digits(frac_part))
+        .then_some((sign, int_part, frac_part))
+}
+
+/// Format a plain decimal number with the separators of the locale, padding
+/// the fractional part with zeros up to `decimals` digits
+fn format_number(
+    (sign, int_part, frac_part): (&str, &str, &str),
+    decimals: usize,
+    locale: NumberLocale,
+) -> String {
+    let plain = if decimals == 0 {
+        format!("{sign}{int_part}")
+    } else {
+        format!("{sign}{int_part}.{frac_part:0<decimals$}")
+    };
+    locale.localize(&plain)
+}
+
+/// Numbers are formatted with the separators of the locale and everything
+/// else is passed through
+fn format_metric_value(value: &str, locale: NumberLocale) -> String {
+    match split_number(value.trim()) {
+        Some(parts) => format_number(parts, parts.2.len(), locale),
+        None => value.to_string(),
+    }
+}
+
+impl TableMetric {
+    /// Build the table from a metrics csv with a header of metric names and a
+    /// single row of values, formatting the numeric values.
+    pub fn from_metrics_csv(csv_reader: impl Read) -> Result<Self> {
+        Self::from_metrics_csv_in_locale(csv_reader, NumberLocale::EnUs)
+    }
+
+    /// Same as `from_metrics_csv()`, with the numeric values formatted with
+    /// the separators of the locale
+    pub fn from_metrics_csv_in_locale(csv_reader: impl Read, locale: NumberLocale) -> Result<Self> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .has_headers(true)
+            .from_reader(csv_reader);
+        let header = rdr.headers()?.clone();
+        let records: Vec<_> = rdr.records().try_collect()?;
+        let values = match records.as_slice() {
+            [values] => values,
+            _ => bail!(
+                "Expected a single row of metric values in the metrics csv, found {} rows",
+                records.len()
+            ),
+        };
+        Ok(TableMetric {
+            rows: header
+                .iter()
+                .zip_eq(values)
+                .map(|(name, value)| (name.to_string(), format_metric_value(value, locale)))
+                .collect(),
+            row_ids: Vec::new(),
+        })
+    }
+}
 
 impl GenericTable {
     pub fn from_csv_file(path: impl AsRef<Path>, has_headers: bool) -> Result<Self> {
-        GenericTable::from_csv_reader(std::fs::read(path)?.as_slice(), has_headers)
+        GenericTable::from_csv_reader(BufReader::new(File::open(path)?), has_headers)
     }
 
     pub fn from_csv_reader(csv_reader: impl Read, has_headers: bool) -> Result<Self> {
@@ -27,12 +110,310 @@ impl GenericTable {
 
         let rows = rdr
             .records()
-            .map(|record| {
-                record.map(|rec| TableRow(rec.into_iter().map(ToString::to_string).collect()))
-            })
+            .map(|record| record.map(|rec| rec.into_iter().map(ToString::to_string).collect()))
             .try_collect()?;
 
-        Ok(GenericTable { header, rows })
+        GenericTable::try_from_rows(rows, header)
+    }
+
+    /// Similar to `from_csv_reader`, but columns in which every non-empty
+    /// value is a number are formatted with thousands separators and the same
+    /// number of decimal places.
+    pub fn from_csv_with_types(csv_reader: impl Read, has_headers: bool) -> Result<Self> {
+        let mut table = GenericTable::from_csv_reader(csv_reader, has_headers)?;
+        format_numeric_columns(&mut table);
+        Ok(table)
+    }
+}
+
+/// Format the columns in which every non-empty value is a number with
+/// thousands separators and the same number of decimal places
+fn format_numeric_columns(table: &mut GenericTable) {
+    let num_cols = table.rows.iter().map(|row| row.0.len()).max().unwrap_or(0);
+    for col in 0..num_cols {
+        let cells = || {
+            table
+                .rows
+                .iter()
+                .filter_map(move |row| row.0.get(col))
+                .filter(|cell| !cell.trim().is_empty())
+        };
+        if cells().next().is_none() || !cells().all(|cell| split_number(cell.trim()).is_some()) {
+            continue;
+        }
+        let decimals = cells()
+            .map(|cell| split_number(cell.trim()).unwrap().2.len())
+            .max()
+            .unwrap();
+        for cell in table.rows.iter_mut().filter_map(|row| row.0.get_mut(col)) {
+            if let Some(parts) = split_number(cell.trim()) {
+                *cell = format_number(parts, decimals, NumberLocale::EnUs);
+            }
+        }
+    }
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+// Summary of a csv which is too large to show as a table
+
+/// Min, mean and max of a column in which every non-empty value is a number
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NumericStats {
+    pub min: f64,
+    pub mean: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnStats {
+    pub name: String,
+    /// Number of non-empty values
+    pub count: u64,
+    /// None if some non-empty value is not a number
+    pub numeric: Option<NumericStats>,
+    /// Number of distinct non-empty values, None if above the cap
+    pub num_distinct: Option<usize>,
+}
+
+/// Running stats of a column, holding at most `max_distinct` values
+struct ColumnAccumulator {
+    name: String,
+    count: u64,
+    all_numeric: bool,
+    min: f64,
+    max: f64,
+    sum: f64,
+    distinct: Option<HashSet<String>>,
+}
+
+impl ColumnAccumulator {
+    fn new(name: String) -> Self {
+        ColumnAccumulator {
+            name,
+            count: 0,
+            all_numeric: true,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            sum: 0.0,
+            distinct: Some(HashSet::new()),
+        }
+    }
+
+    fn add(&mut self, value: &str, max_distinct: usize) {
+        let value = value.trim();
+        if value.is_empty() {
+            return;
+        }
+        self.count += 1;
+        if self.all_numeric {
+            match parse_number(value) {
+                Some(x) => {
+                    self.min = self.min.min(x);
+                    self.max = self.max.max(x);
+                    self.sum += x;
+                }
+                None => self.all_numeric = false,
+            }
+        }
+        if let Some(distinct) = &mut self.distinct {
+            if !distinct.contains(value) {
+                if distinct.len() == max_distinct {
+                    self.distinct = None;
+                } else {
+                    distinct.insert(value.to_string());
+                }
+            }
+        }
+    }
+
+    fn finish(self) -> ColumnStats {
+        ColumnStats {
+            name: self.name,
+            count: self.count,
+            numeric: (self.all_numeric && self.count > 0).then(|| NumericStats {
+                min: self.min,
+                mean: self.sum / self.count as f64,
+                max: self.max,
+            }),
+            num_distinct: self.distinct.map(|distinct| distinct.len()),
+        }
+    }
+}
+
+/// Computes the per column stats and a preview of the first rows of a csv in
+/// a single pass, without holding all the rows in memory.
+pub struct CsvSummarizer<'a> {
+    has_headers: bool,
+    num_preview_rows: usize,
+    max_distinct: usize,
+    progress_every: u64,
+    progress: Option<Box<dyn FnMut(u64) + 'a>>,
+}
+
+impl Default for CsvSummarizer<'_> {
+    fn default() -> Self {
+        CsvSummarizer {
+            has_headers: true,
+            num_preview_rows: 10,
+            max_distinct: 1000,
+            progress_every: 0,
+            progress: None,
+        }
+    }
+}
+
+impl<'a> CsvSummarizer<'a> {
+    pub fn new() -> Self {
+        CsvSummarizer::default()
+    }
+    pub fn has_headers(mut self, has_headers: bool) -> Self {
+        self.has_headers = has_headers;
+        self
+    }
+    /// Number of rows in the preview table
+    pub fn preview_rows(mut self, num_preview_rows: usize) -> Self {
+        self.num_preview_rows = num_preview_rows;
+        self
+    }
+    /// Distinct values are counted up to this many per column
+    pub fn max_distinct(mut self, max_distinct: usize) -> Self {
+        self.max_distinct = max_distinct;
+        self
+    }
+    /// Call `callback` with the number of records read every `every` records
+    pub fn progress(mut self, every: u64, callback: impl FnMut(u64) + 'a) -> Self {
+        self.progress_every = every;
+        self.progress = Some(Box::new(callback));
+        self
+    }
+
+    pub fn summarize_file(self, path: impl AsRef<Path>) -> Result<CsvSummary> {
+        self.summarize_reader(BufReader::new(File::open(path)?))
+    }
+
+    pub fn summarize_reader(mut self, csv_reader: impl Read) -> Result<CsvSummary> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .has_headers(self.has_headers)
+            .flexible(true)
+            .from_reader(csv_reader);
+        let header: Option<Vec<String>> = if self.has_headers {
+            Some(rdr.headers()?.iter().map(ToString::to_string).collect())
+        } else {
+            None
+        };
+        let mut columns: Vec<_> = header
+            .iter()
+            .flatten()
+            .map(|name| ColumnAccumulator::new(name.clone()))
+            .collect();
+        let mut preview = Vec::new();
+        let mut num_records = 0;
+        let mut record = csv::StringRecord::new();
+        while rdr.read_record(&mut record)? {
+            num_records += 1;
+            for (i, value) in record.iter().enumerate() {
+                if i == columns.len() {
+                    columns.push(ColumnAccumulator::new(format!("Column {}", i + 1)));
+                }
+                columns[i].add(value, self.max_distinct);
+            }
+            if preview.len() < self.num_preview_rows {
+                preview.push(TableRow(record.iter().map(ToString::to_string).collect()));
+            }
+            if let Some(progress) = &mut self.progress {
+                if self.progress_every > 0 && num_records % self.progress_every == 0 {
+                    progress(num_records);
+                }
+            }
+        }
+
+        let columns: Vec<_> = columns.into_iter().map(ColumnAccumulator::finish).collect();
+        let mut preview = GenericTable {
+            header,
+            rows: preview,
+            page_size: None,
+            sticky_header: None,
+            rich_rows: None,
+        };
+        format_numeric_columns(&mut preview);
+        Ok(CsvSummary {
+            stats: stats_table(&columns, self.max_distinct),
+            preview,
+            num_records,
+            columns,
+        })
+    }
+}
+
+fn format_float(x: f64) -> String {
+    format_metric_value(&format!("{x:.2}"), NumberLocale::EnUs)
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+fn stats_table(columns: &[ColumnStats], max_distinct: usize) -> GenericTable {
+    let rows = columns
+        .iter()
+        .map(|column| {
+            let (min, mean, max) = match column.numeric {
+                Some(NumericStats { min, mean, max }) => {
+                    (format_float(min), format_float(mean), format_float(max))
+                }
+                None => Default::default(),
+            };
+            let num_distinct = match column.num_distinct {
+                Some(n) => format_metric_value(&n.to_string(), NumberLocale::EnUs),
+                None => format!(
+                    "> {}",
+                    format_metric_value(&max_distinct.to_string(), NumberLocale::EnUs)
+                ),
+            };
+            TableRow(vec![
+                column.name.clone(),
+                format_metric_value(&column.count.to_string(), NumberLocale::EnUs),
+                num_distinct,
+                min,
+                mean,
+                max,
+            ])
+        })
+        .collect();
+    GenericTable {
+        header: Some(
+            ["Column", "Count", "Distinct", "Min", "Mean", "Max"]
+                .map(String::from)
+                .to_vec(),
+        ),
+        rows,
+        page_size: None,
+        sticky_header: None,
+        rich_rows: None,
+    }
+}
+
+/// Stats table followed by a preview table, built by `CsvSummarizer`
+#[derive(Debug, Clone, Serialize)]
+pub struct CsvSummary {
+    pub stats: GenericTable,
+    pub preview: GenericTable,
+    #[serde(skip)]
+    pub num_records: u64,
+    #[serde(skip)]
+    pub columns: Vec<ColumnStats>,
+}
+
+impl HtmlTemplate for CsvSummary {
+    fn template(&self, data_key: Option<String>) -> String {
+        let key = |field: &str| match &data_key {
+            Some(k) => format!("{k}.{field}"),
+            None => field.to_string(),
+        };
+        format!(
+            "{}\n{}",
+            self.stats.template(Some(key("stats"))),
+            self.preview.template(Some(key("preview")))
+        )
     }
 }
 
@@ -40,7 +421,16 @@ impl GenericTable {
 mod tests {
     use anyhow::Result;
 
-    use crate::components::{GenericTable, TableRow};
+    use super::{ColumnStats, CsvSummarizer, NumericStats};
+    use crate::components::{GenericTable, TableMetric, TableRow};
+    use crate::number_format::NumberLocale;
+    use crate::test_utils::ScratchDir;
+    use crate::HtmlTemplate;
+
+    const METRICS_CSV: &str = "\
+Estimated Number of Cells,Mean Reads per Cell,Median Genes per Cell,Number of Reads,Valid Barcodes,Sequencing Saturation,Fraction Reads in Cells,Mean Cell Size
+3487,96123,1850,335174516,97.3%,56.1%,\"93,6%\",1234.56
+";
 
     #[test]
     fn test_from_csv_file() -> Result<()> {
@@ -58,7 +448,10 @@ S2,N2,89.7%
                 rows: vec![
                     TableRow(svec(["S1", "N1", "83.2%"])),
                     TableRow(svec(["S2", "N2", "89.7%"]))
-                ]
+                ],
+                page_size: None,
+                sticky_header: None,
+                rich_rows: None,
             }
         );
         Ok(())
@@ -79,9 +472,197 @@ S2,N2,89.7%
                 rows: vec![
                     TableRow(svec(["S1", "N1", "83.2%"])),
                     TableRow(svec(["S2", "N2", "89.7%"]))
-                ]
+                ],
+                page_size: None,
+                sticky_header: None,
+                rich_rows: None,
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_metrics_csv() -> Result<()> {
+        let table = TableMetric::from_metrics_csv(METRICS_CSV.as_bytes())?;
+        let expected = [
+            ("Estimated Number of Cells", "3,487"),
+            ("Mean Reads per Cell", "96,123"),
+            ("Median Genes per Cell", "1,850"),
+            ("Number of Reads", "335,174,516"),
+            ("Valid Barcodes", "97.3%"),
+            ("Sequencing Saturation", "56.1%"),
+            ("Fraction Reads in Cells", "93,6%"),
+            ("Mean Cell Size", "1,234.56"),
+        ];
+        assert_eq!(
+            table.rows,
+            expected
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_metrics_csv_in_locale() -> Result<()> {
+        let table =
+            TableMetric::from_metrics_csv_in_locale(METRICS_CSV.as_bytes(), NumberLocale::EuComma)?;
+        let value = |name: &str| {
+            table
+                .rows
+                .iter()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.as_str())
+        };
+        assert_eq!(value("Number of Reads"), Some("335.174.516"));
+        assert_eq!(value("Mean Cell Size"), Some("1.234,56"));
+        // Values which are already formatted are passed through
+        assert_eq!(value("Valid Barcodes"), Some("97.3%"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_metrics_csv_multiple_rows() {
+        let data = format!("{METRICS_CSV}3487,96123,1850,335174516,97.3%,56.1%,93.6%,1.0\n");
+        assert_eq!(
+            TableMetric::from_metrics_csv(data.as_bytes())
+                .unwrap_err()
+                .to_string(),
+            "Expected a single row of metric values in the metrics csv, found 2 rows"
+        );
+    }
+
+    #[test]
+    fn test_from_csv_with_types() -> Result<()> {
+        let data = "\
+Sample,Cells,Fraction,Note
+S1,12345,0.5,1
+S2,987,0.125,
+S3,-1000000,-2,x
+";
+        let table = GenericTable::from_csv_with_types(data.as_bytes(), true)?;
+        let svec =
+            |v: [&str; 4]| -> Vec<String> { v.into_iter().map(ToString::to_string).collect() };
+        assert_eq!(
+            table.rows,
+            vec![
+                TableRow(svec(["S1", "12,345", "0.500", "1"])),
+                TableRow(svec(["S2", "987", "0.125", ""])),
+                TableRow(svec(["S3", "-1,000,000", "-2.000", "x"])),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_csv_summarizer() -> Result<()> {
+        let dir = ScratchDir::new("csv_summarizer");
+        let path = dir.join("barcodes.csv");
+        {
+            use std::io::Write;
+            let mut writer = std::io::BufWriter::new(std::fs::File::create(&path)?);
+            writeln!(writer, "barcode,umis,fraction,sample")?;
+            for i in 0..100_000 {
+                let fraction = if i % 2 == 0 { "0.5" } else { "" };
+                writeln!(writer, "BC{i},{},{fraction},S{}", i * 10, i % 3)?;
             }
+        }
+
+        let mut progress = Vec::new();
+        let summary = CsvSummarizer::new()
+            .preview_rows(5)
+            .max_distinct(100)
+            .progress(25_000, |n| progress.push(n))
+            .summarize_file(&path)?;
+
+        assert_eq!(progress, [25_000, 50_000, 75_000, 100_000]);
+        assert_eq!(summary.num_records, 100_000);
+        assert_eq!(summary.preview.rows.len(), 5);
+        assert_eq!(
+            summary.preview.rows[1].0,
+            ["BC1", "10", "", "S1"].map(String::from)
+        );
+        assert_eq!(
+            summary.columns,
+            vec![
+                ColumnStats {
+                    name: "barcode".into(),
+                    count: 100_000,
+                    numeric: None,
+                    num_distinct: None,
+                },
+                ColumnStats {
+                    name: "umis".into(),
+                    count: 100_000,
+                    numeric: Some(NumericStats {
+                        min: 0.0,
+                        mean: 499_995.0,
+                        max: 999_990.0,
+                    }),
+                    num_distinct: None,
+                },
+                ColumnStats {
+                    name: "fraction".into(),
+                    count: 50_000,
+                    numeric: Some(NumericStats {
+                        min: 0.5,
+                        mean: 0.5,
+                        max: 0.5,
+                    }),
+                    num_distinct: Some(1),
+                },
+                ColumnStats {
+                    name: "sample".into(),
+                    count: 100_000,
+                    numeric: None,
+                    num_distinct: Some(3),
+                },
+            ]
+        );
+        assert_eq!(
+            summary.stats.rows[1].0,
+            ["umis", "100,000", "> 100", "0", "499,995", "999,990"].map(String::from)
+        );
+        assert_eq!(
+            summary.template(Some("barcodes".into())),
+            "<div data-key=\"barcodes.stats\" data-component=\"Table\"></div>\n\
+             <div data-key=\"barcodes.preview\" data-component=\"Table\"></div>"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_csv_summarizer_no_headers() -> Result<()> {
+        let data = "S1,12345\nS2,987.5\nS3\n";
+        let summary = CsvSummarizer::new()
+            .has_headers(false)
+            .summarize_reader(data.as_bytes())?;
+        assert_eq!(summary.preview.header, None);
+        assert_eq!(
+            summary.preview.rows[0].0,
+            ["S1", "12,345.0"].map(String::from)
+        );
+        assert_eq!(
+            summary.stats.rows[1].0,
+            ["Column 2", "2", "2", "987.5", "6,666.25", "12,345"].map(String::from)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_csv_summarizer_formatted_numbers() -> Result<()> {
+        let data = "sample,reads,saturation\nS1,\"1,234.5\",\"56,1\"\nS2,1.234.567,\"60,3\"\n";
+        let summary = CsvSummarizer::new().summarize_reader(data.as_bytes())?;
+        assert_eq!(
+            summary.columns[1].numeric,
+            Some(NumericStats {
+                min: 1234.5,
+                mean: (1234.5 + 1234567.0) / 2.0,
+                max: 1234567.0,
+            })
         );
+        assert_eq!(summary.columns[2].numeric.unwrap().max, 60.3);
         Ok(())
     }
 }
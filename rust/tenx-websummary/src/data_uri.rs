@@ -0,0 +1,239 @@
+//!
+//! Validation of the base64 encoded data URIs used for images.
+//!
+//! The fallible constructors (`try_new`) of the image components always
+//! validate the images. The infallible constructors only do so, panicking on
+//! an invalid image, when strict images are enabled using the `strict-images`
+//! feature or `set_strict_images(true)`.
+//!
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Mime types of images supported by the browsers we care about
+const ALLOWED_MIME_TYPES: [&str; 6] = [
+    "image/png",
+    "image/jpeg",
+    "image/jpg",
+    "image/webp",
+    "image/gif",
+    "image/svg+xml",
+];
+
+const MAX_QUOTED_CHARS: usize = 40;
+
+static STRICT_IMAGES: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DataUriError {
+    /// Does not start with `data:`, e.g. a path to an image file
+    NotDataUri {
+        start: String,
+    },
+    /// The data URI is not base64 encoded
+    NotBase64Encoded {
+        mime: String,
+    },
+    UnsupportedMime {
+        mime: String,
+    },
+    InvalidBase64 {
+        reason: String,
+    },
+}
+
+impl std::fmt::Display for DataUriError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataUriError::NotDataUri { start } => {
+                write!(f, "Expected an image data URI, found {start:?}")
+            }
+            DataUriError::NotBase64Encoded { mime } => {
+                write!(
+                    f,
+                    "Image data URI with mime type {mime} is not base64 encoded"
+                )
+            }
+            DataUriError::UnsupportedMime { mime } => write!(
+                f,
+                "Unsupported mime type {mime:?} in image data URI. Expected one of {}",
+                ALLOWED_MIME_TYPES.join(", ")
+            ),
+            DataUriError::InvalidBase64 { reason } => {
+                write!(f, "Invalid base64 payload in image data URI: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DataUriError {}
+
+/// Validate the payload using the forgiving base64 decode used by browsers,
+/// which allows whitespace and missing padding
+fn validate_base64(payload: &str) -> Result<(), String> {
+    let payload: Vec<u8> = payload
+        .bytes()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect();
+    if payload.is_empty() {
+        return Err("payload is empty".into());
+    }
+    let data = match payload.len() % 4 {
+        0 => payload
+            .strip_suffix(b"==")
+            .or_else(|| payload.strip_suffix(b"="))
+            .unwrap_or(&payload),
+        _ => &payload,
+    };
+    if data.len() % 4 == 1 {
+        return Err(format!(
+            "length {} is not valid, the payload is likely truncated",
+            payload.len()
+        ));
+    }
+    match data
+        .iter()
+        .position(|&b| !(b.is_ascii_alphanumeric() || b == b'+' || b == b'/'))
+    {
+        Some(pos) => Err(format!(
+            "unexpected character {:?} at position {pos}",
+            char::from(data[pos])
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Check that the string is a `data:<mime>;base64,<payload>` URI with an
+/// image mime type and a payload which decodes as base64
+pub fn validate_data_uri(s: &str) -> Result<(), DataUriError> {
+    let Some(rest) = s.strip_prefix("data:") else {
+        return Err(DataUriError::NotDataUri {
+            start: s.chars().take(MAX_QUOTED_CHARS).collect(),
+        });
+    };
+    let (header, payload) = rest.split_once(',').unwrap_or((rest, ""));
+    let Some(mime) = header.strip_suffix(";base64") else {
+        return Err(DataUriError::NotBase64Encoded {
+            mime: header.to_string(),
+        });
+    };
+    if !ALLOWED_MIME_TYPES.contains(&mime.to_ascii_lowercase().as_str()) {
+        return Err(DataUriError::UnsupportedMime {
+            mime: mime.to_string(),
+        });
+    }
+    validate_base64(payload).map_err(|reason| DataUriError::InvalidBase64 { reason })
+}
+
+/// Enable or disable the validation of images in the infallible constructors
+/// at runtime. Always enabled with the `strict-images` feature.
+pub fn set_strict_images(strict: bool) {
+    STRICT_IMAGES.store(strict, Ordering::Relaxed);
+}
+
+pub fn strict_images() -> bool {
+    cfg!(feature = "strict-images") || STRICT_IMAGES.load(Ordering::Relaxed)
+}
+
+/// Called from the infallible constructors
+pub(crate) fn check_if_strict(s: &str) {
+    if strict_images() {
+        if let Err(e) = validate_data_uri(s) {
+            panic!("{e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &str = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII=";
+    const JPEG: &str = "data:image/jpeg;base64,/9j/4AAQSkZJRgABAQAAAQABAAD/2wBDAAgGBgcGBQgHBwcJCQgKDBQNDAsLDBkSEw8UHRofHh0aHBwgJC4nICIsIxwcKDcpLDAxNDQ0Hyc5PTgyPC4zNDL/wAALCAABAAEBAREA/8QAFAABAAAAAAAAAAAAAAAAAAAACf/EABQQAQAAAAAAAAAAAAAAAAAAAAD/2gAIAQEAAD8AKp//2Q==";
+    const WEBP: &str = "data:image/webp;base64,UklGRhoAAABXRUJQVlA4TA0AAAAvAAAAEAcQERGIiP4HAA==";
+
+    #[test]
+    fn test_valid_data_uri() {
+        for uri in [PNG, JPEG, WEBP] {
+            assert_eq!(validate_data_uri(uri), Ok(()), "{uri}");
+        }
+        // Padding is optional
+        assert_eq!(validate_data_uri(PNG.trim_end_matches('=')), Ok(()));
+    }
+
+    #[test]
+    fn test_path_instead_of_data_uri() {
+        let err =
+            validate_data_uri("/mnt/analysis/outs/spatial/tissue_lowres_image.png").unwrap_err();
+        assert_eq!(
+            err,
+            DataUriError::NotDataUri {
+                start: "/mnt/analysis/outs/spatial/tissue_lowres".into()
+            }
+        );
+        assert!(err.to_string().starts_with("Expected an image data URI"));
+    }
+
+    #[test]
+    fn test_truncated_payload() {
+        assert!(matches!(
+            validate_data_uri(&PNG[..PNG.len() - 3]),
+            Err(DataUriError::InvalidBase64 { .. })
+        ));
+        assert!(matches!(
+            validate_data_uri("data:image/png;base64,"),
+            Err(DataUriError::InvalidBase64 { .. })
+        ));
+    }
+
+    #[test]
+    fn test_invalid_header_and_payload() {
+        assert_eq!(
+            validate_data_uri("data:image/png,rawbytes"),
+            Err(DataUriError::NotBase64Encoded {
+                mime: "image/png".into()
+            })
+        );
+        assert_eq!(
+            validate_data_uri("data:text/html;base64,AAAA"),
+            Err(DataUriError::UnsupportedMime {
+                mime: "text/html".into()
+            })
+        );
+        assert_eq!(
+            validate_data_uri("data:image/png;base64,AA%A"),
+            Err(DataUriError::InvalidBase64 {
+                reason: "unexpected character '%' at position 2".into()
+            })
+        );
+    }
+
+    #[test]
+    fn test_try_new_constructors() {
+        use crate::components::{
+            BlendedImage, BlendedImageSliderSize, HdClusteringSingleClusterData,
+            HdEndToEndAlignmentUmiLegendImage, LabeledImage, NumOrStr, RawImage,
+        };
+        let size = || BlendedImageSliderSize {
+            width: NumOrStr::Num(300),
+        };
+
+        assert!(RawImage::try_new(PNG.into()).is_ok());
+        assert!(RawImage::try_new("tissue.png".into()).is_err());
+        assert!(LabeledImage::try_new(WEBP.into()).is_ok());
+        assert!(LabeledImage::try_new("data:image/png;base64,A".into()).is_err());
+        assert!(BlendedImage::try_new(PNG.into(), JPEG.into(), size()).is_ok());
+        assert!(BlendedImage::try_new(PNG.into(), "tissue.png".into(), size()).is_err());
+        assert!(HdEndToEndAlignmentUmiLegendImage::try_new("viridis", PNG.into()).is_ok());
+        assert!(HdEndToEndAlignmentUmiLegendImage::try_new("viridis", "".into()).is_err());
+        assert!(
+            HdClusteringSingleClusterData::try_new("1", "#1f77b4", PNG.into(), PNG.into()).is_ok()
+        );
+        assert!(HdClusteringSingleClusterData::try_new(
+            "1",
+            "#1f77b4",
+            PNG.into(),
+            "umap.png".into()
+        )
+        .is_err());
+    }
+}
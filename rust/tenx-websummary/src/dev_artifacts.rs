@@ -0,0 +1,160 @@
+//!
+//! Write the pieces of a web summary as separate files for authoring
+//! templates and components against a dev server, instead of the all-in-one
+//! HTML.
+//!
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error};
+use serde::Serialize;
+
+use crate::{HtmlTemplate, SinglePageHtml};
+
+pub const SUMMARY_HTML: &str = "summary.html";
+pub const DATA_JSON: &str = "data.json";
+pub const INDEX_HTML: &str = "index.html";
+
+/// Minimal page which pulls in the summary and the data using the same
+/// placeholders as the web summary template
+const INDEX_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="utf-8" />
+    <style>[[ tenx-websummary-styles.min.css ]]</style>
+</head>
+<body>
+[[ include summary.html ]]
+<script>
+      const data = [[ data.js ]]
+</script>
+<script>[[ tenx-websummary-script.min.js ]]</script>
+</body>
+</html>
+"#;
+
+fn write_file(dir: &Path, filename: &str, contents: &str) -> Result<PathBuf, Error> {
+    let path = dir.join(filename);
+    std::fs::write(&path, contents).with_context(|| format!("Failed to write {path:?}"))?;
+    Ok(path)
+}
+
+impl<P: Serialize + HtmlTemplate> SinglePageHtml<P> {
+    /// Write `summary.html` holding the template of the page and `data.json`
+    /// holding the pretty printed data into the directory, creating it if
+    /// needed. Both are the ones `generate_html` embeds, e.g. without the
+    /// experimental content if it is stripped. Returns the paths written.
+    pub fn write_dev_artifacts(self, dir: &Path) -> Result<Vec<PathBuf>, Error> {
+        let (json_data, template) = self.render()?;
+        let data: serde_json::Value = serde_json::from_str(&json_data)?;
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {dir:?}"))?;
+        Ok(vec![
+            write_file(dir, SUMMARY_HTML, &template)?,
+            write_file(dir, DATA_JSON, &serde_json::to_string_pretty(&data)?)?,
+        ])
+    }
+
+    /// Same as `write_dev_artifacts`, but also writes an `index.html` which
+    /// includes `summary.html` and the data
+    pub fn write_dev_artifacts_with_index(self, dir: &Path) -> Result<Vec<PathBuf>, Error> {
+        let mut paths = self.write_dev_artifacts(dir)?;
+        paths.push(write_file(dir, INDEX_HTML, INDEX_TEMPLATE)?);
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::components::{Experimental, HeroMetric, TableMetric};
+    use crate::scrape_json::scrape_json_from_html;
+    use crate::test_utils::{test_build_files, ScratchDir};
+    use crate::WebSummary;
+
+    fn summary() -> WebSummary {
+        WebSummary::builder()
+            .nav("Cell Ranger", "sample1", "PBMC")
+            .section_plain("hero", HeroMetric::new("Number of cells", "3,487"))
+            .section_plain(
+                "table",
+                TableMetric {
+                    rows: vec![("Number of reads".into(), "335,174,516".into())],
+                    row_ids: Vec::new(),
+                },
+            )
+            .build()
+    }
+
+    #[test]
+    fn test_write_dev_artifacts() -> Result<(), Error> {
+        let dir = ScratchDir::new("dev_artifacts_plain");
+        let paths = summary().write_dev_artifacts(&dir)?;
+        assert_eq!(paths, [dir.join(SUMMARY_HTML), dir.join(DATA_JSON)]);
+        assert!(!dir.join(INDEX_HTML).exists());
+
+        let summary_html = std::fs::read_to_string(&paths[0])?;
+        let data_json = std::fs::read_to_string(&paths[1])?;
+        assert!(data_json.contains("\n  \"hero\": {"));
+
+        let mut inline = Vec::new();
+        summary().generate_html_with_build_files(&mut inline, test_build_files())?;
+        assert!(String::from_utf8(inline.clone())?.contains(&summary_html));
+        assert_eq!(
+            serde_json::from_str::<Value>(&data_json)?,
+            scrape_json_from_html(inline.as_slice())?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_dev_artifacts_strip_experimental() -> Result<(), Error> {
+        let dir = ScratchDir::new("dev_artifacts_experimental");
+        let paths = WebSummary::builder()
+            .section_plain("hero", HeroMetric::new("Number of cells", "3,487"))
+            .section_plain(
+                "draft",
+                Experimental::new(HeroMetric::new("Doublet rate", "1.2%")),
+            )
+            .build()
+            .strip_experimental()
+            .write_dev_artifacts(&dir)?;
+        assert!(!std::fs::read_to_string(&paths[0])?.contains(r#"data-key="draft""#));
+        let data: Value = serde_json::from_str(&std::fs::read_to_string(&paths[1])?)?;
+        assert!(data["draft"].is_null());
+        assert_eq!(data["hero"]["metric"], "3,487");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_dev_artifacts_with_index() -> Result<(), Error> {
+        let dir = ScratchDir::new("dev_artifacts_index");
+        let paths = summary().write_dev_artifacts_with_index(&dir)?;
+        assert_eq!(paths.len(), 3);
+        assert_eq!(paths[2], dir.join(INDEX_HTML));
+
+        // Filling in the index the way the dev server does gives the same
+        // summary and data
+        let index = std::fs::read_to_string(&paths[2])?
+            .replace(
+                "[[ include summary.html ]]",
+                &std::fs::read_to_string(&paths[0])?,
+            )
+            .replace(
+                "[[ data.js ]]",
+                &serde_json::to_string(&serde_json::from_str::<Value>(&std::fs::read_to_string(
+                    &paths[1],
+                )?)?)?,
+            );
+        assert!(index.contains(&summary().template(None)));
+        assert_eq!(
+            scrape_json_from_html(index.as_bytes())?,
+            serde_json::to_value(summary())?
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,363 @@
+//!
+//! Export the metrics and alerts in a summary as CSV or JSON, e.g. for
+//! ingestion into a LIMS, without parsing the html.
+//!
+
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{GroupedTableMetric, HeroMetric, TableMetric, Threshold};
+use crate::visit::{walk_content, ComponentVisitor};
+use crate::{Alert, SinglePageHtml};
+
+/// A metric from a `HeroMetric` or a row of a `TableMetric`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExportedMetric {
+    /// Location of the metric in the summary data, using the same syntax as
+    /// the data keys, e.g. `tabs.tab_data[0].grid_data[1]`
+    pub path: String,
+    pub name: String,
+    pub value: String,
+    pub threshold: Option<Threshold>,
+    /// Stable id of the metric, if it has one
+    pub id: Option<String>,
+}
+
+impl ExportedMetric {
+    /// The id of the metric, or its name if it has no id. Use it to match
+    /// metrics across releases, since names are reworded.
+    pub fn key(&self) -> &str {
+        self.id.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// The metric with the given id. Metrics without an id are matched by name,
+/// so that summaries written before the ids keep working.
+pub fn find_metric_by_id<'a>(
+    metrics: &'a [ExportedMetric],
+    id: &str,
+) -> Option<&'a ExportedMetric> {
+    metrics
+        .iter()
+        .find(|metric| metric.id.as_deref() == Some(id))
+        .or_else(|| {
+            metrics
+                .iter()
+                .find(|metric| metric.id.is_none() && metric.name == id)
+        })
+}
+
+struct MetricCollector {
+    metrics: Vec<ExportedMetric>,
+}
+
+impl ComponentVisitor for MetricCollector {
+    fn visit_hero_metric(&mut self, metric: &HeroMetric, path: &str) {
+        self.metrics.push(ExportedMetric {
+            path: path.to_string(),
+            name: metric.name.clone(),
+            value: metric.metric.clone(),
+            threshold: metric.threshold.clone(),
+            id: metric.id.clone(),
+        });
+    }
+    fn visit_table_metric(&mut self, table: &TableMetric, path: &str) {
+        self.metrics
+            .extend(
+                table
+                    .rows
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (name, value))| ExportedMetric {
+                        path: format!("{path}.rows[{i}]"),
+                        name: name.clone(),
+                        value: value.clone(),
+                        threshold: None,
+                        id: table.get_row_id(i).map(ToString::to_string),
+                    }),
+            );
+    }
+    fn visit_grouped_table_metric(&mut self, table: &GroupedTableMetric, path: &str) {
+        for (g, group) in table.groups.iter().enumerate() {
+            for (i, (name, value)) in group.rows.iter().enumerate() {
+                self.metrics.push(ExportedMetric {
+                    path: format!("{path}.groups[{g}].rows[{i}]"),
+                    name: name.clone(),
+                    value: value.clone(),
+                    threshold: None,
+                    id: group.row_ids.get(i).cloned().flatten(),
+                });
+            }
+        }
+    }
+}
+
+impl<P: Serialize> SinglePageHtml<P> {
+    /// All the `HeroMetric`s and the rows of all the `TableMetric`s and
+    /// `GroupedTableMetric`s in the content of the summary
+    pub fn export_metrics(&self) -> Result<Vec<ExportedMetric>, Error> {
+        let mut collector = MetricCollector {
+            metrics: Vec::new(),
+        };
+        walk_content(&serde_json::to_value(self)?, &mut collector);
+        Ok(collector.metrics)
+    }
+
+    /// The alerts at the top of the page followed by the alerts of the
+    /// groups, see `Alert::in_group()`
+    pub fn export_alerts(&self) -> Vec<Alert> {
+        self.all_alerts()
+    }
+}
+
+/// Write the exported metrics or alerts as a JSON array
+pub fn write_json<T: Serialize, W: std::io::Write>(records: &[T], writer: W) -> Result<(), Error> {
+    serde_json::to_writer_pretty(writer, records)?;
+    Ok(())
+}
+
+/// Write the exported metrics or alerts as a CSV with a header
+#[cfg(feature = "csv_table")]
+pub fn write_csv<T: Serialize, W: std::io::Write>(records: &[T], writer: W) -> Result<(), Error> {
+    let mut wtr = csv::Writer::from_writer(writer);
+    for record in records {
+        wtr.serialize(record)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{DynGrid, GridLayout, HeroMetric, TableMetric, Tabs};
+    use crate::metrics_mapping::MetricFormat;
+    use crate::{AlertLevel, WebSummary};
+
+    fn summary() -> WebSummary {
+        let mut grid = DynGrid::new(GridLayout::MaxCols(2));
+        grid.push(HeroMetric::new("Number of cells", "3,487"));
+        grid.push(HeroMetric::with_threshold(
+            "Valid barcodes",
+            "97.3%",
+            Threshold::Pass,
+        ));
+        let tabs = Tabs::new().tab("Summary", grid).tab(
+            "Sequencing",
+            TableMetric {
+                rows: vec![
+                    ("Number of reads".into(), "335,174,516".into()),
+                    ("Sequencing saturation".into(), "56.1%".into()),
+                ],
+                row_ids: Vec::new(),
+            },
+        );
+        WebSummary::builder()
+            .nav("Cell Ranger", "sample1", "PBMC")
+            .alert(Alert {
+                level: AlertLevel::Warn,
+                title: "Low fraction reads in cells".into(),
+                formatted_value: Some("50%".into()),
+                message: "Ideal > 70%".into(),
+                group: None,
+            })
+            .section_plain("hero", HeroMetric::new("Median genes per cell", "1,850"))
+            .section_plain("tabs", tabs)
+            .build()
+    }
+
+    fn metric(path: &str, name: &str, value: &str, threshold: Option<Threshold>) -> ExportedMetric {
+        ExportedMetric {
+            path: path.into(),
+            name: name.into(),
+            value: value.into(),
+            threshold,
+            id: None,
+        }
+    }
+
+    #[test]
+    fn test_export_metrics() -> Result<(), Error> {
+        let mut metrics = summary().export_metrics()?;
+        metrics.sort_by(|m1, m2| m1.path.cmp(&m2.path));
+        assert_eq!(
+            metrics,
+            vec![
+                metric("hero", "Median genes per cell", "1,850", None),
+                metric(
+                    "tabs.tab_data[0].grid_data[0]",
+                    "Number of cells",
+                    "3,487",
+                    None
+                ),
+                metric(
+                    "tabs.tab_data[0].grid_data[1]",
+                    "Valid barcodes",
+                    "97.3%",
+                    Some(Threshold::Pass)
+                ),
+                metric(
+                    "tabs.tab_data[1].rows[0]",
+                    "Number of reads",
+                    "335,174,516",
+                    None
+                ),
+                metric(
+                    "tabs.tab_data[1].rows[1]",
+                    "Sequencing saturation",
+                    "56.1%",
+                    None
+                ),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_metric_with_comparison() -> Result<(), Error> {
+        let hero = HeroMetric::new("Valid barcodes", "97.3%").with_comparison(
+            0.973,
+            0.951,
+            MetricFormat::Percent,
+            true,
+        );
+        assert!(hero.comparison.is_some());
+        let summary = WebSummary::builder().section_plain("hero", hero).build();
+        assert_eq!(
+            summary.export_metrics()?,
+            [metric(
+                "hero",
+                "Valid barcodes",
+                "97.3%",
+                Some(Threshold::Pass)
+            )]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_grouped_table_metric() -> Result<(), Error> {
+        let mut table = GroupedTableMetric::from_rows([
+            ("Sequencing", "Number of reads", "335,174,516"),
+            ("Sequencing", "Valid barcodes", "97.3%"),
+            ("Cells", "Estimated number of cells", "3,487"),
+        ]);
+        table.groups[1].row_ids = vec![Some("num_cells".into())];
+        let summary = WebSummary::builder()
+            .section_plain("hero", HeroMetric::new("Median genes per cell", "1,850"))
+            .section_plain("grouped", table)
+            .build();
+        let mut metrics = summary.export_metrics()?;
+        metrics.sort_by(|m1, m2| m1.path.cmp(&m2.path));
+        assert_eq!(
+            metrics,
+            [
+                metric(
+                    "grouped.groups[0].rows[0]",
+                    "Number of reads",
+                    "335,174,516",
+                    None
+                ),
+                metric("grouped.groups[0].rows[1]", "Valid barcodes", "97.3%", None),
+                ExportedMetric {
+                    id: Some("num_cells".into()),
+                    ..metric(
+                        "grouped.groups[1].rows[0]",
+                        "Estimated number of cells",
+                        "3,487",
+                        None,
+                    )
+                },
+                metric("hero", "Median genes per cell", "1,850", None),
+            ]
+        );
+        assert_eq!(
+            find_metric_by_id(&metrics, "num_cells").unwrap().value,
+            "3,487"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_metric_ids() -> Result<(), Error> {
+        let summary = WebSummary::builder()
+            .section_plain(
+                "hero",
+                HeroMetric::new("Valid barcodes", "97.3%").id("valid_barcodes_frac"),
+            )
+            .section_plain(
+                "table",
+                TableMetric {
+                    rows: vec![
+                        ("Number of reads".into(), "335,174,516".into()),
+                        ("Reads mapped to genome".into(), "95.1%".into()),
+                    ],
+                    row_ids: Vec::new(),
+                }
+                .row_id(1, "mapped_reads_frac"),
+            )
+            .build();
+        let metrics = summary.export_metrics()?;
+        let ids: Vec<_> = metrics.iter().map(ExportedMetric::key).collect();
+        assert_eq!(
+            ids,
+            [
+                "valid_barcodes_frac",
+                "Number of reads",
+                "mapped_reads_frac"
+            ]
+        );
+
+        let metric = find_metric_by_id(&metrics, "mapped_reads_frac").unwrap();
+        assert_eq!(metric.value, "95.1%");
+        // The id is preferred over a name which happens to be the same
+        let mut renamed = metrics.clone();
+        renamed[0].name = "mapped_reads_frac".into();
+        assert_eq!(
+            find_metric_by_id(&renamed, "mapped_reads_frac")
+                .unwrap()
+                .path,
+            "table.rows[1]"
+        );
+        // Metrics without an id are found by name, but not those with an id
+        assert!(find_metric_by_id(&metrics, "Number of reads").is_some());
+        assert!(find_metric_by_id(&metrics, "Valid barcodes").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_alerts_json() -> Result<(), Error> {
+        let alerts = summary().export_alerts();
+        assert_eq!(alerts.len(), 1);
+        let mut out = Vec::new();
+        write_json(&alerts, &mut out)?;
+        let json: serde_json::Value = serde_json::from_slice(&out)?;
+        assert_eq!(json[0]["level"], "WARN");
+        assert_eq!(json[0]["formatted_value"], "50%");
+        Ok(())
+    }
+
+    #[cfg(feature = "csv_table")]
+    #[test]
+    fn test_write_csv() -> Result<(), Error> {
+        let metrics = vec![
+            metric("hero", "Median genes per cell", "1,850", None),
+            metric(
+                "grid_data[0]",
+                "Valid barcodes",
+                "97.3%",
+                Some(Threshold::Warn),
+            ),
+        ];
+        let mut out = Vec::new();
+        write_csv(&metrics, &mut out)?;
+        assert_eq!(
+            String::from_utf8(out)?,
+            "path,name,value,threshold,id
+hero,Median genes per cell,\"1,850\",,
+grid_data[0],Valid barcodes,97.3%,warn,
+"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,270 @@
+//!
+//! A minimal page written in place of a summary whose data cannot be
+//! serialized, e.g. because a map has non-string keys or a component fails
+//! to serialize deep inside the content. A degraded page with the nav bar,
+//! the alerts and the metrics is more useful than no summary at all.
+//!
+//! The content of the fallback page is restricted to types made of strings,
+//! so that rendering it cannot fail.
+//!
+
+use std::io::Write;
+
+use anyhow::Error;
+use serde::Serialize;
+
+use crate::components::{EmptyState, GenericTable, WsNavBar};
+use crate::generate_html::{generate_html_summary_with_head, TemplateInfo};
+use crate::{Alert, AlertLevel, Alerts, HtmlTemplate, SinglePageHtml, WebSummaryBuildFiles};
+
+/// Title of the alert reporting why the summary was not generated
+pub const FALLBACK_ALERT_TITLE: &str = "The full summary could not be generated";
+
+/// The page written when the summary cannot be rendered
+#[derive(Debug, Clone)]
+pub struct FallbackPage {
+    nav_bar: Option<WsNavBar>,
+    alerts: Alerts,
+    message: EmptyState,
+    table: Option<GenericTable>,
+}
+
+impl FallbackPage {
+    pub fn new(message: impl ToString) -> Self {
+        FallbackPage {
+            nav_bar: None,
+            alerts: Alerts::default(),
+            message: EmptyState::new(message).title("Summary unavailable"),
+            table: None,
+        }
+    }
+    /// The nav bar of the fallback page. The nav bar of the summary is used
+    /// if there is none.
+    pub fn nav_bar(mut self, nav_bar: WsNavBar) -> Self {
+        self.nav_bar = Some(nav_bar);
+        self
+    }
+    pub fn alerts(mut self, alerts: Alerts) -> Self {
+        self.alerts = alerts;
+        self
+    }
+    /// A table shown below the message, e.g. the metrics csv
+    pub fn table(mut self, table: GenericTable) -> Self {
+        self.table = Some(table);
+        self
+    }
+
+    fn into_page(self, error: &Error) -> SinglePageHtml<FallbackContent> {
+        let mut alerts = self.alerts;
+        alerts.alerts.insert(
+            0,
+            Alert {
+                level: AlertLevel::Error,
+                title: FALLBACK_ALERT_TITLE.to_string(),
+                formatted_value: None,
+                message: format!("{error:#}"),
+                group: None,
+            },
+        );
+        let page = SinglePageHtml::from_content(FallbackContent {
+            message: self.message,
+            table: self.table,
+        })
+        .alerts(alerts.alerts);
+        match self.nav_bar {
+            Some(nav_bar) => page.nav_bar(nav_bar),
+            None => page,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct FallbackContent {
+    message: EmptyState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    table: Option<GenericTable>,
+}
+
+impl HtmlTemplate for FallbackContent {
+    fn template(&self, _: Option<String>) -> String {
+        let mut template = self.message.template(Some("message".into()));
+        if let Some(table) = &self.table {
+            template.push('\n');
+            template.push_str(&table.template(Some("table".into())));
+        }
+        template
+    }
+}
+
+/// Which page `generate_html_or_fallback()` wrote
+#[derive(Debug)]
+pub enum HtmlOutcome {
+    Summary,
+    /// The fallback page, with the error which prevented the summary
+    Fallback(Error),
+}
+
+impl HtmlOutcome {
+    pub fn is_fallback(&self) -> bool {
+        matches!(self, HtmlOutcome::Fallback(_))
+    }
+}
+
+impl<P: Serialize + HtmlTemplate> SinglePageHtml<P> {
+    /// Same as `generate_html()`, writing the fallback page if the summary
+    /// cannot be rendered. Errors writing either page are returned.
+    #[cfg(feature = "generate_html")]
+    pub fn generate_html_or_fallback<W: Write>(
+        self,
+        writer: W,
+        fallback: FallbackPage,
+    ) -> Result<HtmlOutcome, Error> {
+        self.generate_html_or_fallback_with_build_files(
+            writer,
+            fallback,
+            WebSummaryBuildFiles::bundled(),
+        )
+    }
+
+    pub fn generate_html_or_fallback_with_build_files<W: Write>(
+        self,
+        writer: W,
+        mut fallback: FallbackPage,
+        build_files: WebSummaryBuildFiles<'_>,
+    ) -> Result<HtmlOutcome, Error> {
+        let head = self.page_head();
+        if fallback.nav_bar.is_none() {
+            fallback.nav_bar = self.nav_bar.clone();
+        }
+        // Nothing is written until the summary is rendered
+        match self.render() {
+            Ok((json_data, template)) => {
+                generate_html_summary_with_head(
+                    &json_data,
+                    template,
+                    TemplateInfo::<String>::Default,
+                    writer,
+                    build_files,
+                    &head,
+                )?;
+                Ok(HtmlOutcome::Summary)
+            }
+            Err(error) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("Writing the fallback page due to {error:?}");
+                fallback
+                    .into_page(&error)
+                    .generate_html_with_build_files(writer, build_files)?;
+                Ok(HtmlOutcome::Fallback(error))
+            }
+        }
+    }
+
+    /// Respond with the fallback page if the summary cannot be rendered,
+    /// instead of an internal server error
+    #[cfg(feature = "actix")]
+    pub fn or_fallback(self, fallback: FallbackPage) -> WithFallback<P> {
+        WithFallback {
+            page: self,
+            fallback,
+        }
+    }
+}
+
+/// A page to serve along with its fallback. See `SinglePageHtml::or_fallback()`.
+#[cfg(feature = "actix")]
+pub struct WithFallback<P> {
+    pub(crate) page: SinglePageHtml<P>,
+    pub(crate) fallback: FallbackPage,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::ser::Error as _;
+
+    use super::*;
+    use crate::components::{HeroMetric, TableRow};
+    use crate::scrape_json::scrape_json_from_html;
+    use crate::test_utils::test_build_files;
+
+    /// Fails to serialize, like a struct holding a map with non-string keys
+    struct Broken;
+
+    impl Serialize for Broken {
+        fn serialize<S: serde::Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+            Err(S::Error::custom("key must be a string"))
+        }
+    }
+
+    #[derive(Serialize)]
+    struct Content {
+        cells: HeroMetric,
+        clusters: Option<Broken>,
+    }
+
+    impl HtmlTemplate for Content {
+        fn template(&self, _: Option<String>) -> String {
+            self.cells.template(Some("cells".into()))
+        }
+    }
+
+    fn page(broken: bool) -> SinglePageHtml<Content> {
+        SinglePageHtml::from_content(Content {
+            cells: HeroMetric::new("Number of cells", "3,487"),
+            clusters: broken.then_some(Broken),
+        })
+        .nav_bar(WsNavBar::new("Cell Ranger", "sample1", "PBMC"))
+    }
+
+    fn fallback() -> FallbackPage {
+        FallbackPage::new("Showing the metrics only").table(GenericTable {
+            header: Some(vec!["Metric".into(), "Value".into()]),
+            rows: vec![TableRow::two_col("Number of cells", "3,487")],
+            page_size: None,
+            sticky_header: None,
+            rich_rows: None,
+        })
+    }
+
+    fn generate(page: SinglePageHtml<Content>) -> (HtmlOutcome, String) {
+        let mut html = Vec::new();
+        let outcome = page
+            .generate_html_or_fallback_with_build_files(&mut html, fallback(), test_build_files())
+            .unwrap();
+        (outcome, String::from_utf8(html).unwrap())
+    }
+
+    #[test]
+    fn test_fallback_on_serialization_error() {
+        let (outcome, html) = generate(page(true));
+        let HtmlOutcome::Fallback(error) = outcome else {
+            panic!("Expected the fallback page");
+        };
+        assert_eq!(error.to_string(), "key must be a string");
+
+        let data = scrape_json_from_html(html.as_bytes()).unwrap();
+        assert_eq!(data["sample"]["id"], "sample1");
+        assert_eq!(data["alarms"]["alarms"][0]["title"], FALLBACK_ALERT_TITLE);
+        assert_eq!(
+            data["alarms"]["alarms"][0]["message"],
+            "key must be a string"
+        );
+        assert_eq!(data["message"]["message"], "Showing the metrics only");
+        assert_eq!(
+            data["table"]["rows"][0],
+            serde_json::json!(["Number of cells", "3,487"])
+        );
+        assert!(data.get("cells").is_none());
+        assert!(html.contains(r#"<div data-key="message" data-component="EmptyState"></div>"#));
+        assert!(html.contains(r#"<div data-key="table" data-component="Table"></div>"#));
+    }
+
+    #[test]
+    fn test_no_fallback() {
+        let (outcome, html) = generate(page(false));
+        assert!(!outcome.is_fallback());
+        let data = scrape_json_from_html(html.as_bytes()).unwrap();
+        assert_eq!(data["cells"]["metric"], "3,487");
+        assert!(data.get("message").is_none());
+    }
+}
@@ -0,0 +1,173 @@
+//!
+//! Load a summary which was written by hand or with `write_dev_artifacts()`
+//! from a directory holding `data.json` and `summary.html`, e.g. the
+//! fixtures of a pipeline's tests. The data keys in the summary html are
+//! checked when loading, so that a typo in hand edited JSON is reported
+//! right away instead of rendering as a blank component.
+//!
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Error};
+use itertools::Itertools;
+use serde_json::Value;
+
+use crate::check::{check_data_keys, IssueKind};
+use crate::dev_artifacts::{DATA_JSON, SUMMARY_HTML};
+use crate::generate_html::{
+    expand_includes, generate_html_summary_with_build_files, TemplateInfo, WebSummaryBuildFiles,
+    DEFAULT_MAX_INCLUDE_DEPTH,
+};
+
+/// The data and the summary html of a summary, loaded from a directory
+#[derive(Debug, Clone)]
+pub struct SummaryFixture {
+    /// The directory holding the files, which is also searched for a
+    /// `template.html` when rendering
+    pub dir: PathBuf,
+    /// Contents of `data.json`, as written
+    pub data_json: String,
+    /// Contents of `summary.html` with the includes expanded
+    pub summary_html: String,
+}
+
+impl SummaryFixture {
+    /// Read `data.json` and `summary.html` from the directory. The
+    /// `[[ include <filename> ]]` directives in the summary html are resolved
+    /// relative to the directory. Fails if a data key in the summary html
+    /// does not resolve in the data, listing the keys.
+    pub fn load(dir: &Path) -> Result<Self, Error> {
+        let read = |filename: &str| {
+            let path = dir.join(filename);
+            std::fs::read_to_string(&path).with_context(|| format!("Failed to read {path:?}"))
+        };
+        let data_json = read(DATA_JSON)?;
+        let data: Value = serde_json::from_str(&data_json)
+            .with_context(|| format!("Failed to parse {:?}", dir.join(DATA_JSON)))?;
+        let summary_html = expand_includes(
+            &read(SUMMARY_HTML)?,
+            Some(dir),
+            &mut Vec::new(),
+            DEFAULT_MAX_INCLUDE_DEPTH,
+        )?;
+
+        let dangling: Vec<_> = check_data_keys(&summary_html, &data)
+            .into_iter()
+            .filter_map(|issue| match issue {
+                IssueKind::DanglingDataKey(key) => Some(key),
+                _ => None,
+            })
+            .collect();
+        if !dangling.is_empty() {
+            bail!(
+                "The data keys in {:?} which do not resolve in {DATA_JSON}: {}",
+                dir.join(SUMMARY_HTML),
+                dangling.iter().join(", ")
+            );
+        }
+
+        Ok(SummaryFixture {
+            dir: dir.to_path_buf(),
+            data_json,
+            summary_html,
+        })
+    }
+
+    /// Render the complete html page
+    pub fn render(&self, build_files: WebSummaryBuildFiles<'_>) -> Result<String, Error> {
+        let mut html = Vec::new();
+        generate_html_summary_with_build_files(
+            &self.data_json,
+            self.summary_html.clone(),
+            TemplateInfo::Dynamic(&self.dir),
+            &mut html,
+            build_files,
+        )?;
+        Ok(String::from_utf8(html)?)
+    }
+}
+
+impl TryFrom<&Path> for SummaryFixture {
+    type Error = Error;
+
+    fn try_from(dir: &Path) -> Result<Self, Error> {
+        SummaryFixture::load(dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{test_build_files, ScratchDir};
+
+    fn fixture_dir(name: &str, files: &[(&str, &str)]) -> ScratchDir {
+        ScratchDir::with_files(&format!("fixture_{name}"), files)
+    }
+
+    #[test]
+    fn test_render_fixture() {
+        let data = r#"{"cells": {"name": "Cells", "metric": "3,487", "threshold": null},
+            "tabs": {"tab_data": [{"rows": [["Reads", "1M"]]}]}}"#;
+        let summary = r#"<div data-key="cells" data-component="Metric"></div>
+[[ include tabs.html ]]"#;
+        let dir = fixture_dir(
+            "render",
+            &[
+                (DATA_JSON, data),
+                (SUMMARY_HTML, summary),
+                (
+                    "tabs.html",
+                    r#"<div data-key="tabs.tab_data[0]" data-component="TableMetric"></div>"#,
+                ),
+            ],
+        );
+
+        let fixture = SummaryFixture::try_from(dir.path()).unwrap();
+        assert!(!fixture.summary_html.contains("[[ include"));
+
+        let mut expected = Vec::new();
+        generate_html_summary_with_build_files(
+            data,
+            summary.to_string(),
+            TemplateInfo::Dynamic(dir.path()),
+            &mut expected,
+            test_build_files(),
+        )
+        .unwrap();
+        assert_eq!(
+            fixture.render(test_build_files()).unwrap(),
+            String::from_utf8(expected).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_dangling_data_keys() {
+        let dir = fixture_dir(
+            "dangling",
+            &[
+                (DATA_JSON, r#"{"cells": {"metric": "3,487"}, "tabs": []}"#),
+                (
+                    SUMMARY_HTML,
+                    r#"<div data-key="cells" data-component="Metric"></div>
+<div data-key="reads" data-component="Metric"></div>
+<div data-key="tabs[0]" data-component="Metric"></div>"#,
+                ),
+            ],
+        );
+        let err = SummaryFixture::load(&dir).unwrap_err().to_string();
+        assert!(
+            err.ends_with("do not resolve in data.json: reads, tabs[0]"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_missing_files() {
+        let dir = fixture_dir("missing", &[(SUMMARY_HTML, "")]);
+        let err = SummaryFixture::load(&dir).unwrap_err().to_string();
+        assert!(
+            err.starts_with("Failed to read") && err.contains(DATA_JSON),
+            "{err}"
+        );
+    }
+}
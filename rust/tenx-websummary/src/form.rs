@@ -1,20 +1,22 @@
-use std::{collections::HashSet, marker::PhantomData};
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    collections::{BTreeMap, HashMap, HashSet},
+    marker::PhantomData,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
 
 use crate::{
-    components::{ReactComponent, Title},
-    react_component, HtmlTemplate,
+    components::{
+        escape_html, GenericTable, RichCell, RichTableRow, StepProgress, Title, WsNavBar,
+    },
+    HtmlTemplate, SinglePageHtml,
 };
-use anyhow::Error;
+use anyhow::{bail, Error};
 use itertools::Itertools;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-
-// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
-// React components in this mod
-react_component!(InputFeedback, "InputFeedback");
-react_component!(InputElement, "InputElement");
-react_component!(SingleSelect, "SingleSelect");
-react_component!(MultiSelect, "MultiSelect");
-react_component!(TextArea, "TextArea");
+use serde_json::Value;
 
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
 // Input feedback
@@ -23,6 +25,9 @@ react_component!(TextArea, "TextArea");
 pub struct InputFeedback {
     pub error: Option<String>,
     pub text: Option<String>,
+    /// The raw message behind the error, for debugging. See `FriendlyError`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
 }
 
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
@@ -39,6 +44,9 @@ pub struct InputElement {
     step: Option<String>,
     placeholder: Option<String>,
     required: bool,
+    /// Maximum number of characters, see `MaxLength`
+    #[serde(rename = "maxLength", default, skip_serializing_if = "Option::is_none")]
+    max_length: Option<usize>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -71,6 +79,24 @@ pub struct SingleSelect {
     pub options: Vec<String>,
     pub selected: Option<String>,
     pub required: Option<bool>,
+    /// Free text input shown when the `option` of the `SelectOther` is
+    /// selected, see `OrOther`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub other: Option<SelectOther>,
+}
+
+/// The "Other (specify)" option of a `SingleSelect`. The option is the last
+/// one of the select and the text input is only shown while it is selected.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SelectOther {
+    /// Name of the text input, see `other_field_name()`
+    pub name: String,
+    /// The label of the option, also the `selected` value of the select
+    pub option: String,
+    /// Placeholder of the text input
+    pub placeholder: Option<String>,
+    /// The text entered for the option
+    pub value: Option<String>,
 }
 
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
@@ -104,6 +130,9 @@ pub struct TextArea {
     placeholder: Option<String>,
     required: Option<bool>,
     pub value: Option<String>,
+    /// Maximum number of characters, see `MaxLength`
+    #[serde(rename = "maxLength", default, skip_serializing_if = "Option::is_none")]
+    max_length: Option<usize>,
 }
 
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
@@ -118,12 +147,27 @@ pub struct SpreadsheetInput {
     pub n_cols: usize,
     pub max_height: Option<String>,
     pub value: Option<String>,
+    /// Columns which the user cannot edit
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub readonly_columns: Vec<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub computed_column: Option<ComputedColumn>,
+    /// Whether at least one row must be filled in, true unless set
+    #[serde(default = "spreadsheet_required_default")]
+    pub required: Option<bool>,
 }
 
-impl ReactComponent for SpreadsheetInput {
-    fn component_name() -> &'static str {
-        "SpreadsheetInput"
-    }
+fn spreadsheet_required_default() -> Option<bool> {
+    Some(true)
+}
+
+/// A readonly preview column shown after the last column, whose value is
+/// derived from the source columns, e.g. a sample name
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ComputedColumn {
+    pub label: String,
+    pub source_columns: Vec<usize>,
 }
 
 pub struct SpreadsheetInputConfig {
@@ -132,6 +176,36 @@ pub struct SpreadsheetInputConfig {
     pub n_cols: usize,
     pub max_height: Option<String>,
     pub value: Option<String>,
+    pub readonly_columns: Vec<usize>,
+    pub computed_column: Option<ComputedColumn>,
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+// Repeated group
+
+/// Placeholder for the index of a row in the names of the `template` of a
+/// `RepeatedGroupInput`, which the frontend replaces when it adds a row
+pub const GROUP_INDEX_PLACEHOLDER: &str = "__index__";
+
+/// Rows of the same fields which the user can add and remove, e.g. one row
+/// per library. The fields of a row are named `{name}[{index}].{field}`.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RepeatedGroupInput {
+    pub name: String,
+    /// Elements of a new row, cloned by the frontend when a row is added.
+    /// The names hold `GROUP_INDEX_PLACEHOLDER` in place of the index.
+    pub template: Vec<FormElement>,
+    /// Elements of each row, along with the feedback of each field
+    pub rows: Vec<Vec<FormElement>>,
+    pub min_rows: usize,
+    pub max_rows: Option<usize>,
+    pub required: Option<bool>,
+}
+
+/// Name of a field in a row of a repeated group, e.g. `libraries[2].lanes`
+pub fn group_field_name(group: &str, index: &str, field: &str) -> String {
+    format!("{group}[{index}].{field}")
 }
 
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
@@ -145,6 +219,7 @@ pub enum FormInput {
     MultiSelect(MultiSelect),
     SingleSelect(SingleSelect),
     Spreadsheet(SpreadsheetInput),
+    RepeatedGroup(RepeatedGroupInput),
 }
 
 impl FormInput {
@@ -154,7 +229,24 @@ impl FormInput {
             FormInput::TextArea(v) => v.required = Some(false),
             FormInput::MultiSelect(v) => v.required = Some(false),
             FormInput::SingleSelect(v) => v.required = Some(false),
-            FormInput::Spreadsheet(_) => {}
+            FormInput::Spreadsheet(v) => v.required = Some(false),
+            FormInput::RepeatedGroup(v) => {
+                v.required = Some(false);
+                // The rows of a hidden group are not validated either
+                for element in v.rows.iter_mut().flatten() {
+                    element.feedback = InputFeedback::default();
+                }
+            }
+        }
+    }
+    fn name_mut(&mut self) -> &mut String {
+        match self {
+            FormInput::Input(v) => &mut v.name,
+            FormInput::TextArea(v) => &mut v.name,
+            FormInput::MultiSelect(v) => &mut v.name,
+            FormInput::SingleSelect(v) => &mut v.name,
+            FormInput::Spreadsheet(v) => &mut v.name,
+            FormInput::RepeatedGroup(v) => &mut v.name,
         }
     }
 }
@@ -187,6 +279,12 @@ impl From<SpreadsheetInput> for FormInput {
     }
 }
 
+impl From<RepeatedGroupInput> for FormInput {
+    fn from(value: RepeatedGroupInput) -> Self {
+        FormInput::RepeatedGroup(value)
+    }
+}
+
 impl HtmlTemplate for FormInput {
     fn template(&self, data_key: Option<String>) -> String {
         let data_key = Some(
@@ -201,6 +299,7 @@ impl HtmlTemplate for FormInput {
             FormInput::MultiSelect(v) => v.template(data_key),
             FormInput::SingleSelect(v) => v.template(data_key),
             FormInput::Spreadsheet(v) => v.template(data_key),
+            FormInput::RepeatedGroup(v) => v.template(data_key),
         }
     }
 }
@@ -208,21 +307,101 @@ impl HtmlTemplate for FormInput {
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
 // Form element
 
-#[derive(Serialize, Deserialize, HtmlTemplate, Clone)]
-#[html(websummary_crate = "crate")]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct FormElement {
     pub title: Title,
     pub input: FormInput,
     pub feedback: InputFeedback,
+    /// Parsed rows of a table-like input, shown below it after validation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    preview: Option<GenericTable>,
+    /// The element is only shown when the rule is satisfied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visible_when: Option<VisibilityRule>,
+}
+
+/// One row per field, except the visibility rule which the form reads from
+/// the data
+impl HtmlTemplate for FormElement {
+    fn template(&self, data_key: Option<String>) -> String {
+        let field_key = |field: &str| match &data_key {
+            Some(key) => format!("{key}.{field}"),
+            None => field.to_string(),
+        };
+        [
+            self.title.template(Some(field_key("title"))),
+            self.input.template(Some(field_key("input"))),
+            self.feedback.template(Some(field_key("feedback"))),
+            self.preview.template(Some(field_key("preview"))),
+        ]
+        .into_iter()
+        .map(|template| {
+            format!("<div class=\"row\">\n<div class=\"col\">\n{template}\n</div>\n</div>\n")
+        })
+        .collect()
+    }
 }
 
 impl FormElement {
+    /// Element without feedback, shown unconditionally
+    pub fn new(title: Title, input: FormInput) -> Self {
+        FormElement {
+            title,
+            input,
+            feedback: InputFeedback::default(),
+            preview: None,
+            visible_when: None,
+        }
+    }
+    /// Parsed rows of a table-like input, set by `IntoHtmlForm::validate()`
+    pub fn preview(&self) -> Option<&GenericTable> {
+        self.preview.as_ref()
+    }
     pub fn update(&mut self, validation: FieldValidationResult) {
         match validation {
             FieldValidationResult::Valid => {}
             FieldValidationResult::Invalid { error } => {
                 self.feedback.error = Some(error);
             }
+            FieldValidationResult::InvalidWithDetails { error, details } => {
+                self.feedback.error = Some(error);
+                self.feedback.details = Some(details);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum VisibilityOp {
+    Eq,
+    Ne,
+}
+
+/// Show a form element only when another field of the form is equal (or not
+/// equal) to the given value. Generated by `#[html_form(visible_when = "..")]`
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct VisibilityRule {
+    /// Name of the other field
+    pub field: String,
+    pub op: VisibilityOp,
+    pub value: String,
+}
+
+impl VisibilityRule {
+    /// Compare the serialized value of the other field against the rule
+    pub fn is_satisfied_by<T: Serialize>(&self, field_value: &T) -> bool {
+        let is_equal = match serde_json::to_value(field_value) {
+            Ok(Value::String(s)) => s == self.value,
+            Ok(Value::Null) | Err(_) => false,
+            Ok(v) => {
+                let v = v.to_string();
+                v == self.value
+            }
+        };
+        match self.op {
+            VisibilityOp::Eq => is_equal,
+            VisibilityOp::Ne => !is_equal,
         }
     }
 }
@@ -230,7 +409,15 @@ impl FormElement {
 #[derive(Debug, Clone)]
 pub enum FieldValidationResult {
     Valid,
-    Invalid { error: String },
+    Invalid {
+        error: String,
+    },
+    /// The error is shown to the user and the details are kept for
+    /// debugging, see `FriendlyError`
+    InvalidWithDetails {
+        error: String,
+        details: String,
+    },
 }
 
 impl FieldValidationResult {
@@ -242,6 +429,152 @@ impl FieldValidationResult {
             },
         }
     }
+    pub fn is_invalid(&self) -> bool {
+        !matches!(self, FieldValidationResult::Valid)
+    }
+}
+
+impl From<FriendlyError> for FieldValidationResult {
+    fn from(error: FriendlyError) -> Self {
+        match error.details {
+            Some(details) => FieldValidationResult::InvalidWithDetails {
+                error: error.message,
+                details,
+            },
+            None => FieldValidationResult::Invalid {
+                error: error.message,
+            },
+        }
+    }
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+// Friendly errors
+
+/// An error in the input of a form in plain language, naming the column or
+/// field and the offending value, instead of the raw message of the csv
+/// crate or of the number parser. The raw message is kept in `details`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FriendlyError {
+    pub message: String,
+    pub details: Option<String>,
+}
+
+impl std::fmt::Display for FriendlyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for FriendlyError {}
+
+impl FriendlyError {
+    pub fn new(message: impl ToString) -> Self {
+        FriendlyError {
+            message: message.to_string(),
+            details: None,
+        }
+    }
+
+    fn with_details(message: String, details: impl ToString) -> Self {
+        FriendlyError {
+            message,
+            details: Some(details.to_string()),
+        }
+    }
+
+    /// Message for a value of the field `label` which is not a number of
+    /// type `T`, or None if it is one
+    fn number_message<T: std::str::FromStr>(label: &str, value: &str) -> Option<String> {
+        let trimmed = value.trim();
+        if trimmed.parse::<T>().is_ok() {
+            return None;
+        }
+        Some(if trimmed.is_empty() {
+            format!("{label} is empty, expected a number")
+        } else if trimmed.parse::<f64>().is_err() {
+            format!("{label}: {value:?} is not a number")
+        } else if T::from_str("1.5").is_err() && trimmed.contains(['.', 'e', 'E']) {
+            format!("{label}: {value:?} should be a whole number")
+        } else {
+            format!("{label}: {value:?} is too large or too small")
+        })
+    }
+
+    /// Parse the value of the field `label`, e.g. a numeric input of a form
+    pub fn parse_number<T>(label: &str, value: &str) -> Result<T, FriendlyError>
+    where
+        T: std::str::FromStr,
+        T::Err: std::fmt::Display,
+    {
+        value.trim().parse::<T>().map_err(|e| {
+            let message = Self::number_message::<T>(label, value)
+                .unwrap_or_else(|| format!("{label}: {value:?} is not valid"));
+            FriendlyError::with_details(message, e)
+        })
+    }
+
+    /// Translate an error reading a table. The column labels are those of
+    /// the header of the input, if it has one. `record` is the row being
+    /// deserialized and `row` its 1-based number, not counting the header.
+    pub fn from_csv_error(
+        error: &csv::Error,
+        labels: Option<&csv::StringRecord>,
+        record: Option<&csv::StringRecord>,
+        row: usize,
+    ) -> Self {
+        let column = |index: Option<u64>| {
+            let index = index.unwrap_or_default() as usize;
+            match labels.and_then(|labels| labels.get(index)) {
+                Some(label) => format!("column {label}"),
+                None => format!("column {}", index + 1),
+            }
+        };
+        let value = |index: Option<u64>| {
+            index
+                .and_then(|index| record.and_then(|record| record.get(index as usize)))
+                .unwrap_or_default()
+        };
+        let message = match error.kind() {
+            csv::ErrorKind::UnequalLengths {
+                expected_len, len, ..
+            } => format!("Row {row} has {len} columns, expected {expected_len} columns"),
+            csv::ErrorKind::Utf8 { .. } => format!("Row {row} contains invalid characters"),
+            csv::ErrorKind::Deserialize { err, .. } => {
+                let (column, value) = (column(err.field()), value(err.field()));
+                match err.kind() {
+                    csv::DeserializeErrorKind::ParseInt(_) => {
+                        Self::number_message::<i64>(&format!("Row {row}, {column}"), value)
+                            .unwrap_or_else(|| {
+                                format!("Row {row}, {column}: {value:?} is out of range")
+                            })
+                    }
+                    csv::DeserializeErrorKind::ParseFloat(_) => {
+                        format!("Row {row}, {column}: {value:?} is not a number")
+                    }
+                    csv::DeserializeErrorKind::ParseBool(_) => {
+                        format!("Row {row}, {column}: {value:?} should be true or false")
+                    }
+                    csv::DeserializeErrorKind::UnexpectedEndOfRow => {
+                        format!("Row {row} has too few columns")
+                    }
+                    csv::DeserializeErrorKind::Message(message)
+                        if message.starts_with("missing field") =>
+                    {
+                        let field = message
+                            .split('`')
+                            .nth(1)
+                            .unwrap_or(message.as_str())
+                            .to_string();
+                        format!("Row {row}: the column {field} is missing")
+                    }
+                    _ => format!("Row {row}, {column}: {value:?} is not valid"),
+                }
+            }
+            _ => return FriendlyError::new(error),
+        };
+        FriendlyError::with_details(message, error)
+    }
 }
 
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
@@ -258,6 +591,34 @@ pub enum FormMethod {
 pub struct FormConfig {
     pub url: String,
     pub method: FormMethod,
+    /// Sent along with the submitted form so that the server can reject
+    /// duplicate submissions using a `SubmissionGuard`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submission_token: Option<String>,
+    /// Label of the submit button, which is "Submit" in the frontend when
+    /// this is `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    submit_label: Option<String>,
+}
+
+impl FormConfig {
+    pub fn new(url: impl ToString, method: FormMethod) -> Self {
+        FormConfig {
+            url: url.to_string(),
+            method,
+            submission_token: None,
+            submit_label: None,
+        }
+    }
+
+    pub fn with_submit_label(mut self, label: impl ToString) -> Self {
+        self.submit_label = Some(label.to_string());
+        self
+    }
+
+    pub fn submit_label(&self) -> Option<&str> {
+        self.submit_label.as_deref()
+    }
 }
 
 pub enum FormValidationResult {
@@ -302,6 +663,304 @@ impl HtmlTemplate for Form {
     }
 }
 
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+// Form page
+
+/// A standalone full width page holding a form, with the nav bar
+pub struct FormPage;
+
+impl FormPage {
+    /// Page with the empty form, submitted to the url. The method from
+    /// `#[html_form(method = "..")]` is used unless it is overridden.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new<T: IntoHtmlForm>(
+        nav: WsNavBar,
+        action_url: impl ToString,
+        method_override: Option<FormMethod>,
+    ) -> SinglePageHtml<Form> {
+        let mut form = T::form_with_url(action_url);
+        if let Some(method) = method_override {
+            form.config.method = method;
+        }
+        SinglePageHtml::new(nav, form, None).full_width()
+    }
+
+    /// Page to show the form again with the feedback from validation, e.g.
+    /// `FormValidationResult::Invalid`
+    pub fn invalid(
+        mut form_with_feedback: Form,
+        nav: WsNavBar,
+        action_url: impl ToString,
+    ) -> SinglePageHtml<Form> {
+        form_with_feedback.config.url = action_url.to_string();
+        SinglePageHtml::new(nav, form_with_feedback, None).full_width()
+    }
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+// Wizard form
+
+/// The elements shown in one step of a `WizardForm`
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WizardStep {
+    pub elements: Vec<FormElement>,
+}
+
+impl WizardStep {
+    fn has_error(&self) -> bool {
+        self.elements.iter().any(|e| e.feedback.error.is_some())
+    }
+}
+
+/// A form split into steps, which are shown one at a time along with a
+/// stepper. The form is submitted as a whole from the last step.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WizardForm {
+    pub config: FormConfig,
+    /// Titles of the steps and the step shown first
+    pub progress: StepProgress,
+    pub steps: Vec<WizardStep>,
+}
+
+impl WizardForm {
+    /// One step for each form, in order. The config of the first form is
+    /// used for the whole wizard.
+    pub fn new(forms: Vec<Form>) -> anyhow::Result<Self> {
+        if let Some(i) = forms.iter().position(|f| f.elements.is_empty()) {
+            bail!("Step {} of the wizard form has no elements", i + 1);
+        }
+        let steps: Vec<usize> = forms
+            .iter()
+            .enumerate()
+            .flat_map(|(i, form)| vec![i + 1; form.elements.len()])
+            .collect();
+        let mut forms = forms.into_iter();
+        let Some(first) = forms.next() else {
+            bail!("A wizard form needs at least one step");
+        };
+        let form = Form {
+            config: first.config,
+            elements: first
+                .elements
+                .into_iter()
+                .chain(forms.flat_map(|f| f.elements))
+                .collect(),
+        };
+        Self::from_steps(form, &steps)
+    }
+
+    /// Split the elements of the form into steps, where `steps[i]` is the
+    /// step of `form.elements[i]`. The steps should be contiguous starting
+    /// at 1, i.e. every step from 1 up to the largest step has at least one
+    /// element.
+    pub fn from_steps(form: Form, steps: &[usize]) -> anyhow::Result<Self> {
+        if steps.len() != form.elements.len() {
+            bail!(
+                "Found steps for {} elements, expected {} steps as in the form",
+                steps.len(),
+                form.elements.len()
+            );
+        }
+        let num_steps = steps.iter().copied().max().unwrap_or(0);
+        if num_steps == 0 {
+            bail!("A wizard form needs at least one step");
+        }
+        let mut wizard_steps = vec![WizardStep { elements: vec![] }; num_steps];
+        for (element, &step) in form.elements.into_iter().zip(steps) {
+            if step == 0 {
+                bail!("Steps of a wizard form start at 1, found step 0");
+            }
+            wizard_steps[step - 1].elements.push(element);
+        }
+        if let Some(i) = wizard_steps.iter().position(|s| s.elements.is_empty()) {
+            bail!(
+                "Steps of a wizard form should be contiguous starting at 1, step {} has no elements",
+                i + 1
+            );
+        }
+        Ok(Self::with_steps(form.config, wizard_steps))
+    }
+
+    fn with_steps(config: FormConfig, steps: Vec<WizardStep>) -> Self {
+        WizardForm {
+            config,
+            progress: StepProgress {
+                steps: (1..=steps.len()).map(|i| format!("Step {i}")).collect(),
+                active_step: 0,
+                active_step_failed: false,
+            },
+            steps,
+        }
+    }
+
+    /// Titles shown in the stepper, in place of "Step 1", "Step 2" etc.
+    pub fn step_titles(mut self, titles: Vec<String>) -> Self {
+        for (step, title) in self.progress.steps.iter_mut().zip(titles) {
+            *step = title;
+        }
+        self
+    }
+
+    /// Show the first step which failed validation, if any
+    fn focus_first_error(&mut self) {
+        if let Some(i) = self.steps.iter().position(WizardStep::has_error) {
+            self.progress.active_step = i as u8;
+            self.progress.active_step_failed = true;
+        }
+    }
+}
+
+impl HtmlTemplate for WizardForm {
+    fn template(&self, data_key: Option<String>) -> String {
+        let key = |k: &str| {
+            data_key
+                .as_ref()
+                .map_or(k.to_string(), |d| format!("{d}.{k}"))
+        };
+        let progress_data_key = key("progress");
+        let config_data_key = key("config");
+        let steps_data_key = key("steps");
+
+        let steps = self
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| {
+                let children = step
+                    .elements
+                    .iter()
+                    .enumerate()
+                    .map(|(j, element)| {
+                        element.template(Some(format!("{steps_data_key}[{i}].elements[{j}]")))
+                    })
+                    .join("\n");
+                format!(
+                    r#"<div data-key="{steps_data_key}[{i}]" data-component="WizardStep">
+{children}
+</div>"#
+                )
+            })
+            .join("\n");
+        format!(
+            r#"<div data-key="{config_data_key}" data-component="WizardFormWrapper">
+<div data-key="{progress_data_key}" data-component="StepProgress"></div>
+{steps}
+</div>"#
+        )
+    }
+}
+
+pub enum WizardValidationResult {
+    Valid(WizardForm),
+    Invalid(WizardForm),
+}
+impl WizardValidationResult {
+    pub fn inner(self) -> WizardForm {
+        match self {
+            WizardValidationResult::Valid(f) => f,
+            WizardValidationResult::Invalid(f) => f,
+        }
+    }
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+// Submission token
+
+thread_local! {
+    static DETERMINISTIC_TOKEN_COUNTER: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// Generate tokens from a counter starting at zero instead of randomly on the
+/// current thread. Useful to get stable snapshots of forms in tests.
+pub fn use_deterministic_submission_tokens() {
+    DETERMINISTIC_TOKEN_COUNTER.with(|counter| counter.set(Some(0)));
+}
+
+/// A random token formatted like a UUID, or the next value of the counter
+/// after `use_deterministic_submission_tokens()`
+pub fn generate_submission_token() -> String {
+    let value = DETERMINISTIC_TOKEN_COUNTER.with(|counter| {
+        counter.get().map(|n| {
+            counter.set(Some(n + 1));
+            u128::from(n)
+        })
+    });
+    let value = value.unwrap_or_else(rand::random::<u128>);
+    let hex = format!("{value:032x}");
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// Error returned by the `SubmissionGuard` for a token which was seen before
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateSubmission {
+    pub token: String,
+}
+
+impl std::fmt::Display for DuplicateSubmission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Form with submission token {} was already submitted",
+            self.token
+        )
+    }
+}
+
+impl std::error::Error for DuplicateSubmission {}
+
+/// Remembers the submission tokens seen in the last `ttl` so that handlers can
+/// reject duplicate submissions of a form, e.g. from a double click on submit.
+pub struct SubmissionGuard {
+    ttl: Duration,
+    seen: Mutex<HashMap<String, Instant>>,
+}
+
+impl SubmissionGuard {
+    pub fn new(ttl: Duration) -> Self {
+        SubmissionGuard {
+            ttl,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Accept the token the first time it is seen within the ttl and reject it
+    /// afterwards
+    pub fn check(&self, token: &str) -> Result<(), DuplicateSubmission> {
+        self.check_at(token, Instant::now())
+    }
+
+    fn check_at(&self, token: &str, now: Instant) -> Result<(), DuplicateSubmission> {
+        let mut seen = self.seen.lock().unwrap_or_else(PoisonError::into_inner);
+        seen.retain(|_, seen_at| now.saturating_duration_since(*seen_at) < self.ttl);
+        if seen.contains_key(token) {
+            return Err(DuplicateSubmission {
+                token: token.to_string(),
+            });
+        }
+        seen.insert(token.to_string(), now);
+        Ok(())
+    }
+
+    /// Number of tokens currently remembered
+    pub fn len(&self) -> usize {
+        self.seen
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
 // :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
 // Traits
 pub trait CreateFormInput: Sized {
@@ -311,18 +970,35 @@ pub trait CreateFormInput: Sized {
     fn default_form_input(name: String, value: Option<Self>) -> FormInput {
         Self::create_form_input(Self::default_config(), name, value)
     }
+    /// Check the value against the bounds in the config, e.g. the number of
+    /// rows of a `RepeatedGroup`. A derived form checks them after the
+    /// validation of the field, even if `validate_<field>` is overridden.
+    fn validate_config(&self, _config: &Self::Config) -> FieldValidationResult {
+        FieldValidationResult::Valid
+    }
 }
 
 pub enum FormInputConfigString {
     TextArea {
         rows: Option<u32>,
         placeholder: Option<String>,
+        max_length: Option<usize>,
     },
     Text {
         placeholder: Option<String>,
+        max_length: Option<usize>,
     },
 }
 
+impl FormInputConfigString {
+    pub fn max_length(&self) -> Option<usize> {
+        match self {
+            FormInputConfigString::TextArea { max_length, .. }
+            | FormInputConfigString::Text { max_length, .. } => *max_length,
+        }
+    }
+}
+
 impl CreateFormInput for String {
     type Config = FormInputConfigString;
     fn create_form_input(
@@ -331,16 +1007,22 @@ impl CreateFormInput for String {
         value: Option<String>,
     ) -> FormInput {
         match config {
-            FormInputConfigString::TextArea { rows, placeholder } => {
-                FormInput::TextArea(TextArea {
-                    rows,
-                    name,
-                    placeholder,
-                    required: Some(true),
-                    value,
-                })
-            }
-            FormInputConfigString::Text { placeholder } => FormInput::Input(InputElement {
+            FormInputConfigString::TextArea {
+                rows,
+                placeholder,
+                max_length,
+            } => FormInput::TextArea(TextArea {
+                rows,
+                name,
+                placeholder,
+                required: Some(true),
+                value,
+                max_length,
+            }),
+            FormInputConfigString::Text {
+                placeholder,
+                max_length,
+            } => FormInput::Input(InputElement {
                 name,
                 ty: InputType::Text,
                 value,
@@ -349,11 +1031,21 @@ impl CreateFormInput for String {
                 step: None,
                 placeholder,
                 required: true,
+                max_length,
             }),
         }
     }
+    fn validate_config(&self, config: &FormInputConfigString) -> FieldValidationResult {
+        match config.max_length() {
+            Some(max_length) => self.validate_max_length(max_length),
+            None => FieldValidationResult::Valid,
+        }
+    }
     fn default_config() -> FormInputConfigString {
-        FormInputConfigString::Text { placeholder: None }
+        FormInputConfigString::Text {
+            placeholder: None,
+            max_length: None,
+        }
     }
 }
 
@@ -380,6 +1072,7 @@ impl CreateFormInput for i64 {
                 step: Some(step.to_string()),
                 placeholder: None,
                 required: true,
+                max_length: None,
             }),
             FormInputConfigI64::Input { min, max, step } => FormInput::Input(InputElement {
                 name,
@@ -390,6 +1083,7 @@ impl CreateFormInput for i64 {
                 step: Some(step.to_string()),
                 placeholder: None,
                 required: true,
+                max_length: None,
             }),
         }
     }
@@ -407,11 +1101,55 @@ pub trait FieldValidation {
     fn validate(&self) -> FieldValidationResult {
         FieldValidationResult::Valid
     }
+    /// Table shown below the input after validation
+    fn preview(&self) -> Option<GenericTable> {
+        None
+    }
+    /// True if nothing was entered, in which case an optional field is
+    /// valid without validating the value
+    fn is_empty_submission(&self) -> bool {
+        false
+    }
+    /// Reset the optional fields where nothing was entered to `None`, see
+    /// `IntoHtmlForm::clear_empty_submissions()`
+    fn clear_empty_submission(&mut self) {}
 }
 
 impl FieldValidation for String {}
 impl FieldValidation for i64 {}
 
+/// Limit on the number of characters of a text input, which is checked by
+/// `CreateFormInput::validate_config()` of a `String` configured with a
+/// `max_length`, e.g. using `#[html_form(max_length = N)]`. The limit is also
+/// passed to the input, which shows a character counter.
+pub trait MaxLength {
+    fn validate_max_length(&self, max_length: usize) -> FieldValidationResult;
+}
+
+impl MaxLength for String {
+    fn validate_max_length(&self, max_length: usize) -> FieldValidationResult {
+        let length = self.chars().count();
+        if length > max_length {
+            FieldValidationResult::Invalid {
+                error: format!(
+                    "Should be at most {max_length} characters long, but is {length} characters long"
+                ),
+            }
+        } else {
+            FieldValidationResult::Valid
+        }
+    }
+}
+
+impl<T: MaxLength> MaxLength for Option<T> {
+    fn validate_max_length(&self, max_length: usize) -> FieldValidationResult {
+        match self {
+            Some(v) => v.validate_max_length(max_length),
+            None => FieldValidationResult::Valid,
+        }
+    }
+}
+
 pub trait EnumSelect: Serialize + Sized {
     fn variants() -> Vec<Self>;
     fn value(&self) -> String {
@@ -440,6 +1178,7 @@ impl<T: EnumSelect> CreateFormInput for T {
             options: <T as EnumSelect>::options(),
             selected: value.map(|v| <T as EnumSelect>::value(&v)),
             required: Some(true),
+            other: None,
         })
     }
 
@@ -448,47 +1187,222 @@ impl<T: EnumSelect> CreateFormInput for T {
     }
 }
 
-impl<T: EnumSelect> FieldValidation for HashSet<T> {}
+/// Label of the extra option of `OrOther`
+pub const OTHER_OPTION: &str = "Other";
 
-impl<T: EnumSelect> CreateFormInput for HashSet<T> {
-    type Config = MultiSelectType;
+/// Name of the text input of the "Other" option of the select `name`
+pub fn other_field_name(name: &str) -> String {
+    format!("{name}_other")
+}
 
-    fn create_form_input(config: Self::Config, name: String, value: Option<Self>) -> FormInput {
-        FormInput::MultiSelect(MultiSelect {
-            ty: config,
-            name,
-            options: <T as EnumSelect>::options(),
-            selected: value
-                .iter()
-                .flatten()
-                .map(|v| <T as EnumSelect>::value(v))
-                .collect(),
-            required: Some(true),
-        })
-    }
+/// One of the variants of an enum or free text entered with the extra
+/// "Other" option of the select, e.g. `OrOther<Chemistry>` for a chemistry
+/// that is not listed.
+///
+/// Serialized as the value of the variant or as `{"other": "<text>"}`.
+/// In an urlencoded submission, `parse_form_submission()` reads the text
+/// from the input named by `other_field_name()`. The bare `"Other"` string,
+/// which the select submits without its text, is read as an empty `Other`,
+/// which does not validate. No variant of `T` may have the value `"Other"`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum OrOther<T> {
+    Known(T),
+    Other(String),
+}
 
-    fn default_config() -> Self::Config {
-        MultiSelectType::Checkbox
+impl<T: EnumSelect> Serialize for OrOther<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            OrOther::Known(v) => v.serialize(serializer),
+            OrOther::Other(text) => OtherRepr::Other {
+                other: text.clone(),
+            }
+            .serialize(serializer),
+        }
     }
 }
 
-pub trait IntoHtmlForm: Sized {
-    fn _into_html_form(value: Option<&Self>) -> Form;
-    fn _field_validations(&self) -> Vec<FieldValidationResult>;
+/// Whether a variant of `T` has the value reserved for the "Other" option
+fn has_other_variant<T: EnumSelect>() -> bool {
+    T::options().iter().any(|option| option == OTHER_OPTION)
+}
 
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum OtherRepr {
+    Other { other: String },
+    Value(String),
+}
+
+impl<'de, T: EnumSelect> Deserialize<'de> for OrOther<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if has_other_variant::<T>() {
+            return Err(serde::de::Error::custom(format!(
+                "a variant has the value {OTHER_OPTION:?} reserved for the text of OrOther"
+            )));
+        }
+        match OtherRepr::deserialize(deserializer)? {
+            OtherRepr::Other { other } => Ok(OrOther::Other(other)),
+            OtherRepr::Value(value) => {
+                // The text merged into the value by `parse_form_submission()`
+                if let Ok(OtherRepr::Other { other }) = serde_json::from_str(&value) {
+                    return Ok(OrOther::Other(other));
+                }
+                match T::variants().into_iter().find(|v| v.value() == value) {
+                    Some(v) => Ok(OrOther::Known(v)),
+                    None if value == OTHER_OPTION => Ok(OrOther::Other(String::new())),
+                    None => Err(serde::de::Error::custom(format!(
+                        "unknown option {value:?}, expected one of {:?} or {{\"other\": ...}}",
+                        T::options()
+                    ))),
+                }
+            }
+        }
+    }
+}
+
+impl<T: EnumSelect> FieldValidation for OrOther<T> {
+    fn validate(&self) -> FieldValidationResult {
+        match self {
+            OrOther::Other(text) if text.trim().is_empty() => FieldValidationResult::Invalid {
+                error: format!("Specify a value for \"{OTHER_OPTION}\""),
+            },
+            _ => FieldValidationResult::Valid,
+        }
+    }
+}
+
+impl<T: EnumSelect> CreateFormInput for OrOther<T> {
+    type Config = SingleSelectType;
+
+    fn create_form_input(config: Self::Config, name: String, value: Option<Self>) -> FormInput {
+        assert!(
+            !has_other_variant::<T>(),
+            "A variant has the value {OTHER_OPTION:?} reserved for the text of OrOther"
+        );
+        let (selected, other_value) = match value {
+            Some(OrOther::Known(v)) => (Some(v.value()), None),
+            Some(OrOther::Other(text)) => (Some(OTHER_OPTION.to_string()), Some(text)),
+            None => (None, None),
+        };
+        let mut options = T::options();
+        options.push(OTHER_OPTION.to_string());
+        FormInput::SingleSelect(SingleSelect {
+            ty: config,
+            other: Some(SelectOther {
+                name: other_field_name(&name),
+                option: OTHER_OPTION.to_string(),
+                placeholder: None,
+                value: other_value,
+            }),
+            name,
+            options,
+            selected,
+            required: Some(true),
+        })
+    }
+
+    fn default_config() -> Self::Config {
+        SingleSelectType::Radio
+    }
+}
+
+impl<T: EnumSelect> FieldValidation for HashSet<T> {}
+
+impl<T: EnumSelect> CreateFormInput for HashSet<T> {
+    type Config = MultiSelectType;
+
+    fn create_form_input(config: Self::Config, name: String, value: Option<Self>) -> FormInput {
+        FormInput::MultiSelect(MultiSelect {
+            ty: config,
+            name,
+            options: <T as EnumSelect>::options(),
+            selected: value
+                .iter()
+                .flatten()
+                .map(|v| <T as EnumSelect>::value(v))
+                .collect(),
+            required: Some(true),
+        })
+    }
+
+    fn default_config() -> Self::Config {
+        MultiSelectType::Checkbox
+    }
+}
+
+pub trait IntoHtmlForm: Sized {
+    fn _into_html_form(value: Option<&Self>) -> Form;
+    fn _field_validations(&self) -> Vec<FieldValidationResult>;
+    /// Whether every field is visible given the values of the others. The
+    /// fields past the end, e.g. all of them if empty, are visible.
+    fn _field_visibility(&self) -> Vec<bool> {
+        Vec::new()
+    }
+    fn _field_previews(&self) -> Vec<Option<GenericTable>> {
+        Vec::new()
+    }
+    /// Reset the optional fields where nothing was entered to `None`, e.g.
+    /// an `Option<Spreadsheet<T>>` submitted blank, which deserializes to
+    /// `Some`. Generated by the derive, and applied by `from_submission()`.
+    fn clear_empty_submissions(&mut self) {}
+    /// Step of every field in a wizard form. Generated from
+    /// `#[html_form(wizard_step = N)]`, empty if every field is on one step.
+    fn _field_steps() -> Vec<usize> {
+        Vec::new()
+    }
+    /// Titles of the steps in a wizard form
+    fn _step_titles() -> Vec<String> {
+        Vec::new()
+    }
+
+    /// The empty form. Its url is empty, which submits the form to the url
+    /// of the page, see `form_with_url()`.
     fn form() -> Form {
         Self::_into_html_form(None)
     }
+    /// The empty form, submitted to the url
+    fn form_with_url(url: impl ToString) -> Form {
+        let mut form = Self::form();
+        form.config.url = url.to_string();
+        form
+    }
+    /// Parse an urlencoded form submission with `parse_form_submission()`
+    /// and reset the optional fields left blank to `None`. A value of a
+    /// number input which is not a number fails with a `FriendlyError`.
+    fn from_submission(body: &str) -> Result<Self, Error>
+    where
+        Self: DeserializeOwned,
+    {
+        check_number_inputs(&Self::form(), body)?;
+        let mut value: Self = parse_form_submission(body)?;
+        value.clear_empty_submissions();
+        Ok(value)
+    }
     fn filled_form_pre_validation(&self) -> Form {
         Self::_into_html_form(Some(self))
     }
     fn validate(&self) -> FormValidationResult {
         let mut form = self.filled_form_pre_validation();
         let field_validations = self._field_validations();
+        let mut field_visibility = self._field_visibility().into_iter();
+        let mut field_previews = self._field_previews().into_iter();
         let mut invalid = false;
         for (input, validation) in form.elements.iter_mut().zip_eq(field_validations) {
-            invalid |= matches!(validation, FieldValidationResult::Invalid { .. });
+            let visible = field_visibility.next().unwrap_or(true);
+            // Hidden fields are neither required nor validated
+            let validation = if visible {
+                validation
+            } else {
+                input.input.set_optional();
+                FieldValidationResult::Valid
+            };
+            invalid |= validation.is_invalid();
             input.update(validation);
+            let preview = field_previews.next().flatten();
+            if visible {
+                input.preview = preview;
+            }
         }
         if invalid {
             FormValidationResult::Invalid(form)
@@ -496,6 +1410,40 @@ pub trait IntoHtmlForm: Sized {
             FormValidationResult::Valid(form)
         }
     }
+
+    /// Split a form into the steps of a wizard form
+    fn _into_wizard_form(form: Form) -> WizardForm {
+        if form.elements.is_empty() {
+            // Nothing to split, show the wizard as a single empty step
+            let steps = vec![WizardStep { elements: vec![] }];
+            return WizardForm::with_steps(form.config, steps).step_titles(Self::_step_titles());
+        }
+        let steps = match Self::_field_steps() {
+            steps if steps.is_empty() => vec![1; form.elements.len()],
+            steps => steps,
+        };
+        // The steps are checked at compile time by the derive macro
+        WizardForm::from_steps(form, &steps)
+            .expect("Invalid wizard steps")
+            .step_titles(Self::_step_titles())
+    }
+    fn wizard_form() -> WizardForm {
+        Self::_into_wizard_form(Self::form())
+    }
+    /// Validate the complete struct like `validate()`, showing the feedback of
+    /// every field in its step and opening the first step with an error
+    fn validate_wizard(&self) -> WizardValidationResult {
+        match self.validate() {
+            FormValidationResult::Valid(form) => {
+                WizardValidationResult::Valid(Self::_into_wizard_form(form))
+            }
+            FormValidationResult::Invalid(form) => {
+                let mut wizard = Self::_into_wizard_form(form);
+                wizard.focus_first_error();
+                WizardValidationResult::Invalid(wizard)
+            }
+        }
+    }
 }
 
 #[derive(Default)]
@@ -506,6 +1454,31 @@ pub struct TextAreaConfig {
 
 pub trait CsvReaderBuilder {
     fn builder() -> csv::ReaderBuilder;
+    /// Applied to the raw value before it is parsed. The raw value shown in
+    /// the form is not modified.
+    fn preprocess(raw: &str) -> Cow<'_, str> {
+        Cow::Borrowed(raw)
+    }
+}
+
+/// Drop the lines starting with `#` and normalize the line endings to `\n`
+pub fn strip_comment_lines(raw: &str) -> Cow<'_, str> {
+    if !raw.contains('\r') && !raw.lines().any(|line| line.trim_start().starts_with('#')) {
+        return Cow::Borrowed(raw);
+    }
+    Cow::Owned(
+        raw.lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .map(|line| line.trim_end_matches('\r'))
+            .join("\n"),
+    )
+}
+
+fn reader_builder(has_headers: bool, delimiter: u8) -> csv::ReaderBuilder {
+    let mut reader_builder = csv::ReaderBuilder::new();
+    reader_builder.has_headers(has_headers);
+    reader_builder.delimiter(delimiter);
+    reader_builder
 }
 
 #[derive(Debug, Clone)]
@@ -513,10 +1486,46 @@ pub struct TsvNoHeader;
 
 impl CsvReaderBuilder for TsvNoHeader {
     fn builder() -> csv::ReaderBuilder {
-        let mut reader_builder = csv::ReaderBuilder::new();
-        reader_builder.has_headers(false);
-        reader_builder.delimiter(b'\t');
-        reader_builder
+        reader_builder(false, b'\t')
+    }
+}
+
+/// Tab separated with a header line. Lines starting with `#` are ignored.
+#[derive(Debug, Clone)]
+pub struct TsvWithHeader;
+
+impl CsvReaderBuilder for TsvWithHeader {
+    fn builder() -> csv::ReaderBuilder {
+        reader_builder(true, b'\t')
+    }
+    fn preprocess(raw: &str) -> Cow<'_, str> {
+        strip_comment_lines(raw)
+    }
+}
+
+/// Comma separated without a header line. Lines starting with `#` are ignored.
+#[derive(Debug, Clone)]
+pub struct CsvNoHeader;
+
+impl CsvReaderBuilder for CsvNoHeader {
+    fn builder() -> csv::ReaderBuilder {
+        reader_builder(false, b',')
+    }
+    fn preprocess(raw: &str) -> Cow<'_, str> {
+        strip_comment_lines(raw)
+    }
+}
+
+/// Comma separated with a header line. Lines starting with `#` are ignored.
+#[derive(Debug, Clone)]
+pub struct CsvWithHeader;
+
+impl CsvReaderBuilder for CsvWithHeader {
+    fn builder() -> csv::ReaderBuilder {
+        reader_builder(true, b',')
+    }
+    fn preprocess(raw: &str) -> Cow<'_, str> {
+        strip_comment_lines(raw)
     }
 }
 
@@ -530,7 +1539,7 @@ where
 {
     phantom: PhantomData<Builder>,
     raw_value: String,
-    deserialized: Result<Vec<T>, String>,
+    deserialized: Result<Vec<T>, FriendlyError>,
 }
 
 impl<T, Builder> TableInput<T, Builder>
@@ -538,11 +1547,116 @@ where
     T: Clone,
     Builder: CsvReaderBuilder + Clone,
 {
+    /// The rows, or the plain language error. See `deserialized_friendly()`
+    /// for the raw message.
     pub fn deserialized(self) -> Result<Vec<T>, String> {
+        self.deserialized.map_err(|e| e.message)
+    }
+    pub fn deserialized_friendly(self) -> Result<Vec<T>, FriendlyError> {
         self.deserialized
     }
 }
 
+/// Deserialize the rows of the input, translating the errors using the labels
+/// of the header if there is one, or else the given labels
+fn deserialize_rows<T, Builder>(
+    src: &str,
+    labels: Option<csv::StringRecord>,
+) -> Result<Vec<T>, FriendlyError>
+where
+    T: DeserializeOwned,
+    Builder: CsvReaderBuilder,
+{
+    let src = Builder::preprocess(src);
+    let mut reader = Builder::builder().from_reader(src.as_bytes());
+    let headers = match reader.has_headers() {
+        true => Some(
+            reader
+                .headers()
+                .map_err(|e| FriendlyError::from_csv_error(&e, None, None, 0))?
+                .clone(),
+        ),
+        false => None,
+    };
+    let labels = headers.clone().or(labels);
+    let mut rows = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let record =
+            record.map_err(|e| FriendlyError::from_csv_error(&e, labels.as_ref(), None, i + 1))?;
+        let row = record.deserialize(headers.as_ref()).map_err(|e| {
+            FriendlyError::from_csv_error(&e, labels.as_ref(), Some(&record), i + 1)
+        })?;
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// Prefix of the first cell of a row which could not be parsed, in the plain
+/// text fallback of the preview
+const PREVIEW_ERROR_PREFIX: &str = "⚠ ";
+
+impl<T, Builder> TableInput<T, Builder>
+where
+    T: Clone + DeserializeOwned,
+    Builder: CsvReaderBuilder + Clone,
+{
+    /// The rows of the raw value. The rows which cannot be deserialized are
+    /// highlighted and their first cell shows the error when hovered. The
+    /// header line of the input is used if `header` is None.
+    fn preview_with_header(&self, header: Option<Vec<String>>) -> GenericTable {
+        let src = Builder::preprocess(&self.raw_value);
+        let mut builder = Builder::builder();
+        builder.flexible(true);
+        let mut reader = builder.from_reader(src.as_bytes());
+        let headers = match reader.has_headers() {
+            true => reader.headers().ok().cloned(),
+            false => None,
+        };
+        let header = header.or_else(|| {
+            headers
+                .as_ref()
+                .map(|h| h.iter().map(String::from).collect())
+        });
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            let Ok(record) = record else {
+                break;
+            };
+            let row = match record.deserialize::<T>(headers.as_ref()) {
+                Ok(_) => record.iter().map(RichCell::from).collect(),
+                Err(e) => record
+                    .iter()
+                    .enumerate()
+                    .map(|(i, cell)| {
+                        let cell = escape_html(cell);
+                        RichCell::Html(match i {
+                            0 => format!(
+                                r#"<span class="text-danger" title="{}">{PREVIEW_ERROR_PREFIX}{cell}</span>"#,
+                                escape_html(&e.to_string())
+                            ),
+                            _ => format!(r#"<span class="text-danger">{cell}</span>"#),
+                        })
+                    })
+                    .collect(),
+            };
+            rows.push(RichTableRow(row));
+        }
+        GenericTable::from_rich_rows(rows, header)
+    }
+}
+
+impl<T, Builder> TableInput<T, Builder>
+where
+    T: Clone + DeserializeOwned + Serialize + Default,
+    Builder: CsvReaderBuilder + Clone,
+{
+    /// Preview of the parsed rows with the field names of `T` as the header.
+    /// See `FormElement::preview`
+    pub fn to_preview_table(&self) -> GenericTable {
+        self.preview_with_header(tabular_file_header::<T>().ok())
+    }
+}
+
 impl<T, Builder> CreateFormInput for TableInput<T, Builder>
 where
     T: Clone,
@@ -557,6 +1671,7 @@ where
             placeholder: config.placeholder,
             required: Some(true),
             value: value.map(|x| x.raw_value),
+            max_length: None,
         })
     }
 
@@ -571,11 +1686,20 @@ where
     Builder: CsvReaderBuilder + Clone,
 {
     fn from(src: String) -> Self {
-        let deserialized: Result<Vec<T>, _> = Builder::builder()
-            .from_reader(src.as_bytes())
-            .deserialize()
-            .try_collect()
-            .map_err(|e| e.to_string());
+        TableInput::with_labels(src, None)
+    }
+}
+
+impl<T, Builder> TableInput<T, Builder>
+where
+    T: Clone + DeserializeOwned,
+    Builder: CsvReaderBuilder + Clone,
+{
+    /// Parse the input, naming the columns in the errors with the labels if
+    /// the input has no header
+    fn with_labels(src: String, labels: Option<Vec<String>>) -> Self {
+        let deserialized =
+            deserialize_rows::<T, Builder>(&src, labels.map(csv::StringRecord::from));
         TableInput {
             raw_value: src,
             deserialized,
@@ -602,12 +1726,17 @@ where
     fn validate(&self) -> FieldValidationResult {
         match &self.deserialized {
             Ok(_) => FieldValidationResult::Valid,
-            Err(e) => FieldValidationResult::Invalid { error: e.clone() },
+            Err(e) => e.clone().into(),
         }
     }
+    fn preview(&self) -> Option<GenericTable> {
+        // Without the bounds of `to_preview_table`, the header is taken from
+        // the input if it has one
+        (!self.raw_value.trim().is_empty()).then(|| self.preview_with_header(None))
+    }
 }
 
-impl<T: CreateFormInput> CreateFormInput for Option<T> {
+impl<T: CreateFormInput + FieldValidation> CreateFormInput for Option<T> {
     type Config = T::Config;
 
     fn create_form_input(config: Self::Config, name: String, value: Option<Self>) -> FormInput {
@@ -619,17 +1748,344 @@ impl<T: CreateFormInput> CreateFormInput for Option<T> {
     fn default_config() -> Self::Config {
         T::default_config()
     }
+
+    fn validate_config(&self, config: &Self::Config) -> FieldValidationResult {
+        match self {
+            Some(v) if !v.is_empty_submission() => v.validate_config(config),
+            _ => FieldValidationResult::Valid,
+        }
+    }
 }
 
 impl<T: FieldValidation> FieldValidation for Option<T> {
     fn validate(&self) -> FieldValidationResult {
         match self {
-            Some(v) => v.validate(),
-            None => FieldValidationResult::Valid,
+            Some(v) if !v.is_empty_submission() => v.validate(),
+            _ => FieldValidationResult::Valid,
+        }
+    }
+    fn preview(&self) -> Option<GenericTable> {
+        self.as_ref()
+            .filter(|v| !v.is_empty_submission())
+            .and_then(FieldValidation::preview)
+    }
+    fn is_empty_submission(&self) -> bool {
+        self.as_ref()
+            .is_none_or(FieldValidation::is_empty_submission)
+    }
+    fn clear_empty_submission(&mut self) {
+        if self.is_empty_submission() {
+            *self = None;
+        } else if let Some(v) = self {
+            v.clear_empty_submission();
         }
     }
 }
 
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+// Repeated groups of fields
+
+/// A field holding rows of a struct deriving `HtmlForm`, which the user can
+/// add and remove, e.g. one row per library of a library sheet:
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use tenx_websummary::form::{parse_form_submission, RepeatedGroup};
+/// use tenx_websummary::HtmlForm;
+///
+/// #[derive(Clone, Serialize, Deserialize, HtmlForm)]
+/// struct Library {
+///     name: String,
+///     lanes: i64,
+/// }
+///
+/// #[derive(Serialize, Deserialize, HtmlForm)]
+/// struct LibrarySheet {
+///     libraries: RepeatedGroup<Library>,
+/// }
+///
+/// let sheet: LibrarySheet =
+///     parse_form_submission("libraries[0].name=GEX&libraries[0].lanes=2").unwrap();
+/// assert_eq!(sheet.libraries.rows()[0].lanes, 2);
+/// ```
+///
+/// The form submits the fields of the rows as `libraries[0].name` etc.,
+/// which `parse_form_submission()` gathers back into the rows. Each row is
+/// validated like a form of its own, and the feedback is shown next to the
+/// fields of the row.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RepeatedGroup<T>(pub Vec<T>);
+
+impl<T> RepeatedGroup<T> {
+    pub fn rows(&self) -> &[T] {
+        &self.0
+    }
+    pub fn into_rows(self) -> Vec<T> {
+        self.0
+    }
+}
+
+impl<T> From<Vec<T>> for RepeatedGroup<T> {
+    fn from(rows: Vec<T>) -> Self {
+        RepeatedGroup(rows)
+    }
+}
+
+impl<T: Serialize> Serialize for RepeatedGroup<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+/// A list of rows, or the rows gathered by `parse_form_submission()`, which
+/// are a json list of the urlencoded fields of each row
+impl<'de, T: DeserializeOwned> Deserialize<'de> for RepeatedGroup<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr<T> {
+            Rows(Vec<T>),
+            Encoded(String),
+        }
+        match Repr::<T>::deserialize(deserializer)? {
+            Repr::Rows(rows) => Ok(RepeatedGroup(rows)),
+            Repr::Encoded(encoded) => {
+                let rows: Vec<String> =
+                    serde_json::from_str(&encoded).map_err(serde::de::Error::custom)?;
+                rows.iter()
+                    .enumerate()
+                    .map(|(i, row)| {
+                        serde_urlencoded::from_str(row)
+                            .map_err(|e| serde::de::Error::custom(format!("Row {}: {e}", i + 1)))
+                    })
+                    .collect::<Result<_, _>>()
+                    .map(RepeatedGroup)
+            }
+        }
+    }
+}
+
+/// Merge the text of the "Other" option of each select submitted with that
+/// option into the value of the select, as read by `OrOther`
+fn merge_other_texts(pairs: Vec<(String, String)>) -> Result<Vec<(String, String)>, Error> {
+    let mut merged = Vec::with_capacity(pairs.len());
+    let mut texts = Vec::new();
+    for (key, value) in &pairs {
+        if value == OTHER_OPTION {
+            let other_key = other_field_name(key);
+            if let Some((_, text)) = pairs.iter().find(|(k, _)| *k == other_key) {
+                texts.push(other_key);
+                let other = OtherRepr::Other {
+                    other: text.clone(),
+                };
+                merged.push((key.clone(), serde_json::to_string(&other)?));
+                continue;
+            }
+        }
+        merged.push((key.clone(), value.clone()));
+    }
+    merged.retain(|(key, _)| !texts.contains(key));
+    Ok(merged)
+}
+
+/// Parse an urlencoded form submission, e.g. the body of a POST request,
+/// gathering the fields of the rows of each `RepeatedGroup`, such as
+/// `libraries[2].name`, into the rows of the group in the order of their
+/// index. The indices need not be contiguous since rows can be removed.
+/// The text of the "Other" option of a select is merged into its `OrOther`.
+pub fn parse_form_submission<T: DeserializeOwned>(body: &str) -> Result<T, Error> {
+    let pairs: Vec<(String, String)> = serde_urlencoded::from_str(body)?;
+    let mut plain = Vec::new();
+    // The fields of each row of each group, in the order of the groups
+    type Rows = BTreeMap<usize, Vec<(String, String)>>;
+    let mut groups: Vec<(String, Rows)> = Vec::new();
+    for (key, value) in pairs {
+        let indexed = key.split_once('[').and_then(|(group, rest)| {
+            let (index, field) = rest.split_once("].")?;
+            Some((group, index.parse::<usize>().ok()?, field))
+        });
+        let Some((group, index, field)) = indexed else {
+            plain.push((key, value));
+            continue;
+        };
+        let rows = match groups.iter_mut().find(|(name, _)| name == group) {
+            Some((_, rows)) => rows,
+            None => {
+                groups.push((group.to_string(), Default::default()));
+                &mut groups.last_mut().unwrap().1
+            }
+        };
+        rows.entry(index)
+            .or_default()
+            .push((field.to_string(), value));
+    }
+    let mut plain = merge_other_texts(plain)?;
+    for (group, rows) in groups {
+        let rows = rows
+            .into_values()
+            .map(|row| Ok(serde_urlencoded::to_string(merge_other_texts(row)?)?))
+            .collect::<Result<Vec<_>, Error>>()?;
+        plain.push((group, serde_json::to_string(&rows)?));
+    }
+    Ok(serde_urlencoded::from_str(&serde_urlencoded::to_string(
+        plain,
+    )?)?)
+}
+
+/// Check the values submitted for the number inputs of the form with
+/// `FriendlyError::parse_number()`, so that a typo is reported with the title
+/// of the field instead of as an error of the deserializer. The number
+/// inputs are those of the `i64` fields. Optional inputs may be left blank.
+fn check_number_inputs(form: &Form, body: &str) -> Result<(), FriendlyError> {
+    // A malformed body is reported by `parse_form_submission()`
+    let pairs: Vec<(String, String)> = serde_urlencoded::from_str(body).unwrap_or_default();
+    for element in &form.elements {
+        let FormInput::Input(input) = &element.input else {
+            continue;
+        };
+        if !matches!(input.ty, InputType::Number | InputType::Range) {
+            continue;
+        }
+        let label = match &element.title {
+            Title::WithHelp(title) => &title.title,
+            Title::WithTermDesc(title) => &title.title,
+        };
+        for (_, value) in pairs.iter().filter(|(name, _)| *name == input.name) {
+            if input.required || !value.trim().is_empty() {
+                FriendlyError::parse_number::<i64>(label, value)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Bounds on the number of rows of a `RepeatedGroup`
+#[derive(Debug, Clone)]
+pub struct RepeatedGroupConfig {
+    /// Rows shown in the empty form, which cannot be removed
+    pub min_rows: usize,
+    pub max_rows: Option<usize>,
+}
+
+impl Default for RepeatedGroupConfig {
+    fn default() -> Self {
+        RepeatedGroupConfig {
+            min_rows: 1,
+            max_rows: None,
+        }
+    }
+}
+
+/// The elements of a row with the names of its fields prefixed by the group
+/// and the index, including the fields in the visibility rules
+fn group_row_elements(
+    mut elements: Vec<FormElement>,
+    group: &str,
+    index: &str,
+) -> Vec<FormElement> {
+    for element in &mut elements {
+        let name = element.input.name_mut();
+        *name = group_field_name(group, index, name);
+        if let FormInput::SingleSelect(SingleSelect {
+            name,
+            other: Some(other),
+            ..
+        }) = &mut element.input
+        {
+            other.name = other_field_name(name);
+        }
+        if let Some(rule) = &mut element.visible_when {
+            rule.field = group_field_name(group, index, &rule.field);
+        }
+    }
+    elements
+}
+
+impl<T: IntoHtmlForm + Clone> CreateFormInput for RepeatedGroup<T> {
+    type Config = RepeatedGroupConfig;
+
+    /// The rows of a filled form carry the feedback of their fields
+    fn create_form_input(config: Self::Config, name: String, value: Option<Self>) -> FormInput {
+        let template = group_row_elements(T::form().elements, &name, GROUP_INDEX_PLACEHOLDER);
+        let rows = match value {
+            Some(RepeatedGroup(rows)) => rows
+                .iter()
+                .enumerate()
+                .map(|(i, row)| {
+                    group_row_elements(row.validate().inner().elements, &name, &i.to_string())
+                })
+                .collect(),
+            None => (0..config.min_rows)
+                .map(|i| group_row_elements(T::form().elements, &name, &i.to_string()))
+                .collect(),
+        };
+        FormInput::RepeatedGroup(RepeatedGroupInput {
+            name,
+            template,
+            rows,
+            min_rows: config.min_rows,
+            max_rows: config.max_rows,
+            required: Some(true),
+        })
+    }
+
+    fn default_config() -> Self::Config {
+        RepeatedGroupConfig::default()
+    }
+
+    fn validate_config(&self, config: &Self::Config) -> FieldValidationResult {
+        let rows = self.0.len();
+        let plural = |n: usize| if n == 1 { "row" } else { "rows" };
+        if rows < config.min_rows {
+            FieldValidationResult::Invalid {
+                error: format!(
+                    "Enter at least {} {}",
+                    config.min_rows,
+                    plural(config.min_rows)
+                ),
+            }
+        } else if let Some(max_rows) = config.max_rows.filter(|max_rows| rows > *max_rows) {
+            FieldValidationResult::Invalid {
+                error: format!("Enter at most {max_rows} {}", plural(max_rows)),
+            }
+        } else {
+            FieldValidationResult::Valid
+        }
+    }
+}
+
+impl<T: IntoHtmlForm> FieldValidation for RepeatedGroup<T> {
+    fn validate(&self) -> FieldValidationResult {
+        let invalid_rows: Vec<_> = self
+            .0
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| {
+                let mut visibility = row._field_visibility().into_iter();
+                row._field_validations()
+                    .into_iter()
+                    .any(|validation| visibility.next().unwrap_or(true) && validation.is_invalid())
+            })
+            .map(|(i, _)| (i + 1).to_string())
+            .collect();
+        match &invalid_rows[..] {
+            [] => FieldValidationResult::Valid,
+            [row] => FieldValidationResult::Invalid {
+                error: format!("Fix the errors in row {row}"),
+            },
+            rows => FieldValidationResult::Invalid {
+                error: format!("Fix the errors in rows {}", rows.join(", ")),
+            },
+        }
+    }
+    fn is_empty_submission(&self) -> bool {
+        self.0.is_empty()
+    }
+    fn clear_empty_submission(&mut self) {
+        self.0.iter_mut().for_each(T::clear_empty_submissions);
+    }
+}
+
 /// Hack because the csv crate does not expose this explicitly
 pub fn tabular_file_header<T>() -> Result<Vec<String>, Error>
 where
@@ -666,6 +2122,109 @@ pub trait ConfigureSpreadsheet: Serialize + Default {
     {
         None
     }
+    /// Columns which the user cannot edit. The submitted values must match
+    /// the `initial_value()`, and be empty in the rows beyond it.
+    fn readonly_columns() -> Vec<usize> {
+        Vec::new()
+    }
+    /// Preview column derived from the other columns. It may or may not be
+    /// included in the submission, and is dropped before deserializing.
+    fn computed_column() -> Option<ComputedColumn> {
+        None
+    }
+}
+
+/// The initial value of the spreadsheet as tab separated values
+fn initial_value_tsv<T: ConfigureSpreadsheet>() -> Option<String> {
+    T::initial_value().map(|x| {
+        let mut wtr = csv::WriterBuilder::new()
+            .delimiter(b'\t')
+            .has_headers(false)
+            .from_writer(vec![]);
+        for row in x {
+            wtr.serialize(row).unwrap();
+        }
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    })
+}
+
+fn spreadsheet_records(raw: &str) -> Result<Vec<csv::StringRecord>, csv::Error> {
+    let mut builder = TsvNoHeader::builder();
+    builder.flexible(true);
+    builder.from_reader(raw.as_bytes()).records().try_collect()
+}
+
+/// Drop the computed column from the rows which have one more column than
+/// the spreadsheet
+fn drop_computed_column(raw: String, n_cols: usize) -> String {
+    let Ok(records) = spreadsheet_records(&raw) else {
+        return raw;
+    };
+    if records.iter().all(|record| record.len() != n_cols + 1) {
+        return raw;
+    }
+    let mut wtr = csv::WriterBuilder::new()
+        .delimiter(b'\t')
+        .has_headers(false)
+        .flexible(true)
+        .from_writer(vec![]);
+    for record in records {
+        let fields = if record.len() == n_cols + 1 {
+            record.iter().take(n_cols).collect::<Vec<_>>()
+        } else {
+            record.iter().collect()
+        };
+        wtr.write_record(fields).unwrap();
+    }
+    String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+}
+
+/// True if none of the editable cells of the submission have a value, e.g.
+/// for a spreadsheet left as is apart from its readonly columns
+fn is_blank_spreadsheet<T: ConfigureSpreadsheet>(raw: &str, n_cols: Option<usize>) -> bool {
+    let readonly_columns = T::readonly_columns();
+    let computed_column = n_cols.filter(|_| T::computed_column().is_some());
+    let Ok(records) = spreadsheet_records(raw) else {
+        return false;
+    };
+    records.iter().all(|record| {
+        record.iter().enumerate().all(|(col, cell)| {
+            readonly_columns.contains(&col)
+                || Some(col) == computed_column
+                || cell.trim().is_empty()
+        })
+    })
+}
+
+/// Error of a required spreadsheet which was left blank
+pub const SPREADSHEET_REQUIRED_ERROR: &str =
+    "This spreadsheet is required, but no values were entered";
+
+/// Check that the readonly columns have their initial values
+fn check_readonly_columns<T: ConfigureSpreadsheet>(raw: &str) -> Result<(), String> {
+    let readonly_columns = T::readonly_columns();
+    if readonly_columns.is_empty() {
+        return Ok(());
+    }
+    let labels = tabular_file_header::<T>().map_err(|e| e.to_string())?;
+    let initial = spreadsheet_records(&initial_value_tsv::<T>().unwrap_or_default())
+        .map_err(|e| e.to_string())?;
+    let submitted = spreadsheet_records(raw).map_err(|e| e.to_string())?;
+    for (row, record) in submitted.iter().enumerate() {
+        for &col in &readonly_columns {
+            let expected = initial.get(row).and_then(|r| r.get(col)).unwrap_or("");
+            let found = record.get(col).unwrap_or("");
+            if found != expected {
+                let label = labels.get(col).map_or("", String::as_str);
+                return Err(format!(
+                    "Row {}, column {} ({label}) is readonly: expected {expected:?}, found {found:?}",
+                    row + 1,
+                    col + 1,
+                ));
+            }
+        }
+    }
+    Ok(())
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -680,9 +2239,29 @@ where
     T: Clone + ConfigureSpreadsheet + DeserializeOwned,
 {
     fn from(src: String) -> Self {
-        Spreadsheet {
-            input: TableInput::from(src),
+        let labels = tabular_file_header::<T>().ok();
+        // A blank spreadsheet is not parsed, since the csv errors of the
+        // empty cells would be confusing
+        if is_blank_spreadsheet::<T>(&src, labels.as_ref().map(Vec::len)) {
+            return Spreadsheet {
+                input: TableInput {
+                    raw_value: src,
+                    deserialized: Err(FriendlyError::new(SPREADSHEET_REQUIRED_ERROR)),
+                    phantom: PhantomData,
+                },
+            };
         }
+        let src = match (T::computed_column(), &labels) {
+            (Some(_), Some(labels)) => drop_computed_column(src, labels.len()),
+            _ => src,
+        };
+        let mut input = TableInput::with_labels(src, labels);
+        if input.deserialized.is_ok() {
+            if let Err(e) = check_readonly_columns::<T>(&input.raw_value) {
+                input.deserialized = Err(FriendlyError::new(e));
+            }
+        }
+        Spreadsheet { input }
     }
 }
 
@@ -702,6 +2281,14 @@ where
     pub fn deserialized(self) -> Result<Vec<T>, String> {
         self.input.deserialized()
     }
+    pub fn deserialized_friendly(self) -> Result<Vec<T>, FriendlyError> {
+        self.input.deserialized_friendly()
+    }
+    /// True if none of the editable cells have a value
+    pub fn is_blank(&self) -> bool {
+        let n_cols = tabular_file_header::<T>().ok().map(|labels| labels.len());
+        is_blank_spreadsheet::<T>(&self.input.raw_value, n_cols)
+    }
 }
 
 impl<T> CreateFormInput for Spreadsheet<T>
@@ -718,6 +2305,9 @@ where
             n_cols: config.n_cols,
             max_height: config.max_height,
             value: value.map(|x| x.input.raw_value),
+            readonly_columns: config.readonly_columns,
+            computed_column: config.computed_column,
+            required: Some(true),
         })
     }
 
@@ -729,16 +2319,9 @@ where
             n_rows: T::num_rows(),
             n_cols,
             max_height: T::max_height(),
-            value: T::initial_value().map(|x| {
-                let mut wtr = csv::WriterBuilder::new()
-                    .delimiter(b'\t')
-                    .has_headers(false)
-                    .from_writer(vec![]);
-                for row in x {
-                    wtr.serialize(row).unwrap();
-                }
-                String::from_utf8(wtr.into_inner().unwrap()).unwrap()
-            }),
+            value: initial_value_tsv::<T>(),
+            readonly_columns: T::readonly_columns(),
+            computed_column: T::computed_column(),
         }
     }
 }
@@ -750,4 +2333,659 @@ where
     fn validate(&self) -> FieldValidationResult {
         self.input.validate()
     }
+    fn preview(&self) -> Option<GenericTable> {
+        (!self.is_blank()).then(|| self.input.to_preview_table())
+    }
+    fn is_empty_submission(&self) -> bool {
+        self.is_blank()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_submission_guard() {
+        let guard = SubmissionGuard::new(Duration::from_secs(60));
+        let token = generate_submission_token();
+        assert!(guard.check(&token).is_ok());
+        assert_eq!(
+            guard.check(&token),
+            Err(DuplicateSubmission {
+                token: token.clone()
+            })
+        );
+        assert!(guard.check(&generate_submission_token()).is_ok());
+        assert_eq!(guard.len(), 2);
+    }
+
+    #[test]
+    fn test_submission_guard_ttl_eviction() {
+        let guard = SubmissionGuard::new(Duration::from_secs(60));
+        let start = Instant::now();
+        assert!(guard.check_at("a", start).is_ok());
+        assert!(guard.check_at("b", start + Duration::from_secs(30)).is_ok());
+        assert!(guard
+            .check_at("a", start + Duration::from_secs(59))
+            .is_err());
+        // "a" expired, "b" is still remembered
+        assert!(guard.check_at("a", start + Duration::from_secs(61)).is_ok());
+        assert!(guard
+            .check_at("b", start + Duration::from_secs(61))
+            .is_err());
+        assert!(guard
+            .check_at("b", start + Duration::from_secs(200))
+            .is_ok());
+        assert_eq!(guard.len(), 1);
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Sample {
+        name: String,
+        num_cells: u32,
+    }
+
+    fn parse<B: CsvReaderBuilder + Clone>(raw: &str) -> TableInput<Sample, B> {
+        TableInput::from(raw.to_string())
+    }
+
+    fn samples() -> Vec<Sample> {
+        vec![
+            Sample {
+                name: "pbmc".into(),
+                num_cells: 1000,
+            },
+            Sample {
+                name: "brain".into(),
+                num_cells: 500,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_table_input_builders() {
+        assert_eq!(
+            parse::<TsvNoHeader>("pbmc\t1000\nbrain\t500").deserialized(),
+            Ok(samples())
+        );
+        assert_eq!(
+            parse::<CsvNoHeader>("pbmc,1000\nbrain,500\n").deserialized(),
+            Ok(samples())
+        );
+        assert_eq!(
+            parse::<TsvWithHeader>("name\tnum_cells\npbmc\t1000\nbrain\t500").deserialized(),
+            Ok(samples())
+        );
+        // Columns are matched by the header
+        assert_eq!(
+            parse::<CsvWithHeader>("num_cells,name\n1000,pbmc\n500,brain").deserialized(),
+            Ok(samples())
+        );
+        assert!(parse::<CsvNoHeader>("name,num_cells\npbmc,1000")
+            .deserialized()
+            .is_err());
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Library {
+        name: String,
+        num_cells: u32,
+        fraction: f64,
+    }
+
+    fn friendly<B: CsvReaderBuilder + Clone>(raw: &str) -> FriendlyError {
+        TableInput::<Library, B>::from(raw.to_string())
+            .deserialized_friendly()
+            .unwrap_err()
+    }
+
+    #[test]
+    fn test_friendly_csv_errors() {
+        let header = "name,num_cells,fraction\n";
+        let invalid_digit = friendly::<CsvWithHeader>(&format!("{header}pbmc,10a0,0.5"));
+        assert_eq!(
+            invalid_digit.message,
+            r#"Row 1, column num_cells: "10a0" is not a number"#
+        );
+        assert!(invalid_digit
+            .details
+            .unwrap()
+            .contains("invalid digit found in string"));
+
+        assert_eq!(
+            friendly::<CsvWithHeader>(&format!("{header}pbmc,1000,0.5\nbrain,12.5,0.5")).message,
+            r#"Row 2, column num_cells: "12.5" should be a whole number"#
+        );
+        assert_eq!(
+            friendly::<CsvWithHeader>(&format!("{header}pbmc,-5,0.5")).message,
+            r#"Row 1, column num_cells: "-5" is out of range"#
+        );
+        assert_eq!(
+            friendly::<CsvWithHeader>(&format!("{header}pbmc,1000,half")).message,
+            r#"Row 1, column fraction: "half" is not a number"#
+        );
+        assert_eq!(
+            friendly::<CsvWithHeader>("name,num_cells\npbmc,1000").message,
+            "Row 1: the column fraction is missing"
+        );
+        assert_eq!(
+            friendly::<CsvNoHeader>("pbmc,1000,0.5\nbrain,500").message,
+            "Row 2 has 2 columns, expected 3 columns"
+        );
+        // Without a header the columns are numbered
+        assert_eq!(
+            friendly::<CsvNoHeader>("pbmc,x,0.5").message,
+            r#"Row 1, column 2: "x" is not a number"#
+        );
+    }
+
+    #[test]
+    fn test_friendly_validation_feedback() {
+        let input = TableInput::<Library, CsvNoHeader>::from("pbmc,x,0.5".to_string());
+        let mut element = FormElement {
+            title: Title::new("Libraries"),
+            input: String::default_form_input("libraries".into(), None),
+            feedback: InputFeedback::default(),
+            preview: None,
+            visible_when: None,
+        };
+        element.update(input.validate());
+        assert_eq!(
+            element.feedback.error.as_deref(),
+            Some(r#"Row 1, column 2: "x" is not a number"#)
+        );
+        let feedback = serde_json::to_value(&element.feedback).unwrap();
+        assert!(feedback["details"]
+            .as_str()
+            .unwrap()
+            .contains("invalid digit"));
+        assert!(serde_json::to_value(InputFeedback::default())
+            .unwrap()
+            .get("details")
+            .is_none());
+    }
+
+    #[test]
+    fn test_friendly_parse_number() {
+        assert_eq!(
+            FriendlyError::parse_number::<i64>("Expected cells", " 42 "),
+            Ok(42)
+        );
+        let err = |value| {
+            FriendlyError::parse_number::<i64>("Expected cells", value)
+                .unwrap_err()
+                .message
+        };
+        assert_eq!(err("many"), r#"Expected cells: "many" is not a number"#);
+        assert_eq!(
+            err("4.5"),
+            r#"Expected cells: "4.5" should be a whole number"#
+        );
+        assert_eq!(
+            err("99999999999999999999"),
+            r#"Expected cells: "99999999999999999999" is too large or too small"#
+        );
+        assert_eq!(err(""), "Expected cells is empty, expected a number");
+        assert_eq!(
+            FriendlyError::parse_number::<f64>("Fraction", "1,5")
+                .unwrap_err()
+                .message,
+            r#"Fraction: "1,5" is not a number"#
+        );
+    }
+
+    #[test]
+    fn test_table_input_comments() {
+        let raw =
+            "# Samples in the run\r\nname,num_cells\r\n# Control\r\npbmc,1000\r\nbrain,500\r\n";
+        let input = parse::<CsvWithHeader>(raw);
+        assert!(matches!(input.validate(), FieldValidationResult::Valid));
+        assert_eq!(input.deserialized(), Ok(samples()));
+        // Comments are not stripped without the hook
+        assert_eq!(
+            parse::<TsvNoHeader>("# pbmc\t1000").deserialized(),
+            Ok(vec![Sample {
+                name: "# pbmc".into(),
+                num_cells: 1000
+            }])
+        );
+        assert!(matches!(strip_comment_lines("a,1\nb,2"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_table_input_raw_value() {
+        let raw = "# Samples\nname,num_cells\npbmc,1000\nbrain,500";
+        let input = parse::<CsvWithHeader>(raw);
+        let serialized = serde_json::to_value(&input).unwrap();
+        assert_eq!(serialized, raw);
+        let deserialized: TableInput<Sample, CsvWithHeader> =
+            serde_json::from_value(serialized).unwrap();
+        assert_eq!(deserialized.deserialized(), Ok(samples()));
+
+        let FormInput::TextArea(text_area) = TableInput::<Sample, CsvWithHeader>::create_form_input(
+            TextAreaConfig::default(),
+            "samples".into(),
+            Some(parse(raw)),
+        ) else {
+            unreachable!()
+        };
+        assert_eq!(text_area.value.as_deref(), Some(raw));
+    }
+
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    struct SampleSheetRow {
+        well: String,
+        name: String,
+        num_cells: u32,
+    }
+
+    impl ConfigureSpreadsheet for SampleSheetRow {
+        fn initial_value() -> Option<Vec<Self>> {
+            Some(
+                ["A1", "A2"]
+                    .into_iter()
+                    .map(|well| SampleSheetRow {
+                        well: well.into(),
+                        ..Default::default()
+                    })
+                    .collect(),
+            )
+        }
+        fn readonly_columns() -> Vec<usize> {
+            vec![0]
+        }
+        fn computed_column() -> Option<ComputedColumn> {
+            Some(ComputedColumn {
+                label: "Sample ID".into(),
+                source_columns: vec![0, 1],
+            })
+        }
+    }
+
+    fn sheet(raw: &str) -> Result<Vec<SampleSheetRow>, String> {
+        Spreadsheet::<SampleSheetRow>::from(raw.to_string()).deserialized()
+    }
+
+    fn sheet_row(well: &str, name: &str, num_cells: u32) -> SampleSheetRow {
+        SampleSheetRow {
+            well: well.into(),
+            name: name.into(),
+            num_cells,
+        }
+    }
+
+    #[test]
+    fn test_spreadsheet_computed_column() {
+        let expected = Ok(vec![
+            sheet_row("A1", "pbmc", 1000),
+            sheet_row("A2", "brain", 500),
+        ]);
+        // With and without the computed column
+        assert_eq!(sheet("A1\tpbmc\t1000\nA2\tbrain\t500"), expected);
+        assert_eq!(
+            sheet("A1\tpbmc\t1000\tA1_pbmc\nA2\tbrain\t500\tA2_brain\n"),
+            expected
+        );
+
+        let FormInput::Spreadsheet(input) = Spreadsheet::<SampleSheetRow>::create_form_input(
+            Spreadsheet::<SampleSheetRow>::default_config(),
+            "samples".into(),
+            None,
+        ) else {
+            unreachable!()
+        };
+        let json = serde_json::to_value(&input).unwrap();
+        assert_eq!(json["readonlyColumns"], serde_json::json!([0]));
+        assert_eq!(
+            json["computedColumn"],
+            serde_json::json!({"label": "Sample ID", "sourceColumns": [0, 1]})
+        );
+        assert_eq!(json["nCols"], 3);
+        assert_eq!(
+            Spreadsheet::<SampleSheetRow>::default_config()
+                .value
+                .as_deref(),
+            Some("A1\t\t0\nA2\t\t0\n")
+        );
+    }
+
+    #[test]
+    fn test_spreadsheet_readonly_tampering() {
+        assert_eq!(
+            sheet("A1\tpbmc\t1000\nB7\tbrain\t500"),
+            Err(r#"Row 2, column 1 (well) is readonly: expected "A2", found "B7""#.into())
+        );
+        assert_eq!(
+            sheet("A1\tpbmc\t1000\nA2\tbrain\t500\nA3\theart\t10"),
+            Err(r#"Row 3, column 1 (well) is readonly: expected "", found "A3""#.into())
+        );
+        // The other columns are editable, and rows can be left out
+        assert_eq!(sheet("A1\tliver\t1"), Ok(vec![sheet_row("A1", "liver", 1)]));
+        let tampered = Spreadsheet::<SampleSheetRow>::from("A9\tpbmc\t1000".to_string());
+        assert!(matches!(
+            tampered.validate(),
+            FieldValidationResult::Invalid { error } if error.starts_with("Row 1, column 1 (well)")
+        ));
+    }
+
+    #[test]
+    fn test_spreadsheet_optional() {
+        let create = |value: Option<Spreadsheet<SampleSheetRow>>| {
+            let FormInput::Spreadsheet(input) =
+                Option::<Spreadsheet<SampleSheetRow>>::create_form_input(
+                    Spreadsheet::<SampleSheetRow>::default_config(),
+                    "samples".into(),
+                    value.map(Some),
+                )
+            else {
+                unreachable!()
+            };
+            serde_json::to_value(input).unwrap()
+        };
+        assert_eq!(create(None)["required"], false);
+        let FormInput::Spreadsheet(required) = Spreadsheet::<SampleSheetRow>::create_form_input(
+            Spreadsheet::<SampleSheetRow>::default_config(),
+            "samples".into(),
+            None,
+        ) else {
+            unreachable!()
+        };
+        assert_eq!(required.required, Some(true));
+
+        // Blank apart from the readonly wells and the computed column
+        let blank = "A1			A1_
+A2	 		A2_
+";
+        let optional = Some(Spreadsheet::<SampleSheetRow>::from(blank.to_string()));
+        assert!(optional.is_empty_submission());
+        assert!(matches!(optional.validate(), FieldValidationResult::Valid));
+        assert!(optional.preview().is_none());
+
+        let clear = |samples: &str| {
+            let mut samples = Some(Spreadsheet::<SampleSheetRow>::from(samples.to_string()));
+            samples.clear_empty_submission();
+            samples
+        };
+        assert!(clear(blank).is_none());
+        assert!(clear("").is_none());
+        let filled = clear("A1	pbmc	1000").unwrap();
+        assert_eq!(
+            filled.deserialized(),
+            Ok(vec![sheet_row("A1", "pbmc", 1000)])
+        );
+
+        // A required spreadsheet reports the missing values, not a csv error
+        let required = Spreadsheet::<SampleSheetRow>::from(blank.to_string());
+        assert!(matches!(
+            required.validate(),
+            FieldValidationResult::Invalid { error } if error == SPREADSHEET_REQUIRED_ERROR
+        ));
+        assert_eq!(sheet(""), Err(SPREADSHEET_REQUIRED_ERROR.into()));
+    }
+
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    struct PreviewSample {
+        name: String,
+        num_cells: u32,
+    }
+
+    #[test]
+    fn test_table_input_preview() {
+        let input = TableInput::<PreviewSample, TsvNoHeader>::from(
+            "pbmc\t1000\nbrain\t<many>\nliver\t10".to_string(),
+        );
+        let table = input.to_preview_table();
+        assert_eq!(
+            table.header,
+            Some(vec!["name".to_string(), "num_cells".to_string()])
+        );
+        let rows: Vec<_> = table.rows.iter().map(|row| row.0.clone()).collect();
+        assert_eq!(
+            rows,
+            vec![
+                vec!["pbmc", "1000"],
+                vec!["⚠ brain", "<many>"],
+                vec!["liver", "10"],
+            ]
+        );
+        let rich_rows = table.rich_rows.unwrap();
+        assert_eq!(rich_rows[0].0[0], RichCell::Text("pbmc".into()));
+        let RichCell::Html(bad_cell) = &rich_rows[1].0[0] else {
+            panic!("{:?}", rich_rows[1].0[0]);
+        };
+        assert!(bad_cell.starts_with(r#"<span class="text-danger" title="CSV deserialize error"#));
+        assert!(bad_cell.ends_with(">⚠ brain</span>"));
+        assert_eq!(
+            rich_rows[1].0[1],
+            RichCell::Html(r#"<span class="text-danger">&lt;many&gt;</span>"#.into())
+        );
+
+        // Without a header in the input or from the type
+        assert!(input.preview().unwrap().header.is_none());
+        let empty = TableInput::<PreviewSample, TsvNoHeader>::from(" \n".to_string());
+        assert!(empty.preview().is_none());
+    }
+
+    #[test]
+    fn test_form_element_preview_template() {
+        let mut element = FormElement {
+            title: Title::new("Samples"),
+            input: FormInput::TextArea(TextArea {
+                name: "samples".into(),
+                rows: None,
+                placeholder: None,
+                required: Some(true),
+                value: None,
+                max_length: None,
+            }),
+            feedback: InputFeedback::default(),
+            preview: None,
+            visible_when: None,
+        };
+        assert!(!element
+            .template(Some("e".into()))
+            .contains("data-component=\"Table\""));
+        element.preview = Some(
+            TableInput::<PreviewSample, TsvNoHeader>::from("pbmc\t1000".to_string())
+                .to_preview_table(),
+        );
+        let template = element.template(Some("e".into()));
+        assert!(template.ends_with(
+            r#"<div data-key="e.feedback" data-component="InputFeedback"></div>
+</div>
+</div>
+<div class="row">
+<div class="col">
+<div data-key="e.preview" data-component="Table"></div>
+</div>
+</div>
+"#
+        ));
+        let json = serde_json::to_value(&element).unwrap();
+        assert_eq!(
+            json["preview"]["rows"],
+            serde_json::json!([["pbmc", "1000"]])
+        );
+    }
+
+    #[test]
+    fn test_wizard_form_steps() {
+        let form = |names: &[&str]| Form {
+            config: FormConfig::new("", FormMethod::Post).with_submit_label("Run"),
+            elements: names
+                .iter()
+                .map(|name| FormElement {
+                    title: Title::new(*name),
+                    input: String::default_form_input(name.to_string(), None),
+                    feedback: InputFeedback::default(),
+                    preview: None,
+                    visible_when: None,
+                })
+                .collect(),
+        };
+
+        let wizard = WizardForm::new(vec![form(&["sample"]), form(&["reference", "chemistry"])])
+            .unwrap()
+            .step_titles(vec!["Sample".into(), "Reference".into()]);
+        assert_eq!(wizard.progress.steps, vec!["Sample", "Reference"]);
+        assert_eq!(wizard.steps.len(), 2);
+        assert_eq!(wizard.steps[1].elements.len(), 2);
+        assert_eq!(wizard.config.submit_label(), Some("Run"));
+        let template = wizard.template(Some("w".into()));
+        assert!(template.starts_with(
+            r#"<div data-key="w.config" data-component="WizardFormWrapper">
+<div data-key="w.progress" data-component="StepProgress"></div>
+<div data-key="w.steps[0]" data-component="WizardStep">"#
+        ));
+        assert!(template.contains(r#"data-key="w.steps[1].elements[1].feedback""#));
+
+        let three = || form(&["a", "b", "c"]);
+        assert!(WizardForm::from_steps(three(), &[1, 2, 1]).is_ok());
+        assert_eq!(
+            WizardForm::from_steps(three(), &[1, 3, 3])
+                .err()
+                .unwrap()
+                .to_string(),
+            "Steps of a wizard form should be contiguous starting at 1, step 2 has no elements"
+        );
+        assert!(WizardForm::from_steps(three(), &[0, 1, 1]).is_err());
+        assert!(WizardForm::from_steps(three(), &[1, 1]).is_err());
+        assert!(WizardForm::new(vec![]).is_err());
+        assert!(WizardForm::new(vec![form(&["a"]), form(&[])]).is_err());
+    }
+
+    #[test]
+    fn test_deterministic_submission_tokens() {
+        use_deterministic_submission_tokens();
+        assert_eq!(
+            generate_submission_token(),
+            "00000000-0000-0000-0000-000000000000"
+        );
+        assert_eq!(
+            generate_submission_token(),
+            "00000000-0000-0000-0000-000000000001"
+        );
+    }
+
+    #[test]
+    fn test_or_other() {
+        use serde_json::json;
+
+        #[derive(Debug, Clone, PartialEq, Serialize)]
+        enum Chemistry {
+            #[serde(rename = "SC3Pv3")]
+            ThreePrimeV3,
+            #[serde(rename = "SC5P-PE")]
+            FivePrimePe,
+        }
+        impl EnumSelect for Chemistry {
+            fn variants() -> Vec<Self> {
+                vec![Chemistry::ThreePrimeV3, Chemistry::FivePrimePe]
+            }
+        }
+        type Field = OrOther<Chemistry>;
+
+        let known = Field::Known(Chemistry::FivePrimePe);
+        let other = Field::Other("SC3Pv4".into());
+        assert_eq!(serde_json::to_value(&known).unwrap(), json!("SC5P-PE"));
+        assert_eq!(
+            serde_json::to_value(&other).unwrap(),
+            json!({"other": "SC3Pv4"})
+        );
+        for value in [known, other] {
+            let roundtrip: Field =
+                serde_json::from_value(serde_json::to_value(&value).unwrap()).unwrap();
+            assert_eq!(roundtrip, value);
+            assert!(matches!(value.validate(), FieldValidationResult::Valid));
+        }
+        assert_eq!(
+            serde_json::from_value::<Field>(json!("Other")).unwrap(),
+            Field::Other(String::new())
+        );
+        assert!(serde_json::from_value::<Field>(json!("SC3Pv4")).is_err());
+
+        for text in ["", "  "] {
+            assert!(matches!(
+                Field::Other(text.into()).validate(),
+                FieldValidationResult::Invalid { .. }
+            ));
+        }
+        // An optional field is still validated once "Other" is selected
+        assert!(matches!(
+            Some(Field::Other(String::new())).validate(),
+            FieldValidationResult::Invalid { .. }
+        ));
+
+        let FormInput::SingleSelect(select) = Field::create_form_input(
+            SingleSelectType::Dropdown,
+            "chemistry".into(),
+            Some(Field::Other("SC3Pv4".into())),
+        ) else {
+            unreachable!()
+        };
+        assert_eq!(select.options, ["SC3Pv3", "SC5P-PE", "Other"]);
+        assert_eq!(select.selected.as_deref(), Some("Other"));
+        let select_other = select.other.unwrap();
+        assert_eq!(select_other.name, "chemistry_other");
+        assert_eq!(select_other.value.as_deref(), Some("SC3Pv4"));
+
+        // The text input of the submitted form, also in the rows of a group
+        #[derive(Debug, Deserialize)]
+        struct Library {
+            chemistry: Field,
+        }
+        #[derive(Debug, Deserialize)]
+        struct Submission {
+            chemistry: Field,
+            backup: Field,
+            libraries: RepeatedGroup<Library>,
+        }
+        let submission: Submission = parse_form_submission(
+            "chemistry=Other&chemistry_other=SC3Pv4%20%26%20more\
+             &backup=SC5P-PE&backup_other=ignored\
+             &libraries%5B0%5D.chemistry=Other&libraries%5B0%5D.chemistry_other=SC3Pv4\
+             &libraries%5B1%5D.chemistry=Other&libraries%5B1%5D.chemistry_other=",
+        )
+        .unwrap();
+        assert_eq!(submission.chemistry, Field::Other("SC3Pv4 & more".into()));
+        assert_eq!(submission.backup, Field::Known(Chemistry::FivePrimePe));
+        let libraries: Vec<_> = submission
+            .libraries
+            .0
+            .into_iter()
+            .map(|l| l.chemistry)
+            .collect();
+        assert_eq!(
+            libraries,
+            [Field::Other("SC3Pv4".into()), Field::Other(String::new())]
+        );
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize)]
+    enum WithOther {
+        Known,
+        Other,
+    }
+    impl EnumSelect for WithOther {
+        fn variants() -> Vec<Self> {
+            vec![WithOther::Known, WithOther::Other]
+        }
+    }
+
+    #[test]
+    fn test_or_other_rejects_other_variant() {
+        let error =
+            serde_json::from_value::<OrOther<WithOther>>(serde_json::json!("Known")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "a variant has the value \"Other\" reserved for the text of OrOther"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "reserved for the text of OrOther")]
+    fn test_or_other_form_rejects_other_variant() {
+        OrOther::<WithOther>::create_form_input(SingleSelectType::Radio, "field".into(), None);
+    }
 }
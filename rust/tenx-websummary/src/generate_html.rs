@@ -1,9 +1,13 @@
 use std::io::prelude::*;
 use std::path::Path;
+use std::sync::OnceLock;
 use std::{borrow::Cow, fs::read_to_string};
 
-use anyhow::{format_err, Error};
-use regex::Regex;
+use anyhow::{bail, Context, Error};
+use regex::{Captures, Regex};
+
+use crate::components::escape_html;
+use crate::scrape_json::{DATA_BEGIN_MARKER, DATA_END_MARKER};
 
 pub struct WebSummaryBuildFiles<'a> {
     pub script_js: Cow<'a, str>,
@@ -19,6 +23,11 @@ impl WebSummaryBuildFiles<'_> {
             template_html: Cow::Owned(template_html),
         }
     }
+    /// The build files bundled with the crate
+    #[cfg(feature = "generate_html")]
+    pub fn bundled() -> Self {
+        Self::_generated()
+    }
     #[cfg(feature = "generate_html")]
     fn _generated() -> Self {
         use tenx_websummary_build::{SCRIPT, STYLES, TEMPLATE};
@@ -30,6 +39,44 @@ impl WebSummaryBuildFiles<'_> {
     }
 }
 
+/// Marker for the text of the `<title>` in the template
+pub const PAGE_TITLE_MARKER: &str = "[[ page.title ]]";
+/// Marker for the `<link rel="icon">` tag in the template
+pub const FAVICON_MARKER: &str = "[[ favicon ]]";
+/// Title used for the `[[ page.title ]]` marker if there is none
+pub const DEFAULT_PAGE_TITLE: &str = "Web Summary";
+
+/// Values for the optional `[[ page.title ]]` and `[[ favicon ]]` markers of
+/// the template, and the scripts run along with the data. Templates without
+/// the markers are left unchanged.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageHead {
+    /// Text of the browser tab, `DEFAULT_PAGE_TITLE` if `None`
+    pub title: Option<String>,
+    /// Icon of the browser tab as a data uri, e.g.
+    /// `data:image/png;base64,...`. The browser default if `None`.
+    pub favicon_data_uri: Option<String>,
+    /// Javascript run right after the data is defined, before the frontend
+    /// reads it, e.g. the loader of the binary resources. Written after the
+    /// line holding the data, in the same `<script>`.
+    pub data_scripts: Vec<String>,
+}
+
+impl PageHead {
+    fn title_html(&self) -> String {
+        escape_html(self.title.as_deref().unwrap_or(DEFAULT_PAGE_TITLE))
+    }
+    fn favicon_html(&self) -> String {
+        match &self.favicon_data_uri {
+            Some(uri) => format!(r#"<link rel="icon" href="{}">"#, escape_html(uri)),
+            None => String::new(),
+        }
+    }
+}
+
+/// Default limit on how deeply `[[ include ... ]]` directives can be nested
+pub const DEFAULT_MAX_INCLUDE_DEPTH: usize = 100;
+
 /// Possible ways to load template data
 pub enum TemplateInfo<P: AsRef<Path> = String> {
     /// Use the default (bundled) template
@@ -41,6 +88,59 @@ pub enum TemplateInfo<P: AsRef<Path> = String> {
     Static(String),
 }
 
+fn include_regex() -> &'static Regex {
+    static INCLUDE_RE: OnceLock<Regex> = OnceLock::new();
+    INCLUDE_RE
+        .get_or_init(|| Regex::new(r"\[\[ include (?P<filename>[a-zA-Z./_\d-]+) \]\]").unwrap())
+}
+
+/// Recursively replace the `[[ include <filename> ]]` directives with the
+/// contents of the file from the template directory. `chain` holds the files
+/// being included, which is used to detect cycles.
+pub(crate) fn expand_includes(
+    contents: &str,
+    template_dir: Option<&Path>,
+    chain: &mut Vec<String>,
+    max_depth: usize,
+) -> Result<String, Error> {
+    let mut expanded = String::with_capacity(contents.len());
+    let mut last_end = 0;
+    for caps in include_regex().captures_iter(contents) {
+        let directive = caps.get(0).unwrap();
+        let filename = &caps["filename"];
+        let Some(template_dir) = template_dir else {
+            bail!(
+                "found replacement {} but template_dir is None",
+                directive.as_str()
+            );
+        };
+        if let Some(start) = chain.iter().position(|f| f == filename) {
+            bail!(
+                "Include cycle detected: {} -> {filename}",
+                chain[start..].join(" -> ")
+            );
+        }
+        if chain.len() >= max_depth {
+            bail!(
+                "Maximum include depth of {max_depth} exceeded: {} -> {filename}",
+                chain.join(" -> ")
+            );
+        }
+        let path = template_dir.join(filename);
+        let src = read_to_string(&path)
+            .with_context(|| format!("Unable to read the included file {}", path.display()))?;
+        chain.push(filename.to_string());
+        let src = expand_includes(&src, Some(template_dir), chain, max_depth)?;
+        chain.pop();
+
+        expanded.push_str(&contents[last_end..directive.start()]);
+        expanded.push_str(&src);
+        last_end = directive.end();
+    }
+    expanded.push_str(&contents[last_end..]);
+    Ok(expanded)
+}
+
 /// Writes compiled all-in-one HTML of the websummary, returning an error if any.
 ///
 /// # Arguments
@@ -80,7 +180,56 @@ where
 /// * `script_js, styles_css, template` - Web summary build artifacts
 pub fn generate_html_summary_with_build_files<P, W>(
     json_data: &str,
-    mut summary_contents: String,
+    summary_contents: String,
+    template_info: TemplateInfo<P>,
+    writer: W,
+    build_files: WebSummaryBuildFiles<'_>,
+) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+    W: Write,
+{
+    generate_html_summary_with_head(
+        json_data,
+        summary_contents,
+        template_info,
+        writer,
+        build_files,
+        &PageHead::default(),
+    )
+}
+
+/// Same as `generate_html_summary_with_build_files()`, substituting the
+/// `[[ page.title ]]` and `[[ favicon ]]` markers of the template from `head`
+pub fn generate_html_summary_with_head<P, W>(
+    json_data: &str,
+    summary_contents: String,
+    template_info: TemplateInfo<P>,
+    writer: W,
+    build_files: WebSummaryBuildFiles<'_>,
+    head: &PageHead,
+) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+    W: Write,
+{
+    generate_html_summary_with_max_include_depth(
+        json_data,
+        summary_contents,
+        template_info,
+        writer,
+        build_files,
+        head,
+        DEFAULT_MAX_INCLUDE_DEPTH,
+    )
+}
+
+/// Same as `generate_html_summary_with_head()`, with a limit on how deeply
+/// the `[[ include ... ]]` directives can be nested instead of
+/// `DEFAULT_MAX_INCLUDE_DEPTH`
+pub fn generate_html_summary_with_max_include_depth<P, W>(
+    json_data: &str,
+    summary_contents: String,
     template_info: TemplateInfo<P>,
     mut writer: W,
     WebSummaryBuildFiles {
@@ -88,50 +237,62 @@ pub fn generate_html_summary_with_build_files<P, W>(
         styles_css,
         template_html,
     }: WebSummaryBuildFiles<'_>,
+    head: &PageHead,
+    max_include_depth: usize,
 ) -> Result<(), Error>
 where
     P: AsRef<Path>,
     W: Write,
 {
-    let (template_dir, mut template_src) = match template_info {
-        TemplateInfo::Default => (None, String::from(template_html)),
-        TemplateInfo::Dynamic(path) => {
-            let template = path.as_ref().join("template.html");
-            (
-                Some(path),
-                if template.exists() {
-                    read_to_string(template)?
-                } else {
-                    String::from(template_html)
-                },
-            )
-        }
-        TemplateInfo::Static(template_src) => (None, template_src),
+    let (template_dir, static_template) = match template_info {
+        TemplateInfo::Default => (None, None),
+        TemplateInfo::Dynamic(dir) => (Some(dir), None),
+        TemplateInfo::Static(template_src) => (None, Some(template_src)),
     };
-    let re = Regex::new(r"\[\[ include (?P<filename>[a-zA-Z./_\d-]+) \]\]").unwrap();
-    let mut count = 0;
-    loop {
-        if count > 100 {
-            return Err(format_err!("Maximum recursion depth exceeded!"));
-        }
-        count += 1;
-        if let Some(m) = re.captures(&summary_contents) {
-            if let Some(template_dir) = template_dir.as_ref() {
-                let path = template_dir
-                    .as_ref()
-                    .join(m.name("filename").unwrap().as_str());
-                let src = read_to_string(path)?;
-                summary_contents = summary_contents.replace(m.get(0).unwrap().as_str(), &src);
-            } else {
-                return Err(format_err!(
-                    "found replacement {} but template_dir is None",
-                    m.get(0).unwrap().as_str()
-                ));
-            }
-        } else {
-            break;
-        }
-    }
+    let dynamic_template = template_dir
+        .as_ref()
+        .map(|dir| dir.as_ref().join("template.html"))
+        .filter(|template| template.exists());
+    let mut template_src = match (static_template, dynamic_template) {
+        (Some(template_src), _) => template_src,
+        (None, Some(template)) => read_to_string(template)?,
+        (None, None) => String::from(template_html),
+    };
+    let summary_contents = {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("resolve_includes").entered();
+        expand_includes(
+            &summary_contents,
+            template_dir.as_ref().map(AsRef::as_ref),
+            &mut Vec::new(),
+            max_include_depth,
+        )?
+    };
+
+    // Enclose the line holding the data within markers so that the data can be
+    // scraped unambiguously. See `scrape_json`.
+    // The data scripts start with a `;` since the data line may not end with
+    // one
+    let data_scripts: String = head
+        .data_scripts
+        .iter()
+        .map(|script| format!("\n;{script}"))
+        .collect();
+    let data_line_re = Regex::new(r"(?m)^.*\[\[ data\.js \]\].*$").unwrap();
+    template_src = data_line_re
+        .replace(&template_src, |caps: &Captures<'_>| {
+            format!(
+                "{DATA_BEGIN_MARKER}\n{}\n{DATA_END_MARKER}{data_scripts}",
+                &caps[0]
+            )
+        })
+        .into_owned();
+
+    // The head markers are substituted before the data and the summary, which
+    // could contain the same text
+    template_src = template_src
+        .replace(PAGE_TITLE_MARKER, &head.title_html())
+        .replace(FAVICON_MARKER, &head.favicon_html());
 
     for (from, to) in &[
         ("[[ tenx-websummary-script.min.js ]]", script_js),
@@ -139,6 +300,8 @@ where
         ("[[ data.js ]]", json_data.into()),
         ("[[ summary.html ]]", summary_contents.into()),
     ] {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("substitute_marker", marker = from).entered();
         template_src = template_src.replace(from, to);
     }
 
@@ -147,29 +310,190 @@ where
     Ok(())
 }
 
-#[cfg(all(test, feature = "generate_html"))]
+#[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs::read_to_string;
+    #[cfg(feature = "generate_html")]
+    use crate::dev_artifacts::{DATA_JSON, SUMMARY_HTML};
+    #[cfg(feature = "generate_html")]
+    use crate::fixture::SummaryFixture;
+    use crate::test_utils::ScratchDir;
+
+    fn render_with_max_include_depth(
+        summary: &str,
+        template_info: TemplateInfo<&Path>,
+        max_include_depth: usize,
+    ) -> Result<String, Error> {
+        let mut out = Vec::new();
+        generate_html_summary_with_max_include_depth(
+            "{}",
+            summary.to_string(),
+            template_info,
+            &mut out,
+            WebSummaryBuildFiles::new(String::new(), String::new(), "[[ summary.html ]]".into()),
+            &PageHead::default(),
+            max_include_depth,
+        )?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    fn render(summary: &str, template_info: TemplateInfo<&Path>) -> Result<String, Error> {
+        render_with_max_include_depth(summary, template_info, DEFAULT_MAX_INCLUDE_DEPTH)
+    }
 
+    #[test]
+    fn test_include_cycle() {
+        let dir = ScratchDir::with_files(
+            "includes_cycle",
+            &[
+                ("a.html", "A [[ include b.html ]]"),
+                ("b.html", "B [[ include a.html ]]"),
+            ],
+        );
+        let err = render("[[ include a.html ]]", TemplateInfo::Dynamic(dir.path())).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Include cycle detected: a.html -> b.html -> a.html"
+        );
+    }
+
+    #[test]
+    fn test_many_flat_includes() {
+        let files: Vec<_> = (0..150)
+            .map(|i| (format!("part_{i}.html"), format!("<p>{i}</p>")))
+            .collect();
+        let dir = ScratchDir::with_files("includes_flat", &files);
+        let summary = (0..150)
+            .map(|i| format!("[[ include part_{i}.html ]]"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let html = render(&summary, TemplateInfo::Dynamic(dir.path())).unwrap();
+        assert!(html.contains("<p>0</p>") && html.contains("<p>149</p>"));
+        assert!(!html.contains("[[ include"));
+    }
+
+    #[test]
+    fn test_max_include_depth() {
+        // part_0 includes part_1 which includes part_2 and so on
+        let files: Vec<_> = (0..5)
+            .map(|i| {
+                let next = if i < 4 {
+                    format!("[[ include part_{}.html ]]", i + 1)
+                } else {
+                    String::new()
+                };
+                (format!("part_{i}.html"), format!("{i}{next}"))
+            })
+            .collect();
+        let dir = ScratchDir::with_files("includes_depth", &files);
+        let summary = "[[ include part_0.html ]] [[ include part_0.html ]]";
+
+        assert_eq!(
+            render(summary, TemplateInfo::Dynamic(dir.path())).unwrap(),
+            "01234 01234"
+        );
+        assert_eq!(
+            render_with_max_include_depth(summary, TemplateInfo::Dynamic(dir.path()), 5).unwrap(),
+            "01234 01234"
+        );
+        let err = render_with_max_include_depth(summary, TemplateInfo::Dynamic(dir.path()), 3)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Maximum include depth of 3 exceeded: part_0.html -> part_1.html -> part_2.html -> part_3.html"
+        );
+    }
+
+    #[test]
+    fn test_include_without_template_dir() {
+        let err = render("[[ include a.html ]]", TemplateInfo::Default).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "found replacement [[ include a.html ]] but template_dir is None"
+        );
+    }
+
+    #[test]
+    fn test_page_head_markers() {
+        let render_head = |template: &str, head: &PageHead| {
+            let mut out = Vec::new();
+            generate_html_summary_with_head(
+                "{}",
+                "<p>summary</p>".to_string(),
+                TemplateInfo::<String>::Static(template.into()),
+                &mut out,
+                WebSummaryBuildFiles::new(String::new(), String::new(), String::new()),
+                head,
+            )
+            .unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let head = PageHead {
+            title: Some("sample1 - PBMC".into()),
+            favicon_data_uri: Some("data:image/svg+xml;base64,PHN2Zz4=".into()),
+            data_scripts: Vec::new(),
+        };
+
+        let template = "<title>[[ page.title ]]</title>[[ favicon ]]\n[[ summary.html ]]";
+        assert_eq!(
+            render_head(template, &head),
+            r#"<title>sample1 - PBMC</title><link rel="icon" href="data:image/svg+xml;base64,PHN2Zz4=">
+<p>summary</p>"#
+        );
+        assert_eq!(
+            render_head(template, &PageHead::default()),
+            "<title>Web Summary</title>\n<p>summary</p>"
+        );
+
+        let template = "<title>Custom</title>\n[[ summary.html ]]";
+        assert_eq!(
+            render_head(template, &head),
+            "<title>Custom</title>\n<p>summary</p>"
+        );
+
+        // The data scripts follow the data
+        let head = PageHead {
+            data_scripts: vec!["load(data);".into()],
+            ..PageHead::default()
+        };
+        let template = "<script>\nconst data = [[ data.js ]]\n</script>\n[[ summary.html ]]";
+        assert_eq!(
+            render_head(template, &head),
+            format!(
+                "<script>\n{DATA_BEGIN_MARKER}\nconst data = {{}}\n{DATA_END_MARKER}\n;load(data);\n\
+                 </script>\n<p>summary</p>"
+            )
+        );
+    }
+
+    #[cfg(feature = "generate_html")]
     #[test]
     fn generate_html_example() {
-        let json_data = read_to_string("../../example/data.json").unwrap();
-        let contents = read_to_string("../../example/summary.html").unwrap();
-        let template_info = TemplateInfo::Dynamic("../../example");
-        // let mut out = File::create("test.html").unwrap();
-        let mut out: Vec<u8> = vec![];
-        assert!(generate_html_summary(&json_data, contents, template_info, &mut out).is_ok());
-        assert!(!out.is_empty());
+        let fixture = SummaryFixture::load(Path::new("../../example")).unwrap();
+        let html = fixture.render(WebSummaryBuildFiles::bundled()).unwrap();
+        assert!(!html.is_empty());
     }
 
+    #[cfg(feature = "generate_html")]
     #[test]
     fn generate_html_cellranger() {
-        let json_data = read_to_string("../../tests/cr_tests/data/count_small.json").unwrap();
-        let contents = read_to_string("../../tests/cr_tests/summary.html").unwrap();
-        let template_info = TemplateInfo::Dynamic("../../example");
-        let mut out: Vec<u8> = vec![];
-        assert!(generate_html_summary(&json_data, contents, template_info, &mut out).is_ok());
-        assert!(!out.is_empty());
+        // The data of the cellranger summary is not in the same directory,
+        // so the pieces are copied into a fixture directory
+        let dir = ScratchDir::with_files(
+            "cellranger",
+            &[
+                (
+                    DATA_JSON,
+                    read_to_string("../../tests/cr_tests/data/count_small.json").unwrap(),
+                ),
+                (
+                    SUMMARY_HTML,
+                    read_to_string("../../tests/cr_tests/summary.html").unwrap(),
+                ),
+            ],
+        );
+        let fixture = SummaryFixture::load(&dir).unwrap();
+        let html = fixture.render(WebSummaryBuildFiles::bundled()).unwrap();
+        assert!(!html.is_empty());
     }
 }
@@ -1,8 +1,10 @@
 #![cfg(feature = "image_base64_encode")]
 
-use crate::components::RawImage;
+use crate::components::{ImageSource, RawImage};
 use anyhow::{bail, Result};
-use std::path::Path;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
 #[derive(Clone, Copy)]
 pub enum Base64ImageEncoder {
@@ -23,17 +25,56 @@ impl Base64ImageEncoder {
         })
     }
 
+    /// Guess the format from the magic bytes at the start of the image
+    pub fn guess_from_bytes(file_bytes: &[u8]) -> Result<Self> {
+        Ok(if file_bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Base64ImageEncoder::Png
+        } else if file_bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Base64ImageEncoder::Jpeg
+        } else {
+            bail!("Unable to guess the image format from the contents")
+        })
+    }
+
+    pub fn mime(self) -> &'static str {
+        match self {
+            Base64ImageEncoder::Jpeg => "image/jpeg",
+            Base64ImageEncoder::Png => "image/png",
+        }
+    }
+
+    /// The start of the data uri, e.g. `data:image/png;base64,`
+    pub fn data_uri_prefix(self) -> String {
+        format!("data:{};base64,", self.mime())
+    }
+
+    /// Length of the data uri of an image with `num_bytes` bytes
+    pub fn encoded_len(self, num_bytes: u64) -> u64 {
+        self.data_uri_prefix().len() as u64 + num_bytes.div_ceil(3) * 4
+    }
+
+    /// The data uri of the image. The file is streamed into a string sized
+    /// from the length of the file, so that the contents of the file are
+    /// never held in memory along with the encoded string.
     pub fn encode(self, img_path: &Path) -> Result<String> {
-        Ok(self.encode_bytes(&std::fs::read(img_path)?))
+        let num_bytes = std::fs::metadata(img_path)?.len();
+        let mut buffer = Vec::with_capacity(usize::try_from(self.encoded_len(num_bytes))?);
+        self.encode_to_writer(img_path, &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+
+    /// Write the data uri of the image to the writer, reading the file in
+    /// chunks, e.g. to write a very large image straight into the output
+    pub fn encode_to_writer<W: Write>(self, img_path: &Path, mut writer: W) -> Result<()> {
+        writer.write_all(self.data_uri_prefix().as_bytes())?;
+        let mut encoder = base64::write::EncoderWriter::new(writer, base64::STANDARD);
+        std::io::copy(&mut File::open(img_path)?, &mut encoder)?;
+        encoder.finish()?.flush()?;
+        Ok(())
     }
 
     pub fn encode_bytes(self, file_bytes: &[u8]) -> String {
-        let base64_encoded = base64::encode(file_bytes);
-        let mime = match self {
-            Base64ImageEncoder::Jpeg => "jpeg",
-            Base64ImageEncoder::Png => "png",
-        };
-        format!("data:image/{mime};base64,{base64_encoded}")
+        format!("{}{}", self.data_uri_prefix(), base64::encode(file_bytes))
     }
 }
 
@@ -45,4 +86,113 @@ impl RawImage {
     pub fn encode(img_path: &Path) -> Result<Self> {
         RawImage::encode_with_format(img_path, Base64ImageEncoder::guess(img_path)?)
     }
+
+    /// The image is only read and encoded when the RawImage is serialized
+    pub fn encode_lazily(img_path: impl Into<PathBuf>) -> Self {
+        RawImage::from_source(ImageSource::Path(img_path.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::ScratchDir;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    fn tmp_png(dir: &Path) -> PathBuf {
+        let path = dir.join("image.png");
+        std::fs::write(&path, PNG).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_lazy_encoding_matches_eager() -> Result<()> {
+        let dir = ScratchDir::new("lazy_encoding");
+        let path = tmp_png(&dir);
+        let eager = serde_json::to_value(RawImage::encode(&path)?)?;
+        let lazy = RawImage::encode_lazily(&path);
+        let from_bytes = RawImage::from_source(ImageSource::Bytes(PNG.to_vec()));
+        assert_eq!(serde_json::to_value(&lazy)?, eager);
+        assert_eq!(serde_json::to_value(from_bytes)?, eager);
+
+        let mut materialized = lazy;
+        materialized.materialize()?;
+        std::fs::remove_file(&path)?;
+        // Nothing is read from the file anymore
+        assert_eq!(serde_json::to_value(materialized)?, eager);
+        Ok(())
+    }
+
+    #[test]
+    fn test_blended_image_from_sources() -> Result<()> {
+        use crate::components::{BlendedImage, BlendedImageSliderSize};
+        let size = || BlendedImageSliderSize { width: 300.into() };
+        let dir = ScratchDir::new("blended_from_sources");
+        let path = tmp_png(&dir);
+        let blended = BlendedImage::from_sources(
+            ImageSource::Path(path.clone()),
+            ImageSource::Bytes(PNG.to_vec()),
+            size(),
+        )?;
+        std::fs::remove_file(&path)?;
+        assert!(blended.image1.starts_with("data:image/png;base64,"));
+        assert_eq!(blended.image1, blended.image2);
+        assert!(BlendedImage::from_sources(ImageSource::Path(path), "".into(), size()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_streaming_encoding_matches_one_shot() -> Result<()> {
+        // Not a multiple of 3 or of the chunk sizes, so that every chunk
+        // boundary and the padding are exercised
+        let mut bytes = PNG.to_vec();
+        bytes.extend((0..5_000_003u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8));
+        let dir = ScratchDir::new("streaming_encoding");
+        let path = dir.join("image.png");
+        std::fs::write(&path, &bytes)?;
+
+        let one_shot = Base64ImageEncoder::Png.encode_bytes(&bytes);
+        let mut streamed = Vec::new();
+        Base64ImageEncoder::Png.encode_to_writer(&path, &mut streamed)?;
+        let presized = Base64ImageEncoder::Png.encode(&path)?;
+
+        assert!(one_shot.starts_with("data:image/png;base64,iVBORw0KGgo"));
+        assert!(streamed == one_shot.as_bytes());
+        assert!(presized == one_shot);
+        // The buffer was sized exactly, so it was never reallocated
+        assert_eq!(presized.capacity(), presized.len());
+        assert_eq!(
+            Base64ImageEncoder::Png.encoded_len(bytes.len() as u64),
+            one_shot.len() as u64
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_data_uri_prefix() -> Result<()> {
+        let dir = ScratchDir::new("data_uri_prefix");
+        let path = dir.join("image.jpg");
+        std::fs::write(&path, [0xFF, 0xD8, 0xFF, 0xE0])?;
+        let encoder = Base64ImageEncoder::guess(&path)?;
+        assert_eq!(encoder.mime(), "image/jpeg");
+        let mut streamed = Vec::new();
+        encoder.encode_to_writer(&path, &mut streamed)?;
+        assert_eq!(
+            String::from_utf8(streamed)?,
+            "data:image/jpeg;base64,/9j/4A=="
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_lazy_encoding_missing_file() {
+        let dir = ScratchDir::new("missing_file");
+        let path = dir.join("does_not_exist.png");
+        let err = serde_json::to_string(&RawImage::encode_lazily(&path))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(&path.display().to_string()), "{err}");
+        assert!(RawImage::encode_lazily(&path).materialize().is_err());
+    }
 }
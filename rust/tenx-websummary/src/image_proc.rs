@@ -1,10 +1,15 @@
 #![cfg(feature = "image_proc")]
 
 use crate::components::RawImage;
-use anyhow::Result;
+#[cfg(feature = "image_base64_encode")]
+use crate::components::{
+    BlendedImage, BlendedImageSliderSize, BlendedImageZoomable, ImageVariant, NumOrStr, ZoomViewer,
+    ZoomViewerSize,
+};
+use anyhow::{bail, Result};
 use image::imageops::FilterType;
 use image::io::Reader as ImageReader;
-use image::DynamicImage;
+use image::{DynamicImage, GrayImage, Luma, Rgb, RgbImage, Rgba, RgbaImage};
 use std::path::Path;
 
 pub enum ImageResize {
@@ -30,33 +35,33 @@ impl ImageResize {
     }
 
     pub fn resize_dynamic_image(self, img: DynamicImage, filter_type: FilterType) -> DynamicImage {
+        match self.resized_dims(img.width(), img.height()) {
+            Some((new_width, new_height)) => img.resize(new_width, new_height, filter_type),
+            None => img,
+        }
+    }
+
+    /// The (width, height) of the resized image, or None if the image is
+    /// left as is
+    fn resized_dims(&self, width: u32, height: u32) -> Option<(u32, u32)> {
         fn scaled_dim(dim_a: u32, new_dim_b: u32, dim_b: u32) -> u32 {
             ((dim_a as f64) * (new_dim_b as f64) / (dim_b as f64)).round() as u32
         }
 
-        let height = img.height();
-        let width = img.width();
-        let (new_width, new_height) = match self {
+        match *self {
             ImageResize::ClampHeight(new_height) => {
-                if height <= new_height {
-                    return img;
-                } else {
-                    (scaled_dim(width, new_height, height), new_height)
-                }
+                (height > new_height).then(|| (scaled_dim(width, new_height, height), new_height))
             }
             ImageResize::ClampWidth(new_width) => {
-                if width <= new_width {
-                    return img;
-                } else {
-                    (new_width, scaled_dim(height, new_width, width))
-                }
+                (width > new_width).then(|| (new_width, scaled_dim(height, new_width, width)))
             }
             ImageResize::ExactHeight(new_height) => {
-                (scaled_dim(width, new_height, height), new_height)
+                Some((scaled_dim(width, new_height, height), new_height))
             }
-            ImageResize::ExactWidth(new_width) => (new_width, scaled_dim(height, new_width, width)),
-        };
-        img.resize(new_width, new_height, filter_type)
+            ImageResize::ExactWidth(new_width) => {
+                Some((new_width, scaled_dim(height, new_width, width)))
+            }
+        }
     }
 
     #[cfg(feature = "image_base64_encode")]
@@ -65,14 +70,289 @@ impl ImageResize {
         img_path: &Path,
         filter_type: FilterType,
     ) -> Result<String> {
-        use crate::image_base64_encode::Base64ImageEncoder;
-        use std::io::Cursor;
+        encode_png(&self.resize_image(img_path, filter_type)?)
+    }
+
+    /// Encode a thumbnail and the full size image, decoding the image only
+    /// once. Returns the (thumbnail, full size) data URIs along with the
+    /// (width, height) of the full size image.
+    #[cfg(feature = "image_base64_encode")]
+    pub fn thumbnail_pair(
+        img_path: &Path,
+        thumb_spec: ImageResize,
+        filter_type: FilterType,
+    ) -> Result<(String, String, (u32, u32))> {
+        ImageResize::thumbnail_pair_clamped(img_path, thumb_spec, None, filter_type)
+    }
+
+    /// Same as `thumbnail_pair`, with the full size image resized using
+    /// `full_spec`, typically to clamp very large images
+    #[cfg(feature = "image_base64_encode")]
+    pub fn thumbnail_pair_clamped(
+        img_path: &Path,
+        thumb_spec: ImageResize,
+        full_spec: Option<ImageResize>,
+        filter_type: FilterType,
+    ) -> Result<(String, String, (u32, u32))> {
+        let img = ImageReader::open(img_path)?.decode()?;
+        // The thumbnail is dropped once encoded, before the full size image
+        // is resized
+        let thumbnail = match thumb_spec.resized_dims(img.width(), img.height()) {
+            Some((width, height)) => encode_png(&img.resize(width, height, filter_type))?,
+            None => encode_png(&img)?,
+        };
+        let img = match full_spec {
+            Some(full_spec) => full_spec.resize_dynamic_image(img, filter_type),
+            None => img,
+        };
+        let dims = (img.width(), img.height());
+        Ok((thumbnail, encode_png(&img)?, dims))
+    }
+}
+
+#[cfg(feature = "image_base64_encode")]
+fn encode_png(img: &DynamicImage) -> Result<String> {
+    use crate::image_base64_encode::Base64ImageEncoder;
+    use std::io::Cursor;
 
-        let img = self.resize_image(img_path, filter_type)?;
-        let mut buf = Cursor::new(Vec::with_capacity(img.as_bytes().len()));
-        img.write_to(&mut buf, image::ImageFormat::Png)?;
-        Ok(Base64ImageEncoder::Png.encode_bytes(buf.get_ref()))
+    let mut buf = Cursor::new(Vec::with_capacity(img.as_bytes().len()));
+    img.write_to(&mut buf, image::ImageFormat::Png)?;
+    Ok(Base64ImageEncoder::Png.encode_bytes(buf.get_ref()))
+}
+
+/// Linearly stretch the intensities of a grayscale image, such that the
+/// intensities at the low and high percentiles map to black and white. Useful
+/// for fluorescence images, which look black as is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContrastStretch {
+    /// Percentile in [0, 100] which maps to black
+    pub low_percentile: f64,
+    /// Percentile in [0, 100] which maps to white
+    pub high_percentile: f64,
+}
+
+impl Default for ContrastStretch {
+    fn default() -> Self {
+        ContrastStretch {
+            low_percentile: 1.0,
+            high_percentile: 99.0,
+        }
+    }
+}
+
+impl ContrastStretch {
+    pub fn new(low_percentile: f64, high_percentile: f64) -> Self {
+        assert!(
+            (0.0..=high_percentile).contains(&low_percentile) && high_percentile <= 100.0,
+            "Invalid percentiles {low_percentile} and {high_percentile}"
+        );
+        ContrastStretch {
+            low_percentile,
+            high_percentile,
+        }
     }
+
+    /// Stretch the luminance of the image, with 16 bit precision, into an 8 bit
+    /// grayscale image
+    pub fn apply(&self, img: &DynamicImage) -> GrayImage {
+        let img = img.to_luma16();
+        let mut histogram = vec![0u64; 1 << 16];
+        for Luma([v]) in img.pixels() {
+            histogram[*v as usize] += 1;
+        }
+        let num_pixels = img.pixels().len() as f64;
+        let percentile_value = |percentile: f64| {
+            let rank = (percentile / 100.0 * num_pixels).ceil().max(1.0) as u64;
+            let mut cumulative = 0;
+            histogram
+                .iter()
+                .position(|&count| {
+                    cumulative += count;
+                    cumulative >= rank
+                })
+                .unwrap_or(u16::MAX as usize) as f64
+        };
+        let low = percentile_value(self.low_percentile);
+        let high = percentile_value(self.high_percentile);
+        GrayImage::from_fn(img.width(), img.height(), |x, y| {
+            let v = img.get_pixel(x, y)[0] as f64;
+            let stretched = if high > low {
+                ((v - low) / (high - low) * 255.0).round()
+            } else if v > low {
+                255.0
+            } else {
+                0.0
+            };
+            Luma([stretched.clamp(0.0, 255.0) as u8])
+        })
+    }
+}
+
+/// Colormaps for pseudo coloring grayscale images
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Colormap {
+    Gray,
+    Viridis,
+    Magma,
+}
+
+/// Evenly spaced colors of the matplotlib colormaps, which are interpolated
+/// into the lookup table
+const VIRIDIS: [[u8; 3]; 9] = [
+    [0x44, 0x01, 0x54],
+    [0x47, 0x2d, 0x7b],
+    [0x3b, 0x52, 0x8b],
+    [0x2c, 0x72, 0x8e],
+    [0x21, 0x91, 0x8c],
+    [0x28, 0xae, 0x80],
+    [0x5e, 0xc9, 0x62],
+    [0xad, 0xdc, 0x30],
+    [0xfd, 0xe7, 0x25],
+];
+
+const MAGMA: [[u8; 3]; 9] = [
+    [0x00, 0x00, 0x04],
+    [0x1c, 0x10, 0x44],
+    [0x4f, 0x12, 0x7b],
+    [0x81, 0x25, 0x81],
+    [0xb5, 0x36, 0x7a],
+    [0xe5, 0x59, 0x64],
+    [0xfb, 0x87, 0x61],
+    [0xfe, 0xc2, 0x87],
+    [0xfc, 0xfd, 0xbf],
+];
+
+impl Colormap {
+    /// Color for each of the 256 intensities
+    pub fn lookup_table(self) -> Vec<[u8; 3]> {
+        let anchors: &[[u8; 3]] = match self {
+            Colormap::Gray => &[[0, 0, 0], [255, 255, 255]],
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Magma => &MAGMA,
+        };
+        let num_segments = (anchors.len() - 1) as f64;
+        (0..=255u8)
+            .map(|i| {
+                let pos = i as f64 / 255.0 * num_segments;
+                let segment = (pos.floor() as usize).min(anchors.len() - 2);
+                let t = pos - segment as f64;
+                let (start, end) = (anchors[segment], anchors[segment + 1]);
+                [0, 1, 2].map(|c| {
+                    (start[c] as f64 + t * (end[c] as f64 - start[c] as f64)).round() as u8
+                })
+            })
+            .collect()
+    }
+}
+
+/// Color the grayscale image using the colormap
+pub fn pseudo_color(img: &GrayImage, colormap: Colormap) -> RgbImage {
+    let lut = colormap.lookup_table();
+    RgbImage::from_fn(img.width(), img.height(), |x, y| {
+        Rgb(lut[img.get_pixel(x, y)[0] as usize])
+    })
+}
+
+/// How `apply_mask()` combines an image with a binary mask. A pixel of the
+/// mask is inside if its luminance is at least 128.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MaskMode {
+    /// The mask becomes the alpha channel, so the pixels outside the mask are
+    /// transparent
+    Alpha,
+    /// The pixels outside the mask are multiplied by the factor in [0, 1]
+    Darken(f64),
+    /// Draw the boundary of the mask in the (r, g, b) color, with the
+    /// thickness in pixels inwards from the boundary
+    Outline([u8; 3], u32),
+}
+
+/// Pixels of the mask which are inside
+fn mask_inside(mask: &DynamicImage) -> Vec<Vec<bool>> {
+    let mask = mask.to_luma8();
+    (0..mask.height())
+        .map(|y| {
+            (0..mask.width())
+                .map(|x| mask.get_pixel(x, y)[0] >= 128)
+                .collect()
+        })
+        .collect()
+}
+
+/// The pixels inside the mask within `thickness` pixels of the outside, using
+/// 4-connectivity. The edges of the image are not a boundary.
+fn mask_boundary(inside: &[Vec<bool>], thickness: u32) -> Vec<Vec<bool>> {
+    let mut remaining = inside.to_vec();
+    let mut boundary: Vec<Vec<bool>> = inside.iter().map(|row| vec![false; row.len()]).collect();
+    for _ in 0..thickness {
+        let is_outside = |x: usize, y: usize| !remaining[y][x];
+        let mut layer = Vec::new();
+        for (y, row) in remaining.iter().enumerate() {
+            for (x, &pixel) in row.iter().enumerate() {
+                let touches_outside = (x > 0 && is_outside(x - 1, y))
+                    || (x + 1 < row.len() && is_outside(x + 1, y))
+                    || (y > 0 && is_outside(x, y - 1))
+                    || (y + 1 < remaining.len() && is_outside(x, y + 1));
+                if pixel && touches_outside {
+                    layer.push((x, y));
+                }
+            }
+        }
+        if layer.is_empty() {
+            break;
+        }
+        for (x, y) in layer {
+            remaining[y][x] = false;
+            boundary[y][x] = true;
+        }
+    }
+    boundary
+}
+
+/// Combine the image with the binary mask, which must have the same size, e.g.
+/// a tissue image with its tissue detection mask.
+pub fn apply_mask(img: &DynamicImage, mask: &DynamicImage, mode: MaskMode) -> Result<RgbaImage> {
+    if (img.width(), img.height()) != (mask.width(), mask.height()) {
+        bail!(
+            "The mask is {}x{} pixels, but the image is {}x{} pixels",
+            mask.width(),
+            mask.height(),
+            img.width(),
+            img.height()
+        );
+    }
+    let inside = mask_inside(mask);
+    let mut out = img.to_rgba8();
+    match mode {
+        MaskMode::Alpha => {
+            for (x, y, pixel) in out.enumerate_pixels_mut() {
+                if !inside[y as usize][x as usize] {
+                    pixel[3] = 0;
+                }
+            }
+        }
+        MaskMode::Darken(factor) => {
+            if !(0.0..=1.0).contains(&factor) {
+                bail!("The darken factor of a mask should be between 0 and 1, found {factor}");
+            }
+            for (x, y, pixel) in out.enumerate_pixels_mut() {
+                if !inside[y as usize][x as usize] {
+                    for c in 0..3 {
+                        pixel[c] = (pixel[c] as f64 * factor).round() as u8;
+                    }
+                }
+            }
+        }
+        MaskMode::Outline([r, g, b], thickness) => {
+            let boundary = mask_boundary(&inside, thickness);
+            for (x, y, pixel) in out.enumerate_pixels_mut() {
+                if boundary[y as usize][x as usize] {
+                    *pixel = Rgba([r, g, b, 255]);
+                }
+            }
+        }
+    }
+    Ok(out)
 }
 
 impl RawImage {
@@ -86,4 +366,571 @@ impl RawImage {
             resize.resize_and_encode_image(img_path, filter_type)?,
         ))
     }
+
+    /// Encode the image at `base_width` along with a variant at each of the
+    /// `scales` of the base width, e.g. `&[2.0]` for high density displays,
+    /// decoding the image only once. The base image is not upscaled, and
+    /// neither are the variants: scales wider than the image are skipped.
+    #[cfg(feature = "image_base64_encode")]
+    pub fn with_variants(
+        img_path: &Path,
+        base_width: u32,
+        scales: &[f32],
+        filter_type: FilterType,
+    ) -> Result<Self> {
+        let img = ImageReader::open(img_path)?.decode()?;
+        let base =
+            ImageResize::ClampWidth(base_width).resize_dynamic_image(img.clone(), filter_type);
+        let variants = scales
+            .iter()
+            .filter(|&&scale| scale.is_finite() && scale > 0.0 && scale != 1.0)
+            .filter_map(|&scale| {
+                let width = (base.width() as f64 * scale as f64).round() as u32;
+                (width <= img.width() && width > 0).then_some((scale, width))
+            })
+            .map(|(scale, width)| {
+                let variant =
+                    ImageResize::ExactWidth(width).resize_dynamic_image(img.clone(), filter_type);
+                Ok(ImageVariant::new(scale, encode_png(&variant)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(RawImage::new(encode_png(&base)?).variants(variants))
+    }
+
+    /// Apply the mask to the image, see `apply_mask()`, and encode it as a
+    /// PNG. The image is optionally resized after masking.
+    #[cfg(feature = "image_base64_encode")]
+    pub fn masked_from_paths(
+        img_path: &Path,
+        mask_path: &Path,
+        mode: MaskMode,
+        resize: Option<ImageResize>,
+    ) -> Result<Self> {
+        let img = ImageReader::open(img_path)?.decode()?;
+        let mask = ImageReader::open(mask_path)?.decode()?;
+        let masked = DynamicImage::ImageRgba8(apply_mask(&img, &mask, mode)?);
+        let img = match resize {
+            Some(resize) => resize.resize_dynamic_image(masked, FilterType::Triangle),
+            None => masked,
+        };
+        Ok(RawImage::new(encode_png(&img)?))
+    }
+
+    /// Contrast stretch and pseudo color a fluorescence image, e.g. a 16 bit
+    /// TIFF, and encode it as a PNG. The image is optionally resized after
+    /// coloring.
+    #[cfg(feature = "image_base64_encode")]
+    pub fn from_fluorescence(
+        img_path: &Path,
+        stretch: ContrastStretch,
+        colormap: Colormap,
+        resize: Option<ImageResize>,
+    ) -> Result<Self> {
+        let img = ImageReader::open(img_path)?.decode()?;
+        let colored = DynamicImage::ImageRgb8(pseudo_color(&stretch.apply(&img), colormap));
+        let img = match resize {
+            Some(resize) => resize.resize_dynamic_image(colored, FilterType::Triangle),
+            None => colored,
+        };
+        Ok(RawImage::new(encode_png(&img)?))
+    }
+}
+
+#[cfg(feature = "image_base64_encode")]
+impl ZoomViewer {
+    /// Viewer for the image, with a thumbnail whose height is at most
+    /// `thumb_height`. The sizes are the dimensions of the full size image.
+    pub fn from_image_path(
+        img_path: &Path,
+        thumb_height: u32,
+        plot_title: Option<String>,
+    ) -> Result<Self> {
+        let (small_image, big_image, (width, height)) = ImageResize::thumbnail_pair(
+            img_path,
+            ImageResize::ClampHeight(thumb_height),
+            FilterType::Triangle,
+        )?;
+        Ok(ZoomViewer {
+            small_image,
+            big_image,
+            sizes: ZoomViewerSize {
+                width: NumOrStr::Num(width as usize),
+                height: NumOrStr::Num(height as usize),
+            },
+            plot_title,
+        })
+    }
+}
+
+/// Affine transform as the CSS `matrix(a, b, c, d, e, f)`, which maps
+/// `(x, y)` to `(a x + c y + e, b x + d y + f)`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AffineTransform(pub [f64; 6]);
+
+impl From<[f64; 6]> for AffineTransform {
+    fn from(matrix: [f64; 6]) -> Self {
+        AffineTransform(matrix)
+    }
+}
+
+impl AffineTransform {
+    /// The same transform between the images after the source is scaled by
+    /// `src_scale` and the destination by `dst_scale`, along (x, y)
+    pub fn rescaled(self, src_scale: (f64, f64), dst_scale: (f64, f64)) -> Self {
+        let [a, b, c, d, e, f] = self.0;
+        let (sx, sy) = src_scale;
+        let (dx, dy) = dst_scale;
+        AffineTransform([
+            dx * a / sx,
+            dy * b / sx,
+            dx * c / sy,
+            dy * d / sy,
+            dx * e,
+            dy * f,
+        ])
+    }
+}
+
+/// How the registered images are shown by
+/// `BlendedImageZoomable::from_registration()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegistrationDisplay {
+    /// Width of the viewer in pixels
+    pub display_width: u32,
+    /// Images whose width or height is larger than this are downsampled
+    pub max_image_dim: Option<u32>,
+    /// Zooming stops when an image pixel covers this many screen pixels
+    pub max_pixel_zoom: f64,
+}
+
+impl Default for RegistrationDisplay {
+    fn default() -> Self {
+        RegistrationDisplay {
+            display_width: 600,
+            max_image_dim: Some(2000),
+            max_pixel_zoom: 4.0,
+        }
+    }
+}
+
+impl RegistrationDisplay {
+    pub fn display_width(mut self, display_width: u32) -> Self {
+        self.display_width = display_width;
+        self
+    }
+    pub fn max_image_dim(mut self, max_image_dim: Option<u32>) -> Self {
+        self.max_image_dim = max_image_dim;
+        self
+    }
+    pub fn max_pixel_zoom(mut self, max_pixel_zoom: f64) -> Self {
+        self.max_pixel_zoom = max_pixel_zoom;
+        self
+    }
+
+    /// The image within the size budget along with the scale along (x, y)
+    #[cfg(feature = "image_base64_encode")]
+    fn downsample(&self, img: DynamicImage) -> (DynamicImage, (f64, f64)) {
+        let (width, height) = (img.width(), img.height());
+        let img = match self.max_image_dim {
+            Some(max_dim) if width >= height => {
+                ImageResize::ClampWidth(max_dim).resize_dynamic_image(img, FilterType::Triangle)
+            }
+            Some(max_dim) => {
+                ImageResize::ClampHeight(max_dim).resize_dynamic_image(img, FilterType::Triangle)
+            }
+            None => img,
+        };
+        let scale = (
+            img.width() as f64 / width as f64,
+            img.height() as f64 / height as f64,
+        );
+        (img, scale)
+    }
+}
+
+#[cfg(feature = "image_base64_encode")]
+impl BlendedImageZoomable {
+    /// Viewer for two registered images. The `transform` maps the pixel
+    /// coordinates of image B onto those of image A, so image A is shown as is
+    /// and image B is transformed. The images are downsampled to the budget in
+    /// the options, adjusting the transform to match. Zooming out stops at the
+    /// full image and zooming in at `max_pixel_zoom` screen pixels per pixel
+    /// of image A.
+    pub fn from_registration(
+        path_a: &Path,
+        title_a: impl Into<String>,
+        path_b: &Path,
+        title_b: impl Into<String>,
+        transform: impl Into<AffineTransform>,
+        display: RegistrationDisplay,
+    ) -> Result<Self> {
+        let (img_a, scale_a) = display.downsample(ImageReader::open(path_a)?.decode()?);
+        let (img_b, scale_b) = display.downsample(ImageReader::open(path_b)?.decode()?);
+        let transform = transform.into().rescaled(scale_b, scale_a);
+
+        let mut blended = BlendedImage::new(
+            encode_png(&img_a)?,
+            encode_png(&img_b)?,
+            BlendedImageSliderSize {
+                width: NumOrStr::Num(display.display_width as usize),
+            },
+        );
+        blended.image1_title = Some(title_a.into());
+        blended.image2_title = Some(title_b.into());
+
+        let display_width = display.display_width as f64;
+        let max_scale = (img_a.width() as f64 * display.max_pixel_zoom / display_width).max(1.0);
+        let display_height =
+            (display_width * img_a.height() as f64 / img_a.width() as f64).round() as u32;
+        Ok(BlendedImageZoomable::new(blended, 1.0, max_scale)
+            .zoom_pan_width(display.display_width)
+            .zoom_pan_height(display_height)
+            .image2_transform(transform.0.to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::ImageBuffer;
+
+    use crate::test_utils::ScratchDir;
+
+    /// Horizontal 16 bit gradient from 0 to 25500
+    fn gradient() -> DynamicImage {
+        DynamicImage::ImageLuma16(ImageBuffer::from_fn(256, 4, |x, _| Luma([x as u16 * 100])))
+    }
+
+    #[test]
+    fn test_contrast_stretch() {
+        let full = ContrastStretch::new(0.0, 100.0).apply(&gradient());
+        assert_eq!(full.get_pixel(0, 0)[0], 0);
+        assert_eq!(full.get_pixel(255, 0)[0], 255);
+        assert_eq!(full.get_pixel(128, 0)[0], 128);
+
+        // The tails are clamped
+        let stretched = ContrastStretch::new(10.0, 90.0).apply(&gradient());
+        let values: Vec<u8> = stretched.pixels().map(|p| p[0]).collect();
+        assert_eq!(values.iter().min(), Some(&0));
+        assert_eq!(values.iter().max(), Some(&255));
+        assert_eq!(values.iter().filter(|&&v| v == 0).count(), 4 * 26);
+        assert_eq!(values.iter().filter(|&&v| v == 255).count(), 4 * 26);
+
+        // A flat image does not divide by zero
+        let flat = DynamicImage::ImageLuma16(ImageBuffer::from_pixel(4, 4, Luma([1000u16])));
+        assert!(ContrastStretch::default()
+            .apply(&flat)
+            .pixels()
+            .all(|p| p[0] == 0));
+    }
+
+    #[test]
+    fn test_pseudo_color() {
+        let img = GrayImage::from_fn(256, 1, |x, _| Luma([x as u8]));
+        let pixel = |colormap, x| pseudo_color(&img, colormap).get_pixel(x, 0).0;
+        assert_eq!(pixel(Colormap::Gray, 0), [0, 0, 0]);
+        assert_eq!(pixel(Colormap::Gray, 255), [255, 255, 255]);
+        assert_eq!(pixel(Colormap::Gray, 100), [100, 100, 100]);
+        assert_eq!(pixel(Colormap::Viridis, 0), [68, 1, 84]);
+        assert_eq!(pixel(Colormap::Viridis, 255), [253, 231, 37]);
+        assert_eq!(pixel(Colormap::Magma, 0), [0, 0, 4]);
+        assert_eq!(pixel(Colormap::Magma, 255), [252, 253, 191]);
+        for colormap in [Colormap::Gray, Colormap::Viridis, Colormap::Magma] {
+            assert_eq!(colormap.lookup_table().len(), 256);
+        }
+    }
+
+    #[cfg(feature = "image_base64_encode")]
+    #[test]
+    fn test_from_fluorescence_tiff() -> Result<()> {
+        let dir = ScratchDir::new("fluorescence");
+        let path = dir.join("fluorescence.tiff");
+        gradient().save_with_format(&path, image::ImageFormat::Tiff)?;
+        let raw_image = RawImage::from_fluorescence(
+            &path,
+            ContrastStretch::new(0.0, 100.0),
+            Colormap::Viridis,
+            Some(ImageResize::ClampWidth(512)),
+        )?;
+
+        let json = serde_json::to_value(&raw_image)?;
+        let encoded = json["encoded_image"].as_str().unwrap();
+        let png = base64::decode(encoded.strip_prefix("data:image/png;base64,").unwrap())?;
+        let decoded = image::load_from_memory(&png)?.to_rgb8();
+        assert_eq!(decoded.dimensions(), (256, 4));
+        assert_eq!(decoded.get_pixel(0, 0).0, [68, 1, 84]);
+        assert_eq!(decoded.get_pixel(255, 0).0, [253, 231, 37]);
+        Ok(())
+    }
+
+    #[cfg(feature = "image_base64_encode")]
+    fn png_dims(data_uri: &str) -> (u32, u32) {
+        use image::GenericImageView;
+        let png = base64::decode(data_uri.strip_prefix("data:image/png;base64,").unwrap()).unwrap();
+        image::load_from_memory(&png).unwrap().dimensions()
+    }
+
+    #[cfg(feature = "image_base64_encode")]
+    #[test]
+    fn test_thumbnail_pair() -> Result<()> {
+        let dir = ScratchDir::new("thumbnail_pair");
+        let path = dir.join("tissue.png");
+        RgbImage::from_fn(400, 200, |x, y| Rgb([x as u8, y as u8, 0])).save(&path)?;
+
+        let (thumbnail, full, dims) =
+            ImageResize::thumbnail_pair(&path, ImageResize::ClampHeight(50), FilterType::Triangle)?;
+        assert_eq!(png_dims(&thumbnail), (100, 50));
+        assert_eq!(png_dims(&full), (400, 200));
+        assert_eq!(dims, (400, 200));
+        assert_ne!(thumbnail, full);
+
+        let (thumbnail, full, dims) = ImageResize::thumbnail_pair_clamped(
+            &path,
+            ImageResize::ClampWidth(40),
+            Some(ImageResize::ClampWidth(300)),
+            FilterType::Triangle,
+        )?;
+        assert_eq!(png_dims(&thumbnail), (40, 20));
+        assert_eq!(png_dims(&full), (300, 150));
+        assert_eq!(dims, (300, 150));
+
+        let viewer = ZoomViewer::from_image_path(&path, 100, Some("Tissue".into()))?;
+        assert_eq!(png_dims(&viewer.small_image), (200, 100));
+        assert_eq!(png_dims(&viewer.big_image), (400, 200));
+        assert_ne!(viewer.small_image, viewer.big_image);
+        let json = serde_json::to_value(&viewer)?;
+        assert_eq!(
+            json["sizes"],
+            serde_json::json!({"width": 400, "height": 200})
+        );
+        assert_eq!(json["plot_title"], "Tissue");
+        Ok(())
+    }
+
+    #[cfg(feature = "image_base64_encode")]
+    #[test]
+    fn test_with_variants() -> Result<()> {
+        let dir = ScratchDir::new("variants");
+        let path = dir.join("tissue.png");
+        RgbImage::from_fn(400, 200, |x, y| Rgb([x as u8, y as u8, 0])).save(&path)?;
+        let image = RawImage::with_variants(
+            &path,
+            100,
+            &[3.0, 1.5, 2.0, 1.5, 1.0, 8.0],
+            FilterType::Triangle,
+        )?;
+
+        let json = serde_json::to_value(&image)?;
+        assert_eq!(png_dims(json["encoded_image"].as_str().unwrap()), (100, 50));
+        let variants: Vec<_> = json["variants"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|variant| {
+                (
+                    variant["scale"].as_f64().unwrap(),
+                    png_dims(variant["encoded_image"].as_str().unwrap()),
+                )
+            })
+            .collect();
+        // Sorted and deduplicated, without the base scale or a scale wider
+        // than the image
+        assert_eq!(
+            variants,
+            [(1.5, (150, 75)), (2.0, (200, 100)), (3.0, (300, 150))]
+        );
+        Ok(())
+    }
+
+    /// A 6x6 gray image with a 4x4 mask square from (1, 1) to (4, 4)
+    fn masked_square() -> (DynamicImage, DynamicImage) {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_pixel(6, 6, Rgb([100, 150, 201])));
+        let mask = DynamicImage::ImageLuma8(GrayImage::from_fn(6, 6, |x, y| {
+            let inside = (1..5).contains(&x) && (1..5).contains(&y);
+            Luma([if inside { 255 } else { 0 }])
+        }));
+        (img, mask)
+    }
+
+    #[test]
+    fn test_apply_mask_alpha() {
+        let (img, mask) = masked_square();
+        let masked = apply_mask(&img, &mask, MaskMode::Alpha).unwrap();
+        assert_eq!(masked.get_pixel(0, 0).0, [100, 150, 201, 0]);
+        assert_eq!(masked.get_pixel(1, 1).0, [100, 150, 201, 255]);
+        assert_eq!(masked.get_pixel(4, 4).0, [100, 150, 201, 255]);
+        assert_eq!(masked.get_pixel(5, 2).0, [100, 150, 201, 0]);
+        assert_eq!(masked.pixels().filter(|p| p[3] == 255).count(), 16);
+    }
+
+    #[test]
+    fn test_apply_mask_darken() {
+        let (img, mask) = masked_square();
+        let masked = apply_mask(&img, &mask, MaskMode::Darken(0.25)).unwrap();
+        // 201 * 0.25 = 50.25
+        assert_eq!(masked.get_pixel(0, 0).0, [25, 38, 50, 255]);
+        assert_eq!(masked.get_pixel(2, 3).0, [100, 150, 201, 255]);
+        assert_eq!(
+            apply_mask(&img, &mask, MaskMode::Darken(0.0))
+                .unwrap()
+                .get_pixel(5, 5)
+                .0,
+            [0, 0, 0, 255]
+        );
+        assert_eq!(
+            apply_mask(&img, &mask, MaskMode::Darken(1.5))
+                .unwrap_err()
+                .to_string(),
+            "The darken factor of a mask should be between 0 and 1, found 1.5"
+        );
+    }
+
+    #[test]
+    fn test_apply_mask_outline() {
+        let (img, mask) = masked_square();
+        let red = [255, 0, 0];
+        let outlined = |thickness| {
+            let masked = apply_mask(&img, &mask, MaskMode::Outline(red, thickness)).unwrap();
+            let mut rows = Vec::new();
+            for y in 0..6 {
+                let row: String = (0..6)
+                    .map(|x| {
+                        if masked.get_pixel(x, y).0 == [255, 0, 0, 255] {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect();
+                rows.push(row);
+            }
+            rows
+        };
+        assert_eq!(
+            outlined(1),
+            ["......", ".####.", ".#..#.", ".#..#.", ".####.", "......"]
+        );
+        assert_eq!(
+            outlined(2),
+            ["......", ".####.", ".####.", ".####.", ".####.", "......"]
+        );
+        assert!(outlined(0).iter().all(|row| row == "......"));
+
+        // A mask covering the whole image has no boundary
+        let full = DynamicImage::ImageLuma8(GrayImage::from_pixel(6, 6, Luma([255])));
+        let masked = apply_mask(&img, &full, MaskMode::Outline(red, 1)).unwrap();
+        assert_eq!(masked, img.to_rgba8());
+    }
+
+    #[test]
+    fn test_apply_mask_size_mismatch() {
+        let (img, _) = masked_square();
+        let mask = DynamicImage::ImageLuma8(GrayImage::new(4, 3));
+        assert_eq!(
+            apply_mask(&img, &mask, MaskMode::Alpha)
+                .unwrap_err()
+                .to_string(),
+            "The mask is 4x3 pixels, but the image is 6x6 pixels"
+        );
+    }
+
+    #[cfg(feature = "image_base64_encode")]
+    #[test]
+    fn test_masked_from_paths() -> Result<()> {
+        let dir = ScratchDir::new("masked_from_paths");
+        let (img_path, mask_path) = (dir.join("tissue.png"), dir.join("mask.png"));
+        let (img, mask) = masked_square();
+        img.save(&img_path)?;
+        mask.save(&mask_path)?;
+        let raw_image = RawImage::masked_from_paths(
+            &img_path,
+            &mask_path,
+            MaskMode::Alpha,
+            Some(ImageResize::ExactWidth(12)),
+        )?;
+
+        let json = serde_json::to_value(&raw_image)?;
+        assert_eq!(png_dims(json["encoded_image"].as_str().unwrap()), (12, 12));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rescaled_transform() {
+        let transform = AffineTransform([2.0, 0.5, -0.5, 2.0, 10.0, 20.0]);
+        assert_eq!(transform.rescaled((1.0, 1.0), (1.0, 1.0)), transform);
+        // Halving the source doubles the linear part, halving the destination
+        // halves the whole transform
+        assert_eq!(
+            transform.rescaled((0.5, 0.5), (1.0, 1.0)),
+            AffineTransform([4.0, 1.0, -1.0, 4.0, 10.0, 20.0])
+        );
+        assert_eq!(
+            transform.rescaled((1.0, 1.0), (0.5, 0.25)),
+            AffineTransform([1.0, 0.125, -0.25, 0.5, 5.0, 5.0])
+        );
+    }
+
+    #[cfg(feature = "image_base64_encode")]
+    #[test]
+    fn test_from_registration() -> Result<()> {
+        use image::GenericImageView;
+
+        let dir = ScratchDir::new("registration");
+        let (path_a, path_b) = (dir.join("a.png"), dir.join("b.png"));
+        RgbImage::from_pixel(400, 200, Rgb([255, 0, 0])).save(&path_a)?;
+        RgbImage::from_pixel(200, 100, Rgb([0, 0, 255])).save(&path_b)?;
+
+        // B is at half the resolution of A and offset by (10, 20) pixels in A
+        let transform = [2.0, 0.0, 0.0, 2.0, 10.0, 20.0];
+        let zoomable = BlendedImageZoomable::from_registration(
+            &path_a,
+            "CytAssist",
+            &path_b,
+            "Microscope",
+            transform,
+            RegistrationDisplay::default()
+                .display_width(25)
+                .max_image_dim(Some(100)),
+        )?;
+        let full = BlendedImageZoomable::from_registration(
+            &path_a,
+            "CytAssist",
+            &path_b,
+            "Microscope",
+            transform,
+            RegistrationDisplay::default().max_image_dim(None),
+        )?;
+
+        // A is downsampled 4x and B 2x, so B is now at the resolution of A
+        let json = serde_json::to_value(&zoomable)?;
+        assert_eq!(
+            json["imgBTransform"],
+            serde_json::json!([1.0, 0.0, 0.0, 1.0, 2.5, 5.0])
+        );
+        assert!(json["imgATransform"].is_null());
+        let dims = |key: &str| {
+            let encoded = json[key].as_str().unwrap();
+            let png =
+                base64::decode(encoded.strip_prefix("data:image/png;base64,").unwrap()).unwrap();
+            image::load_from_memory(&png).unwrap().dimensions()
+        };
+        assert_eq!(dims("imgA"), (100, 50));
+        assert_eq!(dims("imgB"), (100, 50));
+        assert_eq!(json["imgATitle"], "CytAssist");
+        assert_eq!(json["imgBTitle"], "Microscope");
+        assert_eq!(json["sizes"]["width"], 25);
+        assert_eq!(json["zoom_pan"]["width"], 25);
+        assert_eq!(json["zoom_pan"]["height"], 13);
+        assert_eq!(
+            json["zoom_pan"]["scale_limits"],
+            serde_json::json!({"min": 1.0, "max": 16.0})
+        );
+
+        // Without downsampling the transform is unchanged
+        let json = serde_json::to_value(&full)?;
+        assert_eq!(json["imgBTransform"], serde_json::json!(transform));
+        assert_eq!(json["zoom_pan"]["height"], 300);
+        assert_eq!(json["zoom_pan"]["scale_limits"]["max"], 400.0 * 4.0 / 600.0);
+        Ok(())
+    }
 }
@@ -0,0 +1,471 @@
+//!
+//! A self-test page with one instance of every component registered using
+//! `react_component!`, with small built-in sample data. Render the page and
+//! eyeball it to catch drift between the react components and the props
+//! serialized here, e.g. a component or a prop renamed on one side only.
+//! `component_manifest()` lists the same samples in a machine-readable form.
+//!
+
+use std::any::type_name;
+
+use chrono::{TimeZone, Utc};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::builder::{BuiltContent, WebSummary};
+use crate::components::{
+    AlertGroup, BlendedImage, BlendedImageSliderSize, BlendedImageZoomable, CategoricalLegend,
+    CodeBlock, ColumnValues, ColumnarTable, CommandLine, DataHeading, DifferentialExpressionTable,
+    DownloadableFile, EmptyState, ErasedSection, Footer, FractionBar, GenericTable,
+    GroupedHeaderTable, GroupedTableMetric, HdClusteringPlot, HdClusteringSingleClusterData,
+    HdClusteringSpatialPlotProps, HdClusteringUmapPlotProps, HdEndToEndAlignment,
+    HdEndToEndAlignmentUmiLegendImage, HeaderGroup, HeadingLevel, HeroMetric, HtmlFragment,
+    InitialFocus, InitialZoomPan, JavaScript, LabeledImage, Layer, MultiLayerImages, PlotlyChart,
+    QcBanner, RawImage, ReactComponent, Sparkline, StepProgress, TableMetric, TermDesc, Threshold,
+    Title, TitleWithHelp, TitleWithTermDesc, Tooltip, TooltipPlace, TooltipVariant, VegaLitePlot,
+    ZoomViewer, ZoomViewerSize,
+};
+use crate::form::{
+    group_field_name, CreateFormInput, FormElement, FormInput, FormInputConfigString,
+    InputFeedback, MultiSelect, MultiSelectType, RepeatedGroupInput, SelectOther, SingleSelect,
+    SingleSelectType, SpreadsheetInput, GROUP_INDEX_PLACEHOLDER,
+};
+use crate::metrics_mapping::MetricFormat;
+use crate::timestamp::Timestamp;
+use crate::SinglePageHtml;
+use crate::{Alert, AlertLevel, Alerts};
+
+/// A 1x1 png, which is a valid data URI even with `strict-images`
+const SAMPLE_PNG: &str = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII=";
+
+/// A component registered using `react_component!` along with its sample
+#[derive(Debug, Clone, Serialize)]
+pub struct ComponentManifestEntry {
+    /// Name of the rust struct, without the module path
+    pub rust_type: &'static str,
+    /// Name of the react component
+    pub component_name: &'static str,
+    /// The sample instance as it is serialized in the page
+    pub sample_json: Value,
+}
+
+/// Whether the props deserialize into the rust type of a component
+pub(crate) type AcceptsProps = fn(&Value) -> bool;
+
+struct Sample {
+    rust_type: &'static str,
+    component_name: &'static str,
+    section: ErasedSection,
+    accepts: AcceptsProps,
+}
+
+fn sample<T: ReactComponent + Serialize + DeserializeOwned>(component: T) -> Sample {
+    let rust_type = type_name::<T>();
+    Sample {
+        rust_type: rust_type.rsplit("::").next().unwrap_or(rust_type),
+        component_name: T::component_name(),
+        section: ErasedSection::new(component),
+        accepts: |props| serde_json::from_value::<T>(props.clone()).is_ok(),
+    }
+}
+
+/// The `(rust type, component name)` of every component of the crate, see
+/// `components::REACT_COMPONENTS`
+pub fn registered_components() -> Vec<(&'static str, &'static str)> {
+    crate::components::REACT_COMPONENTS.to_vec()
+}
+
+fn form_input(config: FormInputConfigString, name: &str, value: &str) -> FormInput {
+    String::create_form_input(config, name.to_string(), Some(value.to_string()))
+}
+
+fn samples() -> Vec<Sample> {
+    let blended_image = || {
+        BlendedImage::new(
+            SAMPLE_PNG.into(),
+            SAMPLE_PNG.into(),
+            BlendedImageSliderSize { width: 470.into() },
+        )
+    };
+    let FormInput::Input(input_element) = form_input(
+        FormInputConfigString::Text {
+            placeholder: None,
+            max_length: None,
+        },
+        "sample_id",
+        "PBMC",
+    ) else {
+        unreachable!()
+    };
+    let FormInput::TextArea(text_area) = form_input(
+        FormInputConfigString::TextArea {
+            rows: Some(3),
+            placeholder: Some("Notes".into()),
+            max_length: Some(500),
+        },
+        "notes",
+        "Sequenced twice",
+    ) else {
+        unreachable!()
+    };
+
+    let library_row = |index: &str, name: &str| {
+        vec![FormElement::new(
+            Title::new("Library name"),
+            form_input(
+                FormInputConfigString::Text {
+                    placeholder: None,
+                    max_length: None,
+                },
+                &group_field_name("libraries", index, "name"),
+                name,
+            ),
+        )]
+    };
+
+    vec![
+        sample(
+            HeroMetric::with_threshold("Estimated number of cells", "3,487", Threshold::Pass)
+                .with_comparison(3487.0, 3379.0, MetricFormat::Count, true),
+        ),
+        sample(TitleWithTermDesc {
+            title: "Sequencing".into(),
+            data: vec![TermDesc::with_one_desc(
+                "Number of reads",
+                "Total number of read pairs that were sequenced.",
+            )],
+        }),
+        sample(
+            TitleWithHelp::new("Summary", "Metrics computed from all the reads.")
+                .bullets(["Reads with a valid barcode", "Reads mapped to the genome"])
+                .link("Learn more", "https://www.10xgenomics.com/support"),
+        ),
+        sample(GenericTable::from_rows(
+            vec![
+                vec!["PBMC 1".into(), "3,487".into(), "96,123".into()],
+                vec!["PBMC 2".into(), "4,012".into(), "88,541".into()],
+            ],
+            Some(vec!["Sample".into(), "Cells".into(), "Mean reads".into()]),
+        )),
+        sample(TableMetric {
+            rows: vec![
+                ("Number of reads".into(), "335,174,516".into()),
+                ("Valid barcodes".into(), "97.3%".into()),
+            ],
+            row_ids: vec![None, Some("valid_barcodes_frac".into())],
+        }),
+        sample(AlertGroup {
+            name: "antibody".into(),
+            alerts: Alerts {
+                alerts: vec![Alert {
+                    level: AlertLevel::Warn,
+                    title: "Low antibody reads usable".into(),
+                    formatted_value: Some("12.1%".into()),
+                    message: "Ideal > 50%".into(),
+                    group: Some("antibody".into()),
+                }],
+            },
+        }),
+        sample(
+            Footer::new()
+                .link("Support", "https://support.10xgenomics.com")
+                .note("Licensed under the 10x End User Software License")
+                .with_generation_info("9.0.1")
+                .generated_at("2024-01-31T13:45:00Z"),
+        ),
+        sample(GroupedTableMetric::from_rows([
+            ("Sequencing", "Number of reads", "335,174,516"),
+            ("Sequencing", "Valid barcodes", "97.3%"),
+            ("Cells", "Estimated number of cells", "3,487"),
+        ])),
+        sample(PlotlyChart::with_layout_and_data(
+            json!({"title": "Barcode rank plot"}),
+            vec![json!({"type": "scatter", "x": [1, 10, 100], "y": [1000, 100, 10]})],
+        )),
+        sample(VegaLitePlot::themed(json!({
+            "data": {"values": [{"x": "A", "y": 28}, {"x": "B", "y": 55}]},
+            "mark": "bar",
+            "encoding": {
+                "x": {"field": "x", "type": "nominal"},
+                "y": {"field": "y", "type": "quantitative"}
+            }
+        }))),
+        sample(RawImage::new(SAMPLE_PNG.into())),
+        sample(blended_image()),
+        sample(BlendedImageZoomable::new(blended_image(), 0.5, 8.0)),
+        sample(ZoomViewer {
+            small_image: SAMPLE_PNG.into(),
+            big_image: SAMPLE_PNG.into(),
+            sizes: ZoomViewerSize {
+                width: 400.into(),
+                height: "100%".into(),
+            },
+            plot_title: Some("Tissue image".into()),
+        }),
+        sample(StepProgress {
+            steps: vec!["Upload".into(), "Align".into(), "Review".into()],
+            active_step: 1,
+            active_step_failed: false,
+        }),
+        sample(CodeBlock {
+            code: "cellranger count --id=sample1".into(),
+            max_height: Some("200px".into()),
+        }),
+        sample(Tooltip::new_with_id(
+            "kitchen-sink-tooltip",
+            "Shown on hover",
+            "Hover me",
+            Some(TooltipVariant::Light),
+            Some(TooltipPlace::Top),
+        )),
+        sample(HdClusteringPlot {
+            spatial_plot_props: HdClusteringSpatialPlotProps {
+                title: "Clusters on tissue".into(),
+                tissue_image: SAMPLE_PNG.into(),
+                tissue_css_transform: vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+                spot_css_transform: vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+                width: 400,
+                height: 400,
+                initial_zoom_pan: InitialZoomPan {
+                    scale: Some(1.0),
+                    dx: None,
+                    dy: None,
+                },
+            },
+            umap_plot_props: HdClusteringUmapPlotProps {
+                title: "Clusters on UMAP".into(),
+            },
+            clusters: vec![HdClusteringSingleClusterData::with_cluster_index(
+                0,
+                "Cluster 1",
+                SAMPLE_PNG.into(),
+                SAMPLE_PNG.into(),
+            )],
+        }),
+        sample(HtmlFragment::new("<b>Note:</b> sample data")),
+        sample(JavaScript::new("console.log('kitchen sink');")),
+        sample(DifferentialExpressionTable {
+            table: json!({
+                "columns": ["Feature", "Log2 fold change", "Adjusted p value"],
+                "rows": [["CD3E", 2.5, 1e-12]]
+            }),
+        }),
+        sample(HdEndToEndAlignment {
+            tissue_image: SAMPLE_PNG.into(),
+            tissue_image_title: "Tissue".into(),
+            tissue_css_transform: vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+            display_height: 400,
+            display_width: 400,
+            umi_legend_images: vec![HdEndToEndAlignmentUmiLegendImage::new(
+                "viridis",
+                SAMPLE_PNG.into(),
+            )],
+            grayscale_umi_image: SAMPLE_PNG.into(),
+            umi_image_title: "UMI counts".into(),
+            umi_css_transform: vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+            tissue_mask_image: SAMPLE_PNG.into(),
+            initial_zoom_pan: None,
+        }),
+        sample(MultiLayerImages {
+            focus: InitialFocus {
+                x: 0,
+                y: 0,
+                width: 1,
+                height: 1,
+            },
+            layers: vec![Layer {
+                name: "Tissue".into(),
+                images: vec![LabeledImage::new(SAMPLE_PNG.into())],
+            }],
+            full_screen: false,
+        }),
+        sample(DownloadableFile {
+            data: "Sample,Cells\nPBMC 1,3487\n".into(),
+            filename: "cells.csv".into(),
+            text: "Download cells.csv".into(),
+            mime_type: "text/csv".into(),
+        }),
+        sample(QcBanner {
+            overall: Threshold::Warn,
+            sections: vec![
+                ("Cells".into(), Threshold::Pass),
+                ("Sequencing".into(), Threshold::Warn),
+            ],
+        }),
+        sample(DataHeading::new(HeadingLevel::H2, "Kitchen sink")),
+        sample(EmptyState::new("No cells passed the filters")),
+        sample(
+            Sparkline::new(vec![34.1, 35.6, 35.2, 33.8, 30.9])
+                .unwrap()
+                .size(80, 16),
+        ),
+        sample(
+            FractionBar::new(0.921)
+                .unwrap()
+                .label("92.1%")
+                .threshold(Threshold::Pass),
+        ),
+        sample(
+            GroupedHeaderTable::new(
+                vec![
+                    HeaderGroup::new("Feature", vec!["ID".into(), "Name".into()]),
+                    HeaderGroup::new("Cluster 1", vec!["L2FC".into(), "p-value".into()]),
+                ],
+                vec![vec![
+                    "ENSG00000198851".into(),
+                    "CD3E".into(),
+                    "2.1".into(),
+                    "1e-5".into(),
+                ]],
+            )
+            .unwrap(),
+        ),
+        sample(
+            CategoricalLegend::from_palette(["T cells", "B cells", "NK cells"])
+                .counts([1200, 340, 95]),
+        ),
+        sample(
+            ColumnarTable::from_columns_typed([
+                (
+                    "Barcode",
+                    ColumnValues::from(vec!["AAACCTGA-1".to_string(), "AAACGGGC-1".to_string()]),
+                ),
+                ("UMIs", ColumnValues::from(vec![5123.0, 4871.0])),
+            ])
+            .unwrap(),
+        ),
+        sample(InputFeedback {
+            error: Some("Sample ID is required".into()),
+            text: None,
+            details: None,
+        }),
+        sample(input_element),
+        sample(SingleSelect {
+            ty: SingleSelectType::Dropdown,
+            name: "chemistry".into(),
+            options: vec!["SC3Pv3".into(), "SC5P-PE".into(), "Other".into()],
+            selected: Some("Other".into()),
+            required: Some(true),
+            other: Some(SelectOther {
+                name: "chemistry_other".into(),
+                option: "Other".into(),
+                placeholder: Some("Specify the chemistry".into()),
+                value: Some("SC3Pv4".into()),
+            }),
+        }),
+        sample(MultiSelect {
+            ty: MultiSelectType::Checkbox,
+            name: "libraries".into(),
+            options: vec!["Gene Expression".into(), "Antibody Capture".into()],
+            selected: vec!["Gene Expression".into()],
+            required: Some(false),
+        }),
+        sample(text_area),
+        sample(RepeatedGroupInput {
+            name: "libraries".into(),
+            template: library_row(GROUP_INDEX_PLACEHOLDER, ""),
+            rows: vec![library_row("0", "GEX"), library_row("1", "ADT")],
+            min_rows: 1,
+            max_rows: Some(8),
+            required: Some(true),
+        }),
+        sample(SpreadsheetInput {
+            name: "samples".into(),
+            column_labels: Some(vec!["Sample".into(), "Cells".into()]),
+            n_rows: 2,
+            n_cols: 2,
+            max_height: Some("200px".into()),
+            value: Some("Sample,Cells\nPBMC 1,3487\n".into()),
+            readonly_columns: vec![1],
+            computed_column: None,
+            required: Some(true),
+        }),
+        sample(CommandLine::new("cellranger count --id=sample1").unwrap()),
+        sample(Timestamp::date_time_utc(
+            Utc.with_ymd_and_hms(2024, 1, 31, 13, 45, 0).unwrap(),
+        )),
+    ]
+}
+
+/// One sample of every registered component, in the order in which they
+/// appear in `kitchen_sink()`
+pub fn component_manifest() -> Vec<ComponentManifestEntry> {
+    checked_manifest()
+        .into_iter()
+        .map(|(entry, _)| entry)
+        .collect()
+}
+
+/// The manifest along with a check of the props against the rust type of
+/// each entry
+pub(crate) fn checked_manifest() -> Vec<(ComponentManifestEntry, AcceptsProps)> {
+    samples()
+        .into_iter()
+        .map(|sample| {
+            let entry = ComponentManifestEntry {
+                rust_type: sample.rust_type,
+                component_name: sample.component_name,
+                sample_json: serde_json::to_value(&sample.section).unwrap(),
+            };
+            (entry, sample.accepts)
+        })
+        .collect()
+}
+
+/// A page with one sample of every registered component. Each sample is
+/// under its rust type as the data key.
+pub fn kitchen_sink() -> WebSummary {
+    let content = samples()
+        .into_iter()
+        .fold(BuiltContent::new(), |content, sample| {
+            content.section(sample.rust_type, sample.section)
+        });
+    SinglePageHtml::from_content(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HtmlTemplate;
+
+    #[test]
+    fn test_manifest_is_complete() {
+        let registered = registered_components();
+        let manifest = component_manifest();
+        assert_eq!(manifest.len(), registered.len());
+        for (rust_type, component_name) in registered {
+            assert!(
+                manifest
+                    .iter()
+                    .any(|entry| entry.rust_type == rust_type
+                        && entry.component_name == component_name),
+                "No sample of {rust_type} ({component_name}) in the kitchen sink"
+            );
+        }
+    }
+
+    #[test]
+    fn test_samples_deserialize() {
+        for (entry, accepts) in checked_manifest() {
+            assert!(accepts(&entry.sample_json), "{}", entry.rust_type);
+        }
+    }
+
+    #[test]
+    fn test_kitchen_sink_page() {
+        let template = kitchen_sink().template(None);
+        let data = serde_json::to_value(kitchen_sink()).unwrap();
+        for entry in component_manifest() {
+            assert!(
+                template.contains(&format!(
+                    r#"data-key="{}" data-component="{}""#,
+                    entry.rust_type, entry.component_name
+                )),
+                "{} is not in the template",
+                entry.rust_type
+            );
+            assert_eq!(data[entry.rust_type], entry.sample_json);
+        }
+    }
+}
@@ -5,16 +5,17 @@
 /// Code to generate html from the json data
 pub mod generate_html;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[cfg(feature = "generate_html")]
 pub use generate_html::generate_html_summary;
 
 pub use generate_html::{
-    generate_html_summary_with_build_files, TemplateInfo, WebSummaryBuildFiles,
+    generate_html_summary_with_build_files, generate_html_summary_with_head, PageHead,
+    TemplateInfo, WebSummaryBuildFiles,
 };
 
-use components::WsNavBar;
+use components::{JavaScript, WsNavBar};
 use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "derive")]
@@ -31,6 +32,8 @@ pub mod macros;
 /// Websummary components
 pub mod components;
 
+pub mod data_uri;
+
 #[cfg(feature = "image_base64_encode")]
 pub mod image_base64_encode;
 
@@ -40,6 +43,9 @@ pub mod image_proc;
 #[cfg(feature = "image_proc")]
 pub use image;
 
+#[cfg(all(feature = "image_proc", feature = "image_base64_encode"))]
+pub mod lite;
+
 #[cfg(feature = "csv_table")]
 pub mod csv_table;
 
@@ -48,22 +54,110 @@ pub mod actix;
 
 pub mod scrape_json;
 
+pub mod chunked;
+
+pub mod multi_summary;
+
+pub mod builder;
+pub use builder::{WebSummary, WebSummaryBuilder};
+
+pub mod print;
+
+pub mod export;
+
+pub mod dev_artifacts;
+
+pub mod fixture;
+
+#[cfg(feature = "derive")]
+pub mod comparison;
+
+pub mod metrics_mapping;
+
+pub mod check;
+
 #[cfg(feature = "form")]
 pub mod form;
 
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+
+#[cfg(feature = "time")]
+pub mod timestamp;
+
+pub mod translate;
+use translate::{SharedTranslator, Translator};
+
+pub mod colors;
+
+pub mod plots;
+
+pub mod content_hash;
+
+pub mod sanitize;
+use sanitize::NanPolicy;
+
+pub mod visit;
+
+pub mod binary_resources;
+use binary_resources::BinaryResources;
+
+pub mod fallback;
+
+pub mod nested;
+
+pub mod migrations;
+
+pub mod number_format;
+
+#[cfg(feature = "kitchen-sink")]
+pub mod kitchen_sink;
+
+#[cfg(feature = "schema")]
+pub mod schema;
+
 pub trait HtmlTemplate {
     fn template(&self, data_key: Option<String>) -> String;
+    /// Append the template to `out`. Must produce the same output as
+    /// `template()`. Containers override this so that their children write
+    /// into one buffer instead of allocating a `String` each.
+    fn template_into(&self, data_key: Option<String>, out: &mut String) {
+        out.push_str(&self.template(data_key));
+    }
+    /// Whether the template of every value of the type only depends on the
+    /// data key, as for a react component. Containers of such values render
+    /// the template once and splice in the key of each element. This is a
+    /// property of the type, so `Box<dyn HtmlTemplate>`, `Rc` and `Arc`,
+    /// whose values can have different templates, keep the default.
+    fn template_is_keyed() -> bool
+    where
+        Self: Sized,
+    {
+        false
+    }
 }
 
 #[derive(Debug, Clone)]
 struct SinglePageConfig {
     div_class: String,
+    translator: Option<SharedTranslator>,
+    normalize_alerts: bool,
+    pretty_json: bool,
+    nan_policy: Option<NanPolicy>,
+    strip_experimental: bool,
+    favicon_data_uri: Option<String>,
 }
 
 impl Default for SinglePageConfig {
     fn default() -> Self {
         SinglePageConfig {
             div_class: "container".into(),
+            translator: None,
+            normalize_alerts: false,
+            pretty_json: false,
+            nan_policy: None,
+            strip_experimental: false,
+            favicon_data_uri: None,
         }
     }
 }
@@ -83,20 +177,101 @@ pub struct SinglePageHtml<P> {
     content: P,
     #[serde(rename = "alarms")]
     alerts: Alerts,
+    /// The alerts routed to a group, see `Alert::in_group()`
+    #[serde(rename = "_alert_groups", skip_serializing_if = "BTreeMap::is_empty")]
+    alert_groups: BTreeMap<String, components::AlertGroup>,
     #[serde(skip)]
     config: SinglePageConfig,
     #[serde(default, rename = "_resources")]
     resources: SharedResources,
+    #[serde(rename = "_debug_toggle", skip_serializing_if = "Option::is_none")]
+    debug_toggle: Option<JavaScript>,
+    #[serde(rename = "_dark_mode", skip_serializing_if = "std::ops::Not::not")]
+    dark_mode: bool,
+    #[serde(rename = "_footer", skip_serializing_if = "Option::is_none")]
+    footer: Option<components::Footer>,
+    /// See `migrations`
+    #[serde(rename = "_schema_version")]
+    schema_version: u32,
 }
 
+const DEBUG_TOGGLE_KEY: &str = "_debug_toggle";
+
+/// Keys of the data of a `SinglePageHtml` which are not a part of the
+/// content, which thus cannot be used as section keys
+pub(crate) const RESERVED_KEYS: [&str; 10] = [
+    "sample",
+    "alarms",
+    components::ALERT_GROUPS_KEY,
+    RESOURCES_PREFIX,
+    DEBUG_TOGGLE_KEY,
+    "_dark_mode",
+    components::FOOTER_KEY,
+    migrations::SCHEMA_VERSION_KEY,
+    chunked::CHUNK_MANIFEST_KEY,
+    chunked::CHUNK_LOADER_KEY,
+];
+
 pub const RESOURCES_PREFIX: &str = "_resources";
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
-pub struct SharedResources(pub HashMap<String, Value>);
+/// Resources stored once in the page and referenced by a string, the json
+/// resources by key. See `binary_resources` for the resources held as bytes.
+#[derive(Debug, Clone, Default)]
+pub struct SharedResources(pub HashMap<String, Value>, BinaryResources);
+
+impl From<HashMap<String, Value>> for SharedResources {
+    fn from(json: HashMap<String, Value>) -> Self {
+        SharedResources(json, BinaryResources::default())
+    }
+}
+
+/// The resources are serialized in the order of their keys, so that the
+/// same resources always give the same page
+impl Serialize for SharedResources {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let sorted: BTreeMap<&String, &Value> = self.0.iter().collect();
+        #[cfg(feature = "binary_resources")]
+        if !self.1.is_empty() {
+            use serde::ser::SerializeMap;
+            let binary = self.1.json_entries();
+            let mut map = serializer.serialize_map(Some(sorted.len() + binary.len()))?;
+            for (key, value) in sorted.into_iter().chain(binary.iter().map(|(k, v)| (k, v))) {
+                map.serialize_entry(key, value)?;
+            }
+            return map.end();
+        }
+        sorted.serialize(serializer)
+    }
+}
+
+/// The binary resources of the data are kept as their json descriptors
+impl<'de> Deserialize<'de> for SharedResources {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(HashMap::deserialize(deserializer)?.into())
+    }
+}
 
 impl SharedResources {
     pub fn new() -> Self {
         SharedResources::default()
     }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty() && self.1.is_empty()
+    }
+    /// The json resources by key, without the `_resources_` prefix
+    pub fn json(&self) -> &HashMap<String, Value> {
+        &self.0
+    }
+    /// The resources held as bytes, see `insert_binary()`
+    pub fn binary(&self) -> &BinaryResources {
+        &self.1
+    }
+    /// Store the bytes apart from the json resources, e.g. a file to
+    /// download, returning the reference string. Identical bytes with the
+    /// same mime type are stored once.
+    #[cfg(feature = "binary_resources")]
+    pub fn insert_binary(&mut self, bytes: Vec<u8>, mime: &str) -> String {
+        format!("{}_{}", RESOURCES_PREFIX, self.1.insert(bytes, mime))
+    }
     pub fn insert(&mut self, value: Value) -> String {
         // Check if the value is already in the map
         let key = match self
@@ -104,15 +279,110 @@ impl SharedResources {
             .iter()
             .find_map(|(key, val)| (val == &value).then(|| key.clone()))
         {
-            Some(key) => key,
+            Some(key) => {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(key, "Deduplicated shared resource");
+                key
+            }
             None => {
-                let key = format!("{:03}", self.0.len());
+                let key = next_free_key("", self.0.len(), |key| self.0.contains_key(key));
                 self.0.insert(key.clone(), value);
                 key
             }
         };
         format!("{}_{}", RESOURCES_PREFIX, key)
     }
+    /// Move the resources of `other` into these, e.g. when two stages of a
+    /// pipeline built their resources independently. A resource of `other`
+    /// identical to one of these is stored once, and the resources of
+    /// `other` whose key is already taken get a new key. The returned remap
+    /// is applied to the content referring to the resources of `other` with
+    /// `apply_remap()`. The references to these resources are unchanged.
+    pub fn merge(&mut self, other: SharedResources) -> KeyRemap {
+        let mut remap = BTreeMap::new();
+        let mut entries: Vec<_> = other.0.into_iter().collect();
+        entries.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+        for (key, value) in entries {
+            let new_key = match self
+                .0
+                .iter()
+                .find_map(|(k, v)| (v == &value).then(|| k.clone()))
+            {
+                Some(existing) => existing,
+                None => {
+                    let new_key = if self.0.contains_key(&key) {
+                        next_free_key("", self.0.len(), |k| self.0.contains_key(k))
+                    } else {
+                        key.clone()
+                    };
+                    self.0.insert(new_key.clone(), value);
+                    new_key
+                }
+            };
+            if new_key != key {
+                remap.insert(
+                    format!("{RESOURCES_PREFIX}_{key}"),
+                    format!("{RESOURCES_PREFIX}_{new_key}"),
+                );
+            }
+        }
+        remap.extend(self.1.merge(other.1));
+        KeyRemap(remap)
+    }
+}
+
+/// Key of the shared resource with the given index: three digits up to 999,
+/// then one `x` per extra digit, e.g. `x1000` and `xx10000`. No key is a
+/// prefix of another and the keys sort in the order of their indices.
+pub fn resource_key(index: usize) -> String {
+    let digits = index.to_string();
+    format!("{}{digits:0>3}", "x".repeat(digits.len().saturating_sub(3)))
+}
+
+/// The first key, starting from the index `start`, which is not taken
+pub(crate) fn next_free_key(prefix: &str, start: usize, is_taken: impl Fn(&str) -> bool) -> String {
+    (start..)
+        .map(|index| format!("{prefix}{}", resource_key(index)))
+        .find(|key| !is_taken(key))
+        .unwrap()
+}
+
+/// New reference strings of the resources moved by
+/// `SharedResources::merge()`, keyed by their reference strings before the
+/// merge. Resources which kept their key are not listed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyRemap(pub BTreeMap<String, String>);
+
+impl KeyRemap {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// The new reference string of the resource, if it changed
+    pub fn get(&self, reference: &str) -> Option<&str> {
+        self.0.get(reference).map(String::as_str)
+    }
+}
+
+/// Rewrite the reference strings throughout the serialized content, after
+/// its resources were merged into other resources
+pub fn apply_remap(content: &mut Value, remap: &KeyRemap) {
+    replace_strings(content, &|s| {
+        remap.get(s).map(|new| Value::String(new.into()))
+    });
+}
+
+/// Replace the strings in the value for which `f` returns a value
+pub(crate) fn replace_strings(value: &mut Value, f: &impl Fn(&str) -> Option<Value>) {
+    match value {
+        Value::String(s) => {
+            if let Some(new) = f(s) {
+                *value = new;
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(|v| replace_strings(v, f)),
+        Value::Object(map) => map.values_mut().for_each(|v| replace_strings(v, f)),
+        _ => {}
+    }
 }
 
 pub trait AddToSharedResource {
@@ -126,7 +396,26 @@ pub trait AddToSharedResource {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl<T: AddToSharedResource> AddToSharedResource for Vec<T> {
+    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
+        for inner in self {
+            inner.add_to_shared_resource(shared_resource);
+        }
+    }
+}
+
+impl<T: AddToSharedResource> AddToSharedResource for Option<T> {
+    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
+        if let Some(inner) = self {
+            inner.add_to_shared_resource(shared_resource);
+        }
+    }
+}
+
+/// Level of an alert. The levels are ordered by severity, so that
+/// `Error > Warn > Info`, and displayed and parsed as their serialized
+/// strings, e.g. `ERROR`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum AlertLevel {
     Error,
@@ -134,12 +423,91 @@ pub enum AlertLevel {
     Info,
 }
 
+impl AlertLevel {
+    /// Position of the level when the alerts are sorted
+    fn rank(&self) -> u8 {
+        match self {
+            AlertLevel::Error => 0,
+            AlertLevel::Warn => 1,
+            AlertLevel::Info => 2,
+        }
+    }
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertLevel::Error => "ERROR",
+            AlertLevel::Warn => "WARN",
+            AlertLevel::Info => "INFO",
+        }
+    }
+}
+
+impl PartialOrd for AlertLevel {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for AlertLevel {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.rank().cmp(&self.rank())
+    }
+}
+
+impl std::fmt::Display for AlertLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A string which is not one of the `AlertLevel`s
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseAlertLevelError(pub String);
+
+impl std::fmt::Display for ParseAlertLevelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid alert level {:?}, expected ERROR, WARN or INFO",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseAlertLevelError {}
+
+/// Parse the serialized strings of the levels, e.g. `WARN`, as written in the
+/// summary data
+impl std::str::FromStr for AlertLevel {
+    type Err = ParseAlertLevelError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [AlertLevel::Error, AlertLevel::Warn, AlertLevel::Info]
+            .into_iter()
+            .find(|level| level.as_str() == s)
+            .ok_or_else(|| ParseAlertLevelError(s.to_string()))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Alert {
     pub level: AlertLevel,
     pub title: String,
     pub formatted_value: Option<String>,
     pub message: String,
+    /// Name of the `AlertGroup` showing the alert instead of the banner at
+    /// the top of the page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+}
+
+impl Alert {
+    /// Show the alert in the named group, placed in the content with an
+    /// `AlertGroupSlot`, e.g. on the tab of a library, instead of the banner
+    /// at the top of the page. The alerts of a group without a slot are shown
+    /// in the banner.
+    pub fn in_group(mut self, group: impl Into<String>) -> Self {
+        self.group = Some(group.into());
+        self
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Default, Deserialize)]
@@ -148,33 +516,147 @@ pub struct Alerts {
     pub alerts: Vec<Alert>,
 }
 
+impl Alerts {
+    /// Remove the duplicate alerts, keeping the first one. Two alerts are
+    /// duplicates if they have the same level and exactly the same title and
+    /// message, compared case-sensitively. The `formatted_value` is ignored,
+    /// so the first alert's value is kept.
+    pub fn dedup(&mut self) {
+        let mut kept: Vec<Alert> = Vec::with_capacity(self.alerts.len());
+        for alert in self.alerts.drain(..) {
+            if !kept.iter().any(|k| {
+                k.level.rank() == alert.level.rank()
+                    && k.title == alert.title
+                    && k.message == alert.message
+            }) {
+                kept.push(alert);
+            }
+        }
+        self.alerts = kept;
+    }
+
+    /// Sort the alerts as errors, then warnings, then infos. The order of the
+    /// alerts within a level is unchanged.
+    pub fn sort_by_level(&mut self) {
+        self.alerts.sort_by_key(|alert| alert.level.rank());
+    }
+
+    /// The most severe level of the alerts, `None` if there are no alerts
+    pub fn max_level(&self) -> Option<AlertLevel> {
+        self.alerts.iter().map(|alert| alert.level.clone()).max()
+    }
+
+    /// Number of alerts of each level. The levels without alerts are not
+    /// listed.
+    pub fn count_by_level(&self) -> BTreeMap<AlertLevel, usize> {
+        let mut counts = BTreeMap::new();
+        for alert in &self.alerts {
+            *counts.entry(alert.level.clone()).or_default() += 1;
+        }
+        counts
+    }
+}
+
+/// Outcome of a run according to the alerts of its summary, e.g. for a
+/// pipeline to fail the run if there is an error alert without parsing the
+/// html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SummaryOutcome {
+    /// No alerts, or only infos
+    Pass,
+    /// At least one warning and no errors
+    Warn,
+    /// At least one error
+    Fail,
+}
+
+impl SummaryOutcome {
+    pub const PASS_EXIT_CODE: u8 = 0;
+    pub const WARN_EXIT_CODE: u8 = 2;
+    pub const FAIL_EXIT_CODE: u8 = 3;
+
+    /// Exit code of the process for the outcome, see the `*_EXIT_CODE`
+    /// constants. Warnings give a nonzero code too, so a pipeline which only
+    /// fails on errors should check `is_failure()` instead.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            SummaryOutcome::Pass => Self::PASS_EXIT_CODE,
+            SummaryOutcome::Warn => Self::WARN_EXIT_CODE,
+            SummaryOutcome::Fail => Self::FAIL_EXIT_CODE,
+        }
+    }
+    pub fn is_failure(&self) -> bool {
+        *self == SummaryOutcome::Fail
+    }
+}
+
+impl From<&Alerts> for SummaryOutcome {
+    fn from(alerts: &Alerts) -> Self {
+        match alerts.max_level() {
+            Some(AlertLevel::Error) => SummaryOutcome::Fail,
+            Some(AlertLevel::Warn) => SummaryOutcome::Warn,
+            Some(AlertLevel::Info) | None => SummaryOutcome::Pass,
+        }
+    }
+}
+
+/// Split the alerts into the global ones and the groups, keeping the order
+/// of the alerts within each
+fn route_alerts(alerts: Vec<Alert>) -> (Alerts, BTreeMap<String, components::AlertGroup>) {
+    let mut global = Alerts::default();
+    let mut groups: BTreeMap<String, components::AlertGroup> = BTreeMap::new();
+    for alert in alerts {
+        match &alert.group {
+            Some(name) => groups
+                .entry(name.clone())
+                .or_insert_with(|| components::AlertGroup::new(name.clone()))
+                .alerts
+                .alerts
+                .push(alert),
+            None => global.alerts.push(alert),
+        }
+    }
+    (global, groups)
+}
+
 impl<P> SinglePageHtml<P> {
     pub fn from_content(content: P) -> Self {
         SinglePageHtml {
             nav_bar: None,
             alerts: Alerts::default(),
+            alert_groups: BTreeMap::new(),
             content,
             config: SinglePageConfig::default(),
             resources: SharedResources::new(),
+            debug_toggle: None,
+            dark_mode: false,
+            footer: None,
+            schema_version: migrations::SCHEMA_VERSION,
         }
     }
     pub fn nav_bar(mut self, nav_bar: WsNavBar) -> Self {
         self.nav_bar = Some(nav_bar);
         self
     }
+    /// The alerts of the page. The alerts in a group are shown by the
+    /// `AlertGroupSlot` of the group and the others in the banner at the top.
     pub fn alerts(mut self, alerts: Vec<Alert>) -> Self {
-        self.alerts = Alerts { alerts };
+        (self.alerts, self.alert_groups) = route_alerts(alerts);
         self
     }
     pub fn new(nav_bar: WsNavBar, content: P, alerts: Option<Vec<Alert>>) -> Self {
+        let (alerts, alert_groups) = route_alerts(alerts.unwrap_or_default());
         SinglePageHtml {
             nav_bar: Some(nav_bar),
             content,
-            alerts: Alerts {
-                alerts: alerts.unwrap_or_default(),
-            },
+            alerts,
+            alert_groups,
             config: SinglePageConfig::default(),
             resources: SharedResources::new(),
+            debug_toggle: None,
+            dark_mode: false,
+            footer: None,
+            schema_version: migrations::SCHEMA_VERSION,
         }
     }
     pub fn full_width(mut self) -> Self {
@@ -185,6 +667,137 @@ impl<P> SinglePageHtml<P> {
         self.resources = resources;
         self
     }
+    /// Add a control which reveals the `DebugOnly` sections in the page
+    pub fn with_debug_toggle(mut self) -> Self {
+        self.debug_toggle = Some(components::debug_toggle());
+        self
+    }
+    /// Show the banner above the content, with the overall status computed
+    /// from the alerts of the page and the thresholds of the metrics in each
+    /// section. See `QcBanner::from_alerts_and_metrics()`. The data keys of
+    /// the content are unchanged.
+    pub fn with_qc_banner<'a>(
+        self,
+        metrics: impl IntoIterator<Item = (&'a str, Option<components::Threshold>)>,
+    ) -> SinglePageHtml<components::WithQcBanner<P>> {
+        let qc_banner = components::QcBanner::from_alerts_and_metrics(
+            &Alerts {
+                alerts: self.all_alerts(),
+            },
+            metrics,
+        );
+        SinglePageHtml {
+            nav_bar: self.nav_bar,
+            content: components::WithQcBanner {
+                qc_banner,
+                content: self.content,
+            },
+            alerts: self.alerts,
+            alert_groups: self.alert_groups,
+            config: self.config,
+            resources: self.resources,
+            debug_toggle: self.debug_toggle,
+            dark_mode: self.dark_mode,
+            footer: self.footer,
+            schema_version: self.schema_version,
+        }
+    }
+    /// The global alerts followed by the alerts of each group, in the order
+    /// of the group names
+    pub fn all_alerts(&self) -> Vec<Alert> {
+        self.alerts
+            .alerts
+            .iter()
+            .chain(
+                self.alert_groups
+                    .values()
+                    .flat_map(|group| &group.alerts.alerts),
+            )
+            .cloned()
+            .collect()
+    }
+    /// Outcome of the run according to all the alerts of the page, including
+    /// the grouped ones
+    pub fn outcome(&self) -> SummaryOutcome {
+        SummaryOutcome::from(&Alerts {
+            alerts: self.all_alerts(),
+        })
+    }
+    /// Deduplicate and sort the alerts when the html is generated. See
+    /// `Alerts::dedup()` and `Alerts::sort_by_level()`.
+    pub fn normalized_alerts(mut self) -> Self {
+        self.config.normalize_alerts = true;
+        self
+    }
+    /// Show the footer below the content, e.g. with the support links. There
+    /// is no footer by default.
+    pub fn footer(mut self, footer: components::Footer) -> Self {
+        self.footer = Some(footer);
+        self
+    }
+    /// Render the page in dark mode, for on-screen review. Adds the `dark`
+    /// class to the container div for the css to key off.
+    pub fn dark_mode(mut self) -> Self {
+        self.dark_mode = true;
+        self
+    }
+    /// Embed the data as json indented by two spaces, with one value per
+    /// line, so that diffs of summaries committed to git are readable. The
+    /// data is embedded in a single line by default.
+    pub fn pretty_json(mut self) -> Self {
+        self.config.pretty_json = true;
+        self
+    }
+    /// Translate the user visible strings, such as titles, metric names and
+    /// alerts, when the html is generated. See `translate`.
+    pub fn with_translator(mut self, translator: impl Translator + Send + Sync + 'static) -> Self {
+        self.config.translator = Some(SharedTranslator::new(translator));
+        self
+    }
+    /// Apply the policy to the non-finite numbers in the data when the html
+    /// is generated, after translating. See `sanitize`.
+    pub fn sanitize_numbers(mut self, policy: NanPolicy) -> Self {
+        self.config.nan_policy = Some(policy);
+        self
+    }
+    /// Drop the `Experimental` content from the data and the template when
+    /// the html is generated, e.g. for the builds shipped to customers. The
+    /// data of the dropped content is null.
+    pub fn strip_experimental(mut self) -> Self {
+        self.config.strip_experimental = true;
+        self
+    }
+    /// `strip_experimental()` if the condition holds, e.g. depending on an
+    /// environment variable or a cargo feature of the pipeline
+    pub fn strip_experimental_if(self, condition: bool) -> Self {
+        if condition {
+            self.strip_experimental()
+        } else {
+            self
+        }
+    }
+    /// Icon of the browser tab, e.g. `data:image/png;base64,...`, used if
+    /// the template has a `[[ favicon ]]` marker
+    pub fn favicon_data_uri(mut self, uri: impl Into<String>) -> Self {
+        self.config.favicon_data_uri = Some(uri.into());
+        self
+    }
+    /// Title and favicon of the browser tab. The title is `{id} - {description}`
+    /// from the nav bar.
+    pub fn page_head(&self) -> PageHead {
+        PageHead {
+            title: self
+                .nav_bar
+                .as_ref()
+                .map(|nav_bar| format!("{} - {}", nav_bar.id, nav_bar.description)),
+            favicon_data_uri: self.config.favicon_data_uri.clone(),
+            data_scripts: if self.resources.1.is_empty() {
+                Vec::new()
+            } else {
+                vec![binary_resources::binary_loader()]
+            },
+        }
+    }
 }
 impl<P: HtmlTemplate> HtmlTemplate for SinglePageHtml<P> {
     fn template(&self, data_key: Option<String>) -> String {
@@ -192,28 +805,165 @@ impl<P: HtmlTemplate> HtmlTemplate for SinglePageHtml<P> {
             r#"<div class="navbar-wrapper"></div>
 <div class="namescription-wrapper"></div>"#
         });
+        let debug_toggle = self.debug_toggle.as_ref().map_or(String::new(), |toggle| {
+            format!("{}\n", toggle.template(Some(DEBUG_TOGGLE_KEY.into())))
+        });
+        let dark = if self.dark_mode { " dark" } else { "" };
+        let footer = self.footer.as_ref().map_or(String::new(), |footer| {
+            format!(
+                "<div class=\"{}{dark}\">{}</div>\n",
+                self.config.div_class,
+                footer.template(Some(components::FOOTER_KEY.into()))
+            )
+        });
+        let mut content = self.content.template(data_key);
+        let empty_groups = components::alert_group_slot_keys(&content)
+            .into_iter()
+            .filter(|key| {
+                !self
+                    .alert_groups
+                    .keys()
+                    .any(|name| components::alert_group_data_key(name) == *key)
+            })
+            .collect::<Vec<_>>();
+        if !empty_groups.is_empty() {
+            content = components::remove_data_key_divs(&content, &empty_groups);
+        }
         format!(
             r#"{div_nav_bar}
 <div class="alert-wrapper"></div>
-<div class="{}">{}</div>
-"#,
+<div class="{}{dark}">{}</div>
+{footer}{debug_toggle}"#,
             self.config.div_class,
-            self.content.template(data_key)
+            components::dedup_section_ids(&content)
         )
     }
 }
 
+impl<P: Serialize> SinglePageHtml<P> {
+    /// Data embedded in the html, translated if there is a translator and
+    /// sanitized if there is a policy for non-finite numbers, along with the
+    /// data keys of the experimental content which was stripped
+    fn json_data(&self) -> Result<(String, Vec<String>), anyhow::Error> {
+        fn to_string<T: Serialize>(data: &T, pretty: bool) -> serde_json::Result<String> {
+            if pretty {
+                serde_json::to_string_pretty(data)
+            } else {
+                serde_json::to_string(data)
+            }
+        }
+        let pretty = self.config.pretty_json;
+        if self.config.translator.is_none()
+            && self.config.nan_policy.is_none()
+            && !self.config.strip_experimental
+        {
+            return Ok((to_string(self, pretty)?, Vec::new()));
+        }
+        // Found before serde_json turns them into nulls
+        let non_finite = match self.config.nan_policy {
+            Some(NanPolicy::Error | NanPolicy::ReplaceWith(_)) => sanitize::non_finite_paths(self),
+            _ => Vec::new(),
+        };
+        let mut data = serde_json::to_value(self)?;
+        let stripped = if self.config.strip_experimental {
+            components::strip_experimental_data(&mut data)
+        } else {
+            Vec::new()
+        };
+        if let Some(translator) = &self.config.translator {
+            translate::translate_summary(&mut data, translator);
+        }
+        if let Some(policy) = self.config.nan_policy {
+            sanitize::sanitize_serialized(&mut data, &non_finite, policy)?;
+        }
+        Ok((to_string(&data, pretty)?, stripped))
+    }
+}
+
+impl<P: HtmlTemplate> SinglePageHtml<P> {
+    /// Names of the alert groups without an `AlertGroupSlot` in the content
+    pub(crate) fn unplaced_alert_groups(&self) -> Vec<String> {
+        if self.alert_groups.is_empty() {
+            return Vec::new();
+        }
+        let slots = components::alert_group_slot_keys(&self.content.template(None));
+        self.alert_groups
+            .keys()
+            .filter(|name| !slots.contains(&components::alert_group_data_key(name)))
+            .cloned()
+            .collect()
+    }
+}
+
 impl<P: Serialize + HtmlTemplate> SinglePageHtml<P> {
-    #[cfg(feature = "generate_html")]
-    pub fn generate_html<W: std::io::Write>(self, writer: W) -> Result<(), anyhow::Error> {
-        let json_data = serde_json::to_string(&self)?;
+    /// Apply the alert policy in the config. The alerts of the groups which
+    /// have no slot in the content are shown in the banner, so that no alert
+    /// is hidden.
+    fn prepare_alerts(mut self) -> Self {
+        for name in self.unplaced_alert_groups() {
+            if let Some(group) = self.alert_groups.remove(&name) {
+                self.alerts.alerts.extend(group.alerts.alerts);
+            }
+        }
+        if self.config.normalize_alerts {
+            for alerts in std::iter::once(&mut self.alerts).chain(
+                self.alert_groups
+                    .values_mut()
+                    .map(|group| &mut group.alerts),
+            ) {
+                alerts.dedup();
+                alerts.sort_by_level();
+            }
+        }
+        self
+    }
 
-        generate_html_summary(
-            &json_data,
-            self.template(None),
-            TemplateInfo::<String>::Default,
-            writer,
+    /// The json data and the template of the page, within the `serialize`
+    /// and `template` spans if the `tracing` feature is enabled
+    fn render(self) -> Result<(String, String), anyhow::Error> {
+        let page = self.prepare_alerts();
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!(
+            "serialize",
+            bytes = tracing::field::Empty,
+            content_hash = tracing::field::Empty
         )
+        .entered();
+        let (json_data, stripped) = page.json_data()?;
+        #[cfg(feature = "tracing")]
+        {
+            span.record("bytes", json_data.len());
+            // Hashing serializes the page again, so only do it if the span
+            // is recorded
+            if !span.is_disabled() {
+                if let Ok(hash) = page.content_hash() {
+                    span.record("content_hash", hash);
+                }
+            }
+            span.exit();
+        }
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("template", components = tracing::field::Empty).entered();
+        let mut template = page.template(None);
+        if !stripped.is_empty() {
+            template = components::remove_data_key_divs(&template, &stripped);
+        }
+        let (json_data, template) = check::downgrade_dangling_component_links(
+            json_data,
+            template,
+            page.config.pretty_json,
+        )?;
+        #[cfg(feature = "tracing")]
+        span.record("components", template.matches("data-component=").count());
+
+        Ok((json_data, template))
+    }
+
+    #[cfg(feature = "generate_html")]
+    pub fn generate_html<W: std::io::Write>(self, writer: W) -> Result<(), anyhow::Error> {
+        self.generate_html_with_build_files(writer, WebSummaryBuildFiles::bundled())
     }
 
     #[cfg(feature = "generate_html")]
@@ -230,14 +980,16 @@ impl<P: Serialize + HtmlTemplate> SinglePageHtml<P> {
         writer: W,
         build_files: WebSummaryBuildFiles<'_>,
     ) -> Result<(), anyhow::Error> {
-        let json_data = serde_json::to_string(&self)?;
+        let head = self.page_head();
+        let (json_data, template) = self.render()?;
 
-        generate_html_summary_with_build_files(
+        generate_html_summary_with_head(
             &json_data,
-            self.template(None),
+            template,
             TemplateInfo::<String>::Default,
             writer,
             build_files,
+            &head,
         )
     }
 
@@ -250,3 +1002,856 @@ impl<P: Serialize + HtmlTemplate> SinglePageHtml<P> {
         self.generate_html_with_build_files(writer, build_files)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use components::{DebugOnly, Experimental, HeroMetric, PlotlyChart};
+    use serde_json::json;
+    use test_utils::test_build_files;
+
+    #[derive(Serialize)]
+    struct Content {
+        timing: DebugOnly<HeroMetric>,
+    }
+
+    impl HtmlTemplate for Content {
+        fn template(&self, _: Option<String>) -> String {
+            self.timing.template(Some("timing".into()))
+        }
+    }
+
+    fn page() -> SinglePageHtml<Content> {
+        SinglePageHtml::from_content(Content {
+            timing: DebugOnly::new(HeroMetric::new("Time (s)", "12.5")),
+        })
+    }
+
+    #[test]
+    fn test_debug_toggle() {
+        let without = page();
+        assert!(!without.template(None).contains(DEBUG_TOGGLE_KEY));
+        assert!(serde_json::to_value(&without).unwrap()[DEBUG_TOGGLE_KEY].is_null());
+
+        let with = page().with_debug_toggle().with_debug_toggle();
+        assert_eq!(
+            with.template(None)
+                .matches(r#"<div data-key="_debug_toggle" data-component="JavaScript"></div>"#)
+                .count(),
+            1
+        );
+        let json = serde_json::to_value(&with).unwrap();
+        assert!(json[DEBUG_TOGGLE_KEY]["code"]
+            .as_str()
+            .unwrap()
+            .contains("data-debug"));
+        assert_eq!(json["timing"]["debug"], true);
+    }
+
+    #[test]
+    fn test_qc_banner_keys() {
+        let plain = page();
+        let plain_json = serde_json::to_value(&plain).unwrap();
+        let with = page()
+            .alerts(vec![Alert {
+                level: AlertLevel::Warn,
+                title: "Low fraction reads in cells".into(),
+                formatted_value: None,
+                message: "Ideal > 70%".into(),
+                group: None,
+            }])
+            .with_qc_banner([("Cells", Some(components::Threshold::Pass))]);
+        let template = with.template(None);
+        let json = serde_json::to_value(&with).unwrap();
+
+        assert!(template.contains(
+            r#"<div class="alert-wrapper"></div>
+<div class="container"><div data-key="_qc_banner" data-component="QcBanner"></div>"#
+        ));
+        assert_eq!(json["_qc_banner"]["overall"], "warn");
+        assert_eq!(json["timing"], plain_json["timing"]);
+        // Every data key in the template resolves against the data
+        let re = regex::Regex::new(r#"data-key="([^"]+)""#).unwrap();
+        let keys: Vec<_> = re
+            .captures_iter(&template)
+            .map(|cap| cap.get(1).unwrap().as_str())
+            .collect();
+        assert_eq!(keys, ["_qc_banner", "timing"]);
+        for key in keys {
+            assert!(check::resolve_data_key(&json, key).is_some(), "{key}");
+        }
+        assert!(with.check().is_empty());
+    }
+
+    #[test]
+    fn test_alert_levels() {
+        use std::str::FromStr;
+        assert!(AlertLevel::Error > AlertLevel::Warn);
+        assert!(AlertLevel::Warn > AlertLevel::Info);
+        assert_eq!(
+            [AlertLevel::Warn, AlertLevel::Error, AlertLevel::Info]
+                .into_iter()
+                .max(),
+            Some(AlertLevel::Error)
+        );
+        for level in [AlertLevel::Error, AlertLevel::Warn, AlertLevel::Info] {
+            assert_eq!(AlertLevel::from_str(&level.to_string()), Ok(level.clone()));
+            assert_eq!(
+                serde_json::to_value(&level).unwrap(),
+                serde_json::Value::String(level.to_string())
+            );
+        }
+        assert_eq!("INFO".parse::<AlertLevel>(), Ok(AlertLevel::Info));
+        for bad in ["warn", "WARNING", "", " ERROR"] {
+            assert_eq!(
+                bad.parse::<AlertLevel>(),
+                Err(ParseAlertLevelError(bad.into()))
+            );
+        }
+        assert_eq!(
+            "Warning".parse::<AlertLevel>().unwrap_err().to_string(),
+            r#"invalid alert level "Warning", expected ERROR, WARN or INFO"#
+        );
+    }
+
+    #[test]
+    fn test_summary_outcome() {
+        let alerts = |levels: &[AlertLevel]| Alerts {
+            alerts: levels
+                .iter()
+                .map(|level| Alert {
+                    level: level.clone(),
+                    title: "Low fraction reads in cells".into(),
+                    formatted_value: None,
+                    message: "Ideal > 70%".into(),
+                    group: None,
+                })
+                .collect(),
+        };
+        let none = alerts(&[]);
+        assert_eq!(none.max_level(), None);
+        assert!(none.count_by_level().is_empty());
+        assert_eq!(SummaryOutcome::from(&none).exit_code(), 0);
+
+        let infos = alerts(&[AlertLevel::Info, AlertLevel::Info]);
+        assert_eq!(infos.max_level(), Some(AlertLevel::Info));
+        assert_eq!(SummaryOutcome::from(&infos), SummaryOutcome::Pass);
+
+        let warns = alerts(&[AlertLevel::Info, AlertLevel::Warn]);
+        assert_eq!(SummaryOutcome::from(&warns), SummaryOutcome::Warn);
+        assert_eq!(SummaryOutcome::from(&warns).exit_code(), 2);
+        assert!(!SummaryOutcome::from(&warns).is_failure());
+
+        let errors = alerts(&[
+            AlertLevel::Warn,
+            AlertLevel::Error,
+            AlertLevel::Info,
+            AlertLevel::Warn,
+        ]);
+        assert_eq!(errors.max_level(), Some(AlertLevel::Error));
+        assert_eq!(
+            errors.count_by_level(),
+            BTreeMap::from([
+                (AlertLevel::Info, 1),
+                (AlertLevel::Warn, 2),
+                (AlertLevel::Error, 1),
+            ])
+        );
+        let outcome = SummaryOutcome::from(&errors);
+        assert_eq!(outcome, SummaryOutcome::Fail);
+        assert_eq!(outcome.exit_code(), 3);
+        assert!(outcome.is_failure());
+
+        // The grouped alerts count for the page
+        let page = page().alerts(vec![
+            errors.alerts[2].clone(),
+            errors.alerts[1].clone().in_group("antibody"),
+        ]);
+        assert_eq!(page.outcome(), SummaryOutcome::Fail);
+    }
+
+    #[test]
+    fn test_alert_groups() {
+        use components::{AlertGroupSlot, HeroMetric};
+        let alert = |title: &str| Alert {
+            level: AlertLevel::Warn,
+            title: title.into(),
+            formatted_value: None,
+            message: "Ideal > 70%".into(),
+            group: None,
+        };
+        let page = SinglePageHtml::from_content(html_content! {
+            "gex" => html_content! {
+                "cells" => HeroMetric::new("Cells", "3,487"),
+            },
+            "antibody" => html_content! {
+                "alerts" => AlertGroupSlot::new("antibody"),
+                "reads" => HeroMetric::new("Antibody reads", "1,234"),
+            },
+            "crispr" => html_content! {
+                "alerts" => AlertGroupSlot::new("crispr"),
+            },
+            "multiplexing" => html_content! {
+                "alerts" => AlertGroupSlot::new("multiplexing"),
+            },
+        })
+        .alerts(vec![
+            alert("Low antibody reads").in_group("antibody"),
+            alert("Low fraction reads in cells"),
+            alert("Low guide reads").in_group("crispr"),
+        ]);
+        let template = page.template(None);
+        let json = serde_json::to_value(&page).unwrap();
+
+        // The global alert stays in the banner, unchanged
+        assert!(template.contains(r#"<div class="alert-wrapper"></div>"#));
+        assert_eq!(
+            json["alarms"],
+            serde_json::json!({"alarms": [{
+                "level": "WARN",
+                "title": "Low fraction reads in cells",
+                "formatted_value": null,
+                "message": "Ideal > 70%",
+            }]})
+        );
+        assert_eq!(json["_alert_groups"]["antibody"]["name"], "antibody");
+        assert_eq!(
+            json["_alert_groups"]["antibody"]["alarms"][0]["title"],
+            "Low antibody reads"
+        );
+        assert_eq!(
+            json["_alert_groups"]["crispr"]["alarms"][0]["title"],
+            "Low guide reads"
+        );
+
+        // The slots are placed in the content and the one without alerts is dropped
+        let antibody = template
+            .find(r#"<div data-key="_alert_groups.antibody" data-component="AlertGroup"></div>"#)
+            .unwrap();
+        let crispr = template
+            .find(r#"<div data-key="_alert_groups.crispr" data-component="AlertGroup"></div>"#)
+            .unwrap();
+        assert!(template.find("antibody.reads").unwrap() > antibody);
+        assert!(crispr > antibody);
+        assert!(!template.contains("_alert_groups.multiplexing"));
+        assert!(page.check().is_empty(), "{:?}", page.check());
+
+        assert_eq!(page.all_alerts().len(), 3);
+        assert_eq!(page.all_alerts()[1].group.as_deref(), Some("antibody"));
+    }
+
+    #[test]
+    fn test_alert_group_without_slot() {
+        use check::{IssueKind, SummaryIssue};
+        let page = page().alerts(vec![Alert {
+            level: AlertLevel::Error,
+            title: "Low VDJ reads".into(),
+            formatted_value: None,
+            message: String::new(),
+            group: None,
+        }
+        .in_group("vdj")]);
+        assert_eq!(
+            page.check(),
+            [
+                SummaryIssue::from(IssueKind::EmptyAlertMessage("Low VDJ reads".into())),
+                SummaryIssue::from(IssueKind::AlertGroupWithoutSlot("vdj".into())),
+            ]
+        );
+        // The alert is shown in the banner instead
+        let (json, template) = page.render().unwrap();
+        let json: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json["alarms"]["alarms"][0]["title"], "Low VDJ reads");
+        assert!(json.get("_alert_groups").is_none());
+        assert!(!template.contains("_alert_groups"));
+    }
+
+    fn alert(level: AlertLevel, title: &str, value: &str) -> Alert {
+        Alert {
+            level,
+            title: title.into(),
+            formatted_value: Some(value.into()),
+            message: "Ideal > 75%".into(),
+            group: None,
+        }
+    }
+
+    fn alerts() -> Vec<Alert> {
+        vec![
+            alert(AlertLevel::Info, "Intron mode used", "true"),
+            alert(AlertLevel::Warn, "Low valid barcodes", "70.1%"),
+            alert(AlertLevel::Error, "Low valid barcodes", "50.2%"),
+            alert(AlertLevel::Warn, "Low valid barcodes", "69.3%"),
+            alert(AlertLevel::Warn, "low valid barcodes", "69.3%"),
+            alert(AlertLevel::Error, "Low mapping rate", "20%"),
+            alert(AlertLevel::Warn, "Low valid barcodes", "68.0%"),
+        ]
+    }
+
+    fn summary(alerts: &[Alert]) -> Vec<(String, String, String)> {
+        alerts
+            .iter()
+            .map(|alert| {
+                (
+                    serde_json::to_value(&alert.level)
+                        .unwrap()
+                        .as_str()
+                        .unwrap()
+                        .to_string(),
+                    alert.title.clone(),
+                    alert.formatted_value.clone().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn expected(alerts: &[(&str, &str, &str)]) -> Vec<(String, String, String)> {
+        alerts
+            .iter()
+            .map(|(a, b, c)| (a.to_string(), b.to_string(), c.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_alerts_dedup_and_sort() {
+        let mut alerts = Alerts { alerts: alerts() };
+        alerts.dedup();
+        assert_eq!(
+            summary(&alerts.alerts),
+            expected(&[
+                ("INFO", "Intron mode used", "true"),
+                ("WARN", "Low valid barcodes", "70.1%"),
+                ("ERROR", "Low valid barcodes", "50.2%"),
+                ("WARN", "low valid barcodes", "69.3%"),
+                ("ERROR", "Low mapping rate", "20%"),
+            ])
+        );
+        alerts.sort_by_level();
+        assert_eq!(
+            summary(&alerts.alerts),
+            expected(&[
+                ("ERROR", "Low valid barcodes", "50.2%"),
+                ("ERROR", "Low mapping rate", "20%"),
+                ("WARN", "Low valid barcodes", "70.1%"),
+                ("WARN", "low valid barcodes", "69.3%"),
+                ("INFO", "Intron mode used", "true"),
+            ])
+        );
+
+        // Alerts with a different message are kept
+        let mut alerts = Alerts {
+            alerts: vec![
+                alert(AlertLevel::Warn, "Low valid barcodes", "70.1%"),
+                Alert {
+                    message: "Ideal > 80%".into(),
+                    group: None,
+                    ..alert(AlertLevel::Warn, "Low valid barcodes", "70.1%")
+                },
+            ],
+        };
+        alerts.dedup();
+        assert_eq!(alerts.alerts.len(), 2);
+    }
+
+    #[test]
+    fn test_normalized_alerts() {
+        let generated_alerts = |page: SinglePageHtml<Content>| {
+            let mut html = Vec::new();
+            page.generate_html_with_build_files(&mut html, test_build_files())
+                .unwrap();
+            let data = scrape_json::scrape_json_from_html(html.as_slice()).unwrap();
+            serde_json::from_value::<Alerts>(data["alarms"].clone())
+                .unwrap()
+                .alerts
+        };
+
+        let untouched = generated_alerts(page().alerts(alerts()));
+        assert_eq!(summary(&untouched), summary(&alerts()));
+
+        let normalized = generated_alerts(page().alerts(alerts()).normalized_alerts());
+        assert_eq!(summary(&normalized).len(), 5);
+        assert_eq!(normalized[0].title, "Low valid barcodes");
+        assert_eq!(normalized[1].title, "Low mapping rate");
+        assert_eq!(normalized[4].title, "Intron mode used");
+    }
+
+    #[test]
+    fn test_pretty_json() {
+        let generate = |page: SinglePageHtml<Content>| {
+            let mut html = Vec::new();
+            page.generate_html_with_build_files(&mut html, test_build_files())
+                .unwrap();
+            String::from_utf8(html).unwrap()
+        };
+
+        // Compact by default, byte for byte
+        let compact = generate(page().alerts(alerts()));
+        let compact_data = serde_json::to_string(&page().alerts(alerts())).unwrap();
+        assert!(compact.contains(&format!(
+            "{}\n      const data = {compact_data}\n{}",
+            scrape_json::DATA_BEGIN_MARKER,
+            scrape_json::DATA_END_MARKER
+        )));
+
+        let pretty = generate(page().alerts(alerts()).pretty_json());
+        assert!(pretty.contains("      const data = {\n  \""));
+        assert!(pretty.lines().count() > compact.lines().count() + 10);
+        assert_eq!(
+            scrape_json::scrape_json_from_html(pretty.as_bytes()).unwrap(),
+            scrape_json::scrape_json_from_html(compact.as_bytes()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_sanitize_numbers() {
+        #[derive(Serialize)]
+        struct Numbers {
+            timing: HeroMetric,
+            fraction: f64,
+            plot: PlotlyChart,
+        }
+        impl HtmlTemplate for Numbers {
+            fn template(&self, _: Option<String>) -> String {
+                String::new()
+            }
+        }
+        let page = || {
+            SinglePageHtml::from_content(Numbers {
+                timing: HeroMetric::new("Time (s)", "inf"),
+                fraction: f64::NAN,
+                plot: PlotlyChart::with_layout_and_data(
+                    json!({}),
+                    vec![json!({"y": [1.5, f64::INFINITY.to_string()]})],
+                ),
+            })
+        };
+        let data = |page: SinglePageHtml<Numbers>| -> Value {
+            serde_json::from_str(&page.json_data().unwrap().0).unwrap()
+        };
+        // Not sanitized by default
+        let unsanitized = data(page());
+        assert_eq!(unsanitized["plot"]["data"][0]["y"], json!([1.5, "inf"]));
+        assert!(unsanitized["fraction"].is_null());
+
+        let nulled = data(page().sanitize_numbers(NanPolicy::ReplaceWithNull));
+        assert_eq!(nulled["plot"]["data"][0]["y"], json!([1.5, null]));
+        assert!(nulled["fraction"].is_null());
+        // The text of a metric is not a number
+        assert_eq!(nulled["timing"]["metric"], "inf");
+
+        let zeroed = data(page().sanitize_numbers(NanPolicy::ReplaceWith(0.0)));
+        assert_eq!(zeroed["plot"]["data"][0]["y"], json!([1.5, 0.0]));
+        assert_eq!(zeroed["fraction"], 0.0);
+        assert_eq!(zeroed["timing"]["metric"], "inf");
+
+        let err = page()
+            .sanitize_numbers(NanPolicy::Error)
+            .json_data()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Non-finite number NaN at fraction");
+    }
+
+    #[test]
+    fn test_strip_experimental() {
+        #[derive(Serialize)]
+        struct Preview {
+            cells: HeroMetric,
+            doublets: Experimental<HeroMetric>,
+        }
+        impl HtmlTemplate for Preview {
+            fn template(&self, _: Option<String>) -> String {
+                self.cells.template(Some("cells".into()))
+                    + &self.doublets.template(Some("doublets".into()))
+            }
+        }
+        let page = || {
+            SinglePageHtml::from_content(Preview {
+                cells: HeroMetric::new("Number of cells", "3,487"),
+                doublets: Experimental::new(HeroMetric::new("Doublet rate", "1.2%")),
+            })
+        };
+
+        let (data, template) = page().render().unwrap();
+        assert!(data.contains("Doublet rate"));
+        assert!(template.contains(r#"data-key="doublets""#));
+
+        let (data, template) = page().strip_experimental().render().unwrap();
+        let data: Value = serde_json::from_str(&data).unwrap();
+        assert!(data["doublets"].is_null());
+        assert_eq!(data["cells"]["metric"], "3,487");
+        assert!(!template.contains(r#"data-key="doublets""#));
+        assert!(template.contains(r#"data-key="cells""#));
+
+        let (data, _) = page().strip_experimental_if(false).render().unwrap();
+        assert!(data.contains("Doublet rate"));
+    }
+
+    #[test]
+    fn test_page_head() {
+        let generate = |page: SinglePageHtml<Content>, template: &str| {
+            let mut html = Vec::new();
+            page.generate_html_with_build_files(
+                &mut html,
+                WebSummaryBuildFiles::new(String::new(), String::new(), template.into()),
+            )
+            .unwrap();
+            String::from_utf8(html).unwrap()
+        };
+        let template =
+            "<head><title>[[ page.title ]]</title>[[ favicon ]]</head>[[ summary.html ]]";
+
+        let html = generate(page(), template);
+        assert!(html.starts_with("<head><title>Web Summary</title></head>"));
+
+        let page_with_head = || {
+            page()
+                .nav_bar(WsNavBar::new("Cell Ranger", "sample1", "PBMC <5k>"))
+                .favicon_data_uri("data:image/png;base64,iVBORw0KGgo=")
+        };
+        let html = generate(page_with_head(), template);
+        assert!(html.starts_with(
+            r#"<head><title>sample1 - PBMC &lt;5k&gt;</title><link rel="icon" href="data:image/png;base64,iVBORw0KGgo="></head>"#
+        ));
+
+        // Templates without the markers keep their own tags
+        let template = "<head><title>Custom</title></head>[[ summary.html ]]";
+        let html = generate(page_with_head(), template);
+        assert!(html.starts_with(template.replace("[[ summary.html ]]", "").as_str()));
+        assert!(!html.contains("sample1 - PBMC"));
+    }
+
+    #[cfg(feature = "binary_resources")]
+    #[test]
+    fn test_binary_resources_html() {
+        let csv = b"barcode,umis\nAAACCTGAGAAACCAT-1,5123\n".to_vec();
+        let mut resources = SharedResources::new();
+        let key = resources.insert_binary(csv.clone(), "text/csv");
+        assert_eq!(resources.insert_binary(csv.clone(), "text/csv"), key);
+        let mut html = Vec::new();
+        page()
+            .resources(resources)
+            .generate_html_with_build_files(&mut html, test_build_files())
+            .unwrap();
+        let html = String::from_utf8(html).unwrap();
+        assert_eq!(html.matches(&base64::encode(&csv)).count(), 1);
+        // The loader runs right after the data, before the frontend reads it
+        let loader = html.find("createObjectURL").unwrap();
+        assert!(html.find(scrape_json::DATA_END_MARKER).unwrap() < loader);
+        assert!(loader < html.find("</script>").unwrap());
+        assert!(!html.contains("data-component=\"JavaScript\""));
+
+        let data = scrape_json::scrape_json_from_html(html.as_bytes()).unwrap();
+        let binary = scrape_json::binary_resources(&data);
+        assert_eq!(binary.len(), 1);
+        assert_eq!(
+            (binary[0].key.as_str(), binary[0].size),
+            (key.as_str(), csv.len())
+        );
+        assert_eq!(binary[0].decode(&data).unwrap(), csv);
+
+        // No loader without binary resources
+        assert!(page().page_head().data_scripts.is_empty());
+    }
+
+    #[test]
+    fn test_reserved_keys() {
+        let page = page()
+            .with_debug_toggle()
+            .dark_mode()
+            .footer(components::Footer::new());
+        let data = serde_json::to_value(page).unwrap();
+        for key in data.as_object().unwrap().keys() {
+            assert!(
+                key == "timing" || RESERVED_KEYS.contains(&key.as_str()),
+                "{key} is not reserved"
+            );
+        }
+    }
+
+    #[test]
+    fn test_dark_mode() {
+        let light = page();
+        assert!(light.template(None).contains(r#"<div class="container">"#));
+        assert!(serde_json::to_value(&light)
+            .unwrap()
+            .get("_dark_mode")
+            .is_none());
+
+        let dark = page().full_width().dark_mode();
+        assert!(dark
+            .template(None)
+            .contains(r#"<div class="container-fluid dark">"#));
+        assert_eq!(serde_json::to_value(&dark).unwrap()["_dark_mode"], true);
+    }
+
+    #[test]
+    fn test_footer() {
+        let without = page();
+        let template = without.template(None);
+        assert!(!template.contains("_footer"));
+        assert_eq!(template.matches("<div class=\"container\">").count(), 1);
+        assert!(serde_json::to_value(&without)
+            .unwrap()
+            .get("_footer")
+            .is_none());
+
+        let footer = components::Footer::new()
+            .link("Support", "https://support.10xgenomics.com")
+            .note("Licensed under the 10x End User Software License")
+            .with_generation_info("9.0.1")
+            .generated_at("2024-01-31T13:45:00Z");
+        for (with, class) in [
+            (page().footer(footer.clone()), "container"),
+            (
+                page().footer(footer.clone()).full_width(),
+                "container-fluid",
+            ),
+        ] {
+            let template = with.template(None);
+            let json = serde_json::to_value(&with).unwrap();
+            // The footer follows the content, in a div of the same width
+            let footer = template
+                .find(&format!(
+                    r#"</div>
+<div class="{class}"><div data-key="_footer" data-component="Footer"></div></div>"#
+                ))
+                .unwrap();
+            assert!(template.find(r#"data-key="timing""#).unwrap() < footer);
+            assert_eq!(
+                json["_footer"],
+                serde_json::json!({
+                    "links": [{"link": "https://support.10xgenomics.com", "text": "Support"}],
+                    "note": "Licensed under the 10x End User Software License",
+                    "show_generation_info": true,
+                    "generated_at": "2024-01-31T13:45:00Z",
+                    "pipeline_version": "9.0.1",
+                })
+            );
+            // The content keys are unchanged
+            assert_eq!(
+                json["timing"],
+                serde_json::to_value(page()).unwrap()["timing"]
+            );
+            assert!(with.check().is_empty());
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    type RecordedFields = Vec<(String, String)>;
+
+    /// Records the fields of the spans and events in the order in which
+    /// they are created
+    #[cfg(feature = "tracing")]
+    #[derive(Clone, Default)]
+    struct SpanRecorder(std::sync::Arc<std::sync::Mutex<Vec<(String, RecordedFields)>>>);
+
+    #[cfg(feature = "tracing")]
+    impl SpanRecorder {
+        fn push_fields(&self, index: usize, record: impl FnOnce(&mut dyn tracing::field::Visit)) {
+            struct Visitor<'a>(&'a mut RecordedFields);
+            impl tracing::field::Visit for Visitor<'_> {
+                fn record_debug(
+                    &mut self,
+                    field: &tracing::field::Field,
+                    value: &dyn std::fmt::Debug,
+                ) {
+                    self.0
+                        .push((field.name().to_string(), format!("{value:?}")));
+                }
+            }
+            record(&mut Visitor(&mut self.0.lock().unwrap()[index].1));
+        }
+        fn fields(&self, name: &str) -> Vec<RecordedFields> {
+            self.0
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(n, _)| n == name)
+                .map(|(_, fields)| fields.clone())
+                .collect()
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    impl tracing::Subscriber for SpanRecorder {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::Id {
+            let index = {
+                let mut spans = self.0.lock().unwrap();
+                spans.push((span.metadata().name().to_string(), Vec::new()));
+                spans.len() - 1
+            };
+            self.push_fields(index, |visitor| span.record(visitor));
+            tracing::Id::from_u64(index as u64 + 1)
+        }
+        fn record(&self, span: &tracing::Id, values: &tracing::span::Record<'_>) {
+            self.push_fields(span.into_u64() as usize - 1, |visitor| {
+                values.record(visitor)
+            });
+        }
+        fn record_follows_from(&self, _: &tracing::Id, _: &tracing::Id) {}
+        fn event(&self, event: &tracing::Event<'_>) {
+            let index = {
+                let mut spans = self.0.lock().unwrap();
+                spans.push(("event".to_string(), Vec::new()));
+                spans.len() - 1
+            };
+            self.push_fields(index, |visitor| event.record(visitor));
+        }
+        fn enter(&self, _: &tracing::Id) {}
+        fn exit(&self, _: &tracing::Id) {}
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_render_spans() {
+        let build_files = test_build_files();
+        let page = || {
+            SinglePageHtml::from_content(html_content! {
+                "cells" => HeroMetric::new("Number of cells", "3,487"),
+                "reads" => HeroMetric::new("Mean reads per cell", "96,123"),
+            })
+        };
+        let recorder = SpanRecorder::default();
+        let mut html = Vec::new();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            page()
+                .generate_html_with_build_files(&mut html, build_files)
+                .unwrap();
+            let mut resources = SharedResources::new();
+            resources.insert(Value::String("data:image/png;base64,AAAA".into()));
+            resources.insert(Value::String("data:image/png;base64,AAAA".into()));
+        });
+
+        let field = |name: &str, fields: &[(String, String)]| {
+            fields
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, value)| value.clone())
+        };
+        let serialize = &recorder.fields("serialize")[0];
+        let json_data = serde_json::to_string(&page()).unwrap();
+        assert_eq!(field("bytes", serialize), Some(json_data.len().to_string()));
+        assert_eq!(
+            field("content_hash", serialize),
+            Some(page().content_hash().unwrap().to_string())
+        );
+        assert_eq!(
+            field("components", &recorder.fields("template")[0]),
+            Some("2".to_string())
+        );
+        assert_eq!(recorder.fields("resolve_includes").len(), 1);
+        let markers: Vec<_> = recorder
+            .fields("substitute_marker")
+            .iter()
+            .filter_map(|fields| field("marker", fields))
+            .collect();
+        assert_eq!(
+            markers,
+            [
+                "[[ tenx-websummary-script.min.js ]]",
+                "[[ tenx-websummary-styles.min.css ]]",
+                "[[ data.js ]]",
+                "[[ summary.html ]]"
+            ]
+            .map(|marker| format!("{marker:?}"))
+        );
+        let events = recorder.fields("event");
+        assert_eq!(events.len(), 1);
+        assert_eq!(field("key", &events[0]), Some(r#""000""#.to_string()));
+        assert_eq!(
+            field("message", &events[0]),
+            Some("Deduplicated shared resource".to_string())
+        );
+    }
+
+    #[test]
+    fn test_merge_shared_resources() {
+        let mut first = SharedResources::new();
+        assert_eq!(first.insert("a".into()), "_resources_000");
+        assert_eq!(first.insert("shared".into()), "_resources_001");
+
+        let mut second = SharedResources::new();
+        let c = second.insert("c".into());
+        let shared = second.insert("shared".into());
+        let d = second.insert("d".into());
+        let e = second.insert("e".into());
+        second.0.insert("005".into(), "f".into());
+        let mut content = serde_json::json!({
+            "plot": {"x": c, "y": [shared, d]},
+            "image": e,
+            "file": "_resources_005",
+            "text": "_resources_000 is not a reference",
+        });
+
+        let remap = first.merge(second);
+        assert_eq!(
+            remap.0,
+            BTreeMap::from(
+                [
+                    ("_resources_000", "_resources_002"),
+                    ("_resources_002", "_resources_003"),
+                    ("_resources_003", "_resources_004"),
+                ]
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+            )
+        );
+        // The shared value is stored once, and the free key 005 is kept
+        assert_eq!(remap.get("_resources_001"), None);
+        assert_eq!(remap.get("_resources_005"), None);
+        apply_remap(&mut content, &remap);
+        assert_eq!(
+            content,
+            serde_json::json!({
+                "plot": {"x": "_resources_002", "y": ["_resources_001", "_resources_003"]},
+                "image": "_resources_004",
+                "file": "_resources_005",
+                "text": "_resources_000 is not a reference",
+            })
+        );
+        let resolve =
+            |reference: &str| first.0[reference.strip_prefix("_resources_").unwrap()].clone();
+        assert_eq!(resolve("_resources_000"), "a");
+        assert_eq!(resolve("_resources_002"), "c");
+        assert_eq!(resolve("_resources_004"), "e");
+        assert_eq!(resolve("_resources_005"), "f");
+        assert_eq!(first.0.len(), 6);
+        // The next resource does not collide with the merged ones
+        assert_eq!(first.insert("g".into()), "_resources_006");
+
+        // Merging without collisions keeps the keys
+        let mut empty = SharedResources::new();
+        let mut second = SharedResources::new();
+        second.insert("a".into());
+        assert!(empty.merge(second).is_empty());
+    }
+
+    #[test]
+    fn test_resource_key() {
+        assert_eq!(resource_key(0), "000");
+        assert_eq!(resource_key(999), "999");
+        assert_eq!(resource_key(1000), "x1000");
+        assert_eq!(resource_key(12345), "xx12345");
+        let keys: Vec<_> = [5, 99, 100, 999, 1000, 1001, 9999, 10000]
+            .map(resource_key)
+            .to_vec();
+        assert!(keys.windows(2).all(|w| w[0] < w[1]), "{keys:?}");
+        for k1 in &keys {
+            for k2 in &keys {
+                assert!(k1 == k2 || !k2.starts_with(k1.as_str()), "{k1} {k2}");
+            }
+        }
+
+        let mut resources = SharedResources::new();
+        for i in 0..1001 {
+            resources.insert(i.into());
+        }
+        assert_eq!(resources.insert(1000.into()), "_resources_x1000");
+        assert_eq!(resources.insert("new".into()), "_resources_x1001");
+        let json = serde_json::to_string(&resources).unwrap();
+        assert!(json.starts_with(r#"{"000":0,"001":1,"#));
+        assert!(json.ends_with(r#""999":999,"x1000":1000,"x1001":"new"}"#));
+    }
+}
@@ -0,0 +1,307 @@
+//!
+//! A "lite" version of a web summary for email or bandwidth constrained
+//! review, in which the large embedded images are recompressed or replaced by
+//! a placeholder. This is applied to a summary after it is composed, so the
+//! code composing the content need not change.
+//!
+#![cfg(all(feature = "image_proc", feature = "image_base64_encode"))]
+
+use std::io::Cursor;
+
+use anyhow::{bail, Result};
+use image::codecs::jpeg::JpegEncoder;
+use image::DynamicImage;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::print::PLACEHOLDER_PNG;
+use crate::{image_base64_encode::Base64ImageEncoder, HtmlTemplate, SinglePageHtml};
+
+/// What to do with an image above the size limit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LiteStrategy {
+    /// Re-encode the image as a JPEG with each of the qualities in turn, and
+    /// use the first one within the limit. If none of them are, the last one
+    /// is used.
+    JpegQualityLadder(Vec<u8>),
+    /// Replace the image by a blank placeholder
+    Placeholder,
+}
+
+impl Default for LiteStrategy {
+    fn default() -> Self {
+        LiteStrategy::JpegQualityLadder(vec![85, 70, 50, 30])
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiteOptions {
+    /// Images whose decoded size in bytes is larger than this are shrunk
+    pub max_bytes_per_image: usize,
+    pub strategy: LiteStrategy,
+}
+
+impl LiteOptions {
+    pub fn new(max_bytes_per_image: usize) -> Self {
+        LiteOptions {
+            max_bytes_per_image,
+            strategy: LiteStrategy::default(),
+        }
+    }
+    pub fn strategy(mut self, strategy: LiteStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+}
+
+/// Outcome of `SinglePageHtml::lite()`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LiteReport {
+    /// Number of images which were recompressed or replaced
+    pub num_images_changed: usize,
+    /// Reduction in the size of the embedded data URIs
+    pub bytes_saved: usize,
+    /// Images which were left alone, because they could not be decoded
+    pub warnings: Vec<String>,
+}
+
+/// Serialized content of a summary along with its template
+#[derive(Debug, Clone, Serialize)]
+pub struct LiteContent {
+    #[serde(flatten)]
+    data: Map<String, Value>,
+    #[serde(skip)]
+    template: String,
+}
+
+impl HtmlTemplate for LiteContent {
+    fn template(&self, _: Option<String>) -> String {
+        self.template.clone()
+    }
+}
+
+/// Base64 payload of an image data URI
+fn image_payload(s: &str) -> Option<&str> {
+    let (header, payload) = s.strip_prefix("data:image/")?.split_once(',')?;
+    header.ends_with(";base64").then_some(payload)
+}
+
+fn encode_jpeg(img: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
+    let mut buf = Cursor::new(Vec::new());
+    // JPEG does not support an alpha channel
+    JpegEncoder::new_with_quality(&mut buf, quality)
+        .encode_image(&DynamicImage::ImageRgb8(img.to_rgb8()))?;
+    Ok(buf.into_inner())
+}
+
+/// The new data URI if the image is above the limit
+fn shrink_image(uri: &str, options: &LiteOptions) -> Result<Option<String>> {
+    let Some(payload) = image_payload(uri) else {
+        return Ok(None);
+    };
+    let bytes = base64::decode(payload)?;
+    if bytes.len() <= options.max_bytes_per_image {
+        return Ok(None);
+    }
+    let img = image::load_from_memory(&bytes)?;
+    let new_uri = match &options.strategy {
+        LiteStrategy::Placeholder => PLACEHOLDER_PNG.to_string(),
+        LiteStrategy::JpegQualityLadder(qualities) => {
+            let mut jpeg = None;
+            for &quality in qualities {
+                let encoded = encode_jpeg(&img, quality)?;
+                let fits = encoded.len() <= options.max_bytes_per_image;
+                jpeg = Some(encoded);
+                if fits {
+                    break;
+                }
+            }
+            let Some(jpeg) = jpeg else {
+                bail!("No JPEG quality specified");
+            };
+            Base64ImageEncoder::Jpeg.encode_bytes(&jpeg)
+        }
+    };
+    Ok((new_uri.len() < uri.len()).then_some(new_uri))
+}
+
+fn shrink_images(path: String, value: &mut Value, options: &LiteOptions, report: &mut LiteReport) {
+    match value {
+        Value::String(s) => match shrink_image(s, options) {
+            Ok(Some(new_uri)) => {
+                report.num_images_changed += 1;
+                report.bytes_saved += s.len() - new_uri.len();
+                *s = new_uri;
+            }
+            Ok(None) => {}
+            Err(e) => report
+                .warnings
+                .push(format!("Unable to shrink the image at {path}: {e}")),
+        },
+        Value::Object(map) => {
+            for (key, val) in map {
+                let key_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                shrink_images(key_path, val, options, report);
+            }
+        }
+        Value::Array(values) => {
+            for (i, val) in values.iter_mut().enumerate() {
+                shrink_images(format!("{path}[{i}]"), val, options, report);
+            }
+        }
+        _ => {}
+    }
+}
+
+impl<P: Serialize + HtmlTemplate> SinglePageHtml<P> {
+    /// Recompress or replace every image in the shared resources and in the
+    /// content whose size is above the limit in the options. Other resources
+    /// and the images which cannot be decoded are left as is.
+    pub fn lite(self, options: LiteOptions) -> Result<(SinglePageHtml<LiteContent>, LiteReport)> {
+        let mut report = LiteReport::default();
+
+        let mut resources = self.resources;
+        for (key, value) in resources.0.iter_mut() {
+            shrink_images(
+                format!("{}_{key}", crate::RESOURCES_PREFIX),
+                value,
+                &options,
+                &mut report,
+            );
+        }
+
+        let template = self.content.template(None);
+        let mut data = serde_json::to_value(self.content)?;
+        shrink_images(String::new(), &mut data, &options, &mut report);
+        let Value::Object(data) = data else {
+            bail!("The content of the summary should serialize to a map");
+        };
+
+        Ok((
+            SinglePageHtml {
+                nav_bar: self.nav_bar,
+                content: LiteContent { data, template },
+                alerts: self.alerts,
+                alert_groups: self.alert_groups,
+                config: self.config,
+                resources,
+                debug_toggle: self.debug_toggle,
+                dark_mode: self.dark_mode,
+                footer: self.footer,
+                schema_version: self.schema_version,
+            },
+            report,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{ImageFormat, RgbImage};
+
+    use super::*;
+    use crate::components::{BlendedImage, BlendedImageSliderSize, NumOrStr, RawImage};
+    use crate::{SharedResources, WebSummary};
+
+    /// PNG of noise, which does not compress well
+    fn noise_png(size: u32) -> String {
+        let mut state = 12345u32;
+        let img = RgbImage::from_fn(size, size, |_, _| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let [r, g, b, _] = state.to_be_bytes();
+            image::Rgb([r, g, b])
+        });
+        let mut buf = Cursor::new(Vec::new());
+        img.write_to(&mut buf, ImageFormat::Png).unwrap();
+        Base64ImageEncoder::Png.encode_bytes(buf.get_ref())
+    }
+
+    fn summary(large: &str, small: &str) -> WebSummary {
+        let blended = BlendedImage::new(
+            large.to_string(),
+            small.to_string(),
+            BlendedImageSliderSize {
+                width: NumOrStr::Num(300),
+            },
+        );
+        WebSummary::builder()
+            .section("tissue", blended)
+            .section_plain("inline", RawImage::new(large.to_string()))
+            .build()
+    }
+
+    #[test]
+    fn test_lite_recompress() -> Result<()> {
+        let large = noise_png(128);
+        let small = noise_png(2);
+        let summary = summary(&large, &small);
+        let before = serde_json::to_value(&summary)?;
+        let template = summary.template(None);
+
+        let (lite, report) = summary.lite(LiteOptions::new(20_000))?;
+        let after = serde_json::to_value(&lite)?;
+
+        assert_eq!(report.num_images_changed, 2);
+        assert!(report.warnings.is_empty());
+
+        let resources = after["_resources"].as_object().unwrap();
+        let large_key = before["tissue"]["imgA"].as_str().unwrap();
+        let small_key = before["tissue"]["imgB"].as_str().unwrap();
+        let lite_large = resources[&large_key["_resources_".len()..]]
+            .as_str()
+            .unwrap();
+        assert!(lite_large.starts_with("data:image/jpeg;base64,"));
+        assert_eq!(resources[&small_key["_resources_".len()..]], small);
+        assert_eq!(after["inline"]["encoded_image"], lite_large);
+
+        // Only the data URIs changed
+        assert_eq!(
+            report.bytes_saved,
+            serde_json::to_string(&before)?.len() - serde_json::to_string(&after)?.len()
+        );
+        assert_eq!(lite.template(None), template);
+        Ok(())
+    }
+
+    #[derive(Serialize)]
+    struct Images {
+        images: Vec<String>,
+        plot: String,
+    }
+
+    impl HtmlTemplate for Images {
+        fn template(&self, _: Option<String>) -> String {
+            String::new()
+        }
+    }
+
+    #[test]
+    fn test_lite_placeholder_and_warnings() -> Result<()> {
+        let large = noise_png(128);
+        let mut resources = SharedResources::new();
+        let plot_key = resources.insert(serde_json::json!({"data": [{"x": [1, 2]}]}));
+        let garbage = format!("data:image/png;base64,{}", "A".repeat(40_000));
+        let summary = SinglePageHtml::from_content(Images {
+            images: vec![large.clone(), garbage.clone()],
+            plot: plot_key,
+        })
+        .resources(resources);
+        let before = serde_json::to_value(&summary)?;
+
+        let (lite, report) =
+            summary.lite(LiteOptions::new(20_000).strategy(LiteStrategy::Placeholder))?;
+        let data = serde_json::to_value(&lite)?;
+        assert_eq!(report.num_images_changed, 1);
+        assert_eq!(data["images"][0], PLACEHOLDER_PNG);
+        assert_eq!(data["images"][1], garbage);
+        assert_eq!(report.bytes_saved, large.len() - PLACEHOLDER_PNG.len());
+        assert_eq!(report.warnings.len(), 1);
+        assert!(report.warnings[0].starts_with("Unable to shrink the image at images[1]"));
+        assert_eq!(data["_resources"], before["_resources"]);
+        Ok(())
+    }
+}
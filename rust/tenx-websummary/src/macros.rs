@@ -1,11 +1,72 @@
-/// Shortcut to implement ReactComponent
+/// Shortcut to implement ReactComponent. The list form also defines a
+/// `REACT_COMPONENTS` const in the calling module with the
+/// `(rust type, component name)` of each struct, so that the registered
+/// components can be enumerated, e.g. by `kitchen_sink::component_manifest()`.
+/// Entries of the list can have attributes such as `#[cfg(...)]`.
+///
+/// The expansion uses fully qualified paths, so that crates with their own
+/// react components in a custom JS bundle can use the macro without any
+/// other imports.
+///
+/// ```
+/// #[derive(serde::Serialize)]
+/// struct SampleSheet {
+///     samples: Vec<String>,
+/// }
+///
+/// #[derive(serde::Serialize)]
+/// struct RunSummary {
+///     run_id: String,
+/// }
+///
+/// tenx_websummary::react_component!(SampleSheet, "SampleSheet");
+/// tenx_websummary::react_component! {
+///     RunSummary => "RunSummary",
+/// }
+///
+/// use tenx_websummary::components::ReactComponent;
+/// assert_eq!(SampleSheet::component_name(), "SampleSheet");
+/// assert_eq!(REACT_COMPONENTS, &[("RunSummary", "RunSummary")]);
+/// ```
 #[macro_export]
 macro_rules! react_component {
     ($struct_name:ident, $comp_name:literal) => {
-        impl ReactComponent for $struct_name {
+        impl $crate::components::ReactComponent for $struct_name {
             fn component_name() -> &'static str {
                 $comp_name
             }
         }
     };
+    ($($(#[$attr:meta])* $struct_name:ident => $comp_name:literal),+ $(,)?) => {
+        $($(#[$attr])* $crate::react_component!($struct_name, $comp_name);)+
+
+        /// The `(rust type, component name)` of the components registered
+        /// in this module using `react_component!`
+        pub const REACT_COMPONENTS: &[(&str, &str)] =
+            &[$($(#[$attr])* (stringify!($struct_name), $comp_name)),+];
+    };
+}
+
+/// Assemble the content of a page without defining a struct. Each component
+/// is serialized under its key, which is also its data key, in the order of
+/// declaration. Expands to a `BuiltContent`, which can itself be nested.
+///
+/// ```
+/// use tenx_websummary::components::HeroMetric;
+/// use tenx_websummary::{html_content, SinglePageHtml};
+///
+/// let content = html_content! {
+///     "cells" => HeroMetric::new("Number of cells", "3,487"),
+///     "reads" => html_content! {
+///         "mean" => HeroMetric::new("Mean reads per cell", "96,123"),
+///     },
+/// };
+/// let page = SinglePageHtml::from_content(content);
+/// ```
+#[macro_export]
+macro_rules! html_content {
+    ($($key:expr => $component:expr),* $(,)?) => {
+        $crate::builder::BuiltContent::new()
+            $(.section($key, $component))*
+    };
 }
@@ -0,0 +1,483 @@
+//!
+//! Build the metrics in a summary from a flat metrics JSON, such as the
+//! `metrics_summary_json.json` written by the pipelines, using a declarative
+//! mapping from the metric keys to the components.
+//!
+
+use anyhow::{bail, format_err, Error};
+use serde_json::{Map, Value};
+
+use crate::{
+    components::{HeroMetric, TableMetric, Threshold},
+    number_format::{parse_number, NumberLocale},
+    Alert, AlertLevel,
+};
+
+/// Shown in place of a missing metric with `MissingKeys::Placeholder`
+pub const MISSING_PLACEHOLDER: &str = "N/A";
+
+/// How the metric value is displayed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricFormat {
+    /// Rounded to an integer with thousands separators, e.g. `3,487`
+    Count,
+    /// A fraction displayed as a percentage with one decimal, e.g. `0.936` is
+    /// shown as `93.6%`
+    Percent,
+    /// A number with the given number of decimals
+    Float(usize),
+}
+
+impl MetricFormat {
+    pub fn format(self, value: f64) -> String {
+        self.format_with_locale(value, NumberLocale::EnUs)
+    }
+
+    /// The value formatted with the separators of the locale. Only counts
+    /// have thousands separators, as with `format()`.
+    pub fn format_with_locale(self, value: f64, locale: NumberLocale) -> String {
+        match self {
+            MetricFormat::Count => locale.format_count(value),
+            MetricFormat::Percent => locale.format_percent(value, 1),
+            MetricFormat::Float(decimals) => locale.format_float(value, decimals),
+        }
+    }
+}
+
+/// Whether larger or smaller values of the metric are better
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThresholdDirection {
+    AtLeast,
+    AtMost,
+}
+
+/// Values beyond `warn` are flagged as warnings and values beyond `error` as
+/// errors. The thresholds are compared against the raw value, e.g. the
+/// fraction for a `MetricFormat::Percent`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThresholdRule {
+    pub direction: ThresholdDirection,
+    pub warn: f64,
+    pub error: f64,
+}
+
+impl ThresholdRule {
+    pub fn at_least(warn: f64, error: f64) -> Self {
+        ThresholdRule {
+            direction: ThresholdDirection::AtLeast,
+            warn,
+            error,
+        }
+    }
+    pub fn at_most(warn: f64, error: f64) -> Self {
+        ThresholdRule {
+            direction: ThresholdDirection::AtMost,
+            warn,
+            error,
+        }
+    }
+    pub fn evaluate(&self, value: f64) -> Threshold {
+        let beyond = |limit: f64| match self.direction {
+            ThresholdDirection::AtLeast => value < limit,
+            ThresholdDirection::AtMost => value > limit,
+        };
+        if beyond(self.error) {
+            Threshold::Error
+        } else if beyond(self.warn) {
+            Threshold::Warn
+        } else {
+            Threshold::Pass
+        }
+    }
+    fn ideal(&self, format: MetricFormat, locale: NumberLocale) -> String {
+        let op = match self.direction {
+            ThresholdDirection::AtLeast => ">=",
+            ThresholdDirection::AtMost => "<=",
+        };
+        format!(
+            "Ideal {op} {}",
+            format.format_with_locale(self.warn, locale)
+        )
+    }
+}
+
+/// Where the metric is shown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetricDestination {
+    Hero,
+    /// A row in the table with the given section name
+    TableRow(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricEntry {
+    /// Key in the metrics JSON
+    pub key: String,
+    pub name: String,
+    pub format: MetricFormat,
+    pub threshold: Option<ThresholdRule>,
+    pub destination: MetricDestination,
+}
+
+impl MetricEntry {
+    pub fn hero(key: &str, name: &str, format: MetricFormat) -> Self {
+        MetricEntry {
+            key: key.to_string(),
+            name: name.to_string(),
+            format,
+            threshold: None,
+            destination: MetricDestination::Hero,
+        }
+    }
+    pub fn table_row(section: &str, key: &str, name: &str, format: MetricFormat) -> Self {
+        MetricEntry {
+            destination: MetricDestination::TableRow(section.to_string()),
+            ..MetricEntry::hero(key, name, format)
+        }
+    }
+    pub fn threshold(mut self, rule: ThresholdRule) -> Self {
+        self.threshold = Some(rule);
+        self
+    }
+}
+
+/// What to do when a metric in the mapping is absent or null
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissingKeys {
+    #[default]
+    Error,
+    Skip,
+    /// Show `MISSING_PLACEHOLDER` as the value
+    Placeholder,
+}
+
+/// Components built by `MetricsMapping::apply()`
+#[derive(Debug, Clone, Default)]
+pub struct MappedSections {
+    pub hero_metrics: Vec<HeroMetric>,
+    /// Tables in the order in which their sections first appear in the mapping
+    pub tables: Vec<(String, TableMetric)>,
+    /// An alert for every metric which fails its threshold
+    pub alerts: Vec<Alert>,
+}
+
+impl MappedSections {
+    pub fn table(&self, section: &str) -> Option<&TableMetric> {
+        self.tables
+            .iter()
+            .find_map(|(name, table)| (name == section).then_some(table))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MetricsMapping {
+    entries: Vec<MetricEntry>,
+    missing_keys: MissingKeys,
+    locale: NumberLocale,
+}
+
+impl MetricsMapping {
+    pub fn new() -> Self {
+        MetricsMapping::default()
+    }
+
+    pub fn entry(mut self, entry: MetricEntry) -> Self {
+        self.entries.push(entry);
+        self
+    }
+
+    pub fn missing_keys(mut self, missing_keys: MissingKeys) -> Self {
+        self.missing_keys = missing_keys;
+        self
+    }
+
+    /// Separators of the formatted values, `NumberLocale::EnUs` by default
+    pub fn locale(mut self, locale: NumberLocale) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    pub fn apply(&self, metrics: &Map<String, Value>) -> Result<MappedSections, Error> {
+        let mut sections = MappedSections::default();
+        for entry in &self.entries {
+            let (formatted, threshold) = match metrics.get(&entry.key) {
+                None | Some(Value::Null) => match self.missing_keys {
+                    MissingKeys::Error => bail!("Metric {:?} is missing", entry.key),
+                    MissingKeys::Skip => continue,
+                    MissingKeys::Placeholder => (MISSING_PLACEHOLDER.to_string(), None),
+                },
+                Some(value) => {
+                    let value = value
+                        .as_f64()
+                        .or_else(|| value.as_str().and_then(parse_number))
+                        .ok_or_else(|| {
+                            format_err!("Metric {:?} is not a number: {value}", entry.key)
+                        })?;
+                    let formatted = entry.format.format_with_locale(value, self.locale);
+                    let threshold = entry.threshold.map(|rule| rule.evaluate(value));
+                    let alert_level = match threshold {
+                        Some(Threshold::Warn) => Some(AlertLevel::Warn),
+                        Some(Threshold::Error) => Some(AlertLevel::Error),
+                        _ => None,
+                    };
+                    if let Some(level) = alert_level {
+                        sections.alerts.push(Alert {
+                            level,
+                            title: entry.name.clone(),
+                            formatted_value: Some(formatted.clone()),
+                            message: entry.threshold.unwrap().ideal(entry.format, self.locale),
+                            group: None,
+                        });
+                    }
+                    (formatted, threshold)
+                }
+            };
+            match &entry.destination {
+                MetricDestination::Hero => sections.hero_metrics.push(HeroMetric {
+                    name: entry.name.clone(),
+                    metric: formatted,
+                    threshold,
+                    comparison: None,
+                    id: None,
+                }),
+                MetricDestination::TableRow(section) => {
+                    let row = (entry.name.clone(), formatted);
+                    match sections.tables.iter_mut().find(|(name, _)| name == section) {
+                        Some((_, table)) => table.rows.push(row),
+                        None => sections.tables.push((
+                            section.clone(),
+                            TableMetric {
+                                rows: vec![row],
+                                row_ids: Vec::new(),
+                            },
+                        )),
+                    }
+                }
+            }
+        }
+        Ok(sections)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn metrics() -> Map<String, Value> {
+        let Value::Object(map) = json!({
+            "filtered_bcs": 3487,
+            "filtered_bcs_conf_mapped_barcoded_reads_cum_frac": 0.5,
+            "good_bc_frac": 0.936,
+            "total_reads": 335174516,
+            "multi_cdna_pcr_dupe_reads_frac": "0.561",
+            "median_genes_per_cell": 1850.0,
+            "mean_reads_per_cell": 96121.87,
+        }) else {
+            unreachable!()
+        };
+        map
+    }
+
+    fn mapping() -> MetricsMapping {
+        MetricsMapping::new()
+            .entry(MetricEntry::hero(
+                "filtered_bcs",
+                "Estimated Number of Cells",
+                MetricFormat::Count,
+            ))
+            .entry(
+                MetricEntry::hero(
+                    "filtered_bcs_conf_mapped_barcoded_reads_cum_frac",
+                    "Fraction Reads in Cells",
+                    MetricFormat::Percent,
+                )
+                .threshold(ThresholdRule::at_least(0.7, 0.4)),
+            )
+            .entry(
+                MetricEntry::table_row(
+                    "Sequencing",
+                    "good_bc_frac",
+                    "Valid Barcodes",
+                    MetricFormat::Percent,
+                )
+                .threshold(ThresholdRule::at_least(0.75, 0.5)),
+            )
+            .entry(MetricEntry::table_row(
+                "Sequencing",
+                "total_reads",
+                "Number of Reads",
+                MetricFormat::Count,
+            ))
+            .entry(MetricEntry::table_row(
+                "Cells",
+                "mean_reads_per_cell",
+                "Mean Reads per Cell",
+                MetricFormat::Float(1),
+            ))
+            .entry(MetricEntry::table_row(
+                "Sequencing",
+                "multi_cdna_pcr_dupe_reads_frac",
+                "Sequencing Saturation",
+                MetricFormat::Percent,
+            ))
+    }
+
+    #[test]
+    fn test_metric_format() {
+        assert_eq!(MetricFormat::Count.format(3487.0), "3,487");
+        assert_eq!(MetricFormat::Count.format(335174516.4), "335,174,516");
+        assert_eq!(MetricFormat::Count.format(-1234.0), "-1,234");
+        assert_eq!(MetricFormat::Count.format(12.0), "12");
+        assert_eq!(MetricFormat::Percent.format(0.936), "93.6%");
+        assert_eq!(MetricFormat::Float(2).format(1.0 / 3.0), "0.33");
+    }
+
+    #[test]
+    fn test_metric_format_locale() {
+        let formats = [
+            MetricFormat::Count,
+            MetricFormat::Percent,
+            MetricFormat::Float(2),
+        ];
+        let formatted = |value: f64, locale: NumberLocale| {
+            formats.map(|format| format.format_with_locale(value, locale))
+        };
+        assert_eq!(
+            formatted(12345.678, NumberLocale::EnUs),
+            ["12,346", "1234567.8%", "12345.68"]
+        );
+        assert_eq!(
+            formatted(12345.678, NumberLocale::EuComma),
+            ["12.346", "1234567,8%", "12345,68"]
+        );
+        assert_eq!(
+            formatted(12345.678, NumberLocale::Plain),
+            ["12346", "1234567.8%", "12345.68"]
+        );
+        assert_eq!(
+            formatted(0.936, NumberLocale::EuComma),
+            ["1", "93,6%", "0,94"]
+        );
+    }
+
+    #[test]
+    fn test_apply_locale() -> Result<(), Error> {
+        let mut metrics = metrics();
+        // Values written as strings in either convention
+        metrics.insert("total_reads".into(), "335.174.516".into());
+        let sections = mapping().locale(NumberLocale::EuComma).apply(&metrics)?;
+        assert_eq!(sections.hero_metrics[0].metric, "3.487");
+        let table = &sections.tables[0].1;
+        assert_eq!(table.rows[0].1, "93,6%");
+        assert_eq!(table.rows[1].1, "335.174.516");
+        assert_eq!(sections.alerts[0].formatted_value.as_deref(), Some("50,0%"));
+        assert_eq!(sections.alerts[0].message, "Ideal >= 70,0%");
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply() -> Result<(), Error> {
+        let sections = mapping().apply(&metrics())?;
+        assert_eq!(
+            sections
+                .hero_metrics
+                .iter()
+                .map(|m| (m.name.as_str(), m.metric.as_str(), m.threshold.clone()))
+                .collect::<Vec<_>>(),
+            [
+                ("Estimated Number of Cells", "3,487", None),
+                ("Fraction Reads in Cells", "50.0%", Some(Threshold::Warn)),
+            ]
+        );
+        assert_eq!(
+            sections.tables.iter().map(|(s, _)| s).collect::<Vec<_>>(),
+            ["Sequencing", "Cells"]
+        );
+        assert_eq!(
+            sections.table("Sequencing").unwrap().rows,
+            [
+                ("Valid Barcodes".into(), "93.6%".into()),
+                ("Number of Reads".into(), "335,174,516".into()),
+                ("Sequencing Saturation".into(), "56.1%".into()),
+            ]
+        );
+        assert_eq!(
+            sections.table("Cells").unwrap().rows,
+            [("Mean Reads per Cell".into(), "96121.9".into())]
+        );
+
+        // Only the failing threshold raises an alert
+        assert_eq!(sections.alerts.len(), 1);
+        let alert = &sections.alerts[0];
+        assert!(matches!(alert.level, AlertLevel::Warn));
+        assert_eq!(alert.title, "Fraction Reads in Cells");
+        assert_eq!(alert.formatted_value.as_deref(), Some("50.0%"));
+        assert_eq!(alert.message, "Ideal >= 70.0%");
+        Ok(())
+    }
+
+    #[test]
+    fn test_threshold_rule() {
+        let rule = ThresholdRule::at_most(0.1, 0.2);
+        assert_eq!(rule.evaluate(0.05), Threshold::Pass);
+        assert_eq!(rule.evaluate(0.15), Threshold::Warn);
+        assert_eq!(rule.evaluate(0.25), Threshold::Error);
+
+        let mut metrics = metrics();
+        metrics.insert("good_bc_frac".into(), json!(0.3));
+        let alerts = mapping().apply(&metrics).unwrap().alerts;
+        assert_eq!(alerts.len(), 2);
+        assert!(matches!(alerts[1].level, AlertLevel::Error));
+        assert_eq!(alerts[1].title, "Valid Barcodes");
+    }
+
+    #[test]
+    fn test_missing_keys() {
+        let mut metrics = metrics();
+        metrics.remove("total_reads");
+        metrics.insert("good_bc_frac".into(), Value::Null);
+
+        let err = mapping().apply(&metrics).unwrap_err();
+        assert_eq!(err.to_string(), r#"Metric "good_bc_frac" is missing"#);
+
+        let skipped = mapping()
+            .missing_keys(MissingKeys::Skip)
+            .apply(&metrics)
+            .unwrap();
+        assert_eq!(
+            skipped.table("Sequencing").unwrap().rows,
+            [("Sequencing Saturation".into(), "56.1%".into())]
+        );
+
+        let placeholder = mapping()
+            .missing_keys(MissingKeys::Placeholder)
+            .apply(&metrics)
+            .unwrap();
+        assert_eq!(
+            placeholder.table("Sequencing").unwrap().rows,
+            [
+                ("Valid Barcodes".into(), MISSING_PLACEHOLDER.into()),
+                ("Number of Reads".into(), MISSING_PLACEHOLDER.into()),
+                ("Sequencing Saturation".into(), "56.1%".into()),
+            ]
+        );
+        // Missing metrics do not raise alerts
+        assert_eq!(placeholder.alerts.len(), 1);
+    }
+
+    #[test]
+    fn test_not_a_number() {
+        let mut metrics = metrics();
+        metrics.insert("total_reads".into(), json!("many"));
+        let err = mapping()
+            .missing_keys(MissingKeys::Skip)
+            .apply(&metrics)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"Metric "total_reads" is not a number: "many""#
+        );
+    }
+}
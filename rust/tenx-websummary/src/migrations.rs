@@ -0,0 +1,220 @@
+//!
+//! Migrate the data scraped from summaries generated by older releases to
+//! the serialized shape of the components in this release, e.g. for QA
+//! tooling which deserializes the data into the current structs.
+//!
+//! The data of a `SinglePageHtml` holds its `SCHEMA_VERSION` under
+//! `SCHEMA_VERSION_KEY`. Bump the version whenever the serde shape of a
+//! component changes, and register the migration from the previous version
+//! in `MIGRATIONS`.
+//!
+
+use std::io::Read;
+
+use anyhow::{bail, format_err, Context, Error};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::scrape_json::scrape_json_from_html;
+
+/// Key of the schema version in the data of a `SinglePageHtml`
+pub const SCHEMA_VERSION_KEY: &str = "_schema_version";
+
+/// Version of the serialized shape of the components in this release
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Version of the data generated before the schema version was embedded
+pub const UNVERSIONED_SCHEMA_VERSION: u32 = 1;
+
+/// A migration of the data from one version to the next
+pub struct Migration {
+    /// The version migrated from, to `from + 1`
+    pub from: u32,
+    pub description: &'static str,
+    pub migrate: fn(&mut Value),
+}
+
+/// The migrations in the order of their versions, one per version from
+/// `UNVERSIONED_SCHEMA_VERSION` up to the current one
+pub const MIGRATIONS: &[Migration] = &[Migration {
+    from: 1,
+    description: "Tabs serialize the titles of the tabs",
+    migrate: add_tab_titles,
+}];
+
+/// Version 2: the `Tabs` serialize their `titles` along with the `tab_data`.
+/// The titles were only in the template before, so the migrated titles are
+/// empty, one per tab. The `Tabs` are the objects with the full shape of
+/// version 1, i.e. a `tab_data` array and nothing else, so that other
+/// objects with a `tab_data` field are left alone.
+fn add_tab_titles(data: &mut Value) {
+    match data {
+        Value::Object(map) => {
+            if let (1, Some(Value::Array(tabs))) = (map.len(), map.get("tab_data")) {
+                let titles = vec![Value::String(String::new()); tabs.len()];
+                map.insert("titles".into(), Value::Array(titles));
+            }
+            map.values_mut().for_each(add_tab_titles);
+        }
+        Value::Array(values) => values.iter_mut().for_each(add_tab_titles),
+        _ => {}
+    }
+}
+
+/// The schema version of the data, `UNVERSIONED_SCHEMA_VERSION` if the data
+/// does not have one
+pub fn schema_version(data: &Value) -> Result<u32, Error> {
+    match data.get(SCHEMA_VERSION_KEY) {
+        None => Ok(UNVERSIONED_SCHEMA_VERSION),
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| format_err!("Invalid {SCHEMA_VERSION_KEY} {version} in the data")),
+    }
+}
+
+/// Apply the migrations from `from_version` to `SCHEMA_VERSION` to the data
+/// and update its schema version. Fails for the versions newer than the one
+/// of this release, which it cannot know the shape of.
+pub fn migrate_to_current(data: &mut Value, from_version: u32) -> Result<(), Error> {
+    if from_version > SCHEMA_VERSION {
+        bail!(
+            "The data has schema version {from_version}, newer than the version \
+             {SCHEMA_VERSION} supported by this release of tenx-websummary. \
+             Update the crate to read it."
+        );
+    }
+    if from_version < UNVERSIONED_SCHEMA_VERSION {
+        bail!("Invalid schema version {from_version}");
+    }
+    for migration in MIGRATIONS.iter().filter(|m| m.from >= from_version) {
+        (migration.migrate)(data);
+    }
+    if let Value::Object(map) = data {
+        map.insert(SCHEMA_VERSION_KEY.into(), SCHEMA_VERSION.into());
+    }
+    Ok(())
+}
+
+/// Scrape the data from the html of a summary, migrate it from its schema
+/// version and deserialize it
+pub fn scrape_and_migrate<T: DeserializeOwned, R: Read>(reader: R) -> Result<T, Error> {
+    let mut data = scrape_json_from_html(reader)?;
+    let version = schema_version(&data)?;
+    migrate_to_current(&mut data, version)?;
+    serde_json::from_value(data)
+        .with_context(|| format!("Failed to deserialize the data migrated from version {version}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{HeroMetric, Tabs};
+    use crate::test_utils::test_build_files;
+    use crate::{html_content, SinglePageHtml};
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct ScrapedTabs {
+        tab_data: Vec<Value>,
+        titles: Vec<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct ScrapedPage {
+        #[serde(rename = "_schema_version")]
+        schema_version: u32,
+        tabs: ScrapedTabs,
+    }
+
+    fn html(data: &Value) -> String {
+        let mut out = Vec::new();
+        crate::generate_html_summary_with_build_files(
+            &data.to_string(),
+            String::new(),
+            crate::TemplateInfo::<String>::Default,
+            &mut out,
+            test_build_files(),
+        )
+        .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn current_data() -> Value {
+        let page = SinglePageHtml::from_content(html_content! {
+            "tabs" => Tabs::new()
+                .tab("Cells", HeroMetric::new("Number of cells", "3,487"))
+                .tab("Reads", HeroMetric::new("Number of reads", "335,174,516")),
+        });
+        serde_json::to_value(page).unwrap()
+    }
+
+    #[test]
+    fn test_migrations_are_contiguous() {
+        let froms: Vec<u32> = MIGRATIONS.iter().map(|m| m.from).collect();
+        assert_eq!(
+            froms,
+            (UNVERSIONED_SCHEMA_VERSION..SCHEMA_VERSION).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_current_data() {
+        let data = current_data();
+        assert_eq!(data[SCHEMA_VERSION_KEY], SCHEMA_VERSION);
+        let page: ScrapedPage = scrape_and_migrate(html(&data).as_bytes()).unwrap();
+        assert_eq!(page.schema_version, SCHEMA_VERSION);
+        assert_eq!(page.tabs.titles, ["Cells", "Reads"]);
+    }
+
+    #[test]
+    fn test_migrate_unversioned_data() {
+        // Data generated before the tab titles and the schema version
+        let mut old = current_data();
+        let map = old.as_object_mut().unwrap();
+        map.remove(SCHEMA_VERSION_KEY);
+        map["tabs"].as_object_mut().unwrap().remove("titles");
+        assert!(serde_json::from_value::<ScrapedPage>(old.clone()).is_err());
+        assert_eq!(schema_version(&old).unwrap(), UNVERSIONED_SCHEMA_VERSION);
+
+        let page: ScrapedPage = scrape_and_migrate(html(&old).as_bytes()).unwrap();
+        assert_eq!(page.schema_version, SCHEMA_VERSION);
+        assert_eq!(page.tabs.titles, ["", ""]);
+        assert_eq!(
+            Value::Array(page.tabs.tab_data),
+            current_data()["tabs"]["tab_data"]
+        );
+
+        // Only the objects with the shape of the tabs are migrated
+        let mut data = json!({"tabs": {"tab_data": []}, "other": {"tab_data": [1], "n": 2}});
+        migrate_to_current(&mut data, UNVERSIONED_SCHEMA_VERSION).unwrap();
+        assert_eq!(data["tabs"], json!({"tab_data": [], "titles": []}));
+        assert_eq!(data["other"], json!({"tab_data": [1], "n": 2}));
+
+        // Migrating current data is a no-op
+        let mut data = current_data();
+        migrate_to_current(&mut data, SCHEMA_VERSION).unwrap();
+        assert_eq!(data, current_data());
+    }
+
+    #[test]
+    fn test_future_version() {
+        let data = json!({SCHEMA_VERSION_KEY: SCHEMA_VERSION + 1, "tabs": {}});
+        let error = scrape_and_migrate::<ScrapedPage, _>(html(&data).as_bytes()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "The data has schema version {}, newer than the version {SCHEMA_VERSION} \
+                 supported by this release of tenx-websummary. Update the crate to read it.",
+                SCHEMA_VERSION + 1
+            )
+        );
+
+        let invalid = json!({SCHEMA_VERSION_KEY: "2"});
+        assert_eq!(
+            schema_version(&invalid).unwrap_err().to_string(),
+            r#"Invalid _schema_version "2" in the data"#
+        );
+    }
+}
@@ -0,0 +1,202 @@
+//!
+//! Several independent summaries in one html, e.g. a run before and after a
+//! change, toggled by buttons at the top of the page. The data of each
+//! summary is embedded under its own variable `data_<name>` within its own
+//! markers, so that `scrape_json::scrape_named()` can read it back. The page
+//! data refers to these variables by name, and the data keys of each
+//! summary are prefixed with its name.
+//!
+//! Shared resources are resolved from the top level of the page data, so
+//! summaries with shared resources cannot be combined.
+//!
+
+use std::collections::HashSet;
+use std::io::Write;
+
+use anyhow::{bail, Error};
+use itertools::Itertools;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::components::ButtonSelectorProps;
+use crate::generate_html::{generate_html_summary_with_build_files, TemplateInfo};
+use crate::scrape_json::{named_data_begin_marker, named_data_end_marker, named_data_prefix};
+use crate::RESERVED_KEYS;
+use crate::{HtmlTemplate, SinglePageHtml, WebSummaryBuildFiles};
+
+/// Key of the props of the buttons which toggle between the summaries
+pub const SUMMARY_SELECTOR_KEY: &str = "_summary_selector";
+
+/// Names are used in javascript variable names and in data keys, so only
+/// ascii letters, digits and `_` are allowed
+fn check_name(name: &str, seen: &mut HashSet<String>) -> Result<(), Error> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        bail!("Invalid summary name {name:?}, expected only ascii letters, digits and _");
+    }
+    if name == SUMMARY_SELECTOR_KEY || RESERVED_KEYS.contains(&name) {
+        bail!("The summary name {name} is reserved");
+    }
+    if !seen.insert(name.to_string()) {
+        bail!("The summary name {name} is used more than once");
+    }
+    Ok(())
+}
+
+/// Write the summaries into one html, the first of which is shown initially.
+/// The template of the build files must declare the data in a line with
+/// `const data = [[ data.js ]]`, as the bundled template does.
+pub fn generate_multi_html<P, W>(
+    summaries: Vec<(String, SinglePageHtml<P>)>,
+    writer: W,
+    build_files: WebSummaryBuildFiles<'_>,
+) -> Result<(), Error>
+where
+    P: Serialize + HtmlTemplate,
+    W: Write,
+{
+    if summaries.is_empty() {
+        bail!("No summaries to write");
+    }
+    let mut seen = HashSet::new();
+    let mut rendered = Vec::with_capacity(summaries.len());
+    for (name, page) in summaries {
+        check_name(&name, &mut seen)?;
+        if !page.resources.is_empty() {
+            bail!("The summary {name} has shared resources, which cannot be combined");
+        }
+        let (json_data, template) = page.render()?;
+        let template = template.replace(r#"data-key=""#, &format!(r#"data-key="{name}."#));
+        rendered.push((name, json_data, template));
+    }
+
+    let data_line_re = Regex::new(r"(?m)^.*\[\[ data\.js \]\].*$").unwrap();
+    let Some(data_line) = data_line_re.find(&build_files.template_html) else {
+        bail!("The template does not have a line with [[ data.js ]]");
+    };
+    let data_line = data_line.as_str();
+    let Some(indent) = data_line.find("const data = [[ data.js ]]") else {
+        bail!("The template does not declare the data as `const data = [[ data.js ]]`");
+    };
+    let indent = &data_line[..indent];
+
+    let named_data = rendered
+        .iter()
+        .map(|(name, json_data, _)| {
+            format!(
+                "{}\n{indent}{}{json_data}\n{}\n",
+                named_data_begin_marker(name),
+                named_data_prefix(name),
+                named_data_end_marker(name)
+            )
+        })
+        .join("");
+    let page_data = format!(
+        "{{{:?}: {}, {}}}",
+        SUMMARY_SELECTOR_KEY,
+        serde_json::to_string(&ButtonSelectorProps::default())?,
+        rendered
+            .iter()
+            .map(|(name, _, _)| format!("{name:?}: data_{name}"))
+            .join(", ")
+    );
+    // The static template takes precedence over the one in the build files
+    let template_html = build_files.template_html.replacen(
+        data_line,
+        &format!(
+            "{named_data}{}",
+            data_line.replace("[[ data.js ]]", &page_data)
+        ),
+        1,
+    );
+
+    let options = rendered
+        .iter()
+        .map(|(name, _, template)| format!(r#"<div name="{name}">{template}</div>"#))
+        .join("\n");
+    let summary = format!(
+        r#"<div data-key="{SUMMARY_SELECTOR_KEY}" data-component="ButtonSelector">{options}</div>"#
+    );
+
+    generate_html_summary_with_build_files(
+        "",
+        summary,
+        TemplateInfo::<String>::Static(template_html),
+        writer,
+        build_files,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::HeroMetric;
+    use crate::scrape_json::{scrape_json_from_html, scrape_named};
+    use crate::test_utils::test_build_files;
+    use crate::WebSummary;
+
+    fn summary(cells: &str) -> WebSummary {
+        WebSummary::builder()
+            .nav("Cell Ranger", "sample1", "PBMC")
+            .section_plain("cells", HeroMetric::new("Number of cells", cells))
+            .build()
+    }
+
+    fn generate(summaries: Vec<(&str, WebSummary)>) -> Result<String, Error> {
+        let mut html = Vec::new();
+        generate_multi_html(
+            summaries
+                .into_iter()
+                .map(|(name, summary)| (name.to_string(), summary))
+                .collect(),
+            &mut html,
+            test_build_files(),
+        )?;
+        Ok(String::from_utf8(html).unwrap())
+    }
+
+    #[test]
+    fn test_two_summaries() {
+        let html = generate(vec![
+            ("before", summary("3,487")),
+            ("after", summary("3,601")),
+        ])
+        .unwrap();
+
+        let before = scrape_named(html.as_bytes(), "before").unwrap();
+        let after = scrape_named(html.as_bytes(), "after").unwrap();
+        assert_eq!(before, serde_json::to_value(summary("3,487")).unwrap());
+        assert_eq!(after, serde_json::to_value(summary("3,601")).unwrap());
+        assert!(scrape_named(html.as_bytes(), "during").is_err());
+        // There is no single blob of data
+        assert!(scrape_json_from_html(html.as_bytes()).is_err());
+
+        assert!(html.contains(
+            r#"      const data = {"_summary_selector": {"type":"fullWidth"}, "before": data_before, "after": data_after}"#
+        ));
+        assert!(html.contains(&format!(
+            r#"<div data-key="{SUMMARY_SELECTOR_KEY}" data-component="ButtonSelector"><div name="before">"#
+        )));
+        assert!(html.contains(r#"<div data-key="before.cells" data-component="Metric">"#));
+        assert!(html.contains(r#"<div data-key="after.cells" data-component="Metric">"#));
+        assert!(!html.contains(r#"data-key="cells""#));
+    }
+
+    #[test]
+    fn test_invalid_summaries() {
+        let err = |names: &[&str]| {
+            generate(names.iter().map(|name| (*name, summary("1"))).collect())
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(err(&[]), "No summaries to write");
+        assert_eq!(
+            err(&["before", "before"]),
+            "The summary name before is used more than once"
+        );
+        assert_eq!(
+            err(&["run 1"]),
+            r#"Invalid summary name "run 1", expected only ascii letters, digits and _"#
+        );
+        assert_eq!(err(&["sample"]), "The summary name sample is reserved");
+    }
+}
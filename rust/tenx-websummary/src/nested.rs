@@ -0,0 +1,214 @@
+//!
+//! Embed a previously generated summary as a section of another summary,
+//! e.g. a roll-up of several samples. The inner summary is its data, e.g.
+//! from `scrape_json::scrape_json_from_html()`, and its template. The data
+//! is stored under a single key of the `NestedSummary`, and the data keys of
+//! the inner template are prefixed with the data key of the `NestedSummary`.
+//!
+//! The shared resources of the inner summary are moved into those of the
+//! outer summary by `add_to_shared_resource()`, with the references in the
+//! inner data remapped to the new keys. Until then, the references are
+//! replaced by the resources when serialized. Summaries with binary shared
+//! resources cannot be nested.
+//!
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Error};
+use regex::{Captures, Regex};
+use serde::ser::SerializeMap;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::binary_resources::binary_resource_infos;
+use crate::components::{join_data_key, remove_data_key_divs};
+use crate::RESERVED_KEYS;
+use crate::{
+    replace_strings, AddToSharedResource, HtmlTemplate, SharedResources, RESOURCES_PREFIX,
+};
+
+/// Key of the data of the inner summary within the `NestedSummary`
+pub const NESTED_SUMMARY_KEY: &str = "summary";
+
+#[derive(Debug, Clone)]
+pub struct NestedSummary {
+    /// The data of the inner summary without the nav bar, the alerts etc.
+    content: Value,
+    template: String,
+    /// Shared resources of the inner summary which are not yet merged, keyed
+    /// by their reference string
+    resources: BTreeMap<String, Value>,
+}
+
+impl NestedSummary {
+    /// The summary from its data and its template. The components of the
+    /// template which are not a part of the content, such as the debug
+    /// toggle, are dropped along with their data.
+    pub fn new(data: Value, template: impl Into<String>) -> Result<Self, Error> {
+        if !binary_resource_infos(&data).is_empty() {
+            bail!("Summaries with binary shared resources cannot be nested");
+        }
+        let Value::Object(mut data) = data else {
+            bail!("The data of the nested summary is not an object");
+        };
+        let resources = match data.remove(RESOURCES_PREFIX) {
+            Some(Value::Object(resources)) => resources
+                .into_iter()
+                .map(|(key, value)| (format!("{RESOURCES_PREFIX}_{key}"), value))
+                .collect(),
+            Some(_) => bail!("The shared resources of the nested summary are not an object"),
+            None => BTreeMap::new(),
+        };
+        data.retain(|key, _| !RESERVED_KEYS.contains(&key.as_str()));
+        let non_content: Vec<String> = RESERVED_KEYS.iter().map(ToString::to_string).collect();
+        Ok(NestedSummary {
+            content: Value::Object(data),
+            template: remove_data_key_divs(&template.into(), &non_content),
+            resources,
+        })
+    }
+}
+
+/// Prefix the `data-key` attributes of the tags in the template. Text which
+/// looks like the attribute outside of a tag is left as is.
+pub(crate) fn prefix_data_keys(template: &str, prefix: &str) -> String {
+    let re = Regex::new(r#"<[^<>]*?\sdata-key=""#).unwrap();
+    re.replace_all(template, |cap: &Captures| format!("{}{prefix}.", &cap[0]))
+        .into_owned()
+}
+
+impl Serialize for NestedSummary {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(1))?;
+        if self.resources.is_empty() {
+            map.serialize_entry(NESTED_SUMMARY_KEY, &self.content)?;
+        } else {
+            let mut content = self.content.clone();
+            replace_strings(&mut content, &|s| self.resources.get(s).cloned());
+            map.serialize_entry(NESTED_SUMMARY_KEY, &content)?;
+        }
+        map.end()
+    }
+}
+
+impl HtmlTemplate for NestedSummary {
+    fn template(&self, data_key: Option<String>) -> String {
+        prefix_data_keys(
+            &self.template,
+            &join_data_key(&data_key, NESTED_SUMMARY_KEY),
+        )
+    }
+}
+
+impl AddToSharedResource for NestedSummary {
+    fn add_to_shared_resource(&mut self, shared_resource: &mut SharedResources) {
+        let remapped: BTreeMap<String, String> = std::mem::take(&mut self.resources)
+            .into_iter()
+            .map(|(reference, value)| (reference, shared_resource.insert(value)))
+            .collect();
+        replace_strings(&mut self.content, &|s| {
+            remapped.get(s).map(|new| Value::String(new.clone()))
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::check_data_keys;
+    use crate::components::{HeroMetric, RawImage};
+    use crate::SinglePageHtml;
+
+    const INNER_IMAGE: &str = "data:image/png;base64,AAAA";
+    const OUTER_IMAGE: &str = "data:image/png;base64,BBBB";
+
+    #[derive(Serialize)]
+    struct Inner {
+        cells: HeroMetric,
+        image: RawImage,
+    }
+
+    impl HtmlTemplate for Inner {
+        fn template(&self, _: Option<String>) -> String {
+            format!(
+                "{}\n{}",
+                self.cells.template(Some("cells".into())),
+                self.image.template(Some("image".into()))
+            )
+        }
+    }
+
+    #[derive(Serialize)]
+    struct Outer {
+        image: RawImage,
+        sample1: NestedSummary,
+    }
+
+    impl HtmlTemplate for Outer {
+        fn template(&self, _: Option<String>) -> String {
+            format!(
+                "{}\n{}",
+                self.image.template(Some("image".into())),
+                self.sample1.template(Some("sample1".into()))
+            )
+        }
+    }
+
+    fn inner() -> NestedSummary {
+        let mut resources = SharedResources::new();
+        let page = SinglePageHtml::from_content(Inner {
+            cells: HeroMetric::new("Number of cells", "3,487"),
+            image: RawImage::new(INNER_IMAGE.into()).with_shared_resource(&mut resources),
+        })
+        .resources(resources)
+        .with_debug_toggle();
+        let (json_data, template) = page.render().unwrap();
+        NestedSummary::new(serde_json::from_str(&json_data).unwrap(), template).unwrap()
+    }
+
+    #[test]
+    fn test_nested_summary() {
+        let mut resources = SharedResources::new();
+        let image = RawImage::new(OUTER_IMAGE.into()).with_shared_resource(&mut resources);
+        let sample1 = inner().with_shared_resource(&mut resources);
+        let page = SinglePageHtml::from_content(Outer { image, sample1 }).resources(resources);
+        let (json_data, template) = page.render().unwrap();
+        let data: Value = serde_json::from_str(&json_data).unwrap();
+
+        assert!(template
+            .contains(r#"<div data-key="sample1.summary.cells" data-component="Metric"></div>"#));
+        assert!(!template.contains("_debug_toggle"));
+        assert_eq!(check_data_keys(&template, &data), vec![]);
+
+        // The resource of the inner summary no longer collides with the first
+        // resource of the outer summary
+        let nested = &data["sample1"][NESTED_SUMMARY_KEY];
+        assert_eq!(nested["image"]["encoded_image"], "_resources_001");
+        assert_eq!(data[RESOURCES_PREFIX]["000"], OUTER_IMAGE);
+        assert_eq!(data[RESOURCES_PREFIX]["001"], INNER_IMAGE);
+        assert!(nested.get("sample").is_none());
+        assert!(nested.get(RESOURCES_PREFIX).is_none());
+    }
+
+    #[test]
+    fn test_nested_summary_unmerged() {
+        let json = serde_json::to_value(inner()).unwrap();
+        assert_eq!(
+            json[NESTED_SUMMARY_KEY]["image"]["encoded_image"],
+            INNER_IMAGE
+        );
+        assert_eq!(json[NESTED_SUMMARY_KEY]["cells"]["metric"], "3,487");
+        assert!(NestedSummary::new(Value::Null, "").is_err());
+    }
+
+    #[test]
+    fn test_prefix_data_keys() {
+        let template = r#"<div class="x" data-key="a.b[0]" data-component="Metric">data-key="text"</div>
+<div data-key="c"><span></span></div>"#;
+        assert_eq!(
+            prefix_data_keys(template, "outer.summary"),
+            r#"<div class="x" data-key="outer.summary.a.b[0]" data-component="Metric">data-key="text"</div>
+<div data-key="outer.summary.c"><span></span></div>"#
+        );
+    }
+}
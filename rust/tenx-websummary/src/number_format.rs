@@ -0,0 +1,236 @@
+//!
+//! Locale aware formatting of the metric values, e.g. `3,487.5` in the US
+//! and `3.487,5` in most of Europe. The locale is always passed explicitly,
+//! the functions without a locale use `NumberLocale::EnUs`.
+//!
+//! `parse_number()` reads numbers written in either convention, so that
+//! values formatted elsewhere are still recognized as numbers.
+//!
+
+use itertools::Itertools;
+
+/// Separators of the formatted numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberLocale {
+    /// Comma thousands separator and decimal point, e.g. `10,000.5`
+    #[default]
+    EnUs,
+    /// Period thousands separator and decimal comma, e.g. `10.000,5`
+    EuComma,
+    /// No thousands separator and a decimal point, e.g. `10000.5`
+    Plain,
+}
+
+impl NumberLocale {
+    pub fn thousands_separator(self) -> Option<char> {
+        match self {
+            NumberLocale::EnUs => Some(','),
+            NumberLocale::EuComma => Some('.'),
+            NumberLocale::Plain => None,
+        }
+    }
+
+    pub fn decimal_separator(self) -> char {
+        match self {
+            NumberLocale::EnUs | NumberLocale::Plain => '.',
+            NumberLocale::EuComma => ',',
+        }
+    }
+
+    /// Rewrite a number formatted by rust, such as `-1234.50`, with the
+    /// separators of the locale. Anything else is returned as is.
+    pub fn localize(self, plain: &str) -> String {
+        self.localize_number(plain, true)
+    }
+
+    fn localize_number(self, plain: &str, grouped: bool) -> String {
+        let (sign, unsigned) = match plain.strip_prefix('-') {
+            Some(unsigned) => ("-", unsigned),
+            None => ("", plain),
+        };
+        let (int_part, frac_part) = match unsigned.split_once('.') {
+            Some((int_part, frac_part)) => (int_part, Some(frac_part)),
+            None => (unsigned, None),
+        };
+        let all_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+        if int_part.is_empty() || !all_digits(int_part) || !frac_part.is_none_or(all_digits) {
+            return plain.to_string();
+        }
+        let grouped = match self.thousands_separator().filter(|_| grouped) {
+            Some(separator) => int_part
+                .as_bytes()
+                .rchunks(3)
+                .rev()
+                .map(|chunk| std::str::from_utf8(chunk).unwrap())
+                .join(&separator.to_string()),
+            None => int_part.to_string(),
+        };
+        match frac_part {
+            Some(frac_part) => format!("{sign}{grouped}{}{frac_part}", self.decimal_separator()),
+            None => format!("{sign}{grouped}"),
+        }
+    }
+
+    /// Rounded to an integer with thousands separators, e.g. `3,487`
+    pub fn format_count(self, value: f64) -> String {
+        let value = value.round();
+        // Avoid `-0`
+        let value = if value == 0.0 { 0.0 } else { value };
+        self.localize(&format!("{value:.0}"))
+    }
+
+    /// The number with the given number of decimals and thousands
+    /// separators, e.g. `1,234.57`
+    pub fn format_grouped(self, value: f64, decimals: usize) -> String {
+        self.localize(&format!("{value:.decimals$}"))
+    }
+
+    /// The number with the given number of decimals, without thousands
+    /// separators as for the other metrics which are not counts, e.g.
+    /// `1234.57` or `1234,57`
+    pub fn format_float(self, value: f64, decimals: usize) -> String {
+        self.localize_number(&format!("{value:.decimals$}"), false)
+    }
+
+    /// A fraction as a percentage with the given number of decimals and
+    /// without thousands separators, e.g. `0.936` as `93.6%`
+    pub fn format_percent(self, fraction: f64, decimals: usize) -> String {
+        format!("{}%", self.format_float(100.0 * fraction, decimals))
+    }
+}
+
+/// Read a number written either with a decimal point or a decimal comma,
+/// with or without thousands separators, e.g. `1234.5`, `1,234.5` or
+/// `1.234,5`. A single separator followed by exactly three digits, as in
+/// `1,234`, is a thousands separator unless the number parses as a plain
+/// number, so `1.234` is read as `1.234`. Returns None for anything else,
+/// including percentages and non-finite values.
+pub fn parse_number(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if !value.contains(',') {
+        if let Ok(x) = value.parse::<f64>() {
+            return x.is_finite().then_some(x);
+        }
+    }
+    let unsigned = value.strip_prefix('-').unwrap_or(value);
+    let last_comma = unsigned.rfind(',');
+    let last_period = unsigned.rfind('.');
+    let decimal = match (last_comma, last_period) {
+        (Some(comma), Some(period)) => Some(if comma > period { ',' } else { '.' }),
+        (Some(comma), None) => {
+            let single = unsigned.matches(',').count() == 1;
+            (single && unsigned.len() - comma - 1 != 3).then_some(',')
+        }
+        // Several periods, since a single one parses as a plain number
+        (None, Some(_)) => None,
+        (None, None) => return None,
+    };
+    let grouping = match decimal {
+        Some(',') => '.',
+        Some(_) => ',',
+        None if unsigned.contains(',') => ',',
+        None => '.',
+    };
+    let (int_part, frac_part) = match decimal {
+        Some(decimal) => unsigned.rsplit_once(decimal)?,
+        None => (unsigned, ""),
+    };
+    let all_digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    let mut groups = int_part.split(grouping);
+    let first = groups.next()?;
+    let groups_ok = match int_part.contains(grouping) {
+        true => {
+            all_digits(first)
+                && first.len() <= 3
+                && groups.all(|group| group.len() == 3 && all_digits(group))
+        }
+        false => all_digits(int_part),
+    };
+    if !groups_ok || (decimal.is_some() && !all_digits(frac_part)) {
+        return None;
+    }
+    let digits: String = int_part.chars().filter(char::is_ascii_digit).collect();
+    let sign = if value.starts_with('-') { "-" } else { "" };
+    format!(
+        "{sign}{digits}.{}",
+        if frac_part.is_empty() { "0" } else { frac_part }
+    )
+    .parse()
+    .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_count() {
+        let counts = [3487.0, 335174516.4, -1234.0, 12.0, -0.2];
+        let formatted = |locale: NumberLocale| counts.map(|x| locale.format_count(x));
+        assert_eq!(
+            formatted(NumberLocale::EnUs),
+            ["3,487", "335,174,516", "-1,234", "12", "0"]
+        );
+        assert_eq!(
+            formatted(NumberLocale::EuComma),
+            ["3.487", "335.174.516", "-1.234", "12", "0"]
+        );
+        assert_eq!(
+            formatted(NumberLocale::Plain),
+            ["3487", "335174516", "-1234", "12", "0"]
+        );
+    }
+
+    #[test]
+    fn test_format_percent_and_float() {
+        assert_eq!(NumberLocale::EnUs.format_percent(0.936, 1), "93.6%");
+        assert_eq!(NumberLocale::EuComma.format_percent(0.936, 1), "93,6%");
+        assert_eq!(NumberLocale::Plain.format_percent(12.5, 0), "1250%");
+        assert_eq!(NumberLocale::EnUs.format_percent(12.5, 0), "1250%");
+
+        assert_eq!(NumberLocale::EnUs.format_float(10000.5, 1), "10000.5");
+        assert_eq!(NumberLocale::EuComma.format_float(10000.5, 1), "10000,5");
+        assert_eq!(NumberLocale::Plain.format_float(10000.5, 1), "10000.5");
+        assert_eq!(NumberLocale::EnUs.format_grouped(10000.5, 1), "10,000.5");
+        assert_eq!(NumberLocale::EuComma.format_grouped(10000.5, 1), "10.000,5");
+        assert_eq!(NumberLocale::Plain.format_grouped(10000.5, 1), "10000.5");
+        assert_eq!(NumberLocale::EuComma.format_float(-1.0 / 3.0, 2), "-0,33");
+        assert_eq!(NumberLocale::EuComma.localize("N/A"), "N/A");
+    }
+
+    #[test]
+    fn test_parse_number() {
+        for (value, expected) in [
+            ("1,234.5", Some(1234.5)),
+            ("1.234,5", Some(1234.5)),
+            ("-1,234,567.25", Some(-1234567.25)),
+            ("1.234.567", Some(1234567.0)),
+            ("1,234", Some(1234.0)),
+            ("1.234", Some(1.234)),
+            ("0,5", Some(0.5)),
+            ("12345,5", Some(12345.5)),
+            ("1234.5", Some(1234.5)),
+            (" 3487 ", Some(3487.0)),
+            ("1e3", Some(1000.0)),
+            ("12,34,567", None),
+            ("1,234.5.6", None),
+            ("1.234,5,6", None),
+            ("93.6%", None),
+            ("inf", None),
+            ("", None),
+            ("N/A", None),
+        ] {
+            assert_eq!(parse_number(value), expected, "{value:?}");
+        }
+        for locale in [
+            NumberLocale::EnUs,
+            NumberLocale::EuComma,
+            NumberLocale::Plain,
+        ] {
+            assert_eq!(
+                parse_number(&locale.format_grouped(-1234567.25, 2)),
+                Some(-1234567.25)
+            );
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//!
+//! Constructors of the plotly charts found in most summaries: the barcode
+//! rank plot, histograms, box plots per group and spatial scatter plots.
+//! They use the default config, and the same margins, fonts and colors as
+//! `VegaLitePlot::theme()`, so that the plots look the same across pipelines.
+//!
+
+use serde_json::{json, Value};
+
+use crate::colors::{categorical_color, cluster_color, Colorscale, FONT_FAMILY, GRID_COLOR};
+use crate::components::{merge_json, PlotlyChart};
+
+/// Color of the background barcodes in the barcode rank plot
+const BACKGROUND_COLOR: &str = "#bdbdbd";
+
+/// Layout shared by all the plots, overridden by the layout of each plot
+fn layout(overlay: Value) -> Value {
+    let mut layout = json!({
+        "font": {"family": FONT_FAMILY, "size": 12},
+        "margin": {"l": 60, "r": 20, "t": 30, "b": 50},
+        "hovermode": "closest",
+        "showlegend": false,
+        "plot_bgcolor": "#ffffff",
+        "paper_bgcolor": "#ffffff",
+    });
+    merge_json(&mut layout, overlay);
+    layout
+}
+
+/// A linear axis with a grid and the title, if any
+fn axis(title: Option<&str>) -> Value {
+    let mut axis = json!({"gridcolor": GRID_COLOR, "zeroline": false});
+    if let Some(title) = title {
+        axis["title"] = json!({"text": title, "font": {"size": 13}});
+    }
+    axis
+}
+
+fn log_axis(title: &str) -> Value {
+    let mut axis = axis(Some(title));
+    axis["type"] = json!("log");
+    axis
+}
+
+/// An axis in the coordinates of an image, without grid or labels
+fn hidden_axis() -> Value {
+    json!({
+        "showgrid": false,
+        "zeroline": false,
+        "showticklabels": false,
+    })
+}
+
+/// Line of the barcodes sorted by UMI count, shaded below
+fn barcode_rank_trace(name: &str, points: &[(f64, f64)], color: &str) -> Value {
+    let (x, y): (Vec<f64>, Vec<f64>) = points.iter().copied().unzip();
+    json!({
+        "type": "scattergl",
+        "mode": "lines",
+        "name": name,
+        "x": x,
+        "y": y,
+        "line": {"color": color, "width": 3},
+        "fill": "tozeroy",
+        "fillcolor": format!("{color}33"),
+        "hovertemplate": "Rank %{x}<br>UMIs %{y}<extra></extra>",
+    })
+}
+
+impl PlotlyChart {
+    /// The barcode rank plot: the UMI counts of the barcodes by rank, as
+    /// `(rank, umis)` points, on log-log axes. The barcodes called as cells
+    /// are in the first trace and the background in the second.
+    pub fn barcode_rank(cells: &[(f64, f64)], background: &[(f64, f64)]) -> Self {
+        PlotlyChart::with_layout_and_data(
+            layout(json!({
+                "showlegend": true,
+                "legend": {"x": 1, "xanchor": "right", "y": 1},
+                "xaxis": log_axis("Barcodes"),
+                "yaxis": log_axis("UMI counts"),
+            })),
+            vec![
+                barcode_rank_trace("Cells", cells, categorical_color(0)),
+                barcode_rank_trace("Background", background, BACKGROUND_COLOR),
+            ],
+        )
+    }
+
+    /// Histogram of the values in `bins` bins of equal width spanning the
+    /// values
+    pub fn histogram(values: &[f64], bins: usize, x_title: &str) -> Self {
+        let (min, max) = values
+            .iter()
+            .filter(|v| v.is_finite())
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &v| {
+                (min.min(v), max.max(v))
+            });
+        let mut trace = json!({
+            "type": "histogram",
+            "x": values,
+            "marker": {"color": categorical_color(0)},
+        });
+        if bins > 0 && min < max {
+            trace["xbins"] = json!({
+                "start": min,
+                "end": max,
+                "size": (max - min) / bins as f64,
+            });
+        } else {
+            trace["nbinsx"] = json!(bins);
+        }
+        PlotlyChart::with_layout_and_data(
+            layout(json!({
+                "bargap": 0.05,
+                "xaxis": axis(Some(x_title)),
+                "yaxis": axis(Some("Count")),
+            })),
+            vec![trace],
+        )
+    }
+
+    /// One box per group, e.g. per cluster, colored like the clusters in
+    /// order, see `cluster_color()`
+    pub fn box_by_group<N: AsRef<str>, V: AsRef<[f64]>>(groups: &[(N, V)]) -> Self {
+        let data = groups
+            .iter()
+            .enumerate()
+            .map(|(i, (name, values))| {
+                json!({
+                    "type": "box",
+                    "name": name.as_ref(),
+                    "y": values.as_ref(),
+                    "boxpoints": false,
+                    "marker": {"color": cluster_color(i)},
+                    "line": {"width": 1.5},
+                })
+            })
+            .collect();
+        PlotlyChart::with_layout_and_data(
+            layout(json!({
+                "xaxis": axis(None),
+                "yaxis": axis(None),
+            })),
+            data,
+        )
+    }
+
+    /// Spots at the `x` and `y` coordinates of an image, colored by a
+    /// continuous value. The y axis points down as in the image and both axes
+    /// have the same scale. The extra points of the longer arrays are ignored.
+    pub fn spatial_scatter(x: &[f64], y: &[f64], color: &[f64], colorscale: Colorscale) -> Self {
+        let mut yaxis = hidden_axis();
+        yaxis["autorange"] = json!("reversed");
+        yaxis["scaleanchor"] = json!("x");
+        PlotlyChart::with_layout_and_data(
+            layout(json!({
+                "xaxis": hidden_axis(),
+                "yaxis": yaxis,
+            })),
+            vec![json!({
+                "type": "scattergl",
+                "mode": "markers",
+                "x": x,
+                "y": y,
+                "marker": {
+                    "color": color,
+                    "colorscale": colorscale.to_value(),
+                    "size": 3,
+                    "showscale": true,
+                },
+                "hovertemplate": "%{marker.color}<extra></extra>",
+            })],
+        )
+    }
+}
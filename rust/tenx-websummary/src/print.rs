@@ -0,0 +1,320 @@
+//!
+//! Print friendly export of a web summary, in which the interactive plotly
+//! charts are replaced by static images.
+//!
+
+use anyhow::{Context, Error};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    check::resolve_data_key_mut,
+    components::{PlotlyChart, RawImage, ReactComponent},
+    generate_html_summary_with_head,
+    visit::{walk_content, ComponentVisitor},
+    HtmlTemplate, SinglePageHtml, TemplateInfo, WebSummaryBuildFiles,
+};
+
+/// A transparent 1x1 png
+pub(crate) const PLACEHOLDER_PNG: &str = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII=";
+
+/// Renders a plotly chart into a static image, e.g. using kaleido or an
+/// external process.
+pub trait PlotRenderer {
+    /// Returns the base64 encoded image as a data url given the serialized
+    /// plotly chart
+    fn render(&self, plot: &Value) -> Result<String, Error>;
+}
+
+/// Substitutes a blank placeholder image for every plot
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlaceholderRenderer;
+
+impl PlotRenderer for PlaceholderRenderer {
+    fn render(&self, _: &Value) -> Result<String, Error> {
+        Ok(PLACEHOLDER_PNG.to_string())
+    }
+}
+
+/// Collects the data keys of the plotly charts
+#[derive(Default)]
+struct PlotPaths(Vec<String>);
+
+impl ComponentVisitor for PlotPaths {
+    fn visit_plotly(&mut self, _: &PlotlyChart, path: &str) {
+        self.0.push(path.to_string());
+    }
+}
+
+/// Replace the plotly charts which are rendered as their own components in
+/// the template by images from the renderer. The data keys are unchanged, so
+/// the rest of the page is unaffected. Plots which are a part of another
+/// component are left as is. Returns the new template and the number of plots
+/// which were substituted.
+pub fn substitute_plots(
+    data: &mut Value,
+    template: &str,
+    renderer: &dyn PlotRenderer,
+) -> Result<(String, usize), Error> {
+    let plot_component = |key: &str| {
+        format!(
+            r#"data-key="{key}" data-component="{}""#,
+            PlotlyChart::component_name()
+        )
+    };
+    let mut template = template.to_string();
+    let mut num_substituted = 0;
+    let mut plot_paths = PlotPaths::default();
+    walk_content(data, &mut plot_paths);
+    for path in plot_paths.0 {
+        let component = plot_component(&path);
+        if !template.contains(&component) {
+            continue;
+        }
+        let plot = resolve_data_key_mut(data, &path).unwrap();
+        let image = renderer
+            .render(plot)
+            .with_context(|| format!("Failed to render the plot at {path}"))?;
+        *plot = serde_json::to_value(RawImage::new(image))?;
+        template = template.replace(
+            &component,
+            &format!(
+                r#"data-key="{path}" data-component="{}""#,
+                RawImage::component_name()
+            ),
+        );
+        num_substituted += 1;
+    }
+    Ok((template, num_substituted))
+}
+
+impl<P: Serialize + HtmlTemplate> SinglePageHtml<P> {
+    /// Data and template of the page from `render()`, so that the config of
+    /// the page applies, with the plots replaced by static images
+    fn print_data_and_template(
+        self,
+        renderer: &dyn PlotRenderer,
+    ) -> Result<(String, String), Error> {
+        let pretty = self.config.pretty_json;
+        let (json_data, template) = self.render()?;
+        let mut data: Value = serde_json::from_str(&json_data)?;
+        let (template, _) = substitute_plots(&mut data, &template, renderer)?;
+        let json_data = if pretty {
+            serde_json::to_string_pretty(&data)?
+        } else {
+            serde_json::to_string(&data)?
+        };
+        Ok((json_data, template))
+    }
+
+    /// Similar to `generate_html`, but the plotly charts are replaced by
+    /// static images from the renderer, for printing or exporting to PDF.
+    #[cfg(feature = "generate_html")]
+    pub fn generate_print_html<W: std::io::Write>(
+        self,
+        writer: W,
+        renderer: &dyn PlotRenderer,
+    ) -> Result<(), Error> {
+        self.generate_print_html_with_build_files(writer, renderer, WebSummaryBuildFiles::bundled())
+    }
+
+    pub fn generate_print_html_with_build_files<W: std::io::Write>(
+        self,
+        writer: W,
+        renderer: &dyn PlotRenderer,
+        build_files: WebSummaryBuildFiles<'_>,
+    ) -> Result<(), Error> {
+        let head = self.page_head();
+        let (json_data, template) = self.print_data_and_template(renderer)?;
+        generate_html_summary_with_head(
+            &json_data,
+            template,
+            TemplateInfo::<String>::Default,
+            writer,
+            build_files,
+            &head,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use anyhow::bail;
+    use serde_json::json;
+
+    use super::*;
+    use crate::components::{Experimental, HeroMetric, Title};
+    use crate::scrape_json::scrape_json_from_html;
+    use crate::test_utils::test_build_files;
+
+    /// Always returns a 1x1 png and counts the number of calls
+    #[derive(Default)]
+    struct FakeRenderer {
+        calls: Cell<usize>,
+    }
+
+    impl PlotRenderer for FakeRenderer {
+        fn render(&self, plot: &Value) -> Result<String, Error> {
+            assert!(plot["data"].is_array());
+            self.calls.set(self.calls.get() + 1);
+            Ok(PLACEHOLDER_PNG.to_string())
+        }
+    }
+
+    struct FailingRenderer;
+
+    impl PlotRenderer for FailingRenderer {
+        fn render(&self, _: &Value) -> Result<String, Error> {
+            bail!("kaleido not found")
+        }
+    }
+
+    #[derive(Serialize)]
+    struct Titled {
+        title: Title,
+        inner: PlotlyChart,
+    }
+
+    #[derive(Serialize)]
+    struct Content {
+        metric: HeroMetric,
+        umap: PlotlyChart,
+        titled: Titled,
+    }
+
+    impl HtmlTemplate for Content {
+        fn template(&self, _: Option<String>) -> String {
+            [
+                self.metric.template(Some("metric".into())),
+                self.umap.template(Some("umap".into())),
+                self.titled.title.template(Some("titled.title".into())),
+                self.titled.inner.template(Some("titled.inner".into())),
+            ]
+            .join("\n")
+        }
+    }
+
+    fn summary() -> SinglePageHtml<Content> {
+        let plot = PlotlyChart::with_layout_and_data(
+            json!({"title": "UMAP"}),
+            vec![json!({"x": [1, 2], "y": [3, 4]})],
+        );
+        SinglePageHtml::from_content(Content {
+            metric: HeroMetric::new("Number of cells", "3,487"),
+            umap: plot.clone(),
+            titled: Titled {
+                title: Title::new("Clusters"),
+                inner: plot,
+            },
+        })
+    }
+
+    #[test]
+    fn test_substitute_plots() -> Result<(), Error> {
+        let summary = summary();
+        let original = serde_json::to_value(&summary)?;
+        let mut data = original.clone();
+        let renderer = FakeRenderer::default();
+        let (template, num_substituted) =
+            substitute_plots(&mut data, &summary.template(None), &renderer)?;
+
+        assert_eq!(num_substituted, 2);
+        assert_eq!(renderer.calls.get(), 2);
+        assert!(!template.contains(r#"data-component="Plot""#));
+        assert!(template.contains(r#"data-key="umap" data-component="RawImage""#));
+        assert!(template.contains(r#"data-key="titled.inner" data-component="RawImage""#));
+        assert_eq!(data["umap"]["encoded_image"], PLACEHOLDER_PNG);
+        assert_eq!(data["titled"]["inner"]["encoded_image"], PLACEHOLDER_PNG);
+
+        // Everything else is unchanged
+        assert_eq!(data["metric"], original["metric"]);
+        assert_eq!(data["titled"]["title"], original["titled"]["title"]);
+        assert_eq!(template.replace("RawImage", "Plot"), summary.template(None));
+        Ok(())
+    }
+
+    #[test]
+    fn test_plots_outside_template_untouched() -> Result<(), Error> {
+        let mut data = json!({"dropdown": [{"name": "a", "component": {"data": []}}]});
+        let template = r#"<div data-key="dropdown" data-component="Dropdown"></div>"#;
+        let (new_template, num_substituted) =
+            substitute_plots(&mut data, template, &FakeRenderer::default())?;
+        assert_eq!(num_substituted, 0);
+        assert_eq!(new_template, template);
+        assert_eq!(data["dropdown"][0]["component"], json!({"data": []}));
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_print_html_strip_experimental() -> Result<(), Error> {
+        #[derive(Serialize)]
+        struct WithExperimental {
+            umap: PlotlyChart,
+            draft: Experimental<PlotlyChart>,
+        }
+        impl HtmlTemplate for WithExperimental {
+            fn template(&self, _: Option<String>) -> String {
+                [
+                    self.umap.template(Some("umap".into())),
+                    self.draft.template(Some("draft".into())),
+                ]
+                .join("\n")
+            }
+        }
+        let plot = PlotlyChart::with_layout_and_data(json!({}), vec![json!({"x": [1]})]);
+        let renderer = FakeRenderer::default();
+        let mut out = Vec::new();
+        SinglePageHtml::from_content(WithExperimental {
+            umap: plot.clone(),
+            draft: Experimental::new(plot),
+        })
+        .strip_experimental()
+        .generate_print_html_with_build_files(&mut out, &renderer, test_build_files())?;
+
+        // The stripped plot is neither rendered nor in the page
+        assert_eq!(renderer.calls.get(), 1);
+        let data = scrape_json_from_html(out.as_slice())?;
+        assert_eq!(data["umap"]["encoded_image"], PLACEHOLDER_PNG);
+        assert!(data["draft"].is_null());
+        assert!(!String::from_utf8(out)?.contains(r#"data-key="draft""#));
+        Ok(())
+    }
+
+    #[cfg(feature = "binary_resources")]
+    #[test]
+    fn test_generate_print_html_binary_resources() -> Result<(), Error> {
+        let mut resources = crate::SharedResources::new();
+        resources.insert_binary(b"barcode,umis\n".to_vec(), "text/csv");
+        let mut out = Vec::new();
+        summary()
+            .resources(resources)
+            .generate_print_html_with_build_files(
+                &mut out,
+                &PlaceholderRenderer,
+                test_build_files(),
+            )?;
+        assert!(String::from_utf8(out)?.contains(&crate::binary_resources::binary_loader()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_print_html() -> Result<(), Error> {
+        let mut out = Vec::new();
+        summary().generate_print_html_with_build_files(
+            &mut out,
+            &PlaceholderRenderer,
+            test_build_files(),
+        )?;
+        let data = scrape_json_from_html(out.as_slice())?;
+        assert_eq!(data["umap"]["encoded_image"], PLACEHOLDER_PNG);
+        assert_eq!(data["metric"]["metric"], "3,487");
+
+        let err = summary()
+            .generate_print_html_with_build_files(Vec::new(), &FailingRenderer, test_build_files())
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("kaleido not found"));
+        Ok(())
+    }
+}
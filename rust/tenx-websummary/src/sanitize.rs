@@ -0,0 +1,611 @@
+//!
+//! Find the non-finite numbers in the data before it is embedded in the
+//! html, e.g. in the arrays of a plot.
+//!
+//! serde_json converts a NaN or an infinite `f64` into `null`, so the
+//! non-finite floats of a struct are found while serializing it, before they
+//! become indistinguishable from the other nulls, see `non_finite_paths()`.
+//!
+//! A `Value` can only hold them as strings, e.g. values formatted with
+//! `to_string()` before they were put into a `json!`. Such strings reach the
+//! frontend, which cannot plot them. A string which parses as a non-finite
+//! `f64`, such as `NaN`, `inf` or `-Infinity`, is treated as a non-finite
+//! number only under the keys of the numeric arrays of a plot, see
+//! `NUMERIC_KEYS`, so that the text of a metric is left alone.
+//!
+
+use std::fmt::Write;
+
+use anyhow::{bail, Error};
+use serde::ser::{self, Serialize};
+use serde_json::{Number, Value};
+
+/// What to do with a non-finite number
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NanPolicy {
+    /// Fail, naming the path of the first non-finite number
+    Error,
+    /// Replace it with `null`, which plotly shows as a gap
+    ReplaceWithNull,
+    /// Replace it with the number. A non-finite replacement is `null`.
+    ReplaceWith(f64),
+}
+
+impl NanPolicy {
+    fn replacement(self) -> Value {
+        match self {
+            NanPolicy::ReplaceWith(x) => Number::from_f64(x).map_or(Value::Null, Value::Number),
+            NanPolicy::Error | NanPolicy::ReplaceWithNull => Value::Null,
+        }
+    }
+}
+
+/// Keys of the numeric values of the plotly traces, the strings under which
+/// are numbers. The values can be nested arrays, e.g. the `z` of a heatmap.
+pub const NUMERIC_KEYS: [&str; 4] = ["x", "y", "z", "values"];
+
+fn is_non_finite_string(value: &Value) -> bool {
+    match value {
+        Value::String(s) => s.trim().parse::<f64>().is_ok_and(|x| !x.is_finite()),
+        _ => false,
+    }
+}
+
+/// Apply the policy to every non-finite number in the strings under the
+/// `NUMERIC_KEYS` of the value. The path in the error uses the same syntax
+/// as the data keys, e.g. `data[0].y[1][2]`.
+pub fn sanitize_numbers(value: &mut Value, policy: NanPolicy) -> Result<(), Error> {
+    sanitize_at(value, policy, &mut String::new(), false)
+}
+
+/// Same as `sanitize_numbers()` for a value found at `path`, which starts
+/// the paths in the error
+pub(crate) fn sanitize_numbers_at(
+    value: &mut Value,
+    policy: NanPolicy,
+    path: &str,
+) -> Result<(), Error> {
+    sanitize_at(value, policy, &mut path.to_string(), false)
+}
+
+fn sanitize_at(
+    value: &mut Value,
+    policy: NanPolicy,
+    path: &mut String,
+    numeric: bool,
+) -> Result<(), Error> {
+    if numeric && is_non_finite_string(value) {
+        if policy == NanPolicy::Error {
+            bail!("Non-finite number {value} at {}", display_path(path));
+        }
+        *value = policy.replacement();
+        return Ok(());
+    }
+    let len = path.len();
+    match value {
+        Value::Array(values) => {
+            for (i, value) in values.iter_mut().enumerate() {
+                path.push_str(&format!("[{i}]"));
+                sanitize_at(value, policy, path, numeric)?;
+                path.truncate(len);
+            }
+        }
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(key);
+                let numeric = NUMERIC_KEYS.contains(&key.as_str());
+                sanitize_at(value, policy, path, numeric)?;
+                path.truncate(len);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn display_path(path: &str) -> &str {
+    if path.is_empty() {
+        "the root"
+    } else {
+        path
+    }
+}
+
+/// A step of the path to a value in the serialized data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// The path to a non-finite float of a serialized struct, and the float
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct NonFinite {
+    pub path: Vec<PathSegment>,
+    pub value: f64,
+}
+
+impl NonFinite {
+    /// The path in the syntax of the data keys
+    fn display_path(&self) -> String {
+        let mut path = String::new();
+        for segment in &self.path {
+            match segment {
+                PathSegment::Key(key) if path.is_empty() => path.push_str(key),
+                PathSegment::Key(key) => write!(path, ".{key}").unwrap(),
+                PathSegment::Index(i) => write!(path, "[{i}]").unwrap(),
+            }
+        }
+        display_path(&path).to_string()
+    }
+
+    /// The value at the path in the serialized data
+    fn get_mut<'a>(&self, data: &'a mut Value) -> Option<&'a mut Value> {
+        self.path
+            .iter()
+            .try_fold(data, |value, segment| match segment {
+                PathSegment::Key(key) => value.get_mut(key),
+                PathSegment::Index(i) => value.get_mut(i),
+            })
+    }
+}
+
+/// The non-finite floats of the value, which serde_json serializes as `null`
+pub(crate) fn non_finite_paths<T: Serialize + ?Sized>(value: &T) -> Vec<NonFinite> {
+    let mut path = Vec::new();
+    let mut found = Vec::new();
+    // The finder itself never fails, only the `Serialize` of the value can
+    let _ = value.serialize(Finder {
+        path: &mut path,
+        found: &mut found,
+    });
+    found
+}
+
+/// Apply the policy to the non-finite floats found by `non_finite_paths()`
+/// in the data serialized from the same value, and to the strings of the
+/// data, see `sanitize_numbers()`. The floats which are no longer in the
+/// data, e.g. in stripped content, are ignored.
+pub(crate) fn sanitize_serialized(
+    data: &mut Value,
+    non_finite: &[NonFinite],
+    policy: NanPolicy,
+) -> Result<(), Error> {
+    for found in non_finite {
+        let Some(value) = found.get_mut(data).filter(|value| value.is_null()) else {
+            continue;
+        };
+        if policy == NanPolicy::Error {
+            bail!(
+                "Non-finite number {} at {}",
+                found.value,
+                found.display_path()
+            );
+        }
+        *value = policy.replacement();
+    }
+    sanitize_numbers(data, policy)
+}
+
+/// Serializer which records the paths of the non-finite floats, in the shape
+/// of the serde_json data
+struct Finder<'a> {
+    path: &'a mut Vec<PathSegment>,
+    found: &'a mut Vec<NonFinite>,
+}
+
+impl<'a> Finder<'a> {
+    fn check(self, value: f64) -> Result<(), serde_json::Error> {
+        if !value.is_finite() {
+            self.found.push(NonFinite {
+                path: self.path.clone(),
+                value,
+            });
+        }
+        Ok(())
+    }
+
+    fn compound(self, variant: Option<&str>) -> Compound<'a> {
+        if let Some(variant) = variant {
+            self.path.push(PathSegment::Key(variant.to_string()));
+        }
+        Compound {
+            path: self.path,
+            found: self.found,
+            index: 0,
+            key: None,
+            in_variant: variant.is_some(),
+        }
+    }
+}
+
+struct Compound<'a> {
+    path: &'a mut Vec<PathSegment>,
+    found: &'a mut Vec<NonFinite>,
+    index: usize,
+    /// The key of the next value of a map
+    key: Option<String>,
+    /// Whether the compound is in the key of an enum variant
+    in_variant: bool,
+}
+
+impl Compound<'_> {
+    fn child<T: Serialize + ?Sized>(
+        &mut self,
+        segment: PathSegment,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        self.path.push(segment);
+        let result = value.serialize(Finder {
+            path: self.path,
+            found: self.found,
+        });
+        self.path.pop();
+        result
+    }
+
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), serde_json::Error> {
+        self.index += 1;
+        self.child(PathSegment::Index(self.index - 1), value)
+    }
+
+    fn end(self) -> Result<(), serde_json::Error> {
+        if self.in_variant {
+            self.path.pop();
+        }
+        Ok(())
+    }
+}
+
+impl<'a> ser::Serializer for Finder<'a> {
+    type Ok = ();
+    type Error = serde_json::Error;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn serialize_bool(self, _: bool) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn serialize_i8(self, _: i8) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn serialize_i16(self, _: i16) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn serialize_i32(self, _: i32) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn serialize_i64(self, _: i64) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn serialize_u8(self, _: u8) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn serialize_u16(self, _: u16) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn serialize_u32(self, _: u32) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn serialize_u64(self, _: u64) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn serialize_f32(self, v: f32) -> Result<(), Self::Error> {
+        self.check(v.into())
+    }
+    fn serialize_f64(self, v: f64) -> Result<(), Self::Error> {
+        self.check(v)
+    }
+    fn serialize_char(self, _: char) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn serialize_str(self, _: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn serialize_bytes(self, _: &[u8]) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn serialize_none(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Self::Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.compound(None)
+            .child(PathSegment::Key(variant.to_string()), value)
+    }
+    fn serialize_seq(self, _: Option<usize>) -> Result<Compound<'a>, Self::Error> {
+        Ok(self.compound(None))
+    }
+    fn serialize_tuple(self, _: usize) -> Result<Compound<'a>, Self::Error> {
+        Ok(self.compound(None))
+    }
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Compound<'a>, Self::Error> {
+        Ok(self.compound(None))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Compound<'a>, Self::Error> {
+        Ok(self.compound(Some(variant)))
+    }
+    fn serialize_map(self, _: Option<usize>) -> Result<Compound<'a>, Self::Error> {
+        Ok(self.compound(None))
+    }
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Compound<'a>, Self::Error> {
+        Ok(self.compound(None))
+    }
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Compound<'a>, Self::Error> {
+        Ok(self.compound(Some(variant)))
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = serde_json::Error;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.element(value)
+    }
+    fn end(self) -> Result<(), Self::Error> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = serde_json::Error;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.element(value)
+    }
+    fn end(self) -> Result<(), Self::Error> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = serde_json::Error;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.element(value)
+    }
+    fn end(self) -> Result<(), Self::Error> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = serde_json::Error;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.element(value)
+    }
+    fn end(self) -> Result<(), Self::Error> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = serde_json::Error;
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Self::Error> {
+        // The keys are strings in json, e.g. `1` for an integer key
+        self.key = Some(match serde_json::to_value(key)? {
+            Value::String(key) => key,
+            key => key.to_string(),
+        });
+        Ok(())
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        let key = self.key.take().unwrap_or_default();
+        self.child(PathSegment::Key(key), value)
+    }
+    fn end(self) -> Result<(), Self::Error> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = serde_json::Error;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.child(PathSegment::Key(key.to_string()), value)
+    }
+    fn end(self) -> Result<(), Self::Error> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = serde_json::Error;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.child(PathSegment::Key(key.to_string()), value)
+    }
+    fn end(self) -> Result<(), Self::Error> {
+        Compound::end(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::Serialize;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_sanitize_numbers() {
+        let value = || {
+            json!({
+                "data": [1.5, {"y": ["inf", "Infinity text", "-Infinity"]}],
+                "x": "NaN",
+                "name": "NaN",
+            })
+        };
+
+        let mut replaced = value();
+        sanitize_numbers(&mut replaced, NanPolicy::ReplaceWithNull).unwrap();
+        assert_eq!(
+            replaced,
+            json!({
+                "data": [1.5, {"y": [null, "Infinity text", null]}],
+                "x": null,
+                "name": "NaN",
+            })
+        );
+
+        let mut replaced = value();
+        sanitize_numbers(&mut replaced, NanPolicy::ReplaceWith(0.0)).unwrap();
+        assert_eq!(
+            replaced,
+            json!({
+                "data": [1.5, {"y": [0.0, "Infinity text", 0.0]}],
+                "x": 0.0,
+                "name": "NaN",
+            })
+        );
+
+        let err = sanitize_numbers(&mut value(), NanPolicy::Error).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"Non-finite number "inf" at data[1].y[0]"#
+        );
+
+        let err = sanitize_numbers(&mut json!({"z": [["nan"]]}), NanPolicy::Error).unwrap_err();
+        assert_eq!(err.to_string(), r#"Non-finite number "nan" at z[0][0]"#);
+    }
+
+    #[test]
+    fn test_sanitize_finite() {
+        let mut value = json!({"x": [1, 2.5, "1e3", "text", null, true], "metric": "inf"});
+        let expected = value.clone();
+        sanitize_numbers(&mut value, NanPolicy::Error).unwrap();
+        assert_eq!(value, expected);
+    }
+
+    #[derive(Serialize)]
+    enum Shape {
+        Point(f64),
+        Line { length: f32 },
+    }
+
+    #[derive(Serialize)]
+    struct Data {
+        values: Vec<f64>,
+        fraction: Option<f64>,
+        shapes: Vec<Shape>,
+        by_name: BTreeMap<u32, (f64, f64)>,
+        missing: Option<f64>,
+    }
+
+    fn data() -> Data {
+        Data {
+            values: vec![1.0, f64::NAN],
+            fraction: Some(f64::INFINITY),
+            shapes: vec![Shape::Point(2.0), Shape::Line { length: f32::NAN }],
+            by_name: BTreeMap::from([(7, (0.5, f64::NEG_INFINITY))]),
+            missing: None,
+        }
+    }
+
+    #[test]
+    fn test_non_finite_floats() {
+        let non_finite = non_finite_paths(&data());
+        assert_eq!(
+            non_finite
+                .iter()
+                .map(NonFinite::display_path)
+                .collect::<Vec<_>>(),
+            [
+                "values[1]",
+                "fraction",
+                "shapes[1].Line.length",
+                "by_name.7[1]"
+            ]
+        );
+
+        let serialized = || serde_json::to_value(data()).unwrap();
+        let mut replaced = serialized();
+        sanitize_serialized(&mut replaced, &non_finite, NanPolicy::ReplaceWith(-1.0)).unwrap();
+        assert_eq!(
+            replaced,
+            json!({
+                "values": [1.0, -1.0],
+                "fraction": -1.0,
+                "shapes": [{"Point": 2.0}, {"Line": {"length": -1.0}}],
+                "by_name": {"7": [0.5, -1.0]},
+                "missing": null,
+            })
+        );
+
+        let err =
+            sanitize_serialized(&mut serialized(), &non_finite, NanPolicy::Error).unwrap_err();
+        assert_eq!(err.to_string(), "Non-finite number NaN at values[1]");
+
+        // The floats which are no longer in the data are ignored
+        let mut stripped = serialized();
+        stripped["values"] = Value::Null;
+        stripped["shapes"] = Value::Null;
+        let err = sanitize_serialized(&mut stripped, &non_finite, NanPolicy::Error).unwrap_err();
+        assert_eq!(err.to_string(), "Non-finite number inf at fraction");
+    }
+}
@@ -0,0 +1,455 @@
+//!
+//! JSON schemas of the props of the components registered using
+//! `react_component!`, so that the frontend can check its prop types against
+//! what is serialized here. `write_all_schemas()` writes one file per react
+//! component, e.g. `Metric.schema.json`.
+//!
+//! The schemas are inferred from the serialized samples of the kitchen sink,
+//! which has a sample of every registered component, without a derive on the
+//! components. They are structural: the keys of the sample are listed as
+//! properties with the type of their value. A key whose value is null in the
+//! sample accepts any value, and keys missing from the sample are not
+//! checked.
+//!
+//! Whether a property is required, and whether it may be null, is found by
+//! deserializing the sample into the rust type with the property removed or
+//! set to null. Fields which are neither an `Option` nor have a default are
+//! thus required and not nullable. This covers the objects reachable through
+//! keys from the props; the properties of the objects within arrays are not
+//! required and may be null.
+//!
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error};
+use itertools::Itertools;
+use serde_json::{json, Map, Value};
+
+use crate::components::ReactComponent;
+use crate::kitchen_sink::{checked_manifest, AcceptsProps};
+
+/// Dialect of the schemas
+pub const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Extension of the files written by `write_all_schemas()`
+pub const SCHEMA_FILE_EXTENSION: &str = "schema.json";
+
+/// Structural schema of the value, see the module documentation
+pub fn infer_schema(value: &Value) -> Value {
+    match value {
+        Value::Null => json!({}),
+        Value::Bool(_) => json!({"type": "boolean"}),
+        Value::Number(n) if n.is_f64() => json!({"type": "number"}),
+        Value::Number(_) => json!({"type": "integer"}),
+        Value::String(_) => json!({"type": "string"}),
+        Value::Array(values) => {
+            let mut schema = json!({"type": "array"});
+            if let Some(items) = values.iter().map(infer_schema).reduce(merge_schemas) {
+                schema["items"] = items;
+            }
+            schema
+        }
+        Value::Object(map) => {
+            let properties: Map<String, Value> = map
+                .iter()
+                .map(|(key, value)| (key.clone(), nullable(infer_schema(value))))
+                .collect();
+            json!({"type": "object", "properties": properties})
+        }
+    }
+}
+
+/// The schema also accepting null, for fields which may be `None`
+fn nullable(mut schema: Value) -> Value {
+    match schema.get("type").and_then(Value::as_str) {
+        Some(ty) => schema["type"] = json!([ty, "null"]),
+        None => {
+            if let Some(any_of) = schema.get_mut("anyOf").and_then(Value::as_array_mut) {
+                any_of.push(json!({"type": "null"}));
+            }
+        }
+    }
+    schema
+}
+
+/// The schema without null, for fields which may not be `None`
+fn non_nullable(schema: &mut Value) {
+    if let Some(Value::Array(types)) = schema.get_mut("type") {
+        types.retain(|ty| ty != "null");
+        if types.len() == 1 {
+            let ty = types.pop().unwrap();
+            schema["type"] = ty;
+        }
+    }
+    if let Some(any_of) = schema.get_mut("anyOf").and_then(Value::as_array_mut) {
+        any_of.retain(|schema| *schema != json!({"type": "null"}));
+    }
+}
+
+/// Mark the properties of the object at the pointer of the props which the
+/// rust type requires, and drop null from those which it does not accept as
+/// null. Recurses into the objects held by the properties.
+fn mark_required(schema: &mut Value, props: &Value, pointer: &str, accepts: AcceptsProps) {
+    let Some(object) = props.pointer(pointer).and_then(Value::as_object) else {
+        return;
+    };
+    let Some(properties) = schema.get_mut("properties").and_then(Value::as_object_mut) else {
+        return;
+    };
+    // Whether the props are accepted with the object changed
+    let accepts_with = |change: &dyn Fn(&mut Map<String, Value>)| {
+        let mut props = props.clone();
+        change(props.pointer_mut(pointer).unwrap().as_object_mut().unwrap());
+        accepts(&props)
+    };
+    let mut required = Vec::new();
+    for (key, value) in object {
+        let Some(property) = properties.get_mut(key) else {
+            continue;
+        };
+        if !accepts_with(&|object| {
+            object.remove(key);
+        }) {
+            required.push(key.clone());
+        }
+        if !value.is_null()
+            && !accepts_with(&|object| {
+                object.insert(key.clone(), Value::Null);
+            })
+        {
+            non_nullable(property);
+        }
+        let escaped = key.replace('~', "~0").replace('/', "~1");
+        mark_required(property, props, &format!("{pointer}/{escaped}"), accepts);
+    }
+    if !required.is_empty() {
+        schema["required"] = json!(required);
+    }
+}
+
+/// The types of the schema, e.g. `["string", "null"]`
+fn schema_types(schema: &Value) -> Vec<&str> {
+    match schema.get("type") {
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect(),
+        Some(ty) => ty.as_str().into_iter().collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Schema accepting the values of both schemas, e.g. for the items of an
+/// array. The types are combined, with integers accepted as numbers, and
+/// the properties of objects and the items of arrays are merged. Only the
+/// properties required by both schemas stay required.
+fn merge_schemas(a: Value, b: Value) -> Value {
+    if a == b {
+        return a;
+    }
+    if a == json!({}) || b == json!({}) {
+        return json!({});
+    }
+    let (types_a, types_b) = (schema_types(&a), schema_types(&b));
+    let is_container = |types: &[&str]| types.contains(&"object") || types.contains(&"array");
+    if types_a.is_empty()
+        || types_b.is_empty()
+        || (is_container(&types_a) && is_container(&types_b) && types_a != types_b)
+    {
+        let mut any_of = Vec::new();
+        for schema in [a, b] {
+            match schema.get("anyOf").and_then(Value::as_array) {
+                Some(schemas) => any_of.extend(schemas.iter().cloned()),
+                None => any_of.push(schema),
+            }
+        }
+        any_of.dedup();
+        return json!({ "anyOf": any_of });
+    }
+
+    let mut types: Vec<&str> = types_a.iter().chain(&types_b).copied().unique().collect();
+    if types.contains(&"number") {
+        types.retain(|ty| *ty != "integer");
+    }
+    types.sort_by_key(|ty| *ty == "null");
+    let mut merged = Map::new();
+    merged.insert(
+        "type".into(),
+        match types.as_slice() {
+            [ty] => json!(ty),
+            _ => json!(types),
+        },
+    );
+    let (a, b) = (a.as_object().unwrap(), b.as_object().unwrap());
+    if let Some(items) = match (a.get("items"), b.get("items")) {
+        (Some(x), Some(y)) => Some(merge_schemas(x.clone(), y.clone())),
+        (x, y) => x.or(y).cloned(),
+    } {
+        merged.insert("items".into(), items);
+    }
+    if a.contains_key("properties") || b.contains_key("properties") {
+        let mut properties = a
+            .get("properties")
+            .and_then(Value::as_object)
+            .cloned()
+            .unwrap_or_default();
+        for (key, schema) in b
+            .get("properties")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+        {
+            let schema = match properties.remove(key) {
+                Some(existing) => merge_schemas(existing, schema.clone()),
+                None => schema.clone(),
+            };
+            properties.insert(key.clone(), schema);
+        }
+        merged.insert("properties".into(), Value::Object(properties));
+    }
+    if let (Some(Value::Array(x)), Some(Value::Array(y))) = (a.get("required"), b.get("required")) {
+        let required: Vec<&Value> = x.iter().filter(|key| y.contains(key)).collect();
+        if !required.is_empty() {
+            merged.insert("required".into(), json!(required));
+        }
+    }
+    Value::Object(merged)
+}
+
+fn component_schema(component_name: &str, inferred: Value) -> Value {
+    let mut schema = json!({
+        "$schema": JSON_SCHEMA_DIALECT,
+        "title": component_name,
+    });
+    let Value::Object(inferred) = inferred else {
+        unreachable!()
+    };
+    schema.as_object_mut().unwrap().extend(inferred);
+    schema
+}
+
+/// The schema of the props of the react component of a registered rust
+/// type, see `all_schemas()`. Fails if the component has no sample in the
+/// kitchen sink.
+pub fn json_schema_for<T: ReactComponent>() -> Result<Value, Error> {
+    let component_name = T::component_name();
+    all_schemas()
+        .into_iter()
+        .find(|(name, _)| *name == component_name)
+        .map(|(_, schema)| schema)
+        .with_context(|| {
+            format!("The component {component_name} has no sample in the kitchen sink")
+        })
+}
+
+/// The `(react component name, schema)` of every registered component. The
+/// samples of rust types rendered by the same react component, e.g.
+/// `TitleWithTermDesc` and `CommandLine`, are merged into one schema.
+pub fn all_schemas() -> Vec<(&'static str, Value)> {
+    let mut inferred: Vec<(&'static str, Value)> = Vec::new();
+    for (entry, accepts) in checked_manifest() {
+        let mut schema = infer_schema(&entry.sample_json);
+        mark_required(&mut schema, &entry.sample_json, "", accepts);
+        match inferred
+            .iter_mut()
+            .find(|(name, _)| *name == entry.component_name)
+        {
+            Some((_, existing)) => *existing = merge_schemas(existing.take(), schema),
+            None => inferred.push((entry.component_name, schema)),
+        }
+    }
+    inferred
+        .into_iter()
+        .map(|(name, schema)| (name, component_schema(name, schema)))
+        .collect()
+}
+
+/// Write the schema of every registered component into the directory, which
+/// is created if needed, as `<component name>.schema.json`. Returns the
+/// paths of the files.
+pub fn write_all_schemas(dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, Error> {
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Unable to create the directory {}", dir.display()))?;
+    all_schemas()
+        .into_iter()
+        .map(|(component_name, schema)| {
+            let path = dir.join(format!("{component_name}.{SCHEMA_FILE_EXTENSION}"));
+            std::fs::write(&path, serde_json::to_string_pretty(&schema)?)
+                .with_context(|| format!("Unable to write {}", path.display()))?;
+            Ok(path)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::components::{CommandLine, HeroMetric, TableMetric, TitleWithTermDesc};
+    use crate::form::SpreadsheetInput;
+    use crate::kitchen_sink::{component_manifest, registered_components};
+    use crate::test_utils::ScratchDir;
+
+    /// The distinct react components, some of which render several rust
+    /// types
+    fn component_names() -> HashSet<&'static str> {
+        registered_components()
+            .into_iter()
+            .map(|(_, component_name)| component_name)
+            .collect()
+    }
+
+    /// Check the value against the subset of JSON schema used by the
+    /// inferred schemas
+    fn validate(schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+        if let Some(any_of) = schema.get("anyOf").and_then(Value::as_array) {
+            return match any_of.iter().any(|s| validate(s, value, path).is_ok()) {
+                true => Ok(()),
+                false => Err(format!("{path}: no schema of anyOf matches")),
+            };
+        }
+        let types = schema_types(schema);
+        if types.is_empty() {
+            return Ok(());
+        }
+        let matches_type = types.iter().any(|ty| match *ty {
+            "null" => value.is_null(),
+            "boolean" => value.is_boolean(),
+            "number" => value.is_number(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "string" => value.is_string(),
+            "array" => value.is_array(),
+            "object" => value.is_object(),
+            _ => false,
+        });
+        if !matches_type {
+            return Err(format!(
+                "{path}: expected {}, found {value}",
+                schema["type"]
+            ));
+        }
+        if let (Some(items), Some(values)) = (schema.get("items"), value.as_array()) {
+            for (i, value) in values.iter().enumerate() {
+                validate(items, value, &format!("{path}[{i}]"))?;
+            }
+        }
+        if let Some(map) = value.as_object() {
+            let required = schema.get("required").and_then(Value::as_array);
+            for key in required.into_iter().flatten().filter_map(Value::as_str) {
+                if !map.contains_key(key) {
+                    return Err(format!("{path}: missing {key}"));
+                }
+            }
+            for (key, property) in schema["properties"].as_object().into_iter().flatten() {
+                if let Some(value) = map.get(key) {
+                    validate(property, value, &format!("{path}.{key}"))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_schemas_validate_samples() {
+        let schemas = all_schemas();
+        let names: HashSet<_> = schemas.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names.len(), schemas.len(), "One file per component");
+        assert_eq!(names, component_names());
+        for entry in component_manifest() {
+            let (name, schema) = schemas
+                .iter()
+                .find(|(name, _)| *name == entry.component_name)
+                .unwrap();
+            assert_eq!(schema["title"], *name);
+            assert_eq!(schema["$schema"], JSON_SCHEMA_DIALECT);
+            validate(schema, &entry.sample_json, name).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_json_schema_for() {
+        let schema = json_schema_for::<HeroMetric>().unwrap();
+        assert_eq!(schema["title"], "Metric");
+        assert_eq!(schema["properties"]["name"], json!({"type": "string"}));
+        assert_eq!(
+            schema["properties"]["threshold"],
+            json!({"type": ["string", "null"]})
+        );
+        assert_eq!(schema["required"], json!(["metric", "name"]));
+
+        // Without the threshold and the comparison of the sample
+        let valid = serde_json::to_value(HeroMetric::new("Number of cells", "3,487")).unwrap();
+        validate(&schema, &valid, "").unwrap();
+        for wrong in [
+            json!({"name": "Number of cells", "metric": 3487, "threshold": "pass"}),
+            json!({"name": "Number of cells"}),
+            json!({"name": null, "metric": "3,487"}),
+        ] {
+            assert!(validate(&schema, &wrong, "").is_err(), "{wrong}");
+        }
+
+        let schema = json_schema_for::<TableMetric>().unwrap();
+        assert_eq!(
+            schema["properties"]["rows"],
+            json!({"type": "array", "items": {"type": "array", "items": {"type": "string"}}})
+        );
+        assert_eq!(schema["required"], json!(["rows"]));
+        assert_eq!(
+            schema["properties"]["row_ids"]["items"],
+            json!({}),
+            "The null id accepts any value"
+        );
+
+        let schema = json_schema_for::<SpreadsheetInput>().unwrap();
+        assert_eq!(schema["properties"]["nRows"], json!({"type": "integer"}));
+        assert_eq!(
+            schema["properties"]["readonlyColumns"],
+            json!({"type": "array", "items": {"type": "integer"}}),
+            "A default but not nullable"
+        );
+        assert!(!schema["required"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("readonlyColumns")));
+
+        // The schema of a react component rendering several rust types
+        let schema = json_schema_for::<CommandLine>().unwrap();
+        assert_eq!(schema, json_schema_for::<TitleWithTermDesc>().unwrap());
+        assert_eq!(schema["required"], json!(["data", "title"]));
+    }
+
+    #[test]
+    fn test_merge_schemas() {
+        let schema = infer_schema(&json!([
+            {"a": 1, "b": "x"},
+            {"a": 1.5, "c": null},
+            "text",
+            [1],
+        ]));
+        assert_eq!(
+            schema["items"],
+            json!({"anyOf": [
+                {
+                    "type": ["object", "string"],
+                    "properties": {
+                        "a": {"type": ["number", "null"]},
+                        "b": {"type": ["string", "null"]},
+                        "c": {},
+                    },
+                },
+                {"type": "array", "items": {"type": "integer"}},
+            ]})
+        );
+    }
+
+    #[test]
+    fn test_write_all_schemas() -> Result<(), Error> {
+        let dir = ScratchDir::new("schemas");
+        let paths = write_all_schemas(dir.path())?;
+        assert_eq!(paths.len(), component_names().len());
+        let metric: Value = serde_json::from_str(&std::fs::read_to_string(
+            dir.join(format!("Metric.{SCHEMA_FILE_EXTENSION}")),
+        )?)?;
+        assert_eq!(metric, json_schema_for::<HeroMetric>()?);
+        Ok(())
+    }
+}
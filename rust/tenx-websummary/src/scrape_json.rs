@@ -1,25 +1,254 @@
+use std::fs::File;
 use std::io::Read;
+use std::path::Path;
 
-use anyhow::{format_err, Error};
+use anyhow::{bail, format_err, Context, Error};
 use itertools::Itertools;
 use serde_json::Value;
 
+use crate::binary_resources::{binary_resource_infos, BinaryResourceInfo};
+use crate::chunked::{parse_chunk_script, CHUNK_LOADER_KEY, CHUNK_MANIFEST_KEY};
+
 const PREFIX: &str = "      const data = ";
-/// Tests are in tests/test_scrape.rs
-pub fn scrape_json_str_from_html<R: Read>(mut reader: R) -> Result<String, Error> {
+
+/// Comment line written right before the line holding the data
+pub const DATA_BEGIN_MARKER: &str = "/* tenx-websummary-data-begin */";
+/// Comment line written right after the line holding the data
+pub const DATA_END_MARKER: &str = "/* tenx-websummary-data-end */";
+
+/// Comment line written right before the data of the named summary in an
+/// html holding several summaries, see `multi_summary`
+pub fn named_data_begin_marker(name: &str) -> String {
+    format!("/* tenx-websummary-data-begin {name} */")
+}
+
+/// Comment line written right after the data of the named summary
+pub fn named_data_end_marker(name: &str) -> String {
+    format!("/* tenx-websummary-data-end {name} */")
+}
+
+/// Prefix of the line holding the data of the named summary
+pub(crate) fn named_data_prefix(name: &str) -> String {
+    format!("const data_{name} = ")
+}
+
+fn read_html<R: Read>(mut reader: R) -> Result<String, Error> {
     let mut buf = Vec::new();
     reader.read_to_end(&mut buf)?;
-    let buf = String::from_utf8(buf).unwrap();
+    Ok(String::from_utf8(buf)?)
+}
+
+/// Data enclosed within the begin and end marker lines along with the
+/// 1-based line number of its first line. Pretty printed data spans several
+/// lines.
+fn marked_data_blocks<'a>(
+    html: &'a str,
+    begin_marker: &str,
+    end_marker: &str,
+) -> Vec<(usize, &'a str)> {
+    let mut blocks = Vec::new();
+    // Line number and byte offset of the line after the last begin marker
+    let mut begin = None;
+    let mut offset = 0;
+    for (i, line) in html.split_inclusive('\n').enumerate() {
+        let marker = line.trim();
+        if marker == begin_marker {
+            begin = Some((i + 2, offset + line.len()));
+        } else if marker == end_marker {
+            if let Some((line_num, start)) = begin.take() {
+                if start < offset {
+                    blocks.push((line_num, html[start..offset].trim_end_matches(['\r', '\n'])));
+                }
+            }
+        }
+        offset += line.len();
+    }
+    blocks
+}
+
+/// Lines starting with the data prefix along with their 1-based line numbers.
+/// Used for files generated before the markers were introduced.
+fn prefixed_data_lines(html: &str) -> Vec<(usize, &str)> {
+    html.lines()
+        .enumerate()
+        .filter_map(|(i, line)| line.strip_prefix(PREFIX).map(|data| (i + 1, data)))
+        .collect()
+}
+
+fn exactly_one<'a>(candidates: Vec<(usize, &'a str)>, kind: &str) -> Result<&'a str, Error> {
+    match candidates.as_slice() {
+        [(_, data)] => Ok(data),
+        [] => bail!("No {kind} found in the html"),
+        _ => bail!(
+            "Found {} {kind}s in the html at lines {}",
+            candidates.len(),
+            candidates.iter().map(|(line_num, _)| line_num).join(", ")
+        ),
+    }
+}
+
+/// All the lines which look like they hold the data, using the prefix
+/// heuristic. Useful to diagnose ambiguous html files.
+pub fn scrape_all_candidates<R: Read>(reader: R) -> Result<Vec<String>, Error> {
+    Ok(prefixed_data_lines(&read_html(reader)?)
+        .into_iter()
+        .map(|(_, data)| data.to_string())
+        .collect())
+}
+
+/// Tests are in the `tests` module below
+pub fn scrape_json_str_from_html<R: Read>(reader: R) -> Result<String, Error> {
+    let html = read_html(reader)?;
     // Could use a crate like scraper or html5ever for html parsing instead of this simple approach
     // but that brings in a number of dependencies
-    Ok(buf
-        .lines()
-        .filter_map(|line| line.strip_prefix(PREFIX))
-        .exactly_one()
-        .map_err(|e| format_err!("{e}"))?
-        .to_string())
+    let marked = marked_data_blocks(&html, DATA_BEGIN_MARKER, DATA_END_MARKER);
+    let data = if marked.is_empty() {
+        // Html generated before the markers were introduced
+        exactly_one(prefixed_data_lines(&html), "candidate data line")?
+    } else {
+        let block = exactly_one(marked, "marked data block")?;
+        block
+            .trim_start()
+            .strip_prefix(PREFIX.trim_start())
+            .ok_or_else(|| format_err!("Marked data block does not start with {PREFIX:?}"))?
+    };
+    Ok(data.to_string())
 }
 
 pub fn scrape_json_from_html<R: Read>(reader: R) -> Result<Value, Error> {
     Ok(serde_json::from_str(&scrape_json_str_from_html(reader)?)?)
 }
+
+/// Scrape the data of the named summary from an html written by
+/// `generate_multi_html()`
+pub fn scrape_named<R: Read>(reader: R, name: &str) -> Result<Value, Error> {
+    let html = read_html(reader)?;
+    let marked = marked_data_blocks(
+        &html,
+        &named_data_begin_marker(name),
+        &named_data_end_marker(name),
+    );
+    let kind = format!("marked data block of the summary {name}");
+    let prefix = named_data_prefix(name);
+    let data = exactly_one(marked, &kind)?
+        .trim_start()
+        .strip_prefix(&prefix)
+        .ok_or_else(|| format_err!("Marked data block of {name} does not start with {prefix:?}"))?;
+    Ok(serde_json::from_str(data)?)
+}
+
+/// Scrape the data of a summary written by `generate_html_chunked()`,
+/// merging the chunks next to the html back into the data. The result is
+/// the same as scraping the summary generated without chunks. Html without
+/// chunks is scraped as is.
+pub fn scrape_chunked_json_from_html(html_path: impl AsRef<Path>) -> Result<Value, Error> {
+    let html_path = html_path.as_ref();
+    let mut data = scrape_json_from_html(
+        File::open(html_path).with_context(|| format!("Failed to open {html_path:?}"))?,
+    )?;
+    let Some(object) = data.as_object_mut() else {
+        return Ok(data);
+    };
+    object.remove(CHUNK_LOADER_KEY);
+    let Some(manifest) = object.remove(CHUNK_MANIFEST_KEY) else {
+        return Ok(data);
+    };
+    let Value::Object(manifest) = manifest else {
+        bail!("The chunk manifest in {html_path:?} is not an object");
+    };
+    let dir = html_path.parent().unwrap_or_else(|| Path::new(""));
+    for (key, file_name) in manifest {
+        let Value::String(file_name) = file_name else {
+            bail!("The chunk file of {key} in {html_path:?} is not a string");
+        };
+        let path = dir.join(file_name);
+        let script =
+            std::fs::read_to_string(&path).with_context(|| format!("Failed to open {path:?}"))?;
+        let chunk =
+            parse_chunk_script(&script).with_context(|| format!("Failed to read {path:?}"))?;
+        object.insert(key, chunk);
+    }
+    Ok(data)
+}
+
+/// The binary resources in the scraped data of a summary, with their sizes.
+/// See `binary_resources`.
+pub fn binary_resources(data: &Value) -> Vec<BinaryResourceInfo> {
+    binary_resource_infos(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{test_build_files, TEST_TEMPLATE};
+    use crate::{generate_html_summary_with_build_files, TemplateInfo};
+
+    fn render(json_data: &str, summary: &str) -> String {
+        let mut out = Vec::new();
+        generate_html_summary_with_build_files(
+            json_data,
+            summary.to_string(),
+            TemplateInfo::<String>::Default,
+            &mut out,
+            test_build_files(),
+        )
+        .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_scrape_new_format() {
+        // A code block documenting the template which contains the prefix
+        let poison = format!("<pre>\n{PREFIX}[[ data.js ]]\n</pre>");
+        let html = render(r#"{"a":1}"#, &poison);
+        assert!(html.contains(DATA_BEGIN_MARKER));
+        assert_eq!(scrape_all_candidates(html.as_bytes()).unwrap().len(), 2);
+        assert_eq!(
+            scrape_json_str_from_html(html.as_bytes()).unwrap(),
+            r#"{"a":1}"#
+        );
+    }
+
+    #[test]
+    fn test_scrape_multi_line_block() {
+        let data = "{\n  \"a\": [\n    1,\n    2\n  ]\n}";
+        let html = render(data, "<div></div>");
+        assert_eq!(scrape_json_str_from_html(html.as_bytes()).unwrap(), data);
+        assert_eq!(
+            scrape_json_from_html(html.as_bytes()).unwrap(),
+            serde_json::json!({"a": [1, 2]})
+        );
+        // Only the first line of the data has the prefix
+        assert_eq!(scrape_all_candidates(html.as_bytes()).unwrap(), vec!["{"]);
+    }
+
+    #[test]
+    fn test_scrape_old_format() {
+        let html = TEST_TEMPLATE
+            .replace("[[ data.js ]]", r#"{"a":1}"#)
+            .replace("[[ summary.html ]]", "<div></div>");
+        assert_eq!(
+            scrape_json_from_html(html.as_bytes()).unwrap(),
+            serde_json::json!({"a": 1})
+        );
+    }
+
+    #[test]
+    fn test_scrape_old_format_ambiguous() {
+        let html = TEST_TEMPLATE
+            .replace("[[ data.js ]]", r#"{"a":1}"#)
+            .replace(
+                "[[ summary.html ]]",
+                &format!("<pre>\n{PREFIX}{{}}\n</pre>"),
+            );
+        assert_eq!(
+            scrape_all_candidates(html.as_bytes()).unwrap(),
+            vec![r#"{"a":1}"#.to_string(), "{}".to_string()]
+        );
+        let err = scrape_json_str_from_html(html.as_bytes()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Found 2 candidate data lines in the html at lines 3, 7"
+        );
+    }
+}
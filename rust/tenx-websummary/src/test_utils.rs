@@ -0,0 +1,602 @@
+//!
+//! Golden file test harness for summaries built with this crate.
+//!
+//! A summary is rendered to html, the embedded JSON is scraped back out and
+//! compared against a committed golden file after normalization. The
+//! normalization sorts object keys, replaces the payloads under `_resources`
+//! by their lengths and replaces the randomly generated values such as
+//! tooltip ids. See `content_hash::VOLATILE_FIELDS`.
+//!
+//! The html fixtures are a directory of `{name}.data.json` and
+//! `{name}.summary.html` pairs. Each pair is rendered into a complete page
+//! which is compared against `{name}.expected.html`, so that a change in the
+//! html generation shows up as a diff of the fixtures.
+//!
+//! Run the tests with the environment variable `WEBSUMMARY_GOLDEN=overwrite`
+//! to (re)generate the golden files and the expected html of the fixtures.
+//!
+
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Error};
+use itertools::Itertools;
+#[cfg(feature = "generate_html")]
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::content_hash::volatile_field;
+use crate::generate_html::{
+    generate_html_summary_with_build_files, TemplateInfo, WebSummaryBuildFiles,
+};
+use crate::RESOURCES_PREFIX;
+#[cfg(feature = "generate_html")]
+use crate::{scrape_json::scrape_json_from_html, HtmlTemplate, SinglePageHtml};
+
+/// Environment variable which controls the `GoldenMode`
+pub const GOLDEN_ENV_VAR: &str = "WEBSUMMARY_GOLDEN";
+
+const MAX_REPORTED_DIFFS: usize = 20;
+const MAX_VALUE_CHARS: usize = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GoldenMode {
+    /// Compare against the existing golden file
+    Compare,
+    /// Write the golden file instead of comparing against it
+    Overwrite,
+}
+
+impl GoldenMode {
+    /// `Overwrite` if `WEBSUMMARY_GOLDEN=overwrite`, `Compare` otherwise
+    pub fn from_env() -> Self {
+        match std::env::var(GOLDEN_ENV_VAR).as_deref() {
+            Ok("overwrite") => GoldenMode::Overwrite,
+            _ => GoldenMode::Compare,
+        }
+    }
+}
+
+/// Render the summary into html and scrape the JSON data back out of it
+#[cfg(feature = "generate_html")]
+pub fn render_and_scrape<P: Serialize + HtmlTemplate>(
+    summary: SinglePageHtml<P>,
+) -> Result<Value, Error> {
+    let mut buffer = Vec::new();
+    summary.generate_html(&mut buffer)?;
+    scrape_json_from_html(buffer.as_slice())
+}
+
+/// Normalize the scraped JSON so that it is stable across runs
+pub fn normalize(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let placeholders: Vec<_> = map
+                .iter()
+                .filter_map(|(key, val)| {
+                    volatile_field(&map, key, val).map(|volatile| (key.clone(), volatile))
+                })
+                .collect();
+            Value::Object(
+                map.into_iter()
+                    .sorted_by(|(k1, _), (k2, _)| k1.cmp(k2))
+                    .map(|(key, val)| {
+                        let placeholder = placeholders
+                            .iter()
+                            .find(|(k, _)| *k == key)
+                            .map(|(_, volatile)| volatile.placeholder);
+                        let val = match (key.as_str(), val, placeholder) {
+                            (_, _, Some(placeholder)) => Value::String(placeholder.into()),
+                            (RESOURCES_PREFIX, Value::Object(resources), None) => {
+                                normalize_resources(resources)
+                            }
+                            (_, val, None) => normalize(val),
+                        };
+                        (key, val)
+                    })
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(normalize).collect()),
+        value => value,
+    }
+}
+
+fn normalize_resources(resources: Map<String, Value>) -> Value {
+    Value::Object(
+        resources
+            .into_iter()
+            .sorted_by(|(k1, _), (k2, _)| k1.cmp(k2))
+            .map(|(key, val)| {
+                let len = match val {
+                    Value::String(s) => s.len(),
+                    val => val.to_string().len(),
+                };
+                (key, Value::from(len))
+            })
+            .collect(),
+    )
+}
+
+fn truncated(value: &Value) -> String {
+    let s = value.to_string();
+    if s.chars().count() > MAX_VALUE_CHARS {
+        format!("{}...", s.chars().take(MAX_VALUE_CHARS).collect::<String>())
+    } else {
+        s
+    }
+}
+
+/// List the differences between the two JSON values. Each difference names
+/// the JSON path at which it occurs.
+pub fn json_diff(expected: &Value, actual: &Value) -> Vec<String> {
+    fn inner(path: &str, expected: &Value, actual: &Value, diffs: &mut Vec<String>) {
+        match (expected, actual) {
+            (Value::Object(e), Value::Object(a)) => {
+                for key in e.keys().chain(a.keys()).sorted().dedup() {
+                    let key_path = format!("{path}.{key}");
+                    match (e.get(key), a.get(key)) {
+                        (Some(e), Some(a)) => inner(&key_path, e, a, diffs),
+                        (Some(e), None) => {
+                            diffs.push(format!("{key_path}: missing, expected {}", truncated(e)))
+                        }
+                        (None, Some(a)) => {
+                            diffs.push(format!("{key_path}: unexpected {}", truncated(a)))
+                        }
+                        (None, None) => unreachable!(),
+                    }
+                }
+            }
+            (Value::Array(e), Value::Array(a)) => {
+                if e.len() != a.len() {
+                    diffs.push(format!(
+                        "{path}: expected {} elements, found {}",
+                        e.len(),
+                        a.len()
+                    ));
+                }
+                for (i, (e, a)) in e.iter().zip(a).enumerate() {
+                    inner(&format!("{path}[{i}]"), e, a, diffs);
+                }
+            }
+            (e, a) => {
+                if e != a {
+                    diffs.push(format!(
+                        "{path}: expected {}, found {}",
+                        truncated(e),
+                        truncated(a)
+                    ));
+                }
+            }
+        }
+    }
+    let mut diffs = Vec::new();
+    inner("$", expected, actual, &mut diffs);
+    diffs
+}
+
+/// Normalize the JSON and compare it against (or write it to) the golden file
+pub fn check_json_matches_golden(
+    actual: Value,
+    golden_path: &Path,
+    mode: GoldenMode,
+) -> Result<(), Error> {
+    let actual = normalize(actual);
+    match mode {
+        GoldenMode::Overwrite => {
+            if let Some(parent) = golden_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(
+                golden_path,
+                format!("{}\n", serde_json::to_string_pretty(&actual)?),
+            )?;
+            Ok(())
+        }
+        GoldenMode::Compare => {
+            if !golden_path.exists() {
+                bail!(
+                    "Golden file {} does not exist. Run with {GOLDEN_ENV_VAR}=overwrite to create it.",
+                    golden_path.display()
+                );
+            }
+            let expected: Value = serde_json::from_str(&std::fs::read_to_string(golden_path)?)?;
+            let diffs = json_diff(&expected, &actual);
+            if diffs.is_empty() {
+                return Ok(());
+            }
+            let more = match diffs.len().saturating_sub(MAX_REPORTED_DIFFS) {
+                0 => String::new(),
+                n => format!("\n  ... and {n} more"),
+            };
+            bail!(
+                "Summary does not match the golden file {} ({} differences):\n  {}{more}\n\
+                Run with {GOLDEN_ENV_VAR}=overwrite to update the golden file.",
+                golden_path.display(),
+                diffs.len(),
+                diffs.iter().take(MAX_REPORTED_DIFFS).join("\n  ")
+            )
+        }
+    }
+}
+
+/// Render the summary, scrape the JSON and compare it against the golden file
+#[cfg(feature = "generate_html")]
+pub fn check_summary_matches_golden<P: Serialize + HtmlTemplate>(
+    summary: SinglePageHtml<P>,
+    golden_path: &Path,
+    mode: GoldenMode,
+) -> Result<(), Error> {
+    check_json_matches_golden(render_and_scrape(summary)?, golden_path, mode)
+}
+
+/// Panics with a diff of the JSON paths if the summary does not match the
+/// golden file. The mode is picked up from `WEBSUMMARY_GOLDEN`.
+#[cfg(feature = "generate_html")]
+pub fn assert_summary_matches_golden<P: Serialize + HtmlTemplate>(
+    summary: SinglePageHtml<P>,
+    golden_path: &Path,
+) {
+    if let Err(e) = check_summary_matches_golden(summary, golden_path, GoldenMode::from_env()) {
+        panic!("{e:#}");
+    }
+}
+
+// :::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::::
+// Html fixtures
+
+const FIXTURE_DATA_SUFFIX: &str = ".data.json";
+const FIXTURE_SUMMARY_SUFFIX: &str = ".summary.html";
+const FIXTURE_EXPECTED_SUFFIX: &str = ".expected.html";
+
+fn fixture_path(dir: &Path, name: &str, suffix: &str) -> PathBuf {
+    dir.join(format!("{name}{suffix}"))
+}
+
+/// The names of the fixtures in the directory, i.e. every `{name}` for which
+/// `{name}.data.json` exists, in sorted order
+pub fn fixture_names(dir: &Path) -> Result<Vec<String>, Error> {
+    let mut names = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let file_name = entry?.file_name();
+        if let Some(name) = file_name
+            .to_string_lossy()
+            .strip_suffix(FIXTURE_DATA_SUFFIX)
+        {
+            names.push(name.to_string());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+fn borrow_build_files<'a>(build_files: &'a WebSummaryBuildFiles<'_>) -> WebSummaryBuildFiles<'a> {
+    WebSummaryBuildFiles {
+        script_js: Cow::Borrowed(&build_files.script_js),
+        styles_css: Cow::Borrowed(&build_files.styles_css),
+        template_html: Cow::Borrowed(&build_files.template_html),
+    }
+}
+
+/// Render the fixture into a complete html page. The includes in the summary
+/// html are resolved relative to the fixture directory.
+pub fn render_fixture(
+    name: &str,
+    dir: &Path,
+    build_files: &WebSummaryBuildFiles<'_>,
+) -> Result<String, Error> {
+    let data = std::fs::read_to_string(fixture_path(dir, name, FIXTURE_DATA_SUFFIX))?;
+    let summary = std::fs::read_to_string(fixture_path(dir, name, FIXTURE_SUMMARY_SUFFIX))?;
+    let mut html = Vec::new();
+    generate_html_summary_with_build_files(
+        &data,
+        summary,
+        TemplateInfo::Dynamic(dir),
+        &mut html,
+        borrow_build_files(build_files),
+    )?;
+    Ok(String::from_utf8(html)?)
+}
+
+/// Re-render every fixture in the directory and write `{name}.expected.html`.
+/// Returns the names of the fixtures.
+pub fn regenerate_fixtures_with_build_files(
+    dir: &Path,
+    build_files: &WebSummaryBuildFiles<'_>,
+) -> Result<Vec<String>, Error> {
+    let names = fixture_names(dir)?;
+    for name in &names {
+        std::fs::write(
+            fixture_path(dir, name, FIXTURE_EXPECTED_SUFFIX),
+            render_fixture(name, dir, build_files)?,
+        )?;
+    }
+    Ok(names)
+}
+
+/// Same as `regenerate_fixtures_with_build_files`, using the bundled build
+/// files. Intended to be called from a build script or an xtask.
+#[cfg(feature = "generate_html")]
+pub fn regenerate_fixtures(dir: &Path) -> Result<Vec<String>, Error> {
+    regenerate_fixtures_with_build_files(dir, &WebSummaryBuildFiles::bundled())
+}
+
+/// Describe the lines which differ between the expected and the actual html
+fn html_diff(expected: &str, actual: &str) -> Vec<String> {
+    let expected: Vec<_> = expected.lines().collect();
+    let actual: Vec<_> = actual.lines().collect();
+    let mut diffs = Vec::new();
+    for i in 0..expected.len().max(actual.len()) {
+        match (expected.get(i), actual.get(i)) {
+            (Some(e), Some(a)) if e == a => {}
+            (e, a) => {
+                let mut diff = format!("line {}:", i + 1);
+                if let Some(e) = e {
+                    diff.push_str(&format!("\n    - {}", truncated_line(e)));
+                }
+                if let Some(a) = a {
+                    diff.push_str(&format!("\n    + {}", truncated_line(a)));
+                }
+                diffs.push(diff);
+            }
+        }
+    }
+    diffs
+}
+
+fn truncated_line(line: &str) -> String {
+    if line.chars().count() > MAX_VALUE_CHARS {
+        format!(
+            "{}...",
+            line.chars().take(MAX_VALUE_CHARS).collect::<String>()
+        )
+    } else {
+        line.to_string()
+    }
+}
+
+/// Render the fixture and compare it against (or write it to)
+/// `{name}.expected.html`
+pub fn check_fixture(
+    name: &str,
+    dir: &Path,
+    build_files: &WebSummaryBuildFiles<'_>,
+    mode: GoldenMode,
+) -> Result<(), Error> {
+    let actual = render_fixture(name, dir, build_files)?;
+    let expected_path = fixture_path(dir, name, FIXTURE_EXPECTED_SUFFIX);
+    match mode {
+        GoldenMode::Overwrite => {
+            std::fs::write(expected_path, actual)?;
+            Ok(())
+        }
+        GoldenMode::Compare => {
+            if !expected_path.exists() {
+                bail!(
+                    "Expected html {} does not exist. Run with {GOLDEN_ENV_VAR}=overwrite to create it.",
+                    expected_path.display()
+                );
+            }
+            let expected = std::fs::read_to_string(&expected_path)?;
+            let diffs = html_diff(&expected, &actual);
+            if diffs.is_empty() {
+                return Ok(());
+            }
+            let more = match diffs.len().saturating_sub(MAX_REPORTED_DIFFS) {
+                0 => String::new(),
+                n => format!("\n  ... and {n} more"),
+            };
+            bail!(
+                "Fixture {name} does not match {} ({} differing lines):\n  {}{more}\n\
+                Run with {GOLDEN_ENV_VAR}=overwrite to update the expected html.",
+                expected_path.display(),
+                diffs.len(),
+                diffs.iter().take(MAX_REPORTED_DIFFS).join("\n  ")
+            )
+        }
+    }
+}
+
+/// Panics with the differing lines if the fixture rendered with the bundled
+/// build files does not match its expected html. The mode is picked up from
+/// `WEBSUMMARY_GOLDEN`.
+#[cfg(feature = "generate_html")]
+pub fn assert_matches_fixture(name: &str, dir: &Path) {
+    let build_files = WebSummaryBuildFiles::bundled();
+    if let Err(e) = check_fixture(name, dir, &build_files, GoldenMode::from_env()) {
+        panic!("{e:#}");
+    }
+}
+
+/// Template which only holds the data and the summary, for the tests which
+/// do not depend on the bundled script and styles
+pub const TEST_TEMPLATE: &str = "<html>
+<script>
+      const data = [[ data.js ]]
+</script>
+<body>
+[[ summary.html ]]
+</body>
+</html>
+";
+
+/// Build files with an empty script and styles and `TEST_TEMPLATE`
+pub fn test_build_files() -> WebSummaryBuildFiles<'static> {
+    WebSummaryBuildFiles::new(String::new(), String::new(), TEST_TEMPLATE.into())
+}
+
+/// Empty scratch directory for the files of a test, unique to the name and
+/// the test process. It is removed along with its contents when dropped.
+pub struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    /// Create the directory, removing any leftover of a previous run
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("websummary_{name}_{}", std::process::id()));
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+        std::fs::create_dir_all(&dir).unwrap();
+        ScratchDir(dir)
+    }
+    /// Same as `new()`, holding the given files
+    pub fn with_files(name: &str, files: &[(impl AsRef<Path>, impl AsRef<[u8]>)]) -> Self {
+        let dir = Self::new(name);
+        for (filename, contents) in files {
+            std::fs::write(dir.join(filename), contents).unwrap();
+        }
+        dir
+    }
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl std::ops::Deref for ScratchDir {
+    type Target = Path;
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for ScratchDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(all(test, feature = "generate_html"))]
+mod tests {
+    use serde::Serialize;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::components::{HeroMetric, Tooltip};
+    use crate::SharedResources;
+
+    #[derive(Serialize)]
+    struct Content {
+        metric: HeroMetric,
+        tooltip: Tooltip,
+    }
+
+    impl HtmlTemplate for Content {
+        fn template(&self, _: Option<String>) -> String {
+            [
+                self.metric.template(Some("metric".into())),
+                self.tooltip.template(Some("tooltip".into())),
+            ]
+            .join("\n")
+        }
+    }
+
+    fn summary(cells: &str) -> SinglePageHtml<Content> {
+        let mut resources = SharedResources::new();
+        resources.insert(Value::String("data:image/png;base64,AAAA".into()));
+        SinglePageHtml::from_content(Content {
+            metric: HeroMetric::new("Number of cells", cells),
+            tooltip: Tooltip::new("Help", "Cells", None, None),
+        })
+        .resources(resources)
+    }
+
+    #[test]
+    fn test_golden_match() -> Result<(), Error> {
+        let dir = ScratchDir::new("golden_match");
+        let path = dir.join("golden.json");
+        check_summary_matches_golden(summary("3,487"), &path, GoldenMode::Overwrite)?;
+        // Tooltip ids are random but normalized
+        check_summary_matches_golden(summary("3,487"), &path, GoldenMode::Compare)?;
+        let golden: Value = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        assert_eq!(golden["_resources"]["000"], Value::from(26));
+        assert_eq!(golden["tooltip"]["id"], Value::from("tt-normalized"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_golden_mismatch() -> Result<(), Error> {
+        let dir = ScratchDir::new("golden_mismatch");
+        let path = dir.join("golden.json");
+        check_summary_matches_golden(summary("3,487"), &path, GoldenMode::Overwrite)?;
+        let err = check_summary_matches_golden(summary("1,000"), &path, GoldenMode::Compare)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains(r#"$.metric.metric: expected "3,487", found "1,000""#),
+            "{err}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_golden_overwrite() -> Result<(), Error> {
+        let dir = ScratchDir::new("golden_overwrite");
+        let path = dir.join("golden.json");
+        std::fs::write(&path, "{}")?;
+        assert!(
+            check_summary_matches_golden(summary("3,487"), &path, GoldenMode::Compare).is_err()
+        );
+        check_summary_matches_golden(summary("3,487"), &path, GoldenMode::Overwrite)?;
+        check_summary_matches_golden(summary("3,487"), &path, GoldenMode::Compare)?;
+        Ok(())
+    }
+
+    fn fixture_dir(name: &str) -> ScratchDir {
+        ScratchDir::with_files(
+            &format!("fixtures_{name}"),
+            &[
+                ("a.data.json", r#"{"cells": "3,487"}"#),
+                ("a.summary.html", "<p>cells</p>\n[[ include b.html ]]"),
+                ("b.html", "<p>reads</p>"),
+                ("c.data.json", "{}"),
+                ("c.summary.html", ""),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_regenerate_fixtures() -> Result<(), Error> {
+        let dir = fixture_dir("regenerate");
+        assert_eq!(
+            regenerate_fixtures_with_build_files(&dir, &test_build_files())?,
+            vec!["a", "c"]
+        );
+        let expected = std::fs::read_to_string(dir.join("a.expected.html"))?;
+        assert!(
+            expected.contains("<body>\n<p>cells</p>\n<p>reads</p>\n</body>"),
+            "{expected}"
+        );
+        assert!(dir.join("c.expected.html").exists());
+        check_fixture("a", &dir, &test_build_files(), GoldenMode::Compare)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_fixture_mismatch() -> Result<(), Error> {
+        let dir = fixture_dir("mismatch");
+        let err = check_fixture("a", &dir, &test_build_files(), GoldenMode::Compare)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("a.expected.html does not exist"), "{err}");
+
+        check_fixture("a", &dir, &test_build_files(), GoldenMode::Overwrite)?;
+        std::fs::write(dir.join("b.html"), "<p>umis</p>")?;
+        let err = check_fixture("a", &dir, &test_build_files(), GoldenMode::Compare)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("(1 differing lines)"), "{err}");
+        assert!(
+            err.contains("line 9:\n    - <p>reads</p>\n    + <p>umis</p>"),
+            "{err}"
+        );
+        check_fixture("a", &dir, &test_build_files(), GoldenMode::Overwrite)?;
+        check_fixture("a", &dir, &test_build_files(), GoldenMode::Compare)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+use std::time::SystemTime;
+
+use chrono::{DateTime, NaiveDate, SecondsFormat, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+/// How the timestamp is displayed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampFormat {
+    /// `2024-01-31`
+    DateOnly,
+    /// `2024-01-31T13:45:00Z`
+    #[default]
+    DateTimeUtc,
+    /// Relative to the time the summary is viewed, e.g. "3 days ago". The
+    /// frontend computes this from the `epoch_ms`
+    Relative,
+}
+
+/// A point in time which is displayed consistently across summaries.
+/// Serialized as an ISO 8601 string along with the format hint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timestamp {
+    pub time: DateTime<Utc>,
+    pub format: TimestampFormat,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TimestampProps {
+    value: String,
+    format: TimestampFormat,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    epoch_ms: Option<i64>,
+}
+
+impl Timestamp {
+    pub fn new(time: impl Into<DateTime<Utc>>, format: TimestampFormat) -> Self {
+        Timestamp {
+            time: time.into(),
+            format,
+        }
+    }
+    pub fn date_only(time: impl Into<DateTime<Utc>>) -> Self {
+        Timestamp::new(time, TimestampFormat::DateOnly)
+    }
+    pub fn date_time_utc(time: impl Into<DateTime<Utc>>) -> Self {
+        Timestamp::new(time, TimestampFormat::DateTimeUtc)
+    }
+    pub fn relative(time: impl Into<DateTime<Utc>>) -> Self {
+        Timestamp::new(time, TimestampFormat::Relative)
+    }
+    pub fn now(format: TimestampFormat) -> Self {
+        Timestamp::new(SystemTime::now(), format)
+    }
+    /// RFC 3339 representation of the time in UTC with second precision
+    pub fn rfc3339(&self) -> String {
+        self.time.to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+}
+
+impl From<SystemTime> for Timestamp {
+    fn from(src: SystemTime) -> Self {
+        Timestamp::date_time_utc(src)
+    }
+}
+
+impl From<DateTime<Utc>> for Timestamp {
+    fn from(src: DateTime<Utc>) -> Self {
+        Timestamp::date_time_utc(src)
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (value, epoch_ms) = match self.format {
+            TimestampFormat::DateOnly => (self.time.format("%Y-%m-%d").to_string(), None),
+            TimestampFormat::DateTimeUtc => (self.rfc3339(), None),
+            TimestampFormat::Relative => (self.rfc3339(), Some(self.time.timestamp_millis())),
+        };
+        TimestampProps {
+            value,
+            format: self.format,
+            epoch_ms,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        let props = TimestampProps::deserialize(deserializer)?;
+        let time = match props.format {
+            TimestampFormat::DateOnly => NaiveDate::parse_from_str(&props.value, "%Y-%m-%d")
+                .map(|date| Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap()))
+                .map_err(D::Error::custom)?,
+            TimestampFormat::DateTimeUtc | TimestampFormat::Relative => {
+                DateTime::parse_from_rfc3339(&props.value)
+                    .map_err(D::Error::custom)?
+                    .with_timezone(&Utc)
+            }
+        };
+        Ok(Timestamp::new(time, props.format))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HtmlTemplate;
+    use std::time::Duration;
+
+    fn time() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 31, 13, 45, 0).unwrap()
+    }
+
+    #[test]
+    fn test_timestamp_formats() {
+        assert_eq!(
+            serde_json::to_string(&Timestamp::date_only(time())).unwrap(),
+            r#"{"value":"2024-01-31","format":"date_only"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Timestamp::date_time_utc(time())).unwrap(),
+            r#"{"value":"2024-01-31T13:45:00Z","format":"date_time_utc"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Timestamp::relative(time())).unwrap(),
+            r#"{"value":"2024-01-31T13:45:00Z","format":"relative","epoch_ms":1706708700000}"#
+        );
+        assert_eq!(
+            Timestamp::from(time()).template(Some("run_date".into())),
+            r#"<div data-key="run_date" data-component="Timestamp"></div>"#
+        );
+    }
+
+    #[test]
+    fn test_timestamp_from_system_time() {
+        let system_time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_706_708_700);
+        let timestamp = Timestamp::from(system_time);
+        assert_eq!(timestamp.rfc3339(), "2024-01-31T13:45:00Z");
+        for timestamp in [
+            timestamp,
+            Timestamp::date_only(time()),
+            Timestamp::relative(time()),
+        ] {
+            let json = serde_json::to_string(&timestamp).unwrap();
+            let parsed: Timestamp = serde_json::from_str(&json).unwrap();
+            assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+        }
+        DateTime::parse_from_rfc3339(&Timestamp::now(TimestampFormat::Relative).rfc3339()).unwrap();
+    }
+}
@@ -0,0 +1,272 @@
+//!
+//! Hook to translate the user visible strings in a summary, such as titles,
+//! metric names and alerts. The serialized data is rewritten just before it
+//! is embedded in the html, so the components need not know about it.
+//! Values, ids and data keys are never translated.
+//!
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use serde_json::{Map, Value};
+
+pub trait Translator {
+    /// The translated string, or the input if there is no translation
+    fn translate<'a>(&'a self, s: &'a str) -> Cow<'a, str>;
+}
+
+/// Dictionary from the original to the translated string
+impl Translator for HashMap<String, String> {
+    fn translate<'a>(&'a self, s: &'a str) -> Cow<'a, str> {
+        match self.get(s) {
+            Some(translated) => Cow::Borrowed(translated),
+            None => Cow::Borrowed(s),
+        }
+    }
+}
+
+/// Translator held by the `SinglePageHtml`
+#[derive(Clone)]
+pub(crate) struct SharedTranslator(Arc<dyn Translator + Send + Sync>);
+
+impl SharedTranslator {
+    pub(crate) fn new(translator: impl Translator + Send + Sync + 'static) -> Self {
+        SharedTranslator(Arc::new(translator))
+    }
+}
+
+impl Translator for SharedTranslator {
+    fn translate<'a>(&'a self, s: &'a str) -> Cow<'a, str> {
+        self.0.translate(s)
+    }
+}
+
+impl fmt::Debug for SharedTranslator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedTranslator")
+    }
+}
+
+fn translate_field(map: &mut Map<String, Value>, key: &str, translator: &dyn Translator) {
+    if let Some(value) = map.get_mut(key) {
+        translate_strings(value, translator);
+    }
+}
+
+fn translate_strings(value: &mut Value, translator: &dyn Translator) {
+    match value {
+        Value::String(s) => {
+            let translated = translator.translate(s).into_owned();
+            *s = translated;
+        }
+        Value::Array(values) => {
+            for val in values {
+                translate_strings(val, translator);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn has_keys(map: &Map<String, Value>, keys: &[&str]) -> bool {
+    keys.iter().all(|key| map.contains_key(*key))
+}
+
+/// Translate the user visible fields of the components recognized from the
+/// shape of their data
+fn translate_value(value: &mut Value, translator: &dyn Translator) {
+    match value {
+        Value::Object(map) => {
+            // TitleWithHelp
+            if has_keys(map, &["title", "helpText"]) {
+                translate_field(map, "title", translator);
+                translate_field(map, "helpText", translator);
+                translate_field(map, "helpBullets", translator);
+                if let Some(Value::Object(link)) = map.get_mut("helpLink") {
+                    translate_field(link, "text", translator);
+                }
+            }
+            // TitleWithTermDesc, whose data holds the TermDescs
+            if has_keys(map, &["title", "data"]) && map["data"].is_array() {
+                translate_field(map, "title", translator);
+                if let Some(Value::Array(term_descs)) = map.get_mut("data") {
+                    for term_desc in term_descs {
+                        translate_strings(term_desc, translator);
+                    }
+                }
+            }
+            // HeroMetric
+            if has_keys(map, &["name", "metric"]) {
+                translate_field(map, "name", translator);
+            }
+            // Alert
+            if has_keys(map, &["level", "title", "message"]) {
+                translate_field(map, "title", translator);
+                translate_field(map, "message", translator);
+            }
+            // GenericTable
+            if has_keys(map, &["header", "rows"]) {
+                if let Some(header) = map.get_mut("header") {
+                    translate_strings(header, translator);
+                }
+            }
+            for val in map.values_mut() {
+                if val.is_object() || val.is_array() {
+                    translate_value(val, translator);
+                }
+            }
+        }
+        Value::Array(values) => {
+            for val in values {
+                translate_value(val, translator);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Translate the user visible strings in the serialized summary
+pub fn translate_summary(data: &mut Value, translator: &dyn Translator) {
+    translate_value(data, translator);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{
+        GenericTable, HeroMetric, TermDesc, TitleWithHelp, TitleWithTermDesc, WsNavBar,
+    };
+    use crate::scrape_json::scrape_json_from_html;
+    use crate::test_utils::test_build_files;
+    use crate::{Alert, AlertLevel, HtmlTemplate, WebSummary};
+
+    fn dictionary() -> HashMap<String, String> {
+        [
+            ("Cells", "細胞"),
+            ("Number of cells", "細胞数"),
+            ("Estimated number of cells", "推定細胞数"),
+            ("Donor", "ドナー"),
+            ("Low fraction reads in cells", "細胞内リードの割合が低い"),
+            ("Ideal > 70%", "理想 > 70%"),
+            ("Learn more", "詳細"),
+            // Values are never translated
+            ("3,487", "三千"),
+            ("sample1", "サンプル1"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+    }
+
+    fn summary() -> WebSummary {
+        WebSummary::builder()
+            .nav("Cell Ranger", "sample1", "PBMC")
+            .alert(Alert {
+                level: AlertLevel::Warn,
+                title: "Low fraction reads in cells".into(),
+                formatted_value: Some("50%".into()),
+                message: "Ideal > 70%".into(),
+                group: None,
+            })
+            .section_plain(
+                "title",
+                TitleWithHelp::new("Cells", "Estimated number of cells")
+                    .bullets(["Number of cells"])
+                    .link("Learn more", "https://www.10xgenomics.com/support"),
+            )
+            .section_plain(
+                "term_desc",
+                TitleWithTermDesc {
+                    title: "Cells".into(),
+                    data: vec![TermDesc::with_one_desc(
+                        "Number of cells",
+                        "Estimated number of cells",
+                    )],
+                },
+            )
+            .section_plain("hero", HeroMetric::new("Number of cells", "3,487"))
+            .section_plain(
+                "table",
+                GenericTable::from_rows(
+                    vec![vec!["Cells".into(), "3,487".into()]],
+                    Some(vec!["Donor".into(), "Cells".into()]),
+                ),
+            )
+            .build()
+    }
+
+    #[test]
+    fn test_translate_summary() {
+        let mut data = serde_json::to_value(summary()).unwrap();
+        translate_summary(&mut data, &dictionary());
+
+        assert_eq!(data["title"]["title"], "細胞");
+        assert_eq!(data["title"]["helpText"], "推定細胞数");
+        assert_eq!(data["title"]["helpBullets"], serde_json::json!(["細胞数"]));
+        assert_eq!(data["title"]["helpLink"]["text"], "詳細");
+        assert_eq!(data["term_desc"]["title"], "細胞");
+        assert_eq!(
+            data["term_desc"]["data"],
+            serde_json::json!([["細胞数", ["推定細胞数"]]])
+        );
+        assert_eq!(data["hero"]["name"], "細胞数");
+        assert_eq!(
+            data["table"]["header"],
+            serde_json::json!(["ドナー", "細胞"])
+        );
+        let alert = &data["alarms"]["alarms"][0];
+        assert_eq!(alert["title"], "細胞内リードの割合が低い");
+        assert_eq!(alert["message"], "理想 > 70%");
+
+        // Values, ids and table cells are untouched
+        assert_eq!(data["hero"]["metric"], "3,487");
+        assert_eq!(
+            data["table"]["rows"],
+            serde_json::json!([["Cells", "3,487"]])
+        );
+        assert_eq!(alert["formatted_value"], "50%");
+        assert_eq!(alert["level"], "WARN");
+        assert_eq!(data["sample"]["id"], "sample1");
+        assert_eq!(
+            data["title"]["helpLink"]["url"],
+            "https://www.10xgenomics.com/support"
+        );
+    }
+
+    #[test]
+    fn test_unknown_strings_pass_through() {
+        let dictionary = dictionary();
+        assert!(matches!(
+            dictionary.translate("Median genes per cell"),
+            Cow::Borrowed("Median genes per cell")
+        ));
+        let mut data = serde_json::to_value(summary()).unwrap();
+        let original = data.clone();
+        translate_summary(&mut data, &HashMap::new());
+        assert_eq!(data, original);
+    }
+
+    #[test]
+    fn test_with_translator() {
+        let mut html = Vec::new();
+        summary()
+            .with_translator(dictionary())
+            .generate_html_with_build_files(&mut html, test_build_files())
+            .unwrap();
+        let data = scrape_json_from_html(html.as_slice()).unwrap();
+        assert_eq!(data["hero"]["name"], "細胞数");
+        assert_eq!(data["hero"]["metric"], "3,487");
+
+        // The template and the nav bar are unchanged
+        let untranslated = summary();
+        assert!(String::from_utf8(html)
+            .unwrap()
+            .contains(&untranslated.template(None)));
+        assert!(matches!(
+            serde_json::from_value::<WsNavBar>(data["sample"].clone()),
+            Ok(WsNavBar { id, .. }) if id == "sample1"
+        ));
+    }
+}
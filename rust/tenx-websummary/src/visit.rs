@@ -0,0 +1,332 @@
+//!
+//! Walk the JSON data of a composed summary and visit the components in it,
+//! without matching on the shape of the serialized values in every tool that
+//! needs to find e.g. all the `HeroMetric`s.
+//!
+//! Components are identified by the shape of their serialized object:
+//! - `HeroMetric`: a string `name` and a string `metric`, with an optional
+//!   `threshold`, `comparison` and `id`, and no other keys
+//! - `TableMetric`: a `rows` key, where every row is a pair of strings, with
+//!   an optional `row_ids`, and no other keys
+//! - `GroupedTableMetric`: a `groups` key, where every group has `rows` as
+//!   in a `TableMetric`, with optional `title` and `row_ids`, and no other
+//!   keys
+//! - `GenericTable`: a `rows` key where every row is a list of strings, with
+//!   optional `header`, `pageSize`, `stickyHeader` and `richRows`, and no
+//!   other keys. A table without any of the optional keys and with exactly
+//!   two columns has the same shape as a `TableMetric` and is visited as a
+//!   `TableMetric`.
+//! - `PlotlyChart`: a `data` list of objects, with optional `config`,
+//!   `layout` and `style`, and no other keys
+//! - `RawImage`: an `encoded_image`, with optional `zoom_pan`, `link`,
+//!   `width`, `height`, `style` and `variants`, and no other keys
+//!
+//! The walk does not descend into a component once it is identified. Any
+//! other object or list is walked recursively, and every scalar value that
+//! is not inside an identified component is passed to `visit_unknown`.
+//!
+
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::components::{
+    GenericTable, GroupedTableMetric, HeroMetric, PlotlyChart, RawImage, TableMetric,
+};
+use crate::RESERVED_KEYS;
+
+/// Callbacks for the components found by [`walk_content`]. The path of a
+/// component uses the same syntax as the data keys, e.g.
+/// `tabs.tab_data[0].grid_data[1]`. All the callbacks do nothing by default.
+pub trait ComponentVisitor {
+    fn visit_hero_metric(&mut self, _metric: &HeroMetric, _path: &str) {}
+    fn visit_table_metric(&mut self, _table: &TableMetric, _path: &str) {}
+    fn visit_grouped_table_metric(&mut self, _table: &GroupedTableMetric, _path: &str) {}
+    fn visit_generic_table(&mut self, _table: &GenericTable, _path: &str) {}
+    fn visit_plotly(&mut self, _chart: &PlotlyChart, _path: &str) {}
+    fn visit_raw_image(&mut self, _image: &RawImage, _path: &str) {}
+    /// A scalar value which is not a part of any of the components above
+    fn visit_unknown(&mut self, _value: &Value, _path: &str) {}
+}
+
+/// Walk the content of a summary, calling the visitor for every component.
+/// The keys of a `SinglePageHtml` which are not a part of the content (the
+/// alerts, the shared resources etc.) are skipped at the top level, so this
+/// can be used either on the content or on the whole serialized page.
+pub fn walk_content(value: &Value, visitor: &mut impl ComponentVisitor) {
+    walk(String::new(), value, visitor);
+}
+
+const HERO_METRIC_KEYS: [&str; 5] = ["name", "metric", "threshold", "comparison", "id"];
+const TABLE_METRIC_KEYS: [&str; 2] = ["rows", "row_ids"];
+const GROUPED_TABLE_METRIC_KEYS: [&str; 1] = ["groups"];
+const METRIC_GROUP_KEYS: [&str; 3] = ["title", "rows", "row_ids"];
+const GENERIC_TABLE_KEYS: [&str; 5] = ["header", "rows", "pageSize", "stickyHeader", "richRows"];
+const PLOTLY_KEYS: [&str; 4] = ["config", "data", "layout", "style"];
+const RAW_IMAGE_KEYS: [&str; 7] = [
+    "encoded_image",
+    "zoom_pan",
+    "link",
+    "width",
+    "height",
+    "style",
+    "variants",
+];
+
+fn has_only_keys(map: &Map<String, Value>, keys: &[&str]) -> bool {
+    map.keys().all(|key| keys.contains(&key.as_str()))
+}
+
+fn is_list_of(value: Option<&Value>, f: impl Fn(&Value) -> bool) -> bool {
+    value
+        .and_then(Value::as_array)
+        .is_some_and(|values| values.iter().all(f))
+}
+
+fn is_string_row(row: &Value, num_cols: Option<usize>) -> bool {
+    row.as_array().is_some_and(|cells| {
+        (num_cols.is_none() || num_cols == Some(cells.len())) && cells.iter().all(Value::is_string)
+    })
+}
+
+fn parse<T: DeserializeOwned>(map: &Map<String, Value>) -> Option<T> {
+    serde_json::from_value(Value::Object(map.clone())).ok()
+}
+
+/// Returns true if the object is a component which was visited
+fn visit_component(
+    map: &Map<String, Value>,
+    path: &str,
+    visitor: &mut impl ComponentVisitor,
+) -> bool {
+    if has_only_keys(map, &HERO_METRIC_KEYS) && map.contains_key("name") {
+        if let Some(metric) = parse::<HeroMetric>(map) {
+            visitor.visit_hero_metric(&metric, path);
+            return true;
+        }
+    }
+    if has_only_keys(map, &TABLE_METRIC_KEYS)
+        && is_list_of(map.get("rows"), |row| is_string_row(row, Some(2)))
+    {
+        if let Some(table) = parse::<TableMetric>(map) {
+            visitor.visit_table_metric(&table, path);
+            return true;
+        }
+    }
+    if has_only_keys(map, &GROUPED_TABLE_METRIC_KEYS)
+        && is_list_of(map.get("groups"), |group| {
+            group.as_object().is_some_and(|group| {
+                has_only_keys(group, &METRIC_GROUP_KEYS)
+                    && is_list_of(group.get("rows"), |row| is_string_row(row, Some(2)))
+            })
+        })
+    {
+        if let Some(table) = parse::<GroupedTableMetric>(map) {
+            visitor.visit_grouped_table_metric(&table, path);
+            return true;
+        }
+    }
+    if has_only_keys(map, &GENERIC_TABLE_KEYS)
+        && is_list_of(map.get("rows"), |row| is_string_row(row, None))
+    {
+        if let Some(table) = parse::<GenericTable>(map) {
+            visitor.visit_generic_table(&table, path);
+            return true;
+        }
+    }
+    if has_only_keys(map, &PLOTLY_KEYS) && is_list_of(map.get("data"), Value::is_object) {
+        if let Some(chart) = parse::<PlotlyChart>(map) {
+            visitor.visit_plotly(&chart, path);
+            return true;
+        }
+    }
+    if has_only_keys(map, &RAW_IMAGE_KEYS) && map.contains_key("encoded_image") {
+        if let Some(image) = parse::<RawImage>(map) {
+            visitor.visit_raw_image(&image, path);
+            return true;
+        }
+    }
+    false
+}
+
+fn walk(path: String, value: &Value, visitor: &mut impl ComponentVisitor) {
+    match value {
+        Value::Object(map) => {
+            if visit_component(map, &path, visitor) {
+                return;
+            }
+            for (key, val) in map {
+                if path.is_empty() && RESERVED_KEYS.contains(&key.as_str()) {
+                    continue;
+                }
+                let child_path = if path.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{path}.{key}")
+                };
+                walk(child_path, val, visitor);
+            }
+        }
+        Value::Array(values) => {
+            for (i, val) in values.iter().enumerate() {
+                walk(format!("{path}[{i}]"), val, visitor);
+            }
+        }
+        _ => visitor.visit_unknown(value, &path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{DynGrid, GridLayout, HtmlFragment, Tabs, Threshold, TitleWithHelp};
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    #[derive(Default)]
+    struct Recorder {
+        visited: Vec<(&'static str, String)>,
+    }
+
+    impl ComponentVisitor for Recorder {
+        fn visit_hero_metric(&mut self, _: &HeroMetric, path: &str) {
+            self.visited.push(("hero_metric", path.to_string()));
+        }
+        fn visit_table_metric(&mut self, _: &TableMetric, path: &str) {
+            self.visited.push(("table_metric", path.to_string()));
+        }
+        fn visit_grouped_table_metric(&mut self, _: &GroupedTableMetric, path: &str) {
+            self.visited
+                .push(("grouped_table_metric", path.to_string()));
+        }
+        fn visit_generic_table(&mut self, _: &GenericTable, path: &str) {
+            self.visited.push(("generic_table", path.to_string()));
+        }
+        fn visit_plotly(&mut self, _: &PlotlyChart, path: &str) {
+            self.visited.push(("plotly", path.to_string()));
+        }
+        fn visit_raw_image(&mut self, _: &RawImage, path: &str) {
+            self.visited.push(("raw_image", path.to_string()));
+        }
+        fn visit_unknown(&mut self, _: &Value, path: &str) {
+            self.visited.push(("unknown", path.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_walk_nested_content() {
+        let mut grid = DynGrid::new(GridLayout::MaxCols(2));
+        grid.push(HeroMetric::new("Number of cells", "3,487"));
+        grid.push(HeroMetric::with_threshold(
+            "Valid barcodes",
+            "97.3%",
+            Threshold::Pass,
+        ));
+        let mut inner_grid = DynGrid::new(GridLayout::MaxCols(1));
+        inner_grid.push(PlotlyChart::with_layout_and_data(
+            json!({"title": "Knee plot"}),
+            vec![json!({"type": "scatter"})],
+        ));
+        let tabs = Tabs::new().tab("Summary", grid).tab(
+            "Plots",
+            Tabs::new()
+                .tab("Knee", inner_grid)
+                .tab("Image", RawImage::new("data:image/png;base64,AAAA".into())),
+        );
+        let table = GenericTable {
+            header: Some(vec!["Sample".into(), "Cells".into(), "Reads".into()]),
+            rows: vec![],
+            page_size: None,
+            sticky_header: None,
+            rich_rows: None,
+        };
+        let content = json!({
+            "tabs": tabs,
+            "table": table,
+            "metrics": TableMetric {
+                rows: vec![("Number of reads".into(), "335,174,516".into())],
+                row_ids: Vec::new(),
+            },
+            "help": {
+                "title": TitleWithHelp::new("Help", "Text"),
+                "inner": HtmlFragment::new("<b>Note</b>"),
+            },
+        });
+
+        let mut recorder = Recorder::default();
+        walk_content(&content, &mut recorder);
+        let mut visited = recorder.visited;
+        visited.sort();
+        assert_eq!(
+            visited,
+            vec![
+                ("generic_table", "table".to_string()),
+                ("hero_metric", "tabs.tab_data[0].grid_data[0]".to_string()),
+                ("hero_metric", "tabs.tab_data[0].grid_data[1]".to_string()),
+                (
+                    "plotly",
+                    "tabs.tab_data[1].tab_data[0].grid_data[0]".to_string()
+                ),
+                ("raw_image", "tabs.tab_data[1].tab_data[1]".to_string()),
+                ("table_metric", "metrics".to_string()),
+                ("unknown", "help.inner.html".to_string()),
+                ("unknown", "help.title.helpText".to_string()),
+                ("unknown", "help.title.title".to_string()),
+                ("unknown", "tabs.tab_data[1].titles[0]".to_string()),
+                ("unknown", "tabs.tab_data[1].titles[1]".to_string()),
+                ("unknown", "tabs.titles[0]".to_string()),
+                ("unknown", "tabs.titles[1]".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_walk_skips_non_content_keys() {
+        let page = json!({
+            "sample": {"id": "sample1", "description": "PBMC"},
+            "alarms": {"alarms": []},
+            "_resources": {"000": "data:image/png;base64,AAAA"},
+            "hero": {"name": "Median genes per cell", "metric": "1,850"},
+        });
+        let mut recorder = Recorder::default();
+        walk_content(&page, &mut recorder);
+        assert_eq!(recorder.visited, vec![("hero_metric", "hero".to_string())]);
+    }
+
+    #[test]
+    fn test_shape_ambiguity() {
+        let mut recorder = Recorder::default();
+        walk_content(
+            &json!({
+                // Two columns and no header: same shape as a TableMetric
+                "two_cols": {"rows": [["a", "b"]]},
+                "three_cols": {"rows": [["a", "b", "c"]]},
+                "paged": {"rows": [["a", "b"]], "pageSize": 10},
+                "with_ids": {"rows": [["a", "b"]], "row_ids": ["a_id"]},
+                "metric_id": {"name": "n", "metric": "m", "id": "n_id"},
+                // An extra key means this is not a HeroMetric
+                "not_metric": {"name": "n", "metric": "m", "extra": 1},
+                // Invalid threshold
+                "bad_threshold": {"name": "n", "metric": "m", "threshold": "fail"},
+            }),
+            &mut recorder,
+        );
+        let mut visited = recorder.visited;
+        visited.sort();
+        assert_eq!(
+            visited,
+            vec![
+                ("generic_table", "paged".to_string()),
+                ("generic_table", "three_cols".to_string()),
+                ("hero_metric", "metric_id".to_string()),
+                ("table_metric", "two_cols".to_string()),
+                ("table_metric", "with_ids".to_string()),
+                ("unknown", "bad_threshold.metric".to_string()),
+                ("unknown", "bad_threshold.name".to_string()),
+                ("unknown", "bad_threshold.threshold".to_string()),
+                ("unknown", "not_metric.extra".to_string()),
+                ("unknown", "not_metric.metric".to_string()),
+                ("unknown", "not_metric.name".to_string()),
+            ]
+        );
+    }
+}
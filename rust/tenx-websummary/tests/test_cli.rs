@@ -0,0 +1,133 @@
+//! Run the `tenx-websummary-cli` binary on the fixtures in `tests/fixtures`
+#![cfg(all(feature = "cli", feature = "test-utils"))]
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use serde_json::Value;
+use tenx_websummary::test_utils::ScratchDir;
+
+fn fixtures_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+/// Empty scratch directory for the outputs of a test
+fn out_dir(name: &str) -> ScratchDir {
+    ScratchDir::new(&format!("cli_{name}"))
+}
+
+fn cli(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_tenx-websummary-cli"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+fn render(summary: &Path, out: &Path) -> Output {
+    let fixtures = fixtures_dir();
+    cli(&[
+        "render",
+        "--data",
+        fixtures.join("example.data.json").to_str().unwrap(),
+        "--summary",
+        summary.to_str().unwrap(),
+        "--template-dir",
+        fixtures.to_str().unwrap(),
+        "--out",
+        out.to_str().unwrap(),
+    ])
+}
+
+fn read_json(path: &Path) -> Value {
+    serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
+
+#[test]
+fn test_render_scrape_validate() {
+    let dir = out_dir("roundtrip");
+    let html = dir.join("out.html");
+    let output = render(&fixtures_dir().join("example.summary.html"), &html);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(output.stderr.is_empty());
+    let rendered = std::fs::read_to_string(&html).unwrap();
+    assert!(rendered.contains(r#"data-key="num_cells""#));
+    assert!(!rendered.contains("[[ include"));
+
+    let data = dir.join("data.json");
+    let output = cli(&[
+        "scrape",
+        "--html",
+        html.to_str().unwrap(),
+        "--out",
+        data.to_str().unwrap(),
+    ]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        read_json(&data),
+        read_json(&fixtures_dir().join("example.data.json"))
+    );
+
+    let output = cli(&["validate", "--html", html.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", stderr(&output));
+}
+
+#[test]
+fn test_render_missing_include() {
+    let dir = out_dir("missing_include");
+    let summary = dir.join("summary.html");
+    std::fs::write(&summary, "[[ include missing.html ]]\n").unwrap();
+    let html = dir.join("out.html");
+    let output = render(&summary, &html);
+    assert_eq!(output.status.code(), Some(1));
+    let message = stderr(&output);
+    assert!(message.starts_with("error: "), "{message}");
+    assert_eq!(message.lines().count(), 1, "{message}");
+    assert!(message.contains("missing.html"), "{message}");
+    // A failed render does not leave a partial output
+    assert!(!html.exists());
+}
+
+#[test]
+fn test_validate_dangling_key() {
+    let dir = out_dir("dangling");
+    let summary = dir.join("summary.html");
+    std::fs::write(
+        &summary,
+        r#"<div data-key="num_cells" data-component="Metric"></div>
+<div data-key="missing" data-component="Metric"></div>
+"#,
+    )
+    .unwrap();
+    let html = dir.join("out.html");
+    assert!(render(&summary, &html).status.success());
+    let output = cli(&["validate", "--html", html.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    let message = stderr(&output);
+    assert!(
+        message.contains(r#"error: The data key "missing" in the template does not resolve"#),
+        "{message}"
+    );
+}
+
+#[test]
+fn test_usage_errors() {
+    for args in [
+        &[][..],
+        &["publish"],
+        &["scrape", "--html"],
+        &["scrape", "--html", "out.html"],
+        &["validate", "--html", "a.html", "--html", "b.html"],
+        &["validate", "--out", "out.html"],
+    ] {
+        let output = cli(args);
+        assert_eq!(output.status.code(), Some(2), "{args:?}");
+        assert!(stderr(&output).starts_with("error: "), "{args:?}");
+    }
+    let output = cli(&["validate", "--html", "does_not_exist.html"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(cli(&["--help"]).status.success());
+}
@@ -3,8 +3,11 @@
 
 use pretty_assertions::assert_eq;
 use serde::Serialize;
-use tenx_websummary::components::{DynGrid, Grid, GridLayout, HeroMetric, RawImage};
-use tenx_websummary::HtmlTemplate;
+use tenx_websummary::components::{
+    BlendedImage, BlendedImageSliderSize, Card, Documented, DynGrid, Grid, GridLayout, HeroMetric,
+    NoBreak, NumOrStr, PageBreak, RawImage,
+};
+use tenx_websummary::{AddToSharedResource, HtmlTemplate, SharedResources};
 
 #[test]
 fn test_html_template_simple() {
@@ -182,3 +185,426 @@ fn test_html_template_dyn_grid() {
         FullContent { grid: content }.template(None)
     );
 }
+
+#[test]
+fn test_html_template_page_breaks() {
+    #[derive(Serialize, HtmlTemplate)]
+    struct WebSummaryContent {
+        num_cells: NoBreak<HeroMetric>,
+        page_break: PageBreak,
+        grid: Grid<NoBreak<HeroMetric>>,
+    }
+    let content = WebSummaryContent {
+        num_cells: NoBreak::new(HeroMetric::new("Number of cells", "3,487")),
+        page_break: PageBreak,
+        grid: Grid::with_elements(
+            vec![NoBreak::new(HeroMetric::new("Median UMIs per cell", "867"))],
+            GridLayout::MaxCols(2),
+        ),
+    };
+    const EXPECTED_TEMPLATE: &str = r#"<div class="row">
+<div class="col">
+<div style="break-inside: avoid; page-break-inside: avoid;">
+<div data-key="num_cells" data-component="Metric"></div>
+</div>
+</div>
+</div>
+<div class="row">
+<div class="col">
+<div style="break-before: page; page-break-before: always;"></div>
+</div>
+</div>
+<div class="row">
+<div class="col">
+<div class="row">
+<div class="col-sm-6">
+<div style="break-inside: avoid; page-break-inside: avoid;">
+<div data-key="grid.grid_data[0]" data-component="Metric"></div>
+</div>
+</div>
+</div>
+</div>
+</div>
+"#;
+    assert_eq!(EXPECTED_TEMPLATE, content.template(None));
+    // The wrappers do not change the data
+    let json = serde_json::to_value(&content).unwrap();
+    assert_eq!(json["num_cells"]["metric"], "3,487");
+    assert_eq!(json["grid"]["grid_data"][0]["metric"], "867");
+    assert!(json["page_break"].is_null());
+}
+
+#[test]
+fn test_derive_add_to_shared_resource() {
+    #[derive(Serialize, HtmlTemplate, AddToSharedResource)]
+    struct WebSummaryContent {
+        registration: BlendedImage,
+        tissue_images: Vec<BlendedImage>,
+        card: Option<Card<BlendedImage>>,
+        #[shared_resource(skip)]
+        num_cells: HeroMetric,
+    }
+    let image_a = "data:image/png;base64,AAAA".to_string();
+    let image_b = "data:image/png;base64,BBBB".to_string();
+    let blended = || {
+        BlendedImage::new(
+            image_a.clone(),
+            image_b.clone(),
+            BlendedImageSliderSize {
+                width: NumOrStr::Str("100%".into()),
+            },
+        )
+    };
+    let mut content = WebSummaryContent {
+        registration: blended(),
+        tissue_images: vec![blended(), blended()],
+        card: Some(Card::full_width(blended())),
+        num_cells: HeroMetric::new("Number of cells", "3,487"),
+    };
+    let mut resources = SharedResources::new();
+    content.add_to_shared_resource(&mut resources);
+
+    // Every occurrence of the two images points to the same two resources
+    assert_eq!(resources.json().len(), 2);
+    let json = serde_json::to_value(&content).unwrap();
+    let key_a = json["registration"]["imgA"].as_str().unwrap();
+    let key_b = json["registration"]["imgB"].as_str().unwrap();
+    assert!(key_a.starts_with(tenx_websummary::RESOURCES_PREFIX));
+    assert_ne!(key_a, key_b);
+    for image in [&json["tissue_images"][0], &json["tissue_images"][1]] {
+        assert_eq!(image["imgA"], key_a);
+        assert_eq!(image["imgB"], key_b);
+    }
+    assert_eq!(json["card"]["imgA"], key_a);
+    assert_eq!(json["num_cells"]["metric"], "3,487");
+}
+
+#[test]
+fn test_derive_template_into() {
+    #[derive(Serialize, HtmlTemplate)]
+    struct Inner {
+        cells: HeroMetric,
+        reads: HeroMetric,
+    }
+    #[derive(Serialize, HtmlTemplate)]
+    struct Outer {
+        inner: Inner,
+        #[html(row = "1")]
+        left: Vec<HeroMetric>,
+        #[html(row = "1")]
+        right: Option<HeroMetric>,
+    }
+    let content = Outer {
+        inner: Inner {
+            cells: HeroMetric::new("Number of cells", "3,487"),
+            reads: HeroMetric::new("Number of reads", "335,174,516"),
+        },
+        left: vec![HeroMetric::new("Median UMIs per cell", "867")],
+        right: None,
+    };
+    let mut out = String::from("<p>before</p>\n");
+    content.template_into(Some("content".into()), &mut out);
+    assert_eq!(
+        out,
+        format!(
+            "<p>before</p>\n{}",
+            content.template(Some("content".into()))
+        )
+    );
+    assert!(out.contains(r#"<div data-key="content.inner.reads" data-component="Metric"></div>"#));
+    assert!(out.contains(r#"<div data-key="content.left[0]" data-component="Metric"></div>"#));
+}
+
+#[test]
+fn test_derive_smart_pointer_fields() {
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use tenx_websummary::components::{GenericTable, PlotlyChart};
+
+    #[derive(Serialize, HtmlTemplate)]
+    struct Inner {
+        cells: HeroMetric,
+    }
+    #[derive(Serialize, HtmlTemplate)]
+    struct Plain {
+        table: GenericTable,
+        #[html(row = "1")]
+        plot: PlotlyChart,
+        #[html(row = "1")]
+        cells: HeroMetric,
+        inner: Inner,
+    }
+    #[derive(Serialize, HtmlTemplate)]
+    struct Shared<'a> {
+        table: Arc<GenericTable>,
+        #[html(row = "1")]
+        plot: Box<PlotlyChart>,
+        #[html(row = "1")]
+        cells: &'a HeroMetric,
+        inner: Rc<Inner>,
+    }
+
+    let table = Arc::new(GenericTable::from_rows(
+        vec![vec!["PBMC".into(), "3,487".into()]],
+        Some(vec!["Sample".into(), "Cells".into()]),
+    ));
+    let plot = PlotlyChart::with_layout_and_data(
+        serde_json::json!({"title": "Knee plot"}),
+        vec![serde_json::json!({"type": "scatter"})],
+    );
+    let cells = HeroMetric::new("Number of cells", "3,487");
+    let plain = Plain {
+        table: (*table).clone(),
+        plot: plot.clone(),
+        cells: cells.clone(),
+        inner: Inner {
+            cells: cells.clone(),
+        },
+    };
+    let shared = Shared {
+        table: Arc::clone(&table),
+        plot: Box::new(plot),
+        cells: &cells,
+        inner: Rc::new(Inner {
+            cells: cells.clone(),
+        }),
+    };
+    assert_eq!(
+        shared.template(Some("content".into())),
+        plain.template(Some("content".into()))
+    );
+    assert_eq!(
+        serde_json::to_string(&shared).unwrap(),
+        serde_json::to_string(&plain).unwrap()
+    );
+    assert_eq!(Arc::strong_count(&table), 2);
+}
+
+#[test]
+fn test_derive_custom_components() {
+    use tenx_websummary::components::CustomComponent;
+
+    // Components from a custom JS bundle, registered without importing
+    // `ReactComponent`
+    #[derive(Serialize)]
+    struct LibraryBreakdown {
+        libraries: Vec<String>,
+    }
+    tenx_websummary::react_component!(LibraryBreakdown, "LibraryBreakdown");
+
+    #[derive(Serialize)]
+    struct FlowcellMap {
+        lanes: u8,
+    }
+    tenx_websummary::react_component! {
+        FlowcellMap => "FlowcellMap",
+    }
+    assert_eq!(REACT_COMPONENTS, &[("FlowcellMap", "FlowcellMap")]);
+
+    #[derive(Serialize, HtmlTemplate)]
+    struct WebSummaryContent {
+        #[html(row = "1")]
+        cells: HeroMetric,
+        #[html(row = "1")]
+        breakdown: LibraryBreakdown,
+        flowcell: FlowcellMap,
+        one_off: CustomComponent<serde_json::Value>,
+    }
+
+    let content = WebSummaryContent {
+        cells: HeroMetric::new("Number of cells", "3,487"),
+        breakdown: LibraryBreakdown {
+            libraries: vec!["Gene Expression".into()],
+        },
+        flowcell: FlowcellMap { lanes: 4 },
+        one_off: CustomComponent::new("ReadLengths", serde_json::json!({"r1": 28, "r2": 90})),
+    };
+    assert_eq!(
+        content.template(None),
+        r#"<div class="row">
+<div class="col">
+<div data-key="cells" data-component="Metric"></div>
+</div>
+<div class="col">
+<div data-key="breakdown" data-component="LibraryBreakdown"></div>
+</div>
+</div>
+<div class="row">
+<div class="col">
+<div data-key="flowcell" data-component="FlowcellMap"></div>
+</div>
+</div>
+<div class="row">
+<div class="col">
+<div data-key="one_off" data-component="ReadLengths"></div>
+</div>
+</div>
+"#
+    );
+    assert_eq!(
+        serde_json::to_value(&content).unwrap()["one_off"],
+        serde_json::json!({"r1": 28, "r2": 90})
+    );
+}
+
+#[test]
+fn test_derive_section_ids() {
+    #[derive(Serialize, HtmlTemplate)]
+    #[html(section_ids)]
+    struct Sequencing {
+        #[html(row = "1")]
+        reads: HeroMetric,
+        #[html(row = "1")]
+        q30: HeroMetric,
+    }
+
+    #[derive(Serialize, HtmlTemplate)]
+    #[html(section_ids)]
+    struct WebSummaryContent {
+        cells: HeroMetric,
+        sequencing: Sequencing,
+    }
+
+    let content = WebSummaryContent {
+        cells: HeroMetric::new("Number of cells", "3,487"),
+        sequencing: Sequencing {
+            reads: HeroMetric::new("Number of reads", "335,174,516"),
+            q30: HeroMetric::new("Q30 bases in barcode", "96.8%"),
+        },
+    };
+    assert_eq!(
+        content.template(None),
+        r#"<div class="row">
+<div class="col" data-section id="cells">
+<div data-key="cells" data-component="Metric"></div>
+</div>
+</div>
+<div class="row">
+<div class="col" data-section id="sequencing">
+<div class="row">
+<div class="col" data-section id="sequencing-reads">
+<div data-key="sequencing.reads" data-component="Metric"></div>
+</div>
+<div class="col" data-section id="sequencing-q30">
+<div data-key="sequencing.q30" data-component="Metric"></div>
+</div>
+</div>
+
+</div>
+</div>
+"#
+    );
+
+    // Keys which do not start with a letter
+    let template = content.template(Some("10x_data".into()));
+    assert!(template.contains(r#"<div class="col" data-section id="section-10x_data-cells">"#));
+    assert!(
+        template.contains(r#"<div class="col" data-section id="section-10x_data-sequencing-q30">"#)
+    );
+
+    // Without the attribute, the divs have no ids
+    #[derive(Serialize, HtmlTemplate)]
+    struct NoIds {
+        cells: HeroMetric,
+    }
+    assert!(!NoIds {
+        cells: HeroMetric::new("Number of cells", "3,487"),
+    }
+    .template(None)
+    .contains("id="));
+}
+
+#[test]
+fn test_derive_section_ids_dedup() {
+    #[derive(Serialize, Clone, HtmlTemplate)]
+    #[html(section_ids)]
+    struct Metrics {
+        cells: HeroMetric,
+    }
+
+    /// Renders the metrics twice with the same data key, e.g. once for the
+    /// screen and once for print
+    #[derive(Serialize)]
+    struct ScreenAndPrint(Metrics);
+    impl HtmlTemplate for ScreenAndPrint {
+        fn template(&self, data_key: Option<String>) -> String {
+            self.0.template(data_key.clone()) + &self.0.template(data_key)
+        }
+    }
+
+    let metrics = Metrics {
+        cells: HeroMetric::new("Number of cells", "3,487"),
+    };
+    let page = tenx_websummary::SinglePageHtml::from_content(ScreenAndPrint(metrics.clone()));
+    let template = page.template(None);
+    let ids: Vec<_> = template
+        .match_indices(r#" id=""#)
+        .map(|(i, m)| {
+            let id = &template[i + m.len()..];
+            &id[..id.find('"').unwrap()]
+        })
+        .collect();
+    assert_eq!(ids, ["cells", "cells-2"]);
+    // The same page renders the same ids
+    assert_eq!(page.template(None), template);
+}
+
+#[test]
+fn test_derive_doc_tooltips() {
+    #[derive(Serialize, HtmlTemplate)]
+    #[html(doc_tooltips)]
+    struct Summary {
+        /// Estimated number of cells
+        /// Barcodes associated with cells, called from the UMI counts
+        /// and the RNA profile.
+        cells: Documented<HeroMetric>,
+        /// Valid barcodes
+        valid_barcodes: Documented<HeroMetric>,
+        reads: HeroMetric,
+    }
+
+    let summary = Summary {
+        cells: Documented::new(HeroMetric::new("Estimated number of cells", "3,487")),
+        valid_barcodes: Documented::new(HeroMetric::new("Valid barcodes", "93.6%")),
+        reads: HeroMetric::new("Number of reads", "123,456"),
+    };
+    let json = serde_json::to_value(&summary).unwrap();
+    assert!(json["cells"].get("help").is_none());
+
+    let summary = summary.with_doc_tooltips();
+    assert_eq!(
+        summary.template(None),
+        r#"<div class="row">
+<div class="col">
+<div data-key="cells.help" data-component="HeaderWithHelp"></div>
+<div data-key="cells.inner" data-component="Metric"></div>
+</div>
+</div>
+<div class="row">
+<div class="col">
+<div data-key="valid_barcodes.help" data-component="HeaderWithHelp"></div>
+<div data-key="valid_barcodes.inner" data-component="Metric"></div>
+</div>
+</div>
+<div class="row">
+<div class="col">
+<div data-key="reads" data-component="Metric"></div>
+</div>
+</div>
+"#
+    );
+    let json = serde_json::to_value(&summary).unwrap();
+    assert_eq!(
+        json["cells"]["help"],
+        serde_json::json!({
+            "title": "Estimated number of cells",
+            "helpText": "Barcodes associated with cells, called from the UMI counts\nand the RNA profile."
+        })
+    );
+    assert_eq!(
+        json["valid_barcodes"]["help"],
+        serde_json::json!({"title": "Valid barcodes", "helpText": ""})
+    );
+    assert_eq!(json["valid_barcodes"]["inner"]["metric"], "93.6%");
+    assert_eq!(json["reads"]["metric"], "123,456");
+}
@@ -1,10 +1,14 @@
 #![cfg(feature = "form")]
 use std::collections::HashSet;
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use tenx_websummary::form::{
-    EnumSelect, FieldValidationResult, FormMethod, IntoHtmlForm, SingleSelectType,
+    parse_form_submission, use_deterministic_submission_tokens, ConfigureSpreadsheet,
+    CsvWithHeader, EnumSelect, FieldValidationResult, FormInput, FormInputConfigString, FormMethod,
+    FormPage, FormValidationResult, IntoHtmlForm, OrOther, RepeatedGroup, RepeatedGroupConfig,
+    SingleSelectType, Spreadsheet, TableInput, WizardValidationResult,
 };
+use tenx_websummary::HtmlTemplate;
 use tenx_websummary_derive::HtmlForm;
 
 #[test]
@@ -45,6 +49,7 @@ fn test_struct_derive_get() {
         analysis_id: i64,
     }
 
+    use_deterministic_submission_tokens();
     let form = MyForm::form();
     assert_eq!(form.config.method, FormMethod::Get);
     assert_eq!(form.elements.len(), 1);
@@ -86,6 +91,7 @@ fn test_struct_derive() {
         }
     }
 
+    use_deterministic_submission_tokens();
     let form = MyForm {
         analysis_id: 1000,
         metric: "filtered_bcs".into(),
@@ -98,3 +104,727 @@ fn test_struct_derive() {
     assert_eq!(form.elements.len(), 4);
     insta::assert_ron_snapshot!(form);
 }
+
+#[test]
+fn test_struct_derive_visible_when() {
+    #[derive(Serialize, HtmlForm, Debug, PartialEq, Clone, Eq)]
+    enum Reference {
+        GRCh38,
+        Custom,
+    }
+
+    #[derive(Serialize, HtmlForm, Debug, PartialEq, Eq)]
+    #[html_form(configure)]
+    struct MyForm {
+        reference: Reference,
+        #[html_form(visible_when = "reference == Custom")]
+        custom_reference_path: String,
+    }
+
+    impl MyFormConfiguration for MyForm {
+        fn validate_custom_reference_path(&self, path: &String) -> FieldValidationResult {
+            if path.is_empty() {
+                FieldValidationResult::Invalid {
+                    error: "Enter a reference path".into(),
+                }
+            } else {
+                FieldValidationResult::Valid
+            }
+        }
+    }
+
+    let is_required = |input: &FormInput| match input {
+        FormInput::Input(v) => serde_json::to_value(v).unwrap()["required"] == true,
+        _ => unreachable!(),
+    };
+
+    // Hidden and empty is valid and not required
+    let hidden = MyForm {
+        reference: Reference::GRCh38,
+        custom_reference_path: String::new(),
+    }
+    .validate();
+    assert!(matches!(
+        hidden,
+        tenx_websummary::form::FormValidationResult::Valid(_)
+    ));
+    assert!(!is_required(&hidden.inner().elements[1].input));
+
+    // Visible and empty is invalid
+    let visible = MyForm {
+        reference: Reference::Custom,
+        custom_reference_path: String::new(),
+    }
+    .validate();
+    assert!(matches!(
+        visible,
+        tenx_websummary::form::FormValidationResult::Invalid(_)
+    ));
+    let form = visible.inner();
+    assert!(is_required(&form.elements[1].input));
+    assert!(form.elements[0].visible_when.is_none());
+    insta::assert_ron_snapshot!(form.elements[1].visible_when);
+}
+
+#[test]
+fn test_struct_derive_or_other() {
+    #[derive(Serialize, HtmlForm, Debug, PartialEq, Clone, Eq)]
+    enum Chemistry {
+        #[serde(rename = "SC3Pv3")]
+        ThreePrimeV3,
+        #[serde(rename = "SC5P-PE")]
+        FivePrimePe,
+    }
+
+    #[derive(Serialize, Deserialize, HtmlForm, Debug, PartialEq, Eq)]
+    struct MyForm {
+        chemistry: OrOther<Chemistry>,
+    }
+
+    let parse = |json| serde_json::from_value::<MyForm>(json).unwrap().chemistry;
+    assert_eq!(
+        parse(serde_json::json!({ "chemistry": "SC5P-PE" })),
+        OrOther::Known(Chemistry::FivePrimePe)
+    );
+    assert_eq!(
+        parse(serde_json::json!({ "chemistry": { "other": "SC3Pv4" } })),
+        OrOther::Other("SC3Pv4".into())
+    );
+
+    let invalid = MyForm {
+        chemistry: OrOther::Other(String::new()),
+    }
+    .validate();
+    assert!(matches!(invalid, FormValidationResult::Invalid(_)));
+
+    let form = MyForm {
+        chemistry: OrOther::Other("SC3Pv4".into()),
+    }
+    .validate();
+    assert!(matches!(form, FormValidationResult::Valid(_)));
+    insta::assert_ron_snapshot!(form.inner().elements[0]);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, HtmlForm)]
+#[html_form(configure)]
+struct Library {
+    name: String,
+    library_type: LibraryType,
+    lanes: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, HtmlForm)]
+enum LibraryType {
+    #[serde(rename = "Gene Expression")]
+    GeneExpression,
+    #[serde(rename = "Antibody Capture")]
+    AntibodyCapture,
+}
+
+impl LibraryConfiguration for Library {
+    fn validate_lanes(&self, lanes: &i64) -> FieldValidationResult {
+        if *lanes < 1 {
+            FieldValidationResult::Invalid {
+                error: "At least one lane".into(),
+            }
+        } else {
+            FieldValidationResult::Valid
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, HtmlForm)]
+#[html_form(method = "post")]
+struct LibrarySheet {
+    run_id: String,
+    libraries: RepeatedGroup<Library>,
+}
+
+#[test]
+fn test_struct_derive_repeated_group_round_trip() {
+    let sheet: LibrarySheet = parse_form_submission(
+        "run_id=run1\
+         &libraries[0].name=GEX&libraries[0].library_type=Gene+Expression&libraries[0].lanes=2\
+         &libraries[3].name=ADT&libraries[3].library_type=Antibody+Capture&libraries[3].lanes=1",
+    )
+    .unwrap();
+    let expected = LibrarySheet {
+        run_id: "run1".into(),
+        libraries: RepeatedGroup(vec![
+            Library {
+                name: "GEX".into(),
+                library_type: LibraryType::GeneExpression,
+                lanes: 2,
+            },
+            Library {
+                name: "ADT".into(),
+                library_type: LibraryType::AntibodyCapture,
+                lanes: 1,
+            },
+        ]),
+    };
+    assert_eq!(sheet, expected);
+    assert!(matches!(sheet.validate(), FormValidationResult::Valid(_)));
+
+    // The rows are a list in json
+    let json = serde_json::to_value(&sheet).unwrap();
+    assert_eq!(json["libraries"][1]["name"], "ADT");
+    assert_eq!(
+        serde_json::from_value::<LibrarySheet>(json).unwrap(),
+        expected
+    );
+
+    let err = parse_form_submission::<LibrarySheet>(
+        "run_id=run1&libraries[0].name=GEX&libraries[0].library_type=GEX&libraries[0].lanes=x",
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("Row 1"), "{err}");
+}
+
+#[test]
+fn test_struct_derive_repeated_group_validation() {
+    let sheet: LibrarySheet = parse_form_submission(
+        "run_id=run1\
+         &libraries[0].name=GEX&libraries[0].library_type=Gene+Expression&libraries[0].lanes=2\
+         &libraries[1].name=ADT&libraries[1].library_type=Antibody+Capture&libraries[1].lanes=0",
+    )
+    .unwrap();
+    let form = match sheet.validate() {
+        FormValidationResult::Invalid(form) => form,
+        FormValidationResult::Valid(_) => panic!("row 2 has no lanes"),
+    };
+    let json = serde_json::to_value(&form.elements[1]).unwrap();
+    assert_eq!(json["feedback"]["error"], "Fix the errors in row 2");
+    let rows = &json["input"]["content"]["rows"];
+    // The feedback is next to the field of the row
+    assert_eq!(rows[1][2]["feedback"]["error"], "At least one lane");
+    assert_eq!(rows[1][2]["input"]["content"]["name"], "libraries[1].lanes");
+    for (row, field) in [(0, 0), (0, 1), (0, 2), (1, 0), (1, 1)] {
+        assert!(
+            rows[row][field]["feedback"]["error"].is_null(),
+            "{row} {field}"
+        );
+    }
+}
+
+#[test]
+fn test_struct_derive_repeated_group_bounds() {
+    #[derive(Serialize, Deserialize, HtmlForm)]
+    #[html_form(configure)]
+    struct Pool {
+        libraries: RepeatedGroup<Library>,
+    }
+
+    impl PoolConfiguration for Pool {
+        fn configure_libraries() -> RepeatedGroupConfig {
+            RepeatedGroupConfig {
+                min_rows: 2,
+                max_rows: Some(3),
+            }
+        }
+        // The bounds are checked even if the validation is overridden
+        fn validate_libraries(&self, _: &RepeatedGroup<Library>) -> FieldValidationResult {
+            FieldValidationResult::Valid
+        }
+    }
+
+    let row = |i: usize| {
+        format!(
+            "libraries[{i}].name=L{i}&libraries[{i}].library_type=Gene+Expression\
+             &libraries[{i}].lanes=1"
+        )
+    };
+    let error = |rows: usize| {
+        let body = (0..rows).map(row).collect::<Vec<_>>().join("&");
+        let pool: Pool = parse_form_submission(&body).unwrap();
+        let form = pool.validate().inner();
+        serde_json::to_value(&form.elements[0].feedback).unwrap()["error"].clone()
+    };
+    assert_eq!(error(1), "Enter at least 2 rows");
+    assert!(error(2).is_null());
+    assert!(error(3).is_null());
+    assert_eq!(error(4), "Enter at most 3 rows");
+}
+
+#[test]
+fn test_struct_derive_repeated_group_json() {
+    use_deterministic_submission_tokens();
+    let form = LibrarySheet::form();
+    let FormInput::RepeatedGroup(group) = &form.elements[1].input else {
+        unreachable!()
+    };
+    assert_eq!(group.rows.len(), 1);
+    insta::assert_ron_snapshot!(form.elements[1]);
+}
+
+#[test]
+fn test_struct_derive_submission_token() {
+    #[derive(Serialize, HtmlForm)]
+    struct MyForm {
+        analysis_id: i64,
+    }
+
+    #[derive(Serialize, HtmlForm)]
+    #[html_form(configure)]
+    struct NoTokenForm {
+        analysis_id: i64,
+    }
+
+    impl NoTokenFormConfiguration for NoTokenForm {
+        fn submission_token() -> Option<String> {
+            None
+        }
+    }
+
+    let tokens: HashSet<_> = (0..100)
+        .map(|_| MyForm::form().config.submission_token.unwrap())
+        .chain(std::iter::once(
+            MyForm { analysis_id: 1 }
+                .filled_form_pre_validation()
+                .config
+                .submission_token
+                .unwrap(),
+        ))
+        .collect();
+    assert_eq!(tokens.len(), 101);
+
+    let form = NoTokenForm::form();
+    assert!(form.config.submission_token.is_none());
+    assert!(serde_json::to_value(&form).unwrap()["config"]
+        .get("submission_token")
+        .is_none());
+}
+
+#[test]
+fn test_struct_derive_config_attrs() {
+    #[derive(Serialize, HtmlForm)]
+    #[html_form(configure)]
+    struct MyForm {
+        #[html_form(placeholder = "Sample name")]
+        sample: String,
+        #[html_form(rows = 5, placeholder = "Describe the sample")]
+        description: String,
+        #[html_form(min = 1, max = 100, step = 5)]
+        num_cells: i64,
+        #[html_form(min = 0)]
+        num_reads: i64,
+        #[html_form(placeholder = "Ignored")]
+        overridden: String,
+    }
+
+    impl MyFormConfiguration for MyForm {
+        fn configure_overridden() -> FormInputConfigString {
+            FormInputConfigString::TextArea {
+                rows: Some(2),
+                placeholder: None,
+                max_length: None,
+            }
+        }
+    }
+
+    let form = MyForm::form();
+    assert_eq!(form.elements.len(), 5);
+    insta::assert_ron_snapshot!(form.elements);
+}
+
+#[test]
+fn test_struct_derive_max_length() {
+    #[derive(Serialize, HtmlForm)]
+    struct MyForm {
+        #[html_form(max_length = 10)]
+        sample: String,
+        #[html_form(rows = 3, max_length = 5)]
+        description: Option<String>,
+    }
+
+    let form = MyForm::form();
+    let data = serde_json::to_value(&form.elements).unwrap();
+    assert_eq!(data[0]["input"]["type"], "Input");
+    assert_eq!(data[0]["input"]["content"]["maxLength"], 10);
+    assert_eq!(data[1]["input"]["type"], "TextArea");
+    assert_eq!(data[1]["input"]["content"]["maxLength"], 5);
+
+    let valid = MyForm {
+        sample: "PBMC".into(),
+        // Characters are counted, not bytes
+        description: Some("ßßßßß".into()),
+    };
+    assert!(matches!(valid.validate(), FormValidationResult::Valid(_)));
+    let no_description = MyForm {
+        sample: "PBMC".into(),
+        description: None,
+    };
+    assert!(matches!(
+        no_description.validate(),
+        FormValidationResult::Valid(_)
+    ));
+
+    let too_long = MyForm {
+        sample: "PBMC 10k v3".into(),
+        description: Some("Sequenced twice".into()),
+    };
+    let FormValidationResult::Invalid(form) = too_long.validate() else {
+        panic!("Expected the form to be invalid");
+    };
+    assert_eq!(
+        form.elements[0].feedback.error.as_deref(),
+        Some("Should be at most 10 characters long, but is 11 characters long")
+    );
+    let error = form.elements[1].feedback.error.as_deref().unwrap();
+    assert!(error.contains('5') && error.contains("15"), "{error}");
+
+    // The length is checked even if the validation is overridden
+    #[derive(Serialize, HtmlForm)]
+    #[html_form(configure)]
+    struct CustomForm {
+        #[html_form(max_length = 4)]
+        sample: String,
+    }
+    impl CustomFormConfiguration for CustomForm {
+        fn validate_sample(&self, _: &String) -> FieldValidationResult {
+            FieldValidationResult::Valid
+        }
+    }
+    let too_long = CustomForm {
+        sample: "PBMC 10k".into(),
+    };
+    let FormValidationResult::Invalid(form) = too_long.validate() else {
+        panic!("Expected the form to be invalid");
+    };
+    assert_eq!(
+        form.elements[0].feedback.error.as_deref(),
+        Some("Should be at most 4 characters long, but is 8 characters long")
+    );
+}
+
+#[test]
+fn test_struct_derive_table_preview() {
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    struct Sample {
+        name: String,
+        num_cells: u32,
+    }
+
+    #[derive(Serialize, HtmlForm)]
+    struct MyForm {
+        run_name: String,
+        samples: TableInput<Sample, CsvWithHeader>,
+    }
+
+    let form = MyForm {
+        run_name: "run1".into(),
+        samples: "name,num_cells\npbmc,1000\nbrain,many".to_string().into(),
+    };
+    assert!(form.filled_form_pre_validation().elements[1]
+        .preview()
+        .is_none());
+    let form = form.validate().inner();
+    assert!(form.elements[0].preview().is_none());
+    let preview = form.elements[1].preview().unwrap();
+    assert_eq!(
+        preview.header,
+        Some(vec!["name".to_string(), "num_cells".to_string()])
+    );
+    assert_eq!(preview.rows[0].0, vec!["pbmc", "1000"]);
+    assert_eq!(preview.rows[1].0, vec!["⚠ brain", "many"]);
+
+    let template = form.template(Some("form".into()));
+    assert!(template
+        .contains(r#"<div data-key="form.elements[1].preview" data-component="Table"></div>"#));
+    // The preview comes after the feedback
+    let feedback = template
+        .find(r#"data-key="form.elements[1].feedback""#)
+        .unwrap();
+    let preview = template
+        .find(r#"data-key="form.elements[1].preview""#)
+        .unwrap();
+    assert!(feedback < preview);
+}
+
+#[test]
+fn test_struct_derive_optional_spreadsheet() {
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    struct Well {
+        well: String,
+        sample: String,
+    }
+
+    impl ConfigureSpreadsheet for Well {
+        fn initial_value() -> Option<Vec<Self>> {
+            Some(vec![Well {
+                well: "A1".into(),
+                sample: String::new(),
+            }])
+        }
+        fn readonly_columns() -> Vec<usize> {
+            vec![0]
+        }
+    }
+
+    #[derive(Serialize, Deserialize, HtmlForm)]
+    struct Plate {
+        run_name: String,
+        wells: Option<Spreadsheet<Well>>,
+        rows: RepeatedGroup<PlateRow>,
+    }
+
+    #[derive(Clone, Serialize, Deserialize, HtmlForm)]
+    struct PlateRow {
+        wells: Option<Spreadsheet<Well>>,
+    }
+
+    // Only the readonly well is filled in
+    let blank = "A1%09%0A";
+    let plate = Plate::from_submission(&format!(
+        "run_name=run1&wells={blank}&rows[0].wells={blank}&rows[1].wells=A1%09pbmc"
+    ))
+    .unwrap();
+    assert!(plate.wells.is_none());
+    assert!(plate.rows.0[0].wells.is_none());
+    let wells = plate.rows.0[1]
+        .wells
+        .clone()
+        .unwrap()
+        .deserialized()
+        .unwrap();
+    assert_eq!(wells[0].sample, "pbmc");
+    assert!(matches!(plate.validate(), FormValidationResult::Valid(_)));
+
+    // Deserializing alone keeps the blank spreadsheet
+    let plate: Plate =
+        parse_form_submission(&format!("run_name=run1&wells={blank}&rows[0].wells=")).unwrap();
+    assert!(plate.wells.is_some());
+}
+
+#[test]
+fn test_from_submission_number_error() {
+    #[derive(Debug, Serialize, Deserialize, HtmlForm)]
+    struct Cells {
+        /// Expected cells
+        expected_cells: i64,
+        /// Force cells
+        force_cells: Option<i64>,
+    }
+
+    let cells = Cells::from_submission("expected_cells=3000").unwrap();
+    assert_eq!(cells.expected_cells, 3000);
+    assert_eq!(cells.force_cells, None);
+
+    let err = |body| Cells::from_submission(body).unwrap_err().to_string();
+    assert_eq!(
+        err("expected_cells=3k"),
+        r#"Expected cells: "3k" is not a number"#
+    );
+    assert_eq!(
+        err("expected_cells="),
+        "Expected cells is empty, expected a number"
+    );
+    assert_eq!(
+        err("expected_cells=3000&force_cells=1.5"),
+        r#"Force cells: "1.5" should be a whole number"#
+    );
+}
+
+#[derive(Serialize, HtmlForm)]
+#[html_form(method = "post", submit_label = "Start analysis", configure)]
+struct WizardForm {
+    sample_id: String,
+    #[html_form(wizard_step = 2, min = 1)]
+    expected_cells: i64,
+    #[html_form(wizard_step = 2)]
+    reference_path: String,
+}
+
+impl WizardFormConfiguration for WizardForm {
+    fn validate_reference_path(&self, path: &String) -> FieldValidationResult {
+        if path.starts_with('/') {
+            FieldValidationResult::Valid
+        } else {
+            FieldValidationResult::Invalid {
+                error: "Enter an absolute path".into(),
+            }
+        }
+    }
+    fn step_titles() -> Vec<String> {
+        vec!["Sample".into(), "Analysis".into()]
+    }
+}
+
+#[test]
+fn test_struct_derive_wizard() {
+    use_deterministic_submission_tokens();
+    let wizard = WizardForm::wizard_form();
+    assert_eq!(wizard.steps.len(), 2);
+    assert_eq!(wizard.steps[0].elements.len(), 1);
+    assert_eq!(wizard.steps[1].elements.len(), 2);
+    insta::assert_ron_snapshot!(wizard);
+}
+
+#[test]
+fn test_struct_derive_wizard_validation() {
+    use_deterministic_submission_tokens();
+    let result = WizardForm {
+        sample_id: "pbmc".into(),
+        expected_cells: 3000,
+        reference_path: "GRCh38".into(),
+    }
+    .validate_wizard();
+    assert!(matches!(result, WizardValidationResult::Invalid(_)));
+    let wizard = result.inner();
+    // The error is on the second step, which is shown first
+    assert!(wizard.steps[0].elements[0].feedback.error.is_none());
+    assert_eq!(wizard.progress.active_step, 1);
+    assert!(wizard.progress.active_step_failed);
+    insta::assert_ron_snapshot!(wizard);
+
+    let wizard = WizardForm {
+        sample_id: "pbmc".into(),
+        expected_cells: 3000,
+        reference_path: "/refs/GRCh38".into(),
+    }
+    .validate_wizard();
+    assert!(matches!(wizard, WizardValidationResult::Valid(_)));
+    assert_eq!(wizard.inner().progress.active_step, 0);
+}
+
+#[test]
+fn test_struct_derive_submit_label() {
+    #[derive(Serialize, HtmlForm)]
+    struct MyForm {
+        analysis_id: i64,
+    }
+
+    assert!(MyForm::form().config.submit_label().is_none());
+    assert_eq!(
+        WizardForm::form().config.submit_label(),
+        Some("Start analysis")
+    );
+    // Without steps, a wizard form has a single step
+    let wizard = MyForm::wizard_form();
+    assert_eq!(wizard.steps.len(), 1);
+    assert_eq!(wizard.progress.steps, vec!["Step 1"]);
+}
+
+#[test]
+fn test_struct_derive_wizard_no_fields() {
+    #[derive(Serialize, HtmlForm)]
+    struct EmptyForm {}
+
+    let wizard = EmptyForm::wizard_form();
+    assert_eq!(wizard.steps.len(), 1);
+    assert!(wizard.steps[0].elements.is_empty());
+    assert!(matches!(
+        EmptyForm {}.validate_wizard(),
+        WizardValidationResult::Valid(_)
+    ));
+}
+
+#[derive(Serialize, Deserialize, HtmlForm)]
+#[html_form(method = "post", configure)]
+struct SampleForm {
+    sample_id: String,
+    expected_cells: i64,
+}
+
+impl SampleFormConfiguration for SampleForm {
+    fn validate_expected_cells(&self, expected_cells: &i64) -> FieldValidationResult {
+        if *expected_cells > 0 {
+            FieldValidationResult::Valid
+        } else {
+            FieldValidationResult::Invalid {
+                error: "Expected cells should be positive".into(),
+            }
+        }
+    }
+}
+
+fn nav() -> tenx_websummary::components::WsNavBar {
+    tenx_websummary::components::WsNavBar::new("Sample intake", "run1", "New sample")
+}
+
+#[test]
+fn test_form_page_url() {
+    assert_eq!(SampleForm::form().config.url, "");
+    assert_eq!(SampleForm::form_with_url("/submit").config.url, "/submit");
+
+    let data = |page: &tenx_websummary::SinglePageHtml<_>| serde_json::to_value(page).unwrap();
+    let page = FormPage::new::<SampleForm>(nav(), "/submit", None);
+    assert_eq!(data(&page)["config"]["url"], "/submit");
+    assert_eq!(data(&page)["config"]["method"], "post");
+    assert_eq!(data(&page)["sample"]["id"], "run1");
+    assert!(page
+        .template(None)
+        .contains(r#"<div class="container-fluid">"#));
+
+    let page = FormPage::new::<SampleForm>(nav(), "/search", Some(FormMethod::Get));
+    assert_eq!(data(&page)["config"]["method"], "get");
+
+    let invalid = SampleForm {
+        sample_id: "pbmc".into(),
+        expected_cells: 0,
+    }
+    .validate()
+    .inner();
+    assert_eq!(invalid.config.url, "");
+    let page = FormPage::invalid(invalid, nav(), "/submit");
+    assert_eq!(data(&page)["config"]["url"], "/submit");
+    assert_eq!(
+        data(&page)["elements"][1]["feedback"]["error"],
+        "Expected cells should be positive"
+    );
+}
+
+#[cfg(feature = "actix")]
+#[test]
+fn test_form_page_actix_round_trip() {
+    use actix_web::{http::StatusCode, test, web, App, HttpRequest, HttpResponse};
+    use tenx_websummary::scrape_json::scrape_json_from_html;
+
+    async fn show() -> tenx_websummary::SinglePageHtml<tenx_websummary::form::Form> {
+        FormPage::new::<SampleForm>(nav(), "/submit", None)
+    }
+
+    async fn submit(req: HttpRequest, form: web::Form<SampleForm>) -> HttpResponse {
+        match FormPage::validate_or_respond(&*form, nav(), "/submit", &req) {
+            Ok(_) => HttpResponse::Ok().body(format!("Submitted {}", form.sample_id)),
+            Err(response) => *response,
+        }
+    }
+
+    actix_web::rt::System::new().block_on(async {
+        let app = test::init_service(
+            App::new()
+                .route("/", web::get().to(show))
+                .route("/submit", web::post().to(submit)),
+        )
+        .await;
+
+        let response =
+            test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let data = scrape_json_from_html(test::read_body(response).await.as_ref()).unwrap();
+        assert_eq!(data["config"]["url"], "/submit");
+        assert!(data["elements"][1]["feedback"]["error"].is_null());
+
+        let post = |body: &'static str| {
+            test::TestRequest::post()
+                .uri("/submit")
+                .insert_header(("content-type", "application/x-www-form-urlencoded"))
+                .set_payload(body)
+                .to_request()
+        };
+        let response = test::call_service(&app, post("sample_id=pbmc&expected_cells=0")).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let data = scrape_json_from_html(test::read_body(response).await.as_ref()).unwrap();
+        assert_eq!(data["config"]["url"], "/submit");
+        assert_eq!(
+            data["elements"][1]["feedback"]["error"],
+            "Expected cells should be positive"
+        );
+        assert_eq!(data["elements"][0]["input"]["content"]["value"], "pbmc");
+
+        let response = test::call_service(&app, post("sample_id=pbmc&expected_cells=3000")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(test::read_body(response).await, "Submitted pbmc");
+    });
+}
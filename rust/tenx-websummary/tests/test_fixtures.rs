@@ -0,0 +1,40 @@
+//! The html fixtures in `tests/fixtures`, rendered with `test_build_files()`
+//! so that the expected html does not depend on the bundled script and styles
+#![cfg(feature = "test-utils")]
+
+use std::path::PathBuf;
+
+use tenx_websummary::test_utils::{check_fixture, fixture_names, test_build_files, GoldenMode};
+
+fn fixtures_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+fn check(name: &str) {
+    if let Err(e) = check_fixture(
+        name,
+        &fixtures_dir(),
+        &test_build_files(),
+        GoldenMode::from_env(),
+    ) {
+        panic!("{e:#}");
+    }
+}
+
+#[test]
+fn test_fixture_names() {
+    assert_eq!(
+        fixture_names(&fixtures_dir()).unwrap(),
+        vec!["example", "multi_sample"]
+    );
+}
+
+#[test]
+fn test_example() {
+    check("example");
+}
+
+#[test]
+fn test_multi_sample() {
+    check("multi_sample");
+}
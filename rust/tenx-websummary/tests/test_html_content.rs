@@ -0,0 +1,81 @@
+//! Tests for the html_content! macro
+
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use tenx_websummary::components::{GenericTable, HeroMetric, PlotlyChart};
+use tenx_websummary::{html_content, HtmlTemplate, SinglePageHtml};
+
+fn qc_table() -> GenericTable {
+    GenericTable::from_rows(
+        vec![vec!["Valid barcodes".into(), "97.3%".into()]],
+        Some(vec!["Metric".into(), "Value".into()]),
+    )
+}
+
+fn rank_plot() -> PlotlyChart {
+    PlotlyChart::with_layout_and_data(
+        json!({"title": "Barcode rank"}),
+        vec![json!({"y": [3, 2, 1]})],
+    )
+}
+
+#[test]
+fn test_html_content() {
+    let content = html_content! {
+        "metrics" => HeroMetric::new("Number of cells", "3,487"),
+        "table" => qc_table(),
+        "plot" => rank_plot()
+    };
+    assert_eq!(
+        content.keys().collect::<Vec<_>>(),
+        ["metrics", "table", "plot"]
+    );
+    assert_eq!(
+        content.template(None),
+        r#"<div data-key="metrics" data-component="Metric"></div>
+<div data-key="table" data-component="Table"></div>
+<div data-key="plot" data-component="Plot"></div>"#
+    );
+    let json = serde_json::to_value(&content).unwrap();
+    assert_eq!(json["metrics"]["metric"], "3,487");
+    assert_eq!(json["table"], serde_json::to_value(qc_table()).unwrap());
+    assert_eq!(json["plot"], serde_json::to_value(rank_plot()).unwrap());
+    // The keys of the serialized map are in declaration order
+    let serialized = serde_json::to_string(&content).unwrap();
+    let position = |key: &str| serialized.find(&format!("\"{key}\":")).unwrap();
+    assert!(position("metrics") < position("table"));
+    assert!(position("table") < position("plot"));
+
+    // Every data key resolves against the serialized data
+    let page = SinglePageHtml::from_content(content);
+    assert!(page.check().is_empty(), "{:?}", page.check());
+}
+
+#[test]
+fn test_html_content_nested() {
+    let content = html_content! {
+        "summary" => html_content! {
+            "cells" => HeroMetric::new("Number of cells", "3,487"),
+            "table" => qc_table(),
+        },
+        "plot" => rank_plot(),
+    };
+    assert_eq!(
+        content.template(None),
+        r#"<div data-key="summary.cells" data-component="Metric"></div>
+<div data-key="summary.table" data-component="Table"></div>
+<div data-key="plot" data-component="Plot"></div>"#
+    );
+    let json = serde_json::to_value(&content).unwrap();
+    assert_eq!(json["summary"]["cells"]["name"], "Number of cells");
+    assert!(SinglePageHtml::from_content(content).check().is_empty());
+
+    let empty = html_content! {};
+    assert_eq!(empty.keys().count(), 0);
+}
+
+#[test]
+#[should_panic(expected = "Section key \"alarms\" is reserved by the web summary")]
+fn test_html_content_reserved_key() {
+    let _ = html_content! { "alarms" => qc_table() };
+}
@@ -0,0 +1,49 @@
+//! The layouts of the standard plots are locked by the snapshots, so that
+//! the plots look the same across pipelines
+
+use tenx_websummary::colors::Colorscale;
+use tenx_websummary::components::PlotlyChart;
+
+fn to_json(chart: &PlotlyChart) -> String {
+    serde_json::to_string_pretty(chart).unwrap()
+}
+
+#[test]
+fn test_barcode_rank() {
+    let chart = PlotlyChart::barcode_rank(
+        &[(1.0, 25000.0), (100.0, 8000.0), (3487.0, 1200.0)],
+        &[(3488.0, 1100.0), (10000.0, 40.0), (200000.0, 1.0)],
+    );
+    insta::assert_snapshot!(to_json(&chart));
+}
+
+#[test]
+fn test_histogram() {
+    let chart = PlotlyChart::histogram(&[0.5, 1.0, 1.5, 2.0, 4.5], 4, "Genes per cell");
+    insta::assert_snapshot!(to_json(&chart));
+
+    // A single distinct value leaves the binning to plotly
+    let chart = PlotlyChart::histogram(&[3.0, 3.0], 4, "Genes per cell");
+    assert_eq!(chart.data[0]["nbinsx"], 4);
+    assert!(chart.data[0].get("xbins").is_none());
+}
+
+#[test]
+fn test_box_by_group() {
+    let chart = PlotlyChart::box_by_group(&[
+        ("Cluster 1", vec![1.0, 2.0, 3.0]),
+        ("Cluster 2", vec![2.5, 4.0]),
+    ]);
+    insta::assert_snapshot!(to_json(&chart));
+}
+
+#[test]
+fn test_spatial_scatter() {
+    let chart = PlotlyChart::spatial_scatter(
+        &[10.0, 20.0, 30.0],
+        &[5.0, 15.0, 25.0],
+        &[0.0, 0.5, 1.0],
+        Colorscale::from_colors(&["#440154", "#fde725"]),
+    );
+    insta::assert_snapshot!(to_json(&chart));
+}